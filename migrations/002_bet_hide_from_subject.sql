@@ -0,0 +1,4 @@
+-- Bets hidden from their subjects
+-- The old inline SQLite schema had this column; this file had left it out
+
+ALTER TABLE bets ADD COLUMN hide_from_subject INTEGER NOT NULL DEFAULT 0;
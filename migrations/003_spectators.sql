@@ -0,0 +1,12 @@
+-- Read-only spectators (e.g., the living-room TV)
+
+CREATE TABLE IF NOT EXISTS spectators (
+    id TEXT PRIMARY KEY,
+    market_id TEXT NOT NULL,
+    display_name TEXT NOT NULL,
+    token TEXT NOT NULL UNIQUE,
+    joined_at TEXT NOT NULL,
+    FOREIGN KEY (market_id) REFERENCES markets(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_spectators_market ON spectators(market_id);
@@ -0,0 +1,44 @@
+-- Bets about several players (or the whole group)
+-- Moves bets.subject_user_id into a bet_subjects relation
+
+PRAGMA defer_foreign_keys = true;
+
+CREATE TABLE IF NOT EXISTS bet_subjects (
+    bet_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    PRIMARY KEY (bet_id, user_id),
+    FOREIGN KEY (bet_id) REFERENCES bets(id),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+INSERT OR IGNORE INTO bet_subjects (bet_id, user_id)
+SELECT id, subject_user_id FROM bets;
+
+-- SQLite can't drop a NOT NULL column in place, so rebuild the table
+CREATE TABLE bets_new (
+    id TEXT PRIMARY KEY,
+    market_id TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    description TEXT NOT NULL,
+    initial_odds TEXT NOT NULL,
+    status TEXT NOT NULL,
+    yes_pool INTEGER NOT NULL,
+    no_pool INTEGER NOT NULL,
+    hide_from_subject INTEGER NOT NULL DEFAULT 0,
+    about_everyone INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    resolved_at TEXT,
+    FOREIGN KEY (market_id) REFERENCES markets(id),
+    FOREIGN KEY (created_by) REFERENCES users(id)
+);
+
+INSERT INTO bets_new (id, market_id, created_by, description, initial_odds, status, yes_pool, no_pool, hide_from_subject, about_everyone, created_at, resolved_at)
+SELECT id, market_id, created_by, description, initial_odds, status, yes_pool, no_pool, hide_from_subject, 0, created_at, resolved_at
+FROM bets;
+
+DROP TABLE bets;
+ALTER TABLE bets_new RENAME TO bets;
+
+CREATE INDEX IF NOT EXISTS idx_bets_market ON bets(market_id);
+CREATE INDEX IF NOT EXISTS idx_bets_status ON bets(status);
+CREATE INDEX IF NOT EXISTS idx_bet_subjects_user ON bet_subjects(user_id);
@@ -0,0 +1,10 @@
+-- Admin-led reveal ceremony state, one per market
+
+CREATE TABLE IF NOT EXISTS reveal_ceremonies (
+    market_id TEXT PRIMARY KEY,
+    bet_ids TEXT NOT NULL,
+    revealed_count INTEGER NOT NULL DEFAULT 0,
+    started_at TEXT NOT NULL,
+    completed_at TEXT,
+    FOREIGN KEY (market_id) REFERENCES markets(id)
+);
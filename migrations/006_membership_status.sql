@@ -0,0 +1,3 @@
+-- Persisted membership state for kick, ban and leave
+
+ALTER TABLE users ADD COLUMN status TEXT NOT NULL DEFAULT 'active';
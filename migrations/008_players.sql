@@ -0,0 +1,12 @@
+-- Persistent player identities linked to per-market users
+
+CREATE TABLE IF NOT EXISTS players (
+    id TEXT PRIMARY KEY,
+    display_name TEXT NOT NULL,
+    avatar TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+ALTER TABLE users ADD COLUMN player_id TEXT REFERENCES players(id);
+
+CREATE INDEX IF NOT EXISTS idx_users_player ON users(player_id);
@@ -0,0 +1,26 @@
+-- Seasons group markets under a shared leaderboard
+
+CREATE TABLE IF NOT EXISTS seasons (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    carry_over_balances INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS season_markets (
+    market_id TEXT PRIMARY KEY,
+    season_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    FOREIGN KEY (season_id) REFERENCES seasons(id),
+    FOREIGN KEY (market_id) REFERENCES markets(id)
+);
+
+CREATE TABLE IF NOT EXISTS season_carry_overs (
+    user_id TEXT PRIMARY KEY,
+    market_id TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    FOREIGN KEY (market_id) REFERENCES markets(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_season_markets_season ON season_markets(season_id);
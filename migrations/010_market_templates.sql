@@ -0,0 +1,10 @@
+-- Saved market settings and bet ideas for reuse
+
+CREATE TABLE IF NOT EXISTS market_templates (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    starting_balance INTEGER NOT NULL,
+    duration_hours INTEGER NOT NULL,
+    bets TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
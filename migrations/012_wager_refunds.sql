@@ -0,0 +1,4 @@
+-- Wagers refunded when their owner or the bet's subject leaves the market
+-- are kept for the history, marked instead of deleted
+
+ALTER TABLE wagers ADD COLUMN refunded INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,146 @@
+-- Initial PostgreSQL schema for Cazino
+-- Same tables as SQLite migrations 001-009, with native types
+
+CREATE TYPE market_status AS ENUM ('draft', 'open', 'closed', 'resolved');
+CREATE TYPE bet_status AS ENUM ('pending', 'active', 'resolved_yes', 'resolved_no', 'challenged', 'void');
+CREATE TYPE membership_status AS ENUM ('active', 'left', 'kicked', 'banned');
+CREATE TYPE side AS ENUM ('YES', 'NO');
+
+CREATE TABLE markets (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    status market_status NOT NULL,
+    created_by UUID NOT NULL,
+    opens_at TIMESTAMPTZ NOT NULL,
+    closes_at TIMESTAMPTZ NOT NULL,
+    starting_balance BIGINT NOT NULL,
+    invite_code TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE players (
+    id UUID PRIMARY KEY,
+    display_name TEXT NOT NULL,
+    avatar TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE users (
+    id UUID PRIMARY KEY,
+    market_id UUID NOT NULL REFERENCES markets(id),
+    device_id TEXT NOT NULL,
+    display_name TEXT NOT NULL,
+    avatar TEXT NOT NULL,
+    balance BIGINT NOT NULL,
+    is_admin BOOLEAN NOT NULL,
+    status membership_status NOT NULL DEFAULT 'active',
+    player_id UUID REFERENCES players(id),
+    joined_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (market_id, device_id)
+);
+
+CREATE TABLE spectators (
+    id UUID PRIMARY KEY,
+    market_id UUID NOT NULL REFERENCES markets(id),
+    display_name TEXT NOT NULL,
+    token TEXT NOT NULL UNIQUE,
+    joined_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE bets (
+    id UUID PRIMARY KEY,
+    market_id UUID NOT NULL REFERENCES markets(id),
+    created_by UUID NOT NULL REFERENCES users(id),
+    description TEXT NOT NULL,
+    initial_odds TEXT NOT NULL,
+    status bet_status NOT NULL,
+    yes_pool BIGINT NOT NULL,
+    no_pool BIGINT NOT NULL,
+    hide_from_subject BOOLEAN NOT NULL DEFAULT FALSE,
+    about_everyone BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL,
+    resolved_at TIMESTAMPTZ
+);
+
+CREATE TABLE bet_subjects (
+    bet_id UUID NOT NULL REFERENCES bets(id),
+    user_id UUID NOT NULL REFERENCES users(id),
+    PRIMARY KEY (bet_id, user_id)
+);
+
+CREATE TABLE wagers (
+    id UUID PRIMARY KEY,
+    bet_id UUID NOT NULL REFERENCES bets(id),
+    user_id UUID NOT NULL REFERENCES users(id),
+    side side NOT NULL,
+    amount BIGINT NOT NULL,
+    placed_at TIMESTAMPTZ NOT NULL,
+    yes_pool_after BIGINT NOT NULL,
+    no_pool_after BIGINT NOT NULL,
+    probability_after DOUBLE PRECISION NOT NULL
+);
+
+CREATE TABLE reveal_ceremonies (
+    market_id UUID PRIMARY KEY REFERENCES markets(id),
+    bet_ids UUID[] NOT NULL,
+    revealed_count BIGINT NOT NULL DEFAULT 0,
+    started_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE TABLE market_templates (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    starting_balance BIGINT NOT NULL,
+    duration_hours BIGINT NOT NULL,
+    bets JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE seasons (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    carry_over_balances BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE season_markets (
+    market_id UUID PRIMARY KEY REFERENCES markets(id),
+    season_id UUID NOT NULL REFERENCES seasons(id),
+    position BIGINT NOT NULL
+);
+
+CREATE TABLE season_carry_overs (
+    user_id UUID PRIMARY KEY REFERENCES users(id),
+    market_id UUID NOT NULL REFERENCES markets(id),
+    amount BIGINT NOT NULL
+);
+
+CREATE TABLE link_codes (
+    code TEXT PRIMARY KEY,
+    device_id TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    redeemed_at TIMESTAMPTZ,
+    redeemed_by TEXT
+);
+
+CREATE TABLE audit_log (
+    id UUID PRIMARY KEY,
+    action TEXT NOT NULL,
+    device_id TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_users_market ON users(market_id);
+CREATE INDEX idx_users_device ON users(market_id, device_id);
+CREATE INDEX idx_users_player ON users(player_id);
+CREATE INDEX idx_spectators_market ON spectators(market_id);
+CREATE INDEX idx_bets_market ON bets(market_id);
+CREATE INDEX idx_bets_status ON bets(status);
+CREATE INDEX idx_bet_subjects_user ON bet_subjects(user_id);
+CREATE INDEX idx_wagers_bet ON wagers(bet_id);
+CREATE INDEX idx_wagers_user ON wagers(user_id);
+CREATE INDEX idx_season_markets_season ON season_markets(season_id);
+CREATE INDEX idx_audit_device ON audit_log(device_id, action, created_at);
@@ -0,0 +1,24 @@
+-- Outbound webhooks and their delivery log (SQLite migration 010)
+
+CREATE TABLE webhooks (
+    id UUID PRIMARY KEY,
+    market_id UUID NOT NULL REFERENCES markets(id),
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    events JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE webhook_deliveries (
+    id UUID PRIMARY KEY,
+    webhook_id UUID NOT NULL REFERENCES webhooks(id),
+    event TEXT NOT NULL,
+    attempt INTEGER NOT NULL,
+    status_code INTEGER,
+    error TEXT,
+    succeeded BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_webhooks_market ON webhooks(market_id);
+CREATE INDEX idx_webhook_deliveries_webhook ON webhook_deliveries(webhook_id);
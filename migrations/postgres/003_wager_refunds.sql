@@ -0,0 +1,4 @@
+-- Wagers refunded when their owner or the bet's subject leaves the market
+-- (SQLite migration 012)
+
+ALTER TABLE wagers ADD COLUMN refunded BOOLEAN NOT NULL DEFAULT FALSE;
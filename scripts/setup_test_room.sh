@@ -91,7 +91,7 @@ curl -s -X POST "$API_URL/markets/$MARKET_ID/open/$ADMIN_ID" > /dev/null
 
 echo ""
 echo "Creating test bet..."
-CREATE_BET=$(curl -s -X POST "$API_URL/markets/$MARKET_ID/bets/$ADMIN_ID/create" \
+CREATE_BET=$(curl -s -X POST "$API_URL/markets/$MARKET_ID/bets/$ADMIN_ID" \
   -H "Content-Type: application/json" \
   -d "{
     \"subject_user_id\": \"$ALICE_ID\",
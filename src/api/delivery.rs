@@ -0,0 +1,180 @@
+/// Webhook deliveries from the axum server
+///
+/// A background task follows the WebSocket broadcast channel, so every event
+/// the API publishes also goes out to the market's webhooks. Each delivery
+/// runs in its own task, retries included, and never holds up a request.
+use crate::api::webhooks::{self, WebhookClient};
+use crate::api::websocket::BroadcastRx;
+use crate::db::Database;
+use crate::domain::rules;
+use crate::service::CazinoService;
+use async_trait::async_trait;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use url::{Host, Url};
+
+/// How long one attempt may take to connect, send or wait for a response
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Deliver every event sent on the broadcast channel until it closes
+pub fn spawn_webhook_delivery<D, C>(
+    service: Arc<CazinoService<D>>,
+    client: Arc<C>,
+    mut rx: BroadcastRx,
+) -> JoinHandle<()>
+where
+    D: Database + 'static,
+    C: WebhookClient + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(
+                        "🪝 Webhook delivery fell behind, skipped {} events",
+                        skipped
+                    );
+                    service
+                        .metrics()
+                        .broadcast_dropped_events
+                        .add(&["webhooks"], skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            let message = Arc::new(event.message);
+            for webhook in webhooks::webhooks_for(&service, event.market_id, &message).await {
+                let (service, client, message) = (service.clone(), client.clone(), message.clone());
+                tokio::spawn(async move {
+                    webhooks::deliver(
+                        &service,
+                        client.as_ref(),
+                        &webhook,
+                        event.market_id,
+                        &message,
+                    )
+                    .await;
+                });
+            }
+        }
+    })
+}
+
+/// HTTP client for deliveries, built on reqwest
+///
+/// Every name is resolved through `PublicResolver`, so a host that resolves
+/// to a loopback or private address is refused at delivery time even though
+/// registration only checked the name. Redirects aren't followed: a 3xx is
+/// recorded like any other failed attempt, so webhooks must be registered at
+/// their final URL.
+pub struct HttpClient {
+    client: reqwest::Client,
+    allow_private: bool,
+}
+
+impl HttpClient {
+    pub fn new() -> Self {
+        Self::build(false)
+    }
+
+    /// Also post to loopback and private addresses, for local development
+    /// and tests
+    pub fn allow_private(self) -> Self {
+        Self::build(true)
+    }
+
+    fn build(allow_private: bool) -> Self {
+        let client = reqwest::Client::builder()
+            .dns_resolver(Arc::new(PublicResolver { allow_private }))
+            .redirect(reqwest::redirect::Policy::none())
+            .connect_timeout(REQUEST_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("a client without custom TLS settings always builds");
+
+        Self {
+            client,
+            allow_private,
+        }
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl WebhookClient for HttpClient {
+    async fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<u16, String> {
+        let url = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+
+        // Addresses written into the URL skip the resolver
+        let ip = match url.host() {
+            Some(Host::Ipv4(ip)) => Some(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => Some(IpAddr::V6(ip)),
+            Some(Host::Domain(_)) => None,
+            None => return Err("URL has no host".to_string()),
+        };
+        if let Some(ip) = ip.filter(|ip| !self.allow_private && !rules::is_public_ip(*ip)) {
+            return Err(format!("{} is not a public address", ip));
+        }
+
+        let mut request = self.client.post(url).body(body.to_string());
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+
+        let response = request.send().await.map_err(describe)?;
+        Ok(response.status().as_u16())
+    }
+
+    async fn sleep(&self, delay: Duration) {
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Resolves names like the system does, refusing any that have a loopback
+/// or private address unless those are allowed
+struct PublicResolver {
+    allow_private: bool,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allow_private = self.allow_private;
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            if let Some(addr) = addrs
+                .iter()
+                .find(|addr| !allow_private && !rules::is_public_ip(addr.ip()))
+            {
+                return Err(
+                    format!("{} resolves to non-public address {}", host, addr.ip()).into(),
+                );
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// A reqwest error with its causes, which hold the useful part (refused
+/// addresses, TLS failures and the like)
+fn describe(error: reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(&error);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
@@ -0,0 +1,764 @@
+/// Request dispatch shared by the axum server and the Cloudflare worker
+///
+/// Transports hand over the method, the path below `/api`, the query string
+/// and the raw body,
+/// and turn the `Reply` (or `ApiError`) back into an HTTP response. Every
+/// endpoint is listed once, in `dispatch`.
+use crate::api::handlers::{self, EventSink};
+use crate::api::openapi;
+use crate::db::{Database, DbError};
+use crate::domain::csv::{CsvReport, UnknownReport};
+use crate::service::{CazinoService, CsvExport};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// HTTP methods the API answers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+/// A transport-neutral API request
+#[derive(Debug, Clone, Copy)]
+pub struct Request<'a> {
+    pub method: Method,
+    pub path: &'a str,  // Below `/api`, e.g. `/markets/{id}`
+    pub query: &'a str, // Without the `?`; empty when there is none
+    pub body: &'a [u8],
+}
+
+/// What a handler produced
+pub enum Reply<D: Database> {
+    Json(serde_json::Value),
+    Empty, // 200 with no body
+    Csv {
+        report: CsvReport,
+        export: CsvExport<D>, // Stream with `next_chunk`
+    },
+}
+
+/// An error with the HTTP status it should be reported with
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message}")]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+    pub retry_after: Option<u64>, // Seconds, for 429s (see `limits`)
+}
+
+impl ApiError {
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+}
+
+impl From<DbError> for ApiError {
+    fn from(err: DbError) -> Self {
+        let status = match err {
+            DbError::NotFound(_) => 404,
+            DbError::Constraint(_) => 400,
+            DbError::Internal(_) => 500,
+        };
+        Self::new(status, err.to_string())
+    }
+}
+
+/// Route a request to its handler
+pub async fn dispatch<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    request: Request<'_>,
+) -> Result<Reply<D>, ApiError> {
+    use Method::{Get, Post};
+
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let body = request.body;
+    let query = request.query;
+
+    match (request.method, segments.as_slice()) {
+        // Market routes
+        (Post, ["markets"]) => json(handlers::create_market(service, events, parse(body)?).await?),
+        (Post, ["markets", "import"]) => {
+            json(handlers::import_market(service, parse(body)?).await?)
+        }
+        (Get, ["markets", market_id]) => {
+            json(handlers::get_market(service, uuid(market_id)?).await?)
+        }
+        (Post, ["markets", invite_code, "join"]) => json(
+            handlers::join_market(service, events, invite_code.to_string(), parse(body)?).await?,
+        ),
+        (Post, ["markets", invite_code, "spectate"]) => {
+            json(handlers::spectate_market(service, invite_code.to_string(), parse(body)?).await?)
+        }
+        (Get, ["markets", market_id, "snapshot"]) => json(
+            handlers::get_market_snapshot(service, events, uuid(market_id)?, parse_query(query)?)
+                .await?,
+        ),
+        (Get, ["markets", market_id, "users"]) => {
+            json(handlers::list_users(service, uuid(market_id)?, parse_query(query)?).await?)
+        }
+        (Get, ["markets", market_id, "leaderboard"]) => {
+            json(handlers::get_leaderboard(service, uuid(market_id)?).await?)
+        }
+        (Post, ["markets", market_id, "open", admin_id]) => {
+            handlers::open_market(service, events, uuid(market_id)?, uuid(admin_id)?).await?;
+            Ok(Reply::Empty)
+        }
+        (Post, ["markets", market_id, "close", admin_id]) => {
+            handlers::close_market(service, events, uuid(market_id)?, uuid(admin_id)?).await?;
+            Ok(Reply::Empty)
+        }
+        (Post, ["markets", market_id, "delete", admin_id]) => {
+            handlers::delete_market(service, events, uuid(market_id)?, uuid(admin_id)?).await?;
+            Ok(Reply::Empty)
+        }
+
+        // Bet routes (`pending` before the per-user listing it would match)
+        (Get, ["markets", market_id, "bets", "pending"]) => {
+            json(handlers::get_pending_bets(service, uuid(market_id)?).await?)
+        }
+        // Filtered, sorted pages live under `/page`, so the plain listing
+        // keeps returning every bet for existing clients
+        (Get, ["markets", market_id, "bets", user_id]) => {
+            json(handlers::get_bets(service, uuid(market_id)?, uuid(user_id)?).await?)
+        }
+        (Get, ["markets", market_id, "bets", user_id, "page"]) => json(
+            handlers::list_bets(
+                service,
+                uuid(market_id)?,
+                uuid(user_id)?,
+                parse_query(query)?,
+            )
+            .await?,
+        ),
+        // `/create` is the older spelling, kept for existing clients
+        (Post, ["markets", market_id, "bets", creator_id])
+        | (Post, ["markets", market_id, "bets", creator_id, "create"]) => json(
+            handlers::create_bet(
+                service,
+                events,
+                uuid(market_id)?,
+                uuid(creator_id)?,
+                parse(body)?,
+            )
+            .await?,
+        ),
+        (Post, ["bets", bet_id, "approve", admin_id]) => {
+            handlers::approve_bet(service, events, uuid(bet_id)?, uuid(admin_id)?).await?;
+            Ok(Reply::Empty)
+        }
+        (Post, ["bets", bet_id, "wager", user_id]) => json(
+            handlers::place_wager(service, events, uuid(bet_id)?, uuid(user_id)?, parse(body)?)
+                .await?,
+        ),
+        (Get, ["bets", bet_id, "wagers", user_id]) => json(
+            handlers::list_wagers(service, uuid(bet_id)?, uuid(user_id)?, parse_query(query)?)
+                .await?,
+        ),
+        (Get, ["bets", bet_id, "chart"]) => {
+            json(handlers::get_probability_chart(service, uuid(bet_id)?).await?)
+        }
+        (Post, ["bets", bet_id, "resolve", admin_id]) => {
+            handlers::resolve_bet(
+                service,
+                events,
+                uuid(bet_id)?,
+                uuid(admin_id)?,
+                parse(body)?,
+            )
+            .await?;
+            Ok(Reply::Empty)
+        }
+
+        // Profile and membership routes
+        (Post, ["users", user_id, "profile"]) => {
+            json(handlers::update_profile(service, events, uuid(user_id)?, parse(body)?).await?)
+        }
+        (Post, ["users", user_id, "player"]) => {
+            json(handlers::link_player(service, uuid(user_id)?, parse(body)?).await?)
+        }
+        (Post, ["users", user_id, "leave"]) => {
+            json(handlers::leave_market(service, events, uuid(user_id)?).await?)
+        }
+        (Post, ["markets", market_id, "kick", admin_id, user_id]) => json(
+            handlers::kick_user(
+                service,
+                events,
+                uuid(market_id)?,
+                uuid(admin_id)?,
+                uuid(user_id)?,
+            )
+            .await?,
+        ),
+        (Post, ["markets", market_id, "ban", admin_id, user_id]) => json(
+            handlers::ban_user(
+                service,
+                events,
+                uuid(market_id)?,
+                uuid(admin_id)?,
+                uuid(user_id)?,
+            )
+            .await?,
+        ),
+
+        // Archive routes
+        (Get, ["markets", market_id, "export", admin_id]) => {
+            json(handlers::export_market(service, uuid(market_id)?, uuid(admin_id)?).await?)
+        }
+        (Get, ["markets", market_id, "csv", report, admin_id]) => {
+            let market_id = uuid(market_id)?;
+            let report: CsvReport = report
+                .parse()
+                .map_err(|e: UnknownReport| ApiError::new(400, e.to_string()))?;
+            let export = service
+                .export_csv_as_admin(market_id, uuid(admin_id)?, report)
+                .await?;
+
+            tracing::info!("📊 Exporting {:?} CSV for market {}", report, market_id);
+
+            Ok(Reply::Csv { report, export })
+        }
+
+        // Template routes
+        (Post, ["markets", market_id, "template", admin_id]) => json(
+            handlers::save_template(service, uuid(market_id)?, uuid(admin_id)?, parse(body)?)
+                .await?,
+        ),
+        (Post, ["markets", market_id, "clone", admin_id]) => json(
+            handlers::clone_market(service, uuid(market_id)?, uuid(admin_id)?, parse(body)?)
+                .await?,
+        ),
+        (Get, ["templates", template_id]) => {
+            json(handlers::get_template(service, uuid(template_id)?).await?)
+        }
+        (Post, ["templates", template_id, "markets"]) => json(
+            handlers::create_market_from_template(service, uuid(template_id)?, parse(body)?)
+                .await?,
+        ),
+
+        // Season routes
+        (Post, ["seasons"]) => json(handlers::create_season(service, parse(body)?).await?),
+        (Get, ["seasons", season_id]) => {
+            json(handlers::get_season(service, uuid(season_id)?).await?)
+        }
+        (Post, ["seasons", season_id, "markets", market_id, admin_id]) => json(
+            handlers::add_market_to_season(
+                service,
+                uuid(season_id)?,
+                uuid(market_id)?,
+                uuid(admin_id)?,
+            )
+            .await?,
+        ),
+        (Get, ["seasons", season_id, "leaderboard"]) => {
+            json(handlers::get_season_leaderboard(service, uuid(season_id)?).await?)
+        }
+
+        // Player routes (identity across markets)
+        (Get, ["players", player_id]) => {
+            json(handlers::get_player_profile(service, uuid(player_id)?).await?)
+        }
+
+        // Reveal routes
+        (Get, ["users", user_id, "reveal"]) => {
+            json(handlers::get_reveal(service, uuid(user_id)?).await?)
+        }
+        (Get, ["markets", market_id, "reveal"]) => {
+            json(handlers::get_reveal_ceremony(service, uuid(market_id)?).await?)
+        }
+        (Post, ["markets", market_id, "reveal", "start", admin_id]) => json(
+            handlers::start_reveal_ceremony(service, events, uuid(market_id)?, uuid(admin_id)?)
+                .await?,
+        ),
+        (Post, ["markets", market_id, "reveal", "next", admin_id]) => json(
+            handlers::reveal_next_bet(service, events, uuid(market_id)?, uuid(admin_id)?).await?,
+        ),
+
+        // Spectator routes
+        (Get, ["spectators", token, "bets"]) => {
+            json(handlers::get_spectator_bets(service, token).await?)
+        }
+
+        // Device routes (fingerprint-based)
+        (Get, ["devices", device_id, "markets"]) => {
+            json(handlers::get_device_markets(service, device_id).await?)
+        }
+        (Get, ["devices", device_id, "markets", "page"]) => {
+            json(handlers::list_device_markets(service, device_id, parse_query(query)?).await?)
+        }
+        (Post, ["devices", device_id, "link-code"]) => {
+            json(handlers::create_link_code(service, device_id).await?)
+        }
+        (Post, ["devices", device_id, "link"]) => {
+            json(handlers::redeem_link_code(service, events, device_id, parse(body)?).await?)
+        }
+
+        // Webhook routes
+        (Post, ["markets", market_id, "webhooks", admin_id]) => json(
+            handlers::create_webhook(service, uuid(market_id)?, uuid(admin_id)?, parse(body)?)
+                .await?,
+        ),
+        (Get, ["markets", market_id, "webhooks", admin_id]) => {
+            json(handlers::get_webhooks(service, uuid(market_id)?, uuid(admin_id)?).await?)
+        }
+        (Post, ["webhooks", webhook_id, "delete", admin_id]) => {
+            handlers::delete_webhook(service, uuid(webhook_id)?, uuid(admin_id)?).await?;
+            Ok(Reply::Empty)
+        }
+        (Get, ["webhooks", webhook_id, "deliveries", admin_id]) => json(
+            handlers::get_webhook_deliveries(service, uuid(webhook_id)?, uuid(admin_id)?).await?,
+        ),
+
+        // Schema routes
+        (Get, ["openapi.json"]) => Ok(Reply::Json(openapi::spec())),
+        (Get, ["protocol.schema.json"]) => Ok(Reply::Json(openapi::protocol_schema())),
+
+        _ => Err(ApiError::new(404, format!("No route for {}", request.path))),
+    }
+}
+
+/// The market a request mutates, if it mutates exactly one
+///
+/// The worker uses this to route writes through the market's Durable Object,
+/// which applies them one at a time. Reads, and writes that create markets or
+/// span several (device links), return `None`.
+#[allow(dead_code)] // Only the worker routes by market
+pub async fn market_of<D: Database>(
+    service: &CazinoService<D>,
+    request: Request<'_>,
+) -> Result<Option<Uuid>, ApiError> {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+
+    let market_id = match (request.method, segments.as_slice()) {
+        (Method::Get, _) => return Ok(None),
+        (_, ["markets", invite_code, "join" | "spectate"]) => {
+            service.get_market_by_invite_code(invite_code).await?.id
+        }
+        (_, ["markets", market_id, _, ..]) => uuid(market_id)?,
+        (_, ["bets", bet_id, _, ..]) => service.get_bet(uuid(bet_id)?).await?.market_id,
+        (_, ["users", user_id, _, ..]) => service.get_user(uuid(user_id)?).await?.market_id,
+        (_, ["webhooks", webhook_id, _, ..]) => {
+            service.get_webhook(uuid(webhook_id)?).await?.market_id
+        }
+        (_, ["seasons", _, "markets", market_id, _]) => uuid(market_id)?,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(market_id))
+}
+
+/// Parse a path segment as a UUID
+fn uuid(segment: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(segment).map_err(|e| ApiError::new(400, format!("Invalid UUID: {}", e)))
+}
+
+/// Parse a JSON request body
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+    serde_json::from_slice(body)
+        .map_err(|e| ApiError::new(400, format!("Invalid request body: {}", e)))
+}
+
+/// Parse a query string (filters, sort and paging for listings)
+fn parse_query<T: DeserializeOwned>(query: &str) -> Result<T, ApiError> {
+    serde_urlencoded::from_str(query)
+        .map_err(|e| ApiError::new(400, format!("Invalid query string: {}", e)))
+}
+
+/// Wrap a handler's result as a JSON reply
+fn json<D: Database, T: Serialize>(value: T) -> Result<Reply<D>, ApiError> {
+    serde_json::to_value(value)
+        .map(Reply::Json)
+        .map_err(|e| ApiError::new(500, format!("Failed to serialize response: {}", e)))
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::api::protocol::WsMessage;
+    use crate::db::InMemoryDatabase;
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    /// Keeps every published event for inspection
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<WsMessage>>);
+
+    #[async_trait]
+    impl EventSink for Recorder {
+        async fn publish(&self, _market_id: Uuid, message: WsMessage) {
+            self.0.lock().unwrap().push(message);
+        }
+    }
+
+    async fn call(
+        service: &CazinoService<InMemoryDatabase>,
+        events: &Recorder,
+        method: Method,
+        path: &str,
+        body: Value,
+    ) -> Result<Value, ApiError> {
+        let body = body.to_string();
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let request = Request {
+            method,
+            path,
+            query,
+            body: body.as_bytes(),
+        };
+        match dispatch(service, events, request).await? {
+            Reply::Json(value) => Ok(value),
+            Reply::Empty => Ok(Value::Null),
+            Reply::Csv { .. } => panic!("unexpected CSV reply"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_routes_and_errors() {
+        let service = CazinoService::new(Arc::new(InMemoryDatabase::new()));
+        let events = Recorder::default();
+
+        let created = call(
+            &service,
+            &events,
+            Method::Post,
+            "/markets",
+            json!({ "name": "Dispatch", "admin_name": "Admin", "duration_hours": 24 }),
+        )
+        .await
+        .unwrap();
+        let market_id = created["market"]["id"].as_str().unwrap().to_string();
+        let admin_id = created["user"]["id"].as_str().unwrap().to_string();
+
+        call(
+            &service,
+            &events,
+            Method::Post,
+            &format!("/markets/{}/open/{}", market_id, admin_id),
+            Value::Null,
+        )
+        .await
+        .unwrap();
+
+        // Both spellings of create-bet reach the same handler
+        let bet = json!({
+            "about_everyone": true,
+            "description": "Someone is late",
+            "initial_odds": "1:1",
+            "opening_wager": 10,
+            "hide_from_subject": true,
+        });
+        for path in ["bets/{}", "bets/{}/create"] {
+            let path = format!("/markets/{}/{}", market_id, path.replace("{}", &admin_id));
+            call(&service, &events, Method::Post, &path, bet.clone())
+                .await
+                .unwrap();
+        }
+
+        // `pending` isn't mistaken for a user id
+        let pending = call(
+            &service,
+            &events,
+            Method::Get,
+            &format!("/markets/{}/bets/pending", market_id),
+            Value::Null,
+        )
+        .await
+        .unwrap();
+        assert!(pending.is_array());
+
+        {
+            let published = events.0.lock().unwrap();
+            assert!(matches!(published[0], WsMessage::MarketUpdate { .. }));
+            assert!(matches!(
+                published.last(),
+                Some(WsMessage::BetCreated {
+                    hide_from_subject: true,
+                    ..
+                })
+            ));
+        }
+
+        let status = |result: Result<Value, ApiError>| result.unwrap_err().status;
+        assert_eq!(
+            status(call(&service, &events, Method::Get, "/nowhere", Value::Null).await),
+            404
+        );
+        assert_eq!(
+            status(
+                call(
+                    &service,
+                    &events,
+                    Method::Get,
+                    "/markets/not-a-uuid",
+                    Value::Null
+                )
+                .await
+            ),
+            400
+        );
+        assert_eq!(
+            status(
+                call(
+                    &service,
+                    &events,
+                    Method::Get,
+                    &format!("/markets/{}", Uuid::new_v4()),
+                    Value::Null
+                )
+                .await
+            ),
+            404
+        );
+        assert_eq!(
+            status(call(&service, &events, Method::Post, "/markets", json!({})).await),
+            400
+        );
+    }
+
+    #[tokio::test]
+    async fn test_documented_operations_are_routed() {
+        let service = CazinoService::new(Arc::new(InMemoryDatabase::new()));
+        let events = Recorder::default();
+
+        for op in crate::api::openapi::operations() {
+            if op.server_only {
+                continue;
+            }
+            let path: Vec<String> = op
+                .path
+                .split('/')
+                .map(|segment| match segment {
+                    "{report}" => "wagers".to_string(),
+                    s if s.starts_with('{') => Uuid::nil().to_string(),
+                    s => s.to_string(),
+                })
+                .collect();
+            let path = path.join("/");
+
+            // Nothing exists, so handlers fail; but not for want of a route
+            if let Err(err) = call(&service, &events, op.method, &path, Value::Null).await {
+                assert!(
+                    !err.message.starts_with("No route for"),
+                    "{} ({}) is documented but not routed",
+                    op.path,
+                    op.operation_id
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_listings_page_with_query_strings() {
+        let service = CazinoService::new(Arc::new(InMemoryDatabase::new()));
+        let events = Recorder::default();
+
+        let created = call(
+            &service,
+            &events,
+            Method::Post,
+            "/markets",
+            json!({ "name": "Paging", "admin_name": "Admin", "duration_hours": 24, "device_id": "dev-1" }),
+        )
+        .await
+        .unwrap();
+        let market_id = created["market"]["id"].as_str().unwrap().to_string();
+        let admin_id = created["user"]["id"].as_str().unwrap().to_string();
+        let invite_code = created["market"]["invite_code"].as_str().unwrap();
+        let joined = call(
+            &service,
+            &events,
+            Method::Post,
+            &format!("/markets/{}/join", invite_code),
+            json!({ "display_name": "Guest", "avatar": "🦊", "device_id": "dev-2" }),
+        )
+        .await
+        .unwrap();
+        let guest_id = joined["user"]["id"].as_str().unwrap().to_string();
+        let cousin = call(
+            &service,
+            &events,
+            Method::Post,
+            &format!("/markets/{}/join", invite_code),
+            json!({ "display_name": "Cousin", "avatar": "🐻", "device_id": "dev-3" }),
+        )
+        .await
+        .unwrap();
+        let cousin_id = cousin["user"]["id"].as_str().unwrap().to_string();
+        call(
+            &service,
+            &events,
+            Method::Post,
+            &format!("/markets/{}/open/{}", market_id, admin_id),
+            Value::Null,
+        )
+        .await
+        .unwrap();
+
+        // A bet hidden from the guest that's also about the admin, and an open one
+        let mut bet_ids = Vec::new();
+        for (subjects, hidden) in [
+            (json!([guest_id, admin_id]), true),
+            (json!([admin_id]), false),
+        ] {
+            let bet = call(
+                &service,
+                &events,
+                Method::Post,
+                &format!("/markets/{}/bets/{}", market_id, cousin_id),
+                json!({
+                    "subject_user_ids": subjects,
+                    "description": "Dessert first",
+                    "initial_odds": "1:1",
+                    "opening_wager": 10,
+                    "hide_from_subject": hidden,
+                }),
+            )
+            .await
+            .unwrap();
+            bet_ids.push(bet["bet"]["id"].as_str().unwrap().to_string());
+        }
+
+        let get = |path: String| {
+            let (service, events) = (&service, &events);
+            async move { call(service, events, Method::Get, &path, Value::Null).await }
+        };
+
+        // The plain listing is the whole list, as before, query or not
+        for query in ["", "?limit=1"] {
+            let bets = get(format!("/markets/{}/bets/{}{}", market_id, guest_id, query))
+                .await
+                .unwrap();
+            assert_eq!(bets.as_array().unwrap().len(), 2);
+        }
+
+        // Pages always come back as pages, even without a query
+        let page = get(format!("/markets/{}/bets/{}/page", market_id, guest_id))
+            .await
+            .unwrap();
+        assert_eq!(page["items"].as_array().unwrap().len(), 2);
+        assert!(page["next_cursor"].is_null());
+
+        let page = get(format!(
+            "/markets/{}/bets/{}/page?sort=oldest&limit=1",
+            market_id, guest_id
+        ))
+        .await
+        .unwrap();
+        assert_eq!(page["items"].as_array().unwrap().len(), 1);
+        assert_eq!(page["items"][0]["is_hidden"], true);
+        assert!(page["next_cursor"].is_string());
+
+        // Filtering by someone else as subject leaves the guest's hidden bet out
+        let page = get(format!(
+            "/markets/{}/bets/{}/page?subject={}",
+            market_id, guest_id, admin_id
+        ))
+        .await
+        .unwrap();
+        assert_eq!(page["items"].as_array().unwrap().len(), 1);
+        assert_eq!(page["items"][0]["id"], bet_ids[1]);
+
+        let users = get(format!("/markets/{}/users?sort=balance&limit=2", market_id))
+            .await
+            .unwrap();
+        assert_eq!(users["items"].as_array().unwrap().len(), 2);
+        assert_ne!(users["items"][1]["id"], cousin_id);
+
+        let wagers = get(format!("/bets/{}/wagers/{}", bet_ids[1], guest_id))
+            .await
+            .unwrap();
+        assert_eq!(wagers["items"][0]["amount"], 10);
+        assert_eq!(
+            get(format!("/bets/{}/wagers/{}", bet_ids[0], guest_id))
+                .await
+                .unwrap_err()
+                .status,
+            400
+        );
+
+        let devices = get("/devices/dev-1/markets/page?status=open".to_string())
+            .await
+            .unwrap();
+        assert_eq!(devices["markets"].as_array().unwrap().len(), 1);
+        assert!(devices["next_cursor"].is_null());
+
+        for bad in ["sort=sideways", "limit=lots", "cursor=zz"] {
+            let path = format!("/markets/{}/bets/{}/page?{}", market_id, guest_id, bad);
+            assert_eq!(get(path).await.unwrap_err().status, 400);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_market_of_finds_the_mutated_market() {
+        let service = CazinoService::new(Arc::new(InMemoryDatabase::new()));
+        let events = Recorder::default();
+
+        let created = call(
+            &service,
+            &events,
+            Method::Post,
+            "/markets",
+            json!({ "name": "Routing", "admin_name": "Admin", "duration_hours": 24 }),
+        )
+        .await
+        .unwrap();
+        let market_id: Uuid = created["market"]["id"].as_str().unwrap().parse().unwrap();
+        let admin_id = created["user"]["id"].as_str().unwrap().to_string();
+        let invite_code = created["market"]["invite_code"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let market_of = |method, path: String| {
+            let service = &service;
+            async move {
+                let request = Request {
+                    method,
+                    path: &path,
+                    query: "",
+                    body: &[],
+                };
+                market_of(service, request).await
+            }
+        };
+
+        for path in [
+            format!("/markets/{}/open/{}", market_id, admin_id),
+            format!("/markets/{}/join", invite_code),
+            format!("/users/{}/leave", admin_id),
+            format!(
+                "/seasons/{}/markets/{}/{}",
+                Uuid::new_v4(),
+                market_id,
+                admin_id
+            ),
+        ] {
+            assert_eq!(market_of(Method::Post, path).await, Ok(Some(market_id)));
+        }
+
+        // Reads and writes outside one market stay where they are
+        for (method, path) in [
+            (Method::Get, format!("/markets/{}", market_id)),
+            (Method::Post, "/markets".to_string()),
+            (Method::Post, "/devices/abc/link".to_string()),
+        ] {
+            assert_eq!(market_of(method, path).await, Ok(None));
+        }
+
+        assert_eq!(
+            market_of(Method::Post, format!("/bets/{}/wager/x", Uuid::new_v4()))
+                .await
+                .unwrap_err()
+                .status,
+            404
+        );
+    }
+}
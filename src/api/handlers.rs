@@ -0,0 +1,1088 @@
+/// Typed API handlers shared by the axum server and the Cloudflare worker
+///
+/// Each handler takes already-parsed path parameters and request bodies,
+/// calls the service and publishes any resulting events. Transports only
+/// deal with HTTP; see `dispatch` for the routing table.
+use crate::api::models::{
+    BetResponse, BetWithProbability, CloneMarketRequest, CopiedMarketResponse, CreateBetRequest,
+    CreateFromTemplateRequest, CreateMarketRequest, CreateMarketResponse, CreateSeasonRequest,
+    CreateWebhookRequest, DeviceMarketInfo, DeviceMarketsResponse, ImportMarketResponse,
+    JoinMarketRequest, JoinMarketResponse, LeaderboardResponse, LinkCodeResponse,
+    LinkPlayerRequest, MarketSnapshotResponse, MembershipResponse, PlaceWagerRequest,
+    PlayerProfileResponse, PlayerResponse, ProbabilityChartResponse, ProbabilityPoint,
+    RedeemLinkCodeRequest, ResolveBetRequest, RevealCeremonyResponse, RevealResponse,
+    RevealStepResponse, SaveTemplateRequest, SeasonLeaderboardResponse, SeasonResponse,
+    SnapshotQuery, SpectateRequest, SpectateResponse, TemplateResponse, UpdateProfileRequest,
+    UserResponse, UserWithStats, WagerResponse, WebhookDeliveriesResponse, WebhookResponse,
+    WebhooksResponse,
+};
+use crate::api::protocol::{WsMessage, EVENT_TYPES};
+use crate::db::query::{BetQuery, DeviceMarketQuery, UserQuery, WagerQuery};
+use crate::db::r#trait::DatabaseMarker;
+use crate::db::{Database, DbError, DbResult, Page};
+use crate::domain::archive::MarketArchive;
+use crate::domain::models::{BetStatus, BetView, Market, User, Wager};
+use crate::domain::parimutuel;
+use crate::domain::rules::RuleError;
+use crate::service::{CazinoService, CreateMarketParams, MembershipChange};
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Where handlers publish real-time events for a market
+///
+/// The server fans them out over a broadcast channel; the worker forwards
+/// them to the market's Durable Object. Delivery is best effort, so sinks
+/// log failures instead of returning them.
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+pub trait EventSink: DatabaseMarker {
+    async fn publish(&self, market_id: Uuid, message: WsMessage);
+
+    /// Sequence number of the market's latest event, for sinks that number
+    /// them (see `websocket::BroadcastTx`)
+    fn last_event_seq(&self, _market_id: Uuid) -> Option<u64> {
+        None
+    }
+}
+
+// ===== Market Handlers =====
+
+/// Create a new market
+pub async fn create_market<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    req: CreateMarketRequest,
+) -> DbResult<CreateMarketResponse> {
+    tracing::info!("📊 Creating market: '{}'", req.name);
+
+    // Use provided device_id or generate a new one
+    let device_id = req.device_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let (market, user) = service
+        .create_market(CreateMarketParams {
+            name: req.name.clone(),
+            admin_device_id: device_id,
+            admin_name: req.admin_name.clone(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: req.starting_balance,
+            duration_hours: req.duration_hours,
+            custom_invite_code: req.invite_code,
+        })
+        .await?;
+
+    let invite_code = market.invite_code.clone();
+
+    tracing::info!(
+        "✅ Market created: {} | Admin: {} | Invite: {}",
+        market.id,
+        user.display_name,
+        invite_code
+    );
+
+    events
+        .publish(
+            market.id,
+            WsMessage::MarketUpdate {
+                market: market.clone(),
+            },
+        )
+        .await;
+
+    Ok(CreateMarketResponse {
+        market,
+        user,
+        invite_code,
+    })
+}
+
+/// Join an existing market
+pub async fn join_market<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    invite_code: String,
+    req: JoinMarketRequest,
+) -> DbResult<JoinMarketResponse> {
+    tracing::info!(
+        "👤 User '{}' joining market with code: {}",
+        req.display_name,
+        invite_code
+    );
+
+    // Use provided device_id or generate a new one
+    let device_id = req.device_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let (market, user) = service
+        .join_market(invite_code, device_id, req.display_name.clone(), req.avatar)
+        .await?;
+
+    tracing::info!(
+        "✅ {} joined market: {} ({})",
+        req.display_name,
+        market.name,
+        market.id
+    );
+
+    events
+        .publish(
+            market.id,
+            WsMessage::UserJoined {
+                user_id: user.id,
+                display_name: user.display_name.clone(),
+                market_id: market.id,
+            },
+        )
+        .await;
+
+    Ok(JoinMarketResponse { market, user })
+}
+
+/// Get market details
+pub async fn get_market<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+) -> DbResult<Market> {
+    service.get_market(market_id).await
+}
+
+/// Get all users in a market, richest first
+pub async fn get_leaderboard<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+) -> DbResult<LeaderboardResponse> {
+    let market = service.get_market(market_id).await?;
+    let users = service.get_users(market_id).await?;
+
+    Ok(LeaderboardResponse {
+        users: leaderboard(&market, users),
+    })
+}
+
+/// Get a page of a market's users
+pub async fn list_users<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+    query: UserQuery,
+) -> DbResult<Page<User>> {
+    service.list_users(market_id, &query).await
+}
+
+/// Rank players by balance, richest first
+fn leaderboard(market: &Market, mut users: Vec<User>) -> Vec<UserWithStats> {
+    // Sort by balance descending
+    users.sort_by_key(|u| std::cmp::Reverse(u.balance));
+
+    users
+        .into_iter()
+        .enumerate()
+        .map(|(idx, user)| UserWithStats {
+            profit: user.balance - market.starting_balance,
+            rank: idx + 1,
+            user,
+        })
+        .collect()
+}
+
+/// Get everything a client needs to draw a market, for the viewing user
+///
+/// Replaces separate market, bets, leaderboard and pending requests on load
+/// and reconnect.
+pub async fn get_market_snapshot<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    market_id: Uuid,
+    query: SnapshotQuery,
+) -> DbResult<MarketSnapshotResponse> {
+    // Read the sequence first: events after it may already be reflected in
+    // the contents, but none before it are missing
+    let last_event_seq = events.last_event_seq(market_id);
+    let (contents, user) = service
+        .get_market_contents(market_id, query.user_id)
+        .await?;
+
+    let bets = contents
+        .bets
+        .iter()
+        .map(|bet| BetWithProbability {
+            bet: bet.to_view(user.id),
+            probability: parimutuel::calculate_probability(bet.yes_pool, bet.no_pool),
+        })
+        .collect();
+
+    // Admins see the approval queue, still without bets hidden from them
+    let pending = user.is_admin.then(|| {
+        contents
+            .bets
+            .iter()
+            .filter(|bet| bet.status == BetStatus::Pending)
+            .map(|bet| bet.to_view(user.id))
+            .collect()
+    });
+
+    let players = contents
+        .users
+        .into_iter()
+        .filter(|u| u.is_active())
+        .collect();
+
+    Ok(MarketSnapshotResponse {
+        leaderboard: leaderboard(&contents.market, players),
+        market: contents.market,
+        user,
+        bets,
+        pending,
+        last_event_seq,
+    })
+}
+
+/// Open market for betting (admin only)
+pub async fn open_market<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    market_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<()> {
+    tracing::info!("🔓 Admin {} opening market {}", admin_id, market_id);
+
+    service.open_market(market_id, admin_id).await?;
+    let market = service.get_market(market_id).await?;
+
+    tracing::info!("✅ Market '{}' is now OPEN for betting", market.name);
+
+    events
+        .publish(
+            market_id,
+            WsMessage::MarketStatusChanged {
+                market_id,
+                status: market.status,
+            },
+        )
+        .await;
+
+    Ok(())
+}
+
+/// Close market (admin only)
+pub async fn close_market<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    market_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<()> {
+    tracing::info!("🔒 Admin {} closing market {}", admin_id, market_id);
+
+    service.close_market(market_id, admin_id).await?;
+    let market = service.get_market(market_id).await?;
+
+    tracing::info!("✅ Market '{}' is now CLOSED", market.name);
+
+    events
+        .publish(
+            market_id,
+            WsMessage::MarketStatusChanged {
+                market_id,
+                status: market.status,
+            },
+        )
+        .await;
+
+    Ok(())
+}
+
+/// Delete market (admin only)
+pub async fn delete_market<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    market_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<()> {
+    tracing::info!("🗑️ Admin {} deleting market {}", admin_id, market_id);
+
+    service.delete_market(market_id, admin_id).await?;
+
+    tracing::info!("✅ Market {} deleted", market_id);
+
+    events
+        .publish(market_id, WsMessage::MarketDeleted { market_id })
+        .await;
+
+    Ok(())
+}
+
+/// Join a market as a read-only spectator
+pub async fn spectate_market<D: Database>(
+    service: &CazinoService<D>,
+    invite_code: String,
+    req: SpectateRequest,
+) -> DbResult<SpectateResponse> {
+    tracing::info!(
+        "📺 Spectator '{}' joining market with code: {}",
+        req.display_name,
+        invite_code
+    );
+
+    let (market, spectator) = service
+        .join_as_spectator(invite_code, req.display_name)
+        .await?;
+
+    tracing::info!(
+        "✅ {} is spectating market: {} ({})",
+        spectator.display_name,
+        market.name,
+        market.id
+    );
+
+    Ok(SpectateResponse { market, spectator })
+}
+
+/// Get all bets in a spectator's market (hidden bets stay redacted)
+pub async fn get_spectator_bets<D: Database>(
+    service: &CazinoService<D>,
+    token: &str,
+) -> DbResult<Vec<BetView>> {
+    service.get_spectator_bets(token).await
+}
+
+// ===== Bet Handlers =====
+
+/// Get all bets in a market (filtered for viewing user)
+pub async fn get_bets<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+    user_id: Uuid,
+) -> DbResult<Vec<BetView>> {
+    service.get_bets(market_id, user_id).await
+}
+
+/// Get a page of bets in a market (visibility-filtered for the user)
+pub async fn list_bets<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+    user_id: Uuid,
+    query: BetQuery,
+) -> DbResult<Page<BetView>> {
+    service.list_bets(market_id, user_id, &query).await
+}
+
+/// Get a page of a bet's wagers
+pub async fn list_wagers<D: Database>(
+    service: &CazinoService<D>,
+    bet_id: Uuid,
+    user_id: Uuid,
+    query: WagerQuery,
+) -> DbResult<Page<Wager>> {
+    service.list_wagers(bet_id, user_id, &query).await
+}
+
+/// Create a new bet
+pub async fn create_bet<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    market_id: Uuid,
+    creator_id: Uuid,
+    req: CreateBetRequest,
+) -> DbResult<BetResponse> {
+    tracing::info!(
+        "🎲 Creating bet: '{}' | Opening wager: {}",
+        req.description,
+        req.opening_wager
+    );
+
+    let bet = service
+        .create_bet(
+            market_id,
+            creator_id,
+            req.subject_ids(),
+            req.about_everyone,
+            req.description.clone(),
+            req.initial_odds,
+            req.opening_wager,
+            req.hide_from_subject,
+        )
+        .await?;
+
+    tracing::info!(
+        "✅ Bet created: {} | Status: pending | Pools: {} YES / {} NO",
+        bet.id,
+        bet.yes_pool,
+        bet.no_pool
+    );
+
+    events
+        .publish(
+            market_id,
+            WsMessage::BetCreated {
+                bet_id: bet.id,
+                description: req.description,
+                hide_from_subject: bet.hide_from_subject,
+            },
+        )
+        .await;
+
+    Ok(BetResponse {
+        bet: bet.to_view(creator_id),
+    })
+}
+
+/// Get pending bets (admin only)
+pub async fn get_pending_bets<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+) -> DbResult<Vec<BetView>> {
+    let bets = service.get_pending_bets(market_id).await?;
+    // Convert to BetView (admin can see all)
+    Ok(bets.iter().map(|b| b.to_view(Uuid::nil())).collect())
+}
+
+/// Approve a bet (admin only)
+pub async fn approve_bet<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    bet_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<()> {
+    tracing::info!("✅ Admin {} approving bet {}", admin_id, bet_id);
+
+    let bet = service.approve_bet(bet_id, admin_id).await?;
+
+    tracing::info!("✅ Bet {} is now ACTIVE and open for wagering", bet_id);
+
+    events
+        .publish(bet.market_id, WsMessage::BetApproved { bet_id })
+        .await;
+
+    Ok(())
+}
+
+/// Place a wager on a bet
+pub async fn place_wager<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    bet_id: Uuid,
+    user_id: Uuid,
+    req: PlaceWagerRequest,
+) -> DbResult<WagerResponse> {
+    tracing::info!(
+        "💰 User {} wagering {} on {:?} for bet {}",
+        user_id,
+        req.amount,
+        req.side,
+        bet_id
+    );
+
+    let wager = service
+        .place_wager(bet_id, user_id, req.side, req.amount)
+        .await?;
+
+    tracing::info!(
+        "✅ Wager placed | Pools: {} YES / {} NO | Probability: {:.1}% YES",
+        wager.yes_pool_after,
+        wager.no_pool_after,
+        wager.probability_after * 100.0
+    );
+
+    let bet = service.get_bet(bet_id).await?;
+
+    events
+        .publish(
+            bet.market_id,
+            WsMessage::WagerPlaced {
+                bet_id,
+                user_id,
+                side: req.side,
+                amount: req.amount,
+                new_yes_pool: wager.yes_pool_after,
+                new_no_pool: wager.no_pool_after,
+                new_probability: wager.probability_after,
+            },
+        )
+        .await;
+
+    Ok(WagerResponse {
+        bet_id,
+        user_id,
+        side: req.side,
+        amount: req.amount,
+        new_probability: wager.probability_after,
+    })
+}
+
+/// Get probability chart for a bet
+pub async fn get_probability_chart<D: Database>(
+    service: &CazinoService<D>,
+    bet_id: Uuid,
+) -> DbResult<ProbabilityChartResponse> {
+    let chart = service.get_probability_chart(bet_id).await?;
+
+    let points: Vec<ProbabilityPoint> = chart
+        .into_iter()
+        .map(|p| ProbabilityPoint {
+            timestamp: p.timestamp.to_rfc3339(),
+            yes_probability: p.yes_probability,
+        })
+        .collect();
+
+    Ok(ProbabilityChartResponse { points })
+}
+
+/// Resolve a bet (admin only)
+pub async fn resolve_bet<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    bet_id: Uuid,
+    admin_id: Uuid,
+    req: ResolveBetRequest,
+) -> DbResult<()> {
+    tracing::info!(
+        "🏁 Admin {} resolving bet {} as {:?}",
+        admin_id,
+        bet_id,
+        req.outcome
+    );
+
+    service.resolve_bet(bet_id, admin_id, req.outcome).await?;
+
+    let bet = service.get_bet(bet_id).await?;
+
+    tracing::info!(
+        "✅ Bet resolved | Outcome: {:?} | Total pool: {} coins distributed",
+        req.outcome,
+        bet.yes_pool + bet.no_pool
+    );
+
+    events
+        .publish(
+            bet.market_id,
+            WsMessage::BetResolved {
+                bet_id,
+                outcome: req.outcome,
+                status: bet.status,
+            },
+        )
+        .await;
+
+    Ok(())
+}
+
+/// Get bets about a specific user (reveal screen)
+pub async fn get_reveal<D: Database>(
+    service: &CazinoService<D>,
+    user_id: Uuid,
+) -> DbResult<RevealResponse> {
+    let bets = service.get_reveal(user_id).await?;
+    // For reveal, show full details (use nil UUID so nothing is hidden)
+    let bet_views: Vec<BetView> = bets.iter().map(|b| b.to_view(Uuid::nil())).collect();
+
+    Ok(RevealResponse { bets: bet_views })
+}
+
+/// Update a player's display name and/or avatar
+pub async fn update_profile<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    user_id: Uuid,
+    req: UpdateProfileRequest,
+) -> DbResult<UserResponse> {
+    let user = service
+        .update_profile(user_id, req.display_name, req.avatar)
+        .await?;
+
+    tracing::info!(
+        "✏️ User {} is now {} {}",
+        user.id,
+        user.avatar,
+        user.display_name
+    );
+
+    events
+        .publish(
+            user.market_id,
+            WsMessage::UserUpdated {
+                user_id: user.id,
+                display_name: user.display_name.clone(),
+                avatar: user.avatar.clone(),
+            },
+        )
+        .await;
+
+    Ok(UserResponse { user })
+}
+
+// ===== Archive Handlers =====
+
+/// Export a market as a portable JSON archive (admin only)
+pub async fn export_market<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<MarketArchive> {
+    let archive = service.export_market_as_admin(market_id, admin_id).await?;
+
+    tracing::info!("📦 Exported market {}", market_id);
+
+    Ok(archive)
+}
+
+/// Import a market archive (ids are kept)
+pub async fn import_market<D: Database>(
+    service: &CazinoService<D>,
+    archive: MarketArchive,
+) -> DbResult<ImportMarketResponse> {
+    let market = service.import_market(archive).await?;
+
+    tracing::info!("📦 Imported market {} ({})", market.name, market.id);
+
+    Ok(ImportMarketResponse { market })
+}
+
+// ===== Template Handlers =====
+
+/// Save a market's settings and bets as a template (admin only)
+pub async fn save_template<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+    admin_id: Uuid,
+    req: SaveTemplateRequest,
+) -> DbResult<TemplateResponse> {
+    let template = service.save_template(market_id, admin_id, req.name).await?;
+
+    tracing::info!(
+        "📋 Template saved: {} ({} bets)",
+        template.name,
+        template.bets.len()
+    );
+
+    Ok(TemplateResponse { template })
+}
+
+/// Get a template
+pub async fn get_template<D: Database>(
+    service: &CazinoService<D>,
+    template_id: Uuid,
+) -> DbResult<TemplateResponse> {
+    let template = service.get_template(template_id).await?;
+    Ok(TemplateResponse { template })
+}
+
+/// Create a new market from a template
+pub async fn create_market_from_template<D: Database>(
+    service: &CazinoService<D>,
+    template_id: Uuid,
+    req: CreateFromTemplateRequest,
+) -> DbResult<CopiedMarketResponse> {
+    // Use provided device_id or generate a new one
+    let device_id = req.device_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let (market, user, bets) = service
+        .create_market_from_template(
+            template_id,
+            req.name,
+            device_id,
+            req.admin_name,
+            "👑".to_string(),
+        )
+        .await?;
+
+    tracing::info!("📋 Market {} created from template", market.id);
+
+    let invite_code = market.invite_code.clone();
+    Ok(CopiedMarketResponse {
+        market,
+        user,
+        invite_code,
+        bets,
+    })
+}
+
+/// Clone a market with its players and bets (admin only)
+pub async fn clone_market<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+    admin_id: Uuid,
+    req: CloneMarketRequest,
+) -> DbResult<CopiedMarketResponse> {
+    let (market, user, bets) = service.clone_market(market_id, admin_id, req.name).await?;
+
+    tracing::info!("📋 Market {} cloned into {}", market_id, market.id);
+
+    let invite_code = market.invite_code.clone();
+    Ok(CopiedMarketResponse {
+        market,
+        user,
+        invite_code,
+        bets,
+    })
+}
+
+// ===== Season Handlers =====
+
+/// Start a new season
+pub async fn create_season<D: Database>(
+    service: &CazinoService<D>,
+    req: CreateSeasonRequest,
+) -> DbResult<SeasonResponse> {
+    let season = service
+        .create_season(req.name, req.carry_over_balances)
+        .await?;
+
+    tracing::info!("📅 Season created: {} ({})", season.name, season.id);
+
+    Ok(SeasonResponse {
+        season,
+        markets: vec![],
+    })
+}
+
+/// Get a season and its markets
+pub async fn get_season<D: Database>(
+    service: &CazinoService<D>,
+    season_id: Uuid,
+) -> DbResult<SeasonResponse> {
+    let (season, markets) = service.get_season(season_id).await?;
+    Ok(SeasonResponse { season, markets })
+}
+
+/// Add a market to a season (market admin only)
+pub async fn add_market_to_season<D: Database>(
+    service: &CazinoService<D>,
+    season_id: Uuid,
+    market_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<SeasonResponse> {
+    let (season, markets) = service
+        .add_market_to_season(season_id, market_id, admin_id)
+        .await?;
+
+    Ok(SeasonResponse { season, markets })
+}
+
+/// Get the cumulative season leaderboard
+pub async fn get_season_leaderboard<D: Database>(
+    service: &CazinoService<D>,
+    season_id: Uuid,
+) -> DbResult<SeasonLeaderboardResponse> {
+    let (season, standings) = service.get_season_leaderboard(season_id).await?;
+    Ok(SeasonLeaderboardResponse { season, standings })
+}
+
+// ===== Player and Membership Handlers =====
+
+/// Link a market seat to a persistent player (creating one if needed)
+pub async fn link_player<D: Database>(
+    service: &CazinoService<D>,
+    user_id: Uuid,
+    req: LinkPlayerRequest,
+) -> DbResult<PlayerResponse> {
+    let player = service
+        .link_player(user_id, &req.device_id, req.player_id)
+        .await?;
+
+    tracing::info!("🪪 User {} linked to player {}", user_id, player.id);
+
+    Ok(PlayerResponse { player })
+}
+
+/// Get a player's lifetime stats across markets
+pub async fn get_player_profile<D: Database>(
+    service: &CazinoService<D>,
+    player_id: Uuid,
+) -> DbResult<PlayerProfileResponse> {
+    let (player, stats) = service.get_player_profile(player_id).await?;
+
+    Ok(PlayerProfileResponse { player, stats })
+}
+
+/// Leave a market (players only)
+pub async fn leave_market<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    user_id: Uuid,
+) -> DbResult<MembershipResponse> {
+    tracing::info!("👋 User {} leaving market", user_id);
+
+    let change = service.leave_market(user_id).await?;
+
+    Ok(publish_membership_change(events, change).await)
+}
+
+/// Kick a player (admin only)
+pub async fn kick_user<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    market_id: Uuid,
+    admin_id: Uuid,
+    user_id: Uuid,
+) -> DbResult<MembershipResponse> {
+    tracing::info!("🥾 Admin {} kicking user {}", admin_id, user_id);
+
+    let change = service.kick_user(market_id, admin_id, user_id).await?;
+
+    Ok(publish_membership_change(events, change).await)
+}
+
+/// Ban a player's device (admin only)
+pub async fn ban_user<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    market_id: Uuid,
+    admin_id: Uuid,
+    user_id: Uuid,
+) -> DbResult<MembershipResponse> {
+    tracing::info!("🚫 Admin {} banning user {}", admin_id, user_id);
+
+    let change = service.ban_user(market_id, admin_id, user_id).await?;
+
+    Ok(publish_membership_change(events, change).await)
+}
+
+/// Announce a membership change (and any voided bets), then respond with it
+async fn publish_membership_change<E: EventSink>(
+    events: &E,
+    change: MembershipChange,
+) -> MembershipResponse {
+    let market_id = change.user.market_id;
+
+    for bet_id in &change.voided_bet_ids {
+        events
+            .publish(market_id, WsMessage::BetVoided { bet_id: *bet_id })
+            .await;
+    }
+
+    events
+        .publish(
+            market_id,
+            WsMessage::MembershipChanged {
+                market_id,
+                user_id: change.user.id,
+                status: change.user.status,
+            },
+        )
+        .await;
+
+    MembershipResponse {
+        user: change.user,
+        voided_bet_ids: change.voided_bet_ids,
+    }
+}
+
+// ===== Reveal Handlers =====
+
+/// Start the reveal ceremony (admin only)
+pub async fn start_reveal_ceremony<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    market_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<RevealCeremonyResponse> {
+    tracing::info!(
+        "🎭 Admin {} starting reveal for market {}",
+        admin_id,
+        market_id
+    );
+
+    let ceremony = service.start_reveal_ceremony(market_id, admin_id).await?;
+
+    events
+        .publish(
+            market_id,
+            WsMessage::RevealStarted {
+                market_id,
+                total: ceremony.bet_ids.len(),
+            },
+        )
+        .await;
+
+    let (ceremony, revealed) = service.get_reveal_ceremony(market_id).await?;
+
+    Ok(RevealCeremonyResponse { ceremony, revealed })
+}
+
+/// Reveal the next hidden bet to everyone (admin only)
+pub async fn reveal_next_bet<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    market_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<RevealStepResponse> {
+    let (ceremony, reveal) = service.reveal_next_bet(market_id, admin_id).await?;
+
+    tracing::info!(
+        "🎭 Revealed bet {} ({}/{})",
+        reveal.bet.id,
+        ceremony.revealed_count,
+        ceremony.bet_ids.len()
+    );
+
+    events
+        .publish(
+            market_id,
+            WsMessage::BetRevealed {
+                market_id,
+                reveal: reveal.clone(),
+                revealed_count: ceremony.revealed_count,
+                total: ceremony.bet_ids.len(),
+            },
+        )
+        .await;
+
+    Ok(RevealStepResponse { ceremony, reveal })
+}
+
+/// Get reveal ceremony progress (for clients reconnecting mid-ceremony)
+pub async fn get_reveal_ceremony<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+) -> DbResult<RevealCeremonyResponse> {
+    let (ceremony, revealed) = service.get_reveal_ceremony(market_id).await?;
+
+    Ok(RevealCeremonyResponse { ceremony, revealed })
+}
+
+// ===== Device Handlers =====
+
+/// Get all markets a device has joined (for recent markets feature)
+pub async fn get_device_markets<D: Database>(
+    service: &CazinoService<D>,
+    device_id: &str,
+) -> DbResult<DeviceMarketsResponse> {
+    tracing::info!("📱 Getting markets for device: {}", device_id);
+
+    let markets = service.get_markets_by_device_id(device_id).await?;
+
+    Ok(DeviceMarketsResponse {
+        markets: markets
+            .into_iter()
+            .map(|(market, user)| DeviceMarketInfo { market, user })
+            .collect(),
+        next_cursor: None,
+    })
+}
+
+/// Get a page of every market a device has joined
+pub async fn list_device_markets<D: Database>(
+    service: &CazinoService<D>,
+    device_id: &str,
+    query: DeviceMarketQuery,
+) -> DbResult<DeviceMarketsResponse> {
+    let page = service.list_markets_by_device_id(device_id, &query).await?;
+
+    Ok(DeviceMarketsResponse {
+        markets: page
+            .items
+            .into_iter()
+            .map(|(market, user)| DeviceMarketInfo { market, user })
+            .collect(),
+        next_cursor: page.next_cursor,
+    })
+}
+
+/// Generate a link code for moving this device's players to another device
+pub async fn create_link_code<D: Database>(
+    service: &CazinoService<D>,
+    device_id: &str,
+) -> DbResult<LinkCodeResponse> {
+    let link = service.create_link_code(device_id).await?;
+
+    Ok(LinkCodeResponse {
+        code: link.code,
+        expires_at: link.expires_at,
+    })
+}
+
+/// Redeem a link code on this device, taking over the other device's players
+pub async fn redeem_link_code<D: Database, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    device_id: &str,
+    req: RedeemLinkCodeRequest,
+) -> DbResult<DeviceMarketsResponse> {
+    tracing::info!("🔗 Linking device: {}", device_id);
+
+    let markets = service.redeem_link_code(&req.code, device_id).await?;
+
+    // Seats moved between devices in several markets at once; let each know
+    for (market, user) in &markets {
+        events
+            .publish(
+                market.id,
+                WsMessage::UserUpdated {
+                    user_id: user.id,
+                    display_name: user.display_name.clone(),
+                    avatar: user.avatar.clone(),
+                },
+            )
+            .await;
+    }
+
+    Ok(DeviceMarketsResponse {
+        markets: markets
+            .into_iter()
+            .map(|(market, user)| DeviceMarketInfo { market, user })
+            .collect(),
+        next_cursor: None,
+    })
+}
+
+// ===== Webhook Handlers =====
+
+/// Register a webhook on a market (admin only)
+pub async fn create_webhook<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+    admin_id: Uuid,
+    req: CreateWebhookRequest,
+) -> DbResult<WebhookResponse> {
+    if let Some(event) = req
+        .events
+        .iter()
+        .find(|e| !EVENT_TYPES.contains(&e.as_str()))
+    {
+        return Err(DbError::Constraint(
+            RuleError::UnknownWebhookEvent(event.clone()).to_string(),
+        ));
+    }
+
+    let webhook = service
+        .create_webhook(market_id, admin_id, req.url, req.secret, req.events)
+        .await?;
+
+    tracing::info!(
+        "🪝 Webhook registered on market {}: {}",
+        market_id,
+        webhook.url
+    );
+
+    Ok(WebhookResponse { webhook })
+}
+
+/// List a market's webhooks (admin only)
+pub async fn get_webhooks<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<WebhooksResponse> {
+    let webhooks = service.get_webhooks(market_id, admin_id).await?;
+    Ok(WebhooksResponse { webhooks })
+}
+
+/// Remove a webhook (admin only)
+pub async fn delete_webhook<D: Database>(
+    service: &CazinoService<D>,
+    webhook_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<()> {
+    service.delete_webhook(webhook_id, admin_id).await?;
+
+    tracing::info!("🪝 Webhook removed: {}", webhook_id);
+
+    Ok(())
+}
+
+/// A webhook's delivery log (admin only)
+pub async fn get_webhook_deliveries<D: Database>(
+    service: &CazinoService<D>,
+    webhook_id: Uuid,
+    admin_id: Uuid,
+) -> DbResult<WebhookDeliveriesResponse> {
+    let deliveries = service.get_webhook_deliveries(webhook_id, admin_id).await?;
+    Ok(WebhookDeliveriesResponse { deliveries })
+}
@@ -0,0 +1,804 @@
+/// Rate limits and invite-code lockouts, shared by the axum server and the
+/// Cloudflare worker
+///
+/// Every API request takes a token from its IP's bucket. Requests that create
+/// markets or try an invite or link code also take one from stricter buckets
+/// for the IP and for the device named in the path or body. A client that gets
+/// `max_bad_invite_codes` codes wrong within `lockout_secs` can't try another
+/// code, from that IP or device, until `lockout_secs` have passed. Refusals are
+/// `RateLimited` errors, reported as 429s with a `Retry-After`.
+///
+/// The server keeps one `RateLimiter` in memory. The worker keeps counts in
+/// Durable Objects, one per IP and one per device, each holding a limiter
+/// for its `Partition` and saving a `Snapshot` to storage.
+use crate::api::dispatch::{ApiError, Method, Request};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use thiserror::Error;
+
+/// How often idle buckets and expired lockouts are cleared out
+const SWEEP_EVERY_SECS: i64 = 60;
+
+/// Limits, all adjustable through environment variables (see `from_vars`)
+///
+/// A burst of 0 turns that limit off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// Every API request, per IP
+    pub burst: u32,
+    pub per_minute: u32,
+    /// Creating markets and using invite or link codes, per IP and per device
+    pub sensitive_burst: u32,
+    pub sensitive_per_minute: u32,
+    /// Wrong invite or link codes allowed before a lockout (0 never locks out)
+    pub max_bad_invite_codes: u32,
+    pub lockout_secs: u64,
+    /// Take the client IP from `X-Forwarded-For` (only behind a proxy that
+    /// sets it; the worker always uses `CF-Connecting-IP`)
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            burst: 120,
+            per_minute: 600,
+            sensitive_burst: 10,
+            sensitive_per_minute: 10,
+            max_bad_invite_codes: 5,
+            lockout_secs: 15 * 60,
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Defaults, overridden by any of `CAZINO_RATE_LIMIT_BURST`,
+    /// `CAZINO_RATE_LIMIT_PER_MINUTE`, `CAZINO_RATE_LIMIT_SENSITIVE_BURST`,
+    /// `CAZINO_RATE_LIMIT_SENSITIVE_PER_MINUTE`, `CAZINO_MAX_BAD_INVITE_CODES`,
+    /// `CAZINO_INVITE_LOCKOUT_SECS` and `CAZINO_TRUST_FORWARDED_FOR` that
+    /// `lookup` finds (the process environment, or the worker's vars)
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        fn read<T: std::str::FromStr>(
+            lookup: &impl Fn(&str) -> Option<String>,
+            name: &str,
+            default: T,
+        ) -> Result<T, String> {
+            match lookup(name) {
+                Some(value) => value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("{} must be a number, not {:?}", name, value)),
+                None => Ok(default),
+            }
+        }
+
+        let defaults = Self::default();
+        Ok(Self {
+            burst: read(&lookup, "CAZINO_RATE_LIMIT_BURST", defaults.burst)?,
+            per_minute: read(&lookup, "CAZINO_RATE_LIMIT_PER_MINUTE", defaults.per_minute)?,
+            sensitive_burst: read(
+                &lookup,
+                "CAZINO_RATE_LIMIT_SENSITIVE_BURST",
+                defaults.sensitive_burst,
+            )?,
+            sensitive_per_minute: read(
+                &lookup,
+                "CAZINO_RATE_LIMIT_SENSITIVE_PER_MINUTE",
+                defaults.sensitive_per_minute,
+            )?,
+            max_bad_invite_codes: read(
+                &lookup,
+                "CAZINO_MAX_BAD_INVITE_CODES",
+                defaults.max_bad_invite_codes,
+            )?,
+            lockout_secs: read(&lookup, "CAZINO_INVITE_LOCKOUT_SECS", defaults.lockout_secs)?,
+            trust_forwarded_for: lookup("CAZINO_TRUST_FORWARDED_FOR")
+                .is_some_and(|value| matches!(value.trim(), "1" | "true" | "yes")),
+        })
+    }
+}
+
+/// A request refused for coming too often
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RateLimited {
+    #[error("Too many requests, try again in {retry_after} seconds")]
+    TooManyRequests { retry_after: u64 },
+
+    #[error("Too many wrong codes, try again in {retry_after} seconds")]
+    LockedOut { retry_after: u64 },
+}
+
+impl RateLimited {
+    /// Seconds until a request would be let through
+    pub fn retry_after(&self) -> u64 {
+        match *self {
+            Self::TooManyRequests { retry_after } | Self::LockedOut { retry_after } => retry_after,
+        }
+    }
+}
+
+impl From<RateLimited> for ApiError {
+    fn from(err: RateLimited) -> Self {
+        Self {
+            retry_after: Some(err.retry_after()),
+            ..Self::new(429, err.to_string())
+        }
+    }
+}
+
+/// What the limiter needs to know about a request
+#[derive(Debug, Clone, Copy)]
+struct Kind<'a> {
+    sensitive: bool,         // Creates a market or uses a code
+    wrong_code: Option<u16>, // Tries a code: the status a wrong one gets
+    device_id: Option<&'a str>,
+}
+
+/// Whose counts a limiter keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Partition {
+    All,
+    Ips,
+    Devices,
+}
+
+/// Which bucket a key belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum Scope {
+    Ip,
+    SensitiveIp,
+    SensitiveDevice,
+}
+
+impl Scope {
+    fn in_partition(self, partition: Partition) -> bool {
+        match partition {
+            Partition::All => true,
+            Partition::Ips => self != Scope::SensitiveDevice,
+            Partition::Devices => self == Scope::SensitiveDevice,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Bucket {
+    tokens: f64,
+    updated_at: DateTime<Utc>,
+}
+
+/// Wrong codes from one IP or device
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Strikes {
+    count: u32,
+    since: DateTime<Utc>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct State {
+    buckets: HashMap<(Scope, String), Bucket>,
+    strikes: HashMap<String, Strikes>,
+    swept_at: Option<DateTime<Utc>>,
+}
+
+/// A limiter's counts, for keeping them in storage
+#[allow(dead_code)] // Only the worker's Durable Objects save counts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    buckets: Vec<(Scope, String, Bucket)>,
+    strikes: Vec<(String, Strikes)>,
+}
+
+/// Token buckets and lockouts for every client seen recently
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    partition: Partition,
+    state: Mutex<State>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self::partitioned(config, Partition::All)
+    }
+
+    /// A limiter that only keeps, and only enforces, one partition's counts
+    pub fn partitioned(config: RateLimitConfig, partition: Partition) -> Self {
+        Self {
+            config,
+            partition,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// The counts held right now
+    #[allow(dead_code)]
+    pub fn snapshot(&self) -> Snapshot {
+        let state = self.state();
+        Snapshot {
+            buckets: state
+                .buckets
+                .iter()
+                .map(|((scope, key), bucket)| (*scope, key.clone(), *bucket))
+                .collect(),
+            strikes: state
+                .strikes
+                .iter()
+                .map(|(key, strikes)| (key.clone(), *strikes))
+                .collect(),
+        }
+    }
+
+    /// Pick up counts saved by `snapshot`
+    #[allow(dead_code)]
+    pub fn restore(&self, snapshot: Snapshot) {
+        let mut state = self.state();
+        state.buckets = snapshot
+            .buckets
+            .into_iter()
+            .map(|(scope, key, bucket)| ((scope, key), bucket))
+            .collect();
+        state.strikes = snapshot.strikes.into_iter().collect();
+    }
+
+    /// Let a request from `ip` through, taking its tokens, or refuse it
+    pub fn check(
+        &self,
+        request: &Request,
+        ip: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), RateLimited> {
+        self.admit(request, ip, now, true)
+    }
+
+    /// Whether `check` would let a request through, without taking anything
+    ///
+    /// The worker's counts are split across objects, so it asks each of them
+    /// before any takes a token, and a refusal from one costs nothing in the
+    /// others.
+    #[allow(dead_code)] // Only the worker's Durable Objects peek
+    pub fn peek(&self, request: &Request, ip: &str, now: DateTime<Utc>) -> Result<(), RateLimited> {
+        self.admit(request, ip, now, false)
+    }
+
+    fn admit(
+        &self,
+        request: &Request,
+        ip: &str,
+        now: DateTime<Utc>,
+        take: bool,
+    ) -> Result<(), RateLimited> {
+        let kind = classify(request);
+        let mut state = self.state();
+        self.sweep(&mut state, now);
+
+        if kind.wrong_code.is_some() {
+            for key in self.keys(ip, kind.device_id) {
+                let locked_until = state.strikes.get(&key).and_then(|s| s.locked_until);
+                if let Some(until) = locked_until.filter(|until| *until > now) {
+                    return Err(RateLimited::LockedOut {
+                        retry_after: seconds_until(now, until),
+                    });
+                }
+            }
+        }
+
+        // Check every bucket before taking from any, so a refusal costs nothing
+        let mut takes = vec![(Scope::Ip, ip, self.config.burst, self.config.per_minute)];
+        if kind.sensitive {
+            let (burst, per_minute) = (
+                self.config.sensitive_burst,
+                self.config.sensitive_per_minute,
+            );
+            takes.push((Scope::SensitiveIp, ip, burst, per_minute));
+            if let Some(device_id) = kind.device_id {
+                takes.push((Scope::SensitiveDevice, device_id, burst, per_minute));
+            }
+        }
+        takes.retain(|(scope, _, burst, _)| *burst > 0 && scope.in_partition(self.partition));
+
+        let mut wait = 0;
+        for (scope, key, burst, per_minute) in &takes {
+            let bucket = refill(
+                state.buckets.get(&(*scope, key.to_string())),
+                *burst,
+                *per_minute,
+                now,
+            );
+            if bucket.tokens < 1.0 {
+                let per_second = f64::from(*per_minute) / 60.0;
+                let secs = if per_second > 0.0 {
+                    ((1.0 - bucket.tokens) / per_second).ceil() as u64
+                } else {
+                    u64::MAX
+                };
+                wait = wait.max(secs.max(1));
+            }
+        }
+        if wait > 0 {
+            return Err(RateLimited::TooManyRequests { retry_after: wait });
+        }
+        if !take {
+            return Ok(());
+        }
+
+        for (scope, key, burst, per_minute) in takes {
+            let entry = (scope, key.to_string());
+            let mut bucket = refill(state.buckets.get(&entry), burst, per_minute, now);
+            bucket.tokens -= 1.0;
+            state.buckets.insert(entry, bucket);
+        }
+        Ok(())
+    }
+
+    /// Note how a request went; a wrong invite or link code is a strike
+    /// against its IP and device
+    pub fn record(&self, request: &Request, ip: &str, status: u16, now: DateTime<Utc>) {
+        let kind = classify(request);
+        if !is_wrong_code(request, status) || self.config.max_bad_invite_codes == 0 {
+            return;
+        }
+
+        let window = Duration::seconds(self.config.lockout_secs as i64);
+        let mut state = self.state();
+        for key in self.keys(ip, kind.device_id) {
+            let strikes = state.strikes.entry(key).or_insert(Strikes {
+                count: 0,
+                since: now,
+                locked_until: None,
+            });
+            if now - strikes.since > window {
+                *strikes = Strikes {
+                    count: 0,
+                    since: now,
+                    locked_until: None,
+                };
+            }
+
+            strikes.count += 1;
+            if strikes.count >= self.config.max_bad_invite_codes {
+                tracing::warn!("🔒 Locking out {} after {} wrong codes", ip, strikes.count);
+                *strikes = Strikes {
+                    count: 0,
+                    since: now,
+                    locked_until: Some(now + window),
+                };
+            }
+        }
+    }
+
+    /// Strike keys for the IP and device, as far as this limiter keeps them
+    fn keys(&self, ip: &str, device_id: Option<&str>) -> Vec<String> {
+        let mut keys = Vec::new();
+        if self.partition != Partition::Devices {
+            keys.push(format!("ip:{}", ip));
+        }
+        if self.partition != Partition::Ips {
+            keys.extend(device_id.map(|id| format!("device:{}", id)));
+        }
+        keys
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // Updates are single inserts and field writes, so a poisoned lock
+        // never guards a half-done change
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Forget full buckets and spent lockouts, which behave like new ones
+    fn sweep(&self, state: &mut State, now: DateTime<Utc>) {
+        if state
+            .swept_at
+            .is_some_and(|at| now - at < Duration::seconds(SWEEP_EVERY_SECS))
+        {
+            return;
+        }
+        state.swept_at = Some(now);
+
+        let config = &self.config;
+        state.buckets.retain(|(scope, _), bucket| {
+            let (burst, per_minute) = match scope {
+                Scope::Ip => (config.burst, config.per_minute),
+                _ => (config.sensitive_burst, config.sensitive_per_minute),
+            };
+            refill(Some(bucket), burst, per_minute, now).tokens < f64::from(burst)
+        });
+        let window = Duration::seconds(config.lockout_secs as i64);
+        state.strikes.retain(|_, strikes| {
+            strikes.locked_until.is_some_and(|until| until > now) || now - strikes.since <= window
+        });
+    }
+}
+
+/// The client IP for a request, from the proxy header if it's trusted or the
+/// connection otherwise
+pub fn client_ip<'a>(
+    config: &RateLimitConfig,
+    forwarded_for: Option<&'a str>,
+    peer: Option<&'a str>,
+) -> &'a str {
+    let forwarded = forwarded_for
+        .filter(|_| config.trust_forwarded_for)
+        .and_then(|value| value.split(',').next())
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty());
+    forwarded.or(peer).unwrap_or("unknown")
+}
+
+/// The device a request is counted against, if any
+#[allow(dead_code)] // Only the worker looks up limits per device
+pub fn device_id<'a>(request: &Request<'a>) -> Option<&'a str> {
+    classify(request).device_id
+}
+
+/// Whether a response means a wrong invite or link code, which `record`
+/// counts as a strike
+pub fn is_wrong_code(request: &Request, status: u16) -> bool {
+    classify(request).wrong_code == Some(status)
+}
+
+fn classify<'a>(request: &Request<'a>) -> Kind<'a> {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+
+    // Unknown invite codes are 404s; wrong link codes are rejected as 400s
+    let (sensitive, wrong_code) = match (request.method, segments.as_slice()) {
+        (Method::Post, ["markets", _, "join" | "spectate"]) => (true, Some(404)),
+        (Method::Post, ["devices", _, "link"]) => (true, Some(400)),
+        (Method::Post, ["markets"] | ["markets", "import"] | ["templates", _, "markets"]) => {
+            (true, None)
+        }
+        (Method::Post, ["devices", _, "link-code"]) => (true, None),
+        _ => (false, None),
+    };
+
+    let device_id = match segments.as_slice() {
+        ["devices", device_id, ..] => Some(*device_id),
+        _ if sensitive => body_device_id(request.body),
+        _ => None,
+    };
+
+    Kind {
+        sensitive,
+        wrong_code,
+        device_id,
+    }
+}
+
+/// `device_id` from a JSON body, without decoding the rest
+fn body_device_id(body: &[u8]) -> Option<&str> {
+    #[derive(serde::Deserialize)]
+    struct DeviceOnly<'a> {
+        #[serde(borrow)]
+        device_id: Option<&'a str>,
+    }
+
+    serde_json::from_slice::<DeviceOnly>(body)
+        .ok()?
+        .device_id
+        .filter(|id| !id.is_empty())
+}
+
+fn refill(bucket: Option<&Bucket>, burst: u32, per_minute: u32, now: DateTime<Utc>) -> Bucket {
+    let Some(bucket) = bucket else {
+        return Bucket {
+            tokens: f64::from(burst),
+            updated_at: now,
+        };
+    };
+    let elapsed = (now - bucket.updated_at).num_milliseconds().max(0) as f64 / 1000.0;
+    Bucket {
+        tokens: (bucket.tokens + elapsed * f64::from(per_minute) / 60.0).min(f64::from(burst)),
+        updated_at: now,
+    }
+}
+
+fn seconds_until(now: DateTime<Utc>, until: DateTime<Utc>) -> u64 {
+    let millis = (until - now).num_milliseconds().max(0) as u64;
+    millis.div_ceil(1000).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request<'a>(method: Method, path: &'a str, body: &'a [u8]) -> Request<'a> {
+        Request {
+            method,
+            path,
+            query: "",
+            body,
+        }
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_800_000_000 + secs, 0).unwrap()
+    }
+
+    fn config() -> RateLimitConfig {
+        RateLimitConfig {
+            burst: 5,
+            per_minute: 60,
+            sensitive_burst: 2,
+            sensitive_per_minute: 6,
+            max_bad_invite_codes: 3,
+            lockout_secs: 600,
+            trust_forwarded_for: false,
+        }
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = RateLimiter::new(config());
+        let get = request(Method::Get, "/markets/x", b"");
+
+        for _ in 0..5 {
+            limiter.check(&get, "1.2.3.4", at(0)).unwrap();
+        }
+        assert_eq!(
+            limiter.check(&get, "1.2.3.4", at(0)),
+            Err(RateLimited::TooManyRequests { retry_after: 1 })
+        );
+        // Other IPs have their own bucket
+        limiter.check(&get, "5.6.7.8", at(0)).unwrap();
+
+        // One a second comes back
+        limiter.check(&get, "1.2.3.4", at(1)).unwrap();
+        assert!(limiter.check(&get, "1.2.3.4", at(1)).is_err());
+    }
+
+    #[test]
+    fn test_market_creation_is_limited_per_device_and_ip() {
+        let limiter = RateLimiter::new(config());
+        let create = |device: &str| format!("{{\"name\":\"M\",\"device_id\":\"{}\"}}", device);
+
+        let body = create("phone");
+        let post = request(Method::Post, "/markets", body.as_bytes());
+        limiter.check(&post, "1.1.1.1", at(0)).unwrap();
+        limiter.check(&post, "2.2.2.2", at(0)).unwrap();
+        // The device is out, whichever IP it comes from
+        assert_eq!(
+            limiter.check(&post, "3.3.3.3", at(0)),
+            Err(RateLimited::TooManyRequests { retry_after: 10 })
+        );
+
+        // And the IP is out, whichever device it claims to be
+        let body = create("tablet");
+        let other = request(Method::Post, "/markets", body.as_bytes());
+        limiter.check(&other, "1.1.1.1", at(0)).unwrap();
+        assert!(limiter.check(&other, "1.1.1.1", at(0)).is_err());
+
+        // Ordinary requests are unaffected
+        limiter
+            .check(&request(Method::Get, "/markets/x", b""), "1.1.1.1", at(0))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_bad_invite_codes_lock_out() {
+        let config = RateLimitConfig {
+            sensitive_burst: 0, // Just the lockout
+            ..config()
+        };
+        let limiter = RateLimiter::new(config);
+        let join = request(Method::Post, "/markets/AAAAAA/join", b"{}");
+
+        for secs in 0..3 {
+            limiter.check(&join, "6.6.6.6", at(secs)).unwrap();
+            limiter.record(&join, "6.6.6.6", 404, at(secs));
+        }
+        assert_eq!(
+            limiter.check(&join, "6.6.6.6", at(2)),
+            Err(RateLimited::LockedOut { retry_after: 600 })
+        );
+        // Only invite codes are locked
+        limiter
+            .check(&request(Method::Get, "/markets/x", b""), "6.6.6.6", at(2))
+            .unwrap();
+
+        assert!(limiter.check(&join, "6.6.6.6", at(601)).is_err());
+        limiter.check(&join, "6.6.6.6", at(602)).unwrap();
+    }
+
+    #[test]
+    fn test_lockout_follows_the_device() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            sensitive_burst: 0,
+            ..config()
+        });
+        let body = br#"{"display_name":"Eve","device_id":"eve-phone"}"#;
+        let join = request(Method::Post, "/markets/BBBBBB/spectate", body);
+
+        for (secs, ip) in ["7.0.0.1", "7.0.0.2", "7.0.0.3"].iter().enumerate() {
+            limiter.record(&join, ip, 404, at(secs as i64));
+        }
+        // A fresh IP doesn't help
+        assert!(matches!(
+            limiter.check(&join, "7.0.0.4", at(5)),
+            Err(RateLimited::LockedOut { .. })
+        ));
+        // Codes that exist don't count
+        limiter.record(&join, "8.0.0.1", 200, at(5));
+        limiter
+            .check(
+                &request(Method::Post, "/markets/CCCCCC/join", b"{}"),
+                "8.0.0.1",
+                at(5),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_wrong_link_codes_lock_out_the_ip() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            sensitive_burst: 0,
+            ..config()
+        });
+        let paths: Vec<String> = (0..6).map(|i| format!("/devices/d{}/link", i)).collect();
+        let redeem = |i: usize| request(Method::Post, &paths[i], br#"{"code":"AAAAAAAA"}"#);
+
+        // A new device ID per guess still comes from the same IP
+        for i in 0..3 {
+            limiter.record(&redeem(i), "4.4.4.4", 400, at(i as i64));
+        }
+        assert!(matches!(
+            limiter.check(&redeem(3), "4.4.4.4", at(5)),
+            Err(RateLimited::LockedOut { .. })
+        ));
+        limiter.check(&redeem(3), "4.4.4.5", at(5)).unwrap();
+
+        // Asking for a code isn't a guess, and neither is a missing device
+        let ask = request(Method::Post, "/devices/d4/link-code", b"");
+        for _ in 0..3 {
+            limiter.record(&ask, "4.4.4.5", 400, at(5));
+            limiter.record(&redeem(4), "4.4.4.5", 404, at(5));
+        }
+        limiter.check(&redeem(4), "4.4.4.5", at(5)).unwrap();
+    }
+
+    #[test]
+    fn test_partitions_share_the_work() {
+        let (ips, devices) = (
+            RateLimiter::partitioned(config(), Partition::Ips),
+            RateLimiter::partitioned(config(), Partition::Devices),
+        );
+        let check = |ip: &str, body: &[u8]| {
+            let post = request(Method::Post, "/markets", body);
+            ips.check(&post, ip, at(0))
+                .and_then(|_| devices.check(&post, ip, at(0)))
+        };
+
+        // Each keeps only its own counts, so together they act as one limiter
+        check("1.1.1.1", br#"{"device_id":"phone"}"#).unwrap();
+        check("2.2.2.2", br#"{"device_id":"phone"}"#).unwrap();
+        assert!(check("3.3.3.3", br#"{"device_id":"phone"}"#).is_err());
+        check("1.1.1.1", br#"{"device_id":"tablet"}"#).unwrap();
+        assert!(check("1.1.1.1", br#"{"device_id":"laptop"}"#).is_err());
+        assert!(ips
+            .snapshot()
+            .buckets
+            .iter()
+            .all(|(scope, ..)| *scope != Scope::SensitiveDevice));
+        assert!(devices
+            .snapshot()
+            .buckets
+            .iter()
+            .all(|(scope, ..)| *scope == Scope::SensitiveDevice));
+
+        // Strikes too
+        let join = request(
+            Method::Post,
+            "/markets/AAAAAA/join",
+            br#"{"device_id":"eve"}"#,
+        );
+        for secs in 0..3 {
+            devices.record(&join, "6.6.6.6", 404, at(secs));
+        }
+        assert!(matches!(
+            devices.check(&join, "7.7.7.7", at(5)),
+            Err(RateLimited::LockedOut { .. })
+        ));
+        ips.check(&join, "6.6.6.6", at(5)).unwrap();
+    }
+
+    #[test]
+    fn test_peeking_takes_nothing() {
+        let (ips, devices) = (
+            RateLimiter::partitioned(config(), Partition::Ips),
+            RateLimiter::partitioned(config(), Partition::Devices),
+        );
+        let phone = request(Method::Post, "/markets", br#"{"device_id":"phone"}"#);
+        let tablet = request(Method::Post, "/markets", br#"{"device_id":"tablet"}"#);
+        for ip in ["2.2.2.2", "3.3.3.3"] {
+            devices.check(&phone, ip, at(0)).unwrap();
+        }
+
+        // As the worker does: ask every partition, then take from each
+        let admit = |post: &Request<'_>, ip: &str| {
+            ips.peek(post, ip, at(0))
+                .and(devices.peek(post, ip, at(0)))
+                .and_then(|_| ips.check(post, ip, at(0)))
+                .and_then(|_| devices.check(post, ip, at(0)))
+        };
+        for _ in 0..3 {
+            assert!(admit(&phone, "1.1.1.1").is_err());
+        }
+
+        // The phone's refusals cost its IP nothing
+        admit(&tablet, "1.1.1.1").unwrap();
+        admit(&tablet, "1.1.1.1").unwrap();
+        assert!(admit(&tablet, "1.1.1.1").is_err());
+    }
+
+    #[test]
+    fn test_snapshot_restores_lockouts() {
+        let limiter = RateLimiter::new(config());
+        let join = request(Method::Post, "/markets/AAAAAA/join", b"{}");
+        for secs in 0..3 {
+            limiter.record(&join, "6.6.6.6", 404, at(secs));
+        }
+
+        let saved = serde_json::to_string(&limiter.snapshot()).unwrap();
+        let restored = RateLimiter::new(config());
+        restored.restore(serde_json::from_str(&saved).unwrap());
+        assert!(matches!(
+            restored.check(&join, "6.6.6.6", at(5)),
+            Err(RateLimited::LockedOut { .. })
+        ));
+    }
+
+    #[test]
+    fn test_strikes_expire() {
+        let limiter = RateLimiter::new(config());
+        let join = request(Method::Post, "/markets/AAAAAA/join", b"{}");
+
+        limiter.record(&join, "9.9.9.9", 404, at(0));
+        limiter.record(&join, "9.9.9.9", 404, at(1));
+        limiter.record(&join, "9.9.9.9", 404, at(700));
+        limiter.check(&join, "9.9.9.9", at(700)).unwrap();
+    }
+
+    #[test]
+    fn test_config_from_vars() {
+        let vars: HashMap<&str, &str> = [
+            ("CAZINO_RATE_LIMIT_BURST", "0"),
+            ("CAZINO_INVITE_LOCKOUT_SECS", " 60 "),
+            ("CAZINO_TRUST_FORWARDED_FOR", "true"),
+        ]
+        .into();
+        let config =
+            RateLimitConfig::from_vars(|name| vars.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(config.burst, 0);
+        assert_eq!(config.lockout_secs, 60);
+        assert!(config.trust_forwarded_for);
+        assert_eq!(config.per_minute, RateLimitConfig::default().per_minute);
+
+        let err = RateLimitConfig::from_vars(|name| {
+            (name == "CAZINO_RATE_LIMIT_PER_MINUTE").then(|| "lots".to_string())
+        })
+        .unwrap_err();
+        assert!(err.contains("CAZINO_RATE_LIMIT_PER_MINUTE"));
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let trusting = RateLimitConfig {
+            trust_forwarded_for: true,
+            ..config()
+        };
+        let header = Some(" 203.0.113.9 , 10.0.0.1");
+        assert_eq!(
+            client_ip(&trusting, header, Some("10.0.0.1")),
+            "203.0.113.9"
+        );
+        assert_eq!(client_ip(&config(), header, Some("10.0.0.1")), "10.0.0.1");
+        assert_eq!(client_ip(&config(), None, None), "unknown");
+    }
+}
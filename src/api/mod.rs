@@ -1,10 +1,20 @@
+pub mod dispatch;
+pub mod handlers;
+pub mod limits;
 pub mod models;
+pub mod openapi;
+pub mod protocol;
+pub mod webhooks;
 
+#[cfg(feature = "server")]
+pub mod delivery;
 #[cfg(feature = "server")]
 pub mod routes;
 #[cfg(feature = "server")]
 pub mod server;
 #[cfg(feature = "server")]
+pub mod sse;
+#[cfg(feature = "server")]
 pub mod websocket;
 
 #[cfg(feature = "server")]
@@ -1,11 +1,12 @@
 /// API request/response models
-use crate::domain::models::{BetStatus, BetView, MarketStatus, Side};
+use crate::domain::models::{BetReveal, BetView, RevealCeremony, Side};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 // ===== Request Models =====
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct CreateMarketRequest {
     pub name: String,
     pub admin_name: String,
@@ -20,16 +21,75 @@ fn default_starting_balance() -> i64 {
     1000
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct JoinMarketRequest {
     pub display_name: String,
     pub avatar: String,
     pub device_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SpectateRequest {
+    pub display_name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UpdateProfileRequest {
+    pub display_name: Option<String>,
+    pub avatar: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SaveTemplateRequest {
+    /// Defaults to the market's name
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateFromTemplateRequest {
+    /// Defaults to the template's name
+    #[serde(default)]
+    pub name: Option<String>,
+    pub admin_name: String,
+    pub device_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CloneMarketRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateSeasonRequest {
+    pub name: String,
+    #[serde(default)]
+    pub carry_over_balances: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LinkPlayerRequest {
+    /// The caller's device, which must hold the seat (and a seat of the player)
+    pub device_id: String,
+    /// Existing player to link to; a new one is created when omitted
+    #[serde(default)]
+    pub player_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RedeemLinkCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct CreateBetRequest {
-    pub subject_user_id: Uuid,
+    /// Single subject, still accepted from older clients
+    #[serde(default)]
+    pub subject_user_id: Option<Uuid>,
+    #[serde(default)]
+    pub subject_user_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub about_everyone: bool,
     pub description: String,
     pub initial_odds: String,
     pub opening_wager: i64,
@@ -37,38 +97,72 @@ pub struct CreateBetRequest {
     pub hide_from_subject: bool,
 }
 
-#[derive(Debug, Deserialize)]
+impl CreateBetRequest {
+    /// All named subjects, merging the legacy single-subject field
+    pub fn subject_ids(&self) -> Vec<Uuid> {
+        let mut ids = self.subject_user_ids.clone();
+        if let Some(id) = self.subject_user_id {
+            if !ids.contains(&id) {
+                ids.insert(0, id);
+            }
+        }
+        ids
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct PlaceWagerRequest {
     pub side: Side,
     pub amount: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ResolveBetRequest {
     pub outcome: Side,
 }
 
+/// Whose view of the market a snapshot is drawn for
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SnapshotQuery {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub secret: String,
+    /// Message types to send (see `protocol::EVENT_TYPES`); all when omitted
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
 // ===== Response Models =====
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct CreateMarketResponse {
     pub market: crate::domain::models::Market,
     pub user: crate::domain::models::User,
     pub invite_code: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct JoinMarketResponse {
     pub market: crate::domain::models::Market,
     pub user: crate::domain::models::User,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SpectateResponse {
+    pub market: crate::domain::models::Market,
+    pub spectator: crate::domain::models::Spectator,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct BetResponse {
     pub bet: BetView,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct WagerResponse {
     pub bet_id: Uuid,
     pub user_id: Uuid,
@@ -77,111 +171,156 @@ pub struct WagerResponse {
     pub new_probability: f64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct ProbabilityChartResponse {
     pub points: Vec<ProbabilityPoint>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct ProbabilityPoint {
     pub timestamp: String,
     pub yes_probability: f64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct LeaderboardResponse {
     pub users: Vec<UserWithStats>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct UserWithStats {
     pub user: crate::domain::models::User,
     pub profit: i64,
     pub rank: usize,
 }
 
-#[derive(Debug, Serialize)]
+/// Everything a client needs to draw a market, in one response
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MarketSnapshotResponse {
+    pub market: crate::domain::models::Market,
+    pub user: crate::domain::models::User, // The viewer
+    pub bets: Vec<BetWithProbability>,     // Filtered for the viewer
+    pub leaderboard: Vec<UserWithStats>,
+    pub pending: Option<Vec<BetView>>, // Admins only
+    /// Sequence number of the market's latest event, to resume the event
+    /// stream from; `None` where events aren't numbered
+    pub last_event_seq: Option<u64>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BetWithProbability {
+    #[serde(flatten)]
+    pub bet: BetView,
+    pub probability: f64, // Current YES probability
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct RevealResponse {
     pub bets: Vec<BetView>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RevealCeremonyResponse {
+    pub ceremony: RevealCeremony,
+    pub revealed: Vec<BetReveal>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RevealStepResponse {
+    pub ceremony: RevealCeremony,
+    pub reveal: BetReveal,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UserResponse {
+    pub user: crate::domain::models::User,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MembershipResponse {
+    pub user: crate::domain::models::User,
+    pub voided_bet_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct ErrorResponse {
     pub error: String,
+    /// Seconds to wait before trying again, when rate limited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct DeviceMarketsResponse {
     pub markets: Vec<DeviceMarketInfo>,
+    pub next_cursor: Option<String>, // Only paged listings have one
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct DeviceMarketInfo {
     pub market: crate::domain::models::Market,
     pub user: crate::domain::models::User,
 }
 
-// ===== WebSocket Messages =====
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(tag = "type")]
-pub enum WsMessage {
-    // Client -> Server
-    #[serde(rename = "subscribe")]
-    Subscribe { market_id: Uuid },
-
-    #[serde(rename = "ping")]
-    Ping,
-
-    // Server -> Client
-    #[serde(rename = "market_update")]
-    MarketUpdate {
-        market: crate::domain::models::Market,
-    },
-
-    #[serde(rename = "bet_created")]
-    BetCreated { bet_id: Uuid, description: String },
-
-    #[serde(rename = "bet_approved")]
-    BetApproved { bet_id: Uuid },
-
-    #[serde(rename = "user_joined")]
-    UserJoined {
-        user_id: Uuid,
-        display_name: String,
-        market_id: Uuid,
-    },
-
-    #[serde(rename = "wager_placed")]
-    WagerPlaced {
-        bet_id: Uuid,
-        user_id: Uuid,
-        side: Side,
-        amount: i64,
-        new_yes_pool: i64,
-        new_no_pool: i64,
-        new_probability: f64,
-    },
-
-    #[serde(rename = "bet_resolved")]
-    BetResolved {
-        bet_id: Uuid,
-        outcome: Side,
-        status: BetStatus,
-    },
-
-    #[serde(rename = "market_status_changed")]
-    MarketStatusChanged {
-        market_id: Uuid,
-        status: MarketStatus,
-    },
-
-    #[serde(rename = "market_deleted")]
-    MarketDeleted { market_id: Uuid },
-
-    #[serde(rename = "pong")]
-    Pong,
-
-    #[serde(rename = "error")]
-    Error { message: String },
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ImportMarketResponse {
+    pub market: crate::domain::models::Market,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TemplateResponse {
+    pub template: crate::domain::models::MarketTemplate,
+}
+
+/// A market created from a template or cloned from another market
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CopiedMarketResponse {
+    pub market: crate::domain::models::Market,
+    pub user: crate::domain::models::User,
+    pub invite_code: String,
+    pub bets: Vec<crate::domain::models::Bet>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SeasonResponse {
+    pub season: crate::domain::models::Season,
+    pub markets: Vec<crate::domain::models::Market>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SeasonLeaderboardResponse {
+    pub season: crate::domain::models::Season,
+    pub standings: Vec<crate::domain::models::SeasonStanding>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PlayerResponse {
+    pub player: crate::domain::models::Player,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PlayerProfileResponse {
+    pub player: crate::domain::models::Player,
+    pub stats: crate::domain::models::PlayerStats,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LinkCodeResponse {
+    pub code: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct WebhookResponse {
+    pub webhook: crate::domain::models::Webhook,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct WebhooksResponse {
+    pub webhooks: Vec<crate::domain::models::Webhook>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct WebhookDeliveriesResponse {
+    pub deliveries: Vec<crate::domain::models::WebhookDelivery>,
 }
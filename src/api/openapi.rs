@@ -0,0 +1,531 @@
+/// OpenAPI document for the HTTP API, and JSON Schema for the WebSocket protocol
+///
+/// Request and response schemas are derived from the types in `api::models`
+/// (and the domain types they contain); `operations` pairs them with the
+/// routes in `dispatch`. Both documents are served by the API itself, at
+/// `/api/openapi.json` and `/api/protocol.schema.json`, and pinned by
+/// `tests/openapi_tests.rs`, which also checks the routes against `dispatch`.
+use crate::api::dispatch::Method;
+use crate::api::models::{
+    BetResponse, CloneMarketRequest, CopiedMarketResponse, CreateBetRequest,
+    CreateFromTemplateRequest, CreateMarketRequest, CreateMarketResponse, CreateSeasonRequest,
+    CreateWebhookRequest, DeviceMarketsResponse, ErrorResponse, ImportMarketResponse,
+    JoinMarketRequest, JoinMarketResponse, LeaderboardResponse, LinkCodeResponse,
+    LinkPlayerRequest, MarketSnapshotResponse, MembershipResponse, PlaceWagerRequest,
+    PlayerProfileResponse, PlayerResponse, ProbabilityChartResponse, RedeemLinkCodeRequest,
+    ResolveBetRequest, RevealCeremonyResponse, RevealResponse, RevealStepResponse,
+    SaveTemplateRequest, SeasonLeaderboardResponse, SeasonResponse, SnapshotQuery, SpectateRequest,
+    SpectateResponse, TemplateResponse, UpdateProfileRequest, UserResponse, WagerResponse,
+    WebhookDeliveriesResponse, WebhookResponse, WebhooksResponse,
+};
+use crate::api::protocol::WsMessage;
+use crate::db::query::{BetQuery, DeviceMarketQuery, Page, UserQuery, WagerQuery};
+use crate::domain::archive::MarketArchive;
+use crate::domain::models::{BetView, Market, User, Wager};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+/// Derives a schema, registering any types it refers to
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// What an operation answers with
+#[derive(Clone, Copy)]
+pub enum Body {
+    Json(SchemaFn),
+    Empty,  // 200 with no body
+    Csv,    // A CSV download
+    Events, // Server-Sent Events carrying `WsMessage` JSON
+}
+
+/// One documented endpoint
+#[derive(Clone, Copy)]
+pub struct Operation {
+    pub method: Method,
+    pub path: &'static str, // Below `/api`, parameters in braces
+    pub operation_id: &'static str,
+    pub summary: &'static str,
+    pub query: Option<SchemaFn>, // Properties become query parameters
+    pub body: Option<SchemaFn>,
+    pub response: Body,
+    pub deprecated: bool,
+    pub server_only: bool, // Not served by the Cloudflare worker
+}
+
+impl Operation {
+    fn new(method: Method, path: &'static str, operation_id: &'static str) -> Self {
+        Self {
+            method,
+            path,
+            operation_id,
+            summary: "",
+            query: None,
+            body: None,
+            response: Body::Empty,
+            deprecated: false,
+            server_only: false,
+        }
+    }
+
+    fn summary(self, summary: &'static str) -> Self {
+        Self { summary, ..self }
+    }
+
+    fn query<T: JsonSchema>(self) -> Self {
+        Self {
+            query: Some(T::json_schema),
+            ..self
+        }
+    }
+
+    fn body<T: JsonSchema>(self) -> Self {
+        Self {
+            body: Some(schema::<T>),
+            ..self
+        }
+    }
+
+    fn json<T: JsonSchema>(self) -> Self {
+        self.returns(Body::Json(schema::<T>))
+    }
+
+    fn returns(self, response: Body) -> Self {
+        Self { response, ..self }
+    }
+
+    fn deprecated(self) -> Self {
+        Self {
+            deprecated: true,
+            ..self
+        }
+    }
+
+    fn server_only(self) -> Self {
+        Self {
+            server_only: true,
+            ..self
+        }
+    }
+}
+
+fn schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<T>()
+}
+
+fn get(path: &'static str, operation_id: &'static str) -> Operation {
+    Operation::new(Method::Get, path, operation_id)
+}
+
+fn post(path: &'static str, operation_id: &'static str) -> Operation {
+    Operation::new(Method::Post, path, operation_id)
+}
+
+fn any_object(_: &mut SchemaGenerator) -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(schemars::schema::InstanceType::Object.into()),
+        ..Default::default()
+    })
+}
+
+/// Every endpoint, in the order `dispatch` lists them
+pub fn operations() -> Vec<Operation> {
+    vec![
+        // Markets
+        post("/markets", "create_market")
+            .summary("Create a market")
+            .body::<CreateMarketRequest>()
+            .json::<CreateMarketResponse>(),
+        post("/markets/import", "import_market")
+            .summary("Import a market archive")
+            .body::<MarketArchive>()
+            .json::<ImportMarketResponse>(),
+        get("/markets/{market_id}", "get_market")
+            .summary("Get a market")
+            .json::<Market>(),
+        post("/markets/{invite_code}/join", "join_market")
+            .summary("Join a market with its invite code")
+            .body::<JoinMarketRequest>()
+            .json::<JoinMarketResponse>(),
+        post("/markets/{invite_code}/spectate", "spectate_market")
+            .summary("Watch a market without playing")
+            .body::<SpectateRequest>()
+            .json::<SpectateResponse>(),
+        get("/markets/{market_id}/snapshot", "get_market_snapshot")
+            .summary("Everything a client needs to draw a market, as a user sees it")
+            .query::<SnapshotQuery>()
+            .json::<MarketSnapshotResponse>(),
+        get("/markets/{market_id}/users", "list_users")
+            .summary("List a market's users")
+            .query::<UserQuery>()
+            .json::<Page<User>>(),
+        get("/markets/{market_id}/leaderboard", "get_leaderboard")
+            .summary("Current players, richest first")
+            .json::<LeaderboardResponse>(),
+        post("/markets/{market_id}/open/{admin_id}", "open_market").summary("Open betting"),
+        post("/markets/{market_id}/close/{admin_id}", "close_market").summary("Close betting"),
+        post("/markets/{market_id}/delete/{admin_id}", "delete_market")
+            .summary("Delete a market and everything in it"),
+        // Bets
+        get("/markets/{market_id}/bets/pending", "get_pending_bets")
+            .summary("Bets awaiting approval")
+            .json::<Vec<BetView>>(),
+        get("/markets/{market_id}/bets/{user_id}", "get_bets")
+            .summary("Every bet as a user sees it")
+            .json::<Vec<BetView>>(),
+        get("/markets/{market_id}/bets/{user_id}/page", "list_bets")
+            .summary("A page of bets as a user sees them")
+            .query::<BetQuery>()
+            .json::<Page<BetView>>(),
+        post("/markets/{market_id}/bets/{user_id}", "create_bet")
+            .summary("Propose a bet, created by the user")
+            .body::<CreateBetRequest>()
+            .json::<BetResponse>(),
+        post(
+            "/markets/{market_id}/bets/{user_id}/create",
+            "create_bet_legacy",
+        )
+        .summary("Older spelling of create_bet")
+        .body::<CreateBetRequest>()
+        .json::<BetResponse>()
+        .deprecated(),
+        post("/bets/{bet_id}/approve/{admin_id}", "approve_bet").summary("Approve a pending bet"),
+        post("/bets/{bet_id}/wager/{user_id}", "place_wager")
+            .summary("Place a wager")
+            .body::<PlaceWagerRequest>()
+            .json::<WagerResponse>(),
+        get("/bets/{bet_id}/wagers/{user_id}", "list_wagers")
+            .summary("List a bet's wagers, for a user who can see the bet")
+            .query::<WagerQuery>()
+            .json::<Page<Wager>>(),
+        get("/bets/{bet_id}/chart", "get_probability_chart")
+            .summary("YES probability after each wager")
+            .json::<ProbabilityChartResponse>(),
+        post("/bets/{bet_id}/resolve/{admin_id}", "resolve_bet")
+            .summary("Resolve a bet and pay out")
+            .body::<ResolveBetRequest>(),
+        // Profiles and membership
+        post("/users/{user_id}/profile", "update_profile")
+            .summary("Change display name or avatar")
+            .body::<UpdateProfileRequest>()
+            .json::<UserResponse>(),
+        post("/users/{user_id}/player", "link_player")
+            .summary("Link a seat to a persistent player")
+            .body::<LinkPlayerRequest>()
+            .json::<PlayerResponse>(),
+        post("/users/{user_id}/leave", "leave_market")
+            .summary("Leave a market")
+            .json::<MembershipResponse>(),
+        post(
+            "/markets/{market_id}/kick/{admin_id}/{user_id}",
+            "kick_user",
+        )
+        .summary("Remove a player, who may rejoin")
+        .json::<MembershipResponse>(),
+        post("/markets/{market_id}/ban/{admin_id}/{user_id}", "ban_user")
+            .summary("Remove a player for good")
+            .json::<MembershipResponse>(),
+        // Archives
+        get("/markets/{market_id}/export/{admin_id}", "export_market")
+            .summary("Export a market archive")
+            .json::<MarketArchive>(),
+        get("/markets/{market_id}/csv/{report}/{admin_id}", "export_csv")
+            .summary("Download a CSV report")
+            .returns(Body::Csv),
+        // Templates
+        post("/markets/{market_id}/template/{admin_id}", "save_template")
+            .summary("Save a market as a template")
+            .body::<SaveTemplateRequest>()
+            .json::<TemplateResponse>(),
+        post("/markets/{market_id}/clone/{admin_id}", "clone_market")
+            .summary("Start a new market with the same players and bets")
+            .body::<CloneMarketRequest>()
+            .json::<CopiedMarketResponse>(),
+        get("/templates/{template_id}", "get_template")
+            .summary("Get a template")
+            .json::<TemplateResponse>(),
+        post(
+            "/templates/{template_id}/markets",
+            "create_market_from_template",
+        )
+        .summary("Create a market from a template")
+        .body::<CreateFromTemplateRequest>()
+        .json::<CopiedMarketResponse>(),
+        // Seasons
+        post("/seasons", "create_season")
+            .summary("Create a season")
+            .body::<CreateSeasonRequest>()
+            .json::<SeasonResponse>(),
+        get("/seasons/{season_id}", "get_season")
+            .summary("Get a season and its markets")
+            .json::<SeasonResponse>(),
+        post(
+            "/seasons/{season_id}/markets/{market_id}/{admin_id}",
+            "add_market_to_season",
+        )
+        .summary("Add a market to a season")
+        .json::<SeasonResponse>(),
+        get("/seasons/{season_id}/leaderboard", "get_season_leaderboard")
+            .summary("Standings across a season's markets")
+            .json::<SeasonLeaderboardResponse>(),
+        // Players
+        get("/players/{player_id}", "get_player_profile")
+            .summary("A player's lifetime stats")
+            .json::<PlayerProfileResponse>(),
+        // Reveals
+        get("/users/{user_id}/reveal", "get_reveal")
+            .summary("Bets that were about the user")
+            .json::<RevealResponse>(),
+        get("/markets/{market_id}/reveal", "get_reveal_ceremony")
+            .summary("The reveal ceremony so far")
+            .json::<RevealCeremonyResponse>(),
+        post(
+            "/markets/{market_id}/reveal/start/{admin_id}",
+            "start_reveal_ceremony",
+        )
+        .summary("Start revealing hidden bets")
+        .json::<RevealCeremonyResponse>(),
+        post(
+            "/markets/{market_id}/reveal/next/{admin_id}",
+            "reveal_next_bet",
+        )
+        .summary("Reveal the next hidden bet")
+        .json::<RevealStepResponse>(),
+        // Spectators
+        get("/spectators/{token}/bets", "get_spectator_bets")
+            .summary("Bets as the shared screen shows them")
+            .json::<Vec<BetView>>(),
+        // Devices
+        get("/devices/{device_id}/markets", "get_device_markets")
+            .summary("Every market the device has joined, newest first")
+            .json::<DeviceMarketsResponse>(),
+        get("/devices/{device_id}/markets/page", "list_device_markets")
+            .summary("A page of the markets the device has joined")
+            .query::<DeviceMarketQuery>()
+            .json::<DeviceMarketsResponse>(),
+        post("/devices/{device_id}/link-code", "create_link_code")
+            .summary("Get a code for moving this device's seats")
+            .json::<LinkCodeResponse>(),
+        post("/devices/{device_id}/link", "redeem_link_code")
+            .summary("Move another device's seats to this one")
+            .body::<RedeemLinkCodeRequest>()
+            .json::<DeviceMarketsResponse>(),
+        // Webhooks
+        post("/markets/{market_id}/webhooks/{admin_id}", "create_webhook")
+            .summary("Register a webhook")
+            .body::<CreateWebhookRequest>()
+            .json::<WebhookResponse>(),
+        get("/markets/{market_id}/webhooks/{admin_id}", "get_webhooks")
+            .summary("A market's webhooks")
+            .json::<WebhooksResponse>(),
+        post("/webhooks/{webhook_id}/delete/{admin_id}", "delete_webhook")
+            .summary("Remove a webhook"),
+        get(
+            "/webhooks/{webhook_id}/deliveries/{admin_id}",
+            "get_webhook_deliveries",
+        )
+        .summary("A webhook's delivery log")
+        .json::<WebhookDeliveriesResponse>(),
+        // Schemas
+        get("/openapi.json", "get_openapi")
+            .summary("This document")
+            .returns(Body::Json(any_object)),
+        get("/protocol.schema.json", "get_protocol_schema")
+            .summary("JSON Schema for WebSocket and event stream messages")
+            .returns(Body::Json(any_object)),
+        // Event stream (see `api::sse`)
+        get("/markets/{market_id}/events", "market_events")
+            .summary("A market's events as Server-Sent Events, resumable with Last-Event-ID")
+            .returns(Body::Events)
+            .server_only(),
+    ]
+}
+
+/// The documented path a request path matches, e.g. `/markets/{market_id}`
+///
+/// Used to label request metrics without a series per ID. Where a literal
+/// segment and a parameter both match (`bets/pending`), the literal wins, as
+/// it does in `dispatch`.
+pub fn route_of(method: Method, path: &str) -> Option<&'static str> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    operations()
+        .into_iter()
+        .filter(|op| op.method == method)
+        .filter_map(|op| {
+            let template: Vec<&str> = op.path.trim_matches('/').split('/').collect();
+            if template.len() != segments.len() {
+                return None;
+            }
+            let mut literals = 0;
+            for (expected, actual) in template.iter().zip(&segments) {
+                if !expected.starts_with('{') {
+                    if expected != actual {
+                        return None;
+                    }
+                    literals += 1;
+                }
+            }
+            Some((literals, op.path))
+        })
+        .max_by_key(|(literals, _)| *literals)
+        .map(|(_, path)| path)
+}
+
+/// The OpenAPI 3 document
+pub fn spec() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let error = gen.subschema_for::<ErrorResponse>();
+    let message = gen.subschema_for::<WsMessage>();
+
+    let mut paths = Map::new();
+    for op in operations() {
+        let mut parameters: Vec<Value> = path_parameters(op.path);
+        if let Some(query) = op.query {
+            let query = query(&mut gen);
+            parameters.extend(query_parameters(&mut gen, query));
+        }
+
+        let mut operation = json!({
+            "operationId": op.operation_id,
+            "summary": op.summary,
+            "responses": {
+                "200": response(&mut gen, op.response, &message),
+                "default": {
+                    "description": "Error",
+                    "content": { "application/json": { "schema": finish(&mut gen, error.clone()) } },
+                },
+            },
+        });
+        if !parameters.is_empty() {
+            operation["parameters"] = Value::Array(parameters);
+        }
+        if let Some(body) = op.body {
+            let body = body(&mut gen);
+            let body = finish(&mut gen, body);
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": body } },
+            });
+        }
+        if op.deprecated {
+            operation["deprecated"] = Value::Bool(true);
+        }
+        if op.server_only {
+            operation["description"] =
+                Value::from("Served by the axum server only, not the Cloudflare worker.");
+        }
+
+        let method = match op.method {
+            Method::Get => "get",
+            Method::Post => "post",
+        };
+        paths
+            .entry(op.path)
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .expect("path items are objects")
+            .insert(method.to_string(), operation);
+    }
+
+    let mut schemas = gen.take_definitions();
+    for schema in schemas.values_mut() {
+        visit(&mut gen, schema);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Cazino API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": "/api" }],
+        "paths": paths,
+        "components": { "schemas": schemas },
+    })
+}
+
+/// JSON Schema for `WsMessage`, as sent over WebSockets and the event stream
+pub fn protocol_schema() -> Value {
+    let schema = SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<WsMessage>();
+    serde_json::to_value(schema).expect("schemas serialize")
+}
+
+fn response(gen: &mut SchemaGenerator, body: Body, message: &Schema) -> Value {
+    match body {
+        Body::Json(schema) => {
+            let schema = schema(gen);
+            let schema = finish(gen, schema);
+            json!({
+                "description": "OK",
+                "content": { "application/json": { "schema": schema } },
+            })
+        }
+        Body::Empty => json!({ "description": "OK" }),
+        Body::Csv => json!({
+            "description": "CSV report",
+            "content": { "text/csv": { "schema": { "type": "string" } } },
+        }),
+        Body::Events => json!({
+            "description": "Event stream; each event's data is a message, its ID the sequence number",
+            "content": {
+                "text/event-stream": {
+                    "schema": { "type": "string" },
+                    "x-event-data": finish(gen, message.clone()),
+                },
+            },
+        }),
+    }
+}
+
+/// Parameters named in braces in the path
+fn path_parameters(path: &str) -> Vec<Value> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(|name| {
+            let schema = match name {
+                "report" => {
+                    json!({ "type": "string", "enum": ["wagers", "results", "leaderboard"] })
+                }
+                "invite_code" | "token" | "device_id" => json!({ "type": "string" }),
+                _ => json!({ "type": "string", "format": "uuid" }),
+            };
+            json!({ "name": name, "in": "path", "required": true, "schema": schema })
+        })
+        .collect()
+}
+
+/// One query parameter per property of a query struct, required where the
+/// struct requires it
+fn query_parameters(gen: &mut SchemaGenerator, query: Schema) -> Vec<Value> {
+    let Schema::Object(SchemaObject {
+        object: Some(object),
+        ..
+    }) = query
+    else {
+        return Vec::new();
+    };
+
+    object
+        .properties
+        .into_iter()
+        .map(|(name, schema)| {
+            let required = object.required.contains(&name);
+            json!({ "name": name, "in": "query", "required": required, "schema": finish(gen, schema) })
+        })
+        .collect()
+}
+
+/// Apply the OpenAPI settings' adjustments to a schema and encode it
+fn finish(gen: &mut SchemaGenerator, mut schema: Schema) -> Value {
+    visit(gen, &mut schema);
+    serde_json::to_value(schema).expect("schemas serialize")
+}
+
+fn visit(gen: &mut SchemaGenerator, schema: &mut Schema) {
+    for visitor in gen.visitors_mut() {
+        visitor.visit_schema(schema);
+    }
+}
@@ -0,0 +1,212 @@
+/// WebSocket protocol shared by the axum server and the Cloudflare worker
+///
+/// Clients open with `subscribe`, naming the protocol version they speak, and
+/// the server answers `subscribed` with its own (or `error` if it can't serve
+/// that version). Everything else server -> client is an event published by
+/// the API. The JSON encoding is pinned by `tests/protocol_tests.rs`.
+use crate::domain::models::{BetReveal, BetStatus, Market, MarketStatus, MembershipStatus, Side};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Version of the message protocol below
+///
+/// Bump this for any change existing clients couldn't decode, and update the
+/// golden files with it.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Types of the events the API publishes, which webhooks can subscribe to
+pub const EVENT_TYPES: &[&str] = &[
+    "market_update",
+    "bet_created",
+    "bet_approved",
+    "user_joined",
+    "wager_placed",
+    "bet_resolved",
+    "market_status_changed",
+    "market_deleted",
+    "user_updated",
+    "membership_changed",
+    "bet_voided",
+    "reveal_started",
+    "bet_revealed",
+];
+
+/// Clients from before the handshake don't send a version; they speak 1
+fn unversioned() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(tag = "type")]
+pub enum WsMessage {
+    // Client -> Server
+    #[serde(rename = "subscribe")]
+    Subscribe {
+        market_id: Uuid,
+        #[serde(default = "unversioned")]
+        protocol_version: u32,
+    },
+
+    #[serde(rename = "ping")]
+    Ping,
+
+    // Server -> Client
+    #[serde(rename = "subscribed")]
+    Subscribed {
+        market_id: Uuid,
+        protocol_version: u32,
+    },
+
+    #[serde(rename = "market_update")]
+    MarketUpdate { market: Market },
+
+    #[serde(rename = "bet_created")]
+    BetCreated {
+        bet_id: Uuid,
+        description: String,
+        hide_from_subject: bool, // Spectator screens skip these
+    },
+
+    #[serde(rename = "bet_approved")]
+    BetApproved { bet_id: Uuid },
+
+    #[serde(rename = "user_joined")]
+    UserJoined {
+        user_id: Uuid,
+        display_name: String,
+        market_id: Uuid,
+    },
+
+    #[serde(rename = "wager_placed")]
+    WagerPlaced {
+        bet_id: Uuid,
+        user_id: Uuid,
+        side: Side,
+        amount: i64,
+        new_yes_pool: i64,
+        new_no_pool: i64,
+        new_probability: f64,
+    },
+
+    #[serde(rename = "bet_resolved")]
+    BetResolved {
+        bet_id: Uuid,
+        outcome: Side,
+        status: BetStatus,
+    },
+
+    #[serde(rename = "market_status_changed")]
+    MarketStatusChanged {
+        market_id: Uuid,
+        status: MarketStatus,
+    },
+
+    #[serde(rename = "market_deleted")]
+    MarketDeleted { market_id: Uuid },
+
+    #[serde(rename = "user_updated")]
+    UserUpdated {
+        user_id: Uuid,
+        display_name: String,
+        avatar: String,
+    },
+
+    #[serde(rename = "membership_changed")]
+    MembershipChanged {
+        market_id: Uuid,
+        user_id: Uuid,
+        status: MembershipStatus,
+    },
+
+    #[serde(rename = "bet_voided")]
+    BetVoided { bet_id: Uuid },
+
+    #[serde(rename = "reveal_started")]
+    RevealStarted { market_id: Uuid, total: usize },
+
+    #[serde(rename = "bet_revealed")]
+    BetRevealed {
+        market_id: Uuid,
+        reveal: BetReveal,
+        revealed_count: usize,
+        total: usize,
+    },
+
+    #[serde(rename = "pong")]
+    Pong,
+
+    #[serde(rename = "error")]
+    Error { message: String },
+}
+
+impl WsMessage {
+    /// The message's `type` tag
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            WsMessage::Subscribe { .. } => "subscribe",
+            WsMessage::Ping => "ping",
+            WsMessage::Subscribed { .. } => "subscribed",
+            WsMessage::MarketUpdate { .. } => "market_update",
+            WsMessage::BetCreated { .. } => "bet_created",
+            WsMessage::BetApproved { .. } => "bet_approved",
+            WsMessage::UserJoined { .. } => "user_joined",
+            WsMessage::WagerPlaced { .. } => "wager_placed",
+            WsMessage::BetResolved { .. } => "bet_resolved",
+            WsMessage::MarketStatusChanged { .. } => "market_status_changed",
+            WsMessage::MarketDeleted { .. } => "market_deleted",
+            WsMessage::UserUpdated { .. } => "user_updated",
+            WsMessage::MembershipChanged { .. } => "membership_changed",
+            WsMessage::BetVoided { .. } => "bet_voided",
+            WsMessage::RevealStarted { .. } => "reveal_started",
+            WsMessage::BetRevealed { .. } => "bet_revealed",
+            WsMessage::Pong => "pong",
+            WsMessage::Error { .. } => "error",
+        }
+    }
+
+    /// Whether this message may be streamed to spectators
+    ///
+    /// Client requests and per-connection replies are never public, and
+    /// neither are new bets hidden from their subject, since the subject is
+    /// usually watching the same screen.
+    pub fn is_public(&self) -> bool {
+        !matches!(
+            self,
+            WsMessage::Subscribe { .. }
+                | WsMessage::Ping
+                | WsMessage::Subscribed { .. }
+                | WsMessage::Pong
+                | WsMessage::Error { .. }
+                | WsMessage::BetCreated {
+                    hide_from_subject: true,
+                    ..
+                }
+        )
+    }
+
+    /// The answer to a message from a client, if it gets one
+    ///
+    /// Events sent by clients are ignored: only the API publishes them.
+    pub fn reply(&self) -> Option<WsMessage> {
+        match self {
+            WsMessage::Subscribe {
+                market_id,
+                protocol_version: PROTOCOL_VERSION,
+            } => Some(WsMessage::Subscribed {
+                market_id: *market_id,
+                protocol_version: PROTOCOL_VERSION,
+            }),
+            WsMessage::Subscribe {
+                protocol_version, ..
+            } => Some(WsMessage::Error {
+                message: format!(
+                    "Unsupported protocol version {} (this server speaks {})",
+                    protocol_version, PROTOCOL_VERSION
+                ),
+            }),
+            WsMessage::Ping => Some(WsMessage::Pong),
+            _ => None,
+        }
+    }
+}
@@ -1,30 +1,58 @@
 /// HTTP + WebSocket server
+use crate::api::delivery::{self, HttpClient};
+use crate::api::dispatch::{self, ApiError};
+use crate::api::limits::{self, RateLimitConfig, RateLimiter};
+use crate::api::openapi;
 use crate::api::routes::{self, AppState};
+use crate::api::sse;
 use crate::api::websocket;
 use crate::db::Database;
 use crate::service::CazinoService;
 use axum::{
-    extract::{ws::WebSocketUpgrade, Path, State},
-    response::IntoResponse,
-    routing::{get, post},
+    body::Body,
+    extract::{ws::WebSocketUpgrade, ConnectInfo, MatchedPath, Path, Request, State},
+    http::{header, Method},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{any, get},
     Router,
 };
+use chrono::Utc;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
+use uuid::Uuid;
+
+/// Largest request body read for rate limiting, as axum's own default limit
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
 
 /// Run the HTTP + WebSocket server
-pub async fn run_server<D>(service: CazinoService<D>, port: u16) -> anyhow::Result<()>
+pub async fn run_server<D>(
+    service: CazinoService<D>,
+    port: u16,
+    limits: RateLimitConfig,
+) -> anyhow::Result<()>
 where
     D: Database + Clone + Send + Sync + 'static,
 {
     // Create broadcast channel for WebSocket messages
-    let (broadcast_tx, _) = websocket::create_broadcast_channel();
+    let (broadcast_tx, _) = websocket::create_broadcast_channel(service.metrics().clone());
+    let service = Arc::new(service);
+
+    // Events also go out to each market's webhooks
+    let client = match service.allows_private_webhooks() {
+        true => HttpClient::new().allow_private(),
+        false => HttpClient::new(),
+    };
+    delivery::spawn_webhook_delivery(service.clone(), Arc::new(client), broadcast_tx.subscribe());
 
     let state = AppState {
-        service: Arc::new(service),
+        service,
         broadcast_tx: Arc::new(broadcast_tx),
+        limiter: Arc::new(RateLimiter::new(limits)),
     };
 
     let app = create_router(state);
@@ -36,7 +64,12 @@ where
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     println!("📡 HTTP API:    http://localhost:{}", port);
     println!("🔌 WebSocket:   ws://localhost:{}/ws", port);
+    println!(
+        "📡 SSE:         http://localhost:{}/api/markets/:id/events",
+        port
+    );
     println!("❤️  Health:      http://localhost:{}/health", port);
+    println!("📈 Metrics:     http://localhost:{}/metrics", port);
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     println!("📚 API Docs:    See API.md");
     println!("🧪 Test:        cargo run -- cli");
@@ -45,7 +78,12 @@ where
     tracing::info!("🎲 Cazino API server listening on {}", addr);
     tracing::info!("📡 WebSocket endpoint: ws://{}/ws", addr);
 
-    axum::serve(listener, app).await?;
+    // Peer addresses are the client IPs for rate limiting
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
@@ -56,67 +94,29 @@ fn create_router<D: Database + Clone + Send + Sync + 'static>(state: AppState<D>
         .nest_service("/", ServeDir::new("ui"))
         // WebSocket endpoint (with market_id parameter)
         .route("/ws/:market_id", get(ws_handler))
-        // Market routes
-        .route("/api/markets", post(routes::create_market::<D>))
-        .route("/api/markets/:market_id", get(routes::get_market::<D>))
-        .route(
-            "/api/markets/:invite_code/join",
-            post(routes::join_market::<D>),
-        )
-        .route(
-            "/api/markets/:market_id/leaderboard",
-            get(routes::get_leaderboard::<D>),
-        )
-        .route(
-            "/api/markets/:market_id/open/:admin_id",
-            post(routes::open_market::<D>),
-        )
-        .route(
-            "/api/markets/:market_id/close/:admin_id",
-            post(routes::close_market::<D>),
-        )
-        .route(
-            "/api/markets/:market_id/delete/:admin_id",
-            post(routes::delete_market::<D>),
-        )
-        // Bet routes
-        .route(
-            "/api/markets/:market_id/bets/:user_id",
-            get(routes::get_bets::<D>),
-        )
         .route(
-            "/api/markets/:market_id/bets/:creator_id/create",
-            post(routes::create_bet::<D>),
+            "/ws/:market_id/spectate/:token",
+            get(spectator_ws_handler::<D>),
         )
+        // Server-Sent Events, for networks that block WebSockets
         .route(
-            "/api/markets/:market_id/bets/pending",
-            get(routes::get_pending_bets::<D>),
-        )
-        .route(
-            "/api/bets/:bet_id/approve/:admin_id",
-            post(routes::approve_bet::<D>),
-        )
-        .route(
-            "/api/bets/:bet_id/wager/:user_id",
-            post(routes::place_wager::<D>),
-        )
-        .route(
-            "/api/bets/:bet_id/chart",
-            get(routes::get_probability_chart::<D>),
-        )
-        .route(
-            "/api/bets/:bet_id/resolve/:admin_id",
-            post(routes::resolve_bet::<D>),
-        )
-        // Reveal route
-        .route("/api/users/:user_id/reveal", get(routes::get_reveal::<D>))
-        // Device routes (fingerprint-based)
-        .route(
-            "/api/devices/:device_id/markets",
-            get(routes::get_device_markets::<D>),
+            "/api/markets/:market_id/events",
+            get(sse::market_events::<D>),
         )
+        // API routes, shared with the Cloudflare worker (see api::dispatch)
+        .route("/api/*path", any(routes::api::<D>))
         // Health check
         .route("/health", get(health_check))
+        // Prometheus scrape endpoint
+        .route("/metrics", get(metrics_handler::<D>))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::<D>,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            track_requests::<D>,
+        ))
         .with_state(state)
         .layer(
             CorsLayer::new()
@@ -129,37 +129,585 @@ fn create_router<D: Database + Clone + Send + Sync + 'static>(state: AppState<D>
 
 async fn ws_handler<D: Database + Clone + Send + Sync + 'static>(
     ws: WebSocketUpgrade,
-    Path(market_id): Path<String>,
+    Path(market_id): Path<Uuid>,
     State(state): State<AppState<D>>,
 ) -> impl IntoResponse {
     tracing::info!("🔌 WebSocket upgrade requested for market: {}", market_id);
-    ws.on_upgrade(move |socket| websocket::handle_socket(socket, state.broadcast_tx))
+    ws.on_upgrade(move |socket| websocket::handle_socket(socket, state.broadcast_tx, market_id))
+}
+
+async fn spectator_ws_handler<D: Database + Clone + Send + Sync + 'static>(
+    ws: WebSocketUpgrade,
+    Path((market_id, token)): Path<(Uuid, String)>,
+    State(state): State<AppState<D>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let spectator = state.service.get_spectator(&token).await?;
+
+    if spectator.market_id != market_id {
+        return Err(ApiError::new(
+            403,
+            "Spectator token is not valid for this market",
+        ));
+    }
+
+    tracing::info!(
+        "📺 Spectator WebSocket requested for market {} by {}",
+        market_id,
+        spectator.display_name
+    );
+
+    Ok(ws.on_upgrade(move |socket| websocket::handle_spectator_socket(socket, state, market_id)))
 }
 
 async fn health_check() -> &'static str {
     "OK"
 }
 
+/// Everything in the service's metrics, in the Prometheus text format
+async fn metrics_handler<D: Database + 'static>(State(state): State<AppState<D>>) -> Response {
+    let metrics = state.service.metrics();
+    metrics
+        .broadcast_queue_length
+        .set(state.broadcast_tx.queue_len() as i64);
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+        .into_response()
+}
+
+/// Refuse API requests over their rate limit, and count wrong invite codes
+/// towards a lockout (see `api::limits`)
+async fn rate_limit<D: Database + 'static>(
+    State(state): State<AppState<D>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = match *request.method() {
+        Method::GET => dispatch::Method::Get,
+        Method::POST => dispatch::Method::Post,
+        _ => return next.run(request).await,
+    };
+    let Some(path) = request.uri().path().strip_prefix("/api") else {
+        return next.run(request).await;
+    };
+    let path = path.to_string();
+    let query = request.uri().query().unwrap_or("").to_string();
+
+    let forwarded_for = request
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok());
+    let peer = peer.map(|ConnectInfo(addr)| addr.ip().to_string());
+    let ip = limits::client_ip(state.limiter.config(), forwarded_for, peer.as_deref()).to_string();
+
+    // The device ID can be in the body, which the API reads whole anyway
+    let (parts, body) = request.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, MAX_BODY_BYTES).await else {
+        return ApiError::new(413, "Request body is too large").into_response();
+    };
+    let api_request = dispatch::Request {
+        method,
+        path: &path,
+        query: &query,
+        body: &body,
+    };
+
+    if let Err(e) = state.limiter.check(&api_request, &ip, Utc::now()) {
+        tracing::warn!("🚦 Rate limited {} on {}: {}", ip, path, e);
+        return ApiError::from(e).into_response();
+    }
+    let response = next
+        .run(Request::from_parts(parts, Body::from(body.clone())))
+        .await;
+    state
+        .limiter
+        .record(&api_request, &ip, response.status().as_u16(), Utc::now());
+    response
+}
+
+/// Count and time every request, by route
+async fn track_requests<D: Database + 'static>(
+    State(state): State<AppState<D>>,
+    matched: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let route = route_label(&method, request.uri().path(), matched.as_ref());
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let metrics = state.service.metrics();
+    metrics
+        .http_requests
+        .inc(&[method.as_str(), &route, response.status().as_str()]);
+    metrics
+        .http_request_duration
+        .observe(&[method.as_str(), &route], started.elapsed().as_secs_f64());
+    response
+}
+
+/// The route a request is counted under: the documented path for API
+/// requests, the axum route for the rest, and `other` for anything unrouted
+/// (so unknown URLs don't each get a series)
+fn route_label(method: &Method, path: &str, matched: Option<&MatchedPath>) -> String {
+    let api_method = match *method {
+        Method::GET => Some(dispatch::Method::Get),
+        Method::POST => Some(dispatch::Method::Post),
+        _ => None,
+    };
+    if let (Some(api_method), Some(api_path)) = (api_method, path.strip_prefix("/api")) {
+        if let Some(route) = openapi::route_of(api_method, api_path) {
+            return format!("/api{}", route);
+        }
+    }
+
+    match matched.map(MatchedPath::as_str) {
+        Some(route) if !route.contains('*') => route.to_string(),
+        _ => "other".to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::protocol::WsMessage;
     use crate::db::SqliteDatabase;
+    use crate::service::CreateMarketParams;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use futures::StreamExt;
     use std::sync::Arc;
+    use tower::Service;
 
-    #[tokio::test]
-    async fn test_router_creation() {
+    async fn setup_state() -> AppState<SqliteDatabase> {
+        state_over(setup_db().await)
+    }
+
+    async fn setup_db() -> Arc<SqliteDatabase> {
         let db = SqliteDatabase::new("sqlite::memory:").await.unwrap();
         db.run_migrations().await.unwrap();
-        let service = CazinoService::new(Arc::new(db));
+        Arc::new(db)
+    }
 
-        let (broadcast_tx, _) = websocket::create_broadcast_channel();
+    fn state_over(db: Arc<SqliteDatabase>) -> AppState<SqliteDatabase> {
+        let service = CazinoService::new(db);
+        let (broadcast_tx, _) = websocket::create_broadcast_channel(service.metrics().clone());
 
-        let state = AppState {
+        AppState {
             service: Arc::new(service),
             broadcast_tx: Arc::new(broadcast_tx),
-        };
+            limiter: Arc::new(RateLimiter::new(RateLimitConfig::default())),
+        }
+    }
+
+    async fn create_market(state: &AppState<SqliteDatabase>) -> Uuid {
+        create_market_with_admin(state).await.0
+    }
+
+    async fn create_market_with_admin(state: &AppState<SqliteDatabase>) -> (Uuid, Uuid) {
+        let (market, admin) = state
+            .service
+            .create_market(CreateMarketParams {
+                name: "Streamed Market".to_string(),
+                admin_device_id: "admin-device".to_string(),
+                admin_name: "Admin".to_string(),
+                admin_avatar: "👑".to_string(),
+                starting_balance: 1000,
+                duration_hours: 24,
+                custom_invite_code: None,
+            })
+            .await
+            .unwrap();
+        (market.id, admin.id)
+    }
+
+    async fn get_json(
+        state: &AppState<SqliteDatabase>,
+        path: &str,
+    ) -> (StatusCode, serde_json::Value) {
+        let response = create_router(state.clone())
+            .call(Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    /// Open an SSE stream, returning its body as text chunks
+    async fn open_events(
+        state: &AppState<SqliteDatabase>,
+        market_id: Uuid,
+        last_event_id: Option<&str>,
+    ) -> impl futures::Stream<Item = String> {
+        let mut request = Request::get(format!("/api/markets/{}/events", market_id));
+        if let Some(id) = last_event_id {
+            request = request.header("Last-Event-ID", id);
+        }
+        let response = create_router(state.clone())
+            .call(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        response
+            .into_body()
+            .into_data_stream()
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+    }
+
+    async fn next_event(stream: &mut (impl futures::Stream<Item = String> + Unpin)) -> String {
+        tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+            .await
+            .expect("no event arrived")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_router_creation() {
+        let state = setup_state().await;
 
         let _router = create_router(state);
         // Just ensure the router can be created
     }
+
+    #[tokio::test]
+    async fn test_metrics_count_requests_by_route() {
+        let state = setup_state().await;
+        let (market_id, admin_id) = create_market_with_admin(&state).await;
+        state
+            .service
+            .open_market(market_id, admin_id)
+            .await
+            .unwrap();
+        state
+            .service
+            .create_bet(
+                market_id,
+                admin_id,
+                vec![],
+                true,
+                "Someone is late".to_string(),
+                "1:1".to_string(),
+                40,
+                false,
+            )
+            .await
+            .unwrap();
+
+        for path in [
+            format!("/api/markets/{}", market_id),
+            format!("/api/markets/{}", Uuid::new_v4()),
+            format!("/api/markets/{}/bets/pending", market_id),
+            "/health".to_string(),
+            format!("/api/nowhere/{}", Uuid::new_v4()),
+        ] {
+            create_router(state.clone())
+                .call(Request::get(path).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+        }
+
+        let response = create_router(state.clone())
+            .call(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+
+        // IDs are folded into the documented route
+        for line in [
+            "cazino_http_requests_total{method=\"GET\",route=\"/api/markets/{market_id}\",status=\"200\"} 1",
+            "cazino_http_requests_total{method=\"GET\",route=\"/api/markets/{market_id}\",status=\"404\"} 1",
+            "cazino_http_requests_total{method=\"GET\",route=\"/api/markets/{market_id}/bets/pending\",status=\"200\"} 1",
+            "cazino_http_requests_total{method=\"GET\",route=\"/health\",status=\"200\"} 1",
+            "cazino_http_requests_total{method=\"GET\",route=\"other\",status=\"404\"} 1",
+            "cazino_http_request_duration_seconds_count{method=\"GET\",route=\"/health\"} 1",
+            "cazino_wagers_placed_total 1",
+            "cazino_coins_wagered_total 40",
+        ] {
+            assert!(text.contains(&format!("{}\n", line)), "missing {}", line);
+        }
+        assert!(!text.contains(&market_id.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_bad_invite_codes_lock_out_with_429() {
+        let state = AppState {
+            limiter: Arc::new(RateLimiter::new(RateLimitConfig {
+                max_bad_invite_codes: 2,
+                ..RateLimitConfig::default()
+            })),
+            ..setup_state().await
+        };
+        let join = |code: &str| {
+            let body = serde_json::json!({
+                "display_name": "Mallory",
+                "avatar": "🦊",
+                "device_id": "guessing-device",
+            });
+            Request::post(format!("/api/markets/{}/join", code))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        for code in ["AAAAAA", "BBBBBB"] {
+            let response = create_router(state.clone()).call(join(code)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+
+        // Even a real code is refused now
+        let market_id = create_market(&state).await;
+        let invite_code = state
+            .service
+            .get_market(market_id)
+            .await
+            .unwrap()
+            .invite_code;
+        let response = create_router(state.clone())
+            .call(join(&invite_code))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()["retry-after"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after > 0 && retry_after <= 15 * 60);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["retry_after"], retry_after);
+        assert!(error["error"].as_str().unwrap().contains("wrong codes"));
+
+        // Other requests still go through
+        let (status, _) = get_json(&state, &format!("/api/markets/{}", market_id)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_sse_streams_market_events() {
+        let state = setup_state().await;
+        let market_id = create_market(&state).await;
+        let mut events = Box::pin(open_events(&state, market_id, None).await);
+
+        // Other markets' events are left out
+        let other = Uuid::new_v4();
+        websocket::broadcast(
+            &state.broadcast_tx,
+            other,
+            WsMessage::BetVoided { bet_id: other },
+        );
+        let message = WsMessage::BetVoided { bet_id: market_id };
+        websocket::broadcast(&state.broadcast_tx, market_id, message.clone());
+
+        assert_eq!(
+            next_event(&mut events).await,
+            format!(
+                "id: 1\ndata: {}\n\n",
+                serde_json::to_string(&message).unwrap()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sse_resumes_from_last_event_id() {
+        let state = setup_state().await;
+        let market_id = create_market(&state).await;
+        for _ in 0..3 {
+            websocket::broadcast(
+                &state.broadcast_tx,
+                market_id,
+                WsMessage::BetVoided { bet_id: market_id },
+            );
+        }
+
+        let mut events = Box::pin(open_events(&state, market_id, Some("1")).await);
+        assert!(next_event(&mut events).await.contains("id: 2\n"));
+        assert!(next_event(&mut events).await.contains("id: 3\n"));
+
+        websocket::broadcast(
+            &state.broadcast_tx,
+            market_id,
+            WsMessage::BetVoided { bet_id: market_id },
+        );
+        assert!(next_event(&mut events).await.contains("id: 4\n"));
+
+        // An ID from before a restart asks the client to refetch
+        let mut events = Box::pin(open_events(&state, market_id, Some("99")).await);
+        assert!(next_event(&mut events).await.starts_with("event: resync\n"));
+    }
+
+    #[tokio::test]
+    async fn test_sse_unknown_market() {
+        let state = setup_state().await;
+        let response = create_router(state)
+            .call(
+                Request::get(format!("/api/markets/{}/events", Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_market_snapshot() {
+        let db = setup_db().await;
+        let state = state_over(db.clone());
+        let (market_id, admin_id) = create_market_with_admin(&state).await;
+        let service = &state.service;
+
+        let invite_code = service.get_market(market_id).await.unwrap().invite_code;
+        let (_, alice) = service
+            .join_market(
+                invite_code,
+                "alice-device".to_string(),
+                "Alice".to_string(),
+                "👩".to_string(),
+            )
+            .await
+            .unwrap();
+        service.open_market(market_id, admin_id).await.unwrap();
+        let hidden = service
+            .create_bet(
+                market_id,
+                admin_id,
+                vec![alice.id],
+                false,
+                "Alice oversleeps".to_string(),
+                "1:1".to_string(),
+                100,
+                true,
+            )
+            .await
+            .unwrap();
+        service.approve_bet(hidden.id, admin_id).await.unwrap();
+        let proposed = service
+            .create_bet(
+                market_id,
+                alice.id,
+                vec![admin_id],
+                false,
+                "Admin burns the toast".to_string(),
+                "1:1".to_string(),
+                50,
+                false,
+            )
+            .await
+            .unwrap();
+        let surprise = service
+            .create_bet(
+                market_id,
+                alice.id,
+                vec![admin_id],
+                false,
+                "Admin forgets the anniversary".to_string(),
+                "1:1".to_string(),
+                50,
+                true,
+            )
+            .await
+            .unwrap();
+        // Bets only wait for approval when they come from a template
+        for bet_id in [proposed.id, surprise.id] {
+            db.update_bet_status(bet_id, crate::domain::models::BetStatus::Pending)
+                .await
+                .unwrap();
+        }
+        service
+            .place_wager(hidden.id, admin_id, crate::domain::models::Side::Yes, 100)
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            websocket::broadcast(
+                &state.broadcast_tx,
+                market_id,
+                WsMessage::BetApproved { bet_id: hidden.id },
+            );
+        }
+
+        // Alice can't see the bet about her and gets no approval queue
+        let (status, snapshot) = get_json(
+            &state,
+            &format!("/api/markets/{}/snapshot?user_id={}", market_id, alice.id),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(snapshot["market"]["id"], market_id.to_string());
+        assert_eq!(snapshot["user"]["id"], alice.id.to_string());
+        assert_eq!(snapshot["last_event_seq"], 2);
+        assert!(snapshot["pending"].is_null());
+
+        let bets = snapshot["bets"].as_array().unwrap();
+        assert_eq!(bets.len(), 3);
+        let about_alice = bets
+            .iter()
+            .find(|b| b["id"] == hidden.id.to_string())
+            .unwrap();
+        assert_eq!(about_alice["is_hidden"], true);
+        assert!(about_alice["description"].is_null());
+        let pools = service.get_bet(hidden.id).await.unwrap();
+        assert_eq!(
+            about_alice["probability"],
+            crate::domain::parimutuel::calculate_probability(pools.yes_pool, pools.no_pool)
+        );
+
+        let leaderboard = snapshot["leaderboard"].as_array().unwrap();
+        assert_eq!(leaderboard.len(), 2);
+        assert_eq!(leaderboard[0]["user"]["id"], alice.id.to_string());
+        assert_eq!(leaderboard[0]["rank"], 1);
+
+        // The admin gets the queue
+        let (_, snapshot) = get_json(
+            &state,
+            &format!("/api/markets/{}/snapshot?user_id={}", market_id, admin_id),
+        )
+        .await;
+        let pending = snapshot["pending"].as_array().unwrap();
+        assert_eq!(pending.len(), 2);
+        let queued = |id: uuid::Uuid| pending.iter().find(|b| b["id"] == id.to_string()).unwrap();
+        assert_eq!(queued(proposed.id)["description"], "Admin burns the toast");
+        // Even in the queue, a bet hidden from the admin stays hidden
+        assert_eq!(queued(surprise.id)["is_hidden"], true);
+        assert!(queued(surprise.id)["description"].is_null());
+
+        // The viewer is required
+        let (status, _) = get_json(&state, &format!("/api/markets/{}/snapshot", market_id)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Viewers from other markets are turned away
+        let (other_market, other_admin) = create_market_with_admin(&state).await;
+        let (status, _) = get_json(
+            &state,
+            &format!(
+                "/api/markets/{}/snapshot?user_id={}",
+                market_id, other_admin
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_ne!(other_market, market_id);
+    }
 }
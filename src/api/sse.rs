@@ -0,0 +1,118 @@
+/// Server-Sent Events stream, for networks that block WebSockets
+///
+/// `GET /api/markets/:market_id/events` streams the market's events as the
+/// same JSON the WebSocket carries, each with its sequence number as the
+/// event ID. A client reconnecting with `Last-Event-ID` first gets the events
+/// it missed. When those are no longer held (or the server restarted), it
+/// gets a `resync` event instead and should refetch the market.
+use crate::api::dispatch::ApiError;
+use crate::api::routes::AppState;
+use crate::api::websocket::{BroadcastRx, MarketEvent, Missed};
+use crate::db::Database;
+use crate::metrics::Metrics;
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::stream::{self, Stream};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+/// Stream a market's events
+pub async fn market_events<D: Database + 'static>(
+    State(state): State<AppState<D>>,
+    Path(market_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    state.service.get_market(market_id).await?;
+
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+
+    // Subscribe before reading the history, so no event falls between them
+    let rx = state.broadcast_tx.subscribe();
+    let mut stream = EventStream {
+        market_id,
+        rx,
+        metrics: state.broadcast_tx.metrics().clone(),
+        backlog: VecDeque::new(),
+        seen: 0,
+        resync: false,
+    };
+    if let Some(last_event_id) = last_event_id {
+        match state.broadcast_tx.since(market_id, last_event_id) {
+            Missed::Events(events) => {
+                stream.seen = events.last().map_or(last_event_id, |e| e.seq);
+                stream.backlog = events.into();
+            }
+            Missed::Gap => stream.resync = true,
+        }
+    }
+
+    tracing::info!(
+        "📡 SSE stream opened for market {} (resuming after {:?})",
+        market_id,
+        last_event_id
+    );
+
+    let events = stream::unfold(stream, |mut stream| async move {
+        let event = stream.next().await?;
+        Some((Ok(event), stream))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// One client's view of the broadcast channel
+struct EventStream {
+    market_id: Uuid,
+    rx: BroadcastRx,
+    metrics: Arc<Metrics>,
+    /// Missed events still to replay
+    backlog: VecDeque<MarketEvent>,
+    /// Highest sequence number sent; anything up to it is a replay duplicate
+    seen: u64,
+    /// Whether the client must refetch before the next event
+    resync: bool,
+}
+
+impl EventStream {
+    /// The next event to send, or `None` once the channel closes
+    async fn next(&mut self) -> Option<Event> {
+        if std::mem::take(&mut self.resync) {
+            return Some(Event::default().event("resync").data("{}"));
+        }
+        if let Some(event) = self.backlog.pop_front() {
+            return Some(to_sse(&event));
+        }
+
+        loop {
+            match self.rx.recv().await {
+                Ok(event) if event.market_id == self.market_id && event.seq > self.seen => {
+                    return Some(to_sse(&event));
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("📡 SSE client fell behind, skipped {} events", skipped);
+                    self.metrics.broadcast_dropped_events.add(&["sse"], skipped);
+                    return Some(Event::default().event("resync").data("{}"));
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+fn to_sse(event: &MarketEvent) -> Event {
+    let data = serde_json::to_string(&event.message).unwrap_or_else(|e| {
+        tracing::error!("Failed to serialize message: {}", e);
+        "{}".to_string()
+    });
+    Event::default().id(event.seq.to_string()).data(data)
+}
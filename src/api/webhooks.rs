@@ -0,0 +1,169 @@
+/// Outbound webhooks, shared by the axum server and the Cloudflare worker
+///
+/// Each webhook registered on a market receives the market's events as the
+/// same JSON the WebSocket carries, POSTed with these headers:
+///
+/// - `X-Cazino-Event`: the message `type`
+/// - `X-Cazino-Market`: the market's ID
+/// - `X-Cazino-Attempt`: 1 for the first try, counting up on retries
+/// - `X-Cazino-Signature`: `sha256=` and the hex HMAC-SHA256 of the body,
+///   keyed with the webhook's secret
+///
+/// Anything but a 2xx response is retried with exponential backoff, and every
+/// attempt is written to the webhook's delivery log. Transports provide the
+/// HTTP client and decide how deliveries run alongside requests.
+use crate::api::protocol::WsMessage;
+use crate::db::r#trait::DatabaseMarker;
+use crate::db::Database;
+use crate::domain::models::{Webhook, WebhookDelivery};
+use crate::service::CazinoService;
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Attempts per event before giving up
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Sends deliveries over HTTP
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+pub trait WebhookClient: DatabaseMarker {
+    /// POST `body` with `headers`, returning the response status
+    async fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<u16, String>;
+
+    /// Wait before the next attempt
+    async fn sleep(&self, delay: Duration);
+}
+
+/// Hex HMAC-SHA256 of `body`, keyed with `secret`
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// How long to wait after a failed attempt: 1s, 4s, 16s, 64s
+pub fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_secs(4u64.pow(attempt.saturating_sub(1)))
+}
+
+/// Webhooks on the market that want this message
+///
+/// Only public messages (see `WsMessage::is_public`) are delivered. Lookup
+/// failures are logged and mean no deliveries.
+pub async fn webhooks_for<D: Database>(
+    service: &CazinoService<D>,
+    market_id: Uuid,
+    message: &WsMessage,
+) -> Vec<Webhook> {
+    match service
+        .webhooks_for_event(market_id, message.message_type(), message.is_public())
+        .await
+    {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            tracing::warn!("🪝 Couldn't load webhooks for market {}: {}", market_id, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Deliver a message to one webhook, retrying until it's accepted or
+/// `MAX_ATTEMPTS` run out; returns whether it was accepted
+///
+/// Messages that aren't public are never sent.
+pub async fn deliver<D: Database, C: WebhookClient>(
+    service: &CazinoService<D>,
+    client: &C,
+    webhook: &Webhook,
+    market_id: Uuid,
+    message: &WsMessage,
+) -> bool {
+    let event = message.message_type();
+    if !message.is_public() {
+        tracing::warn!(
+            "🪝 Refusing to deliver non-public {} to {}",
+            event,
+            webhook.url
+        );
+        return false;
+    }
+    let body = match serde_json::to_string(message) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("🪝 Couldn't serialize {} for webhook: {}", event, e);
+            return false;
+        }
+    };
+    let signature = format!("sha256={}", sign(&webhook.secret, &body));
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let headers = [
+            ("Content-Type", "application/json".to_string()),
+            ("X-Cazino-Event", event.to_string()),
+            ("X-Cazino-Market", market_id.to_string()),
+            ("X-Cazino-Attempt", attempt.to_string()),
+            ("X-Cazino-Signature", signature.clone()),
+        ];
+        let result = client.post(&webhook.url, &headers, &body).await;
+        let succeeded = matches!(result, Ok(200..=299));
+
+        let delivery = WebhookDelivery {
+            id: Uuid::new_v4(),
+            webhook_id: webhook.id,
+            event: event.to_string(),
+            attempt,
+            status_code: result.as_ref().ok().copied(),
+            error: result.err(),
+            succeeded,
+            created_at: Utc::now(),
+        };
+        if let Err(e) = service.record_webhook_delivery(delivery).await {
+            // Usually the webhook was deleted meanwhile; stop sending to it
+            tracing::warn!("🪝 Couldn't log delivery to {}: {}", webhook.url, e);
+            return succeeded;
+        }
+
+        if succeeded {
+            tracing::info!("🪝 Delivered {} to {}", event, webhook.url);
+            return true;
+        }
+        if attempt < MAX_ATTEMPTS {
+            client.sleep(retry_delay(attempt)).await;
+        }
+    }
+
+    tracing::warn!(
+        "🪝 Gave up delivering {} to {} after {} attempts",
+        event,
+        webhook.url,
+        MAX_ATTEMPTS
+    );
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_matches_hmac_sha256() {
+        // Wikipedia's HMAC-SHA256 example
+        assert_eq!(
+            sign("key", "The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off() {
+        let delays: Vec<u64> = (1..MAX_ATTEMPTS)
+            .map(|attempt| retry_delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 4, 16, 64]);
+    }
+}
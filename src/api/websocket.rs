@@ -1,31 +1,225 @@
 /// WebSocket handler for real-time updates
-use crate::api::models::WsMessage;
+use crate::api::handlers::EventSink;
+use crate::api::protocol::WsMessage;
+use crate::api::routes::AppState;
+use crate::db::Database;
+use crate::metrics::Metrics;
+use async_trait::async_trait;
 use axum::extract::ws::{Message, WebSocket};
 use futures::{sink::SinkExt, stream::StreamExt};
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
 use tracing::error;
+use uuid::Uuid;
+
+/// Events kept per market for clients resuming a stream
+const HISTORY_LEN: usize = 256;
+
+/// A broadcast message tagged with the market it belongs to
+#[derive(Debug, Clone)]
+pub struct MarketEvent {
+    pub market_id: Uuid,
+    /// Position in the market's event stream, counting from 1
+    pub seq: u64,
+    pub message: WsMessage,
+}
 
 /// Broadcast channel for market updates
-pub type BroadcastTx = broadcast::Sender<WsMessage>;
-pub type BroadcastRx = broadcast::Receiver<WsMessage>;
+///
+/// Events are numbered per market as they're sent, and the most recent ones
+/// are kept so an SSE client can pick up where it left off (see `api::sse`).
+/// Numbering restarts with the server.
+pub struct BroadcastTx {
+    tx: broadcast::Sender<MarketEvent>,
+    history: Mutex<HashMap<Uuid, MarketHistory>>,
+    metrics: Arc<Metrics>,
+}
+
+pub type BroadcastRx = broadcast::Receiver<MarketEvent>;
+
+#[derive(Default)]
+struct MarketHistory {
+    last_seq: u64,
+    recent: VecDeque<MarketEvent>,
+}
+
+/// What a client resuming after an event missed
+#[derive(Debug)]
+pub enum Missed {
+    /// Every event since, oldest first
+    Events(Vec<MarketEvent>),
+    /// More than the history holds, or a number from before a restart
+    Gap,
+}
+
+impl BroadcastTx {
+    /// Number a market event and send it to every subscriber
+    ///
+    /// Returns how many subscribers there were, or `None` when the channel
+    /// has closed.
+    pub fn send(&self, market_id: Uuid, message: WsMessage) -> Option<usize> {
+        let mut history = self.history.lock().unwrap();
+        let market = history.entry(market_id).or_default();
+        market.last_seq += 1;
+
+        let event = MarketEvent {
+            market_id,
+            seq: market.last_seq,
+            message,
+        };
+        if market.recent.len() == HISTORY_LEN {
+            market.recent.pop_front();
+        }
+        market.recent.push_back(event.clone());
+
+        // Sent under the lock so subscribers see events in sequence order
+        self.metrics.broadcast_events.inc();
+        self.tx.send(event).ok()
+    }
+
+    pub fn subscribe(&self) -> BroadcastRx {
+        self.tx.subscribe()
+    }
+
+    /// Where subscribers count connections and the events they miss
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    /// Events the slowest subscriber has yet to receive
+    pub fn queue_len(&self) -> usize {
+        self.tx.len()
+    }
+
+    /// Sequence number of the market's latest event, 0 before the first
+    pub fn last_seq(&self, market_id: Uuid) -> u64 {
+        self.history
+            .lock()
+            .unwrap()
+            .get(&market_id)
+            .map_or(0, |market| market.last_seq)
+    }
+
+    /// The market's events after `seq`
+    pub fn since(&self, market_id: Uuid, seq: u64) -> Missed {
+        let history = self.history.lock().unwrap();
+        let Some(market) = history.get(&market_id) else {
+            return if seq == 0 {
+                Missed::Events(Vec::new())
+            } else {
+                Missed::Gap
+            };
+        };
+
+        let oldest = market.recent.front().map_or(market.last_seq + 1, |e| e.seq);
+        if seq > market.last_seq || seq + 1 < oldest {
+            return Missed::Gap;
+        }
+        Missed::Events(
+            market
+                .recent
+                .iter()
+                .filter(|e| e.seq > seq)
+                .cloned()
+                .collect(),
+        )
+    }
+}
 
 /// Create a new broadcast channel for WebSocket messages
-pub fn create_broadcast_channel() -> (BroadcastTx, BroadcastRx) {
-    broadcast::channel(1000)
+pub fn create_broadcast_channel(metrics: Arc<Metrics>) -> (BroadcastTx, BroadcastRx) {
+    let (tx, rx) = broadcast::channel(1000);
+    let tx = BroadcastTx {
+        tx,
+        history: Mutex::new(HashMap::new()),
+        metrics,
+    };
+    (tx, rx)
 }
 
 /// Handle a WebSocket connection
-pub async fn handle_socket(socket: WebSocket, broadcast_tx: Arc<BroadcastTx>) {
+///
+/// Only events for the market named in the URL are streamed.
+pub async fn handle_socket(socket: WebSocket, broadcast_tx: Arc<BroadcastTx>, market_id: Uuid) {
     tracing::info!("🔌 New WebSocket connection established");
 
-    let (mut sender, mut receiver) = socket.split();
+    serve_socket(
+        socket,
+        &broadcast_tx,
+        market_id.to_string(),
+        player_events(market_id),
+    )
+    .await;
+
+    tracing::info!("🔌 WebSocket connection closed");
+}
+
+/// Handle a read-only spectator WebSocket connection
+///
+/// Only public events for the spectator's market are streamed; the handshake
+/// and pings are answered as usual.
+pub async fn handle_spectator_socket<D: Database + 'static>(
+    socket: WebSocket,
+    state: AppState<D>,
+    market_id: Uuid,
+) {
+    tracing::info!("📺 New spectator connection for market: {}", market_id);
+
+    serve_socket(
+        socket,
+        &state.broadcast_tx,
+        market_id.to_string(),
+        spectator_events(market_id),
+    )
+    .await;
+
+    tracing::info!("📺 Spectator connection closed");
+}
+
+/// Which broadcasts a player's socket gets: all of its market's
+fn player_events(market_id: Uuid) -> impl Fn(&MarketEvent) -> bool + Send + 'static {
+    move |event| event.market_id == market_id
+}
+
+/// Which broadcasts a spectator's socket gets: its market's public ones
+fn spectator_events(market_id: Uuid) -> impl Fn(&MarketEvent) -> bool + Send + 'static {
+    move |event| event.market_id == market_id && event.message.is_public()
+}
+
+/// Stream the broadcasts `forward` accepts to a socket, and answer its
+/// requests, until either side goes away
+async fn serve_socket(
+    socket: WebSocket,
+    broadcast_tx: &BroadcastTx,
+    market: String,
+    forward: impl Fn(&MarketEvent) -> bool + Send + 'static,
+) {
+    let metrics = broadcast_tx.metrics().clone();
+    let _connection = Connection::open(metrics.clone(), market);
     let mut broadcast_rx = broadcast_tx.subscribe();
+    let (mut sender, mut receiver) = socket.split();
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<WsMessage>();
 
-    // Spawn task to receive broadcast messages and send to client
+    // Spawn task to send broadcasts and replies to the client
     let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = broadcast_rx.recv().await {
-            let json = match serde_json::to_string(&msg) {
+        loop {
+            let message = tokio::select! {
+                event = broadcast_rx.recv() => match event {
+                    Ok(event) if forward(&event) => event.message,
+                    Ok(_) => continue,
+                    // The client reconnects and refetches
+                    Err(RecvError::Lagged(skipped)) => {
+                        metrics.broadcast_dropped_events.add(&["websocket"], skipped);
+                        break;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                Some(reply) = reply_rx.recv() => reply,
+            };
+
+            let json = match serde_json::to_string(&message) {
                 Ok(json) => json,
                 Err(e) => {
                     error!("Failed to serialize message: {}", e);
@@ -40,12 +234,15 @@ pub async fn handle_socket(socket: WebSocket, broadcast_tx: Arc<BroadcastTx>) {
     });
 
     // Spawn task to receive messages from client
-    let broadcast_tx_clone = Arc::clone(&broadcast_tx);
     let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
             match msg {
                 Message::Text(text) => {
-                    handle_client_message(&text, &broadcast_tx_clone).await;
+                    if let Some(reply) = handle_client_message(&text) {
+                        if reply_tx.send(reply).is_err() {
+                            break;
+                        }
+                    }
                 }
                 Message::Ping(_) => {
                     // Echo pong is handled automatically by Axum
@@ -69,44 +266,191 @@ pub async fn handle_socket(socket: WebSocket, broadcast_tx: Arc<BroadcastTx>) {
             send_task.abort();
         }
     }
+}
 
-    tracing::info!("🔌 WebSocket connection closed");
+/// Counts a socket in the market's connection gauge while it's open
+struct Connection {
+    metrics: Arc<Metrics>,
+    market: String,
 }
 
-async fn handle_client_message(text: &str, _broadcast_tx: &BroadcastTx) {
-    match serde_json::from_str::<WsMessage>(text) {
-        Ok(WsMessage::Ping) => {
-            tracing::debug!("Received ping from client");
-            // Could send pong back if needed
+impl Connection {
+    fn open(metrics: Arc<Metrics>, market: String) -> Self {
+        metrics.websocket_connections.inc(&[&market]);
+        Self { metrics, market }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.metrics.websocket_connections.dec(&[&self.market]);
+    }
+}
+
+/// Log a client message and work out the reply, if it gets one
+fn handle_client_message(text: &str) -> Option<WsMessage> {
+    let msg = match serde_json::from_str::<WsMessage>(text) {
+        Ok(msg) => msg,
+        Err(e) => {
+            tracing::warn!("Failed to parse client message: {} | Text: {}", e, text);
+            return None;
         }
-        Ok(WsMessage::Subscribe { market_id }) => {
-            tracing::info!("📺 Client subscribed to market: {}", market_id);
-            // In a production system, you'd track subscriptions per market
-            // For MVP, we broadcast all updates to all connected clients
+    };
+
+    match &msg {
+        WsMessage::Subscribe {
+            market_id,
+            protocol_version,
+        } => {
+            tracing::info!(
+                "📺 Client subscribed to market: {} (protocol v{})",
+                market_id,
+                protocol_version
+            );
         }
-        Ok(msg) => {
-            tracing::debug!("Received message from client: {:?}", msg);
+        WsMessage::Ping => {
+            tracing::debug!("Received ping from client");
         }
-        Err(e) => {
-            tracing::warn!("Failed to parse client message: {} | Text: {}", e, text);
+        _ => {
+            tracing::debug!("Ignoring message from client: {:?}", msg);
         }
     }
+
+    msg.reply()
 }
 
 /// Broadcast a message to all connected WebSocket clients
-pub fn broadcast(tx: &BroadcastTx, message: WsMessage) {
-    match tx.send(message) {
-        Ok(receiver_count) => {
+pub fn broadcast(tx: &BroadcastTx, market_id: Uuid, message: WsMessage) {
+    match tx.send(market_id, message) {
+        Some(receiver_count) => {
             if receiver_count > 0 {
                 tracing::debug!("📡 Broadcasted to {} WebSocket client(s)", receiver_count);
             }
             // If receiver_count is 0, no clients connected - this is normal during HTTP-only testing
             // Don't log anything to avoid spam
         }
-        Err(_) => {
+        None => {
             // Channel closed - this shouldn't happen in normal operation
             // Only happens if broadcast channel is dropped, which is a real error
             tracing::error!("Broadcast channel closed - this shouldn't happen!");
         }
     }
 }
+
+#[async_trait]
+impl EventSink for BroadcastTx {
+    async fn publish(&self, market_id: Uuid, message: WsMessage) {
+        broadcast(self, market_id, message);
+    }
+
+    fn last_event_seq(&self, market_id: Uuid) -> Option<u64> {
+        Some(self.last_seq(market_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seqs(missed: Missed) -> Vec<u64> {
+        match missed {
+            Missed::Events(events) => events.iter().map(|e| e.seq).collect(),
+            Missed::Gap => panic!("expected events, got a gap"),
+        }
+    }
+
+    #[test]
+    fn test_events_are_numbered_per_market() {
+        let (tx, mut rx) = create_broadcast_channel(Default::default());
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        broadcast(&tx, a, WsMessage::BetVoided { bet_id: a });
+        broadcast(&tx, b, WsMessage::BetVoided { bet_id: b });
+        broadcast(&tx, a, WsMessage::BetVoided { bet_id: a });
+
+        let received: Vec<(Uuid, u64)> = (0..3)
+            .map(|_| rx.try_recv().unwrap())
+            .map(|e| (e.market_id, e.seq))
+            .collect();
+        assert_eq!(received, vec![(a, 1), (b, 1), (a, 2)]);
+        assert_eq!(tx.last_seq(a), 2);
+        assert_eq!(tx.last_seq(b), 1);
+        assert_eq!(tx.last_seq(Uuid::new_v4()), 0);
+    }
+
+    #[test]
+    fn test_sockets_only_get_their_own_markets_events() {
+        let (market_id, other_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let event = |market_id, hide_from_subject| MarketEvent {
+            market_id,
+            seq: 1,
+            message: WsMessage::BetCreated {
+                bet_id: Uuid::new_v4(),
+                description: "Secret".to_string(),
+                hide_from_subject,
+            },
+        };
+
+        let player = player_events(market_id);
+        assert!(player(&event(market_id, false)));
+        assert!(player(&event(market_id, true)));
+        assert!(!player(&event(other_id, false)));
+        assert!(!player(&event(other_id, true)));
+
+        let spectator = spectator_events(market_id);
+        assert!(spectator(&event(market_id, false)));
+        assert!(!spectator(&event(market_id, true)));
+        assert!(!spectator(&event(other_id, false)));
+    }
+
+    #[test]
+    fn test_connections_are_counted_while_open() {
+        let metrics = Arc::new(Metrics::default());
+        let gauge = |metrics: &Metrics| {
+            let text = metrics.render();
+            text.lines()
+                .find(|l| l.starts_with("cazino_websocket_connections{"))
+                .map(str::to_string)
+        };
+
+        let first = Connection::open(metrics.clone(), "m".to_string());
+        let second = Connection::open(metrics.clone(), "m".to_string());
+        assert_eq!(
+            gauge(&metrics).as_deref(),
+            Some("cazino_websocket_connections{market_id=\"m\"} 2")
+        );
+
+        drop(first);
+        drop(second);
+        assert_eq!(gauge(&metrics), None);
+    }
+
+    #[test]
+    fn test_since_replays_missed_events() {
+        let (tx, _rx) = create_broadcast_channel(Default::default());
+        let market_id = Uuid::new_v4();
+        for _ in 0..5 {
+            broadcast(&tx, market_id, WsMessage::BetVoided { bet_id: market_id });
+        }
+
+        assert_eq!(seqs(tx.since(market_id, 3)), vec![4, 5]);
+        assert_eq!(seqs(tx.since(market_id, 5)), Vec::<u64>::new());
+        assert_eq!(seqs(tx.since(Uuid::new_v4(), 0)), Vec::<u64>::new());
+
+        // Numbers from before a restart can't be resumed
+        assert!(matches!(tx.since(market_id, 9), Missed::Gap));
+        assert!(matches!(tx.since(Uuid::new_v4(), 2), Missed::Gap));
+    }
+
+    #[test]
+    fn test_since_reports_gaps_beyond_history() {
+        let (tx, _rx) = create_broadcast_channel(Default::default());
+        let market_id = Uuid::new_v4();
+        for _ in 0..HISTORY_LEN + 10 {
+            broadcast(&tx, market_id, WsMessage::BetVoided { bet_id: market_id });
+        }
+
+        assert!(matches!(tx.since(market_id, 5), Missed::Gap));
+        assert_eq!(seqs(tx.since(market_id, 10)).len(), HISTORY_LEN);
+    }
+}
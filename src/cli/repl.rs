@@ -1,22 +1,24 @@
 /// Interactive CLI for testing Cazino locally
-use crate::db::SqliteDatabase;
+use crate::db::Database;
 use crate::domain::models::Side;
 use crate::service::{CazinoService, CreateMarketParams};
 use std::io::{self, Write};
 use uuid::Uuid;
 
-pub struct Repl {
-    service: CazinoService<SqliteDatabase>,
+pub struct Repl<D: Database> {
+    service: CazinoService<D>,
     current_market_id: Option<Uuid>,
     current_user_id: Option<Uuid>,
+    current_season_id: Option<Uuid>,
 }
 
-impl Repl {
-    pub fn new(service: CazinoService<SqliteDatabase>) -> Self {
+impl<D: Database> Repl<D> {
+    pub fn new(service: CazinoService<D>) -> Self {
         Self {
             service,
             current_market_id: None,
             current_user_id: None,
+            current_season_id: None,
         }
     }
 
@@ -53,6 +55,13 @@ impl Repl {
                 "resolve" => self.resolve_bet(&parts[1..]).await,
                 "leaderboard" => self.show_leaderboard().await,
                 "reveal" => self.show_reveal(&parts[1..]).await,
+                "ceremony" => self.start_ceremony().await,
+                "next" => self.reveal_next().await,
+                "leave" => self.leave_market().await,
+                "kick" => self.remove_user(&parts[1..], false).await,
+                "ban" => self.remove_user(&parts[1..], true).await,
+                "template" => self.template(&parts[1..]).await,
+                "season" => self.season(&parts[1..]).await,
                 "open" => self.open_market().await,
                 "close" => self.close_market().await,
                 "status" => self.show_status().await,
@@ -76,10 +85,13 @@ Market Management:
   open                               Open market for betting
   close                              Close market (end betting)
   status                             Show current market status
+  leave                              Leave the current market
+  kick <user_name>                   Remove a player (they may rejoin)
+  ban <user_name>                    Remove a player and block their device
 
 Betting:
-  bet <subject_name> <description> <odds> <amount>
-                                     Create a bet about someone
+  bet <name[,name...]|everyone> <description> <odds> <amount>
+                                     Create a bet about someone (or a group)
   pending                            List bets awaiting approval
   approve <bet_index>                Approve a pending bet
   bets                               List all active bets
@@ -91,6 +103,18 @@ Resolution:
   resolve <bet_index> <yes|no>       Resolve a bet
   leaderboard                        Show user rankings
   reveal <user_name>                 Show bets about a user
+  ceremony                           Start (or resume) the reveal ceremony
+  next                               Reveal the next hidden bet
+
+Templates:
+  template save [name]               Save the current market as a template
+  template new <template_id> <name>  Create a market from a template
+  template clone <name>              Clone the current market with its players
+
+Seasons:
+  season new <name> [carry]          Start a season (carry: keep balances between markets)
+  season add                         Add the current market to the season
+  season                             Show the season leaderboard
 
 Other:
   users                              List all users in market
@@ -195,9 +219,85 @@ Examples:
         }
     }
 
+    async fn leave_market(&mut self) {
+        let user_id = match self.current_user_id {
+            Some(id) => id,
+            None => {
+                println!("❌ No user selected");
+                return;
+            }
+        };
+
+        match self.service.leave_market(user_id).await {
+            Ok(change) => {
+                println!("👋 You left the market");
+                println!("   Balance: {} coins", change.user.balance);
+                if !change.voided_bet_ids.is_empty() {
+                    println!("   {} bet(s) about you voided", change.voided_bet_ids.len());
+                }
+                self.current_market_id = None;
+                self.current_user_id = None;
+            }
+            Err(e) => println!("❌ Error: {}", e),
+        }
+    }
+
+    async fn remove_user(&self, args: &[&str], ban: bool) {
+        let command = if ban { "ban" } else { "kick" };
+        if args.is_empty() {
+            println!("Usage: {} <user_name>", command);
+            return;
+        }
+
+        let (market_id, admin_id) = match (self.current_market_id, self.current_user_id) {
+            (Some(m), Some(u)) => (m, u),
+            _ => {
+                println!("❌ No market/user selected");
+                return;
+            }
+        };
+
+        let name = args[0];
+        let users = self.service.get_users(market_id).await.unwrap();
+        let user_id = match users
+            .iter()
+            .find(|u| u.display_name.to_lowercase() == name.to_lowercase())
+        {
+            Some(u) => u.id,
+            None => {
+                println!("❌ User '{}' not found", name);
+                return;
+            }
+        };
+
+        let result = if ban {
+            self.service.ban_user(market_id, admin_id, user_id).await
+        } else {
+            self.service.kick_user(market_id, admin_id, user_id).await
+        };
+
+        match result {
+            Ok(change) => {
+                println!(
+                    "{} {} {}",
+                    if ban { "🚫" } else { "🥾" },
+                    change.user.display_name,
+                    if ban { "was banned" } else { "was kicked" }
+                );
+                if !change.voided_bet_ids.is_empty() {
+                    println!(
+                        "   {} bet(s) about them voided",
+                        change.voided_bet_ids.len()
+                    );
+                }
+            }
+            Err(e) => println!("❌ Error: {}", e),
+        }
+    }
+
     async fn create_bet(&mut self, args: &[&str]) {
         if args.len() < 4 {
-            println!("Usage: bet <subject_name> <description> <odds> <amount>");
+            println!("Usage: bet <name[,name...]|everyone> <description> <odds> <amount>");
             return;
         }
 
@@ -209,31 +309,38 @@ Examples:
             }
         };
 
-        let subject_name = args[0];
+        let subject_names = args[0];
         let description = args[1];
         let odds = args[2];
         let amount = args[3].parse::<i64>().unwrap_or(0);
+        let about_everyone = subject_names.eq_ignore_ascii_case("everyone");
 
-        // Find subject user by name
+        // Find subject users by name
         let users = self.service.get_users(market_id).await.unwrap();
-        let subject = users
-            .iter()
-            .find(|u| u.display_name.to_lowercase() == subject_name.to_lowercase());
-
-        let subject_id = match subject {
-            Some(u) => u.id,
-            None => {
-                println!("❌ User '{}' not found", subject_name);
-                return;
+        let mut subject_ids = Vec::new();
+        if !about_everyone {
+            for subject_name in subject_names.split(',') {
+                let subject = users
+                    .iter()
+                    .find(|u| u.display_name.to_lowercase() == subject_name.to_lowercase());
+
+                match subject {
+                    Some(u) => subject_ids.push(u.id),
+                    None => {
+                        println!("❌ User '{}' not found", subject_name);
+                        return;
+                    }
+                }
             }
-        };
+        }
 
         match self
             .service
             .create_bet(
                 market_id,
                 user_id,
-                subject_id,
+                subject_ids,
+                about_everyone,
                 description.to_string(),
                 odds.to_string(),
                 amount,
@@ -498,7 +605,7 @@ Examples:
 
         match self.service.get_users(market_id).await {
             Ok(mut users) => {
-                users.sort_by(|a, b| b.balance.cmp(&a.balance));
+                users.sort_by_key(|u| std::cmp::Reverse(u.balance));
 
                 println!("\n🏆 Leaderboard 🏆");
                 for (i, user) in users.iter().enumerate() {
@@ -517,6 +624,174 @@ Examples:
         }
     }
 
+    async fn template(&mut self, args: &[&str]) {
+        let result = match args.first().copied() {
+            Some("save") => {
+                let (market_id, user_id) = match (self.current_market_id, self.current_user_id) {
+                    (Some(m), Some(u)) => (m, u),
+                    _ => {
+                        println!("❌ No market selected");
+                        return;
+                    }
+                };
+
+                match self
+                    .service
+                    .save_template(market_id, user_id, args.get(1).map(|s| s.to_string()))
+                    .await
+                {
+                    Ok(template) => {
+                        println!(
+                            "✅ Template saved: {} ({} bets)",
+                            template.name,
+                            template.bets.len()
+                        );
+                        println!("   Template ID: {}", template.id);
+                    }
+                    Err(e) => println!("❌ Error: {}", e),
+                }
+                return;
+            }
+            Some("new") => {
+                if args.len() < 3 {
+                    println!("Usage: template new <template_id> <name>");
+                    return;
+                }
+                let template_id = match Uuid::parse_str(args[1]) {
+                    Ok(id) => id,
+                    Err(_) => {
+                        println!("❌ Invalid template ID");
+                        return;
+                    }
+                };
+
+                self.service
+                    .create_market_from_template(
+                        template_id,
+                        Some(args[2].to_string()),
+                        "cli-admin".to_string(),
+                        "Admin".to_string(),
+                        "👑".to_string(),
+                    )
+                    .await
+            }
+            Some("clone") => {
+                if args.len() < 2 {
+                    println!("Usage: template clone <name>");
+                    return;
+                }
+                let (market_id, user_id) = match (self.current_market_id, self.current_user_id) {
+                    (Some(m), Some(u)) => (m, u),
+                    _ => {
+                        println!("❌ No market selected");
+                        return;
+                    }
+                };
+
+                self.service
+                    .clone_market(market_id, user_id, args[1].to_string())
+                    .await
+            }
+            _ => {
+                println!("Usage: template <save|new|clone> ...");
+                return;
+            }
+        };
+
+        match result {
+            Ok((market, user, bets)) => {
+                println!("✅ Market created: {}", market.name);
+                println!("   Invite code: {}", market.invite_code);
+                println!("   {} bets waiting for approval", bets.len());
+                self.current_market_id = Some(market.id);
+                self.current_user_id = Some(user.id);
+            }
+            Err(e) => println!("❌ Error: {}", e),
+        }
+    }
+
+    async fn season(&mut self, args: &[&str]) {
+        match args.first().copied() {
+            Some("new") => {
+                if args.len() < 2 {
+                    println!("Usage: season new <name> [carry]");
+                    return;
+                }
+                let carry_over = args.get(2) == Some(&"carry");
+
+                match self
+                    .service
+                    .create_season(args[1].to_string(), carry_over)
+                    .await
+                {
+                    Ok(season) => {
+                        println!("✅ Season created: {}", season.name);
+                        if season.carry_over_balances {
+                            println!("   Balances carry over between markets");
+                        }
+                        self.current_season_id = Some(season.id);
+                    }
+                    Err(e) => println!("❌ Error: {}", e),
+                }
+            }
+            Some("add") => {
+                let (season_id, market_id, user_id) = match (
+                    self.current_season_id,
+                    self.current_market_id,
+                    self.current_user_id,
+                ) {
+                    (Some(s), Some(m), Some(u)) => (s, m, u),
+                    _ => {
+                        println!("❌ Start a season and select a market first");
+                        return;
+                    }
+                };
+
+                match self
+                    .service
+                    .add_market_to_season(season_id, market_id, user_id)
+                    .await
+                {
+                    Ok((season, markets)) => {
+                        println!(
+                            "✅ Added to {} ({} markets so far)",
+                            season.name,
+                            markets.len()
+                        );
+                    }
+                    Err(e) => println!("❌ Error: {}", e),
+                }
+            }
+            Some(other) => println!("Unknown season command: {}", other),
+            None => {
+                let season_id = match self.current_season_id {
+                    Some(id) => id,
+                    None => {
+                        println!("❌ No season selected. Use 'season new <name>' first.");
+                        return;
+                    }
+                };
+
+                match self.service.get_season_leaderboard(season_id).await {
+                    Ok((season, standings)) => {
+                        println!("\n📅 {} 📅", season.name);
+                        for standing in standings {
+                            println!(
+                                "  {}. {} {} - {:+} profit over {} market(s)",
+                                standing.rank,
+                                standing.avatar,
+                                standing.display_name,
+                                standing.profit,
+                                standing.markets_played
+                            );
+                        }
+                    }
+                    Err(e) => println!("❌ Error: {}", e),
+                }
+            }
+        }
+    }
+
     async fn show_reveal(&self, args: &[&str]) {
         if args.is_empty() {
             println!("Usage: reveal <user_name>");
@@ -545,7 +820,7 @@ Examples:
             }
         };
 
-        match self.service.get_bets_about_user(user_id).await {
+        match self.service.get_reveal(user_id).await {
             Ok(bets) => {
                 println!("\n🔓 Bets about {}:", name);
                 for bet in bets {
@@ -556,6 +831,65 @@ Examples:
         }
     }
 
+    async fn start_ceremony(&self) {
+        let (market_id, user_id) = match (self.current_market_id, self.current_user_id) {
+            (Some(m), Some(u)) => (m, u),
+            _ => {
+                println!("❌ No market/user selected");
+                return;
+            }
+        };
+
+        match self.service.start_reveal_ceremony(market_id, user_id).await {
+            Ok(ceremony) => {
+                println!(
+                    "🎭 Reveal ceremony: {}/{} bets revealed",
+                    ceremony.revealed_count,
+                    ceremony.bet_ids.len()
+                );
+                if !ceremony.is_complete() {
+                    println!("   Type 'next' to reveal the next bet");
+                }
+            }
+            Err(e) => println!("❌ Error: {}", e),
+        }
+    }
+
+    async fn reveal_next(&self) {
+        let (market_id, user_id) = match (self.current_market_id, self.current_user_id) {
+            (Some(m), Some(u)) => (m, u),
+            _ => {
+                println!("❌ No market/user selected");
+                return;
+            }
+        };
+
+        match self.service.reveal_next_bet(market_id, user_id).await {
+            Ok((ceremony, reveal)) => {
+                println!(
+                    "\n🎭 Reveal {}/{}: {}",
+                    ceremony.revealed_count,
+                    ceremony.bet_ids.len(),
+                    reveal.bet.description.unwrap_or_default()
+                );
+                match reveal.outcome {
+                    Some(side) => println!("   Outcome: {:?}", side),
+                    None => println!("   Outcome: unresolved"),
+                }
+                for winner in reveal.top_winners {
+                    println!(
+                        "   {} {} won {} coins",
+                        winner.avatar, winner.display_name, winner.payout
+                    );
+                }
+                if ceremony.is_complete() {
+                    println!("\n🎉 That's every hidden bet!");
+                }
+            }
+            Err(e) => println!("❌ Error: {}", e),
+        }
+    }
+
     async fn open_market(&mut self) {
         let (market_id, user_id) = match (self.current_market_id, self.current_user_id) {
             (Some(m), Some(u)) => (m, u),
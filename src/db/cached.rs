@@ -0,0 +1,515 @@
+/// Write-through cache of a market's hot rows over another backend
+///
+/// Users (balances) and bets (pools) are read on every wager, so single-row
+/// lookups are served from memory once loaded. Writes go to the inner backend
+/// first and then update or drop the cached copy, which keeps the cache exact
+/// as long as every write goes through this value. The Cloudflare worker's
+/// `CazinoRoom` uses one scoped to the market it serializes (`for_market`):
+/// rows of other markets are read through, since their own rooms and the
+/// worker write them, and anything that changes the market's rows elsewhere
+/// has to call `clear`.
+use crate::db::query::{BetQuery, DeviceMarketQuery, Page, UserQuery, WagerQuery};
+use crate::db::r#trait::{Database, DbResult};
+use crate::domain::archive::MarketArchive;
+use crate::domain::models::{
+    AuditEntry, Bet, BetStatus, BetView, LinkCode, Market, MarketContents, MarketStatus,
+    MarketTemplate, MembershipStatus, Player, Removal, RevealCeremony, Season, Spectator, User,
+    Wager, Webhook, WebhookDelivery,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+pub struct CachedDatabase<D: Database> {
+    inner: D,
+    cache: Mutex<Cache>,
+    market_id: Option<Uuid>, // Only this market's rows are cached, if set
+}
+
+#[derive(Default)]
+struct Cache {
+    users: HashMap<Uuid, User>,
+    bets: HashMap<Uuid, Bet>,
+}
+
+impl<D: Database> CachedDatabase<D> {
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(Cache::default()),
+            market_id: None,
+        }
+    }
+
+    /// A cache that only keeps rows belonging to `market_id`
+    pub fn for_market(inner: D, market_id: Uuid) -> Self {
+        Self {
+            market_id: Some(market_id),
+            ..Self::new(inner)
+        }
+    }
+
+    /// Forget everything cached, e.g. after rows changed behind our back
+    pub fn clear(&self) {
+        *self.cache() = Cache::default();
+    }
+
+    fn cache(&self) -> MutexGuard<'_, Cache> {
+        // Every update is a single insert, removal or field write, so a
+        // poisoned lock never guards a half-done change
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn caches(&self, market_id: Uuid) -> bool {
+        self.market_id.is_none_or(|id| id == market_id)
+    }
+
+    fn forget_user(&self, user_id: Uuid) {
+        self.cache().users.remove(&user_id);
+    }
+
+    fn forget_bet(&self, bet_id: Uuid) {
+        self.cache().bets.remove(&bet_id);
+    }
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl<D: Database> Database for CachedDatabase<D> {
+    // ===== Market Operations =====
+
+    async fn create_market(&self, market: Market) -> DbResult<Market> {
+        self.inner.create_market(market).await
+    }
+
+    async fn get_market(&self, id: Uuid) -> DbResult<Market> {
+        self.inner.get_market(id).await
+    }
+
+    async fn get_market_by_invite_code(&self, code: &str) -> DbResult<Market> {
+        self.inner.get_market_by_invite_code(code).await
+    }
+
+    async fn update_market_status(&self, id: Uuid, status: MarketStatus) -> DbResult<()> {
+        self.inner.update_market_status(id, status).await
+    }
+
+    async fn delete_market(&self, id: Uuid) -> DbResult<()> {
+        self.inner.delete_market(id).await?;
+        // Users and bets go with the market
+        let mut cache = self.cache();
+        cache.users.retain(|_, user| user.market_id != id);
+        cache.bets.retain(|_, bet| bet.market_id != id);
+        Ok(())
+    }
+
+    async fn import_archive(&self, archive: &MarketArchive) -> DbResult<()> {
+        // Every row is new, so there is nothing cached to invalidate
+        self.inner.import_archive(archive).await
+    }
+
+    // ===== User Operations =====
+
+    async fn create_user(&self, user: User) -> DbResult<User> {
+        self.inner.create_user(user).await
+    }
+
+    async fn get_user(&self, id: Uuid) -> DbResult<User> {
+        if let Some(user) = self.cache().users.get(&id) {
+            return Ok(user.clone());
+        }
+
+        let user = self.inner.get_user(id).await?;
+        if self.caches(user.market_id) {
+            self.cache().users.insert(id, user.clone());
+        }
+        Ok(user)
+    }
+
+    async fn get_user_by_device_id(&self, market_id: Uuid, device_id: &str) -> DbResult<User> {
+        self.inner.get_user_by_device_id(market_id, device_id).await
+    }
+
+    async fn get_users_in_market(&self, market_id: Uuid) -> DbResult<Vec<User>> {
+        self.inner.get_users_in_market(market_id).await
+    }
+
+    async fn list_users_in_market(
+        &self,
+        market_id: Uuid,
+        query: &UserQuery,
+    ) -> DbResult<Page<User>> {
+        self.inner.list_users_in_market(market_id, query).await
+    }
+
+    async fn update_user_balance(&self, user_id: Uuid, new_balance: i64) -> DbResult<()> {
+        self.inner.update_user_balance(user_id, new_balance).await?;
+        if let Some(user) = self.cache().users.get_mut(&user_id) {
+            user.balance = new_balance;
+        }
+        Ok(())
+    }
+
+    async fn update_user_status(&self, user_id: Uuid, status: MembershipStatus) -> DbResult<()> {
+        self.inner.update_user_status(user_id, status).await?;
+        self.forget_user(user_id);
+        Ok(())
+    }
+
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        display_name: &str,
+        avatar: &str,
+    ) -> DbResult<()> {
+        self.inner
+            .update_user_profile(user_id, display_name, avatar)
+            .await?;
+        self.forget_user(user_id);
+        Ok(())
+    }
+
+    async fn update_user_device(&self, user_id: Uuid, device_id: &str) -> DbResult<()> {
+        self.inner.update_user_device(user_id, device_id).await?;
+        self.forget_user(user_id);
+        Ok(())
+    }
+
+    async fn get_markets_by_device_id(&self, device_id: &str) -> DbResult<Vec<(Market, User)>> {
+        self.inner.get_markets_by_device_id(device_id).await
+    }
+
+    async fn list_markets_by_device_id(
+        &self,
+        device_id: &str,
+        query: &DeviceMarketQuery,
+    ) -> DbResult<Page<(Market, User)>> {
+        self.inner.list_markets_by_device_id(device_id, query).await
+    }
+
+    // ===== Player Operations =====
+
+    async fn create_player(&self, player: Player) -> DbResult<Player> {
+        self.inner.create_player(player).await
+    }
+
+    async fn get_player(&self, id: Uuid) -> DbResult<Player> {
+        self.inner.get_player(id).await
+    }
+
+    async fn update_user_player(&self, user_id: Uuid, player_id: Uuid) -> DbResult<()> {
+        self.inner.update_user_player(user_id, player_id).await?;
+        self.forget_user(user_id);
+        Ok(())
+    }
+
+    async fn get_users_by_player(&self, player_id: Uuid) -> DbResult<Vec<User>> {
+        self.inner.get_users_by_player(player_id).await
+    }
+
+    // ===== Template Operations =====
+
+    async fn create_template(&self, template: MarketTemplate) -> DbResult<MarketTemplate> {
+        self.inner.create_template(template).await
+    }
+
+    async fn get_template(&self, id: Uuid) -> DbResult<MarketTemplate> {
+        self.inner.get_template(id).await
+    }
+
+    // ===== Season Operations =====
+
+    async fn create_season(&self, season: Season) -> DbResult<Season> {
+        self.inner.create_season(season).await
+    }
+
+    async fn get_season(&self, id: Uuid) -> DbResult<Season> {
+        self.inner.get_season(id).await
+    }
+
+    async fn add_season_market(
+        &self,
+        season_id: Uuid,
+        market_id: Uuid,
+        position: usize,
+    ) -> DbResult<()> {
+        self.inner
+            .add_season_market(season_id, market_id, position)
+            .await
+    }
+
+    async fn get_season_markets(&self, season_id: Uuid) -> DbResult<Vec<Market>> {
+        self.inner.get_season_markets(season_id).await
+    }
+
+    async fn get_season_for_market(&self, market_id: Uuid) -> DbResult<Season> {
+        self.inner.get_season_for_market(market_id).await
+    }
+
+    async fn create_carry_over(&self, market_id: Uuid, user_id: Uuid, amount: i64) -> DbResult<()> {
+        self.inner
+            .create_carry_over(market_id, user_id, amount)
+            .await
+    }
+
+    async fn get_carry_overs(&self, season_id: Uuid) -> DbResult<Vec<(Uuid, i64)>> {
+        self.inner.get_carry_overs(season_id).await
+    }
+
+    // ===== Identity Operations =====
+
+    async fn create_link_code(&self, link: LinkCode) -> DbResult<LinkCode> {
+        self.inner.create_link_code(link).await
+    }
+
+    async fn get_link_code(&self, code: &str) -> DbResult<LinkCode> {
+        self.inner.get_link_code(code).await
+    }
+
+    async fn redeem_link_code(
+        &self,
+        code: &str,
+        device_id: &str,
+        redeemed_at: DateTime<Utc>,
+    ) -> DbResult<bool> {
+        self.inner
+            .redeem_link_code(code, device_id, redeemed_at)
+            .await
+    }
+
+    async fn create_audit_entry(&self, entry: AuditEntry) -> DbResult<AuditEntry> {
+        self.inner.create_audit_entry(entry).await
+    }
+
+    async fn count_audit_entries(
+        &self,
+        device_id: Option<&str>,
+        action: &str,
+        since: DateTime<Utc>,
+    ) -> DbResult<i64> {
+        self.inner
+            .count_audit_entries(device_id, action, since)
+            .await
+    }
+
+    // ===== Spectator Operations =====
+
+    async fn create_spectator(&self, spectator: Spectator) -> DbResult<Spectator> {
+        self.inner.create_spectator(spectator).await
+    }
+
+    async fn get_spectator_by_token(&self, token: &str) -> DbResult<Spectator> {
+        self.inner.get_spectator_by_token(token).await
+    }
+
+    async fn get_spectators_in_market(&self, market_id: Uuid) -> DbResult<Vec<Spectator>> {
+        self.inner.get_spectators_in_market(market_id).await
+    }
+
+    // ===== Webhook Operations =====
+
+    async fn create_webhook(&self, webhook: Webhook) -> DbResult<Webhook> {
+        self.inner.create_webhook(webhook).await
+    }
+
+    async fn get_webhook(&self, id: Uuid) -> DbResult<Webhook> {
+        self.inner.get_webhook(id).await
+    }
+
+    async fn get_webhooks_in_market(&self, market_id: Uuid) -> DbResult<Vec<Webhook>> {
+        self.inner.get_webhooks_in_market(market_id).await
+    }
+
+    async fn delete_webhook(&self, id: Uuid) -> DbResult<()> {
+        self.inner.delete_webhook(id).await
+    }
+
+    async fn create_webhook_delivery(
+        &self,
+        delivery: WebhookDelivery,
+    ) -> DbResult<WebhookDelivery> {
+        self.inner.create_webhook_delivery(delivery).await
+    }
+
+    async fn get_webhook_deliveries(&self, webhook_id: Uuid) -> DbResult<Vec<WebhookDelivery>> {
+        self.inner.get_webhook_deliveries(webhook_id).await
+    }
+
+    // ===== Bet Operations =====
+
+    async fn create_bet(&self, bet: Bet) -> DbResult<Bet> {
+        self.inner.create_bet(bet).await
+    }
+
+    async fn get_bet(&self, id: Uuid) -> DbResult<Bet> {
+        if let Some(bet) = self.cache().bets.get(&id) {
+            return Ok(bet.clone());
+        }
+
+        let bet = self.inner.get_bet(id).await?;
+        if self.caches(bet.market_id) {
+            self.cache().bets.insert(id, bet.clone());
+        }
+        Ok(bet)
+    }
+
+    async fn get_bets_in_market(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        self.inner.get_bets_in_market(market_id).await
+    }
+
+    async fn list_bets_in_market(&self, market_id: Uuid, query: &BetQuery) -> DbResult<Page<Bet>> {
+        self.inner.list_bets_in_market(market_id, query).await
+    }
+
+    async fn get_bets_for_user(
+        &self,
+        market_id: Uuid,
+        viewing_user_id: Uuid,
+    ) -> DbResult<Vec<BetView>> {
+        self.inner
+            .get_bets_for_user(market_id, viewing_user_id)
+            .await
+    }
+
+    async fn get_pending_bets(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        self.inner.get_pending_bets(market_id).await
+    }
+
+    async fn get_market_contents(&self, market_id: Uuid) -> DbResult<MarketContents> {
+        self.inner.get_market_contents(market_id).await
+    }
+
+    async fn update_bet_status(&self, bet_id: Uuid, status: BetStatus) -> DbResult<()> {
+        self.inner.update_bet_status(bet_id, status).await?;
+        // The backend also stamps `resolved_at`, so reload rather than patch
+        self.forget_bet(bet_id);
+        Ok(())
+    }
+
+    async fn update_bet_pools(&self, bet_id: Uuid, yes_pool: i64, no_pool: i64) -> DbResult<()> {
+        self.inner
+            .update_bet_pools(bet_id, yes_pool, no_pool)
+            .await?;
+        if let Some(bet) = self.cache().bets.get_mut(&bet_id) {
+            bet.yes_pool = yes_pool;
+            bet.no_pool = no_pool;
+        }
+        Ok(())
+    }
+
+    // ===== Wager Operations =====
+
+    async fn create_wager(&self, wager: Wager) -> DbResult<Wager> {
+        self.inner.create_wager(wager).await
+    }
+
+    async fn apply_wager(&self, wager: Wager) -> DbResult<Wager> {
+        // Let the backend apply it atomically, then mirror what it wrote
+        let wager = self.inner.apply_wager(wager).await?;
+
+        let mut cache = self.cache();
+        if let Some(bet) = cache.bets.get_mut(&wager.bet_id) {
+            bet.yes_pool = wager.yes_pool_after;
+            bet.no_pool = wager.no_pool_after;
+        }
+        if let Some(user) = cache.users.get_mut(&wager.user_id) {
+            user.balance -= wager.amount;
+        }
+
+        Ok(wager)
+    }
+
+    async fn get_wagers_for_bet(&self, bet_id: Uuid) -> DbResult<Vec<Wager>> {
+        self.inner.get_wagers_for_bet(bet_id).await
+    }
+
+    async fn list_wagers_for_bet(&self, bet_id: Uuid, query: &WagerQuery) -> DbResult<Page<Wager>> {
+        self.inner.list_wagers_for_bet(bet_id, query).await
+    }
+
+    async fn apply_removal(&self, removal: &Removal) -> DbResult<()> {
+        self.inner.apply_removal(removal).await?;
+
+        // Balances, pools and statuses all moved; reload them
+        self.forget_user(removal.user_id);
+        for wager in &removal.refunds {
+            self.forget_user(wager.user_id);
+            self.forget_bet(wager.bet_id);
+        }
+        for bet_id in &removal.voided_bet_ids {
+            self.forget_bet(*bet_id);
+        }
+        Ok(())
+    }
+
+    async fn get_wagers_for_user(&self, user_id: Uuid) -> DbResult<Vec<Wager>> {
+        self.inner.get_wagers_for_user(user_id).await
+    }
+
+    // ===== Reveal Operations =====
+
+    async fn get_bets_about_user(&self, user_id: Uuid) -> DbResult<Vec<Bet>> {
+        self.inner.get_bets_about_user(user_id).await
+    }
+
+    async fn create_reveal_ceremony(&self, ceremony: RevealCeremony) -> DbResult<RevealCeremony> {
+        self.inner.create_reveal_ceremony(ceremony).await
+    }
+
+    async fn get_reveal_ceremony(&self, market_id: Uuid) -> DbResult<RevealCeremony> {
+        self.inner.get_reveal_ceremony(market_id).await
+    }
+
+    async fn update_reveal_progress(
+        &self,
+        market_id: Uuid,
+        revealed_count: usize,
+        completed_at: Option<DateTime<Utc>>,
+    ) -> DbResult<()> {
+        self.inner
+            .update_reveal_progress(market_id, revealed_count, completed_at)
+            .await
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryDatabase;
+    use crate::service::{CazinoService, CreateMarketParams};
+    use std::sync::Arc;
+
+    fn params(name: &str) -> CreateMarketParams {
+        CreateMarketParams {
+            name: name.to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_market_cache_reads_other_markets_through() {
+        let db = Arc::new(InMemoryDatabase::new());
+        let service = CazinoService::new(db.clone());
+        let (ours, our_admin) = service.create_market(params("Ours")).await.unwrap();
+        let (_, their_admin) = service.create_market(params("Theirs")).await.unwrap();
+        drop(service);
+
+        let Ok(db) = Arc::try_unwrap(db) else {
+            panic!("the service still holds the database");
+        };
+        let cached = CachedDatabase::for_market(db, ours.id);
+        cached.get_user(our_admin.id).await.unwrap();
+        cached.get_user(their_admin.id).await.unwrap();
+
+        let cache = cached.cache();
+        assert!(cache.users.contains_key(&our_admin.id));
+        assert!(!cache.users.contains_key(&their_admin.id));
+    }
+}
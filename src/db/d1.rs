@@ -1,140 +1,2202 @@
-/// D1 (Cloudflare) implementation of the Database trait
-#[cfg(feature = "wasm")]
-use crate::db::r#trait::{Database, DbError, DbResult};
-#[cfg(feature = "wasm")]
-use crate::domain::models::{Bet, BetStatus, BetView, Market, MarketStatus, Side, User, Wager};
-#[cfg(feature = "wasm")]
+use crate::db::migrations::{self, Migration, MigrationTarget, Statement};
+use crate::db::query::{
+    BetQuery, BetSort, DeviceMarketQuery, DeviceMarketSort, Page, Select, SqlValue, UserQuery,
+    UserSort, WagerQuery, WagerSort,
+};
+use crate::db::{Database, DbError, DbResult};
+use crate::domain::archive::MarketArchive;
+use crate::domain::models::{
+    AuditEntry, Bet, BetStatus, BetView, LinkCode, Market, MarketContents, MarketStatus,
+    MarketTemplate, MembershipStatus, Player, Removal, RevealCeremony, Season, Side, Spectator,
+    User, Wager, Webhook, WebhookDelivery,
+};
+/// D1 (Cloudflare) implementation of the Database trait for Workers
 use async_trait::async_trait;
-#[cfg(feature = "wasm")]
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
 use uuid::Uuid;
+use wasm_bindgen::JsValue;
+use worker::*;
 
-#[cfg(feature = "wasm")]
 pub struct D1Database {
-    // This will hold the worker::D1Database binding
-    // We use a generic to avoid depending on worker crate in the main library
-    db: Box<dyn std::any::Any + Send + Sync>,
+    db: worker::D1Database,
 }
 
-#[cfg(feature = "wasm")]
 impl D1Database {
-    pub fn new(db: impl std::any::Any + Send + Sync + 'static) -> Self {
-        Self { db: Box::new(db) }
+    pub fn new(db: worker::D1Database) -> Self {
+        Self { db }
     }
 
-    // Helper to get the actual D1 binding
-    // This will be implemented in the worker crate
+    /// Apply pending migrations, returning the ones that ran
+    ///
+    /// See `migrations::run_migrations`, shared with the integration tests.
+    pub async fn run_migrations(&self) -> Result<Vec<&'static Migration>> {
+        migrations::run_migrations(self).await
+    }
+}
+
+#[async_trait(?Send)]
+impl MigrationTarget for D1Database {
+    type Error = Error;
+
+    async fn texts(&self, sql: &str) -> Result<Vec<String>> {
+        let rows = self.db.prepare(sql).raw::<String>().await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| row.into_iter().next())
+            .collect())
+    }
+
+    async fn integers(&self, sql: &str) -> Result<Vec<i64>> {
+        let rows = self.db.prepare(sql).raw::<i64>().await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| row.into_iter().next())
+            .collect())
+    }
+
+    async fn batch(&self, statements: Vec<Statement>) -> Result<()> {
+        let prepared = statements
+            .into_iter()
+            .map(|statement| match statement {
+                Statement::Sql(sql) => Ok(self.db.prepare(sql)),
+                Statement::Record {
+                    version,
+                    description,
+                    applied_at,
+                } => self.db.prepare(migrations::INSERT_SCHEMA_MIGRATION).bind(&[
+                    JsValue::from_f64(version as f64),
+                    JsValue::from_str(description),
+                    JsValue::from_str(&applied_at),
+                ]),
+            })
+            .collect::<Result<Vec<D1PreparedStatement>>>()?;
+        self.db.batch(prepared).await?;
+        Ok(())
+    }
+}
+
+// D1Database doesn't implement Clone, so we can't derive Clone
+// Instead, we'll pass Arc<D1Database> where needed
+
+// Helper functions for serialization
+fn serialize_market_status(status: MarketStatus) -> String {
+    match status {
+        MarketStatus::Draft => "draft".to_string(),
+        MarketStatus::Open => "open".to_string(),
+        MarketStatus::Closed => "closed".to_string(),
+        MarketStatus::Resolved => "resolved".to_string(),
+    }
+}
+
+fn deserialize_market_status(s: &str) -> MarketStatus {
+    match s {
+        "draft" => MarketStatus::Draft,
+        "open" => MarketStatus::Open,
+        "closed" => MarketStatus::Closed,
+        "resolved" => MarketStatus::Resolved,
+        _ => MarketStatus::Draft,
+    }
+}
+
+fn serialize_bet_status(status: BetStatus) -> String {
+    match status {
+        BetStatus::Pending => "pending".to_string(),
+        BetStatus::Active => "active".to_string(),
+        BetStatus::ResolvedYes => "resolved_yes".to_string(),
+        BetStatus::ResolvedNo => "resolved_no".to_string(),
+        BetStatus::Challenged => "challenged".to_string(),
+        BetStatus::Void => "void".to_string(),
+    }
+}
+
+fn deserialize_bet_status(s: &str) -> BetStatus {
+    match s {
+        "pending" => BetStatus::Pending,
+        "active" => BetStatus::Active,
+        "resolved_yes" => BetStatus::ResolvedYes,
+        "resolved_no" => BetStatus::ResolvedNo,
+        "challenged" => BetStatus::Challenged,
+        "void" => BetStatus::Void,
+        _ => BetStatus::Pending,
+    }
+}
+
+fn serialize_membership_status(status: MembershipStatus) -> String {
+    match status {
+        MembershipStatus::Active => "active".to_string(),
+        MembershipStatus::Left => "left".to_string(),
+        MembershipStatus::Kicked => "kicked".to_string(),
+        MembershipStatus::Banned => "banned".to_string(),
+    }
+}
+
+fn deserialize_membership_status(s: &str) -> MembershipStatus {
+    match s {
+        "left" => MembershipStatus::Left,
+        "kicked" => MembershipStatus::Kicked,
+        "banned" => MembershipStatus::Banned,
+        _ => MembershipStatus::Active,
+    }
+}
+
+fn serialize_side(side: Side) -> String {
+    match side {
+        Side::Yes => "YES".to_string(),
+        Side::No => "NO".to_string(),
+    }
+}
+
+fn deserialize_side(s: &str) -> Side {
+    match s {
+        "YES" => Side::Yes,
+        "NO" => Side::No,
+        _ => Side::Yes,
+    }
+}
+
+// D1 row deserializers
+#[derive(Debug, Deserialize)]
+struct MarketRow {
+    id: String,
+    name: String,
+    status: String,
+    created_by: String,
+    opens_at: String,
+    closes_at: String,
+    starting_balance: i64,
+    invite_code: String,
+    created_at: String,
+}
+
+impl MarketRow {
+    fn into_market(self) -> Market {
+        Market {
+            id: Uuid::parse_str(&self.id).unwrap(),
+            name: self.name,
+            status: deserialize_market_status(&self.status),
+            created_by: Uuid::parse_str(&self.created_by).unwrap(),
+            opens_at: chrono::DateTime::parse_from_rfc3339(&self.opens_at)
+                .unwrap()
+                .into(),
+            closes_at: chrono::DateTime::parse_from_rfc3339(&self.closes_at)
+                .unwrap()
+                .into(),
+            starting_balance: self.starting_balance,
+            invite_code: self.invite_code,
+            created_at: chrono::DateTime::parse_from_rfc3339(&self.created_at)
+                .unwrap()
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UserRow {
+    id: String,
+    market_id: String,
+    device_id: String,
+    display_name: String,
+    avatar: String,
+    balance: i64,
+    is_admin: i64,
+    status: String,
+    player_id: Option<String>,
+    joined_at: String,
+}
+
+impl UserRow {
+    fn into_user(self) -> User {
+        User {
+            id: Uuid::parse_str(&self.id).unwrap(),
+            market_id: Uuid::parse_str(&self.market_id).unwrap(),
+            device_id: self.device_id,
+            display_name: self.display_name,
+            avatar: self.avatar,
+            balance: self.balance,
+            is_admin: self.is_admin != 0,
+            status: deserialize_membership_status(&self.status),
+            player_id: self.player_id.map(|id| Uuid::parse_str(&id).unwrap()),
+            joined_at: chrono::DateTime::parse_from_rfc3339(&self.joined_at)
+                .unwrap()
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SpectatorRow {
+    id: String,
+    market_id: String,
+    display_name: String,
+    token: String,
+    joined_at: String,
+}
+
+impl SpectatorRow {
+    fn into_spectator(self) -> Spectator {
+        Spectator {
+            id: Uuid::parse_str(&self.id).unwrap(),
+            market_id: Uuid::parse_str(&self.market_id).unwrap(),
+            display_name: self.display_name,
+            token: self.token,
+            joined_at: chrono::DateTime::parse_from_rfc3339(&self.joined_at)
+                .unwrap()
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookRow {
+    id: String,
+    market_id: String,
+    url: String,
+    secret: String,
+    events: String,
+    created_at: String,
+}
+
+impl WebhookRow {
+    fn into_webhook(self) -> Webhook {
+        Webhook {
+            id: Uuid::parse_str(&self.id).unwrap(),
+            market_id: Uuid::parse_str(&self.market_id).unwrap(),
+            url: self.url,
+            secret: self.secret,
+            events: serde_json::from_str(&self.events).unwrap(),
+            created_at: chrono::DateTime::parse_from_rfc3339(&self.created_at)
+                .unwrap()
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookDeliveryRow {
+    id: String,
+    webhook_id: String,
+    event: String,
+    attempt: i64,
+    status_code: Option<i64>,
+    error: Option<String>,
+    succeeded: i64,
+    created_at: String,
+}
+
+impl WebhookDeliveryRow {
+    fn into_delivery(self) -> WebhookDelivery {
+        WebhookDelivery {
+            id: Uuid::parse_str(&self.id).unwrap(),
+            webhook_id: Uuid::parse_str(&self.webhook_id).unwrap(),
+            event: self.event,
+            attempt: self.attempt as u32,
+            status_code: self.status_code.map(|code| code as u16),
+            error: self.error,
+            succeeded: self.succeeded != 0,
+            created_at: chrono::DateTime::parse_from_rfc3339(&self.created_at)
+                .unwrap()
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RevealCeremonyRow {
+    market_id: String,
+    bet_ids: String,
+    revealed_count: i64,
+    started_at: String,
+    completed_at: Option<String>,
+}
+
+impl RevealCeremonyRow {
+    fn into_reveal_ceremony(self) -> RevealCeremony {
+        RevealCeremony {
+            market_id: Uuid::parse_str(&self.market_id).unwrap(),
+            bet_ids: serde_json::from_str(&self.bet_ids).unwrap(),
+            revealed_count: self.revealed_count as usize,
+            started_at: chrono::DateTime::parse_from_rfc3339(&self.started_at)
+                .unwrap()
+                .into(),
+            completed_at: self
+                .completed_at
+                .map(|s| chrono::DateTime::parse_from_rfc3339(&s).unwrap().into()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PlayerRow {
+    id: String,
+    display_name: String,
+    avatar: String,
+    created_at: String,
+}
+
+impl PlayerRow {
+    fn into_player(self) -> Player {
+        Player {
+            id: Uuid::parse_str(&self.id).unwrap(),
+            display_name: self.display_name,
+            avatar: self.avatar,
+            created_at: chrono::DateTime::parse_from_rfc3339(&self.created_at)
+                .unwrap()
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MarketTemplateRow {
+    id: String,
+    name: String,
+    starting_balance: i64,
+    duration_hours: i64,
+    bets: String,
+    created_at: String,
+}
+
+impl MarketTemplateRow {
+    fn into_template(self) -> MarketTemplate {
+        MarketTemplate {
+            id: Uuid::parse_str(&self.id).unwrap(),
+            name: self.name,
+            starting_balance: self.starting_balance,
+            duration_hours: self.duration_hours,
+            bets: serde_json::from_str(&self.bets).unwrap(),
+            created_at: chrono::DateTime::parse_from_rfc3339(&self.created_at)
+                .unwrap()
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SeasonRow {
+    id: String,
+    name: String,
+    carry_over_balances: i64,
+    created_at: String,
+}
+
+impl SeasonRow {
+    fn into_season(self) -> Season {
+        Season {
+            id: Uuid::parse_str(&self.id).unwrap(),
+            name: self.name,
+            carry_over_balances: self.carry_over_balances != 0,
+            created_at: chrono::DateTime::parse_from_rfc3339(&self.created_at)
+                .unwrap()
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CarryOverRow {
+    user_id: String,
+    amount: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinkCodeRow {
+    code: String,
+    device_id: String,
+    created_at: String,
+    expires_at: String,
+    redeemed_at: Option<String>,
+    redeemed_by: Option<String>,
+}
+
+impl LinkCodeRow {
+    fn into_link_code(self) -> LinkCode {
+        LinkCode {
+            code: self.code,
+            device_id: self.device_id,
+            created_at: chrono::DateTime::parse_from_rfc3339(&self.created_at)
+                .unwrap()
+                .into(),
+            expires_at: chrono::DateTime::parse_from_rfc3339(&self.expires_at)
+                .unwrap()
+                .into(),
+            redeemed_at: self
+                .redeemed_at
+                .map(|s| chrono::DateTime::parse_from_rfc3339(&s).unwrap().into()),
+            redeemed_by: self.redeemed_by,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CountRow {
+    count: i64,
+}
+
+/// Bet columns plus the comma-separated subject ids from `bet_subjects`
+const SELECT_BETS: &str = "SELECT b.*, \
+     (SELECT group_concat(bs.user_id) FROM bet_subjects bs WHERE bs.bet_id = b.id) AS subject_user_ids \
+     FROM bets b";
+
+#[derive(Debug, Deserialize)]
+struct BetRow {
+    id: String,
+    market_id: String,
+    subject_user_ids: Option<String>,
+    created_by: String,
+    description: String,
+    initial_odds: String,
+    status: String,
+    yes_pool: i64,
+    no_pool: i64,
+    hide_from_subject: i64,
+    about_everyone: i64,
+    created_at: String,
+    resolved_at: Option<String>,
+}
+
+impl BetRow {
+    fn into_bet(self) -> Bet {
+        Bet {
+            id: Uuid::parse_str(&self.id).unwrap(),
+            market_id: Uuid::parse_str(&self.market_id).unwrap(),
+            subject_user_ids: self
+                .subject_user_ids
+                .map(|ids| {
+                    ids.split(',')
+                        .map(|id| Uuid::parse_str(id).unwrap())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            about_everyone: self.about_everyone != 0,
+            created_by: Uuid::parse_str(&self.created_by).unwrap(),
+            description: self.description,
+            initial_odds: self.initial_odds,
+            status: deserialize_bet_status(&self.status),
+            yes_pool: self.yes_pool,
+            no_pool: self.no_pool,
+            hide_from_subject: self.hide_from_subject != 0,
+            created_at: chrono::DateTime::parse_from_rfc3339(&self.created_at)
+                .unwrap()
+                .into(),
+            resolved_at: self
+                .resolved_at
+                .map(|s| chrono::DateTime::parse_from_rfc3339(&s).unwrap().into()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WagerRow {
+    id: String,
+    bet_id: String,
+    user_id: String,
+    side: String,
+    amount: i64,
+    placed_at: String,
+    yes_pool_after: i64,
+    no_pool_after: i64,
+    probability_after: f64,
+    refunded: i64,
+}
+
+impl WagerRow {
+    fn into_wager(self) -> Wager {
+        Wager {
+            id: Uuid::parse_str(&self.id).unwrap(),
+            bet_id: Uuid::parse_str(&self.bet_id).unwrap(),
+            user_id: Uuid::parse_str(&self.user_id).unwrap(),
+            side: deserialize_side(&self.side),
+            amount: self.amount,
+            placed_at: chrono::DateTime::parse_from_rfc3339(&self.placed_at)
+                .unwrap()
+                .into(),
+            yes_pool_after: self.yes_pool_after,
+            no_pool_after: self.no_pool_after,
+            probability_after: self.probability_after,
+            refunded: self.refunded != 0,
+        }
+    }
+}
+
+/// A device's seats joined with their markets, columns renamed apart
+const SELECT_DEVICE_MARKETS: &str = "SELECT \
+     m.id AS market_id, m.name, m.status, m.created_by, m.opens_at, m.closes_at, \
+     m.starting_balance, m.invite_code, m.created_at, \
+     u.id AS user_id, u.device_id, u.display_name, u.avatar, u.balance, u.is_admin, \
+     u.status AS user_status, u.player_id, u.joined_at \
+     FROM users u JOIN markets m ON u.market_id = m.id";
+
+#[derive(Debug, Deserialize)]
+struct DeviceMarketRow {
+    market_id: String,
+    name: String,
+    status: String,
+    created_by: String,
+    opens_at: String,
+    closes_at: String,
+    starting_balance: i64,
+    invite_code: String,
+    created_at: String,
+    user_id: String,
+    device_id: String,
+    display_name: String,
+    avatar: String,
+    balance: i64,
+    is_admin: i64,
+    user_status: String,
+    player_id: Option<String>,
+    joined_at: String,
+}
+
+impl DeviceMarketRow {
+    fn into_pair(self) -> (Market, User) {
+        let market = MarketRow {
+            id: self.market_id.clone(),
+            name: self.name,
+            status: self.status,
+            created_by: self.created_by,
+            opens_at: self.opens_at,
+            closes_at: self.closes_at,
+            starting_balance: self.starting_balance,
+            invite_code: self.invite_code,
+            created_at: self.created_at,
+        };
+        let user = UserRow {
+            id: self.user_id,
+            market_id: self.market_id,
+            device_id: self.device_id,
+            display_name: self.display_name,
+            avatar: self.avatar,
+            balance: self.balance,
+            is_admin: self.is_admin,
+            status: self.user_status,
+            player_id: self.player_id,
+            joined_at: self.joined_at,
+        };
+        (market.into_market(), user.into_user())
+    }
+}
+
+impl D1Database {
+    /// Run a `query::Select` and deserialize its rows
+    async fn select<T: for<'de> Deserialize<'de>>(
+        &self,
+        sql: String,
+        params: Vec<SqlValue>,
+    ) -> DbResult<Vec<T>> {
+        let params: Vec<JsValue> = params
+            .into_iter()
+            .map(|param| match param {
+                SqlValue::Text(text) => JsValue::from_str(&text),
+                SqlValue::Int(n) => JsValue::from_f64(n as f64),
+            })
+            .collect();
+
+        self.db
+            .prepare(sql)
+            .bind(&params)
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .results::<T>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize rows: {}", e)))
+    }
+}
+
+// ===== Inserts (shared by the create_ methods and import_archive) =====
+impl D1Database {
+    fn insert_market(&self, market: &Market) -> DbResult<D1PreparedStatement> {
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO markets (id, name, status, created_by, opens_at, closes_at, starting_balance, invite_code, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&market.id.to_string()),
+                JsValue::from_str(&market.name),
+                JsValue::from_str(&serialize_market_status(market.status)),
+                JsValue::from_str(&market.created_by.to_string()),
+                JsValue::from_str(&market.opens_at.to_rfc3339()),
+                JsValue::from_str(&market.closes_at.to_rfc3339()),
+                JsValue::from_f64(market.starting_balance as f64),
+                JsValue::from_str(&market.invite_code),
+                JsValue::from_str(&market.created_at.to_rfc3339()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))
+    }
+
+    fn insert_user(&self, user: &User) -> DbResult<D1PreparedStatement> {
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO users (id, market_id, device_id, display_name, avatar, balance, is_admin, status, player_id, joined_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&user.id.to_string()),
+                JsValue::from_str(&user.market_id.to_string()),
+                JsValue::from_str(&user.device_id),
+                JsValue::from_str(&user.display_name),
+                JsValue::from_str(&user.avatar),
+                JsValue::from_f64(user.balance as f64),
+                JsValue::from_f64(if user.is_admin { 1.0 } else { 0.0 }),
+                JsValue::from_str(&serialize_membership_status(user.status)),
+                user.player_id
+                    .map(|id| JsValue::from_str(&id.to_string()))
+                    .unwrap_or(JsValue::null()),
+                JsValue::from_str(&user.joined_at.to_rfc3339()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))
+    }
+
+    fn insert_player(&self, player: &Player) -> DbResult<D1PreparedStatement> {
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO players (id, display_name, avatar, created_at)
+                VALUES (?1, ?2, ?3, ?4)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&player.id.to_string()),
+                JsValue::from_str(&player.display_name),
+                JsValue::from_str(&player.avatar),
+                JsValue::from_str(&player.created_at.to_rfc3339()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))
+    }
+
+    fn insert_season(&self, season: &Season) -> DbResult<D1PreparedStatement> {
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO seasons (id, name, carry_over_balances, created_at)
+                VALUES (?1, ?2, ?3, ?4)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&season.id.to_string()),
+                JsValue::from_str(&season.name),
+                JsValue::from_f64(if season.carry_over_balances { 1.0 } else { 0.0 }),
+                JsValue::from_str(&season.created_at.to_rfc3339()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))
+    }
+
+    fn insert_spectator(&self, spectator: &Spectator) -> DbResult<D1PreparedStatement> {
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO spectators (id, market_id, display_name, token, joined_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&spectator.id.to_string()),
+                JsValue::from_str(&spectator.market_id.to_string()),
+                JsValue::from_str(&spectator.display_name),
+                JsValue::from_str(&spectator.token),
+                JsValue::from_str(&spectator.joined_at.to_rfc3339()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))
+    }
+
+    fn insert_wager(&self, wager: &Wager) -> DbResult<D1PreparedStatement> {
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO wagers (id, bet_id, user_id, side, amount, placed_at, yes_pool_after, no_pool_after, probability_after, refunded)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&wager.id.to_string()),
+                JsValue::from_str(&wager.bet_id.to_string()),
+                JsValue::from_str(&wager.user_id.to_string()),
+                JsValue::from_str(&serialize_side(wager.side)),
+                JsValue::from_f64(wager.amount as f64),
+                JsValue::from_str(&wager.placed_at.to_rfc3339()),
+                JsValue::from_f64(wager.yes_pool_after as f64),
+                JsValue::from_f64(wager.no_pool_after as f64),
+                JsValue::from_f64(wager.probability_after),
+                JsValue::from_f64(if wager.refunded { 1.0 } else { 0.0 }),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))
+    }
+
+    /// The bet row followed by one row per subject
+    fn insert_bet(&self, bet: &Bet) -> DbResult<Vec<D1PreparedStatement>> {
+        let mut statements = vec![self
+            .db
+            .prepare(
+                r#"
+                INSERT INTO bets (id, market_id, created_by, description, initial_odds, status, yes_pool, no_pool, hide_from_subject, about_everyone, created_at, resolved_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&bet.id.to_string()),
+                JsValue::from_str(&bet.market_id.to_string()),
+                JsValue::from_str(&bet.created_by.to_string()),
+                JsValue::from_str(&bet.description),
+                JsValue::from_str(&bet.initial_odds),
+                JsValue::from_str(&serialize_bet_status(bet.status)),
+                JsValue::from_f64(bet.yes_pool as f64),
+                JsValue::from_f64(bet.no_pool as f64),
+                JsValue::from_f64(if bet.hide_from_subject { 1.0 } else { 0.0 }),
+                JsValue::from_f64(if bet.about_everyone { 1.0 } else { 0.0 }),
+                JsValue::from_str(&bet.created_at.to_rfc3339()),
+                bet.resolved_at.map(|d| JsValue::from_str(&d.to_rfc3339())).unwrap_or(JsValue::null()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?];
+
+        for subject_id in &bet.subject_user_ids {
+            statements.push(
+                self.db
+                    .prepare("INSERT INTO bet_subjects (bet_id, user_id) VALUES (?1, ?2)")
+                    .bind(&[
+                        JsValue::from_str(&bet.id.to_string()),
+                        JsValue::from_str(&subject_id.to_string()),
+                    ])
+                    .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?,
+            );
+        }
+
+        Ok(statements)
+    }
+
+    fn insert_reveal_ceremony(&self, ceremony: &RevealCeremony) -> DbResult<D1PreparedStatement> {
+        let bet_ids = serde_json::to_string(&ceremony.bet_ids)
+            .map_err(|e| DbError::Internal(format!("Failed to serialize bet ids: {}", e)))?;
+
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO reveal_ceremonies (market_id, bet_ids, revealed_count, started_at, completed_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&ceremony.market_id.to_string()),
+                JsValue::from_str(&bet_ids),
+                JsValue::from_f64(ceremony.revealed_count as f64),
+                JsValue::from_str(&ceremony.started_at.to_rfc3339()),
+                ceremony
+                    .completed_at
+                    .map(|d| JsValue::from_str(&d.to_rfc3339()))
+                    .unwrap_or(JsValue::null()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))
+    }
+
+    fn insert_season_market(
+        &self,
+        season_id: Uuid,
+        market_id: Uuid,
+        position: usize,
+    ) -> DbResult<D1PreparedStatement> {
+        self.db
+            .prepare(
+                "INSERT INTO season_markets (market_id, season_id, position) VALUES (?1, ?2, ?3)",
+            )
+            .bind(&[
+                JsValue::from_str(&market_id.to_string()),
+                JsValue::from_str(&season_id.to_string()),
+                JsValue::from_f64(position as f64),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))
+    }
+
+    fn insert_carry_over(
+        &self,
+        market_id: Uuid,
+        user_id: Uuid,
+        amount: i64,
+    ) -> DbResult<D1PreparedStatement> {
+        self.db
+            .prepare(
+                "INSERT INTO season_carry_overs (user_id, market_id, amount) VALUES (?1, ?2, ?3)",
+            )
+            .bind(&[
+                JsValue::from_str(&user_id.to_string()),
+                JsValue::from_str(&market_id.to_string()),
+                JsValue::from_f64(amount as f64),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))
+    }
 }
 
-// Placeholder implementation - will be fully implemented when integrating with worker crate
-#[cfg(feature = "wasm")]
 #[async_trait(?Send)]
 impl Database for D1Database {
-    async fn create_market(
+    async fn create_market(&self, market: Market) -> DbResult<Market> {
+        self.insert_market(&market)?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert market: {}", e)))?;
+
+        Ok(market)
+    }
+
+    async fn get_market(&self, id: Uuid) -> DbResult<Market> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM markets WHERE id = ?1")
+            .bind(&[JsValue::from_str(&id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<MarketRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Market not found".to_string()))?;
+
+        Ok(result.into_market())
+    }
+
+    async fn get_market_by_invite_code(&self, code: &str) -> DbResult<Market> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM markets WHERE invite_code = ?1")
+            .bind(&[JsValue::from_str(code)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<MarketRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Market not found".to_string()))?;
+
+        Ok(result.into_market())
+    }
+
+    async fn update_market_status(&self, id: Uuid, status: MarketStatus) -> DbResult<()> {
+        self.db
+            .prepare("UPDATE markets SET status = ?1 WHERE id = ?2")
+            .bind(&[
+                JsValue::from_str(&serialize_market_status(status)),
+                JsValue::from_str(&id.to_string()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to update market status: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn delete_market(&self, id: Uuid) -> DbResult<()> {
+        let id_str = id.to_string();
+
+        // Delete wagers for bets in this market
+        self.db
+            .prepare(
+                "DELETE FROM wagers WHERE bet_id IN (SELECT id FROM bets WHERE market_id = ?1)",
+            )
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete wagers: {}", e)))?;
+
+        // Delete subjects of bets in this market
+        self.db
+            .prepare(
+                "DELETE FROM bet_subjects WHERE bet_id IN (SELECT id FROM bets WHERE market_id = ?1)",
+            )
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete bet subjects: {}", e)))?;
+
+        // Delete bets
+        self.db
+            .prepare("DELETE FROM bets WHERE market_id = ?1")
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete bets: {}", e)))?;
+
+        // Delete reveal ceremony
+        self.db
+            .prepare("DELETE FROM reveal_ceremonies WHERE market_id = ?1")
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete reveal ceremony: {}", e)))?;
+
+        // Delete spectators
+        self.db
+            .prepare("DELETE FROM spectators WHERE market_id = ?1")
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete spectators: {}", e)))?;
+
+        // Delete webhooks and their delivery logs
+        self.db
+            .prepare(
+                "DELETE FROM webhook_deliveries WHERE webhook_id IN (SELECT id FROM webhooks WHERE market_id = ?1)",
+            )
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete webhook deliveries: {}", e)))?;
+
+        self.db
+            .prepare("DELETE FROM webhooks WHERE market_id = ?1")
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete webhooks: {}", e)))?;
+
+        // Delete season links
+        self.db
+            .prepare("DELETE FROM season_carry_overs WHERE market_id = ?1")
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete carry-overs: {}", e)))?;
+
+        self.db
+            .prepare("DELETE FROM season_markets WHERE market_id = ?1")
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete season link: {}", e)))?;
+
+        // Delete users
+        self.db
+            .prepare("DELETE FROM users WHERE market_id = ?1")
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete users: {}", e)))?;
+
+        // Delete market
+        self.db
+            .prepare("DELETE FROM markets WHERE id = ?1")
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete market: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn import_archive(&self, archive: &MarketArchive) -> DbResult<()> {
+        // Players and the season may already exist; everything else goes in
+        // one batch, which D1 runs as a single transaction
+        let mut statements = Vec::new();
+        for player in &archive.players {
+            match self.get_player(player.id).await {
+                Ok(_) => {}
+                Err(DbError::NotFound(_)) => statements.push(self.insert_player(player)?),
+                Err(e) => return Err(e),
+            }
+        }
+        if let Some(membership) = &archive.season {
+            match self.get_season(membership.season.id).await {
+                Ok(_) => {}
+                Err(DbError::NotFound(_)) => {
+                    statements.push(self.insert_season(&membership.season)?)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let market_id = archive.market.id;
+        statements.push(self.insert_market(&archive.market)?);
+        for user in &archive.users {
+            statements.push(self.insert_user(user)?);
+        }
+        for bet in &archive.bets {
+            statements.extend(self.insert_bet(bet)?);
+        }
+        for wager in &archive.wagers {
+            statements.push(self.insert_wager(wager)?);
+        }
+        for spectator in &archive.spectators {
+            statements.push(self.insert_spectator(spectator)?);
+        }
+        if let Some(ceremony) = &archive.reveal_ceremony {
+            statements.push(self.insert_reveal_ceremony(ceremony)?);
+        }
+        if let Some(membership) = &archive.season {
+            statements.push(self.insert_season_market(
+                membership.season.id,
+                market_id,
+                membership.position,
+            )?);
+        }
+        for carry_over in &archive.carry_overs {
+            statements.push(self.insert_carry_over(
+                market_id,
+                carry_over.user_id,
+                carry_over.amount,
+            )?);
+        }
+
+        self.db
+            .batch(statements)
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to import market: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn create_user(&self, user: User) -> DbResult<User> {
+        self.insert_user(&user)?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert user: {}", e)))?;
+
+        Ok(user)
+    }
+
+    async fn get_user(&self, id: Uuid) -> DbResult<User> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM users WHERE id = ?1")
+            .bind(&[JsValue::from_str(&id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<UserRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("User not found".to_string()))?;
+
+        Ok(result.into_user())
+    }
+
+    async fn get_user_by_device_id(&self, market_id: Uuid, device_id: &str) -> DbResult<User> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM users WHERE market_id = ?1 AND device_id = ?2")
+            .bind(&[
+                JsValue::from_str(&market_id.to_string()),
+                JsValue::from_str(device_id),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<UserRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("User not found".to_string()))?;
+
+        Ok(result.into_user())
+    }
+
+    async fn get_users_in_market(&self, market_id: Uuid) -> DbResult<Vec<User>> {
+        let results = self
+            .db
+            .prepare("SELECT * FROM users WHERE market_id = ?1")
+            .bind(&[JsValue::from_str(&market_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let users: Vec<User> = results
+            .results::<UserRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize users: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_user())
+            .collect();
+
+        Ok(users)
+    }
+
+    async fn list_users_in_market(
         &self,
-        _name: &str,
-        _created_by: &str,
-        _starting_balance: i32,
-    ) -> DbResult<Market> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+        market_id: Uuid,
+        query: &UserQuery,
+    ) -> DbResult<Page<User>> {
+        let mut select = Select::new("SELECT * FROM users");
+        select.filter("market_id = ?", [market_id.into()]);
+        select.filter_by("status = ?", query.status.map(serialize_membership_status));
+        select.filter_by("joined_at >= ?", query.joined_after);
+        select.filter_by("joined_at < ?", query.joined_before);
+        let key = match query.sort {
+            UserSort::Joined => "joined_at",
+            UserSort::Balance => "balance",
+        };
+        let (sql, params) = select.page(
+            key,
+            "id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
+
+        let rows: Vec<UserRow> = self.select(sql, params).await?;
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.into_iter().map(|row| row.into_user()).collect(),
+            query.limit(),
+            |user| sort.key(user),
+        ))
+    }
+
+    async fn update_user_balance(&self, user_id: Uuid, new_balance: i64) -> DbResult<()> {
+        self.db
+            .prepare("UPDATE users SET balance = ?1 WHERE id = ?2")
+            .bind(&[
+                JsValue::from_f64(new_balance as f64),
+                JsValue::from_str(&user_id.to_string()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to update user balance: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn update_user_status(&self, user_id: Uuid, status: MembershipStatus) -> DbResult<()> {
+        self.db
+            .prepare("UPDATE users SET status = ?1 WHERE id = ?2")
+            .bind(&[
+                JsValue::from_str(&serialize_membership_status(status)),
+                JsValue::from_str(&user_id.to_string()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to update user status: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        display_name: &str,
+        avatar: &str,
+    ) -> DbResult<()> {
+        self.db
+            .prepare("UPDATE users SET display_name = ?1, avatar = ?2 WHERE id = ?3")
+            .bind(&[
+                JsValue::from_str(display_name),
+                JsValue::from_str(avatar),
+                JsValue::from_str(&user_id.to_string()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to update user profile: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn update_user_device(&self, user_id: Uuid, device_id: &str) -> DbResult<()> {
+        self.db
+            .prepare("UPDATE users SET device_id = ?1 WHERE id = ?2")
+            .bind(&[
+                JsValue::from_str(device_id),
+                JsValue::from_str(&user_id.to_string()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to update user device: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn get_markets_by_device_id(&self, device_id: &str) -> DbResult<Vec<(Market, User)>> {
+        // D1 doesn't support complex JOINs well with the current API, so we'll do two queries
+        // First get all users with this device_id
+        let user_results = self
+            .db
+            .prepare("SELECT * FROM users WHERE device_id = ?1 ORDER BY joined_at DESC LIMIT 10")
+            .bind(&[JsValue::from_str(device_id)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let users: Vec<User> = user_results
+            .results::<UserRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize users: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_user())
+            .collect();
+
+        // Now fetch each market
+        let mut result = Vec::new();
+        for user in users {
+            if let Ok(market) = self.get_market(user.market_id).await {
+                result.push((market, user));
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn list_markets_by_device_id(
+        &self,
+        device_id: &str,
+        query: &DeviceMarketQuery,
+    ) -> DbResult<Page<(Market, User)>> {
+        let mut select = Select::new(SELECT_DEVICE_MARKETS);
+        select.filter("u.device_id = ?", [device_id.to_string().into()]);
+        select.filter_by("m.status = ?", query.status.map(serialize_market_status));
+        select.filter_by("m.created_at >= ?", query.created_after);
+        select.filter_by("m.created_at < ?", query.created_before);
+        let key = match query.sort {
+            DeviceMarketSort::Recent => "u.joined_at",
+            DeviceMarketSort::Newest => "m.created_at",
+        };
+        let (sql, params) = select.page(
+            key,
+            "u.id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
+
+        let rows: Vec<DeviceMarketRow> = self.select(sql, params).await?;
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.into_iter().map(|row| row.into_pair()).collect(),
+            query.limit(),
+            |row| sort.key(row),
+        ))
+    }
+
+    async fn create_player(&self, player: Player) -> DbResult<Player> {
+        self.insert_player(&player)?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert player: {}", e)))?;
+
+        Ok(player)
+    }
+
+    async fn get_player(&self, id: Uuid) -> DbResult<Player> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM players WHERE id = ?1")
+            .bind(&[JsValue::from_str(&id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<PlayerRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Player not found".to_string()))?;
+
+        Ok(result.into_player())
+    }
+
+    async fn update_user_player(&self, user_id: Uuid, player_id: Uuid) -> DbResult<()> {
+        self.db
+            .prepare("UPDATE users SET player_id = ?1 WHERE id = ?2")
+            .bind(&[
+                JsValue::from_str(&player_id.to_string()),
+                JsValue::from_str(&user_id.to_string()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to link user to player: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn get_users_by_player(&self, player_id: Uuid) -> DbResult<Vec<User>> {
+        let results = self
+            .db
+            .prepare("SELECT * FROM users WHERE player_id = ?1 ORDER BY joined_at")
+            .bind(&[JsValue::from_str(&player_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let users = results
+            .results::<UserRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize users: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_user())
+            .collect();
+
+        Ok(users)
+    }
+
+    async fn create_template(&self, template: MarketTemplate) -> DbResult<MarketTemplate> {
+        let bets = serde_json::to_string(&template.bets)
+            .map_err(|e| DbError::Internal(format!("Failed to serialize bets: {}", e)))?;
+
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO market_templates (id, name, starting_balance, duration_hours, bets, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&template.id.to_string()),
+                JsValue::from_str(&template.name),
+                JsValue::from_f64(template.starting_balance as f64),
+                JsValue::from_f64(template.duration_hours as f64),
+                JsValue::from_str(&bets),
+                JsValue::from_str(&template.created_at.to_rfc3339()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert template: {}", e)))?;
+
+        Ok(template)
+    }
+
+    async fn get_template(&self, id: Uuid) -> DbResult<MarketTemplate> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM market_templates WHERE id = ?1")
+            .bind(&[JsValue::from_str(&id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<MarketTemplateRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Template not found".to_string()))?;
+
+        Ok(result.into_template())
+    }
+
+    async fn create_season(&self, season: Season) -> DbResult<Season> {
+        self.insert_season(&season)?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert season: {}", e)))?;
+
+        Ok(season)
+    }
+
+    async fn get_season(&self, id: Uuid) -> DbResult<Season> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM seasons WHERE id = ?1")
+            .bind(&[JsValue::from_str(&id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<SeasonRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Season not found".to_string()))?;
+
+        Ok(result.into_season())
+    }
+
+    async fn add_season_market(
+        &self,
+        season_id: Uuid,
+        market_id: Uuid,
+        position: usize,
+    ) -> DbResult<()> {
+        self.insert_season_market(season_id, market_id, position)?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to add season market: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn get_season_markets(&self, season_id: Uuid) -> DbResult<Vec<Market>> {
+        let results = self
+            .db
+            .prepare(
+                r#"
+                SELECT m.* FROM markets m
+                JOIN season_markets sm ON sm.market_id = m.id
+                WHERE sm.season_id = ?1
+                ORDER BY sm.position
+                "#,
+            )
+            .bind(&[JsValue::from_str(&season_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let markets = results
+            .results::<MarketRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize markets: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_market())
+            .collect();
+
+        Ok(markets)
+    }
+
+    async fn get_season_for_market(&self, market_id: Uuid) -> DbResult<Season> {
+        let result = self
+            .db
+            .prepare(
+                r#"
+                SELECT s.* FROM seasons s
+                JOIN season_markets sm ON sm.season_id = s.id
+                WHERE sm.market_id = ?1
+                "#,
+            )
+            .bind(&[JsValue::from_str(&market_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<SeasonRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Season not found".to_string()))?;
+
+        Ok(result.into_season())
     }
 
-    async fn get_market(&self, _id: Uuid) -> DbResult<Market> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn create_carry_over(&self, market_id: Uuid, user_id: Uuid, amount: i64) -> DbResult<()> {
+        self.insert_carry_over(market_id, user_id, amount)?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert carry-over: {}", e)))?;
+
+        Ok(())
     }
 
-    async fn get_market_by_invite(&self, _code: &str) -> DbResult<Market> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn get_carry_overs(&self, season_id: Uuid) -> DbResult<Vec<(Uuid, i64)>> {
+        let results = self
+            .db
+            .prepare(
+                r#"
+                SELECT c.user_id, c.amount FROM season_carry_overs c
+                JOIN season_markets sm ON sm.market_id = c.market_id
+                WHERE sm.season_id = ?1
+                "#,
+            )
+            .bind(&[JsValue::from_str(&season_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let carry_overs = results
+            .results::<CarryOverRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize carry-overs: {}", e)))?
+            .into_iter()
+            .map(|row| (Uuid::parse_str(&row.user_id).unwrap(), row.amount))
+            .collect();
+
+        Ok(carry_overs)
     }
 
-    async fn start_market(&self, _id: Uuid) -> DbResult<()> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn create_link_code(&self, link: LinkCode) -> DbResult<LinkCode> {
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO link_codes (code, device_id, created_at, expires_at, redeemed_at, redeemed_by)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&link.code),
+                JsValue::from_str(&link.device_id),
+                JsValue::from_str(&link.created_at.to_rfc3339()),
+                JsValue::from_str(&link.expires_at.to_rfc3339()),
+                link.redeemed_at
+                    .map(|d| JsValue::from_str(&d.to_rfc3339()))
+                    .unwrap_or(JsValue::null()),
+                link.redeemed_by
+                    .as_deref()
+                    .map(JsValue::from_str)
+                    .unwrap_or(JsValue::null()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert link code: {}", e)))?;
+
+        Ok(link)
     }
 
-    async fn close_market(&self, _id: Uuid) -> DbResult<()> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn get_link_code(&self, code: &str) -> DbResult<LinkCode> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM link_codes WHERE code = ?1")
+            .bind(&[JsValue::from_str(code)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<LinkCodeRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Link code not found".to_string()))?;
+
+        Ok(result.into_link_code())
     }
 
-    async fn create_user(
+    async fn redeem_link_code(
         &self,
-        _market_id: Uuid,
-        _device_id: &str,
-        _display_name: &str,
-        _avatar: &str,
-        _is_admin: bool,
-    ) -> DbResult<User> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+        code: &str,
+        device_id: &str,
+        redeemed_at: DateTime<Utc>,
+    ) -> DbResult<bool> {
+        let result = self
+            .db
+            .prepare(
+                "UPDATE link_codes SET redeemed_at = ?1, redeemed_by = ?2 WHERE code = ?3 AND redeemed_at IS NULL",
+            )
+            .bind(&[
+                JsValue::from_str(&redeemed_at.to_rfc3339()),
+                JsValue::from_str(device_id),
+                JsValue::from_str(code),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to redeem link code: {}", e)))?;
+
+        let changes = result
+            .meta()
+            .map_err(|e| DbError::Internal(format!("Failed to read result meta: {}", e)))?
+            .and_then(|meta| meta.changes)
+            .unwrap_or(0);
+
+        Ok(changes == 1)
     }
 
-    async fn get_user(&self, _id: Uuid) -> DbResult<User> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn create_audit_entry(&self, entry: AuditEntry) -> DbResult<AuditEntry> {
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO audit_log (id, action, device_id, detail, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&entry.id.to_string()),
+                JsValue::from_str(&entry.action),
+                JsValue::from_str(&entry.device_id),
+                JsValue::from_str(&entry.detail),
+                JsValue::from_str(&entry.created_at.to_rfc3339()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert audit entry: {}", e)))?;
+
+        Ok(entry)
     }
 
-    async fn find_user_by_device(&self, _market_id: Uuid, _device_id: &str) -> DbResult<User> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn count_audit_entries(
+        &self,
+        device_id: Option<&str>,
+        action: &str,
+        since: DateTime<Utc>,
+    ) -> DbResult<i64> {
+        let result = self
+            .db
+            .prepare(
+                "SELECT COUNT(*) AS count FROM audit_log WHERE (?1 IS NULL OR device_id = ?1) AND action = ?2 AND created_at >= ?3",
+            )
+            .bind(&[
+                device_id.map(JsValue::from_str).unwrap_or(JsValue::null()),
+                JsValue::from_str(action),
+                JsValue::from_str(&since.to_rfc3339()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<CountRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        Ok(result.map(|row| row.count).unwrap_or(0))
     }
 
-    async fn list_users(&self, _market_id: Uuid) -> DbResult<Vec<User>> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn create_spectator(&self, spectator: Spectator) -> DbResult<Spectator> {
+        self.insert_spectator(&spectator)?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert spectator: {}", e)))?;
+
+        Ok(spectator)
     }
 
-    async fn update_user_balance(&self, _id: Uuid, _balance: i32) -> DbResult<()> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn get_spectator_by_token(&self, token: &str) -> DbResult<Spectator> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM spectators WHERE token = ?1")
+            .bind(&[JsValue::from_str(token)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<SpectatorRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Spectator not found".to_string()))?;
+
+        Ok(result.into_spectator())
+    }
+
+    async fn get_spectators_in_market(&self, market_id: Uuid) -> DbResult<Vec<Spectator>> {
+        let results = self
+            .db
+            .prepare("SELECT * FROM spectators WHERE market_id = ?1")
+            .bind(&[JsValue::from_str(&market_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let spectators = results
+            .results::<SpectatorRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize spectators: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_spectator())
+            .collect();
+
+        Ok(spectators)
     }
 
-    async fn create_bet(
+    async fn create_webhook(&self, webhook: Webhook) -> DbResult<Webhook> {
+        let events = serde_json::to_string(&webhook.events)
+            .map_err(|e| DbError::Internal(format!("Failed to serialize events: {}", e)))?;
+
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO webhooks (id, market_id, url, secret, events, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&webhook.id.to_string()),
+                JsValue::from_str(&webhook.market_id.to_string()),
+                JsValue::from_str(&webhook.url),
+                JsValue::from_str(&webhook.secret),
+                JsValue::from_str(&events),
+                JsValue::from_str(&webhook.created_at.to_rfc3339()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert webhook: {}", e)))?;
+
+        Ok(webhook)
+    }
+
+    async fn get_webhook(&self, id: Uuid) -> DbResult<Webhook> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM webhooks WHERE id = ?1")
+            .bind(&[JsValue::from_str(&id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<WebhookRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Webhook not found".to_string()))?;
+
+        Ok(result.into_webhook())
+    }
+
+    async fn get_webhooks_in_market(&self, market_id: Uuid) -> DbResult<Vec<Webhook>> {
+        let results = self
+            .db
+            .prepare("SELECT * FROM webhooks WHERE market_id = ?1 ORDER BY created_at, id")
+            .bind(&[JsValue::from_str(&market_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let webhooks = results
+            .results::<WebhookRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize webhooks: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_webhook())
+            .collect();
+
+        Ok(webhooks)
+    }
+
+    async fn delete_webhook(&self, id: Uuid) -> DbResult<()> {
+        let id_str = id.to_string();
+
+        self.db
+            .prepare("DELETE FROM webhook_deliveries WHERE webhook_id = ?1")
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| {
+                DbError::Internal(format!("Failed to delete webhook deliveries: {}", e))
+            })?;
+
+        self.db
+            .prepare("DELETE FROM webhooks WHERE id = ?1")
+            .bind(&[JsValue::from_str(&id_str)])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to delete webhook: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn create_webhook_delivery(
         &self,
-        _market_id: Uuid,
-        _subject_user_id: Uuid,
-        _statement: &str,
-        _initial_prob: f64,
-        _created_by: Uuid,
-    ) -> DbResult<Bet> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+        delivery: WebhookDelivery,
+    ) -> DbResult<WebhookDelivery> {
+        self.db
+            .prepare(
+                r#"
+                INSERT INTO webhook_deliveries (id, webhook_id, event, attempt, status_code, error, succeeded, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                "#,
+            )
+            .bind(&[
+                JsValue::from_str(&delivery.id.to_string()),
+                JsValue::from_str(&delivery.webhook_id.to_string()),
+                JsValue::from_str(&delivery.event),
+                JsValue::from_f64(delivery.attempt as f64),
+                delivery
+                    .status_code
+                    .map(|code| JsValue::from_f64(code as f64))
+                    .unwrap_or(JsValue::null()),
+                delivery
+                    .error
+                    .as_deref()
+                    .map(JsValue::from_str)
+                    .unwrap_or(JsValue::null()),
+                JsValue::from_f64(if delivery.succeeded { 1.0 } else { 0.0 }),
+                JsValue::from_str(&delivery.created_at.to_rfc3339()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert webhook delivery: {}", e)))?;
+
+        Ok(delivery)
     }
 
-    async fn get_bet(&self, _id: Uuid) -> DbResult<Bet> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn get_webhook_deliveries(&self, webhook_id: Uuid) -> DbResult<Vec<WebhookDelivery>> {
+        let results = self
+            .db
+            .prepare(
+                "SELECT * FROM webhook_deliveries WHERE webhook_id = ?1 ORDER BY created_at, attempt",
+            )
+            .bind(&[JsValue::from_str(&webhook_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let deliveries = results
+            .results::<WebhookDeliveryRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize deliveries: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_delivery())
+            .collect();
+
+        Ok(deliveries)
     }
 
-    async fn list_bets(&self, _market_id: Uuid) -> DbResult<Vec<Bet>> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn create_bet(&self, bet: Bet) -> DbResult<Bet> {
+        // One batch, run as one transaction, so a bet is never left about nobody
+        self.db
+            .batch(self.insert_bet(&bet)?)
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert bet: {}", e)))?;
+
+        Ok(bet)
     }
 
-    async fn approve_bet(&self, _id: Uuid) -> DbResult<()> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn get_bet(&self, id: Uuid) -> DbResult<Bet> {
+        let result = self
+            .db
+            .prepare(format!("{} WHERE b.id = ?1", SELECT_BETS))
+            .bind(&[JsValue::from_str(&id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<BetRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Bet not found".to_string()))?;
+
+        Ok(result.into_bet())
     }
 
-    async fn update_bet_probability(&self, _id: Uuid, _prob: f64) -> DbResult<()> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn get_bets_in_market(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        let results = self
+            .db
+            .prepare(format!("{} WHERE b.market_id = ?1", SELECT_BETS))
+            .bind(&[JsValue::from_str(&market_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let bets: Vec<Bet> = results
+            .results::<BetRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize bets: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_bet())
+            .collect();
+
+        Ok(bets)
     }
 
-    async fn resolve_bet(&self, _id: Uuid, _outcome: bool) -> DbResult<()> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn list_bets_in_market(&self, market_id: Uuid, query: &BetQuery) -> DbResult<Page<Bet>> {
+        let mut select = Select::new(SELECT_BETS);
+        select.filter("b.market_id = ?", [market_id.into()]);
+        select.filter_by("b.status = ?", query.status.map(serialize_bet_status));
+        select.filter_by(
+            "(b.about_everyone = 1 OR b.id IN (SELECT bet_id FROM bet_subjects WHERE user_id = ?))",
+            query.subject,
+        );
+        select.filter_by("b.created_by = ?", query.creator);
+        select.filter_by("b.created_at >= ?", query.created_after);
+        select.filter_by("b.created_at < ?", query.created_before);
+        let key = match query.sort {
+            BetSort::Newest | BetSort::Oldest => "b.created_at",
+            BetSort::Pool => "(b.yes_pool + b.no_pool)",
+        };
+        let (sql, params) = select.page(
+            key,
+            "b.id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
+
+        let rows: Vec<BetRow> = self.select(sql, params).await?;
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.into_iter().map(|row| row.into_bet()).collect(),
+            query.limit(),
+            |bet| sort.key(bet),
+        ))
     }
 
-    async fn create_wager(
+    async fn get_bets_for_user(
         &self,
-        _bet_id: Uuid,
-        _user_id: Uuid,
-        _side: Side,
-        _amount: i32,
-    ) -> DbResult<Wager> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+        market_id: Uuid,
+        viewing_user_id: Uuid,
+    ) -> DbResult<Vec<BetView>> {
+        let bets = self.get_bets_in_market(market_id).await?;
+        Ok(bets
+            .iter()
+            .map(|bet| bet.to_view(viewing_user_id))
+            .collect())
     }
 
-    async fn list_wagers(&self, _bet_id: Uuid) -> DbResult<Vec<Wager>> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn get_pending_bets(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        let results = self
+            .db
+            .prepare(format!(
+                "{} WHERE b.market_id = ?1 AND b.status = ?2",
+                SELECT_BETS
+            ))
+            .bind(&[
+                JsValue::from_str(&market_id.to_string()),
+                JsValue::from_str(&serialize_bet_status(BetStatus::Pending)),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let bets: Vec<Bet> = results
+            .results::<BetRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize bets: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_bet())
+            .collect();
+
+        Ok(bets)
+    }
+
+    async fn get_market_contents(&self, market_id: Uuid) -> DbResult<MarketContents> {
+        // One batch: a single round trip, run as one transaction
+        let id = [JsValue::from_str(&market_id.to_string())];
+        let statements = [
+            "SELECT * FROM markets WHERE id = ?1".to_string(),
+            "SELECT * FROM users WHERE market_id = ?1".to_string(),
+            format!("{} WHERE b.market_id = ?1", SELECT_BETS),
+        ]
+        .into_iter()
+        .map(|sql| self.db.prepare(sql).bind(&id))
+        .collect::<Result<Vec<_>>>()
+        .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?;
+
+        let results = self
+            .db
+            .batch(statements)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+        let [markets, users, bets] = <[D1Result; 3]>::try_from(results)
+            .map_err(|_| DbError::Internal("Batch returned the wrong number of results".into()))?;
+
+        let market = markets
+            .results::<MarketRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize market: {}", e)))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| DbError::NotFound("Market not found".to_string()))?;
+        let users = users
+            .results::<UserRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize users: {}", e)))?;
+        let bets = bets
+            .results::<BetRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize bets: {}", e)))?;
+
+        Ok(MarketContents {
+            market: market.into_market(),
+            users: users.into_iter().map(|row| row.into_user()).collect(),
+            bets: bets.into_iter().map(|row| row.into_bet()).collect(),
+        })
+    }
+
+    async fn update_bet_status(&self, bet_id: Uuid, status: BetStatus) -> DbResult<()> {
+        let resolved_at = match status {
+            BetStatus::ResolvedYes | BetStatus::ResolvedNo => {
+                JsValue::from_str(&chrono::Utc::now().to_rfc3339())
+            }
+            _ => JsValue::null(),
+        };
+
+        self.db
+            .prepare("UPDATE bets SET status = ?1, resolved_at = ?2 WHERE id = ?3")
+            .bind(&[
+                JsValue::from_str(&serialize_bet_status(status)),
+                resolved_at,
+                JsValue::from_str(&bet_id.to_string()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to update bet status: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn update_bet_pools(&self, bet_id: Uuid, yes_pool: i64, no_pool: i64) -> DbResult<()> {
+        self.db
+            .prepare("UPDATE bets SET yes_pool = ?1, no_pool = ?2 WHERE id = ?3")
+            .bind(&[
+                JsValue::from_f64(yes_pool as f64),
+                JsValue::from_f64(no_pool as f64),
+                JsValue::from_str(&bet_id.to_string()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to update bet pools: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn create_wager(&self, wager: Wager) -> DbResult<Wager> {
+        self.insert_wager(&wager)?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert wager: {}", e)))?;
+
+        Ok(wager)
+    }
+
+    async fn get_wagers_for_bet(&self, bet_id: Uuid) -> DbResult<Vec<Wager>> {
+        let results = self
+            .db
+            .prepare("SELECT * FROM wagers WHERE bet_id = ?1 ORDER BY placed_at")
+            .bind(&[JsValue::from_str(&bet_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let wagers: Vec<Wager> = results
+            .results::<WagerRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize wagers: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_wager())
+            .collect();
+
+        Ok(wagers)
     }
 
-    async fn list_user_wagers(&self, _user_id: Uuid) -> DbResult<Vec<Wager>> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn list_wagers_for_bet(&self, bet_id: Uuid, query: &WagerQuery) -> DbResult<Page<Wager>> {
+        let mut select = Select::new("SELECT * FROM wagers");
+        select.filter("bet_id = ?", [bet_id.into()]);
+        select.filter_by("user_id = ?", query.user);
+        select.filter_by("side = ?", query.side.map(serialize_side));
+        select.filter_by("placed_at >= ?", query.placed_after);
+        select.filter_by("placed_at < ?", query.placed_before);
+        let key = match query.sort {
+            WagerSort::Newest | WagerSort::Oldest => "placed_at",
+            WagerSort::Largest => "amount",
+        };
+        let (sql, params) = select.page(
+            key,
+            "id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
+
+        let rows: Vec<WagerRow> = self.select(sql, params).await?;
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.into_iter().map(|row| row.into_wager()).collect(),
+            query.limit(),
+            |wager| sort.key(wager),
+        ))
+    }
+
+    async fn apply_removal(&self, removal: &Removal) -> DbResult<()> {
+        let statement = |sql: &str, params: &[JsValue]| {
+            self.db
+                .prepare(sql)
+                .bind(params)
+                .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))
+        };
+
+        let mut statements = Vec::new();
+        for wager in &removal.refunds {
+            let wager_id = JsValue::from_str(&wager.id.to_string());
+            let amount = JsValue::from_f64(wager.amount as f64);
+            statements.push(statement(
+                "UPDATE wagers SET refunded = 1 WHERE id = ?1",
+                &[wager_id],
+            )?);
+            statements.push(statement(
+                "UPDATE users SET balance = balance + ?1 WHERE id = ?2",
+                &[
+                    amount.clone(),
+                    JsValue::from_str(&wager.user_id.to_string()),
+                ],
+            )?);
+            if !removal.voided_bet_ids.contains(&wager.bet_id) {
+                let sql = match wager.side {
+                    Side::Yes => "UPDATE bets SET yes_pool = yes_pool - ?1 WHERE id = ?2",
+                    Side::No => "UPDATE bets SET no_pool = no_pool - ?1 WHERE id = ?2",
+                };
+                statements.push(statement(
+                    sql,
+                    &[amount, JsValue::from_str(&wager.bet_id.to_string())],
+                )?);
+            }
+        }
+        for bet_id in &removal.voided_bet_ids {
+            statements.push(statement(
+                "UPDATE bets SET status = ?1 WHERE id = ?2",
+                &[
+                    JsValue::from_str(&serialize_bet_status(BetStatus::Void)),
+                    JsValue::from_str(&bet_id.to_string()),
+                ],
+            )?);
+        }
+        statements.push(statement(
+            "UPDATE users SET status = ?1 WHERE id = ?2",
+            &[
+                JsValue::from_str(&serialize_membership_status(removal.status)),
+                JsValue::from_str(&removal.user_id.to_string()),
+            ],
+        )?);
+
+        // One batch, run as one transaction
+        self.db
+            .batch(statements)
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to remove member: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn get_wagers_for_user(&self, user_id: Uuid) -> DbResult<Vec<Wager>> {
+        let results = self
+            .db
+            .prepare("SELECT * FROM wagers WHERE user_id = ?1 ORDER BY placed_at")
+            .bind(&[JsValue::from_str(&user_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let wagers: Vec<Wager> = results
+            .results::<WagerRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize wagers: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_wager())
+            .collect();
+
+        Ok(wagers)
     }
 
-    async fn get_bet_views(&self, _market_id: Uuid, _user_id: Uuid) -> DbResult<Vec<BetView>> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn get_bets_about_user(&self, user_id: Uuid) -> DbResult<Vec<Bet>> {
+        let results = self
+            .db
+            .prepare(format!(
+                "{} WHERE b.id IN (SELECT bet_id FROM bet_subjects WHERE user_id = ?1) \
+                 OR (b.about_everyone = 1 AND b.market_id = (SELECT market_id FROM users WHERE id = ?1))",
+                SELECT_BETS
+            ))
+            .bind(&[JsValue::from_str(&user_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .all()
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?;
+
+        let bets: Vec<Bet> = results
+            .results::<BetRow>()
+            .map_err(|e| DbError::Internal(format!("Failed to deserialize bets: {}", e)))?
+            .into_iter()
+            .map(|row| row.into_bet())
+            .collect();
+
+        Ok(bets)
+    }
+
+    async fn create_reveal_ceremony(&self, ceremony: RevealCeremony) -> DbResult<RevealCeremony> {
+        self.insert_reveal_ceremony(&ceremony)?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to insert reveal ceremony: {}", e)))?;
+
+        Ok(ceremony)
     }
 
-    async fn get_leaderboard(&self, _market_id: Uuid) -> DbResult<Vec<(User, i32)>> {
-        Err(DbError::Internal("D1 not yet implemented".to_string()))
+    async fn get_reveal_ceremony(&self, market_id: Uuid) -> DbResult<RevealCeremony> {
+        let result = self
+            .db
+            .prepare("SELECT * FROM reveal_ceremonies WHERE market_id = ?1")
+            .bind(&[JsValue::from_str(&market_id.to_string())])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .first::<RevealCeremonyRow>(None)
+            .await
+            .map_err(|e| DbError::Internal(format!("Query failed: {}", e)))?
+            .ok_or_else(|| DbError::NotFound("Reveal ceremony not found".to_string()))?;
+
+        Ok(result.into_reveal_ceremony())
+    }
+
+    async fn update_reveal_progress(
+        &self,
+        market_id: Uuid,
+        revealed_count: usize,
+        completed_at: Option<DateTime<Utc>>,
+    ) -> DbResult<()> {
+        self.db
+            .prepare(
+                "UPDATE reveal_ceremonies SET revealed_count = ?1, completed_at = ?2 WHERE market_id = ?3",
+            )
+            .bind(&[
+                JsValue::from_f64(revealed_count as f64),
+                completed_at
+                    .map(|d| JsValue::from_str(&d.to_rfc3339()))
+                    .unwrap_or(JsValue::null()),
+                JsValue::from_str(&market_id.to_string()),
+            ])
+            .map_err(|e| DbError::Internal(format!("Failed to bind: {}", e)))?
+            .run()
+            .await
+            .map_err(|e| DbError::Internal(format!("Failed to update reveal progress: {}", e)))?;
+
+        Ok(())
     }
 }
@@ -0,0 +1,939 @@
+/// In-memory implementation of the Database trait
+///
+/// Keeps everything in insertion-ordered vectors behind a lock, mirroring
+/// `SqliteDatabase`: the same unique and foreign key checks, the same
+/// orderings and the same cascade on `delete_market`. Data is gone when the
+/// value is dropped, which suits tests, demos and WASM builds without D1.
+use crate::db::query::{DeviceMarketQuery, Page};
+use crate::db::r#trait::{Database, DbError, DbResult};
+use crate::domain::models::{
+    AuditEntry, Bet, BetStatus, BetView, LinkCode, Market, MarketContents, MarketStatus,
+    MarketTemplate, MembershipStatus, Player, Removal, RevealCeremony, Season, Side, Spectator,
+    User, Wager, Webhook, WebhookDelivery,
+};
+use crate::domain::{parimutuel, rules::RuleError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use uuid::Uuid;
+
+#[derive(Default)]
+pub struct InMemoryDatabase {
+    state: RwLock<State>,
+}
+
+#[derive(Default)]
+struct State {
+    markets: Vec<Market>,
+    players: Vec<Player>,
+    users: Vec<User>,
+    spectators: Vec<Spectator>,
+    webhooks: Vec<Webhook>,
+    webhook_deliveries: Vec<WebhookDelivery>,
+    bets: Vec<Bet>,
+    wagers: Vec<Wager>,
+    reveal_ceremonies: Vec<RevealCeremony>,
+    templates: Vec<MarketTemplate>,
+    seasons: Vec<Season>,
+    season_markets: Vec<SeasonMarket>,
+    carry_overs: Vec<CarryOver>,
+    link_codes: Vec<LinkCode>,
+    audit_log: Vec<AuditEntry>,
+}
+
+struct SeasonMarket {
+    market_id: Uuid,
+    season_id: Uuid,
+    position: usize,
+}
+
+struct CarryOver {
+    user_id: Uuid,
+    market_id: Uuid,
+    amount: i64,
+}
+
+impl InMemoryDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, State> {
+        // A panic elsewhere can't leave the vectors half-written, so keep going
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, State> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Same messages SQLite gives, so callers see identical errors
+
+fn unique(constraint: &str) -> DbError {
+    DbError::Internal(format!("UNIQUE constraint failed: {}", constraint))
+}
+
+fn foreign_key() -> DbError {
+    DbError::Internal("FOREIGN KEY constraint failed".to_string())
+}
+
+impl State {
+    fn check_market(&self, id: Uuid) -> DbResult<()> {
+        match self.markets.iter().any(|m| m.id == id) {
+            true => Ok(()),
+            false => Err(foreign_key()),
+        }
+    }
+
+    fn check_user(&self, id: Uuid) -> DbResult<()> {
+        match self.users.iter().any(|u| u.id == id) {
+            true => Ok(()),
+            false => Err(foreign_key()),
+        }
+    }
+
+    fn check_player(&self, id: Uuid) -> DbResult<()> {
+        match self.players.iter().any(|p| p.id == id) {
+            true => Ok(()),
+            false => Err(foreign_key()),
+        }
+    }
+
+    fn user_mut(&mut self, id: Uuid) -> Option<&mut User> {
+        self.users.iter_mut().find(|u| u.id == id)
+    }
+
+    fn bet_mut(&mut self, id: Uuid) -> Option<&mut Bet> {
+        self.bets.iter_mut().find(|b| b.id == id)
+    }
+
+    /// Every market a device has a seat in, with the seat
+    fn device_markets(&self, device_id: &str) -> Vec<(Market, User)> {
+        self.users
+            .iter()
+            .filter(|u| u.device_id == device_id)
+            .filter_map(|user| {
+                self.markets
+                    .iter()
+                    .find(|m| m.id == user.market_id)
+                    .map(|market| (market.clone(), user.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl Database for InMemoryDatabase {
+    async fn create_market(&self, market: Market) -> DbResult<Market> {
+        let mut state = self.write();
+        if state.markets.iter().any(|m| m.id == market.id) {
+            return Err(unique("markets.id"));
+        }
+        if state
+            .markets
+            .iter()
+            .any(|m| m.invite_code == market.invite_code)
+        {
+            return Err(unique("markets.invite_code"));
+        }
+
+        state.markets.push(market.clone());
+        Ok(market)
+    }
+
+    async fn get_market(&self, id: Uuid) -> DbResult<Market> {
+        self.read()
+            .markets
+            .iter()
+            .find(|m| m.id == id)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Market not found".to_string()))
+    }
+
+    async fn get_market_by_invite_code(&self, code: &str) -> DbResult<Market> {
+        self.read()
+            .markets
+            .iter()
+            .find(|m| m.invite_code == code)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Market not found".to_string()))
+    }
+
+    async fn update_market_status(&self, id: Uuid, status: MarketStatus) -> DbResult<()> {
+        if let Some(market) = self.write().markets.iter_mut().find(|m| m.id == id) {
+            market.status = status;
+        }
+        Ok(())
+    }
+
+    async fn delete_market(&self, id: Uuid) -> DbResult<()> {
+        let mut state = self.write();
+
+        // Same order as SQLite: wagers -> bets (with subjects) -> reveal
+        // ceremony -> spectators -> webhooks (with deliveries) -> season
+        // links -> users -> market
+        let bet_ids: Vec<Uuid> = state
+            .bets
+            .iter()
+            .filter(|b| b.market_id == id)
+            .map(|b| b.id)
+            .collect();
+        state.wagers.retain(|w| !bet_ids.contains(&w.bet_id));
+        state.bets.retain(|b| b.market_id != id);
+        state.reveal_ceremonies.retain(|c| c.market_id != id);
+        state.spectators.retain(|s| s.market_id != id);
+        let webhook_ids: Vec<Uuid> = state
+            .webhooks
+            .iter()
+            .filter(|w| w.market_id == id)
+            .map(|w| w.id)
+            .collect();
+        state
+            .webhook_deliveries
+            .retain(|d| !webhook_ids.contains(&d.webhook_id));
+        state.webhooks.retain(|w| w.market_id != id);
+        state.carry_overs.retain(|c| c.market_id != id);
+        state.season_markets.retain(|sm| sm.market_id != id);
+        state.users.retain(|u| u.market_id != id);
+        state.markets.retain(|m| m.id != id);
+
+        Ok(())
+    }
+
+    async fn create_user(&self, user: User) -> DbResult<User> {
+        let mut state = self.write();
+        if state.users.iter().any(|u| u.id == user.id) {
+            return Err(unique("users.id"));
+        }
+        if state
+            .users
+            .iter()
+            .any(|u| u.market_id == user.market_id && u.device_id == user.device_id)
+        {
+            return Err(unique("users.market_id, users.device_id"));
+        }
+        state.check_market(user.market_id)?;
+        if let Some(player_id) = user.player_id {
+            state.check_player(player_id)?;
+        }
+
+        state.users.push(user.clone());
+        Ok(user)
+    }
+
+    async fn get_user(&self, id: Uuid) -> DbResult<User> {
+        self.read()
+            .users
+            .iter()
+            .find(|u| u.id == id)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("User not found".to_string()))
+    }
+
+    async fn get_user_by_device_id(&self, market_id: Uuid, device_id: &str) -> DbResult<User> {
+        self.read()
+            .users
+            .iter()
+            .find(|u| u.market_id == market_id && u.device_id == device_id)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("User not found".to_string()))
+    }
+
+    async fn get_users_in_market(&self, market_id: Uuid) -> DbResult<Vec<User>> {
+        Ok(self
+            .read()
+            .users
+            .iter()
+            .filter(|u| u.market_id == market_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn update_user_balance(&self, user_id: Uuid, new_balance: i64) -> DbResult<()> {
+        if let Some(user) = self.write().user_mut(user_id) {
+            user.balance = new_balance;
+        }
+        Ok(())
+    }
+
+    async fn update_user_status(&self, user_id: Uuid, status: MembershipStatus) -> DbResult<()> {
+        if let Some(user) = self.write().user_mut(user_id) {
+            user.status = status;
+        }
+        Ok(())
+    }
+
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        display_name: &str,
+        avatar: &str,
+    ) -> DbResult<()> {
+        if let Some(user) = self.write().user_mut(user_id) {
+            user.display_name = display_name.to_string();
+            user.avatar = avatar.to_string();
+        }
+        Ok(())
+    }
+
+    async fn update_user_device(&self, user_id: Uuid, device_id: &str) -> DbResult<()> {
+        let mut state = self.write();
+        let Some(market_id) = state
+            .users
+            .iter()
+            .find(|u| u.id == user_id)
+            .map(|u| u.market_id)
+        else {
+            return Ok(());
+        };
+        if state
+            .users
+            .iter()
+            .any(|u| u.id != user_id && u.market_id == market_id && u.device_id == device_id)
+        {
+            return Err(unique("users.market_id, users.device_id"));
+        }
+
+        if let Some(user) = state.user_mut(user_id) {
+            user.device_id = device_id.to_string();
+        }
+        Ok(())
+    }
+
+    async fn get_markets_by_device_id(&self, device_id: &str) -> DbResult<Vec<(Market, User)>> {
+        let mut markets = self.read().device_markets(device_id);
+        markets.sort_by_key(|(_, user)| std::cmp::Reverse(user.joined_at));
+        markets.truncate(10);
+        Ok(markets)
+    }
+
+    async fn list_markets_by_device_id(
+        &self,
+        device_id: &str,
+        query: &DeviceMarketQuery,
+    ) -> DbResult<Page<(Market, User)>> {
+        query.apply(self.read().device_markets(device_id))
+    }
+
+    async fn create_player(&self, player: Player) -> DbResult<Player> {
+        let mut state = self.write();
+        if state.players.iter().any(|p| p.id == player.id) {
+            return Err(unique("players.id"));
+        }
+
+        state.players.push(player.clone());
+        Ok(player)
+    }
+
+    async fn get_player(&self, id: Uuid) -> DbResult<Player> {
+        self.read()
+            .players
+            .iter()
+            .find(|p| p.id == id)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Player not found".to_string()))
+    }
+
+    async fn update_user_player(&self, user_id: Uuid, player_id: Uuid) -> DbResult<()> {
+        let mut state = self.write();
+        state.check_player(player_id)?;
+        if let Some(user) = state.user_mut(user_id) {
+            user.player_id = Some(player_id);
+        }
+        Ok(())
+    }
+
+    async fn get_users_by_player(&self, player_id: Uuid) -> DbResult<Vec<User>> {
+        let mut users: Vec<User> = self
+            .read()
+            .users
+            .iter()
+            .filter(|u| u.player_id == Some(player_id))
+            .cloned()
+            .collect();
+        users.sort_by_key(|u| u.joined_at);
+        Ok(users)
+    }
+
+    async fn create_template(&self, template: MarketTemplate) -> DbResult<MarketTemplate> {
+        let mut state = self.write();
+        if state.templates.iter().any(|t| t.id == template.id) {
+            return Err(unique("market_templates.id"));
+        }
+
+        state.templates.push(template.clone());
+        Ok(template)
+    }
+
+    async fn get_template(&self, id: Uuid) -> DbResult<MarketTemplate> {
+        self.read()
+            .templates
+            .iter()
+            .find(|t| t.id == id)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Template not found".to_string()))
+    }
+
+    async fn create_season(&self, season: Season) -> DbResult<Season> {
+        let mut state = self.write();
+        if state.seasons.iter().any(|s| s.id == season.id) {
+            return Err(unique("seasons.id"));
+        }
+
+        state.seasons.push(season.clone());
+        Ok(season)
+    }
+
+    async fn get_season(&self, id: Uuid) -> DbResult<Season> {
+        self.read()
+            .seasons
+            .iter()
+            .find(|s| s.id == id)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Season not found".to_string()))
+    }
+
+    async fn add_season_market(
+        &self,
+        season_id: Uuid,
+        market_id: Uuid,
+        position: usize,
+    ) -> DbResult<()> {
+        let mut state = self.write();
+        if state
+            .season_markets
+            .iter()
+            .any(|sm| sm.market_id == market_id)
+        {
+            return Err(unique("season_markets.market_id"));
+        }
+        state.check_market(market_id)?;
+        if !state.seasons.iter().any(|s| s.id == season_id) {
+            return Err(foreign_key());
+        }
+
+        state.season_markets.push(SeasonMarket {
+            market_id,
+            season_id,
+            position,
+        });
+        Ok(())
+    }
+
+    async fn get_season_markets(&self, season_id: Uuid) -> DbResult<Vec<Market>> {
+        let state = self.read();
+        let mut links: Vec<&SeasonMarket> = state
+            .season_markets
+            .iter()
+            .filter(|sm| sm.season_id == season_id)
+            .collect();
+        links.sort_by_key(|sm| sm.position);
+
+        Ok(links
+            .into_iter()
+            .filter_map(|sm| state.markets.iter().find(|m| m.id == sm.market_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_season_for_market(&self, market_id: Uuid) -> DbResult<Season> {
+        let state = self.read();
+        state
+            .season_markets
+            .iter()
+            .find(|sm| sm.market_id == market_id)
+            .and_then(|sm| state.seasons.iter().find(|s| s.id == sm.season_id))
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Season not found".to_string()))
+    }
+
+    async fn create_carry_over(&self, market_id: Uuid, user_id: Uuid, amount: i64) -> DbResult<()> {
+        let mut state = self.write();
+        if state.carry_overs.iter().any(|c| c.user_id == user_id) {
+            return Err(unique("season_carry_overs.user_id"));
+        }
+        state.check_user(user_id)?;
+        state.check_market(market_id)?;
+
+        state.carry_overs.push(CarryOver {
+            user_id,
+            market_id,
+            amount,
+        });
+        Ok(())
+    }
+
+    async fn get_carry_overs(&self, season_id: Uuid) -> DbResult<Vec<(Uuid, i64)>> {
+        let state = self.read();
+        Ok(state
+            .carry_overs
+            .iter()
+            .filter(|c| {
+                state
+                    .season_markets
+                    .iter()
+                    .any(|sm| sm.market_id == c.market_id && sm.season_id == season_id)
+            })
+            .map(|c| (c.user_id, c.amount))
+            .collect())
+    }
+
+    async fn create_link_code(&self, link: LinkCode) -> DbResult<LinkCode> {
+        let mut state = self.write();
+        if state.link_codes.iter().any(|l| l.code == link.code) {
+            return Err(unique("link_codes.code"));
+        }
+
+        state.link_codes.push(link.clone());
+        Ok(link)
+    }
+
+    async fn get_link_code(&self, code: &str) -> DbResult<LinkCode> {
+        self.read()
+            .link_codes
+            .iter()
+            .find(|l| l.code == code)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Link code not found".to_string()))
+    }
+
+    async fn redeem_link_code(
+        &self,
+        code: &str,
+        device_id: &str,
+        redeemed_at: DateTime<Utc>,
+    ) -> DbResult<bool> {
+        let mut state = self.write();
+        match state
+            .link_codes
+            .iter_mut()
+            .find(|l| l.code == code && l.redeemed_at.is_none())
+        {
+            Some(link) => {
+                link.redeemed_at = Some(redeemed_at);
+                link.redeemed_by = Some(device_id.to_string());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn create_audit_entry(&self, entry: AuditEntry) -> DbResult<AuditEntry> {
+        let mut state = self.write();
+        if state.audit_log.iter().any(|e| e.id == entry.id) {
+            return Err(unique("audit_log.id"));
+        }
+
+        state.audit_log.push(entry.clone());
+        Ok(entry)
+    }
+
+    async fn count_audit_entries(
+        &self,
+        device_id: Option<&str>,
+        action: &str,
+        since: DateTime<Utc>,
+    ) -> DbResult<i64> {
+        Ok(self
+            .read()
+            .audit_log
+            .iter()
+            .filter(|e| {
+                device_id.is_none_or(|id| e.device_id == id)
+                    && e.action == action
+                    && e.created_at >= since
+            })
+            .count() as i64)
+    }
+
+    async fn create_spectator(&self, spectator: Spectator) -> DbResult<Spectator> {
+        let mut state = self.write();
+        if state.spectators.iter().any(|s| s.id == spectator.id) {
+            return Err(unique("spectators.id"));
+        }
+        if state.spectators.iter().any(|s| s.token == spectator.token) {
+            return Err(unique("spectators.token"));
+        }
+        state.check_market(spectator.market_id)?;
+
+        state.spectators.push(spectator.clone());
+        Ok(spectator)
+    }
+
+    async fn get_spectator_by_token(&self, token: &str) -> DbResult<Spectator> {
+        self.read()
+            .spectators
+            .iter()
+            .find(|s| s.token == token)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Spectator not found".to_string()))
+    }
+
+    async fn get_spectators_in_market(&self, market_id: Uuid) -> DbResult<Vec<Spectator>> {
+        Ok(self
+            .read()
+            .spectators
+            .iter()
+            .filter(|s| s.market_id == market_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn create_webhook(&self, webhook: Webhook) -> DbResult<Webhook> {
+        let mut state = self.write();
+        if state.webhooks.iter().any(|w| w.id == webhook.id) {
+            return Err(unique("webhooks.id"));
+        }
+        state.check_market(webhook.market_id)?;
+
+        state.webhooks.push(webhook.clone());
+        Ok(webhook)
+    }
+
+    async fn get_webhook(&self, id: Uuid) -> DbResult<Webhook> {
+        self.read()
+            .webhooks
+            .iter()
+            .find(|w| w.id == id)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Webhook not found".to_string()))
+    }
+
+    async fn get_webhooks_in_market(&self, market_id: Uuid) -> DbResult<Vec<Webhook>> {
+        let mut webhooks: Vec<Webhook> = self
+            .read()
+            .webhooks
+            .iter()
+            .filter(|w| w.market_id == market_id)
+            .cloned()
+            .collect();
+        webhooks.sort_by_key(|w| w.created_at);
+        Ok(webhooks)
+    }
+
+    async fn delete_webhook(&self, id: Uuid) -> DbResult<()> {
+        let mut state = self.write();
+        state.webhook_deliveries.retain(|d| d.webhook_id != id);
+        state.webhooks.retain(|w| w.id != id);
+        Ok(())
+    }
+
+    async fn create_webhook_delivery(
+        &self,
+        delivery: WebhookDelivery,
+    ) -> DbResult<WebhookDelivery> {
+        let mut state = self.write();
+        if state.webhook_deliveries.iter().any(|d| d.id == delivery.id) {
+            return Err(unique("webhook_deliveries.id"));
+        }
+        if !state.webhooks.iter().any(|w| w.id == delivery.webhook_id) {
+            return Err(foreign_key());
+        }
+
+        state.webhook_deliveries.push(delivery.clone());
+        Ok(delivery)
+    }
+
+    async fn get_webhook_deliveries(&self, webhook_id: Uuid) -> DbResult<Vec<WebhookDelivery>> {
+        let mut deliveries: Vec<WebhookDelivery> = self
+            .read()
+            .webhook_deliveries
+            .iter()
+            .filter(|d| d.webhook_id == webhook_id)
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|d| d.created_at);
+        Ok(deliveries)
+    }
+
+    async fn create_bet(&self, bet: Bet) -> DbResult<Bet> {
+        let mut state = self.write();
+        if state.bets.iter().any(|b| b.id == bet.id) {
+            return Err(unique("bets.id"));
+        }
+        state.check_market(bet.market_id)?;
+        state.check_user(bet.created_by)?;
+        for subject_id in &bet.subject_user_ids {
+            state.check_user(*subject_id)?;
+        }
+
+        state.bets.push(bet.clone());
+        Ok(bet)
+    }
+
+    async fn get_bet(&self, id: Uuid) -> DbResult<Bet> {
+        self.read()
+            .bets
+            .iter()
+            .find(|b| b.id == id)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Bet not found".to_string()))
+    }
+
+    async fn get_bets_in_market(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        Ok(self
+            .read()
+            .bets
+            .iter()
+            .filter(|b| b.market_id == market_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_bets_for_user(
+        &self,
+        market_id: Uuid,
+        viewing_user_id: Uuid,
+    ) -> DbResult<Vec<BetView>> {
+        let bets = self.get_bets_in_market(market_id).await?;
+        Ok(bets
+            .iter()
+            .map(|bet| bet.to_view(viewing_user_id))
+            .collect())
+    }
+
+    async fn get_pending_bets(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        Ok(self
+            .read()
+            .bets
+            .iter()
+            .filter(|b| b.market_id == market_id && b.status == BetStatus::Pending)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_market_contents(&self, market_id: Uuid) -> DbResult<MarketContents> {
+        // Under one lock, so the three reads agree with each other
+        let state = self.read();
+        let market = state
+            .markets
+            .iter()
+            .find(|m| m.id == market_id)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Market not found".to_string()))?;
+
+        Ok(MarketContents {
+            market,
+            users: state
+                .users
+                .iter()
+                .filter(|u| u.market_id == market_id)
+                .cloned()
+                .collect(),
+            bets: state
+                .bets
+                .iter()
+                .filter(|b| b.market_id == market_id)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    async fn update_bet_status(&self, bet_id: Uuid, status: BetStatus) -> DbResult<()> {
+        if let Some(bet) = self.write().bet_mut(bet_id) {
+            bet.status = status;
+            bet.resolved_at = match status {
+                BetStatus::ResolvedYes | BetStatus::ResolvedNo => Some(Utc::now()),
+                _ => None,
+            };
+        }
+        Ok(())
+    }
+
+    async fn update_bet_pools(&self, bet_id: Uuid, yes_pool: i64, no_pool: i64) -> DbResult<()> {
+        if let Some(bet) = self.write().bet_mut(bet_id) {
+            bet.yes_pool = yes_pool;
+            bet.no_pool = no_pool;
+        }
+        Ok(())
+    }
+
+    async fn create_wager(&self, wager: Wager) -> DbResult<Wager> {
+        let mut state = self.write();
+        if state.wagers.iter().any(|w| w.id == wager.id) {
+            return Err(unique("wagers.id"));
+        }
+        if !state.bets.iter().any(|b| b.id == wager.bet_id) {
+            return Err(foreign_key());
+        }
+        state.check_user(wager.user_id)?;
+
+        state.wagers.push(wager.clone());
+        Ok(wager)
+    }
+
+    /// Apply a wager under the write lock, so concurrent wagers can't
+    /// overdraw a balance or build on stale pools
+    async fn apply_wager(&self, wager: Wager) -> DbResult<Wager> {
+        let mut state = self.write();
+        if state.wagers.iter().any(|w| w.id == wager.id) {
+            return Err(unique("wagers.id"));
+        }
+        let bet = state
+            .bets
+            .iter()
+            .find(|b| b.id == wager.bet_id)
+            .ok_or_else(|| DbError::NotFound("Bet not found".to_string()))?;
+        let balance = state
+            .users
+            .iter()
+            .find(|u| u.id == wager.user_id)
+            .map(|u| u.balance)
+            .ok_or_else(|| DbError::NotFound("User not found".to_string()))?;
+
+        // Another request may have resolved the bet or spent the coins first
+        if bet.status != BetStatus::Active {
+            return Err(DbError::Constraint(RuleError::BetNotActive.to_string()));
+        }
+        if balance < wager.amount {
+            return Err(DbError::Constraint(
+                RuleError::InsufficientBalance {
+                    needed: wager.amount,
+                    available: balance,
+                }
+                .to_string(),
+            ));
+        }
+
+        let (yes_pool_after, no_pool_after, _) = parimutuel::calculate_potential_payout(
+            bet.yes_pool,
+            bet.no_pool,
+            wager.side,
+            wager.amount,
+        );
+        let wager = Wager {
+            yes_pool_after,
+            no_pool_after,
+            probability_after: parimutuel::calculate_probability(yes_pool_after, no_pool_after),
+            ..wager
+        };
+
+        if let Some(bet) = state.bet_mut(wager.bet_id) {
+            bet.yes_pool = wager.yes_pool_after;
+            bet.no_pool = wager.no_pool_after;
+        }
+        if let Some(user) = state.user_mut(wager.user_id) {
+            user.balance -= wager.amount;
+        }
+        state.wagers.push(wager.clone());
+        Ok(wager)
+    }
+
+    async fn get_wagers_for_bet(&self, bet_id: Uuid) -> DbResult<Vec<Wager>> {
+        let mut wagers: Vec<Wager> = self
+            .read()
+            .wagers
+            .iter()
+            .filter(|w| w.bet_id == bet_id)
+            .cloned()
+            .collect();
+        wagers.sort_by_key(|w| w.placed_at);
+        Ok(wagers)
+    }
+
+    async fn apply_removal(&self, removal: &Removal) -> DbResult<()> {
+        let mut state = self.write();
+
+        for refund in &removal.refunds {
+            if let Some(wager) = state.wagers.iter_mut().find(|w| w.id == refund.id) {
+                wager.refunded = true;
+            }
+            if let Some(user) = state.user_mut(refund.user_id) {
+                user.balance += refund.amount;
+            }
+            if removal.voided_bet_ids.contains(&refund.bet_id) {
+                continue;
+            }
+            if let Some(bet) = state.bet_mut(refund.bet_id) {
+                match refund.side {
+                    Side::Yes => bet.yes_pool -= refund.amount,
+                    Side::No => bet.no_pool -= refund.amount,
+                }
+            }
+        }
+        for bet_id in &removal.voided_bet_ids {
+            if let Some(bet) = state.bet_mut(*bet_id) {
+                bet.status = BetStatus::Void;
+            }
+        }
+        if let Some(user) = state.user_mut(removal.user_id) {
+            user.status = removal.status;
+        }
+        Ok(())
+    }
+
+    async fn get_wagers_for_user(&self, user_id: Uuid) -> DbResult<Vec<Wager>> {
+        let mut wagers: Vec<Wager> = self
+            .read()
+            .wagers
+            .iter()
+            .filter(|w| w.user_id == user_id)
+            .cloned()
+            .collect();
+        wagers.sort_by_key(|w| w.placed_at);
+        Ok(wagers)
+    }
+
+    async fn get_bets_about_user(&self, user_id: Uuid) -> DbResult<Vec<Bet>> {
+        let state = self.read();
+        let market_id = state
+            .users
+            .iter()
+            .find(|u| u.id == user_id)
+            .map(|u| u.market_id);
+
+        Ok(state
+            .bets
+            .iter()
+            .filter(|b| {
+                b.subject_user_ids.contains(&user_id)
+                    || (b.about_everyone && Some(b.market_id) == market_id)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn create_reveal_ceremony(&self, ceremony: RevealCeremony) -> DbResult<RevealCeremony> {
+        let mut state = self.write();
+        if state
+            .reveal_ceremonies
+            .iter()
+            .any(|c| c.market_id == ceremony.market_id)
+        {
+            return Err(unique("reveal_ceremonies.market_id"));
+        }
+        state.check_market(ceremony.market_id)?;
+
+        state.reveal_ceremonies.push(ceremony.clone());
+        Ok(ceremony)
+    }
+
+    async fn get_reveal_ceremony(&self, market_id: Uuid) -> DbResult<RevealCeremony> {
+        self.read()
+            .reveal_ceremonies
+            .iter()
+            .find(|c| c.market_id == market_id)
+            .cloned()
+            .ok_or_else(|| DbError::NotFound("Reveal ceremony not found".to_string()))
+    }
+
+    async fn update_reveal_progress(
+        &self,
+        market_id: Uuid,
+        revealed_count: usize,
+        completed_at: Option<DateTime<Utc>>,
+    ) -> DbResult<()> {
+        if let Some(ceremony) = self
+            .write()
+            .reveal_ceremonies
+            .iter_mut()
+            .find(|c| c.market_id == market_id)
+        {
+            ceremony.revealed_count = revealed_count;
+            ceremony.completed_at = completed_at;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,550 @@
+/// Database wrapper that times every operation
+///
+/// Each call's latency goes into `cazino_db_query_duration_seconds`, labelled
+/// with the `Database` method, whichever backend is underneath. The server
+/// wraps its backend in one of these; see `metrics`.
+use crate::db::query::{BetQuery, DeviceMarketQuery, Page, UserQuery, WagerQuery};
+use crate::db::r#trait::{Database, DbResult};
+use crate::domain::archive::MarketArchive;
+use crate::domain::models::{
+    AuditEntry, Bet, BetStatus, BetView, LinkCode, Market, MarketContents, MarketStatus,
+    MarketTemplate, MembershipStatus, Player, Removal, RevealCeremony, Season, Spectator, User,
+    Wager, Webhook, WebhookDelivery,
+};
+use crate::metrics::Metrics;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct MeteredDatabase<D: Database> {
+    inner: D,
+    metrics: Arc<Metrics>,
+}
+
+impl<D: Database> MeteredDatabase<D> {
+    pub fn new(inner: D, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+
+    async fn time<T>(
+        &self,
+        operation: &'static str,
+        query: impl Future<Output = DbResult<T>>,
+    ) -> DbResult<T> {
+        let started = Instant::now();
+        let result = query.await;
+        self.metrics
+            .db_query_duration
+            .observe(&[operation], started.elapsed().as_secs_f64());
+        result
+    }
+}
+
+#[async_trait]
+impl<D: Database> Database for MeteredDatabase<D> {
+    // ===== Market Operations =====
+
+    async fn create_market(&self, market: Market) -> DbResult<Market> {
+        self.time("create_market", self.inner.create_market(market))
+            .await
+    }
+
+    async fn get_market(&self, id: Uuid) -> DbResult<Market> {
+        self.time("get_market", self.inner.get_market(id)).await
+    }
+
+    async fn get_market_by_invite_code(&self, code: &str) -> DbResult<Market> {
+        self.time(
+            "get_market_by_invite_code",
+            self.inner.get_market_by_invite_code(code),
+        )
+        .await
+    }
+
+    async fn update_market_status(&self, id: Uuid, status: MarketStatus) -> DbResult<()> {
+        self.time(
+            "update_market_status",
+            self.inner.update_market_status(id, status),
+        )
+        .await
+    }
+
+    async fn delete_market(&self, id: Uuid) -> DbResult<()> {
+        self.time("delete_market", self.inner.delete_market(id))
+            .await
+    }
+
+    async fn import_archive(&self, archive: &MarketArchive) -> DbResult<()> {
+        self.time("import_archive", self.inner.import_archive(archive))
+            .await
+    }
+
+    // ===== User Operations =====
+
+    async fn create_user(&self, user: User) -> DbResult<User> {
+        self.time("create_user", self.inner.create_user(user)).await
+    }
+
+    async fn get_user(&self, id: Uuid) -> DbResult<User> {
+        self.time("get_user", self.inner.get_user(id)).await
+    }
+
+    async fn get_user_by_device_id(&self, market_id: Uuid, device_id: &str) -> DbResult<User> {
+        self.time(
+            "get_user_by_device_id",
+            self.inner.get_user_by_device_id(market_id, device_id),
+        )
+        .await
+    }
+
+    async fn get_users_in_market(&self, market_id: Uuid) -> DbResult<Vec<User>> {
+        self.time(
+            "get_users_in_market",
+            self.inner.get_users_in_market(market_id),
+        )
+        .await
+    }
+
+    async fn list_users_in_market(
+        &self,
+        market_id: Uuid,
+        query: &UserQuery,
+    ) -> DbResult<Page<User>> {
+        self.time(
+            "list_users_in_market",
+            self.inner.list_users_in_market(market_id, query),
+        )
+        .await
+    }
+
+    async fn update_user_balance(&self, user_id: Uuid, new_balance: i64) -> DbResult<()> {
+        self.time(
+            "update_user_balance",
+            self.inner.update_user_balance(user_id, new_balance),
+        )
+        .await
+    }
+
+    async fn update_user_status(&self, user_id: Uuid, status: MembershipStatus) -> DbResult<()> {
+        self.time(
+            "update_user_status",
+            self.inner.update_user_status(user_id, status),
+        )
+        .await
+    }
+
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        display_name: &str,
+        avatar: &str,
+    ) -> DbResult<()> {
+        self.time(
+            "update_user_profile",
+            self.inner
+                .update_user_profile(user_id, display_name, avatar),
+        )
+        .await
+    }
+
+    async fn update_user_device(&self, user_id: Uuid, device_id: &str) -> DbResult<()> {
+        self.time(
+            "update_user_device",
+            self.inner.update_user_device(user_id, device_id),
+        )
+        .await
+    }
+
+    async fn get_markets_by_device_id(&self, device_id: &str) -> DbResult<Vec<(Market, User)>> {
+        self.time(
+            "get_markets_by_device_id",
+            self.inner.get_markets_by_device_id(device_id),
+        )
+        .await
+    }
+
+    async fn list_markets_by_device_id(
+        &self,
+        device_id: &str,
+        query: &DeviceMarketQuery,
+    ) -> DbResult<Page<(Market, User)>> {
+        self.time(
+            "list_markets_by_device_id",
+            self.inner.list_markets_by_device_id(device_id, query),
+        )
+        .await
+    }
+
+    // ===== Player Operations (identity across markets) =====
+
+    async fn create_player(&self, player: Player) -> DbResult<Player> {
+        self.time("create_player", self.inner.create_player(player))
+            .await
+    }
+
+    async fn get_player(&self, id: Uuid) -> DbResult<Player> {
+        self.time("get_player", self.inner.get_player(id)).await
+    }
+
+    async fn update_user_player(&self, user_id: Uuid, player_id: Uuid) -> DbResult<()> {
+        self.time(
+            "update_user_player",
+            self.inner.update_user_player(user_id, player_id),
+        )
+        .await
+    }
+
+    async fn get_users_by_player(&self, player_id: Uuid) -> DbResult<Vec<User>> {
+        self.time(
+            "get_users_by_player",
+            self.inner.get_users_by_player(player_id),
+        )
+        .await
+    }
+
+    // ===== Template Operations =====
+
+    async fn create_template(&self, template: MarketTemplate) -> DbResult<MarketTemplate> {
+        self.time("create_template", self.inner.create_template(template))
+            .await
+    }
+
+    async fn get_template(&self, id: Uuid) -> DbResult<MarketTemplate> {
+        self.time("get_template", self.inner.get_template(id)).await
+    }
+
+    // ===== Season Operations =====
+
+    async fn create_season(&self, season: Season) -> DbResult<Season> {
+        self.time("create_season", self.inner.create_season(season))
+            .await
+    }
+
+    async fn get_season(&self, id: Uuid) -> DbResult<Season> {
+        self.time("get_season", self.inner.get_season(id)).await
+    }
+
+    async fn add_season_market(
+        &self,
+        season_id: Uuid,
+        market_id: Uuid,
+        position: usize,
+    ) -> DbResult<()> {
+        self.time(
+            "add_season_market",
+            self.inner.add_season_market(season_id, market_id, position),
+        )
+        .await
+    }
+
+    async fn get_season_markets(&self, season_id: Uuid) -> DbResult<Vec<Market>> {
+        self.time(
+            "get_season_markets",
+            self.inner.get_season_markets(season_id),
+        )
+        .await
+    }
+
+    async fn get_season_for_market(&self, market_id: Uuid) -> DbResult<Season> {
+        self.time(
+            "get_season_for_market",
+            self.inner.get_season_for_market(market_id),
+        )
+        .await
+    }
+
+    async fn create_carry_over(&self, market_id: Uuid, user_id: Uuid, amount: i64) -> DbResult<()> {
+        self.time(
+            "create_carry_over",
+            self.inner.create_carry_over(market_id, user_id, amount),
+        )
+        .await
+    }
+
+    async fn get_carry_overs(&self, season_id: Uuid) -> DbResult<Vec<(Uuid, i64)>> {
+        self.time("get_carry_overs", self.inner.get_carry_overs(season_id))
+            .await
+    }
+
+    // ===== Identity Operations =====
+
+    async fn create_link_code(&self, link: LinkCode) -> DbResult<LinkCode> {
+        self.time("create_link_code", self.inner.create_link_code(link))
+            .await
+    }
+
+    async fn get_link_code(&self, code: &str) -> DbResult<LinkCode> {
+        self.time("get_link_code", self.inner.get_link_code(code))
+            .await
+    }
+
+    async fn redeem_link_code(
+        &self,
+        code: &str,
+        device_id: &str,
+        redeemed_at: DateTime<Utc>,
+    ) -> DbResult<bool> {
+        self.time(
+            "redeem_link_code",
+            self.inner.redeem_link_code(code, device_id, redeemed_at),
+        )
+        .await
+    }
+
+    async fn create_audit_entry(&self, entry: AuditEntry) -> DbResult<AuditEntry> {
+        self.time("create_audit_entry", self.inner.create_audit_entry(entry))
+            .await
+    }
+
+    async fn count_audit_entries(
+        &self,
+        device_id: Option<&str>,
+        action: &str,
+        since: DateTime<Utc>,
+    ) -> DbResult<i64> {
+        self.time(
+            "count_audit_entries",
+            self.inner.count_audit_entries(device_id, action, since),
+        )
+        .await
+    }
+
+    // ===== Spectator Operations =====
+
+    async fn create_spectator(&self, spectator: Spectator) -> DbResult<Spectator> {
+        self.time("create_spectator", self.inner.create_spectator(spectator))
+            .await
+    }
+
+    async fn get_spectator_by_token(&self, token: &str) -> DbResult<Spectator> {
+        self.time(
+            "get_spectator_by_token",
+            self.inner.get_spectator_by_token(token),
+        )
+        .await
+    }
+
+    async fn get_spectators_in_market(&self, market_id: Uuid) -> DbResult<Vec<Spectator>> {
+        self.time(
+            "get_spectators_in_market",
+            self.inner.get_spectators_in_market(market_id),
+        )
+        .await
+    }
+
+    // ===== Webhook Operations =====
+
+    async fn create_webhook(&self, webhook: Webhook) -> DbResult<Webhook> {
+        self.time("create_webhook", self.inner.create_webhook(webhook))
+            .await
+    }
+
+    async fn get_webhook(&self, id: Uuid) -> DbResult<Webhook> {
+        self.time("get_webhook", self.inner.get_webhook(id)).await
+    }
+
+    async fn get_webhooks_in_market(&self, market_id: Uuid) -> DbResult<Vec<Webhook>> {
+        self.time(
+            "get_webhooks_in_market",
+            self.inner.get_webhooks_in_market(market_id),
+        )
+        .await
+    }
+
+    async fn delete_webhook(&self, id: Uuid) -> DbResult<()> {
+        self.time("delete_webhook", self.inner.delete_webhook(id))
+            .await
+    }
+
+    async fn create_webhook_delivery(
+        &self,
+        delivery: WebhookDelivery,
+    ) -> DbResult<WebhookDelivery> {
+        self.time(
+            "create_webhook_delivery",
+            self.inner.create_webhook_delivery(delivery),
+        )
+        .await
+    }
+
+    async fn get_webhook_deliveries(&self, webhook_id: Uuid) -> DbResult<Vec<WebhookDelivery>> {
+        self.time(
+            "get_webhook_deliveries",
+            self.inner.get_webhook_deliveries(webhook_id),
+        )
+        .await
+    }
+
+    // ===== Bet Operations =====
+
+    async fn create_bet(&self, bet: Bet) -> DbResult<Bet> {
+        self.time("create_bet", self.inner.create_bet(bet)).await
+    }
+
+    async fn get_bet(&self, id: Uuid) -> DbResult<Bet> {
+        self.time("get_bet", self.inner.get_bet(id)).await
+    }
+
+    async fn get_bets_in_market(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        self.time(
+            "get_bets_in_market",
+            self.inner.get_bets_in_market(market_id),
+        )
+        .await
+    }
+
+    async fn list_bets_in_market(&self, market_id: Uuid, query: &BetQuery) -> DbResult<Page<Bet>> {
+        self.time(
+            "list_bets_in_market",
+            self.inner.list_bets_in_market(market_id, query),
+        )
+        .await
+    }
+
+    async fn get_bets_for_user(
+        &self,
+        market_id: Uuid,
+        viewing_user_id: Uuid,
+    ) -> DbResult<Vec<BetView>> {
+        self.time(
+            "get_bets_for_user",
+            self.inner.get_bets_for_user(market_id, viewing_user_id),
+        )
+        .await
+    }
+
+    async fn get_pending_bets(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        self.time("get_pending_bets", self.inner.get_pending_bets(market_id))
+            .await
+    }
+
+    async fn get_market_contents(&self, market_id: Uuid) -> DbResult<MarketContents> {
+        self.time(
+            "get_market_contents",
+            self.inner.get_market_contents(market_id),
+        )
+        .await
+    }
+
+    async fn update_bet_status(&self, bet_id: Uuid, status: BetStatus) -> DbResult<()> {
+        self.time(
+            "update_bet_status",
+            self.inner.update_bet_status(bet_id, status),
+        )
+        .await
+    }
+
+    async fn update_bet_pools(&self, bet_id: Uuid, yes_pool: i64, no_pool: i64) -> DbResult<()> {
+        self.time(
+            "update_bet_pools",
+            self.inner.update_bet_pools(bet_id, yes_pool, no_pool),
+        )
+        .await
+    }
+
+    // ===== Wager Operations =====
+
+    async fn create_wager(&self, wager: Wager) -> DbResult<Wager> {
+        self.time("create_wager", self.inner.create_wager(wager))
+            .await
+    }
+
+    async fn apply_wager(&self, wager: Wager) -> DbResult<Wager> {
+        self.time("apply_wager", self.inner.apply_wager(wager))
+            .await
+    }
+
+    async fn get_wagers_for_bet(&self, bet_id: Uuid) -> DbResult<Vec<Wager>> {
+        self.time("get_wagers_for_bet", self.inner.get_wagers_for_bet(bet_id))
+            .await
+    }
+
+    async fn list_wagers_for_bet(&self, bet_id: Uuid, query: &WagerQuery) -> DbResult<Page<Wager>> {
+        self.time(
+            "list_wagers_for_bet",
+            self.inner.list_wagers_for_bet(bet_id, query),
+        )
+        .await
+    }
+
+    async fn apply_removal(&self, removal: &Removal) -> DbResult<()> {
+        self.time("apply_removal", self.inner.apply_removal(removal))
+            .await
+    }
+
+    async fn get_wagers_for_user(&self, user_id: Uuid) -> DbResult<Vec<Wager>> {
+        self.time(
+            "get_wagers_for_user",
+            self.inner.get_wagers_for_user(user_id),
+        )
+        .await
+    }
+
+    // ===== Reveal Operations (end of market) =====
+
+    async fn get_bets_about_user(&self, user_id: Uuid) -> DbResult<Vec<Bet>> {
+        self.time(
+            "get_bets_about_user",
+            self.inner.get_bets_about_user(user_id),
+        )
+        .await
+    }
+
+    async fn create_reveal_ceremony(&self, ceremony: RevealCeremony) -> DbResult<RevealCeremony> {
+        self.time(
+            "create_reveal_ceremony",
+            self.inner.create_reveal_ceremony(ceremony),
+        )
+        .await
+    }
+
+    async fn get_reveal_ceremony(&self, market_id: Uuid) -> DbResult<RevealCeremony> {
+        self.time(
+            "get_reveal_ceremony",
+            self.inner.get_reveal_ceremony(market_id),
+        )
+        .await
+    }
+
+    async fn update_reveal_progress(
+        &self,
+        market_id: Uuid,
+        revealed_count: usize,
+        completed_at: Option<DateTime<Utc>>,
+    ) -> DbResult<()> {
+        self.time(
+            "update_reveal_progress",
+            self.inner
+                .update_reveal_progress(market_id, revealed_count, completed_at),
+        )
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryDatabase;
+
+    #[tokio::test]
+    async fn test_operations_are_timed() {
+        let metrics = Arc::new(Metrics::default());
+        let db = MeteredDatabase::new(InMemoryDatabase::new(), metrics.clone());
+
+        assert!(db.get_market(Uuid::new_v4()).await.is_err());
+        db.get_users_in_market(Uuid::new_v4()).await.unwrap();
+        db.get_users_in_market(Uuid::new_v4()).await.unwrap();
+
+        let text = metrics.render();
+        assert!(
+            text.contains("cazino_db_query_duration_seconds_count{operation=\"get_market\"} 1\n")
+        );
+        assert!(text.contains(
+            "cazino_db_query_duration_seconds_count{operation=\"get_users_in_market\"} 2\n"
+        ));
+    }
+}
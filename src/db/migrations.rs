@@ -0,0 +1,298 @@
+/// Versioned schema migrations shared by every backend
+///
+/// The files in `migrations/` are the single source of truth for the schema.
+/// SQLite and Postgres apply them with sqlx's migrator (`sqlx::migrate!`),
+/// which records them in `_sqlx_migrations`. They're also embedded here in
+/// order for D1, which has no sqlx; `run_migrations` below applies them there
+/// and records them in `schema_migrations`.
+///
+/// The two version tables stay separate on purpose. sqlx's migrator only
+/// reads and writes `_sqlx_migrations`, with checksums of each file, and
+/// refuses to run if that table is missing rows or has unknown ones; D1 can't
+/// compute those checksums without sqlx. Both tables hold the same versions
+/// for the same files, and `migration status` reads whichever the backend
+/// keeps.
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashSet;
+
+/// One ordered migration file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+macro_rules! migration {
+    ($version:expr, $description:expr, $file:expr) => {
+        Migration {
+            version: $version,
+            description: $description,
+            sql: include_str!(concat!("../../migrations/", $file)),
+        }
+    };
+}
+
+/// Every SQLite migration, oldest first (local server and D1)
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "initial schema", "001_initial_schema.sql"),
+    migration!(2, "bet hide from subject", "002_bet_hide_from_subject.sql"),
+    migration!(3, "spectators", "003_spectators.sql"),
+    migration!(4, "bet subjects", "004_bet_subjects.sql"),
+    migration!(5, "reveal ceremonies", "005_reveal_ceremonies.sql"),
+    migration!(6, "membership status", "006_membership_status.sql"),
+    migration!(7, "link codes", "007_link_codes.sql"),
+    migration!(8, "players", "008_players.sql"),
+    migration!(9, "seasons", "009_seasons.sql"),
+    migration!(10, "market templates", "010_market_templates.sql"),
+    migration!(11, "webhooks", "011_webhooks.sql"),
+    migration!(12, "wager refunds", "012_wager_refunds.sql"),
+];
+
+/// The SQLite files, applied by sqlx's migrator (local server)
+#[cfg(feature = "sqlite")]
+pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations");
+
+/// Every PostgreSQL migration, oldest first
+///
+/// Postgres starts from the current schema with native types, so its
+/// versions count separately from the SQLite ones.
+#[cfg(feature = "postgres")]
+pub const POSTGRES_MIGRATIONS: &[Migration] = &[
+    migration!(1, "initial schema", "postgres/001_initial_schema.sql"),
+    migration!(2, "webhooks", "postgres/002_webhooks.sql"),
+    migration!(3, "wager refunds", "postgres/003_wager_refunds.sql"),
+];
+
+/// The PostgreSQL files, applied by sqlx's migrator
+#[cfg(feature = "postgres")]
+pub static POSTGRES_MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// Table recording which migrations D1 has applied
+pub const CREATE_SCHEMA_MIGRATIONS: &str = "CREATE TABLE IF NOT EXISTS schema_migrations (\
+     version INTEGER PRIMARY KEY, \
+     description TEXT NOT NULL, \
+     applied_at TEXT NOT NULL)";
+
+/// Records a migration as applied; binds version, description and applied_at
+#[allow(dead_code)] // Bound by D1 and the tests' runner, never by the server
+pub const INSERT_SCHEMA_MIGRATION: &str =
+    "INSERT INTO schema_migrations (version, description, applied_at) VALUES (?, ?, ?)";
+
+/// Versions already in a database from before versioned migrations
+///
+/// Those databases hold the `001` tables but no version table, so they're
+/// recorded at version 1 and everything after it runs. The old inline SQLite
+/// schema also had `bets.hide_from_subject`, which counts as version 2.
+pub fn baseline(has_hide_from_subject: bool) -> Vec<i64> {
+    if has_hide_from_subject {
+        vec![1, 2]
+    } else {
+        vec![1]
+    }
+}
+
+/// Where a migration stands in a particular database
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: &'static str,
+    pub applied_at: Option<String>,
+}
+
+/// Migrations not yet in `applied`, in order
+pub fn pending(migrations: &'static [Migration], applied: &[i64]) -> Vec<&'static Migration> {
+    let applied: HashSet<i64> = applied.iter().copied().collect();
+    migrations
+        .iter()
+        .filter(|m| !applied.contains(&m.version))
+        .collect()
+}
+
+/// Status of every migration given the `(version, applied_at)` rows recorded
+pub fn status(migrations: &'static [Migration], applied: &[(i64, String)]) -> Vec<MigrationStatus> {
+    migrations
+        .iter()
+        .map(|m| MigrationStatus {
+            version: m.version,
+            description: m.description,
+            applied_at: applied
+                .iter()
+                .find(|(version, _)| *version == m.version)
+                .map(|(_, at)| at.clone()),
+        })
+        .collect()
+}
+
+/// A SQLite database `run_migrations` can drive without sqlx
+///
+/// Implemented by the D1 backend, and by the integration tests over a plain
+/// SQLite pool so this runner is checked natively too.
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+pub trait MigrationTarget {
+    type Error;
+
+    /// The first column of every row `sql` returns, as text
+    async fn texts(&self, sql: &str) -> Result<Vec<String>, Self::Error>;
+
+    /// The first column of every row `sql` returns, as integers
+    async fn integers(&self, sql: &str) -> Result<Vec<i64>, Self::Error>;
+
+    /// Run statements in order, all or nothing
+    async fn batch(&self, statements: Vec<Statement>) -> Result<(), Self::Error>;
+}
+
+/// One statement in a `MigrationTarget::batch`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// SQL from a migration file (or `CREATE_SCHEMA_MIGRATIONS`)
+    Sql(String),
+    /// A `schema_migrations` row, written with `INSERT_SCHEMA_MIGRATION`
+    Record {
+        version: i64,
+        description: &'static str,
+        applied_at: String,
+    },
+}
+
+impl Statement {
+    fn record(migration: &Migration, applied_at: &str) -> Self {
+        Statement::Record {
+            version: migration.version,
+            description: migration.description,
+            applied_at: applied_at.to_string(),
+        }
+    }
+}
+
+/// Apply pending migrations to a `schema_migrations` database, returning the
+/// ones that ran
+///
+/// Each file runs as one batch together with its `schema_migrations` row, so
+/// a failed file leaves nothing behind.
+#[allow(dead_code)] // The server migrates with sqlx; D1 and the tests use this
+pub async fn run_migrations<T: MigrationTarget + ?Sized>(
+    target: &T,
+) -> Result<Vec<&'static Migration>, T::Error> {
+    baseline_existing_schema(target).await?;
+
+    let applied = target
+        .integers("SELECT version FROM schema_migrations")
+        .await?;
+    let pending = pending(MIGRATIONS, &applied);
+
+    for migration in &pending {
+        let mut batch: Vec<Statement> = statements(migration.sql)
+            .into_iter()
+            .map(Statement::Sql)
+            .collect();
+        batch.push(Statement::record(migration, &Utc::now().to_rfc3339()));
+        target.batch(batch).await?;
+    }
+
+    Ok(pending)
+}
+
+/// Create `schema_migrations` for databases set up before it existed
+///
+/// Versions applied by `wrangler d1 migrations apply` are carried over from
+/// its `d1_migrations` table; otherwise see `baseline`.
+#[allow(dead_code)]
+pub async fn baseline_existing_schema<T: MigrationTarget + ?Sized>(
+    target: &T,
+) -> Result<(), T::Error> {
+    let tables = target
+        .texts("SELECT name FROM sqlite_master WHERE type = 'table'")
+        .await?;
+    let has_table = |name: &str| tables.iter().any(|t| t == name);
+
+    if has_table("schema_migrations") {
+        return Ok(());
+    }
+
+    let versions: Vec<i64> = if has_table("d1_migrations") {
+        target
+            .texts("SELECT name FROM d1_migrations")
+            .await?
+            .iter()
+            .filter_map(|name| {
+                let digits: String = name.chars().take_while(char::is_ascii_digit).collect();
+                digits.parse().ok()
+            })
+            .collect()
+    } else if has_table("markets") {
+        let has_hide_from_subject = target
+            .texts("SELECT name FROM pragma_table_info('bets')")
+            .await?
+            .iter()
+            .any(|column| column == "hide_from_subject");
+        baseline(has_hide_from_subject)
+    } else {
+        Vec::new()
+    };
+
+    let now = Utc::now().to_rfc3339();
+    let mut batch = vec![Statement::Sql(CREATE_SCHEMA_MIGRATIONS.to_string())];
+    for migration in MIGRATIONS.iter().filter(|m| versions.contains(&m.version)) {
+        batch.push(Statement::record(migration, &now));
+    }
+    target.batch(batch).await
+}
+
+/// Split a migration file into individual statements
+///
+/// D1 prepares one statement at a time, so files are run as a batch of
+/// statements there. Comment lines are dropped; statements end with `;`.
+pub fn statements(sql: &str) -> Vec<String> {
+    let without_comments: String = sql
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("--"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    without_comments
+        .split(';')
+        .map(str::trim)
+        .filter(|statement| !statement.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// D1 runs the embedded list, so it must match what the migrator reads
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    fn assert_matches(migrator: &sqlx::migrate::Migrator, migrations: &[Migration]) {
+        let from_files: Vec<(i64, &str, &str)> = migrator
+            .iter()
+            .map(|m| (m.version, &*m.description, &*m.sql))
+            .collect();
+        let embedded: Vec<(i64, &str, &str)> = migrations
+            .iter()
+            .map(|m| (m.version, m.description, m.sql))
+            .collect();
+        assert_eq!(from_files, embedded);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_embedded_migrations_match_the_migrator() {
+        assert_matches(&MIGRATOR, MIGRATIONS);
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn test_embedded_postgres_migrations_match_the_migrator() {
+        assert_matches(&POSTGRES_MIGRATOR, POSTGRES_MIGRATIONS);
+    }
+
+    #[test]
+    fn test_baseline_counts_the_inline_schema_column() {
+        assert_eq!(baseline(false), vec![1]);
+        assert_eq!(baseline(true), vec![1, 2]);
+    }
+}
@@ -1,9 +1,42 @@
+pub mod migrations;
+pub mod query;
 pub mod r#trait;
 
+pub use query::Page;
 pub use r#trait::{Database, DbError, DbResult};
 
+#[allow(dead_code)] // Only the worker's Durable Object caches rows
+pub mod cached;
+
+#[allow(unused_imports)]
+pub use cached::CachedDatabase;
+
+#[cfg(feature = "server")]
+pub mod metered;
+
+#[cfg(feature = "server")]
+pub use metered::MeteredDatabase;
+
 #[cfg(feature = "sqlite")]
 pub mod sqlite;
 
 #[cfg(feature = "sqlite")]
 pub use sqlite::SqliteDatabase;
+
+#[cfg(feature = "d1")]
+pub mod d1;
+
+#[cfg(feature = "d1")]
+pub use d1::D1Database;
+
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresDatabase;
+
+#[cfg(feature = "memory")]
+pub mod memory;
+
+#[cfg(feature = "memory")]
+pub use memory::InMemoryDatabase;
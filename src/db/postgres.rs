@@ -0,0 +1,1591 @@
+/// PostgreSQL implementation of the Database trait
+///
+/// Uses native UUID, TIMESTAMPTZ and enum columns. Wagers are applied in a
+/// transaction that locks the bet and user rows, so concurrent wagers can't
+/// overwrite each other's pools or balances.
+use crate::db::migrations::{
+    self, Migration, MigrationStatus, POSTGRES_MIGRATIONS, POSTGRES_MIGRATOR,
+};
+use crate::db::query::{
+    BetQuery, BetSort, DeviceMarketQuery, Page, Select, SortKey, UserQuery, UserSort, WagerQuery,
+    WagerSort,
+};
+use crate::db::r#trait::{Database, DbError, DbResult};
+use crate::domain::archive::MarketArchive;
+use crate::domain::models::{
+    AuditEntry, Bet, BetStatus, BetView, LinkCode, Market, MarketStatus, MarketTemplate,
+    MembershipStatus, Player, Removal, RevealCeremony, Season, Side, Spectator, User, Wager,
+    Webhook, WebhookDelivery,
+};
+use crate::domain::{parimutuel, rules::RuleError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgArguments, PgPool, PgRow, Postgres};
+use sqlx::types::Json;
+use sqlx::Row;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PostgresDatabase {
+    pool: PgPool,
+}
+
+impl PostgresDatabase {
+    pub async fn new(database_url: &str) -> Result<Self, sqlx::Error> {
+        let pool = PgPool::connect(database_url).await?;
+        Ok(Self { pool })
+    }
+
+    /// Apply pending migrations with sqlx's migrator, returning the ones that ran
+    pub async fn run_migrations(&self) -> Result<Vec<&'static Migration>, sqlx::Error> {
+        let applied = self.applied_migrations().await?;
+        let versions: Vec<i64> = applied.iter().map(|(version, _)| *version).collect();
+        let pending = migrations::pending(POSTGRES_MIGRATIONS, &versions);
+
+        POSTGRES_MIGRATOR
+            .run(&self.pool)
+            .await
+            .map_err(|e| sqlx::Error::Migrate(Box::new(e)))?;
+
+        Ok(pending)
+    }
+
+    /// Every known migration and whether it has been applied
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+        Ok(migrations::status(
+            POSTGRES_MIGRATIONS,
+            &self.applied_migrations().await?,
+        ))
+    }
+
+    async fn applied_migrations(&self) -> Result<Vec<(i64, String)>, sqlx::Error> {
+        let exists: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(&self.pool)
+            .await?;
+        if !exists {
+            return Ok(Vec::new());
+        }
+
+        let rows = sqlx::query(
+            "SELECT version, installed_on FROM _sqlx_migrations WHERE success ORDER BY version",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get("version"),
+                    row.get::<DateTime<Utc>, _>("installed_on").to_rfc3339(),
+                )
+            })
+            .collect())
+    }
+}
+
+// Native enum types, mirroring the domain enums
+
+#[derive(sqlx::Type)]
+#[sqlx(type_name = "market_status", rename_all = "lowercase")]
+enum PgMarketStatus {
+    Draft,
+    Open,
+    Closed,
+    Resolved,
+}
+
+impl From<MarketStatus> for PgMarketStatus {
+    fn from(status: MarketStatus) -> Self {
+        match status {
+            MarketStatus::Draft => PgMarketStatus::Draft,
+            MarketStatus::Open => PgMarketStatus::Open,
+            MarketStatus::Closed => PgMarketStatus::Closed,
+            MarketStatus::Resolved => PgMarketStatus::Resolved,
+        }
+    }
+}
+
+impl From<PgMarketStatus> for MarketStatus {
+    fn from(status: PgMarketStatus) -> Self {
+        match status {
+            PgMarketStatus::Draft => MarketStatus::Draft,
+            PgMarketStatus::Open => MarketStatus::Open,
+            PgMarketStatus::Closed => MarketStatus::Closed,
+            PgMarketStatus::Resolved => MarketStatus::Resolved,
+        }
+    }
+}
+
+#[derive(sqlx::Type)]
+#[sqlx(type_name = "bet_status", rename_all = "snake_case")]
+enum PgBetStatus {
+    Pending,
+    Active,
+    ResolvedYes,
+    ResolvedNo,
+    Challenged,
+    Void,
+}
+
+impl From<BetStatus> for PgBetStatus {
+    fn from(status: BetStatus) -> Self {
+        match status {
+            BetStatus::Pending => PgBetStatus::Pending,
+            BetStatus::Active => PgBetStatus::Active,
+            BetStatus::ResolvedYes => PgBetStatus::ResolvedYes,
+            BetStatus::ResolvedNo => PgBetStatus::ResolvedNo,
+            BetStatus::Challenged => PgBetStatus::Challenged,
+            BetStatus::Void => PgBetStatus::Void,
+        }
+    }
+}
+
+impl From<PgBetStatus> for BetStatus {
+    fn from(status: PgBetStatus) -> Self {
+        match status {
+            PgBetStatus::Pending => BetStatus::Pending,
+            PgBetStatus::Active => BetStatus::Active,
+            PgBetStatus::ResolvedYes => BetStatus::ResolvedYes,
+            PgBetStatus::ResolvedNo => BetStatus::ResolvedNo,
+            PgBetStatus::Challenged => BetStatus::Challenged,
+            PgBetStatus::Void => BetStatus::Void,
+        }
+    }
+}
+
+#[derive(sqlx::Type)]
+#[sqlx(type_name = "membership_status", rename_all = "lowercase")]
+enum PgMembershipStatus {
+    Active,
+    Left,
+    Kicked,
+    Banned,
+}
+
+impl From<MembershipStatus> for PgMembershipStatus {
+    fn from(status: MembershipStatus) -> Self {
+        match status {
+            MembershipStatus::Active => PgMembershipStatus::Active,
+            MembershipStatus::Left => PgMembershipStatus::Left,
+            MembershipStatus::Kicked => PgMembershipStatus::Kicked,
+            MembershipStatus::Banned => PgMembershipStatus::Banned,
+        }
+    }
+}
+
+impl From<PgMembershipStatus> for MembershipStatus {
+    fn from(status: PgMembershipStatus) -> Self {
+        match status {
+            PgMembershipStatus::Active => MembershipStatus::Active,
+            PgMembershipStatus::Left => MembershipStatus::Left,
+            PgMembershipStatus::Kicked => MembershipStatus::Kicked,
+            PgMembershipStatus::Banned => MembershipStatus::Banned,
+        }
+    }
+}
+
+#[derive(sqlx::Type)]
+#[sqlx(type_name = "side", rename_all = "UPPERCASE")]
+enum PgSide {
+    Yes,
+    No,
+}
+
+impl From<Side> for PgSide {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Yes => PgSide::Yes,
+            Side::No => PgSide::No,
+        }
+    }
+}
+
+impl From<PgSide> for Side {
+    fn from(side: PgSide) -> Self {
+        match side {
+            PgSide::Yes => Side::Yes,
+            PgSide::No => Side::No,
+        }
+    }
+}
+
+/// Bet columns plus the subject ids from `bet_subjects`
+const SELECT_BETS: &str = "SELECT b.*, \
+     ARRAY(SELECT bs.user_id FROM bet_subjects bs WHERE bs.bet_id = b.id) AS subject_user_ids \
+     FROM bets b";
+
+fn market_from_row(row: &PgRow) -> Market {
+    Market {
+        id: row.get("id"),
+        name: row.get("name"),
+        status: row.get::<PgMarketStatus, _>("status").into(),
+        created_by: row.get("created_by"),
+        opens_at: row.get("opens_at"),
+        closes_at: row.get("closes_at"),
+        starting_balance: row.get("starting_balance"),
+        invite_code: row.get("invite_code"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn user_from_row(row: &PgRow) -> User {
+    User {
+        id: row.get("id"),
+        market_id: row.get("market_id"),
+        device_id: row.get("device_id"),
+        display_name: row.get("display_name"),
+        avatar: row.get("avatar"),
+        balance: row.get("balance"),
+        is_admin: row.get("is_admin"),
+        status: row.get::<PgMembershipStatus, _>("status").into(),
+        player_id: row.get("player_id"),
+        joined_at: row.get("joined_at"),
+    }
+}
+
+fn bet_from_row(row: &PgRow) -> Bet {
+    Bet {
+        id: row.get("id"),
+        market_id: row.get("market_id"),
+        subject_user_ids: row.get("subject_user_ids"),
+        about_everyone: row.get("about_everyone"),
+        created_by: row.get("created_by"),
+        description: row.get("description"),
+        initial_odds: row.get("initial_odds"),
+        status: row.get::<PgBetStatus, _>("status").into(),
+        yes_pool: row.get("yes_pool"),
+        no_pool: row.get("no_pool"),
+        hide_from_subject: row.get("hide_from_subject"),
+        created_at: row.get("created_at"),
+        resolved_at: row.get("resolved_at"),
+    }
+}
+
+fn wager_from_row(row: &PgRow) -> Wager {
+    Wager {
+        id: row.get("id"),
+        bet_id: row.get("bet_id"),
+        user_id: row.get("user_id"),
+        side: row.get::<PgSide, _>("side").into(),
+        amount: row.get("amount"),
+        placed_at: row.get("placed_at"),
+        yes_pool_after: row.get("yes_pool_after"),
+        no_pool_after: row.get("no_pool_after"),
+        probability_after: row.get("probability_after"),
+        refunded: row.get("refunded"),
+    }
+}
+
+fn spectator_from_row(row: &PgRow) -> Spectator {
+    Spectator {
+        id: row.get("id"),
+        market_id: row.get("market_id"),
+        display_name: row.get("display_name"),
+        token: row.get("token"),
+        joined_at: row.get("joined_at"),
+    }
+}
+
+fn webhook_from_row(row: &PgRow) -> Webhook {
+    Webhook {
+        id: row.get("id"),
+        market_id: row.get("market_id"),
+        url: row.get("url"),
+        secret: row.get("secret"),
+        events: row.get::<Json<_>, _>("events").0,
+        created_at: row.get("created_at"),
+    }
+}
+
+fn webhook_delivery_from_row(row: &PgRow) -> WebhookDelivery {
+    WebhookDelivery {
+        id: row.get("id"),
+        webhook_id: row.get("webhook_id"),
+        event: row.get("event"),
+        attempt: row.get::<i32, _>("attempt") as u32,
+        status_code: row
+            .get::<Option<i32>, _>("status_code")
+            .map(|code| code as u16),
+        error: row.get("error"),
+        succeeded: row.get("succeeded"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn season_from_row(row: &PgRow) -> Season {
+    Season {
+        id: row.get("id"),
+        name: row.get("name"),
+        carry_over_balances: row.get("carry_over_balances"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn internal(e: sqlx::Error) -> DbError {
+    DbError::Internal(e.to_string())
+}
+
+/// A value bound to a `query::Select` placeholder, in the column's own type
+enum PgValue {
+    Uuid(Uuid),
+    Time(DateTime<Utc>),
+    Int(i64),
+    MembershipStatus(PgMembershipStatus),
+    BetStatus(PgBetStatus),
+    Side(PgSide),
+}
+
+impl From<SortKey> for PgValue {
+    fn from(key: SortKey) -> Self {
+        match key {
+            SortKey::Time(time) => PgValue::Time(time),
+            SortKey::Int(n) => PgValue::Int(n),
+        }
+    }
+}
+
+impl From<Uuid> for PgValue {
+    fn from(id: Uuid) -> Self {
+        PgValue::Uuid(id)
+    }
+}
+
+impl From<DateTime<Utc>> for PgValue {
+    fn from(time: DateTime<Utc>) -> Self {
+        PgValue::Time(time)
+    }
+}
+
+impl From<MembershipStatus> for PgValue {
+    fn from(status: MembershipStatus) -> Self {
+        PgValue::MembershipStatus(status.into())
+    }
+}
+
+impl From<BetStatus> for PgValue {
+    fn from(status: BetStatus) -> Self {
+        PgValue::BetStatus(status.into())
+    }
+}
+
+impl From<Side> for PgValue {
+    fn from(side: Side) -> Self {
+        PgValue::Side(side.into())
+    }
+}
+
+/// Bind a `query::Select`'s parameters in order
+fn bind_all<'q>(
+    mut query: sqlx::query::Query<'q, Postgres, PgArguments>,
+    params: Vec<PgValue>,
+) -> sqlx::query::Query<'q, Postgres, PgArguments> {
+    for param in params {
+        query = match param {
+            PgValue::Uuid(id) => query.bind(id),
+            PgValue::Time(time) => query.bind(time),
+            PgValue::Int(n) => query.bind(n),
+            PgValue::MembershipStatus(status) => query.bind(status),
+            PgValue::BetStatus(status) => query.bind(status),
+            PgValue::Side(side) => query.bind(side),
+        };
+    }
+    query
+}
+
+// ===== Inserts (shared by the create_ methods and import_archive) =====
+
+type Insert<'q> = sqlx::query::Query<'q, Postgres, PgArguments>;
+
+fn insert_market(market: &Market) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO markets (id, name, status, created_by, opens_at, closes_at, starting_balance, invite_code, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(market.id)
+    .bind(&market.name)
+    .bind(PgMarketStatus::from(market.status))
+    .bind(market.created_by)
+    .bind(market.opens_at)
+    .bind(market.closes_at)
+    .bind(market.starting_balance)
+    .bind(&market.invite_code)
+    .bind(market.created_at)
+}
+
+fn insert_user(user: &User) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO users (id, market_id, device_id, display_name, avatar, balance, is_admin, status, player_id, joined_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+    )
+    .bind(user.id)
+    .bind(user.market_id)
+    .bind(&user.device_id)
+    .bind(&user.display_name)
+    .bind(&user.avatar)
+    .bind(user.balance)
+    .bind(user.is_admin)
+    .bind(PgMembershipStatus::from(user.status))
+    .bind(user.player_id)
+    .bind(user.joined_at)
+}
+
+fn insert_player(player: &Player) -> Insert<'_> {
+    sqlx::query(
+        "INSERT INTO players (id, display_name, avatar, created_at) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(player.id)
+    .bind(&player.display_name)
+    .bind(&player.avatar)
+    .bind(player.created_at)
+}
+
+fn insert_season(season: &Season) -> Insert<'_> {
+    sqlx::query(
+        "INSERT INTO seasons (id, name, carry_over_balances, created_at) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(season.id)
+    .bind(&season.name)
+    .bind(season.carry_over_balances)
+    .bind(season.created_at)
+}
+
+fn insert_spectator(spectator: &Spectator) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO spectators (id, market_id, display_name, token, joined_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(spectator.id)
+    .bind(spectator.market_id)
+    .bind(&spectator.display_name)
+    .bind(&spectator.token)
+    .bind(spectator.joined_at)
+}
+
+fn insert_wager(wager: &Wager) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO wagers (id, bet_id, user_id, side, amount, placed_at, yes_pool_after, no_pool_after, probability_after, refunded)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+    )
+    .bind(wager.id)
+    .bind(wager.bet_id)
+    .bind(wager.user_id)
+    .bind(PgSide::from(wager.side))
+    .bind(wager.amount)
+    .bind(wager.placed_at)
+    .bind(wager.yes_pool_after)
+    .bind(wager.no_pool_after)
+    .bind(wager.probability_after)
+    .bind(wager.refunded)
+}
+
+/// The bet row followed by one row per subject
+fn insert_bet(bet: &Bet) -> Vec<Insert<'_>> {
+    let mut inserts = vec![sqlx::query(
+        r#"
+        INSERT INTO bets (id, market_id, created_by, description, initial_odds, status, yes_pool, no_pool, hide_from_subject, about_everyone, created_at, resolved_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        "#,
+    )
+    .bind(bet.id)
+    .bind(bet.market_id)
+    .bind(bet.created_by)
+    .bind(&bet.description)
+    .bind(&bet.initial_odds)
+    .bind(PgBetStatus::from(bet.status))
+    .bind(bet.yes_pool)
+    .bind(bet.no_pool)
+    .bind(bet.hide_from_subject)
+    .bind(bet.about_everyone)
+    .bind(bet.created_at)
+    .bind(bet.resolved_at)];
+
+    for subject_id in &bet.subject_user_ids {
+        inserts.push(
+            sqlx::query("INSERT INTO bet_subjects (bet_id, user_id) VALUES ($1, $2)")
+                .bind(bet.id)
+                .bind(subject_id),
+        );
+    }
+    inserts
+}
+
+fn insert_reveal_ceremony(ceremony: &RevealCeremony) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO reveal_ceremonies (market_id, bet_ids, revealed_count, started_at, completed_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(ceremony.market_id)
+    .bind(&ceremony.bet_ids)
+    .bind(ceremony.revealed_count as i64)
+    .bind(ceremony.started_at)
+    .bind(ceremony.completed_at)
+}
+
+fn insert_season_market<'q>(season_id: Uuid, market_id: Uuid, position: usize) -> Insert<'q> {
+    sqlx::query("INSERT INTO season_markets (market_id, season_id, position) VALUES ($1, $2, $3)")
+        .bind(market_id)
+        .bind(season_id)
+        .bind(position as i64)
+}
+
+fn insert_carry_over<'q>(market_id: Uuid, user_id: Uuid, amount: i64) -> Insert<'q> {
+    sqlx::query("INSERT INTO season_carry_overs (user_id, market_id, amount) VALUES ($1, $2, $3)")
+        .bind(user_id)
+        .bind(market_id)
+        .bind(amount)
+}
+
+#[async_trait]
+impl Database for PostgresDatabase {
+    async fn create_market(&self, market: Market) -> DbResult<Market> {
+        insert_market(&market)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(market)
+    }
+
+    async fn get_market(&self, id: Uuid) -> DbResult<Market> {
+        sqlx::query("SELECT * FROM markets WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| market_from_row(&row))
+            .ok_or_else(|| DbError::NotFound("Market not found".to_string()))
+    }
+
+    async fn get_market_by_invite_code(&self, code: &str) -> DbResult<Market> {
+        sqlx::query("SELECT * FROM markets WHERE invite_code = $1")
+            .bind(code)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| market_from_row(&row))
+            .ok_or_else(|| DbError::NotFound("Market not found".to_string()))
+    }
+
+    async fn update_market_status(&self, id: Uuid, status: MarketStatus) -> DbResult<()> {
+        sqlx::query("UPDATE markets SET status = $1 WHERE id = $2")
+            .bind(PgMarketStatus::from(status))
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+        Ok(())
+    }
+
+    async fn delete_market(&self, id: Uuid) -> DbResult<()> {
+        let mut tx = self.pool.begin().await.map_err(internal)?;
+
+        // Delete in order: wagers -> bet subjects -> bets -> reveal ceremony
+        // -> spectators -> webhook deliveries -> webhooks -> season links
+        // -> users -> market
+        for statement in [
+            "DELETE FROM wagers WHERE bet_id IN (SELECT id FROM bets WHERE market_id = $1)",
+            "DELETE FROM bet_subjects WHERE bet_id IN (SELECT id FROM bets WHERE market_id = $1)",
+            "DELETE FROM bets WHERE market_id = $1",
+            "DELETE FROM reveal_ceremonies WHERE market_id = $1",
+            "DELETE FROM spectators WHERE market_id = $1",
+            "DELETE FROM webhook_deliveries WHERE webhook_id IN (SELECT id FROM webhooks WHERE market_id = $1)",
+            "DELETE FROM webhooks WHERE market_id = $1",
+            "DELETE FROM season_carry_overs WHERE market_id = $1",
+            "DELETE FROM season_markets WHERE market_id = $1",
+            "DELETE FROM users WHERE market_id = $1",
+            "DELETE FROM markets WHERE id = $1",
+        ] {
+            sqlx::query(statement)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+        }
+
+        tx.commit().await.map_err(internal)
+    }
+
+    async fn import_archive(&self, archive: &MarketArchive) -> DbResult<()> {
+        let market_id = archive.market.id;
+        let mut tx = self.pool.begin().await.map_err(internal)?;
+
+        let mut inserts = Vec::new();
+        for player in &archive.players {
+            let exists = sqlx::query("SELECT 1 FROM players WHERE id = $1")
+                .bind(player.id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(internal)?
+                .is_some();
+            if !exists {
+                inserts.push(insert_player(player));
+            }
+        }
+        if let Some(membership) = &archive.season {
+            let exists = sqlx::query("SELECT 1 FROM seasons WHERE id = $1")
+                .bind(membership.season.id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(internal)?
+                .is_some();
+            if !exists {
+                inserts.push(insert_season(&membership.season));
+            }
+        }
+
+        inserts.push(insert_market(&archive.market));
+        inserts.extend(archive.users.iter().map(insert_user));
+        inserts.extend(archive.bets.iter().flat_map(insert_bet));
+        inserts.extend(archive.wagers.iter().map(insert_wager));
+        inserts.extend(archive.spectators.iter().map(insert_spectator));
+        inserts.extend(archive.reveal_ceremony.iter().map(insert_reveal_ceremony));
+        if let Some(membership) = &archive.season {
+            inserts.push(insert_season_market(
+                membership.season.id,
+                market_id,
+                membership.position,
+            ));
+        }
+        inserts.extend(
+            archive
+                .carry_overs
+                .iter()
+                .map(|c| insert_carry_over(market_id, c.user_id, c.amount)),
+        );
+
+        for insert in inserts {
+            insert.execute(&mut *tx).await.map_err(internal)?;
+        }
+        tx.commit().await.map_err(internal)
+    }
+
+    async fn create_user(&self, user: User) -> DbResult<User> {
+        insert_user(&user)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(user)
+    }
+
+    async fn get_user(&self, id: Uuid) -> DbResult<User> {
+        sqlx::query("SELECT * FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| user_from_row(&row))
+            .ok_or_else(|| DbError::NotFound("User not found".to_string()))
+    }
+
+    async fn get_user_by_device_id(&self, market_id: Uuid, device_id: &str) -> DbResult<User> {
+        sqlx::query("SELECT * FROM users WHERE market_id = $1 AND device_id = $2")
+            .bind(market_id)
+            .bind(device_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| user_from_row(&row))
+            .ok_or_else(|| DbError::NotFound("User not found".to_string()))
+    }
+
+    async fn get_users_in_market(&self, market_id: Uuid) -> DbResult<Vec<User>> {
+        let rows = sqlx::query("SELECT * FROM users WHERE market_id = $1 ORDER BY joined_at, id")
+            .bind(market_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(rows.iter().map(user_from_row).collect())
+    }
+
+    async fn list_users_in_market(
+        &self,
+        market_id: Uuid,
+        query: &UserQuery,
+    ) -> DbResult<Page<User>> {
+        let mut select = Select::numbered("SELECT * FROM users");
+        select.filter("market_id = ?", [market_id.into()]);
+        select.filter_by("status = ?", query.status);
+        select.filter_by("joined_at >= ?", query.joined_after);
+        select.filter_by("joined_at < ?", query.joined_before);
+        let key = match query.sort {
+            UserSort::Joined => "joined_at",
+            UserSort::Balance => "balance",
+        };
+        let (sql, params) = select.page(
+            key,
+            "id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
+
+        let rows = bind_all(sqlx::query(&sql), params)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.iter().map(user_from_row).collect(),
+            query.limit(),
+            |user| sort.key(user),
+        ))
+    }
+
+    async fn update_user_balance(&self, user_id: Uuid, new_balance: i64) -> DbResult<()> {
+        sqlx::query("UPDATE users SET balance = $1 WHERE id = $2")
+            .bind(new_balance)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+        Ok(())
+    }
+
+    async fn update_user_status(&self, user_id: Uuid, status: MembershipStatus) -> DbResult<()> {
+        sqlx::query("UPDATE users SET status = $1 WHERE id = $2")
+            .bind(PgMembershipStatus::from(status))
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+        Ok(())
+    }
+
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        display_name: &str,
+        avatar: &str,
+    ) -> DbResult<()> {
+        sqlx::query("UPDATE users SET display_name = $1, avatar = $2 WHERE id = $3")
+            .bind(display_name)
+            .bind(avatar)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+        Ok(())
+    }
+
+    async fn update_user_device(&self, user_id: Uuid, device_id: &str) -> DbResult<()> {
+        sqlx::query("UPDATE users SET device_id = $1 WHERE id = $2")
+            .bind(device_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+        Ok(())
+    }
+
+    async fn get_markets_by_device_id(&self, device_id: &str) -> DbResult<Vec<(Market, User)>> {
+        let rows = sqlx::query(
+            "SELECT * FROM users WHERE device_id = $1 ORDER BY joined_at DESC LIMIT 10",
+        )
+        .bind(device_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        let mut markets = Vec::with_capacity(rows.len());
+        for user in rows.iter().map(user_from_row) {
+            markets.push((self.get_market(user.market_id).await?, user));
+        }
+        Ok(markets)
+    }
+
+    async fn list_markets_by_device_id(
+        &self,
+        device_id: &str,
+        query: &DeviceMarketQuery,
+    ) -> DbResult<Page<(Market, User)>> {
+        let rows = sqlx::query("SELECT * FROM users WHERE device_id = $1")
+            .bind(device_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        let mut markets = Vec::with_capacity(rows.len());
+        for user in rows.iter().map(user_from_row) {
+            markets.push((self.get_market(user.market_id).await?, user));
+        }
+        query.apply(markets)
+    }
+
+    async fn create_player(&self, player: Player) -> DbResult<Player> {
+        insert_player(&player)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(player)
+    }
+
+    async fn get_player(&self, id: Uuid) -> DbResult<Player> {
+        sqlx::query("SELECT * FROM players WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| Player {
+                id: row.get("id"),
+                display_name: row.get("display_name"),
+                avatar: row.get("avatar"),
+                created_at: row.get("created_at"),
+            })
+            .ok_or_else(|| DbError::NotFound("Player not found".to_string()))
+    }
+
+    async fn update_user_player(&self, user_id: Uuid, player_id: Uuid) -> DbResult<()> {
+        sqlx::query("UPDATE users SET player_id = $1 WHERE id = $2")
+            .bind(player_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+        Ok(())
+    }
+
+    async fn get_users_by_player(&self, player_id: Uuid) -> DbResult<Vec<User>> {
+        let rows = sqlx::query("SELECT * FROM users WHERE player_id = $1 ORDER BY joined_at")
+            .bind(player_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(rows.iter().map(user_from_row).collect())
+    }
+
+    async fn create_template(&self, template: MarketTemplate) -> DbResult<MarketTemplate> {
+        sqlx::query(
+            r#"
+            INSERT INTO market_templates (id, name, starting_balance, duration_hours, bets, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(template.id)
+        .bind(&template.name)
+        .bind(template.starting_balance)
+        .bind(template.duration_hours)
+        .bind(Json(&template.bets))
+        .bind(template.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(template)
+    }
+
+    async fn get_template(&self, id: Uuid) -> DbResult<MarketTemplate> {
+        sqlx::query("SELECT * FROM market_templates WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| MarketTemplate {
+                id: row.get("id"),
+                name: row.get("name"),
+                starting_balance: row.get("starting_balance"),
+                duration_hours: row.get("duration_hours"),
+                bets: row.get::<Json<_>, _>("bets").0,
+                created_at: row.get("created_at"),
+            })
+            .ok_or_else(|| DbError::NotFound("Template not found".to_string()))
+    }
+
+    async fn create_season(&self, season: Season) -> DbResult<Season> {
+        insert_season(&season)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(season)
+    }
+
+    async fn get_season(&self, id: Uuid) -> DbResult<Season> {
+        sqlx::query("SELECT * FROM seasons WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| season_from_row(&row))
+            .ok_or_else(|| DbError::NotFound("Season not found".to_string()))
+    }
+
+    async fn add_season_market(
+        &self,
+        season_id: Uuid,
+        market_id: Uuid,
+        position: usize,
+    ) -> DbResult<()> {
+        insert_season_market(season_id, market_id, position)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+        Ok(())
+    }
+
+    async fn get_season_markets(&self, season_id: Uuid) -> DbResult<Vec<Market>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT m.* FROM markets m
+            JOIN season_markets sm ON sm.market_id = m.id
+            WHERE sm.season_id = $1
+            ORDER BY sm.position
+            "#,
+        )
+        .bind(season_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(rows.iter().map(market_from_row).collect())
+    }
+
+    async fn get_season_for_market(&self, market_id: Uuid) -> DbResult<Season> {
+        sqlx::query(
+            r#"
+            SELECT s.* FROM seasons s
+            JOIN season_markets sm ON sm.season_id = s.id
+            WHERE sm.market_id = $1
+            "#,
+        )
+        .bind(market_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(internal)?
+        .map(|row| season_from_row(&row))
+        .ok_or_else(|| DbError::NotFound("Season not found".to_string()))
+    }
+
+    async fn create_carry_over(&self, market_id: Uuid, user_id: Uuid, amount: i64) -> DbResult<()> {
+        insert_carry_over(market_id, user_id, amount)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+        Ok(())
+    }
+
+    async fn get_carry_overs(&self, season_id: Uuid) -> DbResult<Vec<(Uuid, i64)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT c.user_id, c.amount FROM season_carry_overs c
+            JOIN season_markets sm ON sm.market_id = c.market_id
+            WHERE sm.season_id = $1
+            "#,
+        )
+        .bind(season_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("user_id"), row.get("amount")))
+            .collect())
+    }
+
+    async fn create_link_code(&self, link: LinkCode) -> DbResult<LinkCode> {
+        sqlx::query(
+            r#"
+            INSERT INTO link_codes (code, device_id, created_at, expires_at, redeemed_at, redeemed_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&link.code)
+        .bind(&link.device_id)
+        .bind(link.created_at)
+        .bind(link.expires_at)
+        .bind(link.redeemed_at)
+        .bind(&link.redeemed_by)
+        .execute(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(link)
+    }
+
+    async fn get_link_code(&self, code: &str) -> DbResult<LinkCode> {
+        sqlx::query("SELECT * FROM link_codes WHERE code = $1")
+            .bind(code)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| LinkCode {
+                code: row.get("code"),
+                device_id: row.get("device_id"),
+                created_at: row.get("created_at"),
+                expires_at: row.get("expires_at"),
+                redeemed_at: row.get("redeemed_at"),
+                redeemed_by: row.get("redeemed_by"),
+            })
+            .ok_or_else(|| DbError::NotFound("Link code not found".to_string()))
+    }
+
+    async fn redeem_link_code(
+        &self,
+        code: &str,
+        device_id: &str,
+        redeemed_at: DateTime<Utc>,
+    ) -> DbResult<bool> {
+        let result = sqlx::query(
+            "UPDATE link_codes SET redeemed_at = $1, redeemed_by = $2 WHERE code = $3 AND redeemed_at IS NULL",
+        )
+        .bind(redeemed_at)
+        .bind(device_id)
+        .bind(code)
+        .execute(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn create_audit_entry(&self, entry: AuditEntry) -> DbResult<AuditEntry> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log (id, action, device_id, detail, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(entry.id)
+        .bind(&entry.action)
+        .bind(&entry.device_id)
+        .bind(&entry.detail)
+        .bind(entry.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(entry)
+    }
+
+    async fn count_audit_entries(
+        &self,
+        device_id: Option<&str>,
+        action: &str,
+        since: DateTime<Utc>,
+    ) -> DbResult<i64> {
+        sqlx::query_scalar(
+            "SELECT COUNT(*) FROM audit_log WHERE ($1::TEXT IS NULL OR device_id = $1) AND action = $2 AND created_at >= $3",
+        )
+        .bind(device_id)
+        .bind(action)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(internal)
+    }
+
+    async fn create_spectator(&self, spectator: Spectator) -> DbResult<Spectator> {
+        insert_spectator(&spectator)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(spectator)
+    }
+
+    async fn get_spectator_by_token(&self, token: &str) -> DbResult<Spectator> {
+        sqlx::query("SELECT * FROM spectators WHERE token = $1")
+            .bind(token)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| spectator_from_row(&row))
+            .ok_or_else(|| DbError::NotFound("Spectator not found".to_string()))
+    }
+
+    async fn get_spectators_in_market(&self, market_id: Uuid) -> DbResult<Vec<Spectator>> {
+        let rows =
+            sqlx::query("SELECT * FROM spectators WHERE market_id = $1 ORDER BY joined_at, id")
+                .bind(market_id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(internal)?;
+
+        Ok(rows.iter().map(spectator_from_row).collect())
+    }
+
+    async fn create_webhook(&self, webhook: Webhook) -> DbResult<Webhook> {
+        sqlx::query(
+            r#"
+            INSERT INTO webhooks (id, market_id, url, secret, events, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(webhook.id)
+        .bind(webhook.market_id)
+        .bind(&webhook.url)
+        .bind(&webhook.secret)
+        .bind(Json(&webhook.events))
+        .bind(webhook.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(webhook)
+    }
+
+    async fn get_webhook(&self, id: Uuid) -> DbResult<Webhook> {
+        sqlx::query("SELECT * FROM webhooks WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| webhook_from_row(&row))
+            .ok_or_else(|| DbError::NotFound("Webhook not found".to_string()))
+    }
+
+    async fn get_webhooks_in_market(&self, market_id: Uuid) -> DbResult<Vec<Webhook>> {
+        let rows =
+            sqlx::query("SELECT * FROM webhooks WHERE market_id = $1 ORDER BY created_at, id")
+                .bind(market_id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(internal)?;
+
+        Ok(rows.iter().map(webhook_from_row).collect())
+    }
+
+    async fn delete_webhook(&self, id: Uuid) -> DbResult<()> {
+        let mut tx = self.pool.begin().await.map_err(internal)?;
+
+        for statement in [
+            "DELETE FROM webhook_deliveries WHERE webhook_id = $1",
+            "DELETE FROM webhooks WHERE id = $1",
+        ] {
+            sqlx::query(statement)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+        }
+
+        tx.commit().await.map_err(internal)
+    }
+
+    async fn create_webhook_delivery(
+        &self,
+        delivery: WebhookDelivery,
+    ) -> DbResult<WebhookDelivery> {
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (id, webhook_id, event, attempt, status_code, error, succeeded, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(delivery.id)
+        .bind(delivery.webhook_id)
+        .bind(&delivery.event)
+        .bind(delivery.attempt as i32)
+        .bind(delivery.status_code.map(|code| code as i32))
+        .bind(&delivery.error)
+        .bind(delivery.succeeded)
+        .bind(delivery.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(delivery)
+    }
+
+    async fn get_webhook_deliveries(&self, webhook_id: Uuid) -> DbResult<Vec<WebhookDelivery>> {
+        let rows = sqlx::query(
+            "SELECT * FROM webhook_deliveries WHERE webhook_id = $1 ORDER BY created_at, attempt",
+        )
+        .bind(webhook_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(rows.iter().map(webhook_delivery_from_row).collect())
+    }
+
+    async fn create_bet(&self, bet: Bet) -> DbResult<Bet> {
+        let mut tx = self.pool.begin().await.map_err(internal)?;
+
+        for insert in insert_bet(&bet) {
+            insert.execute(&mut *tx).await.map_err(internal)?;
+        }
+
+        tx.commit().await.map_err(internal)?;
+        Ok(bet)
+    }
+
+    async fn get_bet(&self, id: Uuid) -> DbResult<Bet> {
+        sqlx::query(&format!("{} WHERE b.id = $1", SELECT_BETS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| bet_from_row(&row))
+            .ok_or_else(|| DbError::NotFound("Bet not found".to_string()))
+    }
+
+    async fn get_bets_in_market(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE b.market_id = $1 ORDER BY b.created_at, b.id",
+            SELECT_BETS
+        ))
+        .bind(market_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(rows.iter().map(bet_from_row).collect())
+    }
+
+    async fn list_bets_in_market(&self, market_id: Uuid, query: &BetQuery) -> DbResult<Page<Bet>> {
+        let mut select = Select::numbered(SELECT_BETS);
+        select.filter("b.market_id = ?", [market_id.into()]);
+        select.filter_by("b.status = ?", query.status);
+        select.filter_by(
+            "(b.about_everyone OR b.id IN (SELECT bet_id FROM bet_subjects WHERE user_id = ?))",
+            query.subject,
+        );
+        select.filter_by("b.created_by = ?", query.creator);
+        select.filter_by("b.created_at >= ?", query.created_after);
+        select.filter_by("b.created_at < ?", query.created_before);
+        let key = match query.sort {
+            BetSort::Newest | BetSort::Oldest => "b.created_at",
+            BetSort::Pool => "(b.yes_pool + b.no_pool)",
+        };
+        let (sql, params) = select.page(
+            key,
+            "b.id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
+
+        let rows = bind_all(sqlx::query(&sql), params)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.iter().map(bet_from_row).collect(),
+            query.limit(),
+            |bet| sort.key(bet),
+        ))
+    }
+
+    async fn get_bets_for_user(
+        &self,
+        market_id: Uuid,
+        viewing_user_id: Uuid,
+    ) -> DbResult<Vec<BetView>> {
+        let bets = self.get_bets_in_market(market_id).await?;
+        Ok(bets
+            .iter()
+            .map(|bet| bet.to_view(viewing_user_id))
+            .collect())
+    }
+
+    async fn get_pending_bets(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE b.market_id = $1 AND b.status = $2 ORDER BY b.created_at, b.id",
+            SELECT_BETS
+        ))
+        .bind(market_id)
+        .bind(PgBetStatus::Pending)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(rows.iter().map(bet_from_row).collect())
+    }
+
+    async fn update_bet_status(&self, bet_id: Uuid, status: BetStatus) -> DbResult<()> {
+        let resolved_at = match status {
+            BetStatus::ResolvedYes | BetStatus::ResolvedNo => Some(Utc::now()),
+            _ => None,
+        };
+
+        sqlx::query("UPDATE bets SET status = $1, resolved_at = $2 WHERE id = $3")
+            .bind(PgBetStatus::from(status))
+            .bind(resolved_at)
+            .bind(bet_id)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+        Ok(())
+    }
+
+    async fn update_bet_pools(&self, bet_id: Uuid, yes_pool: i64, no_pool: i64) -> DbResult<()> {
+        sqlx::query("UPDATE bets SET yes_pool = $1, no_pool = $2 WHERE id = $3")
+            .bind(yes_pool)
+            .bind(no_pool)
+            .bind(bet_id)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+        Ok(())
+    }
+
+    async fn create_wager(&self, wager: Wager) -> DbResult<Wager> {
+        insert_wager(&wager)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(wager)
+    }
+
+    /// Apply a wager with the bet and user rows locked
+    ///
+    /// Pools and balance are re-read under `FOR UPDATE`, so the snapshot on
+    /// the wager is recomputed from what's actually stored.
+    async fn apply_wager(&self, wager: Wager) -> DbResult<Wager> {
+        let mut tx = self.pool.begin().await.map_err(internal)?;
+
+        let bet =
+            sqlx::query("SELECT status, yes_pool, no_pool FROM bets WHERE id = $1 FOR UPDATE")
+                .bind(wager.bet_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(internal)?
+                .ok_or_else(|| DbError::NotFound("Bet not found".to_string()))?;
+        let balance: i64 = sqlx::query_scalar("SELECT balance FROM users WHERE id = $1 FOR UPDATE")
+            .bind(wager.user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| DbError::NotFound("User not found".to_string()))?;
+
+        // Another request may have resolved the bet or spent the coins first
+        if BetStatus::from(bet.get::<PgBetStatus, _>("status")) != BetStatus::Active {
+            return Err(DbError::Constraint(RuleError::BetNotActive.to_string()));
+        }
+        if balance < wager.amount {
+            return Err(DbError::Constraint(
+                RuleError::InsufficientBalance {
+                    needed: wager.amount,
+                    available: balance,
+                }
+                .to_string(),
+            ));
+        }
+
+        let (yes_pool_after, no_pool_after, _) = parimutuel::calculate_potential_payout(
+            bet.get("yes_pool"),
+            bet.get("no_pool"),
+            wager.side,
+            wager.amount,
+        );
+        let wager = Wager {
+            yes_pool_after,
+            no_pool_after,
+            probability_after: parimutuel::calculate_probability(yes_pool_after, no_pool_after),
+            ..wager
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO wagers (id, bet_id, user_id, side, amount, placed_at, yes_pool_after, no_pool_after, probability_after, refunded)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(wager.id)
+        .bind(wager.bet_id)
+        .bind(wager.user_id)
+        .bind(PgSide::from(wager.side))
+        .bind(wager.amount)
+        .bind(wager.placed_at)
+        .bind(wager.yes_pool_after)
+        .bind(wager.no_pool_after)
+        .bind(wager.probability_after)
+        .bind(wager.refunded)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal)?;
+
+        sqlx::query("UPDATE bets SET yes_pool = $1, no_pool = $2 WHERE id = $3")
+            .bind(wager.yes_pool_after)
+            .bind(wager.no_pool_after)
+            .bind(wager.bet_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal)?;
+
+        sqlx::query("UPDATE users SET balance = balance - $1 WHERE id = $2")
+            .bind(wager.amount)
+            .bind(wager.user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal)?;
+
+        tx.commit().await.map_err(internal)?;
+        Ok(wager)
+    }
+
+    async fn get_wagers_for_bet(&self, bet_id: Uuid) -> DbResult<Vec<Wager>> {
+        let rows = sqlx::query("SELECT * FROM wagers WHERE bet_id = $1 ORDER BY placed_at, id")
+            .bind(bet_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(rows.iter().map(wager_from_row).collect())
+    }
+
+    async fn list_wagers_for_bet(&self, bet_id: Uuid, query: &WagerQuery) -> DbResult<Page<Wager>> {
+        let mut select = Select::numbered("SELECT * FROM wagers");
+        select.filter("bet_id = ?", [bet_id.into()]);
+        select.filter_by("user_id = ?", query.user);
+        select.filter_by("side = ?", query.side);
+        select.filter_by("placed_at >= ?", query.placed_after);
+        select.filter_by("placed_at < ?", query.placed_before);
+        let key = match query.sort {
+            WagerSort::Newest | WagerSort::Oldest => "placed_at",
+            WagerSort::Largest => "amount",
+        };
+        let (sql, params) = select.page(
+            key,
+            "id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
+
+        let rows = bind_all(sqlx::query(&sql), params)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.iter().map(wager_from_row).collect(),
+            query.limit(),
+            |wager| sort.key(wager),
+        ))
+    }
+
+    async fn apply_removal(&self, removal: &Removal) -> DbResult<()> {
+        let mut tx = self.pool.begin().await.map_err(internal)?;
+
+        for wager in &removal.refunds {
+            sqlx::query("UPDATE wagers SET refunded = TRUE WHERE id = $1")
+                .bind(wager.id)
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+            sqlx::query("UPDATE users SET balance = balance + $1 WHERE id = $2")
+                .bind(wager.amount)
+                .bind(wager.user_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+            if !removal.voided_bet_ids.contains(&wager.bet_id) {
+                sqlx::query(match wager.side {
+                    Side::Yes => "UPDATE bets SET yes_pool = yes_pool - $1 WHERE id = $2",
+                    Side::No => "UPDATE bets SET no_pool = no_pool - $1 WHERE id = $2",
+                })
+                .bind(wager.amount)
+                .bind(wager.bet_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+            }
+        }
+        for bet_id in &removal.voided_bet_ids {
+            sqlx::query("UPDATE bets SET status = $1 WHERE id = $2")
+                .bind(PgBetStatus::Void)
+                .bind(bet_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+        }
+        sqlx::query("UPDATE users SET status = $1 WHERE id = $2")
+            .bind(PgMembershipStatus::from(removal.status))
+            .bind(removal.user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal)?;
+
+        tx.commit().await.map_err(internal)
+    }
+
+    async fn get_wagers_for_user(&self, user_id: Uuid) -> DbResult<Vec<Wager>> {
+        let rows = sqlx::query("SELECT * FROM wagers WHERE user_id = $1 ORDER BY placed_at, id")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(rows.iter().map(wager_from_row).collect())
+    }
+
+    async fn get_bets_about_user(&self, user_id: Uuid) -> DbResult<Vec<Bet>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE b.id IN (SELECT bet_id FROM bet_subjects WHERE user_id = $1) \
+             OR (b.about_everyone AND b.market_id = (SELECT market_id FROM users WHERE id = $1)) \
+             ORDER BY b.created_at, b.id",
+            SELECT_BETS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(rows.iter().map(bet_from_row).collect())
+    }
+
+    async fn create_reveal_ceremony(&self, ceremony: RevealCeremony) -> DbResult<RevealCeremony> {
+        insert_reveal_ceremony(&ceremony)
+            .execute(&self.pool)
+            .await
+            .map_err(internal)?;
+
+        Ok(ceremony)
+    }
+
+    async fn get_reveal_ceremony(&self, market_id: Uuid) -> DbResult<RevealCeremony> {
+        sqlx::query("SELECT * FROM reveal_ceremonies WHERE market_id = $1")
+            .bind(market_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal)?
+            .map(|row| RevealCeremony {
+                market_id: row.get("market_id"),
+                bet_ids: row.get("bet_ids"),
+                revealed_count: row.get::<i64, _>("revealed_count") as usize,
+                started_at: row.get("started_at"),
+                completed_at: row.get("completed_at"),
+            })
+            .ok_or_else(|| DbError::NotFound("Reveal ceremony not found".to_string()))
+    }
+
+    async fn update_reveal_progress(
+        &self,
+        market_id: Uuid,
+        revealed_count: usize,
+        completed_at: Option<DateTime<Utc>>,
+    ) -> DbResult<()> {
+        sqlx::query(
+            "UPDATE reveal_ceremonies SET revealed_count = $1, completed_at = $2 WHERE market_id = $3",
+        )
+        .bind(revealed_count as i64)
+        .bind(completed_at)
+        .bind(market_id)
+        .execute(&self.pool)
+        .await
+        .map_err(internal)?;
+
+        Ok(())
+    }
+}
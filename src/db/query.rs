@@ -0,0 +1,755 @@
+/// Listing queries: filters, sort orders and cursor pagination
+///
+/// Listings come back a `Page` at a time. The cursor is opaque to clients; it
+/// holds the sort key and id of the last item returned, and the next page
+/// starts strictly after that item (keyset pagination), so rows added in the
+/// meantime don't shift or repeat later pages.
+///
+/// The `apply` methods filter and page rows already in memory, for backends
+/// without a SQL override. SQLite, D1 and Postgres build the same query with
+/// `Select`.
+use crate::db::{DbError, DbResult};
+use crate::domain::models::{
+    Bet, BetStatus, Market, MarketStatus, MembershipStatus, Side, User, Wager,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Items per page when the client doesn't ask for a size
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Largest page a client can ask for
+pub const MAX_PAGE_SIZE: usize = 200;
+
+/// One page of a listing
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>, // None on the last page
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+
+    /// Build a page from up to `limit + 1` sorted rows
+    ///
+    /// Backends fetch one row more than the page holds; if it's there, the
+    /// page's last item becomes the cursor for the next one.
+    pub(crate) fn from_rows(
+        mut rows: Vec<T>,
+        limit: usize,
+        key: impl Fn(&T) -> (SortKey, Uuid),
+    ) -> Self {
+        let next_cursor = if rows.len() > limit {
+            rows.truncate(limit);
+            rows.last().map(|last| {
+                let (key, id) = key(last);
+                Cursor { key, id }.encode()
+            })
+        } else {
+            None
+        };
+        Page {
+            items: rows,
+            next_cursor,
+        }
+    }
+}
+
+/// The value a listing is sorted by
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortKey {
+    Time(DateTime<Utc>),
+    Int(i64),
+}
+
+/// Where the previous page ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub key: SortKey,
+    pub id: Uuid, // Breaks ties between equal keys
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let key = match &self.key {
+            SortKey::Time(time) => format!("t{}", time.to_rfc3339()),
+            SortKey::Int(n) => format!("i{}", n),
+        };
+        hex::encode(format!("{}|{}", key, self.id))
+    }
+
+    /// Decode a client's cursor, which must come from a listing with the same order
+    pub fn decode(cursor: &str, order: Order) -> DbResult<Self> {
+        let invalid = || DbError::Constraint("Invalid cursor".to_string());
+
+        let decoded = hex::decode(cursor).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let (key, id) = decoded.rsplit_once('|').ok_or_else(invalid)?;
+        let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+
+        let key = match (key.split_at_checked(1), order.by_time) {
+            (Some(("t", time)), true) => SortKey::Time(
+                DateTime::parse_from_rfc3339(time)
+                    .map_err(|_| invalid())?
+                    .into(),
+            ),
+            (Some(("i", n)), false) => SortKey::Int(n.parse().map_err(|_| invalid())?),
+            _ => {
+                return Err(DbError::Constraint(
+                    "Cursor is from a different sort order".to_string(),
+                ))
+            }
+        };
+
+        Ok(Cursor { key, id })
+    }
+}
+
+/// How a sort compares rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Order {
+    pub by_time: bool, // Keys are `SortKey::Time` rather than `SortKey::Int`
+    pub descending: bool,
+}
+
+/// The page size to use for a client's `limit`
+fn page_size(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Sort filtered rows and cut the page after `cursor` out of them
+fn paginate<T>(
+    mut rows: Vec<T>,
+    order: Order,
+    cursor: Option<&str>,
+    limit: Option<usize>,
+    key: impl Fn(&T) -> (SortKey, Uuid),
+) -> DbResult<Page<T>> {
+    let cursor = cursor.map(|c| Cursor::decode(c, order)).transpose()?;
+
+    rows.sort_by(|a, b| {
+        let ordering = key(a).cmp(&key(b));
+        if order.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+    if let Some(cursor) = cursor {
+        let after = (cursor.key, cursor.id);
+        rows.retain(|row| {
+            let row = key(row);
+            if order.descending {
+                row < after
+            } else {
+                row > after
+            }
+        });
+    }
+
+    let limit = page_size(limit);
+    rows.truncate(limit + 1);
+    Ok(Page::from_rows(rows, limit, key))
+}
+
+// ===== Bets =====
+
+/// Bet listing orders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BetSort {
+    #[default]
+    Newest,
+    Oldest,
+    Pool, // Most coins wagered first
+}
+
+impl BetSort {
+    pub fn order(self) -> Order {
+        match self {
+            BetSort::Newest => Order {
+                by_time: true,
+                descending: true,
+            },
+            BetSort::Oldest => Order {
+                by_time: true,
+                descending: false,
+            },
+            BetSort::Pool => Order {
+                by_time: false,
+                descending: true,
+            },
+        }
+    }
+
+    pub fn key(self, bet: &Bet) -> (SortKey, Uuid) {
+        let key = match self {
+            BetSort::Newest | BetSort::Oldest => SortKey::Time(bet.created_at),
+            BetSort::Pool => SortKey::Int(bet.yes_pool + bet.no_pool),
+        };
+        (key, bet.id)
+    }
+}
+
+/// Which of a market's bets to list
+///
+/// `created_after` is inclusive and `created_before` exclusive.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct BetQuery {
+    pub status: Option<BetStatus>,
+    pub subject: Option<Uuid>, // Bets about this user, "everyone" bets included
+    pub creator: Option<Uuid>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub sort: BetSort,
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+}
+
+impl BetQuery {
+    pub fn matches(&self, bet: &Bet) -> bool {
+        self.status.is_none_or(|status| bet.status == status)
+            && self.subject.is_none_or(|subject| bet.is_subject(subject))
+            && self.creator.is_none_or(|creator| bet.created_by == creator)
+            && self
+                .created_after
+                .is_none_or(|after| bet.created_at >= after)
+            && self
+                .created_before
+                .is_none_or(|before| bet.created_at < before)
+    }
+
+    pub fn limit(&self) -> usize {
+        page_size(self.limit)
+    }
+
+    pub fn cursor(&self) -> DbResult<Option<Cursor>> {
+        decode(&self.cursor, self.sort.order())
+    }
+
+    /// Filter and page a market's bets in memory
+    pub fn apply(&self, mut bets: Vec<Bet>) -> DbResult<Page<Bet>> {
+        bets.retain(|bet| self.matches(bet));
+        let sort = self.sort;
+        paginate(
+            bets,
+            sort.order(),
+            self.cursor.as_deref(),
+            self.limit,
+            |bet| sort.key(bet),
+        )
+    }
+}
+
+// ===== Wagers =====
+
+/// Wager listing orders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WagerSort {
+    #[default]
+    Newest,
+    Oldest,
+    Largest,
+}
+
+impl WagerSort {
+    pub fn order(self) -> Order {
+        match self {
+            WagerSort::Newest => Order {
+                by_time: true,
+                descending: true,
+            },
+            WagerSort::Oldest => Order {
+                by_time: true,
+                descending: false,
+            },
+            WagerSort::Largest => Order {
+                by_time: false,
+                descending: true,
+            },
+        }
+    }
+
+    pub fn key(self, wager: &Wager) -> (SortKey, Uuid) {
+        let key = match self {
+            WagerSort::Newest | WagerSort::Oldest => SortKey::Time(wager.placed_at),
+            WagerSort::Largest => SortKey::Int(wager.amount),
+        };
+        (key, wager.id)
+    }
+}
+
+/// Which of a bet's wagers to list
+///
+/// `placed_after` is inclusive and `placed_before` exclusive.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct WagerQuery {
+    pub user: Option<Uuid>,
+    pub side: Option<Side>,
+    pub placed_after: Option<DateTime<Utc>>,
+    pub placed_before: Option<DateTime<Utc>>,
+    pub sort: WagerSort,
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+}
+
+impl WagerQuery {
+    pub fn matches(&self, wager: &Wager) -> bool {
+        self.user.is_none_or(|user| wager.user_id == user)
+            && self.side.is_none_or(|side| wager.side == side)
+            && self
+                .placed_after
+                .is_none_or(|after| wager.placed_at >= after)
+            && self
+                .placed_before
+                .is_none_or(|before| wager.placed_at < before)
+    }
+
+    pub fn limit(&self) -> usize {
+        page_size(self.limit)
+    }
+
+    pub fn cursor(&self) -> DbResult<Option<Cursor>> {
+        decode(&self.cursor, self.sort.order())
+    }
+
+    /// Filter and page a bet's wagers in memory
+    pub fn apply(&self, mut wagers: Vec<Wager>) -> DbResult<Page<Wager>> {
+        wagers.retain(|wager| self.matches(wager));
+        let sort = self.sort;
+        paginate(
+            wagers,
+            sort.order(),
+            self.cursor.as_deref(),
+            self.limit,
+            |wager| sort.key(wager),
+        )
+    }
+}
+
+// ===== Users =====
+
+/// User listing orders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSort {
+    #[default]
+    Joined, // Earliest first
+    Balance, // Richest first
+}
+
+impl UserSort {
+    pub fn order(self) -> Order {
+        match self {
+            UserSort::Joined => Order {
+                by_time: true,
+                descending: false,
+            },
+            UserSort::Balance => Order {
+                by_time: false,
+                descending: true,
+            },
+        }
+    }
+
+    pub fn key(self, user: &User) -> (SortKey, Uuid) {
+        let key = match self {
+            UserSort::Joined => SortKey::Time(user.joined_at),
+            UserSort::Balance => SortKey::Int(user.balance),
+        };
+        (key, user.id)
+    }
+}
+
+/// Which of a market's users to list
+///
+/// `joined_after` is inclusive and `joined_before` exclusive.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct UserQuery {
+    pub status: Option<MembershipStatus>,
+    pub joined_after: Option<DateTime<Utc>>,
+    pub joined_before: Option<DateTime<Utc>>,
+    pub sort: UserSort,
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+}
+
+impl UserQuery {
+    pub fn matches(&self, user: &User) -> bool {
+        self.status.is_none_or(|status| user.status == status)
+            && self
+                .joined_after
+                .is_none_or(|after| user.joined_at >= after)
+            && self
+                .joined_before
+                .is_none_or(|before| user.joined_at < before)
+    }
+
+    pub fn limit(&self) -> usize {
+        page_size(self.limit)
+    }
+
+    pub fn cursor(&self) -> DbResult<Option<Cursor>> {
+        decode(&self.cursor, self.sort.order())
+    }
+
+    /// Filter and page a market's users in memory
+    pub fn apply(&self, mut users: Vec<User>) -> DbResult<Page<User>> {
+        users.retain(|user| self.matches(user));
+        let sort = self.sort;
+        paginate(
+            users,
+            sort.order(),
+            self.cursor.as_deref(),
+            self.limit,
+            |user| sort.key(user),
+        )
+    }
+}
+
+// ===== Device markets =====
+
+/// Orders for the markets a device has joined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceMarketSort {
+    #[default]
+    Recent, // Most recently joined first
+    Newest, // Most recently created market first
+}
+
+impl DeviceMarketSort {
+    pub fn order(self) -> Order {
+        Order {
+            by_time: true,
+            descending: true,
+        }
+    }
+
+    /// Keyed by the device's seat, which is unique per market
+    pub fn key(self, (market, user): &(Market, User)) -> (SortKey, Uuid) {
+        let key = match self {
+            DeviceMarketSort::Recent => SortKey::Time(user.joined_at),
+            DeviceMarketSort::Newest => SortKey::Time(market.created_at),
+        };
+        (key, user.id)
+    }
+}
+
+/// Which of a device's markets to list
+///
+/// `created_after` is inclusive and `created_before` exclusive; both refer
+/// to the market.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DeviceMarketQuery {
+    pub status: Option<MarketStatus>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub sort: DeviceMarketSort,
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+}
+
+impl DeviceMarketQuery {
+    pub fn matches(&self, (market, _): &(Market, User)) -> bool {
+        self.status.is_none_or(|status| market.status == status)
+            && self
+                .created_after
+                .is_none_or(|after| market.created_at >= after)
+            && self
+                .created_before
+                .is_none_or(|before| market.created_at < before)
+    }
+
+    pub fn limit(&self) -> usize {
+        page_size(self.limit)
+    }
+
+    pub fn cursor(&self) -> DbResult<Option<Cursor>> {
+        decode(&self.cursor, self.sort.order())
+    }
+
+    /// Filter and page a device's markets in memory
+    pub fn apply(&self, mut markets: Vec<(Market, User)>) -> DbResult<Page<(Market, User)>> {
+        markets.retain(|row| self.matches(row));
+        let sort = self.sort;
+        paginate(
+            markets,
+            sort.order(),
+            self.cursor.as_deref(),
+            self.limit,
+            |row| sort.key(row),
+        )
+    }
+}
+
+fn decode(cursor: &Option<String>, order: Order) -> DbResult<Option<Cursor>> {
+    cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, order))
+        .transpose()
+}
+
+// ===== SQL =====
+
+/// A value bound to a `?` placeholder
+#[cfg(any(feature = "sqlite", feature = "d1"))]
+#[derive(Debug, Clone)]
+pub(crate) enum SqlValue {
+    Text(String),
+    Int(i64),
+}
+
+#[cfg(any(feature = "sqlite", feature = "d1"))]
+impl From<SortKey> for SqlValue {
+    fn from(key: SortKey) -> Self {
+        match key {
+            // Timestamps are stored as RFC 3339 text, which sorts chronologically
+            SortKey::Time(time) => SqlValue::Text(time.to_rfc3339()),
+            SortKey::Int(n) => SqlValue::Int(n),
+        }
+    }
+}
+
+#[cfg(any(feature = "sqlite", feature = "d1"))]
+impl From<DateTime<Utc>> for SqlValue {
+    fn from(time: DateTime<Utc>) -> Self {
+        SqlValue::Text(time.to_rfc3339())
+    }
+}
+
+#[cfg(any(feature = "sqlite", feature = "d1"))]
+impl From<Uuid> for SqlValue {
+    fn from(id: Uuid) -> Self {
+        SqlValue::Text(id.to_string())
+    }
+}
+
+#[cfg(any(feature = "sqlite", feature = "d1"))]
+impl From<String> for SqlValue {
+    fn from(text: String) -> Self {
+        SqlValue::Text(text)
+    }
+}
+
+/// A keyset-paginated SELECT
+///
+/// Conditions are written with `?` placeholders; a `numbered` select rewrites
+/// them as Postgres's `$1`, `$2`, ... and binds the backend's own values `V`.
+#[cfg(any(feature = "sqlite", feature = "d1", feature = "postgres"))]
+pub(crate) struct Select<V> {
+    select: String,
+    conditions: Vec<String>,
+    params: Vec<V>,
+    numbered: bool,
+}
+
+#[cfg(any(feature = "sqlite", feature = "d1"))]
+impl Select<SqlValue> {
+    /// Start from a `SELECT ... FROM ...` with no `WHERE`, in SQLite's
+    /// dialect, which D1 shares
+    pub fn new(select: &str) -> Self {
+        Self {
+            select: select.to_string(),
+            conditions: Vec::new(),
+            params: Vec::new(),
+            numbered: false,
+        }
+    }
+}
+
+#[cfg(any(feature = "sqlite", feature = "d1", feature = "postgres"))]
+impl<V: From<SortKey> + From<Uuid>> Select<V> {
+    /// Start from a `SELECT ... FROM ...` with no `WHERE`, in Postgres's dialect
+    #[cfg(feature = "postgres")]
+    pub fn numbered(select: &str) -> Self {
+        Self {
+            select: select.to_string(),
+            conditions: Vec::new(),
+            params: Vec::new(),
+            numbered: true,
+        }
+    }
+
+    /// Add a condition, with a value for each of its placeholders
+    pub fn filter(&mut self, condition: &str, params: impl IntoIterator<Item = V>) {
+        let condition = if self.numbered {
+            number_placeholders(condition, self.params.len())
+        } else {
+            condition.to_string()
+        };
+        self.conditions.push(condition);
+        self.params.extend(params);
+    }
+
+    /// Add a condition if the filter is set
+    pub fn filter_by<T: Into<V>>(&mut self, condition: &str, value: Option<T>) {
+        if let Some(value) = value {
+            self.filter(condition, [value.into()]);
+        }
+    }
+
+    /// Finish the statement: rows after `cursor` in order of `key` then `id`,
+    /// one more than `limit` (see `Page::from_rows`)
+    pub fn page(
+        mut self,
+        key: &str,
+        id: &str,
+        order: Order,
+        cursor: Option<Cursor>,
+        limit: usize,
+    ) -> (String, Vec<V>) {
+        let (cmp, direction) = if order.descending {
+            ("<", "DESC")
+        } else {
+            (">", "ASC")
+        };
+        if let Some(cursor) = cursor {
+            self.filter(
+                &format!("({key} {cmp} ? OR ({key} = ? AND {id} {cmp} ?))"),
+                [
+                    cursor.key.clone().into(),
+                    cursor.key.into(),
+                    cursor.id.into(),
+                ],
+            );
+        }
+
+        let mut sql = self.select;
+        if !self.conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&self.conditions.join(" AND "));
+        }
+        sql.push_str(&format!(
+            " ORDER BY {key} {direction}, {id} {direction} LIMIT {}",
+            limit + 1
+        ));
+        (sql, self.params)
+    }
+}
+
+/// Rewrite a condition's `?` placeholders as `$n`, after `bound` earlier ones
+#[cfg(any(feature = "sqlite", feature = "d1", feature = "postgres"))]
+fn number_placeholders(condition: &str, bound: usize) -> String {
+    let mut n = bound;
+    let mut numbered = String::with_capacity(condition.len());
+    for c in condition.chars() {
+        if c == '?' {
+            n += 1;
+            numbered.push_str(&format!("${}", n));
+        } else {
+            numbered.push(c);
+        }
+    }
+    numbered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn bet(minutes: i64, pool: i64) -> Bet {
+        Bet {
+            id: Uuid::new_v4(),
+            market_id: Uuid::nil(),
+            subject_user_ids: vec![],
+            about_everyone: true,
+            created_by: Uuid::nil(),
+            description: String::new(),
+            initial_odds: "1:1".to_string(),
+            status: BetStatus::Active,
+            yes_pool: pool,
+            no_pool: 0,
+            hide_from_subject: false,
+            created_at: DateTime::UNIX_EPOCH + Duration::minutes(minutes),
+            resolved_at: None,
+        }
+    }
+
+    #[test]
+    fn test_pages_follow_cursors_to_the_end() {
+        let bets: Vec<Bet> = (0..5).map(|i| bet(i, 10)).collect();
+        let mut query = BetQuery {
+            sort: BetSort::Oldest,
+            limit: Some(2),
+            ..Default::default()
+        };
+
+        let mut seen = Vec::new();
+        loop {
+            let page = query.apply(bets.clone()).unwrap();
+            seen.extend(page.items.iter().map(|b| b.id));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, bets.iter().map(|b| b.id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_ties_are_broken_by_id() {
+        let bets: Vec<Bet> = (0..4).map(|_| bet(0, 50)).collect();
+        let query = BetQuery {
+            sort: BetSort::Pool,
+            limit: Some(3),
+            ..Default::default()
+        };
+        let first = query.apply(bets.clone()).unwrap();
+        let rest = BetQuery {
+            cursor: first.next_cursor.clone(),
+            ..query
+        }
+        .apply(bets.clone())
+        .unwrap();
+
+        assert_eq!(first.items.len(), 3);
+        assert_eq!(rest.items.len(), 1);
+        assert!(rest.next_cursor.is_none());
+        assert!(first.items.iter().all(|b| b.id > rest.items[0].id));
+    }
+
+    #[test]
+    fn test_cursors_are_checked() {
+        let cursor = Cursor {
+            key: SortKey::Int(30),
+            id: Uuid::new_v4(),
+        };
+        let encoded = cursor.encode();
+
+        assert_eq!(
+            Cursor::decode(&encoded, BetSort::Pool.order()).unwrap(),
+            cursor
+        );
+        assert!(matches!(
+            Cursor::decode(&encoded, BetSort::Newest.order()),
+            Err(DbError::Constraint(_))
+        ));
+        assert!(matches!(
+            Cursor::decode("not-a-cursor", BetSort::Pool.order()),
+            Err(DbError::Constraint(_))
+        ));
+    }
+
+    #[test]
+    fn test_placeholders_are_numbered_after_earlier_ones() {
+        assert_eq!(
+            number_placeholders("(key < ? OR (key = ? AND id < ?))", 2),
+            "(key < $3 OR (key = $4 AND id < $5))"
+        );
+    }
+}
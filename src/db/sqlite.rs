@@ -1,8 +1,21 @@
 /// SQLite implementation of the Database trait
+use crate::db::migrations::{self, Migration, MigrationStatus, MIGRATIONS, MIGRATOR};
+use crate::db::query::{
+    BetQuery, BetSort, DeviceMarketQuery, DeviceMarketSort, Page, Select, SqlValue, UserQuery,
+    UserSort, WagerQuery, WagerSort,
+};
 use crate::db::r#trait::{Database, DbError, DbResult};
-use crate::domain::models::{Bet, BetStatus, BetView, Market, MarketStatus, Side, User, Wager};
+use crate::domain::archive::MarketArchive;
+use crate::domain::models::{
+    AuditEntry, Bet, BetStatus, BetView, LinkCode, Market, MarketContents, MarketStatus,
+    MarketTemplate, MembershipStatus, Player, Removal, RevealCeremony, Season, Side, Spectator,
+    User, Wager, Webhook, WebhookDelivery,
+};
 use async_trait::async_trait;
-use sqlx::{sqlite::SqlitePool, Row};
+use chrono::{DateTime, Utc};
+use sqlx::migrate::Migrate;
+use sqlx::sqlite::{Sqlite, SqliteArguments, SqlitePool};
+use sqlx::Row;
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -16,83 +29,287 @@ impl SqliteDatabase {
         Ok(Self { pool })
     }
 
-    pub async fn run_migrations(&self) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS markets (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                status TEXT NOT NULL,
-                created_by TEXT NOT NULL,
-                opens_at TEXT NOT NULL,
-                closes_at TEXT NOT NULL,
-                starting_balance INTEGER NOT NULL,
-                invite_code TEXT NOT NULL UNIQUE,
-                created_at TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS users (
-                id TEXT PRIMARY KEY,
-                market_id TEXT NOT NULL,
-                device_id TEXT NOT NULL,
-                display_name TEXT NOT NULL,
-                avatar TEXT NOT NULL,
-                balance INTEGER NOT NULL,
-                is_admin INTEGER NOT NULL,
-                joined_at TEXT NOT NULL,
-                FOREIGN KEY (market_id) REFERENCES markets(id),
-                UNIQUE(market_id, device_id)
-            );
-
-            CREATE TABLE IF NOT EXISTS bets (
-                id TEXT PRIMARY KEY,
-                market_id TEXT NOT NULL,
-                subject_user_id TEXT NOT NULL,
-                created_by TEXT NOT NULL,
-                description TEXT NOT NULL,
-                initial_odds TEXT NOT NULL,
-                status TEXT NOT NULL,
-                yes_pool INTEGER NOT NULL,
-                no_pool INTEGER NOT NULL,
-                hide_from_subject INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL,
-                resolved_at TEXT,
-                FOREIGN KEY (market_id) REFERENCES markets(id),
-                FOREIGN KEY (subject_user_id) REFERENCES users(id),
-                FOREIGN KEY (created_by) REFERENCES users(id)
-            );
-
-            CREATE TABLE IF NOT EXISTS wagers (
-                id TEXT PRIMARY KEY,
-                bet_id TEXT NOT NULL,
-                user_id TEXT NOT NULL,
-                side TEXT NOT NULL,
-                amount INTEGER NOT NULL,
-                placed_at TEXT NOT NULL,
-                yes_pool_after INTEGER NOT NULL,
-                no_pool_after INTEGER NOT NULL,
-                probability_after REAL NOT NULL,
-                FOREIGN KEY (bet_id) REFERENCES bets(id),
-                FOREIGN KEY (user_id) REFERENCES users(id)
-            );
-
-            CREATE INDEX IF NOT EXISTS idx_users_market ON users(market_id);
-            CREATE INDEX IF NOT EXISTS idx_users_device ON users(market_id, device_id);
-            CREATE INDEX IF NOT EXISTS idx_bets_market ON bets(market_id);
-            CREATE INDEX IF NOT EXISTS idx_bets_status ON bets(status);
-            CREATE INDEX IF NOT EXISTS idx_bets_subject ON bets(subject_user_id);
-            CREATE INDEX IF NOT EXISTS idx_wagers_bet ON wagers(bet_id);
-            CREATE INDEX IF NOT EXISTS idx_wagers_user ON wagers(user_id);
-            "#,
+    /// Apply pending migrations with sqlx's migrator, returning the ones that ran
+    pub async fn run_migrations(&self) -> Result<Vec<&'static Migration>, sqlx::Error> {
+        self.baseline_existing_schema().await?;
+
+        let applied = self.applied_migrations().await?;
+        let versions: Vec<i64> = applied.iter().map(|(version, _)| *version).collect();
+        let pending = migrations::pending(MIGRATIONS, &versions);
+
+        // Rebuilding a table (see 004) drops rows other tables point at, so
+        // run with foreign keys off, as SQLite advises, and check them after
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await?;
+        let result = MIGRATOR.run(&mut *conn).await;
+        sqlx::query("PRAGMA foreign_keys = ON")
+            .execute(&mut *conn)
+            .await?;
+        result.map_err(|e| sqlx::Error::Migrate(Box::new(e)))?;
+
+        let violations = sqlx::query("PRAGMA foreign_key_check")
+            .fetch_all(&mut *conn)
+            .await?;
+        if !violations.is_empty() {
+            return Err(sqlx::Error::Protocol(format!(
+                "{} rows break foreign keys after migrating",
+                violations.len()
+            )));
+        }
+
+        Ok(pending)
+    }
+
+    /// Every known migration and whether it has been applied
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+        self.baseline_existing_schema().await?;
+        Ok(migrations::status(
+            MIGRATIONS,
+            &self.applied_migrations().await?,
+        ))
+    }
+
+    async fn has_table(&self, name: &str) -> Result<bool, sqlx::Error> {
+        Ok(
+            sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?")
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some(),
         )
-        .execute(&self.pool)
+    }
+
+    async fn applied_migrations(&self) -> Result<Vec<(i64, String)>, sqlx::Error> {
+        if !self.has_table("_sqlx_migrations").await? {
+            return Ok(Vec::new());
+        }
+
+        let rows = sqlx::query(
+            "SELECT version, installed_on FROM _sqlx_migrations WHERE success ORDER BY version",
+        )
+        .fetch_all(&self.pool)
         .await?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get("version"),
+                    row.get::<DateTime<Utc>, _>("installed_on").to_rfc3339(),
+                )
+            })
+            .collect())
+    }
 
-        Ok(())
+    /// Record the versions a database from before versioned migrations
+    /// already has (see `migrations::baseline`), so the migrator runs the rest
+    async fn baseline_existing_schema(&self) -> Result<(), sqlx::Error> {
+        if self.has_table("_sqlx_migrations").await? || !self.has_table("markets").await? {
+            return Ok(());
+        }
+
+        let has_hide_from_subject =
+            sqlx::query("SELECT 1 FROM pragma_table_info('bets') WHERE name = 'hide_from_subject'")
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+        let versions = migrations::baseline(has_hide_from_subject);
+
+        let mut tx = self.pool.begin().await?;
+        tx.ensure_migrations_table()
+            .await
+            .map_err(|e| sqlx::Error::Migrate(Box::new(e)))?;
+        for migration in MIGRATOR.iter().filter(|m| versions.contains(&m.version)) {
+            sqlx::query(
+                "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+                 VALUES (?, ?, TRUE, ?, 0)",
+            )
+            .bind(migration.version)
+            .bind(&*migration.description)
+            .bind(&*migration.checksum)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+}
+
+/// Bet columns plus the comma-separated subject ids from `bet_subjects`
+const SELECT_BETS: &str = "SELECT b.*, \
+     (SELECT group_concat(bs.user_id) FROM bet_subjects bs WHERE bs.bet_id = b.id) AS subject_user_ids \
+     FROM bets b";
+
+fn bet_from_row(row: &sqlx::sqlite::SqliteRow) -> Bet {
+    Bet {
+        id: Uuid::parse_str(row.get("id")).unwrap(),
+        market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
+        subject_user_ids: row
+            .get::<Option<String>, _>("subject_user_ids")
+            .map(|ids| {
+                ids.split(',')
+                    .map(|id| Uuid::parse_str(id).unwrap())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        about_everyone: row.get::<i64, _>("about_everyone") != 0,
+        created_by: Uuid::parse_str(row.get("created_by")).unwrap(),
+        description: row.get("description"),
+        initial_odds: row.get("initial_odds"),
+        status: deserialize_bet_status(row.get("status")),
+        yes_pool: row.get("yes_pool"),
+        no_pool: row.get("no_pool"),
+        hide_from_subject: row.get::<i64, _>("hide_from_subject") != 0,
+        created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
+            .unwrap()
+            .into(),
+        resolved_at: row
+            .get::<Option<String>, _>("resolved_at")
+            .map(|s| chrono::DateTime::parse_from_rfc3339(&s).unwrap().into()),
+    }
+}
+
+/// A device's seats joined with their markets, columns renamed apart
+const SELECT_DEVICE_MARKETS: &str = "SELECT \
+     m.id as market_id, m.name, m.status, m.created_by, m.opens_at, m.closes_at, \
+     m.starting_balance, m.invite_code, m.created_at, \
+     u.id as user_id, u.market_id as u_market_id, u.device_id, u.display_name, \
+     u.avatar, u.balance, u.is_admin, u.status as user_status, u.player_id, u.joined_at \
+     FROM users u JOIN markets m ON u.market_id = m.id";
+
+fn device_market_from_row(row: &sqlx::sqlite::SqliteRow) -> (Market, User) {
+    let market = Market {
+        id: Uuid::parse_str(row.get("market_id")).unwrap(),
+        name: row.get("name"),
+        status: deserialize_market_status(row.get("status")),
+        created_by: Uuid::parse_str(row.get("created_by")).unwrap(),
+        opens_at: chrono::DateTime::parse_from_rfc3339(row.get("opens_at"))
+            .unwrap()
+            .into(),
+        closes_at: chrono::DateTime::parse_from_rfc3339(row.get("closes_at"))
+            .unwrap()
+            .into(),
+        starting_balance: row.get("starting_balance"),
+        invite_code: row.get("invite_code"),
+        created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
+            .unwrap()
+            .into(),
+    };
+    let user = User {
+        id: Uuid::parse_str(row.get("user_id")).unwrap(),
+        market_id: Uuid::parse_str(row.get("u_market_id")).unwrap(),
+        device_id: row.get("device_id"),
+        display_name: row.get("display_name"),
+        avatar: row.get("avatar"),
+        balance: row.get("balance"),
+        is_admin: row.get::<i64, _>("is_admin") != 0,
+        status: deserialize_membership_status(row.get("user_status")),
+        player_id: row
+            .get::<Option<String>, _>("player_id")
+            .map(|id| Uuid::parse_str(&id).unwrap()),
+        joined_at: chrono::DateTime::parse_from_rfc3339(row.get("joined_at"))
+            .unwrap()
+            .into(),
+    };
+    (market, user)
+}
+
+fn wager_from_row(row: &sqlx::sqlite::SqliteRow) -> Wager {
+    Wager {
+        id: Uuid::parse_str(row.get("id")).unwrap(),
+        bet_id: Uuid::parse_str(row.get("bet_id")).unwrap(),
+        user_id: Uuid::parse_str(row.get("user_id")).unwrap(),
+        side: deserialize_side(row.get("side")),
+        amount: row.get("amount"),
+        placed_at: chrono::DateTime::parse_from_rfc3339(row.get("placed_at"))
+            .unwrap()
+            .into(),
+        yes_pool_after: row.get("yes_pool_after"),
+        no_pool_after: row.get("no_pool_after"),
+        probability_after: row.get("probability_after"),
+        refunded: row.get::<i64, _>("refunded") != 0,
+    }
+}
+
+/// Bind a `query::Select`'s parameters in order
+fn bind_all<'q>(
+    mut query: sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>>,
+    params: Vec<SqlValue>,
+) -> sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>> {
+    for param in params {
+        query = match param {
+            SqlValue::Text(text) => query.bind(text),
+            SqlValue::Int(n) => query.bind(n),
+        };
     }
+    query
 }
 
 // Helper functions for serialization
+fn market_from_row(row: &sqlx::sqlite::SqliteRow) -> Market {
+    Market {
+        id: Uuid::parse_str(row.get("id")).unwrap(),
+        name: row.get("name"),
+        status: deserialize_market_status(row.get("status")),
+        created_by: Uuid::parse_str(row.get("created_by")).unwrap(),
+        opens_at: chrono::DateTime::parse_from_rfc3339(row.get("opens_at"))
+            .unwrap()
+            .into(),
+        closes_at: chrono::DateTime::parse_from_rfc3339(row.get("closes_at"))
+            .unwrap()
+            .into(),
+        starting_balance: row.get("starting_balance"),
+        invite_code: row.get("invite_code"),
+        created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
+            .unwrap()
+            .into(),
+    }
+}
+
+fn user_from_row(row: &sqlx::sqlite::SqliteRow) -> User {
+    User {
+        id: Uuid::parse_str(row.get("id")).unwrap(),
+        market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
+        device_id: row.get("device_id"),
+        display_name: row.get("display_name"),
+        avatar: row.get("avatar"),
+        balance: row.get("balance"),
+        is_admin: row.get::<i64, _>("is_admin") != 0,
+        status: deserialize_membership_status(row.get("status")),
+        player_id: row
+            .get::<Option<String>, _>("player_id")
+            .map(|id| Uuid::parse_str(&id).unwrap()),
+        joined_at: chrono::DateTime::parse_from_rfc3339(row.get("joined_at"))
+            .unwrap()
+            .into(),
+    }
+}
+
+fn season_from_row(row: &sqlx::sqlite::SqliteRow) -> Season {
+    Season {
+        id: Uuid::parse_str(row.get("id")).unwrap(),
+        name: row.get("name"),
+        carry_over_balances: row.get::<i64, _>("carry_over_balances") != 0,
+        created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
+            .unwrap()
+            .into(),
+    }
+}
+
+fn webhook_from_row(row: &sqlx::sqlite::SqliteRow) -> DbResult<Webhook> {
+    Ok(Webhook {
+        id: Uuid::parse_str(row.get("id")).unwrap(),
+        market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
+        url: row.get("url"),
+        secret: row.get("secret"),
+        events: serde_json::from_str(row.get("events"))
+            .map_err(|e| DbError::Internal(e.to_string()))?,
+        created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
+            .unwrap()
+            .into(),
+    })
+}
+
 fn serialize_market_status(status: MarketStatus) -> String {
     match status {
         MarketStatus::Draft => "draft".to_string(),
@@ -119,6 +336,7 @@ fn serialize_bet_status(status: BetStatus) -> String {
         BetStatus::ResolvedYes => "resolved_yes".to_string(),
         BetStatus::ResolvedNo => "resolved_no".to_string(),
         BetStatus::Challenged => "challenged".to_string(),
+        BetStatus::Void => "void".to_string(),
     }
 }
 
@@ -129,10 +347,29 @@ fn deserialize_bet_status(s: &str) -> BetStatus {
         "resolved_yes" => BetStatus::ResolvedYes,
         "resolved_no" => BetStatus::ResolvedNo,
         "challenged" => BetStatus::Challenged,
+        "void" => BetStatus::Void,
         _ => BetStatus::Pending,
     }
 }
 
+fn serialize_membership_status(status: MembershipStatus) -> String {
+    match status {
+        MembershipStatus::Active => "active".to_string(),
+        MembershipStatus::Left => "left".to_string(),
+        MembershipStatus::Kicked => "kicked".to_string(),
+        MembershipStatus::Banned => "banned".to_string(),
+    }
+}
+
+fn deserialize_membership_status(s: &str) -> MembershipStatus {
+    match s {
+        "left" => MembershipStatus::Left,
+        "kicked" => MembershipStatus::Kicked,
+        "banned" => MembershipStatus::Banned,
+        _ => MembershipStatus::Active,
+    }
+}
+
 fn serialize_side(side: Side) -> String {
     match side {
         Side::Yes => "YES".to_string(),
@@ -148,27 +385,175 @@ fn deserialize_side(s: &str) -> Side {
     }
 }
 
+// ===== Inserts (shared by the create_ methods and import_archive) =====
+
+type Insert<'q> = sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>>;
+
+fn insert_market(market: &Market) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO markets (id, name, status, created_by, opens_at, closes_at, starting_balance, invite_code, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(market.id.to_string())
+    .bind(&market.name)
+    .bind(serialize_market_status(market.status))
+    .bind(market.created_by.to_string())
+    .bind(market.opens_at.to_rfc3339())
+    .bind(market.closes_at.to_rfc3339())
+    .bind(market.starting_balance)
+    .bind(&market.invite_code)
+    .bind(market.created_at.to_rfc3339())
+}
+
+fn insert_user(user: &User) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO users (id, market_id, device_id, display_name, avatar, balance, is_admin, status, player_id, joined_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(user.id.to_string())
+    .bind(user.market_id.to_string())
+    .bind(&user.device_id)
+    .bind(&user.display_name)
+    .bind(&user.avatar)
+    .bind(user.balance)
+    .bind(user.is_admin as i64)
+    .bind(serialize_membership_status(user.status))
+    .bind(user.player_id.map(|id| id.to_string()))
+    .bind(user.joined_at.to_rfc3339())
+}
+
+fn insert_player(player: &Player) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO players (id, display_name, avatar, created_at)
+        VALUES (?, ?, ?, ?)
+        "#,
+    )
+    .bind(player.id.to_string())
+    .bind(&player.display_name)
+    .bind(&player.avatar)
+    .bind(player.created_at.to_rfc3339())
+}
+
+fn insert_season(season: &Season) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO seasons (id, name, carry_over_balances, created_at)
+        VALUES (?, ?, ?, ?)
+        "#,
+    )
+    .bind(season.id.to_string())
+    .bind(&season.name)
+    .bind(season.carry_over_balances as i64)
+    .bind(season.created_at.to_rfc3339())
+}
+
+fn insert_spectator(spectator: &Spectator) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO spectators (id, market_id, display_name, token, joined_at)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(spectator.id.to_string())
+    .bind(spectator.market_id.to_string())
+    .bind(&spectator.display_name)
+    .bind(&spectator.token)
+    .bind(spectator.joined_at.to_rfc3339())
+}
+
+fn insert_wager(wager: &Wager) -> Insert<'_> {
+    sqlx::query(
+        r#"
+        INSERT INTO wagers (id, bet_id, user_id, side, amount, placed_at, yes_pool_after, no_pool_after, probability_after, refunded)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(wager.id.to_string())
+    .bind(wager.bet_id.to_string())
+    .bind(wager.user_id.to_string())
+    .bind(serialize_side(wager.side))
+    .bind(wager.amount)
+    .bind(wager.placed_at.to_rfc3339())
+    .bind(wager.yes_pool_after)
+    .bind(wager.no_pool_after)
+    .bind(wager.probability_after)
+    .bind(wager.refunded as i64)
+}
+
+/// The bet row followed by one row per subject
+fn insert_bet(bet: &Bet) -> Vec<Insert<'_>> {
+    let mut inserts = vec![sqlx::query(
+        r#"
+        INSERT INTO bets (id, market_id, created_by, description, initial_odds, status, yes_pool, no_pool, hide_from_subject, about_everyone, created_at, resolved_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(bet.id.to_string())
+    .bind(bet.market_id.to_string())
+    .bind(bet.created_by.to_string())
+    .bind(&bet.description)
+    .bind(&bet.initial_odds)
+    .bind(serialize_bet_status(bet.status))
+    .bind(bet.yes_pool)
+    .bind(bet.no_pool)
+    .bind(bet.hide_from_subject as i64)
+    .bind(bet.about_everyone as i64)
+    .bind(bet.created_at.to_rfc3339())
+    .bind(bet.resolved_at.map(|d| d.to_rfc3339()))];
+
+    for subject_id in &bet.subject_user_ids {
+        inserts.push(
+            sqlx::query("INSERT INTO bet_subjects (bet_id, user_id) VALUES (?, ?)")
+                .bind(bet.id.to_string())
+                .bind(subject_id.to_string()),
+        );
+    }
+    inserts
+}
+
+fn insert_reveal_ceremony(ceremony: &RevealCeremony) -> DbResult<Insert<'_>> {
+    let bet_ids =
+        serde_json::to_string(&ceremony.bet_ids).map_err(|e| DbError::Internal(e.to_string()))?;
+
+    Ok(sqlx::query(
+        r#"
+        INSERT INTO reveal_ceremonies (market_id, bet_ids, revealed_count, started_at, completed_at)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(ceremony.market_id.to_string())
+    .bind(bet_ids)
+    .bind(ceremony.revealed_count as i64)
+    .bind(ceremony.started_at.to_rfc3339())
+    .bind(ceremony.completed_at.map(|d| d.to_rfc3339())))
+}
+
+fn insert_season_market<'q>(season_id: Uuid, market_id: Uuid, position: usize) -> Insert<'q> {
+    sqlx::query("INSERT INTO season_markets (market_id, season_id, position) VALUES (?, ?, ?)")
+        .bind(market_id.to_string())
+        .bind(season_id.to_string())
+        .bind(position as i64)
+}
+
+fn insert_carry_over<'q>(market_id: Uuid, user_id: Uuid, amount: i64) -> Insert<'q> {
+    sqlx::query("INSERT INTO season_carry_overs (user_id, market_id, amount) VALUES (?, ?, ?)")
+        .bind(user_id.to_string())
+        .bind(market_id.to_string())
+        .bind(amount)
+}
+
 #[async_trait]
 impl Database for SqliteDatabase {
     async fn create_market(&self, market: Market) -> DbResult<Market> {
-        sqlx::query(
-            r#"
-            INSERT INTO markets (id, name, status, created_by, opens_at, closes_at, starting_balance, invite_code, created_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(market.id.to_string())
-        .bind(&market.name)
-        .bind(serialize_market_status(market.status))
-        .bind(market.created_by.to_string())
-        .bind(market.opens_at.to_rfc3339())
-        .bind(market.closes_at.to_rfc3339())
-        .bind(market.starting_balance)
-        .bind(&market.invite_code)
-        .bind(market.created_at.to_rfc3339())
-        .execute(&self.pool)
-        .await
-        .map_err(|e| DbError::Internal(e.to_string()))?;
+        insert_market(&market)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
 
         Ok(market)
     }
@@ -180,23 +565,7 @@ impl Database for SqliteDatabase {
             .await
             .map_err(|_| DbError::NotFound("Market not found".to_string()))?;
 
-        Ok(Market {
-            id: Uuid::parse_str(row.get("id")).unwrap(),
-            name: row.get("name"),
-            status: deserialize_market_status(row.get("status")),
-            created_by: Uuid::parse_str(row.get("created_by")).unwrap(),
-            opens_at: chrono::DateTime::parse_from_rfc3339(row.get("opens_at"))
-                .unwrap()
-                .into(),
-            closes_at: chrono::DateTime::parse_from_rfc3339(row.get("closes_at"))
-                .unwrap()
-                .into(),
-            starting_balance: row.get("starting_balance"),
-            invite_code: row.get("invite_code"),
-            created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
-                .unwrap()
-                .into(),
-        })
+        Ok(market_from_row(&row))
     }
 
     async fn get_market_by_invite_code(&self, code: &str) -> DbResult<Market> {
@@ -206,23 +575,7 @@ impl Database for SqliteDatabase {
             .await
             .map_err(|_| DbError::NotFound("Market not found".to_string()))?;
 
-        Ok(Market {
-            id: Uuid::parse_str(row.get("id")).unwrap(),
-            name: row.get("name"),
-            status: deserialize_market_status(row.get("status")),
-            created_by: Uuid::parse_str(row.get("created_by")).unwrap(),
-            opens_at: chrono::DateTime::parse_from_rfc3339(row.get("opens_at"))
-                .unwrap()
-                .into(),
-            closes_at: chrono::DateTime::parse_from_rfc3339(row.get("closes_at"))
-                .unwrap()
-                .into(),
-            starting_balance: row.get("starting_balance"),
-            invite_code: row.get("invite_code"),
-            created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
-                .unwrap()
-                .into(),
-        })
+        Ok(market_from_row(&row))
     }
 
     async fn update_market_status(&self, id: Uuid, status: MarketStatus) -> DbResult<()> {
@@ -238,7 +591,9 @@ impl Database for SqliteDatabase {
     async fn delete_market(&self, id: Uuid) -> DbResult<()> {
         let id_str = id.to_string();
 
-        // Delete in order: wagers -> bets -> users -> market
+        // Delete in order: wagers -> bet subjects -> bets -> reveal ceremony
+        // -> spectators -> webhook deliveries -> webhooks -> season links
+        // -> users -> market
         sqlx::query(
             r#"
             DELETE FROM wagers WHERE bet_id IN (SELECT id FROM bets WHERE market_id = ?)
@@ -249,46 +604,145 @@ impl Database for SqliteDatabase {
         .await
         .map_err(|e| DbError::Internal(e.to_string()))?;
 
+        sqlx::query(
+            r#"
+            DELETE FROM bet_subjects WHERE bet_id IN (SELECT id FROM bets WHERE market_id = ?)
+            "#,
+        )
+        .bind(&id_str)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
         sqlx::query("DELETE FROM bets WHERE market_id = ?")
             .bind(&id_str)
             .execute(&self.pool)
             .await
             .map_err(|e| DbError::Internal(e.to_string()))?;
 
-        sqlx::query("DELETE FROM users WHERE market_id = ?")
+        sqlx::query("DELETE FROM reveal_ceremonies WHERE market_id = ?")
             .bind(&id_str)
             .execute(&self.pool)
             .await
             .map_err(|e| DbError::Internal(e.to_string()))?;
 
-        sqlx::query("DELETE FROM markets WHERE id = ?")
+        sqlx::query("DELETE FROM spectators WHERE market_id = ?")
             .bind(&id_str)
             .execute(&self.pool)
             .await
             .map_err(|e| DbError::Internal(e.to_string()))?;
 
-        Ok(())
-    }
-
-    async fn create_user(&self, user: User) -> DbResult<User> {
         sqlx::query(
             r#"
-            INSERT INTO users (id, market_id, device_id, display_name, avatar, balance, is_admin, joined_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            DELETE FROM webhook_deliveries WHERE webhook_id IN (SELECT id FROM webhooks WHERE market_id = ?)
             "#,
         )
-        .bind(user.id.to_string())
-        .bind(user.market_id.to_string())
-        .bind(&user.device_id)
-        .bind(&user.display_name)
-        .bind(&user.avatar)
-        .bind(user.balance)
-        .bind(user.is_admin as i64)
-        .bind(user.joined_at.to_rfc3339())
+        .bind(&id_str)
         .execute(&self.pool)
         .await
         .map_err(|e| DbError::Internal(e.to_string()))?;
 
+        sqlx::query("DELETE FROM webhooks WHERE market_id = ?")
+            .bind(&id_str)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        sqlx::query("DELETE FROM season_carry_overs WHERE market_id = ?")
+            .bind(&id_str)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        sqlx::query("DELETE FROM season_markets WHERE market_id = ?")
+            .bind(&id_str)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        sqlx::query("DELETE FROM users WHERE market_id = ?")
+            .bind(&id_str)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        sqlx::query("DELETE FROM markets WHERE id = ?")
+            .bind(&id_str)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn import_archive(&self, archive: &MarketArchive) -> DbResult<()> {
+        let internal = |e: sqlx::Error| DbError::Internal(e.to_string());
+        let market_id = archive.market.id;
+        let mut tx = self.pool.begin().await.map_err(internal)?;
+
+        for player in &archive.players {
+            let exists = sqlx::query("SELECT 1 FROM players WHERE id = ?")
+                .bind(player.id.to_string())
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(internal)?
+                .is_some();
+            if !exists {
+                insert_player(player)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(internal)?;
+            }
+        }
+        if let Some(membership) = &archive.season {
+            let exists = sqlx::query("SELECT 1 FROM seasons WHERE id = ?")
+                .bind(membership.season.id.to_string())
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(internal)?
+                .is_some();
+            if !exists {
+                insert_season(&membership.season)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(internal)?;
+            }
+        }
+
+        let mut inserts = vec![insert_market(&archive.market)];
+        inserts.extend(archive.users.iter().map(insert_user));
+        inserts.extend(archive.bets.iter().flat_map(insert_bet));
+        inserts.extend(archive.wagers.iter().map(insert_wager));
+        inserts.extend(archive.spectators.iter().map(insert_spectator));
+        if let Some(ceremony) = &archive.reveal_ceremony {
+            inserts.push(insert_reveal_ceremony(ceremony)?);
+        }
+        if let Some(membership) = &archive.season {
+            inserts.push(insert_season_market(
+                membership.season.id,
+                market_id,
+                membership.position,
+            ));
+        }
+        inserts.extend(
+            archive
+                .carry_overs
+                .iter()
+                .map(|c| insert_carry_over(market_id, c.user_id, c.amount)),
+        );
+
+        for insert in inserts {
+            insert.execute(&mut *tx).await.map_err(internal)?;
+        }
+        tx.commit().await.map_err(internal)
+    }
+
+    async fn create_user(&self, user: User) -> DbResult<User> {
+        insert_user(&user)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
         Ok(user)
     }
 
@@ -299,18 +753,7 @@ impl Database for SqliteDatabase {
             .await
             .map_err(|_| DbError::NotFound("User not found".to_string()))?;
 
-        Ok(User {
-            id: Uuid::parse_str(row.get("id")).unwrap(),
-            market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
-            device_id: row.get("device_id"),
-            display_name: row.get("display_name"),
-            avatar: row.get("avatar"),
-            balance: row.get("balance"),
-            is_admin: row.get::<i64, _>("is_admin") != 0,
-            joined_at: chrono::DateTime::parse_from_rfc3339(row.get("joined_at"))
-                .unwrap()
-                .into(),
-        })
+        Ok(user_from_row(&row))
     }
 
     async fn get_user_by_device_id(&self, market_id: Uuid, device_id: &str) -> DbResult<User> {
@@ -321,18 +764,7 @@ impl Database for SqliteDatabase {
             .await
             .map_err(|_| DbError::NotFound("User not found".to_string()))?;
 
-        Ok(User {
-            id: Uuid::parse_str(row.get("id")).unwrap(),
-            market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
-            device_id: row.get("device_id"),
-            display_name: row.get("display_name"),
-            avatar: row.get("avatar"),
-            balance: row.get("balance"),
-            is_admin: row.get::<i64, _>("is_admin") != 0,
-            joined_at: chrono::DateTime::parse_from_rfc3339(row.get("joined_at"))
-                .unwrap()
-                .into(),
-        })
+        Ok(user_from_row(&row))
     }
 
     async fn get_users_in_market(&self, market_id: Uuid) -> DbResult<Vec<User>> {
@@ -342,21 +774,42 @@ impl Database for SqliteDatabase {
             .await
             .map_err(|e| DbError::Internal(e.to_string()))?;
 
-        Ok(rows
-            .iter()
-            .map(|row| User {
-                id: Uuid::parse_str(row.get("id")).unwrap(),
-                market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
-                device_id: row.get("device_id"),
-                display_name: row.get("display_name"),
-                avatar: row.get("avatar"),
-                balance: row.get("balance"),
-                is_admin: row.get::<i64, _>("is_admin") != 0,
-                joined_at: chrono::DateTime::parse_from_rfc3339(row.get("joined_at"))
-                    .unwrap()
-                    .into(),
-            })
-            .collect())
+        Ok(rows.iter().map(user_from_row).collect())
+    }
+
+    async fn list_users_in_market(
+        &self,
+        market_id: Uuid,
+        query: &UserQuery,
+    ) -> DbResult<Page<User>> {
+        let mut select = Select::new("SELECT * FROM users");
+        select.filter("market_id = ?", [market_id.into()]);
+        select.filter_by("status = ?", query.status.map(serialize_membership_status));
+        select.filter_by("joined_at >= ?", query.joined_after);
+        select.filter_by("joined_at < ?", query.joined_before);
+        let key = match query.sort {
+            UserSort::Joined => "joined_at",
+            UserSort::Balance => "balance",
+        };
+        let (sql, params) = select.page(
+            key,
+            "id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
+
+        let rows = bind_all(sqlx::query(&sql), params)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.iter().map(user_from_row).collect(),
+            query.limit(),
+            |user| sort.key(user),
+        ))
     }
 
     async fn update_user_balance(&self, user_id: Uuid, new_balance: i64) -> DbResult<()> {
@@ -369,189 +822,697 @@ impl Database for SqliteDatabase {
         Ok(())
     }
 
+    async fn update_user_status(&self, user_id: Uuid, status: MembershipStatus) -> DbResult<()> {
+        sqlx::query("UPDATE users SET status = ? WHERE id = ?")
+            .bind(serialize_membership_status(status))
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        display_name: &str,
+        avatar: &str,
+    ) -> DbResult<()> {
+        sqlx::query("UPDATE users SET display_name = ?, avatar = ? WHERE id = ?")
+            .bind(display_name)
+            .bind(avatar)
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn update_user_device(&self, user_id: Uuid, device_id: &str) -> DbResult<()> {
+        sqlx::query("UPDATE users SET device_id = ? WHERE id = ?")
+            .bind(device_id)
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+        Ok(())
+    }
+
     async fn get_markets_by_device_id(&self, device_id: &str) -> DbResult<Vec<(Market, User)>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT
-                m.id as market_id, m.name, m.status, m.created_by, m.opens_at, m.closes_at,
-                m.starting_balance, m.invite_code, m.created_at,
-                u.id as user_id, u.market_id as u_market_id, u.device_id, u.display_name,
-                u.avatar, u.balance, u.is_admin, u.joined_at
-            FROM users u
-            JOIN markets m ON u.market_id = m.id
-            WHERE u.device_id = ?
-            ORDER BY u.joined_at DESC
-            LIMIT 10
-            "#,
-        )
+        let rows = sqlx::query(&format!(
+            "{} WHERE u.device_id = ? ORDER BY u.joined_at DESC LIMIT 10",
+            SELECT_DEVICE_MARKETS
+        ))
         .bind(device_id)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| DbError::Internal(e.to_string()))?;
 
-        Ok(rows
-            .iter()
-            .map(|row| {
-                let market = Market {
-                    id: Uuid::parse_str(row.get("market_id")).unwrap(),
-                    name: row.get("name"),
-                    status: deserialize_market_status(row.get("status")),
-                    created_by: Uuid::parse_str(row.get("created_by")).unwrap(),
-                    opens_at: chrono::DateTime::parse_from_rfc3339(row.get("opens_at"))
-                        .unwrap()
-                        .into(),
-                    closes_at: chrono::DateTime::parse_from_rfc3339(row.get("closes_at"))
-                        .unwrap()
-                        .into(),
-                    starting_balance: row.get("starting_balance"),
-                    invite_code: row.get("invite_code"),
-                    created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
-                        .unwrap()
-                        .into(),
-                };
-                let user = User {
-                    id: Uuid::parse_str(row.get("user_id")).unwrap(),
-                    market_id: Uuid::parse_str(row.get("u_market_id")).unwrap(),
-                    device_id: row.get("device_id"),
-                    display_name: row.get("display_name"),
-                    avatar: row.get("avatar"),
-                    balance: row.get("balance"),
-                    is_admin: row.get::<i64, _>("is_admin") != 0,
-                    joined_at: chrono::DateTime::parse_from_rfc3339(row.get("joined_at"))
-                        .unwrap()
-                        .into(),
-                };
-                (market, user)
-            })
-            .collect())
+        Ok(rows.iter().map(device_market_from_row).collect())
     }
 
-    async fn create_bet(&self, bet: Bet) -> DbResult<Bet> {
-        sqlx::query(
-            r#"
-            INSERT INTO bets (id, market_id, subject_user_id, created_by, description, initial_odds, status, yes_pool, no_pool, hide_from_subject, created_at, resolved_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(bet.id.to_string())
-        .bind(bet.market_id.to_string())
-        .bind(bet.subject_user_id.to_string())
-        .bind(bet.created_by.to_string())
-        .bind(&bet.description)
-        .bind(&bet.initial_odds)
-        .bind(serialize_bet_status(bet.status))
-        .bind(bet.yes_pool)
-        .bind(bet.no_pool)
-        .bind(bet.hide_from_subject as i64)
-        .bind(bet.created_at.to_rfc3339())
-        .bind(bet.resolved_at.map(|d| d.to_rfc3339()))
-        .execute(&self.pool)
-        .await
-        .map_err(|e| DbError::Internal(e.to_string()))?;
+    async fn list_markets_by_device_id(
+        &self,
+        device_id: &str,
+        query: &DeviceMarketQuery,
+    ) -> DbResult<Page<(Market, User)>> {
+        let mut select = Select::new(SELECT_DEVICE_MARKETS);
+        select.filter("u.device_id = ?", [device_id.to_string().into()]);
+        select.filter_by("m.status = ?", query.status.map(serialize_market_status));
+        select.filter_by("m.created_at >= ?", query.created_after);
+        select.filter_by("m.created_at < ?", query.created_before);
+        let key = match query.sort {
+            DeviceMarketSort::Recent => "u.joined_at",
+            DeviceMarketSort::Newest => "m.created_at",
+        };
+        let (sql, params) = select.page(
+            key,
+            "u.id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
 
-        Ok(bet)
+        let rows = bind_all(sqlx::query(&sql), params)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.iter().map(device_market_from_row).collect(),
+            query.limit(),
+            |row| sort.key(row),
+        ))
     }
 
-    async fn get_bet(&self, id: Uuid) -> DbResult<Bet> {
-        let row = sqlx::query("SELECT * FROM bets WHERE id = ?")
+    async fn create_player(&self, player: Player) -> DbResult<Player> {
+        insert_player(&player)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(player)
+    }
+
+    async fn get_player(&self, id: Uuid) -> DbResult<Player> {
+        let row = sqlx::query("SELECT * FROM players WHERE id = ?")
             .bind(id.to_string())
             .fetch_one(&self.pool)
             .await
-            .map_err(|_| DbError::NotFound("Bet not found".to_string()))?;
+            .map_err(|_| DbError::NotFound("Player not found".to_string()))?;
 
-        Ok(Bet {
+        Ok(Player {
             id: Uuid::parse_str(row.get("id")).unwrap(),
-            market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
-            subject_user_id: Uuid::parse_str(row.get("subject_user_id")).unwrap(),
-            created_by: Uuid::parse_str(row.get("created_by")).unwrap(),
-            description: row.get("description"),
-            initial_odds: row.get("initial_odds"),
-            status: deserialize_bet_status(row.get("status")),
-            yes_pool: row.get("yes_pool"),
-            no_pool: row.get("no_pool"),
-            hide_from_subject: row.get::<i64, _>("hide_from_subject") != 0,
+            display_name: row.get("display_name"),
+            avatar: row.get("avatar"),
             created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
                 .unwrap()
                 .into(),
-            resolved_at: row
-                .get::<Option<String>, _>("resolved_at")
-                .map(|s| chrono::DateTime::parse_from_rfc3339(&s).unwrap().into()),
         })
     }
 
-    async fn get_bets_in_market(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
-        let rows = sqlx::query("SELECT * FROM bets WHERE market_id = ?")
-            .bind(market_id.to_string())
+    async fn update_user_player(&self, user_id: Uuid, player_id: Uuid) -> DbResult<()> {
+        sqlx::query("UPDATE users SET player_id = ? WHERE id = ?")
+            .bind(player_id.to_string())
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_users_by_player(&self, player_id: Uuid) -> DbResult<Vec<User>> {
+        let rows = sqlx::query("SELECT * FROM users WHERE player_id = ? ORDER BY joined_at")
+            .bind(player_id.to_string())
             .fetch_all(&self.pool)
             .await
             .map_err(|e| DbError::Internal(e.to_string()))?;
 
+        Ok(rows.iter().map(user_from_row).collect())
+    }
+
+    async fn create_template(&self, template: MarketTemplate) -> DbResult<MarketTemplate> {
+        let bets =
+            serde_json::to_string(&template.bets).map_err(|e| DbError::Internal(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO market_templates (id, name, starting_balance, duration_hours, bets, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(template.id.to_string())
+        .bind(&template.name)
+        .bind(template.starting_balance)
+        .bind(template.duration_hours)
+        .bind(bets)
+        .bind(template.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(template)
+    }
+
+    async fn get_template(&self, id: Uuid) -> DbResult<MarketTemplate> {
+        let row = sqlx::query("SELECT * FROM market_templates WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|_| DbError::NotFound("Template not found".to_string()))?;
+
+        Ok(MarketTemplate {
+            id: Uuid::parse_str(row.get("id")).unwrap(),
+            name: row.get("name"),
+            starting_balance: row.get("starting_balance"),
+            duration_hours: row.get("duration_hours"),
+            bets: serde_json::from_str(row.get("bets"))
+                .map_err(|e| DbError::Internal(e.to_string()))?,
+            created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
+                .unwrap()
+                .into(),
+        })
+    }
+
+    async fn create_season(&self, season: Season) -> DbResult<Season> {
+        insert_season(&season)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(season)
+    }
+
+    async fn get_season(&self, id: Uuid) -> DbResult<Season> {
+        let row = sqlx::query("SELECT * FROM seasons WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|_| DbError::NotFound("Season not found".to_string()))?;
+
+        Ok(season_from_row(&row))
+    }
+
+    async fn add_season_market(
+        &self,
+        season_id: Uuid,
+        market_id: Uuid,
+        position: usize,
+    ) -> DbResult<()> {
+        insert_season_market(season_id, market_id, position)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_season_markets(&self, season_id: Uuid) -> DbResult<Vec<Market>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT m.* FROM markets m
+            JOIN season_markets sm ON sm.market_id = m.id
+            WHERE sm.season_id = ?
+            ORDER BY sm.position
+            "#,
+        )
+        .bind(season_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
         Ok(rows
             .iter()
-            .map(|row| Bet {
+            .map(|row| Market {
                 id: Uuid::parse_str(row.get("id")).unwrap(),
-                market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
-                subject_user_id: Uuid::parse_str(row.get("subject_user_id")).unwrap(),
+                name: row.get("name"),
+                status: deserialize_market_status(row.get("status")),
                 created_by: Uuid::parse_str(row.get("created_by")).unwrap(),
-                description: row.get("description"),
-                initial_odds: row.get("initial_odds"),
-                status: deserialize_bet_status(row.get("status")),
-                yes_pool: row.get("yes_pool"),
-                no_pool: row.get("no_pool"),
-                hide_from_subject: row.get::<i64, _>("hide_from_subject") != 0,
+                opens_at: chrono::DateTime::parse_from_rfc3339(row.get("opens_at"))
+                    .unwrap()
+                    .into(),
+                closes_at: chrono::DateTime::parse_from_rfc3339(row.get("closes_at"))
+                    .unwrap()
+                    .into(),
+                starting_balance: row.get("starting_balance"),
+                invite_code: row.get("invite_code"),
                 created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
                     .unwrap()
                     .into(),
-                resolved_at: row
-                    .get::<Option<String>, _>("resolved_at")
-                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s).unwrap().into()),
             })
             .collect())
     }
 
-    async fn get_bets_for_user(
-        &self,
-        market_id: Uuid,
-        viewing_user_id: Uuid,
-    ) -> DbResult<Vec<BetView>> {
-        let bets = self.get_bets_in_market(market_id).await?;
-        Ok(bets
+    async fn get_season_for_market(&self, market_id: Uuid) -> DbResult<Season> {
+        let row = sqlx::query(
+            r#"
+            SELECT s.* FROM seasons s
+            JOIN season_markets sm ON sm.season_id = s.id
+            WHERE sm.market_id = ?
+            "#,
+        )
+        .bind(market_id.to_string())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|_| DbError::NotFound("Season not found".to_string()))?;
+
+        Ok(season_from_row(&row))
+    }
+
+    async fn create_carry_over(&self, market_id: Uuid, user_id: Uuid, amount: i64) -> DbResult<()> {
+        insert_carry_over(market_id, user_id, amount)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_carry_overs(&self, season_id: Uuid) -> DbResult<Vec<(Uuid, i64)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT c.user_id, c.amount FROM season_carry_overs c
+            JOIN season_markets sm ON sm.market_id = c.market_id
+            WHERE sm.season_id = ?
+            "#,
+        )
+        .bind(season_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(rows
             .iter()
-            .map(|bet| bet.to_view(viewing_user_id))
+            .map(|row| {
+                (
+                    Uuid::parse_str(row.get("user_id")).unwrap(),
+                    row.get("amount"),
+                )
+            })
             .collect())
     }
 
-    async fn get_pending_bets(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
-        let rows = sqlx::query("SELECT * FROM bets WHERE market_id = ? AND status = ?")
+    async fn create_link_code(&self, link: LinkCode) -> DbResult<LinkCode> {
+        sqlx::query(
+            r#"
+            INSERT INTO link_codes (code, device_id, created_at, expires_at, redeemed_at, redeemed_by)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&link.code)
+        .bind(&link.device_id)
+        .bind(link.created_at.to_rfc3339())
+        .bind(link.expires_at.to_rfc3339())
+        .bind(link.redeemed_at.map(|d| d.to_rfc3339()))
+        .bind(&link.redeemed_by)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(link)
+    }
+
+    async fn get_link_code(&self, code: &str) -> DbResult<LinkCode> {
+        let row = sqlx::query("SELECT * FROM link_codes WHERE code = ?")
+            .bind(code)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|_| DbError::NotFound("Link code not found".to_string()))?;
+
+        Ok(LinkCode {
+            code: row.get("code"),
+            device_id: row.get("device_id"),
+            created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
+                .unwrap()
+                .into(),
+            expires_at: chrono::DateTime::parse_from_rfc3339(row.get("expires_at"))
+                .unwrap()
+                .into(),
+            redeemed_at: row
+                .get::<Option<String>, _>("redeemed_at")
+                .map(|s| chrono::DateTime::parse_from_rfc3339(&s).unwrap().into()),
+            redeemed_by: row.get("redeemed_by"),
+        })
+    }
+
+    async fn redeem_link_code(
+        &self,
+        code: &str,
+        device_id: &str,
+        redeemed_at: DateTime<Utc>,
+    ) -> DbResult<bool> {
+        let result = sqlx::query(
+            "UPDATE link_codes SET redeemed_at = ?, redeemed_by = ? WHERE code = ? AND redeemed_at IS NULL",
+        )
+        .bind(redeemed_at.to_rfc3339())
+        .bind(device_id)
+        .bind(code)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn create_audit_entry(&self, entry: AuditEntry) -> DbResult<AuditEntry> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log (id, action, device_id, detail, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(entry.id.to_string())
+        .bind(&entry.action)
+        .bind(&entry.device_id)
+        .bind(&entry.detail)
+        .bind(entry.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(entry)
+    }
+
+    async fn count_audit_entries(
+        &self,
+        device_id: Option<&str>,
+        action: &str,
+        since: DateTime<Utc>,
+    ) -> DbResult<i64> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS count FROM audit_log WHERE (? IS NULL OR device_id = ?) AND action = ? AND created_at >= ?",
+        )
+        .bind(device_id)
+        .bind(device_id)
+        .bind(action)
+        .bind(since.to_rfc3339())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(row.get("count"))
+    }
+
+    async fn create_spectator(&self, spectator: Spectator) -> DbResult<Spectator> {
+        insert_spectator(&spectator)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(spectator)
+    }
+
+    async fn get_spectator_by_token(&self, token: &str) -> DbResult<Spectator> {
+        let row = sqlx::query("SELECT * FROM spectators WHERE token = ?")
+            .bind(token)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|_| DbError::NotFound("Spectator not found".to_string()))?;
+
+        Ok(Spectator {
+            id: Uuid::parse_str(row.get("id")).unwrap(),
+            market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
+            display_name: row.get("display_name"),
+            token: row.get("token"),
+            joined_at: chrono::DateTime::parse_from_rfc3339(row.get("joined_at"))
+                .unwrap()
+                .into(),
+        })
+    }
+
+    async fn get_spectators_in_market(&self, market_id: Uuid) -> DbResult<Vec<Spectator>> {
+        let rows = sqlx::query("SELECT * FROM spectators WHERE market_id = ?")
             .bind(market_id.to_string())
-            .bind(serialize_bet_status(BetStatus::Pending))
             .fetch_all(&self.pool)
             .await
             .map_err(|e| DbError::Internal(e.to_string()))?;
 
         Ok(rows
             .iter()
-            .map(|row| Bet {
+            .map(|row| Spectator {
                 id: Uuid::parse_str(row.get("id")).unwrap(),
                 market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
-                subject_user_id: Uuid::parse_str(row.get("subject_user_id")).unwrap(),
-                created_by: Uuid::parse_str(row.get("created_by")).unwrap(),
-                description: row.get("description"),
-                initial_odds: row.get("initial_odds"),
-                status: deserialize_bet_status(row.get("status")),
-                yes_pool: row.get("yes_pool"),
-                no_pool: row.get("no_pool"),
-                hide_from_subject: row.get::<i64, _>("hide_from_subject") != 0,
+                display_name: row.get("display_name"),
+                token: row.get("token"),
+                joined_at: chrono::DateTime::parse_from_rfc3339(row.get("joined_at"))
+                    .unwrap()
+                    .into(),
+            })
+            .collect())
+    }
+
+    async fn create_webhook(&self, webhook: Webhook) -> DbResult<Webhook> {
+        let events =
+            serde_json::to_string(&webhook.events).map_err(|e| DbError::Internal(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO webhooks (id, market_id, url, secret, events, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(webhook.id.to_string())
+        .bind(webhook.market_id.to_string())
+        .bind(&webhook.url)
+        .bind(&webhook.secret)
+        .bind(events)
+        .bind(webhook.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(webhook)
+    }
+
+    async fn get_webhook(&self, id: Uuid) -> DbResult<Webhook> {
+        let row = sqlx::query("SELECT * FROM webhooks WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|_| DbError::NotFound("Webhook not found".to_string()))?;
+
+        webhook_from_row(&row)
+    }
+
+    async fn get_webhooks_in_market(&self, market_id: Uuid) -> DbResult<Vec<Webhook>> {
+        let rows =
+            sqlx::query("SELECT * FROM webhooks WHERE market_id = ? ORDER BY created_at, id")
+                .bind(market_id.to_string())
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        rows.iter().map(webhook_from_row).collect()
+    }
+
+    async fn delete_webhook(&self, id: Uuid) -> DbResult<()> {
+        sqlx::query("DELETE FROM webhook_deliveries WHERE webhook_id = ?")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        sqlx::query("DELETE FROM webhooks WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn create_webhook_delivery(
+        &self,
+        delivery: WebhookDelivery,
+    ) -> DbResult<WebhookDelivery> {
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (id, webhook_id, event, attempt, status_code, error, succeeded, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(delivery.id.to_string())
+        .bind(delivery.webhook_id.to_string())
+        .bind(&delivery.event)
+        .bind(delivery.attempt as i64)
+        .bind(delivery.status_code.map(|code| code as i64))
+        .bind(&delivery.error)
+        .bind(delivery.succeeded as i64)
+        .bind(delivery.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(delivery)
+    }
+
+    async fn get_webhook_deliveries(&self, webhook_id: Uuid) -> DbResult<Vec<WebhookDelivery>> {
+        let rows = sqlx::query(
+            "SELECT * FROM webhook_deliveries WHERE webhook_id = ? ORDER BY created_at, attempt",
+        )
+        .bind(webhook_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| WebhookDelivery {
+                id: Uuid::parse_str(row.get("id")).unwrap(),
+                webhook_id: Uuid::parse_str(row.get("webhook_id")).unwrap(),
+                event: row.get("event"),
+                attempt: row.get::<i64, _>("attempt") as u32,
+                status_code: row
+                    .get::<Option<i64>, _>("status_code")
+                    .map(|code| code as u16),
+                error: row.get("error"),
+                succeeded: row.get::<i64, _>("succeeded") != 0,
                 created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
                     .unwrap()
                     .into(),
-                resolved_at: row
-                    .get::<Option<String>, _>("resolved_at")
-                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s).unwrap().into()),
             })
             .collect())
     }
 
+    async fn create_bet(&self, bet: Bet) -> DbResult<Bet> {
+        // One transaction, so a bet is never left about nobody
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        for insert in insert_bet(&bet) {
+            insert
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| DbError::Internal(e.to_string()))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(bet)
+    }
+
+    async fn get_bet(&self, id: Uuid) -> DbResult<Bet> {
+        let row = sqlx::query(&format!("{} WHERE b.id = ?", SELECT_BETS))
+            .bind(id.to_string())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|_| DbError::NotFound("Bet not found".to_string()))?;
+
+        Ok(bet_from_row(&row))
+    }
+
+    async fn get_bets_in_market(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        let rows = sqlx::query(&format!("{} WHERE b.market_id = ?", SELECT_BETS))
+            .bind(market_id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(rows.iter().map(bet_from_row).collect())
+    }
+
+    async fn list_bets_in_market(&self, market_id: Uuid, query: &BetQuery) -> DbResult<Page<Bet>> {
+        let mut select = Select::new(SELECT_BETS);
+        select.filter("b.market_id = ?", [market_id.into()]);
+        select.filter_by("b.status = ?", query.status.map(serialize_bet_status));
+        select.filter_by(
+            "(b.about_everyone = 1 OR b.id IN (SELECT bet_id FROM bet_subjects WHERE user_id = ?))",
+            query.subject,
+        );
+        select.filter_by("b.created_by = ?", query.creator);
+        select.filter_by("b.created_at >= ?", query.created_after);
+        select.filter_by("b.created_at < ?", query.created_before);
+        let key = match query.sort {
+            BetSort::Newest | BetSort::Oldest => "b.created_at",
+            BetSort::Pool => "(b.yes_pool + b.no_pool)",
+        };
+        let (sql, params) = select.page(
+            key,
+            "b.id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
+
+        let rows = bind_all(sqlx::query(&sql), params)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.iter().map(bet_from_row).collect(),
+            query.limit(),
+            |bet| sort.key(bet),
+        ))
+    }
+
+    async fn get_bets_for_user(
+        &self,
+        market_id: Uuid,
+        viewing_user_id: Uuid,
+    ) -> DbResult<Vec<BetView>> {
+        let bets = self.get_bets_in_market(market_id).await?;
+        Ok(bets
+            .iter()
+            .map(|bet| bet.to_view(viewing_user_id))
+            .collect())
+    }
+
+    async fn get_pending_bets(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE b.market_id = ? AND b.status = ?",
+            SELECT_BETS
+        ))
+        .bind(market_id.to_string())
+        .bind(serialize_bet_status(BetStatus::Pending))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(rows.iter().map(bet_from_row).collect())
+    }
+
+    async fn get_market_contents(&self, market_id: Uuid) -> DbResult<MarketContents> {
+        // One read transaction, so the three reads agree with each other
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+        let id = market_id.to_string();
+
+        let market = sqlx::query("SELECT * FROM markets WHERE id = ?")
+            .bind(&id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|_| DbError::NotFound("Market not found".to_string()))?;
+        let users = sqlx::query("SELECT * FROM users WHERE market_id = ?")
+            .bind(&id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+        let bets = sqlx::query(&format!("{} WHERE b.market_id = ?", SELECT_BETS))
+            .bind(&id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(MarketContents {
+            market: market_from_row(&market),
+            users: users.iter().map(user_from_row).collect(),
+            bets: bets.iter().map(bet_from_row).collect(),
+        })
+    }
+
     async fn update_bet_status(&self, bet_id: Uuid, status: BetStatus) -> DbResult<()> {
         let resolved_at = match status {
             BetStatus::ResolvedYes | BetStatus::ResolvedNo => Some(chrono::Utc::now().to_rfc3339()),
@@ -580,24 +1541,10 @@ impl Database for SqliteDatabase {
     }
 
     async fn create_wager(&self, wager: Wager) -> DbResult<Wager> {
-        sqlx::query(
-            r#"
-            INSERT INTO wagers (id, bet_id, user_id, side, amount, placed_at, yes_pool_after, no_pool_after, probability_after)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(wager.id.to_string())
-        .bind(wager.bet_id.to_string())
-        .bind(wager.user_id.to_string())
-        .bind(serialize_side(wager.side))
-        .bind(wager.amount)
-        .bind(wager.placed_at.to_rfc3339())
-        .bind(wager.yes_pool_after)
-        .bind(wager.no_pool_after)
-        .bind(wager.probability_after)
-        .execute(&self.pool)
-        .await
-        .map_err(|e| DbError::Internal(e.to_string()))?;
+        insert_wager(&wager)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
 
         Ok(wager)
     }
@@ -609,22 +1556,85 @@ impl Database for SqliteDatabase {
             .await
             .map_err(|e| DbError::Internal(e.to_string()))?;
 
-        Ok(rows
-            .iter()
-            .map(|row| Wager {
-                id: Uuid::parse_str(row.get("id")).unwrap(),
-                bet_id: Uuid::parse_str(row.get("bet_id")).unwrap(),
-                user_id: Uuid::parse_str(row.get("user_id")).unwrap(),
-                side: deserialize_side(row.get("side")),
-                amount: row.get("amount"),
-                placed_at: chrono::DateTime::parse_from_rfc3339(row.get("placed_at"))
-                    .unwrap()
-                    .into(),
-                yes_pool_after: row.get("yes_pool_after"),
-                no_pool_after: row.get("no_pool_after"),
-                probability_after: row.get("probability_after"),
-            })
-            .collect())
+        Ok(rows.iter().map(wager_from_row).collect())
+    }
+
+    async fn list_wagers_for_bet(&self, bet_id: Uuid, query: &WagerQuery) -> DbResult<Page<Wager>> {
+        let mut select = Select::new("SELECT * FROM wagers");
+        select.filter("bet_id = ?", [bet_id.into()]);
+        select.filter_by("user_id = ?", query.user);
+        select.filter_by("side = ?", query.side.map(serialize_side));
+        select.filter_by("placed_at >= ?", query.placed_after);
+        select.filter_by("placed_at < ?", query.placed_before);
+        let key = match query.sort {
+            WagerSort::Newest | WagerSort::Oldest => "placed_at",
+            WagerSort::Largest => "amount",
+        };
+        let (sql, params) = select.page(
+            key,
+            "id",
+            query.sort.order(),
+            query.cursor()?,
+            query.limit(),
+        );
+
+        let rows = bind_all(sqlx::query(&sql), params)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        let sort = query.sort;
+        Ok(Page::from_rows(
+            rows.iter().map(wager_from_row).collect(),
+            query.limit(),
+            |wager| sort.key(wager),
+        ))
+    }
+
+    async fn apply_removal(&self, removal: &Removal) -> DbResult<()> {
+        let internal = |e: sqlx::Error| DbError::Internal(e.to_string());
+        let mut tx = self.pool.begin().await.map_err(internal)?;
+
+        for wager in &removal.refunds {
+            sqlx::query("UPDATE wagers SET refunded = 1 WHERE id = ?")
+                .bind(wager.id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+            sqlx::query("UPDATE users SET balance = balance + ? WHERE id = ?")
+                .bind(wager.amount)
+                .bind(wager.user_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+            if !removal.voided_bet_ids.contains(&wager.bet_id) {
+                sqlx::query(match wager.side {
+                    Side::Yes => "UPDATE bets SET yes_pool = yes_pool - ? WHERE id = ?",
+                    Side::No => "UPDATE bets SET no_pool = no_pool - ? WHERE id = ?",
+                })
+                .bind(wager.amount)
+                .bind(wager.bet_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+            }
+        }
+        for bet_id in &removal.voided_bet_ids {
+            sqlx::query("UPDATE bets SET status = ? WHERE id = ?")
+                .bind(serialize_bet_status(BetStatus::Void))
+                .bind(bet_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(internal)?;
+        }
+        sqlx::query("UPDATE users SET status = ? WHERE id = ?")
+            .bind(serialize_membership_status(removal.status))
+            .bind(removal.user_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(internal)?;
+
+        tx.commit().await.map_err(internal)
     }
 
     async fn get_wagers_for_user(&self, user_id: Uuid) -> DbResult<Vec<Wager>> {
@@ -634,51 +1644,69 @@ impl Database for SqliteDatabase {
             .await
             .map_err(|e| DbError::Internal(e.to_string()))?;
 
-        Ok(rows
-            .iter()
-            .map(|row| Wager {
-                id: Uuid::parse_str(row.get("id")).unwrap(),
-                bet_id: Uuid::parse_str(row.get("bet_id")).unwrap(),
-                user_id: Uuid::parse_str(row.get("user_id")).unwrap(),
-                side: deserialize_side(row.get("side")),
-                amount: row.get("amount"),
-                placed_at: chrono::DateTime::parse_from_rfc3339(row.get("placed_at"))
-                    .unwrap()
-                    .into(),
-                yes_pool_after: row.get("yes_pool_after"),
-                no_pool_after: row.get("no_pool_after"),
-                probability_after: row.get("probability_after"),
-            })
-            .collect())
+        Ok(rows.iter().map(wager_from_row).collect())
     }
 
     async fn get_bets_about_user(&self, user_id: Uuid) -> DbResult<Vec<Bet>> {
-        let rows = sqlx::query("SELECT * FROM bets WHERE subject_user_id = ?")
-            .bind(user_id.to_string())
+        let rows = sqlx::query(&format!(
+            "{} WHERE b.id IN (SELECT bet_id FROM bet_subjects WHERE user_id = ?1) \
+             OR (b.about_everyone = 1 AND b.market_id = (SELECT market_id FROM users WHERE id = ?1))",
+            SELECT_BETS
+        ))
+        .bind(user_id.to_string())
             .fetch_all(&self.pool)
             .await
             .map_err(|e| DbError::Internal(e.to_string()))?;
 
-        Ok(rows
-            .iter()
-            .map(|row| Bet {
-                id: Uuid::parse_str(row.get("id")).unwrap(),
-                market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
-                subject_user_id: Uuid::parse_str(row.get("subject_user_id")).unwrap(),
-                created_by: Uuid::parse_str(row.get("created_by")).unwrap(),
-                description: row.get("description"),
-                initial_odds: row.get("initial_odds"),
-                status: deserialize_bet_status(row.get("status")),
-                yes_pool: row.get("yes_pool"),
-                no_pool: row.get("no_pool"),
-                hide_from_subject: row.get::<i64, _>("hide_from_subject") != 0,
-                created_at: chrono::DateTime::parse_from_rfc3339(row.get("created_at"))
-                    .unwrap()
-                    .into(),
-                resolved_at: row
-                    .get::<Option<String>, _>("resolved_at")
-                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s).unwrap().into()),
-            })
-            .collect())
+        Ok(rows.iter().map(bet_from_row).collect())
+    }
+
+    async fn create_reveal_ceremony(&self, ceremony: RevealCeremony) -> DbResult<RevealCeremony> {
+        insert_reveal_ceremony(&ceremony)?
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(ceremony)
+    }
+
+    async fn get_reveal_ceremony(&self, market_id: Uuid) -> DbResult<RevealCeremony> {
+        let row = sqlx::query("SELECT * FROM reveal_ceremonies WHERE market_id = ?")
+            .bind(market_id.to_string())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|_| DbError::NotFound("Reveal ceremony not found".to_string()))?;
+
+        Ok(RevealCeremony {
+            market_id: Uuid::parse_str(row.get("market_id")).unwrap(),
+            bet_ids: serde_json::from_str(row.get("bet_ids"))
+                .map_err(|e| DbError::Internal(e.to_string()))?,
+            revealed_count: row.get::<i64, _>("revealed_count") as usize,
+            started_at: chrono::DateTime::parse_from_rfc3339(row.get("started_at"))
+                .unwrap()
+                .into(),
+            completed_at: row
+                .get::<Option<String>, _>("completed_at")
+                .map(|s| chrono::DateTime::parse_from_rfc3339(&s).unwrap().into()),
+        })
+    }
+
+    async fn update_reveal_progress(
+        &self,
+        market_id: Uuid,
+        revealed_count: usize,
+        completed_at: Option<DateTime<Utc>>,
+    ) -> DbResult<()> {
+        sqlx::query(
+            "UPDATE reveal_ceremonies SET revealed_count = ?, completed_at = ? WHERE market_id = ?",
+        )
+        .bind(revealed_count as i64)
+        .bind(completed_at.map(|d| d.to_rfc3339()))
+        .bind(market_id.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DbError::Internal(e.to_string()))?;
+
+        Ok(())
     }
 }
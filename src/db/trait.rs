@@ -1,9 +1,16 @@
+use crate::db::query::{BetQuery, DeviceMarketQuery, Page, UserQuery, WagerQuery};
+use crate::domain::archive::MarketArchive;
 /// Database abstraction trait
 ///
 /// This trait defines all database operations needed by the application.
-/// We can swap implementations (SQLite, Supabase, etc.) without changing business logic.
-use crate::domain::models::{Bet, BetStatus, BetView, Market, MarketStatus, User, Wager};
+/// We can swap implementations (SQLite, Postgres, D1) without changing business logic.
+use crate::domain::models::{
+    AuditEntry, Bet, BetStatus, BetView, LinkCode, Market, MarketContents, MarketStatus,
+    MarketTemplate, MembershipStatus, Player, Removal, RevealCeremony, Season, Spectator, User,
+    Wager, Webhook, WebhookDelivery,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 #[derive(Debug, thiserror::Error)]
@@ -45,6 +52,67 @@ pub trait Database: DatabaseMarker {
 
     async fn delete_market(&self, id: Uuid) -> DbResult<()>;
 
+    /// Write an archived market and everything in it, keeping all ids
+    ///
+    /// Players and the season are created if missing. Backends that can
+    /// should write it all in one transaction; the default writes row by row
+    /// and deletes the market again if a write fails (players and a season it
+    /// created are left behind).
+    async fn import_archive(&self, archive: &MarketArchive) -> DbResult<()> {
+        for player in &archive.players {
+            match self.get_player(player.id).await {
+                Ok(_) => {}
+                Err(DbError::NotFound(_)) => {
+                    self.create_player(player.clone()).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if let Some(membership) = &archive.season {
+            match self.get_season(membership.season.id).await {
+                Ok(_) => {}
+                Err(DbError::NotFound(_)) => {
+                    self.create_season(membership.season.clone()).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let market_id = self.create_market(archive.market.clone()).await?.id;
+        let result = async {
+            for user in &archive.users {
+                self.create_user(user.clone()).await?;
+            }
+            for bet in &archive.bets {
+                self.create_bet(bet.clone()).await?;
+            }
+            for wager in &archive.wagers {
+                self.create_wager(wager.clone()).await?;
+            }
+            for spectator in &archive.spectators {
+                self.create_spectator(spectator.clone()).await?;
+            }
+            if let Some(ceremony) = &archive.reveal_ceremony {
+                self.create_reveal_ceremony(ceremony.clone()).await?;
+            }
+            if let Some(membership) = &archive.season {
+                self.add_season_market(membership.season.id, market_id, membership.position)
+                    .await?;
+            }
+            for carry_over in &archive.carry_overs {
+                self.create_carry_over(market_id, carry_over.user_id, carry_over.amount)
+                    .await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = self.delete_market(market_id).await;
+        }
+        result
+    }
+
     // ===== User Operations =====
 
     async fn create_user(&self, user: User) -> DbResult<User>;
@@ -55,11 +123,137 @@ pub trait Database: DatabaseMarker {
 
     async fn get_users_in_market(&self, market_id: Uuid) -> DbResult<Vec<User>>;
 
+    /// A page of a market's users, filtered and sorted
+    ///
+    /// The default filters `get_users_in_market` in memory; SQL backends
+    /// should override it (likewise the other `list_` methods).
+    async fn list_users_in_market(
+        &self,
+        market_id: Uuid,
+        query: &UserQuery,
+    ) -> DbResult<Page<User>> {
+        query.apply(self.get_users_in_market(market_id).await?)
+    }
+
     async fn update_user_balance(&self, user_id: Uuid, new_balance: i64) -> DbResult<()>;
 
+    async fn update_user_status(&self, user_id: Uuid, status: MembershipStatus) -> DbResult<()>;
+
+    async fn update_user_profile(
+        &self,
+        user_id: Uuid,
+        display_name: &str,
+        avatar: &str,
+    ) -> DbResult<()>;
+
+    /// Move a user row to another device (account linking)
+    async fn update_user_device(&self, user_id: Uuid, device_id: &str) -> DbResult<()>;
+
     /// Get all markets a device has joined (for recent markets feature)
     async fn get_markets_by_device_id(&self, device_id: &str) -> DbResult<Vec<(Market, User)>>;
 
+    /// A page of every market a device has joined, filtered and sorted
+    ///
+    /// Unlike `get_markets_by_device_id` this isn't capped at the ten most
+    /// recent, so there is no default; backends list the markets themselves.
+    async fn list_markets_by_device_id(
+        &self,
+        device_id: &str,
+        query: &DeviceMarketQuery,
+    ) -> DbResult<Page<(Market, User)>>;
+
+    // ===== Player Operations (identity across markets) =====
+
+    async fn create_player(&self, player: Player) -> DbResult<Player>;
+
+    async fn get_player(&self, id: Uuid) -> DbResult<Player>;
+
+    async fn update_user_player(&self, user_id: Uuid, player_id: Uuid) -> DbResult<()>;
+
+    /// Get every per-market user row linked to a player
+    async fn get_users_by_player(&self, player_id: Uuid) -> DbResult<Vec<User>>;
+
+    // ===== Template Operations =====
+
+    async fn create_template(&self, template: MarketTemplate) -> DbResult<MarketTemplate>;
+
+    async fn get_template(&self, id: Uuid) -> DbResult<MarketTemplate>;
+
+    // ===== Season Operations =====
+
+    async fn create_season(&self, season: Season) -> DbResult<Season>;
+
+    async fn get_season(&self, id: Uuid) -> DbResult<Season>;
+
+    async fn add_season_market(
+        &self,
+        season_id: Uuid,
+        market_id: Uuid,
+        position: usize,
+    ) -> DbResult<()>;
+
+    /// Get a season's markets in the order they were added
+    async fn get_season_markets(&self, season_id: Uuid) -> DbResult<Vec<Market>>;
+
+    async fn get_season_for_market(&self, market_id: Uuid) -> DbResult<Season>;
+
+    /// Record the balance a seat brought in from an earlier market (relative to starting balance)
+    async fn create_carry_over(&self, market_id: Uuid, user_id: Uuid, amount: i64) -> DbResult<()>;
+
+    /// Get (user_id, amount) for every carried-over seat in a season
+    async fn get_carry_overs(&self, season_id: Uuid) -> DbResult<Vec<(Uuid, i64)>>;
+
+    // ===== Identity Operations =====
+
+    async fn create_link_code(&self, link: LinkCode) -> DbResult<LinkCode>;
+
+    async fn get_link_code(&self, code: &str) -> DbResult<LinkCode>;
+
+    /// Mark a link code as used; returns false if it was already redeemed
+    async fn redeem_link_code(
+        &self,
+        code: &str,
+        device_id: &str,
+        redeemed_at: DateTime<Utc>,
+    ) -> DbResult<bool>;
+
+    async fn create_audit_entry(&self, entry: AuditEntry) -> DbResult<AuditEntry>;
+
+    /// Count a device's audit entries for an action since a point in time,
+    /// or every device's with no `device_id`
+    async fn count_audit_entries(
+        &self,
+        device_id: Option<&str>,
+        action: &str,
+        since: DateTime<Utc>,
+    ) -> DbResult<i64>;
+
+    // ===== Spectator Operations =====
+
+    async fn create_spectator(&self, spectator: Spectator) -> DbResult<Spectator>;
+
+    async fn get_spectator_by_token(&self, token: &str) -> DbResult<Spectator>;
+
+    async fn get_spectators_in_market(&self, market_id: Uuid) -> DbResult<Vec<Spectator>>;
+
+    // ===== Webhook Operations =====
+
+    async fn create_webhook(&self, webhook: Webhook) -> DbResult<Webhook>;
+
+    async fn get_webhook(&self, id: Uuid) -> DbResult<Webhook>;
+
+    /// Webhooks registered on a market, oldest first
+    async fn get_webhooks_in_market(&self, market_id: Uuid) -> DbResult<Vec<Webhook>>;
+
+    /// Remove a webhook along with its delivery log
+    async fn delete_webhook(&self, id: Uuid) -> DbResult<()>;
+
+    async fn create_webhook_delivery(&self, delivery: WebhookDelivery)
+        -> DbResult<WebhookDelivery>;
+
+    /// A webhook's delivery log, oldest first
+    async fn get_webhook_deliveries(&self, webhook_id: Uuid) -> DbResult<Vec<WebhookDelivery>>;
+
     // ===== Bet Operations =====
 
     async fn create_bet(&self, bet: Bet) -> DbResult<Bet>;
@@ -68,6 +262,11 @@ pub trait Database: DatabaseMarker {
 
     async fn get_bets_in_market(&self, market_id: Uuid) -> DbResult<Vec<Bet>>;
 
+    /// A page of a market's bets, filtered and sorted (no visibility rules applied)
+    async fn list_bets_in_market(&self, market_id: Uuid, query: &BetQuery) -> DbResult<Page<Bet>> {
+        query.apply(self.get_bets_in_market(market_id).await?)
+    }
+
     /// Get bets with visibility filtering for a specific user
     /// This applies the "hidden bet" rule: users can't see bets about themselves
     async fn get_bets_for_user(
@@ -78,6 +277,18 @@ pub trait Database: DatabaseMarker {
 
     async fn get_pending_bets(&self, market_id: Uuid) -> DbResult<Vec<Bet>>;
 
+    /// A market with all its users and bets, for hydrating a client
+    ///
+    /// Backends that can should read these in one round trip or transaction;
+    /// the default runs the reads one after another.
+    async fn get_market_contents(&self, market_id: Uuid) -> DbResult<MarketContents> {
+        Ok(MarketContents {
+            market: self.get_market(market_id).await?,
+            users: self.get_users_in_market(market_id).await?,
+            bets: self.get_bets_in_market(market_id).await?,
+        })
+    }
+
     async fn update_bet_status(&self, bet_id: Uuid, status: BetStatus) -> DbResult<()>;
 
     async fn update_bet_pools(&self, bet_id: Uuid, yes_pool: i64, no_pool: i64) -> DbResult<()>;
@@ -86,13 +297,49 @@ pub trait Database: DatabaseMarker {
 
     async fn create_wager(&self, wager: Wager) -> DbResult<Wager>;
 
+    /// Record a wager and take it out of the bet's pools and the user's balance
+    ///
+    /// Backends that can lock rows should override this to do it atomically;
+    /// the default runs the steps one after another.
+    async fn apply_wager(&self, wager: Wager) -> DbResult<Wager> {
+        let user = self.get_user(wager.user_id).await?;
+        let wager = self.create_wager(wager).await?;
+        self.update_bet_pools(wager.bet_id, wager.yes_pool_after, wager.no_pool_after)
+            .await?;
+        self.update_user_balance(wager.user_id, user.balance - wager.amount)
+            .await?;
+        Ok(wager)
+    }
+
     async fn get_wagers_for_bet(&self, bet_id: Uuid) -> DbResult<Vec<Wager>>;
 
-    #[allow(dead_code)]
+    /// A page of a bet's wagers, filtered and sorted
+    async fn list_wagers_for_bet(&self, bet_id: Uuid, query: &WagerQuery) -> DbResult<Page<Wager>> {
+        query.apply(self.get_wagers_for_bet(bet_id).await?)
+    }
+
+    /// Take a member out of their market: mark the refunded wagers and credit
+    /// their stakes, take stakes on bets that carry on out of the pools, void
+    /// the bets and set the member's status
+    ///
+    /// All or nothing, so balances and pools never end up half refunded.
+    async fn apply_removal(&self, removal: &Removal) -> DbResult<()>;
+
     async fn get_wagers_for_user(&self, user_id: Uuid) -> DbResult<Vec<Wager>>;
 
     // ===== Reveal Operations (end of market) =====
 
     /// Get all bets about a specific user (for reveal screen)
     async fn get_bets_about_user(&self, user_id: Uuid) -> DbResult<Vec<Bet>>;
+
+    async fn create_reveal_ceremony(&self, ceremony: RevealCeremony) -> DbResult<RevealCeremony>;
+
+    async fn get_reveal_ceremony(&self, market_id: Uuid) -> DbResult<RevealCeremony>;
+
+    async fn update_reveal_progress(
+        &self,
+        market_id: Uuid,
+        revealed_count: usize,
+        completed_at: Option<DateTime<Utc>>,
+    ) -> DbResult<()>;
 }
@@ -0,0 +1,182 @@
+/// Portable JSON archive of a single market
+///
+/// Used to back up finished markets and to move them between the SQLite
+/// server and the D1 deployment. Ids are preserved, so an archive can only be
+/// imported into a database that doesn't already hold the market.
+///
+/// Webhooks are left out on purpose: their URLs and signing secrets belong to
+/// the deployment that registered them, so admins register them again after
+/// an import. Spectator tokens are left out too, and spectators get new ones
+/// on import.
+use crate::domain::models::{Bet, Market, Player, RevealCeremony, Season, Spectator, User, Wager};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Current archive format version
+///
+/// Bump this when the format changes; `validate_archive` rejects versions it
+/// doesn't know how to read.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MarketArchive {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub market: Market,
+    pub users: Vec<User>,
+    pub bets: Vec<Bet>,
+    pub wagers: Vec<Wager>,
+    #[serde(default)]
+    pub spectators: Vec<Spectator>,
+    #[serde(default)]
+    pub reveal_ceremony: Option<RevealCeremony>,
+    /// Players that users in this market are linked to
+    #[serde(default)]
+    pub players: Vec<Player>,
+    /// The season this market is part of; created on import if missing
+    #[serde(default)]
+    pub season: Option<SeasonMembership>,
+    /// Balances this market's seats brought in from earlier season markets
+    #[serde(default)]
+    pub carry_overs: Vec<CarryOver>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SeasonMembership {
+    pub season: Season,
+    /// Where the market sits in the season's running order
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CarryOver {
+    pub user_id: Uuid,
+    pub amount: i64,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ArchiveError {
+    #[error("Unsupported archive version {0} (expected {ARCHIVE_VERSION})")]
+    UnsupportedVersion(u32),
+
+    #[error("Archive has no admin for the market")]
+    MissingAdmin,
+
+    #[error("Duplicate id in archive: {0}")]
+    DuplicateId(Uuid),
+
+    #[error("{0} belongs to a different market")]
+    WrongMarket(Uuid),
+
+    #[error("{0} refers to a user that isn't in the archive")]
+    UnknownUser(Uuid),
+
+    #[error("{0} refers to a bet that isn't in the archive")]
+    UnknownBet(Uuid),
+
+    #[error("{0} refers to a player that isn't in the archive")]
+    UnknownPlayer(Uuid),
+
+    #[error("Archive has carry-overs but no season")]
+    CarryOverWithoutSeason,
+}
+
+/// Check that an archive is self-consistent before importing it
+pub fn validate_archive(archive: &MarketArchive) -> Result<(), ArchiveError> {
+    if archive.version != ARCHIVE_VERSION {
+        return Err(ArchiveError::UnsupportedVersion(archive.version));
+    }
+
+    let market_id = archive.market.id;
+    let mut ids = HashSet::new();
+    let mut unique = |id: Uuid| {
+        if ids.insert(id) {
+            Ok(())
+        } else {
+            Err(ArchiveError::DuplicateId(id))
+        }
+    };
+
+    let player_ids: HashSet<Uuid> = archive.players.iter().map(|p| p.id).collect();
+    for user in &archive.users {
+        unique(user.id)?;
+        if user.market_id != market_id {
+            return Err(ArchiveError::WrongMarket(user.id));
+        }
+        if let Some(player_id) = user.player_id {
+            if !player_ids.contains(&player_id) {
+                return Err(ArchiveError::UnknownPlayer(player_id));
+            }
+        }
+    }
+
+    if !archive
+        .users
+        .iter()
+        .any(|u| u.id == archive.market.created_by && u.is_admin)
+    {
+        return Err(ArchiveError::MissingAdmin);
+    }
+
+    let user_ids: HashSet<Uuid> = archive.users.iter().map(|u| u.id).collect();
+    let known_user = |id: Uuid| {
+        if user_ids.contains(&id) {
+            Ok(())
+        } else {
+            Err(ArchiveError::UnknownUser(id))
+        }
+    };
+
+    for bet in &archive.bets {
+        unique(bet.id)?;
+        if bet.market_id != market_id {
+            return Err(ArchiveError::WrongMarket(bet.id));
+        }
+        known_user(bet.created_by)?;
+        for subject in &bet.subject_user_ids {
+            known_user(*subject)?;
+        }
+    }
+
+    let bet_ids: HashSet<Uuid> = archive.bets.iter().map(|b| b.id).collect();
+    for wager in &archive.wagers {
+        unique(wager.id)?;
+        if !bet_ids.contains(&wager.bet_id) {
+            return Err(ArchiveError::UnknownBet(wager.bet_id));
+        }
+        known_user(wager.user_id)?;
+    }
+
+    for spectator in &archive.spectators {
+        unique(spectator.id)?;
+        if spectator.market_id != market_id {
+            return Err(ArchiveError::WrongMarket(spectator.id));
+        }
+    }
+
+    if !archive.carry_overs.is_empty() && archive.season.is_none() {
+        return Err(ArchiveError::CarryOverWithoutSeason);
+    }
+    let mut carried = HashSet::new();
+    for carry_over in &archive.carry_overs {
+        known_user(carry_over.user_id)?;
+        if !carried.insert(carry_over.user_id) {
+            return Err(ArchiveError::DuplicateId(carry_over.user_id));
+        }
+    }
+
+    if let Some(ceremony) = &archive.reveal_ceremony {
+        if ceremony.market_id != market_id {
+            return Err(ArchiveError::WrongMarket(market_id));
+        }
+        if let Some(bet_id) = ceremony.bet_ids.iter().find(|id| !bet_ids.contains(id)) {
+            return Err(ArchiveError::UnknownBet(*bet_id));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,180 @@
+/// CSV reports of a market for spreadsheets
+///
+/// Each report is a header line followed by one line per row. Rows are built
+/// here so the service can stream them a bet or a user at a time.
+use crate::domain::models::{Bet, BetStatus, MembershipStatus, Side, User, Wager};
+use serde::Deserialize;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Which CSV report to export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvReport {
+    Wagers,      // Every wager with the pools and probability after it, refunds included
+    Results,     // Per-user totals
+    Leaderboard, // Final standings of current players
+}
+
+#[derive(Debug, Error, PartialEq)]
+#[error("Unknown report '{0}' (expected wagers, results or leaderboard)")]
+pub struct UnknownReport(pub String);
+
+impl FromStr for CsvReport {
+    type Err = UnknownReport;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "wagers" => Ok(CsvReport::Wagers),
+            "results" => Ok(CsvReport::Results),
+            "leaderboard" => Ok(CsvReport::Leaderboard),
+            _ => Err(UnknownReport(s.to_string())),
+        }
+    }
+}
+
+impl CsvReport {
+    /// Header line, including the trailing newline
+    pub fn header(self) -> &'static str {
+        match self {
+            CsvReport::Wagers => {
+                "bet_id,bet,bet_status,wager_id,user_id,display_name,side,amount,placed_at,yes_pool_after,no_pool_after,probability_after,refunded\n"
+            }
+            CsvReport::Results => {
+                "user_id,display_name,status,wagers,staked,bets_settled,bets_won,payouts,balance,profit\n"
+            }
+            CsvReport::Leaderboard => "rank,user_id,display_name,avatar,balance,profit\n",
+        }
+    }
+
+    /// Suggested file name for downloads
+    pub fn file_name(self) -> &'static str {
+        match self {
+            CsvReport::Wagers => "wagers.csv",
+            CsvReport::Results => "results.csv",
+            CsvReport::Leaderboard => "leaderboard.csv",
+        }
+    }
+}
+
+/// Per-user totals for the results report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserResult {
+    pub wagers: usize,
+    pub staked: i64,
+    pub bets_settled: usize, // Resolved bets the user had coins on
+    pub bets_won: usize,     // Settled bets that paid out more than was staked
+    pub payouts: i64,
+}
+
+/// One wager line; `user` is `None` if the wager's user is no longer around
+pub fn wager_row(bet: &Bet, wager: &Wager, user: Option<&User>) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{},{:.4},{}\n",
+        bet.id,
+        text(&bet.description),
+        bet_status(bet.status),
+        wager.id,
+        wager.user_id,
+        text(user.map(|u| u.display_name.as_str()).unwrap_or("")),
+        match wager.side {
+            Side::Yes => "YES",
+            Side::No => "NO",
+        },
+        wager.amount,
+        wager.placed_at.to_rfc3339(),
+        wager.yes_pool_after,
+        wager.no_pool_after,
+        wager.probability_after,
+        wager.refunded,
+    )
+}
+
+pub fn result_row(user: &User, result: &UserResult, starting_balance: i64) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{},{},{}\n",
+        user.id,
+        text(&user.display_name),
+        membership_status(user.status),
+        result.wagers,
+        result.staked,
+        result.bets_settled,
+        result.bets_won,
+        result.payouts,
+        user.balance,
+        user.balance - starting_balance,
+    )
+}
+
+pub fn leaderboard_row(rank: usize, user: &User, starting_balance: i64) -> String {
+    format!(
+        "{},{},{},{},{},{}\n",
+        rank,
+        user.id,
+        text(&user.display_name),
+        text(&user.avatar),
+        user.balance,
+        user.balance - starting_balance,
+    )
+}
+
+/// Escape a free-text field
+///
+/// Fields are quoted when they contain a delimiter, quote or line break.
+/// Text a spreadsheet would treat as a formula is prefixed with `'` so a
+/// player named `=HYPERLINK(...)` stays a name.
+pub fn text(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn bet_status(status: BetStatus) -> &'static str {
+    match status {
+        BetStatus::Pending => "pending",
+        BetStatus::Active => "active",
+        BetStatus::ResolvedYes => "resolved_yes",
+        BetStatus::ResolvedNo => "resolved_no",
+        BetStatus::Challenged => "challenged",
+        BetStatus::Void => "void",
+    }
+}
+
+fn membership_status(status: MembershipStatus) -> &'static str {
+    match status {
+        MembershipStatus::Active => "active",
+        MembershipStatus::Left => "left",
+        MembershipStatus::Kicked => "kicked",
+        MembershipStatus::Banned => "banned",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_escaping() {
+        assert_eq!(text("Grandma"), "Grandma");
+        assert_eq!(text("Dad, again"), "\"Dad, again\"");
+        assert_eq!(text("the \"big\" game"), "\"the \"\"big\"\" game\"");
+        assert_eq!(text("two\nlines"), "\"two\nlines\"");
+        assert_eq!(text("=1+1"), "'=1+1");
+        assert_eq!(text("-5, really"), "\"'-5, really\"");
+    }
+
+    #[test]
+    fn test_report_from_str() {
+        assert_eq!("Wagers".parse(), Ok(CsvReport::Wagers));
+        assert_eq!("leaderboard".parse(), Ok(CsvReport::Leaderboard));
+        assert!("bets".parse::<CsvReport>().is_err());
+    }
+}
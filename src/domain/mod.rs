@@ -1,3 +1,5 @@
+pub mod archive;
+pub mod csv;
 pub mod models;
 pub mod parimutuel;
 pub mod rules;
@@ -1,9 +1,10 @@
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 /// Market status lifecycle
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum MarketStatus {
     Draft,    // Collecting players and bet ideas
@@ -13,7 +14,7 @@ pub enum MarketStatus {
 }
 
 /// A prediction market (e.g., "Thanksgiving 2024")
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Market {
     pub id: Uuid,
     pub name: String,
@@ -26,8 +27,30 @@ pub struct Market {
     pub created_at: DateTime<Utc>,
 }
 
+/// A market together with every user and bet in it
+///
+/// Read in one go to hydrate a client (see `Database::get_market_contents`).
+#[derive(Debug, Clone)]
+pub struct MarketContents {
+    pub market: Market,
+    pub users: Vec<User>, // Including those who left or were removed
+    pub bets: Vec<Bet>,   // Every status, pending included
+}
+
+/// Everything removing a member from a market changes
+///
+/// Applied in one go by `Database::apply_removal`, so balances and pools
+/// never end up half refunded.
+#[derive(Debug, Clone)]
+pub struct Removal {
+    pub user_id: Uuid,
+    pub status: MembershipStatus,
+    pub refunds: Vec<Wager>, // Marked refunded, stake credited to the owner
+    pub voided_bet_ids: Vec<Uuid>, // Voided; refunds on other bets leave their pools
+}
+
 /// A user in a market (Jackbox-style: device ID + display name)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct User {
     pub id: Uuid,
     pub market_id: Uuid,
@@ -36,11 +59,165 @@ pub struct User {
     pub avatar: String, // Emoji
     pub balance: i64,   // Current coin balance
     pub is_admin: bool,
+    pub status: MembershipStatus,
+    pub player_id: Option<Uuid>, // Persistent identity across markets, if linked
     pub joined_at: DateTime<Utc>,
 }
 
+impl User {
+    pub fn is_active(&self) -> bool {
+        self.status == MembershipStatus::Active
+    }
+}
+
+/// Whether a user is still playing in their market
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MembershipStatus {
+    Active, // Playing
+    Left,   // Left on their own; can rejoin
+    Kicked, // Removed by the admin; can rejoin
+    Banned, // Removed by the admin; their device can't rejoin
+}
+
+/// A read-only viewer of a market (e.g., the TV in the living room)
+///
+/// Spectators have no balance and are not `User`s, so they can never wager
+/// or be the subject of a bet. They authenticate with their `token`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Spectator {
+    pub id: Uuid,
+    pub market_id: Uuid,
+    pub display_name: String,
+    pub token: String, // Secret handed out on join, used instead of a user ID
+    pub joined_at: DateTime<Utc>,
+}
+
+/// An outbound webhook registered on a market
+///
+/// Events published for the market are POSTed to `url`, signed with `secret`
+/// (see `api::webhooks`).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub market_id: Uuid,
+    pub url: String,
+    #[serde(skip_serializing)] // Never echoed back by the API
+    pub secret: String,
+    pub events: Vec<String>, // Message types to send; empty means all
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// Whether events of this type should be sent here
+    pub fn wants(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event)
+    }
+}
+
+/// One attempt at delivering an event to a webhook
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,            // The message's `type`
+    pub attempt: u32,             // 1 for the first try
+    pub status_code: Option<u16>, // None when no response came back
+    pub error: Option<String>,
+    pub succeeded: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A persistent player identity linked to per-market `User` rows
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Player {
+    pub id: Uuid,
+    pub display_name: String,
+    pub avatar: String, // Emoji
+    pub created_at: DateTime<Utc>,
+}
+
+/// Lifetime stats for a player across every market they've been linked to
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PlayerStats {
+    pub markets_played: usize,
+    pub net_profit: i64,     // Sum of balance minus starting balance
+    pub bets_settled: usize, // Resolved bets the player wagered on
+    pub bets_won: usize,     // Of those, bets that paid back more than was staked
+    pub win_rate: f64,       // bets_won / bets_settled (0.0 with no settled bets)
+    pub biggest_payout: i64, // Largest single-bet payout
+}
+
+/// Reusable market settings and bet ideas (e.g. "Thanksgiving" every year)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MarketTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub starting_balance: i64,
+    pub duration_hours: i64,
+    pub bets: Vec<TemplateBet>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A bet idea in a template; people are referenced by display name
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TemplateBet {
+    pub description: String,
+    pub initial_odds: String,
+    pub hide_from_subject: bool,
+    pub about_everyone: bool,
+    pub subject_names: Vec<String>,
+    pub creator_name: Option<String>, // Falls back to the new market's admin
+}
+
+/// A series of markets with a shared leaderboard (e.g. one market per holiday)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Season {
+    pub id: Uuid,
+    pub name: String,
+    pub carry_over_balances: bool, // Players start each market with their previous balance
+    pub created_at: DateTime<Utc>,
+}
+
+/// One row of a season leaderboard
+///
+/// Seats are grouped by linked player, falling back to device for unlinked seats.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SeasonStanding {
+    pub player_id: Option<Uuid>,
+    pub display_name: String, // From the most recent seat
+    pub avatar: String,
+    pub markets_played: usize,
+    pub profit: i64, // Sum of per-market profit, excluding carried-over balances
+    pub rank: usize,
+}
+
+/// A short-lived code for moving a player's seats to another device
+///
+/// Generated on the old device and redeemed on the new one; every `User` row
+/// bound to `device_id` is rebound to the redeeming device.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LinkCode {
+    pub code: String,
+    pub device_id: String, // Device whose seats will move
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub redeemed_at: Option<DateTime<Utc>>,
+    pub redeemed_by: Option<String>, // Device that redeemed the code
+}
+
+/// A security-relevant event, also used to rate limit identity operations
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub action: String,
+    pub device_id: String,
+    pub detail: String,
+    pub created_at: DateTime<Utc>,
+}
+
 /// Bet status lifecycle
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum BetStatus {
     Pending,     // Awaiting admin approval
@@ -48,10 +225,11 @@ pub enum BetStatus {
     ResolvedYes, // Outcome: YES
     ResolvedNo,  // Outcome: NO
     Challenged,  // Under dispute
+    Void,        // Called off (e.g., its subject left); all wagers refunded
 }
 
 /// Challenge status
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 #[allow(dead_code)]
 pub enum ChallengeStatus {
@@ -61,15 +239,16 @@ pub enum ChallengeStatus {
     Resolved,  // Dispute settled
 }
 
-/// A prediction bet about a user
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A prediction bet about one or more users
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Bet {
     pub id: Uuid,
     pub market_id: Uuid,
-    pub subject_user_id: Uuid, // Who the bet is about
-    pub created_by: Uuid,      // User ID who created it
-    pub description: String,   // "Dad falls asleep during movie"
-    pub initial_odds: String,  // e.g., "3:1" - for display only
+    pub subject_user_ids: Vec<Uuid>, // Who the bet is about
+    pub about_everyone: bool,        // Bet is about the whole group
+    pub created_by: Uuid,            // User ID who created it
+    pub description: String,         // "Dad falls asleep during movie"
+    pub initial_odds: String,        // e.g., "3:1" - for display only
     pub status: BetStatus,
     pub yes_pool: i64,           // Total coins bet on YES
     pub no_pool: i64,            // Total coins bet on NO
@@ -79,7 +258,7 @@ pub struct Bet {
 }
 
 /// Betting side
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum Side {
     Yes,
@@ -87,7 +266,7 @@ pub enum Side {
 }
 
 /// A wager on a bet
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Wager {
     pub id: Uuid,
     pub bet_id: Uuid,
@@ -100,10 +279,15 @@ pub struct Wager {
     pub yes_pool_after: i64,
     pub no_pool_after: i64,
     pub probability_after: f64, // YES probability as decimal (0.0-1.0)
+
+    // Stake given back when its owner or the bet's subject left the market;
+    // the wager stays for the history but no longer counts
+    #[serde(default)]
+    pub refunded: bool,
 }
 
 /// A challenge to a bet resolution
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[allow(dead_code)]
 pub struct Challenge {
     pub id: Uuid,
@@ -119,7 +303,7 @@ pub struct Challenge {
 }
 
 /// View model: Bet with visibility filtering applied
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BetView {
     pub id: Uuid,
     pub market_id: Uuid,
@@ -128,10 +312,11 @@ pub struct BetView {
     pub is_hidden: bool,
 
     // If hidden, these fields are redacted
-    pub subject_user_id: Option<Uuid>,
+    pub subject_user_ids: Option<Vec<Uuid>>,
     pub description: Option<String>,
 
     // Always visible
+    pub about_everyone: bool,
     pub created_by: Uuid,
     pub initial_odds: String,
     pub status: BetStatus,
@@ -146,28 +331,56 @@ impl Bet {
     pub fn to_view(&self, viewing_user_id: Uuid) -> BetView {
         // Hide from subject only if:
         // 1. The bet is marked as hide_from_subject by creator
-        // 2. The viewing user IS the subject
+        // 2. The viewing user IS a subject (and didn't write the bet)
         // 3. The bet is not yet resolved (reveal on resolution)
         let is_hidden = self.hide_from_subject
-            && self.subject_user_id == viewing_user_id
-            && self.status != BetStatus::ResolvedYes
-            && self.status != BetStatus::ResolvedNo
-            && self.status != BetStatus::Challenged;
+            && self.is_subject(viewing_user_id)
+            && self.created_by != viewing_user_id
+            && !self.is_revealed();
+
+        self.view(is_hidden)
+    }
+
+    /// Whether the bet is about this user
+    ///
+    /// "Everyone" bets are about every player in the market, so any user id
+    /// passed here is assumed to belong to the bet's market.
+    pub fn is_subject(&self, user_id: Uuid) -> bool {
+        self.about_everyone || self.subject_user_ids.contains(&user_id)
+    }
+
+    /// Convert to a view model for a spectator
+    ///
+    /// Spectator screens are shared (the subject is usually in the room), so
+    /// every hidden bet stays hidden until it is resolved.
+    pub fn to_spectator_view(&self) -> BetView {
+        self.view(self.hide_from_subject && !self.is_revealed())
+    }
 
+    /// Whether the outcome is known, which lifts the hidden-bet redaction
+    fn is_revealed(&self) -> bool {
+        matches!(
+            self.status,
+            BetStatus::ResolvedYes | BetStatus::ResolvedNo | BetStatus::Challenged
+        )
+    }
+
+    fn view(&self, is_hidden: bool) -> BetView {
         BetView {
             id: self.id,
             market_id: self.market_id,
             is_hidden,
-            subject_user_id: if is_hidden {
+            subject_user_ids: if is_hidden {
                 None
             } else {
-                Some(self.subject_user_id)
+                Some(self.subject_user_ids.clone())
             },
             description: if is_hidden {
                 None
             } else {
                 Some(self.description.clone())
             },
+            about_everyone: self.about_everyone,
             created_by: self.created_by,
             initial_odds: self.initial_odds.clone(),
             status: self.status,
@@ -179,8 +392,53 @@ impl Bet {
     }
 }
 
+/// An admin-led reveal of hidden bets, one at a time, once betting is over
+///
+/// Persisted so players who reconnect mid-ceremony pick up where it left off.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RevealCeremony {
+    pub market_id: Uuid,
+    pub bet_ids: Vec<Uuid>,    // Hidden bets, in reveal order
+    pub revealed_count: usize, // How many of `bet_ids` have been shown
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl RevealCeremony {
+    /// Bets already shown to the room, in reveal order
+    pub fn revealed_bet_ids(&self) -> &[Uuid] {
+        &self.bet_ids[..self.revealed_count]
+    }
+
+    /// The bet the admin will reveal next, if any are left
+    pub fn next_bet_id(&self) -> Option<Uuid> {
+        self.bet_ids.get(self.revealed_count).copied()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.completed_at.is_some()
+    }
+}
+
+/// One step of a reveal ceremony: the bet, how it ended, and who cashed in
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BetReveal {
+    pub bet: BetView,
+    pub outcome: Option<Side>, // None if the bet never resolved
+    pub top_winners: Vec<RevealWinner>,
+}
+
+/// A winning player shown during a reveal
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RevealWinner {
+    pub user_id: Uuid,
+    pub display_name: String,
+    pub avatar: String,
+    pub payout: i64,
+}
+
 /// Probability chart data point
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProbabilityPoint {
     pub timestamp: DateTime<Utc>,
     pub yes_probability: f64,
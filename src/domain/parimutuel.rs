@@ -46,7 +46,16 @@ pub fn calculate_potential_payout(
 
 /// Calculate actual payouts for all wagers on a bet after resolution
 /// Returns a map of user_id -> payout_amount
-pub fn calculate_payouts(bet: &Bet, wagers: &[Wager]) -> Vec<(uuid::Uuid, i64)> {
+///
+/// Stakes of `forfeited` users (banned players) stay in the pool but win
+/// nothing, so the other winners share them. If every winner forfeited, the
+/// other bettors get their stakes back instead. Refunded wagers are already
+/// out of the pools and count for nothing.
+pub fn calculate_payouts(
+    bet: &Bet,
+    wagers: &[Wager],
+    forfeited: &[uuid::Uuid],
+) -> Vec<(uuid::Uuid, i64)> {
     use std::collections::HashMap;
 
     let winning_side = match bet.status {
@@ -56,24 +65,42 @@ pub fn calculate_payouts(bet: &Bet, wagers: &[Wager]) -> Vec<(uuid::Uuid, i64)>
     };
 
     let total_pool = bet.yes_pool + bet.no_pool;
-    let winning_pool = match winning_side {
+    let mut winning_pool = match winning_side {
         Side::Yes => bet.yes_pool,
         Side::No => bet.no_pool,
     };
 
-    if winning_pool == 0 {
-        return vec![]; // No winners (shouldn't happen)
-    }
-
     // Group wagers by user and sum their winning bets
     let mut user_wagers: HashMap<uuid::Uuid, i64> = HashMap::new();
+    let mut winners_forfeited = false;
 
     for wager in wagers {
-        if wager.side == winning_side {
+        if wager.refunded || wager.side != winning_side {
+            continue;
+        }
+        if forfeited.contains(&wager.user_id) {
+            winning_pool -= wager.amount;
+            winners_forfeited = true;
+        } else {
             *user_wagers.entry(wager.user_id).or_insert(0) += wager.amount;
         }
     }
 
+    // Nobody is left to win, so the pool goes back to whoever can still have it
+    if user_wagers.is_empty() && winners_forfeited {
+        let mut refunds: HashMap<uuid::Uuid, i64> = HashMap::new();
+        for wager in wagers {
+            if !wager.refunded && !forfeited.contains(&wager.user_id) {
+                *refunds.entry(wager.user_id).or_insert(0) += wager.amount;
+            }
+        }
+        return refunds.into_iter().collect();
+    }
+
+    if winning_pool <= 0 {
+        return vec![]; // No winners left to pay
+    }
+
     // Calculate payout for each user
     user_wagers
         .into_iter()
@@ -146,6 +173,104 @@ mod tests {
         assert_eq!(payout, 83);
     }
 
+    #[test]
+    fn test_forfeited_stakes_go_to_other_winners() {
+        use crate::domain::models::BetStatus;
+        use chrono::Utc;
+        use uuid::Uuid;
+
+        let (alice, troll, bob) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let bet_id = Uuid::new_v4();
+        let wager = |user_id, side, amount| Wager {
+            id: Uuid::new_v4(),
+            bet_id,
+            user_id,
+            side,
+            amount,
+            placed_at: Utc::now(),
+            yes_pool_after: 0,
+            no_pool_after: 0,
+            probability_after: 0.5,
+            refunded: false,
+        };
+        let wagers = vec![
+            wager(alice, Side::Yes, 100),
+            wager(troll, Side::Yes, 100),
+            wager(bob, Side::No, 200),
+        ];
+        let bet = Bet {
+            id: bet_id,
+            market_id: Uuid::new_v4(),
+            subject_user_ids: vec![Uuid::new_v4()],
+            about_everyone: false,
+            created_by: alice,
+            description: "Test bet".to_string(),
+            initial_odds: "1:1".to_string(),
+            status: BetStatus::ResolvedYes,
+            yes_pool: 200,
+            no_pool: 200,
+            hide_from_subject: false,
+            created_at: Utc::now(),
+            resolved_at: None,
+        };
+
+        let mut payouts = calculate_payouts(&bet, &wagers, &[]);
+        payouts.sort_by_key(|(_, payout)| *payout);
+        assert_eq!(payouts.iter().map(|(_, p)| *p).sum::<i64>(), 400);
+
+        // Troll's stake stays in the pool, so Alice wins all of it
+        assert_eq!(
+            calculate_payouts(&bet, &wagers, &[troll]),
+            vec![(alice, 400)]
+        );
+    }
+
+    #[test]
+    fn test_stakes_are_refunded_when_every_winner_forfeited() {
+        use crate::domain::models::BetStatus;
+        use chrono::Utc;
+        use uuid::Uuid;
+
+        let (troll, bob, carol) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let bet_id = Uuid::new_v4();
+        let wager = |user_id, side, amount, refunded| Wager {
+            id: Uuid::new_v4(),
+            bet_id,
+            user_id,
+            side,
+            amount,
+            placed_at: Utc::now(),
+            yes_pool_after: 0,
+            no_pool_after: 0,
+            probability_after: 0.5,
+            refunded,
+        };
+        let wagers = vec![
+            wager(troll, Side::Yes, 100, false),
+            wager(bob, Side::No, 200, false),
+            wager(carol, Side::No, 50, true),
+        ];
+        let bet = Bet {
+            id: bet_id,
+            market_id: Uuid::new_v4(),
+            subject_user_ids: vec![Uuid::new_v4()],
+            about_everyone: false,
+            created_by: troll,
+            description: "Test bet".to_string(),
+            initial_odds: "1:1".to_string(),
+            status: BetStatus::ResolvedYes,
+            yes_pool: 100,
+            no_pool: 200,
+            hide_from_subject: false,
+            created_at: Utc::now(),
+            resolved_at: None,
+        };
+
+        // The troll's stake is forfeited, Bob's comes back and Carol's was
+        // already refunded
+        assert_eq!(calculate_payouts(&bet, &wagers, &[troll]), vec![(bob, 200)]);
+    }
+
     #[test]
     fn test_parse_initial_odds() {
         let (yes, no) = parse_initial_odds("1:1", 100).unwrap();
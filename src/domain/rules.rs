@@ -1,8 +1,32 @@
 /// Game rules and validation logic
-use crate::domain::models::{Bet, BetStatus, Market, MarketStatus, User};
+use crate::domain::models::{
+    Bet, BetStatus, LinkCode, Market, MarketStatus, MembershipStatus, User,
+};
+use chrono::{DateTime, Utc};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use thiserror::Error;
+use unicode_segmentation::UnicodeSegmentation;
+use url::{Host, Url};
 use uuid::Uuid;
 
+/// Longest display name allowed (in characters)
+pub const MAX_DISPLAY_NAME_LEN: usize = 24;
+
+/// How long a device link code stays valid
+pub const LINK_CODE_TTL_MINUTES: i64 = 10;
+
+/// Link codes a device may generate per hour
+pub const MAX_LINK_CODES_PER_HOUR: i64 = 5;
+
+/// Redeem attempts (including failures) a device may make per hour
+pub const MAX_LINK_REDEEMS_PER_HOUR: i64 = 10;
+
+/// Webhooks a market may have registered at once
+pub const MAX_WEBHOOKS_PER_MARKET: usize = 5;
+
+/// Shortest webhook signing secret allowed
+pub const MIN_WEBHOOK_SECRET_LEN: usize = 16;
+
 #[derive(Error, Debug)]
 pub enum RuleError {
     #[error("Market is not open for betting")]
@@ -28,6 +52,72 @@ pub enum RuleError {
 
     #[error("Market not in correct status for this action")]
     InvalidMarketStatus,
+
+    #[error("Bet subject must be a player in this market")]
+    InvalidSubject,
+
+    #[error("Bet must be about at least one player or everyone")]
+    MissingSubject,
+
+    #[error("Bets are revealed once the market is closed")]
+    RevealNotAvailable,
+
+    #[error("User is no longer a member of this market")]
+    NotAMember,
+
+    #[error("The admin can't leave or be removed from their own market")]
+    AdminCannotLeave,
+
+    #[error("This device has been banned from the market")]
+    Banned,
+
+    #[error("Display name must be 1-{MAX_DISPLAY_NAME_LEN} characters")]
+    InvalidDisplayName,
+
+    #[error("Display name is already taken in this market")]
+    DisplayNameTaken,
+
+    #[error("Avatar must be a single emoji")]
+    InvalidAvatar,
+
+    #[error("This seat is already linked to another player")]
+    AlreadyLinked,
+
+    #[error("Player already has a seat in this market")]
+    PlayerAlreadyInMarket,
+
+    #[error("This seat belongs to another device")]
+    NotYourSeat,
+
+    #[error("Only a player with a seat on this device can be linked")]
+    NotYourPlayer,
+
+    #[error("Market is already part of a season")]
+    AlreadyInSeason,
+
+    #[error("Link code is invalid or has expired")]
+    InvalidLinkCode,
+
+    #[error("Too many attempts, try again later")]
+    RateLimited,
+
+    #[error("Webhook URL must start with http:// or https://")]
+    InvalidWebhookUrl,
+
+    #[error("Webhook URL must point at a public host")]
+    PrivateWebhookHost,
+
+    #[error("Webhook secret must be at least {MIN_WEBHOOK_SECRET_LEN} characters")]
+    WeakWebhookSecret,
+
+    #[error("This bet is hidden from you until it's resolved")]
+    BetHidden,
+
+    #[error("Unknown webhook event: {0}")]
+    UnknownWebhookEvent(String),
+
+    #[error("A market can have at most {MAX_WEBHOOKS_PER_MARKET} webhooks")]
+    TooManyWebhooks,
 }
 
 /// Validate that a user can place a wager
@@ -47,6 +137,11 @@ pub fn validate_wager(
         return Err(RuleError::BetNotActive);
     }
 
+    // Kicked, banned and departed players can't wager
+    if !user.is_active() {
+        return Err(RuleError::NotAMember);
+    }
+
     // User must have sufficient balance
     if user.balance < amount {
         return Err(RuleError::InsufficientBalance {
@@ -64,7 +159,9 @@ pub fn validate_wager(
 
     // CRITICAL: Cannot bet on bets about yourself
     // (This is the core "hidden bet" mechanic)
-    if bet.subject_user_id == user.id {
+    // Every subject is excluded, which on an "everyone" bet is every player
+    // but its author: they wrote it, and can never be a named subject.
+    if bet.is_subject(user.id) && bet.created_by != user.id {
         return Err(RuleError::CannotBetOnSelf);
     }
 
@@ -75,7 +172,8 @@ pub fn validate_wager(
 pub fn validate_bet_creation(
     market: &Market,
     user: &User,
-    _subject_user_id: Uuid,
+    subjects: &[User],
+    about_everyone: bool,
     opening_wager: i64,
 ) -> Result<(), RuleError> {
     // Market must be in draft or open status
@@ -83,6 +181,10 @@ pub fn validate_bet_creation(
         return Err(RuleError::InvalidMarketStatus);
     }
 
+    if !user.is_active() {
+        return Err(RuleError::NotAMember);
+    }
+
     // User must have sufficient balance for opening wager
     if user.balance < opening_wager {
         return Err(RuleError::InsufficientBalance {
@@ -98,8 +200,24 @@ pub fn validate_bet_creation(
         ));
     }
 
-    // Subject must be in the same market
-    // (This would be validated at the DB layer, but we document it here)
+    // Bet must be about someone
+    if subjects.is_empty() && !about_everyone {
+        return Err(RuleError::MissingSubject);
+    }
+
+    // Subjects must be players in the same market
+    // (spectators are not users, so they can never be subjects)
+    if subjects
+        .iter()
+        .any(|subject| subject.market_id != market.id)
+    {
+        return Err(RuleError::InvalidSubject);
+    }
+
+    // The opening wager is a wager, so the creator can't name themselves
+    if subjects.iter().any(|subject| subject.id == user.id) {
+        return Err(RuleError::CannotBetOnSelf);
+    }
 
     Ok(())
 }
@@ -112,6 +230,280 @@ pub fn validate_bet_approval(user: &User) -> Result<(), RuleError> {
     Ok(())
 }
 
+/// Validate that bets in a market may be revealed
+///
+/// Revealing while betting is still open would spoil the hidden bets.
+pub fn validate_reveal(market: &Market) -> Result<(), RuleError> {
+    if market.status != MarketStatus::Closed && market.status != MarketStatus::Resolved {
+        return Err(RuleError::RevealNotAvailable);
+    }
+    Ok(())
+}
+
+/// Validate that a user can run the reveal ceremony (admin only)
+pub fn validate_reveal_ceremony(market: &Market, user: &User) -> Result<(), RuleError> {
+    if !user.is_admin || user.market_id != market.id {
+        return Err(RuleError::AdminOnly);
+    }
+    validate_reveal(market)
+}
+
+/// Validate that a user is the admin of a specific market
+pub fn validate_market_admin(market: &Market, user: &User) -> Result<(), RuleError> {
+    if !user.is_admin || user.market_id != market.id {
+        return Err(RuleError::AdminOnly);
+    }
+    Ok(())
+}
+
+/// Validate that an admin can add their market to a season
+///
+/// `current_season` is the season the market already belongs to, if any.
+pub fn validate_season_market(
+    market: &Market,
+    user: &User,
+    current_season: Option<Uuid>,
+) -> Result<(), RuleError> {
+    validate_market_admin(market, user)?;
+    if current_season.is_some() {
+        return Err(RuleError::AlreadyInSeason);
+    }
+    Ok(())
+}
+
+/// Validate that a player can leave their market
+pub fn validate_leave(user: &User) -> Result<(), RuleError> {
+    if !user.is_active() {
+        return Err(RuleError::NotAMember);
+    }
+    // Someone has to run the market
+    if user.is_admin {
+        return Err(RuleError::AdminCannotLeave);
+    }
+    Ok(())
+}
+
+/// Validate that an admin can kick or ban a player
+pub fn validate_removal(
+    market: &Market,
+    admin: &User,
+    target: &User,
+    status: MembershipStatus,
+) -> Result<(), RuleError> {
+    if !admin.is_admin || admin.market_id != market.id {
+        return Err(RuleError::AdminOnly);
+    }
+    if target.market_id != market.id {
+        return Err(RuleError::NotAMember);
+    }
+    if target.is_admin {
+        return Err(RuleError::AdminCannotLeave);
+    }
+    // Kicks apply to current players; a ban can follow a kick or a leave
+    match status {
+        MembershipStatus::Banned if target.status != MembershipStatus::Banned => Ok(()),
+        MembershipStatus::Kicked if target.is_active() => Ok(()),
+        _ => Err(RuleError::NotAMember),
+    }
+}
+
+/// Validate that a device may (re)join a market
+pub fn validate_rejoin(user: &User) -> Result<(), RuleError> {
+    if user.status == MembershipStatus::Banned {
+        return Err(RuleError::Banned);
+    }
+    Ok(())
+}
+
+/// Validate a profile change against the other players in the market
+///
+/// Names are matched case-insensitively, since players @mention each other.
+pub fn validate_profile(
+    user: &User,
+    others: &[User],
+    display_name: &str,
+    avatar: &str,
+) -> Result<(), RuleError> {
+    if !user.is_active() {
+        return Err(RuleError::NotAMember);
+    }
+
+    let len = display_name.chars().count();
+    if len == 0 || len > MAX_DISPLAY_NAME_LEN || display_name.trim() != display_name {
+        return Err(RuleError::InvalidDisplayName);
+    }
+
+    let taken = others.iter().any(|other| {
+        other.id != user.id && other.display_name.to_lowercase() == display_name.to_lowercase()
+    });
+    if taken {
+        return Err(RuleError::DisplayNameTaken);
+    }
+
+    if !is_single_emoji(avatar) {
+        return Err(RuleError::InvalidAvatar);
+    }
+
+    Ok(())
+}
+
+/// Whether a string is exactly one emoji (including ZWJ sequences and flags)
+fn is_single_emoji(s: &str) -> bool {
+    let mut graphemes = s.graphemes(true);
+    let (Some(grapheme), None) = (graphemes.next(), graphemes.next()) else {
+        return false;
+    };
+
+    grapheme.chars().any(|c| {
+        matches!(c as u32,
+            0x1F000..=0x1FAFF // Pictographs, emoticons, transport, flags
+            | 0x2600..=0x27BF // Misc symbols and dingbats
+            | 0x2300..=0x23FF // Misc technical (⌚, ⏰)
+            | 0x2B00..=0x2BFF // Arrows and shapes (⭐, ⬛)
+            | 0x20E3          // Keycap (1️⃣)
+        )
+    })
+}
+
+/// Validate linking a market seat to a persistent player
+///
+/// `device_id` is the caller's device, which must hold the seat. `player` is
+/// the existing player and the rows already linked to it, one of which must be
+/// on the same device, or `None` for a new player. Relinking to the same
+/// player is allowed so the call is idempotent.
+pub fn validate_player_link(
+    user: &User,
+    device_id: &str,
+    player: Option<(Uuid, &[User])>,
+) -> Result<(), RuleError> {
+    if user.device_id != device_id {
+        return Err(RuleError::NotYourSeat);
+    }
+
+    let Some((player_id, player_seats)) = player else {
+        return match user.player_id {
+            Some(_) => Err(RuleError::AlreadyLinked),
+            None => Ok(()),
+        };
+    };
+
+    if user.player_id.is_some_and(|id| id != player_id) {
+        return Err(RuleError::AlreadyLinked);
+    }
+
+    if !player_seats.iter().any(|seat| seat.device_id == device_id) {
+        return Err(RuleError::NotYourPlayer);
+    }
+
+    if player_seats
+        .iter()
+        .any(|seat| seat.market_id == user.market_id && seat.id != user.id)
+    {
+        return Err(RuleError::PlayerAlreadyInMarket);
+    }
+
+    Ok(())
+}
+
+/// Validate that a device may make another rate-limited request
+pub fn validate_rate_limit(recent_attempts: i64, max_per_hour: i64) -> Result<(), RuleError> {
+    if recent_attempts >= max_per_hour {
+        return Err(RuleError::RateLimited);
+    }
+    Ok(())
+}
+
+/// Validate that a link code can be redeemed by a device
+///
+/// Every failure reports the same error so codes can't be probed.
+pub fn validate_link_redemption(
+    link: &LinkCode,
+    device_id: &str,
+    now: DateTime<Utc>,
+) -> Result<(), RuleError> {
+    if link.redeemed_at.is_some() || link.expires_at <= now || link.device_id == device_id {
+        return Err(RuleError::InvalidLinkCode);
+    }
+    Ok(())
+}
+
+/// Validate a webhook registration
+///
+/// `existing` is how many webhooks the market already has. Webhooks are
+/// posted from the server, so unless `allow_private` is set (local
+/// development and tests) the host must not be loopback, link-local or on a
+/// private network.
+pub fn validate_webhook(
+    url: &str,
+    secret: &str,
+    existing: usize,
+    allow_private: bool,
+) -> Result<(), RuleError> {
+    let parsed = Url::parse(url).map_err(|_| RuleError::InvalidWebhookUrl)?;
+    // The parser forgives `https:///hook` as host `hook`; we don't
+    let authority = url.split_once("://").map_or("", |(_, rest)| rest);
+    if !matches!(parsed.scheme(), "http" | "https")
+        || authority.is_empty()
+        || authority.starts_with('/')
+        || url.contains(char::is_whitespace)
+    {
+        return Err(RuleError::InvalidWebhookUrl);
+    }
+    let public = match parsed.host() {
+        None => return Err(RuleError::InvalidWebhookUrl),
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(ip)) => is_public_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_public_ip(IpAddr::V6(ip)),
+    };
+    if !public && !allow_private {
+        return Err(RuleError::PrivateWebhookHost);
+    }
+    if secret.chars().count() < MIN_WEBHOOK_SECRET_LEN {
+        return Err(RuleError::WeakWebhookSecret);
+    }
+    if existing >= MAX_WEBHOOKS_PER_MARKET {
+        return Err(RuleError::TooManyWebhooks);
+    }
+    Ok(())
+}
+
+/// Whether an address is reachable on the public internet
+///
+/// Loopback, link-local (including the 169.254.169.254 metadata service),
+/// private, shared (CGNAT), unspecified and broadcast addresses are not, and
+/// neither are IPv6 addresses wrapping one of those.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    let shared = a == 100 && (64..128).contains(&b);
+    !(ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_private()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || shared
+        || a == 0)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    let unique_local = first & 0xfe00 == 0xfc00;
+    let link_local = first & 0xffc0 == 0xfe80;
+    !(ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
+}
+
 /// Validate that a bet can be resolved
 pub fn validate_bet_resolution(_market: &Market, bet: &Bet, user: &User) -> Result<(), RuleError> {
     // Only admin can resolve
@@ -139,6 +531,7 @@ pub fn validate_bet_resolution(_market: &Market, bet: &Bet, user: &User) -> Resu
 mod tests {
     use super::*;
     use chrono::Utc;
+    use uuid::Uuid;
 
     fn mock_market() -> Market {
         Market {
@@ -163,6 +556,8 @@ mod tests {
             avatar: "🎲".to_string(),
             balance,
             is_admin,
+            status: MembershipStatus::Active,
+            player_id: None,
             joined_at: Utc::now(),
         }
     }
@@ -171,7 +566,8 @@ mod tests {
         Bet {
             id: Uuid::new_v4(),
             market_id: Uuid::new_v4(),
-            subject_user_id: subject_id,
+            subject_user_ids: vec![subject_id],
+            about_everyone: false,
             created_by: Uuid::new_v4(),
             description: "Test bet".to_string(),
             initial_odds: "1:1".to_string(),
@@ -213,6 +609,229 @@ mod tests {
         assert!(matches!(result, Err(RuleError::CannotBetOnSelf)));
     }
 
+    #[test]
+    fn test_validate_wager_any_subject_cannot_bet() {
+        let market = mock_market();
+        let alice = mock_user(1000, false);
+        let bob = mock_user(1000, false);
+        let mut bet = mock_bet(alice.id);
+        bet.subject_user_ids.push(bob.id);
+
+        let result = validate_wager(&market, &bet, &bob, 100);
+        assert!(matches!(result, Err(RuleError::CannotBetOnSelf)));
+    }
+
+    #[test]
+    fn test_validate_wager_removed_user() {
+        let market = mock_market();
+        let mut user = mock_user(1000, false);
+        user.status = MembershipStatus::Kicked;
+        let bet = mock_bet(Uuid::new_v4());
+
+        let result = validate_wager(&market, &bet, &user, 100);
+        assert!(matches!(result, Err(RuleError::NotAMember)));
+    }
+
+    #[test]
+    fn test_validate_wager_everyone_is_a_subject() {
+        let market = mock_market();
+        let player = mock_user(1000, false);
+        let mut bet = mock_bet(Uuid::new_v4());
+        bet.subject_user_ids.clear();
+        bet.about_everyone = true;
+
+        let result = validate_wager(&market, &bet, &player, 100);
+        assert!(matches!(result, Err(RuleError::CannotBetOnSelf)));
+
+        bet.created_by = player.id;
+        assert!(validate_wager(&market, &bet, &player, 100).is_ok());
+    }
+
+    #[test]
+    fn test_validate_bet_creation_about_everyone() {
+        let market = mock_market();
+        let mut creator = mock_user(1000, false);
+        creator.market_id = market.id;
+
+        assert!(validate_bet_creation(&market, &creator, &[], true, 10).is_ok());
+    }
+
+    #[test]
+    fn test_validate_profile() {
+        let user = mock_user(1000, false);
+        let mut other = mock_user(1000, false);
+        other.display_name = "Bob".to_string();
+        let others = vec![user.clone(), other];
+
+        assert!(validate_profile(&user, &others, "Alice", "👩").is_ok());
+        assert!(validate_profile(&user, &others, "Test User", "👨‍👩‍👧").is_ok());
+        assert!(validate_profile(&user, &others, "Alice", "🇺🇸").is_ok());
+        assert!(matches!(
+            validate_profile(&user, &others, "bob", "👩"),
+            Err(RuleError::DisplayNameTaken)
+        ));
+        assert!(matches!(
+            validate_profile(&user, &others, "", "👩"),
+            Err(RuleError::InvalidDisplayName)
+        ));
+        assert!(matches!(
+            validate_profile(&user, &others, &"x".repeat(25), "👩"),
+            Err(RuleError::InvalidDisplayName)
+        ));
+        assert!(matches!(
+            validate_profile(&user, &others, "Alice", "👩👩"),
+            Err(RuleError::InvalidAvatar)
+        ));
+        assert!(matches!(
+            validate_profile(&user, &others, "Alice", "A"),
+            Err(RuleError::InvalidAvatar)
+        ));
+    }
+
+    #[test]
+    fn test_validate_webhook() {
+        let secret = "0123456789abcdef";
+
+        assert!(validate_webhook("https://chat.example/hook", secret, 0, false).is_ok());
+        assert!(validate_webhook("http://93.184.216.34:8080/hook", secret, 0, false).is_ok());
+        assert!(matches!(
+            validate_webhook("ftp://chat.example/hook", secret, 0, false),
+            Err(RuleError::InvalidWebhookUrl)
+        ));
+        assert!(matches!(
+            validate_webhook("https:///hook", secret, 0, false),
+            Err(RuleError::InvalidWebhookUrl)
+        ));
+        assert!(matches!(
+            validate_webhook("not a url", secret, 0, false),
+            Err(RuleError::InvalidWebhookUrl)
+        ));
+        assert!(matches!(
+            validate_webhook("https://chat.example/hook", "short", 0, false),
+            Err(RuleError::WeakWebhookSecret)
+        ));
+        assert!(matches!(
+            validate_webhook(
+                "https://chat.example/hook",
+                secret,
+                MAX_WEBHOOKS_PER_MARKET,
+                false
+            ),
+            Err(RuleError::TooManyWebhooks)
+        ));
+
+        // The server mustn't be pointed at itself or its network
+        for url in [
+            "http://localhost:8080/hook",
+            "http://api.localhost/hook",
+            "http://127.0.0.1/hook",
+            "http://2130706433/hook",
+            "http://0.0.0.0/hook",
+            "http://10.1.2.3/hook",
+            "http://172.16.0.1/hook",
+            "http://192.168.1.1/hook",
+            "http://100.64.0.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+            "http://[fe80::1]/hook",
+            "http://[fd00::1]/hook",
+        ] {
+            assert!(
+                matches!(
+                    validate_webhook(url, secret, 0, false),
+                    Err(RuleError::PrivateWebhookHost)
+                ),
+                "{} was accepted",
+                url
+            );
+            assert!(validate_webhook(url, secret, 0, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_validate_player_link() {
+        let player_id = Uuid::new_v4();
+        let mut seat = mock_user(1000, false);
+        let mut other_seat = mock_user(1000, false);
+        other_seat.player_id = Some(player_id);
+        let seats = vec![other_seat.clone()];
+
+        assert!(validate_player_link(&seat, "test-device", None).is_ok());
+        assert!(validate_player_link(&seat, "test-device", Some((player_id, &seats))).is_ok());
+        assert!(matches!(
+            validate_player_link(&seat, "other-device", None),
+            Err(RuleError::NotYourSeat)
+        ));
+
+        // Someone else's player
+        other_seat.device_id = "other-device".to_string();
+        assert!(matches!(
+            validate_player_link(&seat, "test-device", Some((player_id, &[other_seat]))),
+            Err(RuleError::NotYourPlayer)
+        ));
+        assert!(matches!(
+            validate_player_link(&seat, "test-device", Some((player_id, &[]))),
+            Err(RuleError::NotYourPlayer)
+        ));
+
+        // One seat per market
+        let mut same_market = seats[0].clone();
+        same_market.market_id = seat.market_id;
+        assert!(matches!(
+            validate_player_link(&seat, "test-device", Some((player_id, &[same_market]))),
+            Err(RuleError::PlayerAlreadyInMarket)
+        ));
+
+        seat.player_id = Some(Uuid::new_v4());
+        assert!(matches!(
+            validate_player_link(&seat, "test-device", None),
+            Err(RuleError::AlreadyLinked)
+        ));
+    }
+
+    #[test]
+    fn test_validate_link_redemption() {
+        let now = Utc::now();
+        let mut link = LinkCode {
+            code: "ABCD2345".to_string(),
+            device_id: "phone".to_string(),
+            created_at: now,
+            expires_at: now + chrono::Duration::minutes(LINK_CODE_TTL_MINUTES),
+            redeemed_at: None,
+            redeemed_by: None,
+        };
+
+        assert!(validate_link_redemption(&link, "laptop", now).is_ok());
+        assert!(validate_link_redemption(&link, "phone", now).is_err());
+        assert!(validate_link_redemption(&link, "laptop", link.expires_at).is_err());
+
+        link.redeemed_at = Some(now);
+        assert!(validate_link_redemption(&link, "laptop", now).is_err());
+    }
+
+    #[test]
+    fn test_validate_leave_admin() {
+        let admin = mock_user(1000, true);
+        assert!(matches!(
+            validate_leave(&admin),
+            Err(RuleError::AdminCannotLeave)
+        ));
+        assert!(validate_leave(&mock_user(1000, false)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_reveal_requires_closed_market() {
+        let mut market = mock_market();
+        assert!(matches!(
+            validate_reveal(&market),
+            Err(RuleError::RevealNotAvailable)
+        ));
+
+        market.status = MarketStatus::Closed;
+        assert!(validate_reveal(&market).is_ok());
+    }
+
     #[test]
     fn test_validate_wager_market_not_open() {
         let mut market = mock_market();
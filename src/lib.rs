@@ -6,6 +6,7 @@ pub mod api;
 pub mod cli;
 pub mod db;
 pub mod domain;
+pub mod metrics;
 pub mod service;
 
 // Re-export commonly used types
@@ -2,13 +2,20 @@ mod api;
 mod cli;
 mod db;
 mod domain;
+mod metrics;
 mod service;
 
+use api::limits::RateLimitConfig;
 use clap::{Parser, Subcommand};
 use cli::Repl;
-use db::SqliteDatabase;
+use db::{InMemoryDatabase, SqliteDatabase};
+use domain::archive::MarketArchive;
+use domain::csv::CsvReport;
+use metrics::Metrics;
 use service::CazinoService;
+use std::path::PathBuf;
 use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Parser)]
 #[command(name = "cazino")]
@@ -21,7 +28,11 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Run interactive CLI for local testing
-    Cli,
+    Cli {
+        /// Keep everything in memory instead of cazino.db
+        #[arg(long)]
+        ephemeral: bool,
+    },
 
     /// Run HTTP + WebSocket API server
     Serve {
@@ -37,9 +48,131 @@ enum Commands {
             default_value = "sqlite://cazino.db?mode=rwc"
         )]
         database: String,
+
+        /// Accept webhooks on localhost and private networks (for local
+        /// development only)
+        #[arg(long, env = "CAZINO_ALLOW_PRIVATE_WEBHOOKS")]
+        allow_private_webhooks: bool,
+    },
+
+    /// Show or apply database migrations
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+
+        /// Database URL
+        #[arg(
+            short,
+            long,
+            env = "CAZINO_DATABASE_URL",
+            default_value = "sqlite://cazino.db?mode=rwc"
+        )]
+        database: String,
+    },
+
+    /// Export a market as a JSON archive
+    Export {
+        /// Market ID or invite code
+        market: String,
+
+        /// Write the archive to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Database URL
+        #[arg(
+            short,
+            long,
+            env = "CAZINO_DATABASE_URL",
+            default_value = "sqlite://cazino.db?mode=rwc"
+        )]
+        database: String,
+    },
+
+    /// Export a market report as CSV for spreadsheets
+    Csv {
+        /// Market ID or invite code
+        market: String,
+
+        /// Report to export: wagers, results or leaderboard
+        report: CsvReport,
+
+        /// Write the report to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Database URL
+        #[arg(
+            short,
+            long,
+            env = "CAZINO_DATABASE_URL",
+            default_value = "sqlite://cazino.db?mode=rwc"
+        )]
+        database: String,
+    },
+
+    /// Save a market's settings and bet ideas as a template, as its admin
+    Template {
+        /// Market ID or invite code
+        market: String,
+
+        /// Template name (defaults to the market's)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Database URL
+        #[arg(
+            short,
+            long,
+            env = "CAZINO_DATABASE_URL",
+            default_value = "sqlite://cazino.db?mode=rwc"
+        )]
+        database: String,
+    },
+
+    /// Clone a market, as its admin: same players, bets recreated as pending
+    Clone {
+        /// Market ID or invite code
+        market: String,
+
+        /// Name for the new market
+        name: String,
+
+        /// Database URL
+        #[arg(
+            short,
+            long,
+            env = "CAZINO_DATABASE_URL",
+            default_value = "sqlite://cazino.db?mode=rwc"
+        )]
+        database: String,
+    },
+
+    /// Import a market from a JSON archive
+    Import {
+        /// Archive file produced by `export`
+        file: PathBuf,
+
+        /// Database URL
+        #[arg(
+            short,
+            long,
+            env = "CAZINO_DATABASE_URL",
+            default_value = "sqlite://cazino.db?mode=rwc"
+        )]
+        database: String,
     },
 }
 
+#[derive(Subcommand)]
+enum MigrateAction {
+    /// List migrations and whether each has been applied
+    Status,
+
+    /// Apply pending migrations
+    Up,
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load environment variables from .env when available for local dev convenience
@@ -56,16 +189,50 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Cli => run_cli().await?,
-        Commands::Serve { port, database } => run_server(port, database).await?,
+        Commands::Cli { ephemeral } => run_cli(ephemeral).await?,
+        Commands::Serve {
+            port,
+            database,
+            allow_private_webhooks,
+        } => run_server(port, database, allow_private_webhooks).await?,
+        Commands::Migrate { action, database } => run_migrate(action, database).await?,
+        Commands::Export {
+            market,
+            output,
+            database,
+        } => run_export(market, output, database).await?,
+        Commands::Csv {
+            market,
+            report,
+            output,
+            database,
+        } => run_csv(market, report, output, database).await?,
+        Commands::Template {
+            market,
+            name,
+            database,
+        } => run_template(market, name, database).await?,
+        Commands::Clone {
+            market,
+            name,
+            database,
+        } => run_clone(market, name, database).await?,
+        Commands::Import { file, database } => run_import(file, database).await?,
     }
 
     Ok(())
 }
 
-async fn run_cli() -> Result<(), Box<dyn std::error::Error>> {
+async fn run_cli(ephemeral: bool) -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Starting Cazino CLI");
 
+    if ephemeral {
+        // Nothing is written to disk; everything is gone on exit
+        let service = CazinoService::new(Arc::new(InMemoryDatabase::new()));
+        Repl::new(service).run().await;
+        return Ok(());
+    }
+
     // Connect to SQLite database
     let db = SqliteDatabase::new("sqlite://cazino.db?mode=rwc").await?;
     db.run_migrations().await?;
@@ -80,7 +247,11 @@ async fn run_cli() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn run_server(port: u16, database: String) -> Result<(), Box<dyn std::error::Error>> {
+async fn run_server(
+    port: u16,
+    database: String,
+    allow_private_webhooks: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     println!("🔧 Initializing Cazino API server...");
     println!("   Port: {}", port);
     println!("   Database: {}", database);
@@ -88,18 +259,277 @@ async fn run_server(port: u16, database: String) -> Result<(), Box<dyn std::erro
     tracing::info!("Starting Cazino API server on port {}", port);
     tracing::info!("Database: {}", database);
 
-    // Connect to database
+    // Rate limits come from CAZINO_RATE_LIMIT_* and friends (see api::limits)
+    let limits = RateLimitConfig::from_vars(|name| std::env::var(name).ok())?;
+
+    // Connect to database (the URL scheme picks the backend)
     println!("📦 Connecting to database...");
+    if database.starts_with("postgres://") || database.starts_with("postgresql://") {
+        return run_postgres_server(port, &database, limits, allow_private_webhooks).await;
+    }
     let db = SqliteDatabase::new(&database).await?;
     println!("🔨 Running migrations...");
     db.run_migrations().await?;
     println!("✅ Database ready!");
 
-    // Create service
-    let service = CazinoService::new(Arc::new(db));
+    // Create service (with queries timed for /metrics)
+    let service = metered_service(db, allow_private_webhooks);
 
     // Start server
-    api::run_server(service, port).await?;
+    api::run_server(service, port, limits).await?;
+
+    Ok(())
+}
+
+/// A service whose database calls are timed into its metrics
+fn metered_service<D: db::Database>(
+    db: D,
+    allow_private_webhooks: bool,
+) -> CazinoService<db::MeteredDatabase<D>> {
+    let metrics = Arc::new(Metrics::default());
+    let db = db::MeteredDatabase::new(db, metrics.clone());
+    let service = CazinoService::with_metrics(Arc::new(db), metrics);
+    if allow_private_webhooks {
+        tracing::warn!("🪝 Webhooks may point at localhost and private networks");
+        service.allow_private_webhooks()
+    } else {
+        service
+    }
+}
+
+#[cfg(feature = "postgres")]
+async fn run_postgres_server(
+    port: u16,
+    database: &str,
+    limits: RateLimitConfig,
+    allow_private_webhooks: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = db::PostgresDatabase::new(database).await?;
+    println!("🔨 Running migrations...");
+    db.run_migrations().await?;
+    println!("✅ Database ready!");
+
+    let service = metered_service(db, allow_private_webhooks);
+    api::run_server(service, port, limits).await?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn run_postgres_server(
+    _port: u16,
+    _database: &str,
+    _limits: RateLimitConfig,
+    _allow_private_webhooks: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("This build doesn't include PostgreSQL support; rebuild with `--features postgres`".into())
+}
+
+async fn run_migrate(
+    action: MigrateAction,
+    database: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if database.starts_with("postgres://") || database.starts_with("postgresql://") {
+        return run_postgres_migrate(action, &database).await;
+    }
+
+    let db = SqliteDatabase::new(&database).await?;
+    match action {
+        MigrateAction::Status => print_migration_status(db.migration_status().await?),
+        MigrateAction::Up => print_applied_migrations(db.run_migrations().await?),
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "postgres")]
+async fn run_postgres_migrate(
+    action: MigrateAction,
+    database: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = db::PostgresDatabase::new(database).await?;
+    match action {
+        MigrateAction::Status => print_migration_status(db.migration_status().await?),
+        MigrateAction::Up => print_applied_migrations(db.run_migrations().await?),
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn run_postgres_migrate(
+    _action: MigrateAction,
+    _database: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("This build doesn't include PostgreSQL support; rebuild with `--features postgres`".into())
+}
+
+fn print_migration_status(status: Vec<db::migrations::MigrationStatus>) {
+    for migration in status {
+        match migration.applied_at {
+            Some(at) => println!(
+                "✅ {:03} {} (applied {})",
+                migration.version, migration.description, at
+            ),
+            None => println!(
+                "⏳ {:03} {} (pending)",
+                migration.version, migration.description
+            ),
+        }
+    }
+}
+
+fn print_applied_migrations(applied: Vec<&db::migrations::Migration>) {
+    if applied.is_empty() {
+        println!("✅ Database is up to date");
+    }
+    for migration in applied {
+        println!(
+            "✅ Applied {:03} {}",
+            migration.version, migration.description
+        );
+    }
+}
+
+async fn run_export(
+    market: String,
+    output: Option<PathBuf>,
+    database: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = SqliteDatabase::new(&database).await?;
+    db.run_migrations().await?;
+    let service = CazinoService::new(Arc::new(db));
+
+    // Accept either a market ID or an invite code
+    let market_id = match Uuid::parse_str(&market) {
+        Ok(id) => id,
+        Err(_) => service.get_market_by_invite_code(&market).await?.id,
+    };
+
+    let archive = service.export_market(market_id).await?;
+    let json = serde_json::to_string_pretty(&archive)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, json)?;
+            eprintln!(
+                "✅ Exported {} ({} users, {} bets) to {}",
+                archive.market.name,
+                archive.users.len(),
+                archive.bets.len(),
+                path.display()
+            );
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
+
+async fn run_csv(
+    market: String,
+    report: CsvReport,
+    output: Option<PathBuf>,
+    database: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let db = SqliteDatabase::new(&database).await?;
+    db.run_migrations().await?;
+    let service = CazinoService::new(Arc::new(db));
+
+    let market_id = match Uuid::parse_str(&market) {
+        Ok(id) => id,
+        Err(_) => service.get_market_by_invite_code(&market).await?.id,
+    };
+
+    let mut export = service.export_csv(market_id, report).await?;
+    let mut out: Box<dyn Write> = match &output {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    // Write as we go rather than building the whole report first
+    while let Some(chunk) = export.next_chunk().await? {
+        out.write_all(chunk.as_bytes())?;
+    }
+    out.flush()?;
+
+    if let Some(path) = output {
+        eprintln!("✅ Exported {:?} to {}", report, path.display());
+    }
+
+    Ok(())
+}
+
+async fn run_template(
+    market: String,
+    name: Option<String>,
+    database: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = SqliteDatabase::new(&database).await?;
+    db.run_migrations().await?;
+    let service = CazinoService::new(Arc::new(db));
+
+    let market = match Uuid::parse_str(&market) {
+        Ok(id) => service.get_market(id).await?,
+        Err(_) => service.get_market_by_invite_code(&market).await?,
+    };
+
+    let template = service
+        .save_template(market.id, market.created_by, name)
+        .await?;
+    println!(
+        "✅ Saved template {} ({} bets) as {}",
+        template.name,
+        template.bets.len(),
+        template.id
+    );
+
+    Ok(())
+}
+
+async fn run_clone(
+    market: String,
+    name: String,
+    database: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = SqliteDatabase::new(&database).await?;
+    db.run_migrations().await?;
+    let service = CazinoService::new(Arc::new(db));
+
+    let market = match Uuid::parse_str(&market) {
+        Ok(id) => service.get_market(id).await?,
+        Err(_) => service.get_market_by_invite_code(&market).await?,
+    };
+
+    let (clone, _, bets) = service
+        .clone_market(market.id, market.created_by, name)
+        .await?;
+    println!(
+        "✅ Cloned {} into {} ({} pending bets, invite code {})",
+        market.name,
+        clone.name,
+        bets.len(),
+        clone.invite_code
+    );
+
+    Ok(())
+}
+
+async fn run_import(file: PathBuf, database: String) -> Result<(), Box<dyn std::error::Error>> {
+    let json = std::fs::read_to_string(&file)?;
+    let archive: MarketArchive = serde_json::from_str(&json)?;
+
+    let db = SqliteDatabase::new(&database).await?;
+    db.run_migrations().await?;
+    let service = CazinoService::new(Arc::new(db));
+
+    let market = service.import_market(archive).await?;
+    println!(
+        "✅ Imported {} (invite code {})",
+        market.name, market.invite_code
+    );
 
     Ok(())
 }
@@ -0,0 +1,402 @@
+/// Prometheus metrics
+///
+/// A small registry of counters, gauges and histograms, rendered in the
+/// Prometheus text format by the server's `/metrics` endpoint. The service
+/// owns one (see `CazinoService::metrics`) and counts wagers into it; the
+/// server adds requests, WebSocket connections and broadcasts, and
+/// `MeteredDatabase` times queries. Everything is plain atomics and mutexes,
+/// so the service can count on the worker too, where nothing is exported.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// Upper bounds, in seconds, of the latency histogram buckets
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Everything the server exports
+pub struct Metrics {
+    /// By method, route template and status
+    pub http_requests: Family<Counter>,
+    /// By method and route template
+    pub http_request_duration: Family<Histogram>,
+    /// By market
+    pub websocket_connections: Family<Gauge>,
+    pub broadcast_events: Counter,
+    /// Events waiting in the broadcast channel for its slowest subscriber
+    pub broadcast_queue_length: Gauge,
+    /// Events slow subscribers missed, by transport
+    pub broadcast_dropped_events: Family<Counter>,
+    pub wagers_placed: Counter,
+    pub coins_wagered: Counter,
+    /// By `Database` method
+    pub db_query_duration: Family<Histogram>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            http_requests: Family::new(&["method", "route", "status"]),
+            http_request_duration: Family::new(&["method", "route"]),
+            websocket_connections: Family::new(&["market_id"]),
+            broadcast_events: Counter::default(),
+            broadcast_queue_length: Gauge::default(),
+            broadcast_dropped_events: Family::new(&["transport"]),
+            wagers_placed: Counter::default(),
+            coins_wagered: Counter::default(),
+            db_query_duration: Family::new(&["operation"]),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn record_wager(&self, amount: i64) {
+        self.wagers_placed.inc();
+        self.coins_wagered.add(amount.max(0) as u64);
+    }
+
+    /// Everything, in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.http_requests.render(
+            &mut out,
+            "cazino_http_requests_total",
+            "HTTP requests served, by route and status",
+        );
+        self.http_request_duration.render(
+            &mut out,
+            "cazino_http_request_duration_seconds",
+            "Time taken to answer HTTP requests, by route",
+        );
+        self.websocket_connections.render(
+            &mut out,
+            "cazino_websocket_connections",
+            "Open WebSocket connections, by market",
+        );
+        self.broadcast_events.render_single(
+            &mut out,
+            "cazino_broadcast_events_total",
+            "Market events broadcast to subscribers",
+        );
+        self.broadcast_queue_length.render_single(
+            &mut out,
+            "cazino_broadcast_queue_length",
+            "Events in the broadcast channel not yet received by every subscriber",
+        );
+        self.broadcast_dropped_events.render(
+            &mut out,
+            "cazino_broadcast_dropped_events_total",
+            "Events subscribers fell too far behind to receive, by transport",
+        );
+        self.wagers_placed.render_single(
+            &mut out,
+            "cazino_wagers_placed_total",
+            "Wagers placed, including bets' opening wagers",
+        );
+        self.coins_wagered.render_single(
+            &mut out,
+            "cazino_coins_wagered_total",
+            "Coins staked on wagers",
+        );
+        self.db_query_duration.render(
+            &mut out,
+            "cazino_db_query_duration_seconds",
+            "Time taken by database operations, by operation",
+        );
+        out
+    }
+}
+
+/// A metric kind, as rendered
+pub trait Metric: Default {
+    const TYPE: &'static str;
+
+    /// Write the sample lines; `labels` is the rendered label set, or empty
+    fn samples(&self, out: &mut String, name: &str, labels: &str);
+
+    fn render_single(&self, out: &mut String, name: &str, help: &str) {
+        header(out, name, help, Self::TYPE);
+        self.samples(out, name, "");
+    }
+}
+
+#[derive(Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for Counter {
+    const TYPE: &'static str = "counter";
+
+    fn samples(&self, out: &mut String, name: &str, labels: &str) {
+        let _ = writeln!(out, "{}{} {}", name, braced(labels), self.get());
+    }
+}
+
+#[derive(Default)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn set(&self, value: i64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for Gauge {
+    const TYPE: &'static str = "gauge";
+
+    fn samples(&self, out: &mut String, name: &str, labels: &str) {
+        let _ = writeln!(out, "{}{} {}", name, braced(labels), self.get());
+    }
+}
+
+/// Observations counted into `LATENCY_BUCKETS`
+pub struct Histogram(Mutex<HistogramState>);
+
+struct HistogramState {
+    buckets: Vec<u64>, // Not cumulative; one per bound
+    sum: f64,
+    count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self(Mutex::new(HistogramState {
+            buckets: vec![0; LATENCY_BUCKETS.len()],
+            sum: 0.0,
+            count: 0,
+        }))
+    }
+}
+
+impl Histogram {
+    pub fn observe(&self, value: f64) {
+        let mut state = lock(&self.0);
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|bound| value <= *bound) {
+            state.buckets[i] += 1;
+        }
+        state.sum += value;
+        state.count += 1;
+    }
+}
+
+impl Metric for Histogram {
+    const TYPE: &'static str = "histogram";
+
+    fn samples(&self, out: &mut String, name: &str, labels: &str) {
+        let state = lock(&self.0);
+        let separator = if labels.is_empty() { "" } else { "," };
+
+        let mut cumulative = 0;
+        for (bound, n) in LATENCY_BUCKETS.iter().zip(&state.buckets) {
+            cumulative += n;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, separator, state.count
+        );
+        let _ = writeln!(out, "{}_sum{} {}", name, braced(labels), state.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, braced(labels), state.count);
+    }
+}
+
+/// One metric split into series by label values
+pub struct Family<M: Metric> {
+    labels: &'static [&'static str],
+    series: Mutex<BTreeMap<Vec<String>, M>>,
+}
+
+impl<M: Metric> Family<M> {
+    pub fn new(labels: &'static [&'static str]) -> Self {
+        Self {
+            labels,
+            series: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Run `f` on the series with these label values, creating it if needed
+    pub fn with<R>(&self, values: &[&str], f: impl FnOnce(&M) -> R) -> R {
+        debug_assert_eq!(values.len(), self.labels.len());
+        let mut series = lock(&self.series);
+        let key: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        f(series.entry(key).or_default())
+    }
+
+    pub fn render(&self, out: &mut String, name: &str, help: &str) {
+        header(out, name, help, M::TYPE);
+        for (values, metric) in lock(&self.series).iter() {
+            let labels: Vec<String> = self
+                .labels
+                .iter()
+                .zip(values)
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+                .collect();
+            metric.samples(out, name, &labels.join(","));
+        }
+    }
+}
+
+impl Family<Counter> {
+    pub fn inc(&self, values: &[&str]) {
+        self.with(values, Counter::inc);
+    }
+
+    pub fn add(&self, values: &[&str], n: u64) {
+        self.with(values, |counter| counter.add(n));
+    }
+}
+
+impl Family<Histogram> {
+    pub fn observe(&self, values: &[&str], value: f64) {
+        self.with(values, |histogram| histogram.observe(value));
+    }
+}
+
+impl Family<Gauge> {
+    pub fn inc(&self, values: &[&str]) {
+        self.with(values, Gauge::inc);
+    }
+
+    /// Decrement, dropping the series once it's back to zero (so markets
+    /// nobody is watching any more don't linger in every scrape)
+    pub fn dec(&self, values: &[&str]) {
+        let mut series = lock(&self.series);
+        let key: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        let gauge = series.entry(key.clone()).or_default();
+        gauge.dec();
+        if gauge.get() == 0 {
+            series.remove(&key);
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn braced(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels)
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // Every update is a single field write or insert, so a poisoned lock
+    // never guards a half-done change
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_and_gauges_render_by_label() {
+        let metrics = Metrics::default();
+        metrics
+            .http_requests
+            .inc(&["GET", "/api/markets/{market_id}", "200"]);
+        metrics
+            .http_requests
+            .inc(&["GET", "/api/markets/{market_id}", "200"]);
+        metrics.http_requests.inc(&["POST", "/api/markets", "400"]);
+        metrics.record_wager(25);
+        metrics.record_wager(10);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE cazino_http_requests_total counter\n"));
+        assert!(text.contains(
+            "cazino_http_requests_total{method=\"GET\",route=\"/api/markets/{market_id}\",status=\"200\"} 2\n"
+        ));
+        assert!(text.contains(
+            "cazino_http_requests_total{method=\"POST\",route=\"/api/markets\",status=\"400\"} 1\n"
+        ));
+        assert!(text.contains("cazino_wagers_placed_total 2\n"));
+        assert!(text.contains("cazino_coins_wagered_total 35\n"));
+    }
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let metrics = Metrics::default();
+        metrics.db_query_duration.observe(&["get_user"], 0.0004);
+        metrics.db_query_duration.observe(&["get_user"], 0.003);
+        metrics.db_query_duration.observe(&["get_user"], 60.0);
+
+        let text = metrics.render();
+        let line = |suffix: &str| {
+            text.lines()
+                .find(|l| l.starts_with(&format!("cazino_db_query_duration_seconds{}", suffix)))
+                .unwrap_or_else(|| panic!("no {} line", suffix))
+                .rsplit(' ')
+                .next()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(line("_bucket{operation=\"get_user\",le=\"0.0005\"}"), "1");
+        assert_eq!(line("_bucket{operation=\"get_user\",le=\"0.005\"}"), "2");
+        assert_eq!(line("_bucket{operation=\"get_user\",le=\"10\"}"), "2");
+        assert_eq!(line("_bucket{operation=\"get_user\",le=\"+Inf\"}"), "3");
+        assert_eq!(line("_count{operation=\"get_user\"}"), "3");
+    }
+
+    #[test]
+    fn test_gauge_series_go_away_at_zero() {
+        let connections = Family::<Gauge>::new(&["market_id"]);
+        connections.inc(&["a"]);
+        connections.inc(&["a"]);
+        connections.inc(&["b\"c"]);
+        connections.dec(&["a"]);
+
+        let mut text = String::new();
+        connections.render(&mut text, "open", "Open");
+        assert!(text.contains("open{market_id=\"a\"} 1\n"));
+        assert!(text.contains("open{market_id=\"b\\\"c\"} 1\n"));
+
+        connections.dec(&["a"]);
+        let mut text = String::new();
+        connections.render(&mut text, "open", "Open");
+        assert!(!text.contains("market_id=\"a\""));
+    }
+}
@@ -1,12 +1,29 @@
 /// Service layer - orchestrates domain logic and database operations
 /// This is where transactions and complex business flows live
-use crate::db::{Database, DbResult};
-use crate::domain::models::{Bet, BetStatus, BetView, Market, MarketStatus, Side, User, Wager};
+use crate::db::query::{BetQuery, DeviceMarketQuery, UserQuery, WagerQuery};
+use crate::db::{Database, DbResult, Page};
+use crate::domain::archive::{self, MarketArchive};
+use crate::domain::csv::{self, CsvReport, UserResult};
+use crate::domain::models::{
+    AuditEntry, Bet, BetReveal, BetStatus, BetView, LinkCode, Market, MarketContents, MarketStatus,
+    MarketTemplate, MembershipStatus, Player, PlayerStats, Removal, RevealCeremony, RevealWinner,
+    Season, SeasonStanding, Side, Spectator, TemplateBet, User, Wager, Webhook, WebhookDelivery,
+};
 use crate::domain::{parimutuel, rules};
-use chrono::Utc;
+use crate::metrics::Metrics;
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
 use uuid::Uuid;
 
+/// How many winners are announced with each ceremony reveal
+const REVEAL_TOP_WINNERS: usize = 3;
+
+/// Audit actions for device linking
+const AUDIT_LINK_CODE_CREATED: &str = "link_code_created";
+const AUDIT_LINK_CODE_REDEEM_ATTEMPT: &str = "link_code_redeem_attempt";
+const AUDIT_LINK_CODE_REDEEM_FAILED: &str = "link_code_redeem_failed";
+const AUDIT_DEVICE_LINKED: &str = "device_linked";
+
 /// Parameters for creating a new market
 pub struct CreateMarketParams {
     pub name: String,
@@ -18,13 +35,58 @@ pub struct CreateMarketParams {
     pub custom_invite_code: Option<String>,
 }
 
+/// Outcome of a player leaving or being removed from a market
+pub struct MembershipChange {
+    pub user: User,
+    pub voided_bet_ids: Vec<Uuid>, // Bets about the player, called off and refunded
+}
+
+/// A CSV report being produced a bet or a user at a time
+///
+/// Call `next_chunk` until it returns `None`; the first chunk is the header.
+pub struct CsvExport<D: Database> {
+    db: Arc<D>,
+    report: CsvReport,
+    market: Market,
+    users: Vec<User>,
+    bets: Vec<Bet>,
+    next_row: Option<usize>, // None until the header has been sent
+}
+
 pub struct CazinoService<D: Database> {
     db: Arc<D>,
+    metrics: Arc<Metrics>,
+    allow_private_webhooks: bool,
 }
 
 impl<D: Database> CazinoService<D> {
     pub fn new(db: Arc<D>) -> Self {
-        Self { db }
+        Self::with_metrics(db, Arc::new(Metrics::default()))
+    }
+
+    /// A service counting into metrics shared with the server (and usually
+    /// a `MeteredDatabase`)
+    pub fn with_metrics(db: Arc<D>, metrics: Arc<Metrics>) -> Self {
+        Self {
+            db,
+            metrics,
+            allow_private_webhooks: false,
+        }
+    }
+
+    /// Accept webhooks on loopback and private hosts, for local development
+    /// and tests
+    pub fn allow_private_webhooks(mut self) -> Self {
+        self.allow_private_webhooks = true;
+        self
+    }
+
+    pub fn allows_private_webhooks(&self) -> bool {
+        self.allow_private_webhooks
+    }
+
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
     }
 
     /// Create a new market
@@ -65,6 +127,8 @@ impl<D: Database> CazinoService<D> {
             avatar: params.admin_avatar,
             balance: params.starting_balance,
             is_admin: true,
+            status: MembershipStatus::Active,
+            player_id: None,
             joined_at: now,
         };
 
@@ -85,7 +149,18 @@ impl<D: Database> CazinoService<D> {
         let market = self.db.get_market_by_invite_code(&invite_code).await?;
 
         // Check if user already exists (returning user)
-        if let Ok(existing_user) = self.db.get_user_by_device_id(market.id, &device_id).await {
+        if let Ok(mut existing_user) = self.db.get_user_by_device_id(market.id, &device_id).await {
+            // Banned devices stay out; kicked or departed players may come back
+            rules::validate_rejoin(&existing_user)
+                .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+            if !existing_user.is_active() {
+                self.db
+                    .update_user_status(existing_user.id, MembershipStatus::Active)
+                    .await?;
+                existing_user.status = MembershipStatus::Active;
+            }
+
             return Ok((market, existing_user));
         }
 
@@ -98,21 +173,59 @@ impl<D: Database> CazinoService<D> {
             avatar,
             balance: market.starting_balance,
             is_admin: false,
+            status: MembershipStatus::Active,
+            player_id: None,
             joined_at: Utc::now(),
         };
 
         let user = self.db.create_user(user).await?;
+        let user = self.carry_over_balance(&market, user).await?;
 
         Ok((market, user))
     }
 
+    /// Join a market as a read-only spectator (no balance, cannot bet)
+    pub async fn join_as_spectator(
+        &self,
+        invite_code: String,
+        display_name: String,
+    ) -> DbResult<(Market, Spectator)> {
+        let market = self.db.get_market_by_invite_code(&invite_code).await?;
+
+        let spectator = Spectator {
+            id: Uuid::new_v4(),
+            market_id: market.id,
+            display_name,
+            token: generate_spectator_token(),
+            joined_at: Utc::now(),
+        };
+
+        let spectator = self.db.create_spectator(spectator).await?;
+
+        Ok((market, spectator))
+    }
+
+    /// Look up a spectator by their token
+    pub async fn get_spectator(&self, token: &str) -> DbResult<Spectator> {
+        self.db.get_spectator_by_token(token).await
+    }
+
+    /// Get all bets in a spectator's market (hidden bets stay redacted)
+    pub async fn get_spectator_bets(&self, token: &str) -> DbResult<Vec<BetView>> {
+        let spectator = self.db.get_spectator_by_token(token).await?;
+        let bets = self.db.get_bets_in_market(spectator.market_id).await?;
+
+        Ok(bets.iter().map(|bet| bet.to_spectator_view()).collect())
+    }
+
     /// Create a new bet (goes to pending approval)
     #[allow(clippy::too_many_arguments)]
     pub async fn create_bet(
         &self,
         market_id: Uuid,
         creator_id: Uuid,
-        subject_user_id: Uuid,
+        subject_user_ids: Vec<Uuid>,
+        about_everyone: bool,
         description: String,
         initial_odds: String,
         opening_wager: i64,
@@ -121,8 +234,25 @@ impl<D: Database> CazinoService<D> {
         let market = self.db.get_market(market_id).await?;
         let creator = self.db.get_user(creator_id).await?;
 
+        // "Everyone" already covers every player, so named subjects are dropped
+        let mut subject_user_ids = if about_everyone {
+            Vec::new()
+        } else {
+            subject_user_ids
+        };
+        subject_user_ids.sort();
+        subject_user_ids.dedup();
+
+        let mut subjects = Vec::with_capacity(subject_user_ids.len());
+        for subject_id in &subject_user_ids {
+            let subject = self.db.get_user(*subject_id).await.map_err(|_| {
+                crate::db::DbError::Constraint(rules::RuleError::InvalidSubject.to_string())
+            })?;
+            subjects.push(subject);
+        }
+
         // Validate
-        rules::validate_bet_creation(&market, &creator, subject_user_id, opening_wager)
+        rules::validate_bet_creation(&market, &creator, &subjects, about_everyone, opening_wager)
             .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
 
         // Parse initial odds to determine starting pools
@@ -132,7 +262,8 @@ impl<D: Database> CazinoService<D> {
         let bet = Bet {
             id: Uuid::new_v4(),
             market_id,
-            subject_user_id,
+            subject_user_ids,
+            about_everyone,
             created_by: creator_id,
             description,
             initial_odds,
@@ -157,9 +288,11 @@ impl<D: Database> CazinoService<D> {
             yes_pool_after: yes_pool,
             no_pool_after: no_pool,
             probability_after: parimutuel::calculate_probability(yes_pool, no_pool),
+            refunded: false,
         };
 
         self.db.create_wager(opening_wager_record).await?;
+        self.metrics.record_wager(opening_wager);
 
         // Deduct opening wager from creator's balance
         self.db
@@ -215,20 +348,12 @@ impl<D: Database> CazinoService<D> {
             yes_pool_after,
             no_pool_after,
             probability_after,
+            refunded: false,
         };
 
-        let wager = self.db.create_wager(wager).await?;
-
-        // Update bet pools
-        self.db
-            .update_bet_pools(bet_id, yes_pool_after, no_pool_after)
-            .await?;
-
-        // Deduct from user balance
-        self.db
-            .update_user_balance(user_id, user.balance - amount)
-            .await?;
-
+        // Record it, update the bet pools and deduct from the user's balance
+        let wager = self.db.apply_wager(wager).await?;
+        self.metrics.record_wager(wager.amount);
         Ok(wager)
     }
 
@@ -260,7 +385,8 @@ impl<D: Database> CazinoService<D> {
 
         // Calculate payouts
         let wagers = self.db.get_wagers_for_bet(bet_id).await?;
-        let payouts = parimutuel::calculate_payouts(&bet, &wagers);
+        let users = self.db.get_users_in_market(bet.market_id).await?;
+        let payouts = parimutuel::calculate_payouts(&bet, &wagers, &forfeited(&users));
 
         // Update user balances
         for (user_id, payout) in &payouts {
@@ -278,6 +404,75 @@ impl<D: Database> CazinoService<D> {
         self.db.get_bets_for_user(market_id, viewing_user_id).await
     }
 
+    /// A page of a market's bets, as one user sees them
+    ///
+    /// Filtering by someone else as subject would tell the viewer who their
+    /// own hidden bets are about, so hidden bets are left out of those pages
+    /// (which can then come up short of the limit).
+    pub async fn list_bets(
+        &self,
+        market_id: Uuid,
+        viewing_user_id: Uuid,
+        query: &BetQuery,
+    ) -> DbResult<Page<BetView>> {
+        let mut page = self
+            .db
+            .list_bets_in_market(market_id, query)
+            .await?
+            .map(|bet| bet.to_view(viewing_user_id));
+        if query
+            .subject
+            .is_some_and(|subject| subject != viewing_user_id)
+        {
+            page.items.retain(|view| !view.is_hidden);
+        }
+        Ok(page)
+    }
+
+    /// A page of a bet's wagers, for a user in its market who can see the bet
+    pub async fn list_wagers(
+        &self,
+        bet_id: Uuid,
+        viewing_user_id: Uuid,
+        query: &WagerQuery,
+    ) -> DbResult<Page<Wager>> {
+        let bet = self.db.get_bet(bet_id).await?;
+        let viewer = self.db.get_user(viewing_user_id).await?;
+        if viewer.market_id != bet.market_id {
+            return Err(crate::db::DbError::NotFound(
+                "User not found in this market".to_string(),
+            ));
+        }
+        if bet.to_view(viewing_user_id).is_hidden {
+            return Err(crate::db::DbError::Constraint(
+                rules::RuleError::BetHidden.to_string(),
+            ));
+        }
+
+        self.db.list_wagers_for_bet(bet_id, query).await
+    }
+
+    /// Everything a client needs to draw a market, read together
+    ///
+    /// Returns the contents along with the viewer, who must belong to the market.
+    pub async fn get_market_contents(
+        &self,
+        market_id: Uuid,
+        viewer_id: Uuid,
+    ) -> DbResult<(MarketContents, User)> {
+        let contents = self.db.get_market_contents(market_id).await?;
+        let viewer = contents
+            .users
+            .iter()
+            .find(|u| u.id == viewer_id)
+            .cloned()
+            .ok_or_else(|| {
+                crate::db::DbError::NotFound("User not found in this market".to_string())
+            })?;
+
+        Ok((contents, viewer))
+    }
+
     /// Get pending bets for admin approval
     pub async fn get_pending_bets(&self, market_id: Uuid) -> DbResult<Vec<Bet>> {
         self.db.get_pending_bets(market_id).await
@@ -299,16 +494,316 @@ impl<D: Database> CazinoService<D> {
             .collect())
     }
 
-    /// Get all users in a market (for leaderboard)
+    /// Get all current players in a market (for leaderboard)
+    ///
+    /// Players who left, were kicked or were banned are not included.
     pub async fn get_users(&self, market_id: Uuid) -> DbResult<Vec<User>> {
-        self.db.get_users_in_market(market_id).await
+        let users = self.db.get_users_in_market(market_id).await?;
+        Ok(users.into_iter().filter(|u| u.is_active()).collect())
+    }
+
+    /// A page of a market's users, including those who left or were removed
+    /// unless the query filters by status
+    pub async fn list_users(&self, market_id: Uuid, query: &UserQuery) -> DbResult<Page<User>> {
+        self.db.get_market(market_id).await?;
+        self.db.list_users_in_market(market_id, query).await
+    }
+
+    /// Change a player's display name and/or avatar
+    ///
+    /// Fields left as `None` keep their current value.
+    pub async fn update_profile(
+        &self,
+        user_id: Uuid,
+        display_name: Option<String>,
+        avatar: Option<String>,
+    ) -> DbResult<User> {
+        let mut user = self.db.get_user(user_id).await?;
+        let others = self.db.get_users_in_market(user.market_id).await?;
+
+        let display_name = display_name
+            .map(|name| name.trim().to_string())
+            .unwrap_or_else(|| user.display_name.clone());
+        let avatar = avatar
+            .map(|avatar| avatar.trim().to_string())
+            .unwrap_or_else(|| user.avatar.clone());
+
+        rules::validate_profile(&user, &others, &display_name, &avatar)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        self.db
+            .update_user_profile(user_id, &display_name, &avatar)
+            .await?;
+
+        user.display_name = display_name;
+        user.avatar = avatar;
+
+        Ok(user)
+    }
+
+    /// Leave a market (players only; the admin has to stay)
+    ///
+    /// Open wagers are refunded and bets about the player are voided.
+    pub async fn leave_market(&self, user_id: Uuid) -> DbResult<MembershipChange> {
+        let user = self.db.get_user(user_id).await?;
+
+        rules::validate_leave(&user).map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        self.remove_member(user, MembershipStatus::Left).await
+    }
+
+    /// Kick a player (admin only)
+    ///
+    /// Open wagers are refunded and bets about the player are voided. The
+    /// player may rejoin with the invite code.
+    pub async fn kick_user(
+        &self,
+        market_id: Uuid,
+        admin_id: Uuid,
+        user_id: Uuid,
+    ) -> DbResult<MembershipChange> {
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+        let user = self.db.get_user(user_id).await?;
+
+        rules::validate_removal(&market, &admin, &user, MembershipStatus::Kicked)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        self.remove_member(user, MembershipStatus::Kicked).await
+    }
+
+    /// Ban a player (admin only)
+    ///
+    /// Open wagers are forfeited: their stakes stay in the pools for the other
+    /// bettors to win. Bets about the player are voided. The ban is tied to the
+    /// player's device, so rejoining with the invite code fails.
+    pub async fn ban_user(
+        &self,
+        market_id: Uuid,
+        admin_id: Uuid,
+        user_id: Uuid,
+    ) -> DbResult<MembershipChange> {
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+        let user = self.db.get_user(user_id).await?;
+
+        rules::validate_removal(&market, &admin, &user, MembershipStatus::Banned)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        self.remove_member(user, MembershipStatus::Banned).await
+    }
+
+    async fn remove_member(
+        &self,
+        user: User,
+        status: MembershipStatus,
+    ) -> DbResult<MembershipChange> {
+        // Banned players forfeit their stakes: they stay in the pools, and
+        // payouts skip them (see `parimutuel::calculate_payouts`)
+        let mut forfeited = forfeited(&self.db.get_users_in_market(user.market_id).await?);
+        if status == MembershipStatus::Banned {
+            forfeited.push(user.id);
+        }
+
+        // Pull the player's stakes out of bets that are still open and give
+        // them back
+        let mut refunds: Vec<Wager> = Vec::new();
+        if !forfeited.contains(&user.id) {
+            for wager in self.db.get_wagers_for_user(user.id).await? {
+                if wager.refunded
+                    || self.db.get_bet(wager.bet_id).await?.status != BetStatus::Active
+                {
+                    continue;
+                }
+                refunds.push(wager);
+            }
+        }
+
+        // Bets about the player can't be settled any more, so everyone who
+        // hasn't forfeited gets their stake back
+        // ("everyone" bets are about the group, so they carry on)
+        let mut voided_bet_ids = Vec::new();
+        for bet in self.db.get_bets_about_user(user.id).await? {
+            if bet.about_everyone || !matches!(bet.status, BetStatus::Active | BetStatus::Pending) {
+                continue;
+            }
+
+            for wager in self.db.get_wagers_for_bet(bet.id).await? {
+                if wager.refunded
+                    || forfeited.contains(&wager.user_id)
+                    || refunds.iter().any(|w| w.id == wager.id)
+                {
+                    continue;
+                }
+                refunds.push(wager);
+            }
+            voided_bet_ids.push(bet.id);
+        }
+
+        // One write, so a failure can't leave balances and pools out of step
+        self.db
+            .apply_removal(&Removal {
+                user_id: user.id,
+                status,
+                refunds,
+                voided_bet_ids: voided_bet_ids.clone(),
+            })
+            .await?;
+
+        Ok(MembershipChange {
+            user: self.db.get_user(user.id).await?,
+            voided_bet_ids,
+        })
     }
 
-    /// Get bets about a specific user (for reveal screen)
+    /// Get bets about a specific user, regardless of market status
+    #[allow(dead_code)]
     pub async fn get_bets_about_user(&self, user_id: Uuid) -> DbResult<Vec<Bet>> {
         self.db.get_bets_about_user(user_id).await
     }
 
+    /// Get bets about a specific user for the reveal screen
+    ///
+    /// Only available once the market is closed, so hidden bets aren't spoiled.
+    pub async fn get_reveal(&self, user_id: Uuid) -> DbResult<Vec<Bet>> {
+        let user = self.db.get_user(user_id).await?;
+        let market = self.db.get_market(user.market_id).await?;
+
+        rules::validate_reveal(&market)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        self.db.get_bets_about_user(user_id).await
+    }
+
+    /// Start the reveal ceremony (admin only)
+    ///
+    /// Queues every hidden bet in creation order. Starting again returns the
+    /// ceremony already in progress, so an admin can safely retry.
+    pub async fn start_reveal_ceremony(
+        &self,
+        market_id: Uuid,
+        admin_id: Uuid,
+    ) -> DbResult<RevealCeremony> {
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+
+        rules::validate_reveal_ceremony(&market, &admin)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        if let Ok(ceremony) = self.db.get_reveal_ceremony(market_id).await {
+            return Ok(ceremony);
+        }
+
+        let mut bets: Vec<Bet> = self
+            .db
+            .get_bets_in_market(market_id)
+            .await?
+            .into_iter()
+            .filter(|bet| {
+                bet.hide_from_subject && !matches!(bet.status, BetStatus::Pending | BetStatus::Void)
+            })
+            .collect();
+        bets.sort_by_key(|bet| bet.created_at);
+
+        let started_at = Utc::now();
+        let ceremony = RevealCeremony {
+            market_id,
+            bet_ids: bets.iter().map(|bet| bet.id).collect(),
+            revealed_count: 0,
+            started_at,
+            completed_at: if bets.is_empty() {
+                Some(started_at)
+            } else {
+                None
+            },
+        };
+
+        self.db.create_reveal_ceremony(ceremony).await
+    }
+
+    /// Reveal the next bet in the ceremony (admin only)
+    pub async fn reveal_next_bet(
+        &self,
+        market_id: Uuid,
+        admin_id: Uuid,
+    ) -> DbResult<(RevealCeremony, BetReveal)> {
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+
+        rules::validate_reveal_ceremony(&market, &admin)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        let mut ceremony = self.db.get_reveal_ceremony(market_id).await?;
+        let bet_id = ceremony.next_bet_id().ok_or_else(|| {
+            crate::db::DbError::Constraint("Reveal ceremony is already complete".to_string())
+        })?;
+
+        ceremony.revealed_count += 1;
+        if ceremony.next_bet_id().is_none() {
+            ceremony.completed_at = Some(Utc::now());
+        }
+
+        self.db
+            .update_reveal_progress(market_id, ceremony.revealed_count, ceremony.completed_at)
+            .await?;
+
+        let reveal = self.build_bet_reveal(bet_id).await?;
+
+        Ok((ceremony, reveal))
+    }
+
+    /// Get the ceremony and everything revealed so far (for reconnecting clients)
+    pub async fn get_reveal_ceremony(
+        &self,
+        market_id: Uuid,
+    ) -> DbResult<(RevealCeremony, Vec<BetReveal>)> {
+        let ceremony = self.db.get_reveal_ceremony(market_id).await?;
+
+        let mut reveals = Vec::with_capacity(ceremony.revealed_count);
+        for bet_id in ceremony.revealed_bet_ids() {
+            reveals.push(self.build_bet_reveal(*bet_id).await?);
+        }
+
+        Ok((ceremony, reveals))
+    }
+
+    /// Assemble a bet's reveal: full details, outcome, and its biggest winners
+    async fn build_bet_reveal(&self, bet_id: Uuid) -> DbResult<BetReveal> {
+        let bet = self.db.get_bet(bet_id).await?;
+        let wagers = self.db.get_wagers_for_bet(bet_id).await?;
+        let users = self.db.get_users_in_market(bet.market_id).await?;
+
+        let outcome = match bet.status {
+            BetStatus::ResolvedYes => Some(Side::Yes),
+            BetStatus::ResolvedNo => Some(Side::No),
+            _ => None,
+        };
+
+        let mut payouts = parimutuel::calculate_payouts(&bet, &wagers, &forfeited(&users));
+        payouts.sort_by_key(|(_, payout)| std::cmp::Reverse(*payout));
+
+        let top_winners = payouts
+            .into_iter()
+            .filter_map(|(user_id, payout)| {
+                let user = users.iter().find(|u| u.id == user_id)?;
+                Some(RevealWinner {
+                    user_id,
+                    display_name: user.display_name.clone(),
+                    avatar: user.avatar.clone(),
+                    payout,
+                })
+            })
+            .take(REVEAL_TOP_WINNERS)
+            .collect();
+
+        Ok(BetReveal {
+            // For reveal, show full details (use nil UUID so nothing is hidden)
+            bet: bet.to_view(Uuid::nil()),
+            outcome,
+            top_winners,
+        })
+    }
+
     /// Open a market for betting
     pub async fn open_market(&self, market_id: Uuid, admin_id: Uuid) -> DbResult<()> {
         let admin = self.db.get_user(admin_id).await?;
@@ -365,6 +860,11 @@ impl<D: Database> CazinoService<D> {
         self.db.get_market(market_id).await
     }
 
+    /// Get a market by its invite code
+    pub async fn get_market_by_invite_code(&self, invite_code: &str) -> DbResult<Market> {
+        self.db.get_market_by_invite_code(invite_code).await
+    }
+
     /// Get a user by ID
     #[allow(dead_code)]
     pub async fn get_user(&self, user_id: Uuid) -> DbResult<User> {
@@ -380,6 +880,883 @@ impl<D: Database> CazinoService<D> {
     pub async fn get_markets_by_device_id(&self, device_id: &str) -> DbResult<Vec<(Market, User)>> {
         self.db.get_markets_by_device_id(device_id).await
     }
+
+    /// A page of every market a device has joined
+    pub async fn list_markets_by_device_id(
+        &self,
+        device_id: &str,
+        query: &DeviceMarketQuery,
+    ) -> DbResult<Page<(Market, User)>> {
+        self.db.list_markets_by_device_id(device_id, query).await
+    }
+
+    /// Link a market seat to a persistent player
+    ///
+    /// `device_id` is the caller's device, which must hold the seat and, for an
+    /// existing player, one of that player's seats. With no `player_id` a new
+    /// player is created from the seat's name and avatar.
+    pub async fn link_player(
+        &self,
+        user_id: Uuid,
+        device_id: &str,
+        player_id: Option<Uuid>,
+    ) -> DbResult<Player> {
+        let user = self.db.get_user(user_id).await?;
+
+        let player = match player_id {
+            Some(id) => {
+                let player = self.db.get_player(id).await?;
+                let seats = self.db.get_users_by_player(id).await?;
+                rules::validate_player_link(&user, device_id, Some((id, &seats)))
+                    .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+                player
+            }
+            None => {
+                rules::validate_player_link(&user, device_id, None)
+                    .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+                self.db
+                    .create_player(Player {
+                        id: Uuid::new_v4(),
+                        display_name: user.display_name.clone(),
+                        avatar: user.avatar.clone(),
+                        created_at: Utc::now(),
+                    })
+                    .await?
+            }
+        };
+
+        self.db.update_user_player(user_id, player.id).await?;
+
+        Ok(player)
+    }
+
+    /// Get a player with lifetime stats across every linked market
+    pub async fn get_player_profile(&self, player_id: Uuid) -> DbResult<(Player, PlayerStats)> {
+        let player = self.db.get_player(player_id).await?;
+        let seats = self.db.get_users_by_player(player_id).await?;
+
+        let mut stats = PlayerStats {
+            markets_played: seats.len(),
+            ..PlayerStats::default()
+        };
+
+        for seat in &seats {
+            let market = self.db.get_market(seat.market_id).await?;
+            let forfeited = forfeited(&self.db.get_users_in_market(seat.market_id).await?);
+            stats.net_profit += seat.balance - market.starting_balance;
+
+            // Stake per bet, so a player hedging both sides counts once
+            let mut stakes: Vec<(Uuid, i64)> = Vec::new();
+            for wager in self.db.get_wagers_for_user(seat.id).await? {
+                if wager.refunded {
+                    continue;
+                }
+                match stakes
+                    .iter_mut()
+                    .find(|(bet_id, _)| *bet_id == wager.bet_id)
+                {
+                    Some((_, staked)) => *staked += wager.amount,
+                    None => stakes.push((wager.bet_id, wager.amount)),
+                }
+            }
+
+            for (bet_id, staked) in stakes {
+                let bet = self.db.get_bet(bet_id).await?;
+                if !matches!(bet.status, BetStatus::ResolvedYes | BetStatus::ResolvedNo) {
+                    continue;
+                }
+
+                let wagers = self.db.get_wagers_for_bet(bet_id).await?;
+                let payout = parimutuel::calculate_payouts(&bet, &wagers, &forfeited)
+                    .into_iter()
+                    .find(|(user_id, _)| *user_id == seat.id)
+                    .map(|(_, payout)| payout)
+                    .unwrap_or(0);
+
+                stats.bets_settled += 1;
+                if payout > staked {
+                    stats.bets_won += 1;
+                }
+                stats.biggest_payout = stats.biggest_payout.max(payout);
+            }
+        }
+
+        if stats.bets_settled > 0 {
+            stats.win_rate = stats.bets_won as f64 / stats.bets_settled as f64;
+        }
+
+        Ok((player, stats))
+    }
+
+    /// Save a market's settings and bet ideas as a reusable template (admin only)
+    pub async fn save_template(
+        &self,
+        market_id: Uuid,
+        admin_id: Uuid,
+        name: Option<String>,
+    ) -> DbResult<MarketTemplate> {
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+
+        rules::validate_market_admin(&market, &admin)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        let mut template = self.template_from_market(&market).await?;
+        if let Some(name) = name {
+            template.name = name;
+        }
+
+        self.db.create_template(template).await
+    }
+
+    pub async fn get_template(&self, template_id: Uuid) -> DbResult<MarketTemplate> {
+        self.db.get_template(template_id).await
+    }
+
+    /// Create a new draft market from a template
+    ///
+    /// Only the admin is seated, so bets about named players are skipped;
+    /// bets about everyone come across as `Pending`.
+    pub async fn create_market_from_template(
+        &self,
+        template_id: Uuid,
+        name: Option<String>,
+        admin_device_id: String,
+        admin_name: String,
+        admin_avatar: String,
+    ) -> DbResult<(Market, User, Vec<Bet>)> {
+        let template = self.db.get_template(template_id).await?;
+
+        let params = CreateMarketParams {
+            name: name.unwrap_or_else(|| template.name.clone()),
+            admin_device_id,
+            admin_name,
+            admin_avatar,
+            starting_balance: template.starting_balance,
+            duration_hours: template.duration_hours,
+            custom_invite_code: None,
+        };
+
+        self.instantiate_template(&template, params, &[]).await
+    }
+
+    /// Clone a market (admin only): same settings, same players seated with fresh
+    /// balances, and bets recreated as `Pending` with subjects matched by display name
+    pub async fn clone_market(
+        &self,
+        market_id: Uuid,
+        admin_id: Uuid,
+        name: String,
+    ) -> DbResult<(Market, User, Vec<Bet>)> {
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+
+        rules::validate_market_admin(&market, &admin)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        let template = self.template_from_market(&market).await?;
+        let players: Vec<User> = self
+            .get_users(market_id)
+            .await?
+            .into_iter()
+            .filter(|u| u.id != admin.id)
+            .collect();
+
+        let params = CreateMarketParams {
+            name,
+            admin_device_id: admin.device_id.clone(),
+            admin_name: admin.display_name.clone(),
+            admin_avatar: admin.avatar.clone(),
+            starting_balance: template.starting_balance,
+            duration_hours: template.duration_hours,
+            custom_invite_code: None,
+        };
+
+        let (market, mut new_admin, bets) = self
+            .instantiate_template(&template, params, &players)
+            .await?;
+
+        if let Some(player_id) = admin.player_id {
+            self.db.update_user_player(new_admin.id, player_id).await?;
+            new_admin.player_id = Some(player_id);
+        }
+
+        Ok((market, new_admin, bets))
+    }
+
+    /// Capture a market's settings and bets (void bets are left out)
+    async fn template_from_market(&self, market: &Market) -> DbResult<MarketTemplate> {
+        let users = self.db.get_users_in_market(market.id).await?;
+        let name_of = |id: Uuid| {
+            users
+                .iter()
+                .find(|u| u.id == id)
+                .map(|u| u.display_name.clone())
+        };
+
+        let bets = self
+            .db
+            .get_bets_in_market(market.id)
+            .await?
+            .into_iter()
+            .filter(|bet| bet.status != BetStatus::Void)
+            .map(|bet| TemplateBet {
+                subject_names: bet
+                    .subject_user_ids
+                    .iter()
+                    .filter_map(|id| name_of(*id))
+                    .collect(),
+                creator_name: name_of(bet.created_by),
+                description: bet.description,
+                initial_odds: bet.initial_odds,
+                hide_from_subject: bet.hide_from_subject,
+                about_everyone: bet.about_everyone,
+            })
+            .collect();
+
+        Ok(MarketTemplate {
+            id: Uuid::new_v4(),
+            name: market.name.clone(),
+            starting_balance: market.starting_balance,
+            duration_hours: (market.closes_at - market.opens_at).num_hours(),
+            bets,
+            created_at: Utc::now(),
+        })
+    }
+
+    /// Create a market from a template, seat `players`, and recreate the bets
+    ///
+    /// Bets are `Pending` with empty pools so the admin can review them. Bets
+    /// about anyone not seated in the new market are skipped.
+    async fn instantiate_template(
+        &self,
+        template: &MarketTemplate,
+        params: CreateMarketParams,
+        players: &[User],
+    ) -> DbResult<(Market, User, Vec<Bet>)> {
+        let (market, admin) = self.create_market(params).await?;
+
+        let mut seats = vec![admin.clone()];
+        for player in players {
+            let seat = User {
+                id: Uuid::new_v4(),
+                market_id: market.id,
+                device_id: player.device_id.clone(),
+                display_name: player.display_name.clone(),
+                avatar: player.avatar.clone(),
+                balance: market.starting_balance,
+                is_admin: false,
+                status: MembershipStatus::Active,
+                player_id: player.player_id,
+                joined_at: Utc::now(),
+            };
+            seats.push(self.db.create_user(seat).await?);
+        }
+
+        let seat_named = |name: &str| {
+            seats
+                .iter()
+                .find(|seat| seat.display_name.to_lowercase() == name.to_lowercase())
+                .map(|seat| seat.id)
+        };
+
+        let mut bets = Vec::new();
+        for idea in &template.bets {
+            let Some(subject_user_ids) = idea
+                .subject_names
+                .iter()
+                .map(|name| seat_named(name))
+                .collect::<Option<Vec<Uuid>>>()
+            else {
+                continue;
+            };
+            if !idea.about_everyone && subject_user_ids.is_empty() {
+                continue;
+            }
+
+            let bet = Bet {
+                id: Uuid::new_v4(),
+                market_id: market.id,
+                subject_user_ids,
+                about_everyone: idea.about_everyone,
+                created_by: idea
+                    .creator_name
+                    .as_deref()
+                    .and_then(seat_named)
+                    .unwrap_or(admin.id),
+                description: idea.description.clone(),
+                initial_odds: idea.initial_odds.clone(),
+                status: BetStatus::Pending,
+                yes_pool: 0,
+                no_pool: 0,
+                hide_from_subject: idea.hide_from_subject,
+                created_at: Utc::now(),
+                resolved_at: None,
+            };
+            bets.push(self.db.create_bet(bet).await?);
+        }
+
+        Ok((market, admin, bets))
+    }
+
+    /// Export a market and everything in it as a portable archive
+    pub async fn export_market(&self, market_id: Uuid) -> DbResult<MarketArchive> {
+        let market = self.db.get_market(market_id).await?;
+        let users = self.db.get_users_in_market(market_id).await?;
+        let bets = self.db.get_bets_in_market(market_id).await?;
+
+        let mut wagers = Vec::new();
+        for bet in &bets {
+            wagers.extend(self.db.get_wagers_for_bet(bet.id).await?);
+        }
+
+        let reveal_ceremony = match self.db.get_reveal_ceremony(market_id).await {
+            Ok(ceremony) => Some(ceremony),
+            Err(crate::db::DbError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        let mut players: Vec<Player> = Vec::new();
+        for player_id in users.iter().filter_map(|u| u.player_id) {
+            if !players.iter().any(|p| p.id == player_id) {
+                players.push(self.db.get_player(player_id).await?);
+            }
+        }
+
+        let season = match self.db.get_season_for_market(market_id).await {
+            Ok(season) => Some(season),
+            Err(crate::db::DbError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        let (season, carry_overs) = match season {
+            Some(season) => {
+                let position = self
+                    .db
+                    .get_season_markets(season.id)
+                    .await?
+                    .iter()
+                    .position(|m| m.id == market_id)
+                    .unwrap_or_default();
+                let carry_overs = self
+                    .db
+                    .get_carry_overs(season.id)
+                    .await?
+                    .into_iter()
+                    .filter(|(user_id, _)| users.iter().any(|u| u.id == *user_id))
+                    .map(|(user_id, amount)| archive::CarryOver { user_id, amount })
+                    .collect();
+                (
+                    Some(archive::SeasonMembership { season, position }),
+                    carry_overs,
+                )
+            }
+            None => (None, Vec::new()),
+        };
+
+        // Spectator tokens are credentials, so they stay behind
+        let mut spectators = self.db.get_spectators_in_market(market_id).await?;
+        for spectator in &mut spectators {
+            spectator.token.clear();
+        }
+
+        Ok(MarketArchive {
+            version: archive::ARCHIVE_VERSION,
+            exported_at: Utc::now(),
+            spectators,
+            market,
+            users,
+            bets,
+            wagers,
+            reveal_ceremony,
+            players,
+            season,
+            carry_overs,
+        })
+    }
+
+    /// Export a market (admin only)
+    pub async fn export_market_as_admin(
+        &self,
+        market_id: Uuid,
+        admin_id: Uuid,
+    ) -> DbResult<MarketArchive> {
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+
+        rules::validate_market_admin(&market, &admin)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        self.export_market(market_id).await
+    }
+
+    /// Import a market archive, keeping all ids
+    ///
+    /// The archive is validated up front and rejected if the market or its
+    /// invite code already exists. Linked players and the season are created
+    /// if missing, and the rest is written in one transaction where the
+    /// backend supports it (see `Database::import_archive`). Spectators are
+    /// given new tokens.
+    pub async fn import_market(&self, mut archive: MarketArchive) -> DbResult<Market> {
+        archive::validate_archive(&archive)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+        for spectator in &mut archive.spectators {
+            spectator.token = generate_spectator_token();
+        }
+
+        if self.db.get_market(archive.market.id).await.is_ok() {
+            return Err(crate::db::DbError::Constraint(
+                "Market already exists".to_string(),
+            ));
+        }
+        if self
+            .db
+            .get_market_by_invite_code(&archive.market.invite_code)
+            .await
+            .is_ok()
+        {
+            return Err(crate::db::DbError::Constraint(
+                "Invite code already in use".to_string(),
+            ));
+        }
+
+        self.db.import_archive(&archive).await?;
+
+        Ok(archive.market)
+    }
+
+    /// Start a CSV export of a market
+    pub async fn export_csv(&self, market_id: Uuid, report: CsvReport) -> DbResult<CsvExport<D>> {
+        let market = self.db.get_market(market_id).await?;
+        let mut users = self.db.get_users_in_market(market_id).await?;
+        let bets = match report {
+            CsvReport::Leaderboard => Vec::new(),
+            _ => self.db.get_bets_in_market(market_id).await?,
+        };
+
+        if report == CsvReport::Leaderboard {
+            users.retain(|u| u.is_active());
+            users.sort_by_key(|u| std::cmp::Reverse(u.balance));
+        }
+
+        Ok(CsvExport {
+            db: self.db.clone(),
+            report,
+            market,
+            users,
+            bets,
+            next_row: None,
+        })
+    }
+
+    /// Start a CSV export of a market (admin only)
+    pub async fn export_csv_as_admin(
+        &self,
+        market_id: Uuid,
+        admin_id: Uuid,
+        report: CsvReport,
+    ) -> DbResult<CsvExport<D>> {
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+
+        rules::validate_market_admin(&market, &admin)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        self.export_csv(market_id, report).await
+    }
+
+    /// Start a new season (markets are added to it afterwards)
+    pub async fn create_season(&self, name: String, carry_over_balances: bool) -> DbResult<Season> {
+        let season = Season {
+            id: Uuid::new_v4(),
+            name,
+            carry_over_balances,
+            created_at: Utc::now(),
+        };
+
+        self.db.create_season(season).await
+    }
+
+    /// Get a season and its markets in order
+    pub async fn get_season(&self, season_id: Uuid) -> DbResult<(Season, Vec<Market>)> {
+        let season = self.db.get_season(season_id).await?;
+        let markets = self.db.get_season_markets(season_id).await?;
+        Ok((season, markets))
+    }
+
+    /// Add a market to the end of a season (market admin only)
+    ///
+    /// In carry-over seasons, players already seated in a draft market bring
+    /// their balance from the previous market.
+    pub async fn add_market_to_season(
+        &self,
+        season_id: Uuid,
+        market_id: Uuid,
+        admin_id: Uuid,
+    ) -> DbResult<(Season, Vec<Market>)> {
+        let season = self.db.get_season(season_id).await?;
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+
+        let current_season = match self.db.get_season_for_market(market_id).await {
+            Ok(season) => Some(season.id),
+            Err(crate::db::DbError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        rules::validate_season_market(&market, &admin, current_season)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        let position = self.db.get_season_markets(season_id).await?.len();
+        self.db
+            .add_season_market(season_id, market_id, position)
+            .await?;
+
+        if season.carry_over_balances && market.status == MarketStatus::Draft {
+            for user in self.db.get_users_in_market(market_id).await? {
+                self.carry_over_balance(&market, user).await?;
+            }
+        }
+
+        self.get_season(season_id).await
+    }
+
+    /// Season table: profit relative to each market's starting balance, summed per player
+    ///
+    /// Seats linked to the same player count together; unlinked seats are
+    /// matched by device. Seats count whatever their membership status, so a
+    /// player who left a market keeps its result. Carried-over balances aren't
+    /// counted twice.
+    pub async fn get_season_leaderboard(
+        &self,
+        season_id: Uuid,
+    ) -> DbResult<(Season, Vec<SeasonStanding>)> {
+        let season = self.db.get_season(season_id).await?;
+        let carry_overs = self.db.get_carry_overs(season_id).await?;
+
+        let mut standings: Vec<(String, SeasonStanding)> = Vec::new();
+        for market in self.db.get_season_markets(season_id).await? {
+            // Everyone who played, including those who left or were removed
+            for user in self.db.get_users_in_market(market.id).await? {
+                let carried = carry_overs
+                    .iter()
+                    .find(|(user_id, _)| *user_id == user.id)
+                    .map(|(_, amount)| *amount)
+                    .unwrap_or(0);
+                let profit = user.balance - market.starting_balance - carried;
+
+                let key = match user.player_id {
+                    Some(player_id) => player_id.to_string(),
+                    None => format!("device:{}", user.device_id),
+                };
+
+                match standings.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, standing)) => {
+                        // Markets are in season order, so the latest seat names the row
+                        standing.display_name = user.display_name;
+                        standing.avatar = user.avatar;
+                        standing.markets_played += 1;
+                        standing.profit += profit;
+                    }
+                    None => standings.push((
+                        key,
+                        SeasonStanding {
+                            player_id: user.player_id,
+                            display_name: user.display_name,
+                            avatar: user.avatar,
+                            markets_played: 1,
+                            profit,
+                            rank: 0,
+                        },
+                    )),
+                }
+            }
+        }
+
+        let mut standings: Vec<SeasonStanding> = standings.into_iter().map(|(_, s)| s).collect();
+        standings.sort_by_key(|s| std::cmp::Reverse(s.profit));
+        for (idx, standing) in standings.iter_mut().enumerate() {
+            standing.rank = idx + 1;
+        }
+
+        Ok((season, standings))
+    }
+
+    /// Start a new seat with the balance from the device's latest earlier market in
+    /// the season, if the market belongs to a carry-over season
+    async fn carry_over_balance(&self, market: &Market, mut user: User) -> DbResult<User> {
+        let season = match self.db.get_season_for_market(market.id).await {
+            Ok(season) if season.carry_over_balances => season,
+            Ok(_) | Err(crate::db::DbError::NotFound(_)) => return Ok(user),
+            Err(e) => return Err(e),
+        };
+
+        let markets = self.db.get_season_markets(season.id).await?;
+        let position = markets
+            .iter()
+            .position(|m| m.id == market.id)
+            .unwrap_or(markets.len());
+
+        for previous in markets[..position].iter().rev() {
+            if let Ok(seat) = self
+                .db
+                .get_user_by_device_id(previous.id, &user.device_id)
+                .await
+            {
+                self.db.update_user_balance(user.id, seat.balance).await?;
+                self.db
+                    .create_carry_over(market.id, user.id, seat.balance - market.starting_balance)
+                    .await?;
+                user.balance = seat.balance;
+                break;
+            }
+        }
+
+        Ok(user)
+    }
+
+    /// Generate a short-lived code for moving this device's seats to another device
+    pub async fn create_link_code(&self, device_id: &str) -> DbResult<LinkCode> {
+        let now = Utc::now();
+        let recent = self
+            .db
+            .count_audit_entries(
+                Some(device_id),
+                AUDIT_LINK_CODE_CREATED,
+                now - chrono::Duration::hours(1),
+            )
+            .await?;
+        rules::validate_rate_limit(recent, rules::MAX_LINK_CODES_PER_HOUR)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        let seats = self.db.get_markets_by_device_id(device_id).await?;
+        if seats.is_empty() {
+            return Err(crate::db::DbError::NotFound(
+                "No markets joined on this device".to_string(),
+            ));
+        }
+        // Banned seats stay on the banned device (see `redeem_link_code`)
+        if seats
+            .iter()
+            .all(|(_, user)| user.status == MembershipStatus::Banned)
+        {
+            return Err(crate::db::DbError::Constraint(
+                rules::RuleError::Banned.to_string(),
+            ));
+        }
+
+        let link = LinkCode {
+            code: generate_link_code(),
+            device_id: device_id.to_string(),
+            created_at: now,
+            expires_at: now + chrono::Duration::minutes(rules::LINK_CODE_TTL_MINUTES),
+            redeemed_at: None,
+            redeemed_by: None,
+        };
+        let link = self.db.create_link_code(link).await?;
+
+        self.audit(AUDIT_LINK_CODE_CREATED, device_id, String::new())
+            .await?;
+
+        Ok(link)
+    }
+
+    /// Redeem a link code on a new device, rebinding the old device's players to it
+    ///
+    /// Markets the new device has already joined keep their existing player,
+    /// and seats banned from their market stay behind.
+    /// Returns the new device's markets after linking.
+    pub async fn redeem_link_code(
+        &self,
+        code: &str,
+        device_id: &str,
+    ) -> DbResult<Vec<(Market, User)>> {
+        let now = Utc::now();
+        let code = code.trim().to_uppercase();
+        let hour_ago = now - chrono::Duration::hours(1);
+
+        // Every attempt counts against the device. Device IDs are the
+        // caller's to choose, so the transport's limiter also locks out an IP
+        // after a few wrong codes, and codes are long enough that guessing
+        // one within its lifetime is hopeless.
+        let recent = self
+            .db
+            .count_audit_entries(Some(device_id), AUDIT_LINK_CODE_REDEEM_ATTEMPT, hour_ago)
+            .await?;
+        rules::validate_rate_limit(recent, rules::MAX_LINK_REDEEMS_PER_HOUR)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+        self.audit(AUDIT_LINK_CODE_REDEEM_ATTEMPT, device_id, code.clone())
+            .await?;
+
+        let link = match self.claim_link_code(&code, device_id, now).await {
+            Ok(link) => link,
+            Err(e) => {
+                self.audit(AUDIT_LINK_CODE_REDEEM_FAILED, device_id, code)
+                    .await?;
+                return Err(e);
+            }
+        };
+
+        // Banned seats are never moved, so a ban can't be shed with a new device
+        let existing = self.db.get_markets_by_device_id(device_id).await?;
+        let mut moved = Vec::new();
+        for (market, user) in self.db.get_markets_by_device_id(&link.device_id).await? {
+            if existing.iter().any(|(m, _)| m.id == market.id)
+                || user.status == MembershipStatus::Banned
+            {
+                continue;
+            }
+            self.db.update_user_device(user.id, device_id).await?;
+            moved.push(user.id.to_string());
+        }
+
+        self.audit(
+            AUDIT_DEVICE_LINKED,
+            device_id,
+            format!("from={} users={}", link.device_id, moved.join(",")),
+        )
+        .await?;
+
+        self.db.get_markets_by_device_id(device_id).await
+    }
+
+    /// Check a link code and mark it used, so it can only be redeemed once
+    async fn claim_link_code(
+        &self,
+        code: &str,
+        device_id: &str,
+        now: DateTime<Utc>,
+    ) -> DbResult<LinkCode> {
+        let invalid =
+            || crate::db::DbError::Constraint(rules::RuleError::InvalidLinkCode.to_string());
+        let link = self.db.get_link_code(code).await.map_err(|_| invalid())?;
+        rules::validate_link_redemption(&link, device_id, now)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        if !self.db.redeem_link_code(code, device_id, now).await? {
+            return Err(invalid());
+        }
+        Ok(link)
+    }
+
+    /// Register a webhook on a market (admin only)
+    ///
+    /// `events` limits which message types are sent; empty means all.
+    pub async fn create_webhook(
+        &self,
+        market_id: Uuid,
+        admin_id: Uuid,
+        url: String,
+        secret: String,
+        events: Vec<String>,
+    ) -> DbResult<Webhook> {
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+
+        rules::validate_market_admin(&market, &admin)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        let existing = self.db.get_webhooks_in_market(market_id).await?;
+        rules::validate_webhook(&url, &secret, existing.len(), self.allow_private_webhooks)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        let webhook = Webhook {
+            id: Uuid::new_v4(),
+            market_id,
+            url,
+            secret,
+            events,
+            created_at: Utc::now(),
+        };
+        self.db.create_webhook(webhook).await
+    }
+
+    /// Get a market's webhooks (admin only)
+    pub async fn get_webhooks(&self, market_id: Uuid, admin_id: Uuid) -> DbResult<Vec<Webhook>> {
+        let market = self.db.get_market(market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+
+        rules::validate_market_admin(&market, &admin)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        self.db.get_webhooks_in_market(market_id).await
+    }
+
+    /// Get a webhook by ID
+    pub async fn get_webhook(&self, webhook_id: Uuid) -> DbResult<Webhook> {
+        self.db.get_webhook(webhook_id).await
+    }
+
+    /// Remove a webhook and its delivery log (admin of its market only)
+    pub async fn delete_webhook(&self, webhook_id: Uuid, admin_id: Uuid) -> DbResult<()> {
+        self.webhook_as_admin(webhook_id, admin_id).await?;
+        self.db.delete_webhook(webhook_id).await
+    }
+
+    /// Get a webhook's delivery log, oldest first (admin of its market only)
+    pub async fn get_webhook_deliveries(
+        &self,
+        webhook_id: Uuid,
+        admin_id: Uuid,
+    ) -> DbResult<Vec<WebhookDelivery>> {
+        self.webhook_as_admin(webhook_id, admin_id).await?;
+        self.db.get_webhook_deliveries(webhook_id).await
+    }
+
+    /// Webhooks on a market that want events of this type
+    ///
+    /// Events that aren't `public` (new bets hidden from their subject) go to
+    /// none: whoever reads a webhook's channel may well be the subject.
+    pub async fn webhooks_for_event(
+        &self,
+        market_id: Uuid,
+        event: &str,
+        public: bool,
+    ) -> DbResult<Vec<Webhook>> {
+        if !public {
+            return Ok(Vec::new());
+        }
+        let webhooks = self.db.get_webhooks_in_market(market_id).await?;
+        Ok(webhooks.into_iter().filter(|w| w.wants(event)).collect())
+    }
+
+    /// Record one delivery attempt
+    pub async fn record_webhook_delivery(
+        &self,
+        delivery: WebhookDelivery,
+    ) -> DbResult<WebhookDelivery> {
+        self.db.create_webhook_delivery(delivery).await
+    }
+
+    async fn webhook_as_admin(&self, webhook_id: Uuid, admin_id: Uuid) -> DbResult<Webhook> {
+        let webhook = self.db.get_webhook(webhook_id).await?;
+        let market = self.db.get_market(webhook.market_id).await?;
+        let admin = self.db.get_user(admin_id).await?;
+
+        rules::validate_market_admin(&market, &admin)
+            .map_err(|e| crate::db::DbError::Constraint(e.to_string()))?;
+
+        Ok(webhook)
+    }
+
+    async fn audit(&self, action: &str, device_id: &str, detail: String) -> DbResult<()> {
+        let entry = AuditEntry {
+            id: Uuid::new_v4(),
+            action: action.to_string(),
+            device_id: device_id.to_string(),
+            detail,
+            created_at: Utc::now(),
+        };
+        self.db.create_audit_entry(entry).await?;
+        Ok(())
+    }
+}
+
+/// Players whose stakes are forfeited: those banned from the market
+fn forfeited(users: &[User]) -> Vec<Uuid> {
+    users
+        .iter()
+        .filter(|u| u.status == MembershipStatus::Banned)
+        .map(|u| u.id)
+        .collect()
 }
 
 /// Generate a random 6-character invite code
@@ -395,3 +1772,117 @@ fn generate_invite_code() -> String {
         })
         .collect()
 }
+
+impl<D: Database> CsvExport<D> {
+    /// Produce the next piece of the report, or `None` once it's complete
+    ///
+    /// Wager rows are loaded one bet at a time and result rows one user at a
+    /// time, so large markets are never held in memory as a whole.
+    pub async fn next_chunk(&mut self) -> DbResult<Option<String>> {
+        let Some(row) = self.next_row else {
+            self.next_row = Some(0);
+            return Ok(Some(self.report.header().to_string()));
+        };
+        self.next_row = Some(row + 1);
+
+        let chunk = match self.report {
+            CsvReport::Wagers => {
+                let Some(bet) = self.bets.get(row) else {
+                    return Ok(None);
+                };
+                let mut chunk = String::new();
+                for wager in self.db.get_wagers_for_bet(bet.id).await? {
+                    let user = self.users.iter().find(|u| u.id == wager.user_id);
+                    chunk.push_str(&csv::wager_row(bet, &wager, user));
+                }
+                chunk
+            }
+            CsvReport::Results => {
+                let Some(user) = self.users.get(row) else {
+                    return Ok(None);
+                };
+                let result = self.user_result(user.id).await?;
+                csv::result_row(user, &result, self.market.starting_balance)
+            }
+            CsvReport::Leaderboard => {
+                let Some(user) = self.users.get(row) else {
+                    return Ok(None);
+                };
+                csv::leaderboard_row(row + 1, user, self.market.starting_balance)
+            }
+        };
+
+        Ok(Some(chunk))
+    }
+
+    async fn user_result(&self, user_id: Uuid) -> DbResult<UserResult> {
+        let mut result = UserResult::default();
+
+        // Stake per bet, so hedging both sides settles once
+        let mut stakes: Vec<(Uuid, i64)> = Vec::new();
+        for wager in self.db.get_wagers_for_user(user_id).await? {
+            if wager.refunded {
+                continue;
+            }
+            result.wagers += 1;
+            result.staked += wager.amount;
+            match stakes
+                .iter_mut()
+                .find(|(bet_id, _)| *bet_id == wager.bet_id)
+            {
+                Some((_, staked)) => *staked += wager.amount,
+                None => stakes.push((wager.bet_id, wager.amount)),
+            }
+        }
+
+        for (bet_id, staked) in stakes {
+            let Some(bet) = self.bets.iter().find(|b| b.id == bet_id) else {
+                continue;
+            };
+            if !matches!(bet.status, BetStatus::ResolvedYes | BetStatus::ResolvedNo) {
+                continue;
+            }
+
+            let wagers = self.db.get_wagers_for_bet(bet_id).await?;
+            let payout = parimutuel::calculate_payouts(bet, &wagers, &forfeited(&self.users))
+                .into_iter()
+                .find(|(id, _)| *id == user_id)
+                .map(|(_, payout)| payout)
+                .unwrap_or(0);
+
+            result.bets_settled += 1;
+            result.payouts += payout;
+            if payout > staked {
+                result.bets_won += 1;
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Generate a random 12-character device link code (60 bits)
+fn generate_link_code() -> String {
+    use rand::Rng;
+    const CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789"; // No confusing chars
+    let mut rng = rand::thread_rng();
+
+    (0..12)
+        .map(|_| {
+            let idx = rng.gen_range(0..CHARSET.len());
+            CHARSET[idx] as char
+        })
+        .collect()
+}
+
+/// Generate a random 32-character spectator token
+fn generate_spectator_token() -> String {
+    use rand::distributions::Alphanumeric;
+    use rand::Rng;
+
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
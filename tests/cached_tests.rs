@@ -0,0 +1,21 @@
+#![cfg(feature = "memory")]
+
+/// The shared scenarios and conformance checks against the write-through
+/// cache, layered over the in-memory backend
+use cazino::db::{CachedDatabase, InMemoryDatabase};
+use cazino::service::CazinoService;
+use std::sync::Arc;
+
+mod conformance;
+mod suite;
+
+type TestDatabase = CachedDatabase<InMemoryDatabase>;
+
+/// Helper to create a service over an empty cached database
+async fn setup_test_db() -> CazinoService<TestDatabase> {
+    CazinoService::new(Arc::new(setup_database().await))
+}
+
+async fn setup_database() -> TestDatabase {
+    CachedDatabase::new(InMemoryDatabase::new())
+}
@@ -0,0 +1,1740 @@
+/// Conformance checks for `Database` implementations
+///
+/// Every trait method is exercised directly, below the service layer, so
+/// backends can't drift apart on ordering, visibility, cascades or errors.
+/// Each test binary provides `setup_database` returning an empty backend.
+use super::setup_database;
+use cazino::db::query::{
+    BetQuery, BetSort, DeviceMarketQuery, DeviceMarketSort, UserQuery, UserSort, WagerQuery,
+    WagerSort,
+};
+use cazino::db::{Database, DbError, Page};
+use cazino::domain::archive::{CarryOver, MarketArchive, SeasonMembership, ARCHIVE_VERSION};
+use cazino::domain::models::{
+    AuditEntry, Bet, BetStatus, LinkCode, Market, MarketStatus, MarketTemplate, MembershipStatus,
+    Player, Removal, RevealCeremony, Season, Side, Spectator, TemplateBet, User, Wager, Webhook,
+    WebhookDelivery,
+};
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+use uuid::Uuid;
+
+/// Current time at whole seconds, so round trips compare equal on every backend
+fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(0)
+}
+
+fn market(invite_code: &str) -> Market {
+    let opens_at = now();
+    Market {
+        id: Uuid::new_v4(),
+        name: format!("Market {}", invite_code),
+        status: MarketStatus::Draft,
+        created_by: Uuid::new_v4(),
+        opens_at,
+        closes_at: opens_at + Duration::hours(24),
+        starting_balance: 1000,
+        invite_code: invite_code.to_string(),
+        created_at: opens_at,
+    }
+}
+
+fn user(market_id: Uuid, device_id: &str, joined_at: DateTime<Utc>) -> User {
+    User {
+        id: Uuid::new_v4(),
+        market_id,
+        device_id: device_id.to_string(),
+        display_name: device_id.to_string(),
+        avatar: "🙂".to_string(),
+        balance: 1000,
+        is_admin: false,
+        status: MembershipStatus::Active,
+        player_id: None,
+        joined_at,
+    }
+}
+
+fn bet(market_id: Uuid, created_by: Uuid, subject_user_ids: Vec<Uuid>) -> Bet {
+    Bet {
+        id: Uuid::new_v4(),
+        market_id,
+        subject_user_ids,
+        about_everyone: false,
+        created_by,
+        description: "Someone burns the toast".to_string(),
+        initial_odds: "1:1".to_string(),
+        status: BetStatus::Active,
+        yes_pool: 100,
+        no_pool: 100,
+        hide_from_subject: true,
+        created_at: now(),
+        resolved_at: None,
+    }
+}
+
+fn webhook(market_id: Uuid, url: &str, created_at: DateTime<Utc>) -> Webhook {
+    Webhook {
+        id: Uuid::new_v4(),
+        market_id,
+        url: url.to_string(),
+        secret: "0123456789abcdef".to_string(),
+        events: vec!["bet_created".to_string(), "wager_placed".to_string()],
+        created_at,
+    }
+}
+
+fn delivery(webhook_id: Uuid, attempt: u32, created_at: DateTime<Utc>) -> WebhookDelivery {
+    WebhookDelivery {
+        id: Uuid::new_v4(),
+        webhook_id,
+        event: "bet_created".to_string(),
+        attempt,
+        status_code: Some(500),
+        error: None,
+        succeeded: false,
+        created_at,
+    }
+}
+
+fn wager(bet_id: Uuid, user_id: Uuid, amount: i64, placed_at: DateTime<Utc>) -> Wager {
+    Wager {
+        id: Uuid::new_v4(),
+        bet_id,
+        user_id,
+        side: Side::Yes,
+        amount,
+        placed_at,
+        yes_pool_after: 100 + amount,
+        no_pool_after: 100,
+        probability_after: (100 + amount) as f64 / (200 + amount) as f64,
+        refunded: false,
+    }
+}
+
+fn is_not_found<T>(result: Result<T, DbError>) -> bool {
+    matches!(result, Err(DbError::NotFound(_)))
+}
+
+/// A market with one user in it
+async fn seeded<D: Database>(db: &D, invite_code: &str) -> (Market, User) {
+    let market = db.create_market(market(invite_code)).await.unwrap();
+    let user = db
+        .create_user(user(market.id, "device-a", now()))
+        .await
+        .unwrap();
+    (market, user)
+}
+
+#[tokio::test]
+async fn conformance_market_round_trip() {
+    let db = setup_database().await;
+    let created = db.create_market(market("ROUND1")).await.unwrap();
+
+    let fetched = db.get_market(created.id).await.unwrap();
+    assert_eq!(fetched.name, created.name);
+    assert_eq!(fetched.status, MarketStatus::Draft);
+    assert_eq!(fetched.created_by, created.created_by);
+    assert_eq!(fetched.opens_at, created.opens_at);
+    assert_eq!(fetched.closes_at, created.closes_at);
+    assert_eq!(fetched.starting_balance, 1000);
+    assert_eq!(fetched.created_at, created.created_at);
+
+    let by_code = db.get_market_by_invite_code("ROUND1").await.unwrap();
+    assert_eq!(by_code.id, created.id);
+
+    db.update_market_status(created.id, MarketStatus::Open)
+        .await
+        .unwrap();
+    assert_eq!(
+        db.get_market(created.id).await.unwrap().status,
+        MarketStatus::Open
+    );
+
+    assert!(is_not_found(db.get_market(Uuid::new_v4()).await));
+    assert!(is_not_found(db.get_market_by_invite_code("NOPE").await));
+}
+
+#[tokio::test]
+async fn conformance_market_invite_codes_are_unique() {
+    let db = setup_database().await;
+    db.create_market(market("TAKEN1")).await.unwrap();
+    assert!(db.create_market(market("TAKEN1")).await.is_err());
+}
+
+#[tokio::test]
+async fn conformance_user_round_trip() {
+    let db = setup_database().await;
+    let (market, created) = seeded(&db, "USERS1").await;
+
+    let fetched = db.get_user(created.id).await.unwrap();
+    assert_eq!(fetched.market_id, market.id);
+    assert_eq!(fetched.device_id, "device-a");
+    assert_eq!(fetched.balance, 1000);
+    assert!(!fetched.is_admin);
+    assert_eq!(fetched.status, MembershipStatus::Active);
+    assert_eq!(fetched.player_id, None);
+    assert_eq!(fetched.joined_at, created.joined_at);
+
+    let by_device = db
+        .get_user_by_device_id(market.id, "device-a")
+        .await
+        .unwrap();
+    assert_eq!(by_device.id, created.id);
+
+    db.update_user_balance(created.id, 750).await.unwrap();
+    db.update_user_status(created.id, MembershipStatus::Kicked)
+        .await
+        .unwrap();
+    db.update_user_profile(created.id, "Renamed", "🦊")
+        .await
+        .unwrap();
+    let updated = db.get_user(created.id).await.unwrap();
+    assert_eq!(updated.balance, 750);
+    assert_eq!(updated.status, MembershipStatus::Kicked);
+    assert_eq!(updated.display_name, "Renamed");
+    assert_eq!(updated.avatar, "🦊");
+
+    assert!(is_not_found(db.get_user(Uuid::new_v4()).await));
+    assert!(is_not_found(
+        db.get_user_by_device_id(market.id, "device-z").await
+    ));
+}
+
+#[tokio::test]
+async fn conformance_users_need_a_market_and_a_free_device() {
+    let db = setup_database().await;
+    let (market, _) = seeded(&db, "USERS2").await;
+
+    // One seat per device in a market
+    assert!(db
+        .create_user(user(market.id, "device-a", now()))
+        .await
+        .is_err());
+
+    // No seats in markets that don't exist
+    assert!(db
+        .create_user(user(Uuid::new_v4(), "device-b", now()))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn conformance_users_in_market() {
+    let db = setup_database().await;
+    let (market, first) = seeded(&db, "USERS3").await;
+    let second = db
+        .create_user(user(market.id, "device-b", now()))
+        .await
+        .unwrap();
+    let (_, elsewhere) = seeded(&db, "USERS4").await;
+
+    let ids: Vec<Uuid> = db
+        .get_users_in_market(market.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|u| u.id)
+        .collect();
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains(&first.id));
+    assert!(ids.contains(&second.id));
+    assert!(!ids.contains(&elsewhere.id));
+
+    assert!(db
+        .get_users_in_market(Uuid::new_v4())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn conformance_update_user_device() {
+    let db = setup_database().await;
+    let (market, first) = seeded(&db, "DEVICE1").await;
+    db.create_user(user(market.id, "device-b", now()))
+        .await
+        .unwrap();
+
+    db.update_user_device(first.id, "device-c").await.unwrap();
+    assert_eq!(
+        db.get_user_by_device_id(market.id, "device-c")
+            .await
+            .unwrap()
+            .id,
+        first.id
+    );
+
+    // Moving onto a device that already has a seat here fails
+    assert!(db.update_user_device(first.id, "device-b").await.is_err());
+}
+
+#[tokio::test]
+async fn conformance_markets_by_device_newest_first() {
+    let db = setup_database().await;
+    let start = now() - Duration::hours(12);
+
+    for i in 0..12 {
+        let market = db
+            .create_market(market(&format!("RECENT{:02}", i)))
+            .await
+            .unwrap();
+        db.create_user(user(market.id, "device-r", start + Duration::minutes(i)))
+            .await
+            .unwrap();
+    }
+
+    // Newest ten only
+    let recent = db.get_markets_by_device_id("device-r").await.unwrap();
+    let codes: Vec<&str> = recent
+        .iter()
+        .map(|(market, _)| market.invite_code.as_str())
+        .collect();
+    assert_eq!(codes.len(), 10);
+    assert_eq!(codes[0], "RECENT11");
+    assert_eq!(codes[9], "RECENT02");
+    assert!(recent
+        .iter()
+        .all(|(market, user)| user.market_id == market.id && user.device_id == "device-r"));
+
+    assert!(db
+        .get_markets_by_device_id("device-unknown")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn conformance_list_markets_by_device() {
+    let db = setup_database().await;
+    let start = now() - Duration::hours(12);
+
+    // More than `get_markets_by_device_id` returns; markets created in
+    // the opposite order to joining
+    let mut joined = Vec::new();
+    for i in 0..12 {
+        let market = db
+            .create_market(Market {
+                status: if i % 3 == 0 {
+                    MarketStatus::Open
+                } else {
+                    MarketStatus::Draft
+                },
+                created_at: start - Duration::minutes(i),
+                ..market(&format!("LIST{:02}", i))
+            })
+            .await
+            .unwrap();
+        db.create_user(user(market.id, "device-l", start + Duration::minutes(i)))
+            .await
+            .unwrap();
+        joined.push(market.id);
+    }
+    seeded(&db, "LISTXX").await;
+
+    let mut query = DeviceMarketQuery {
+        limit: Some(5),
+        ..Default::default()
+    };
+    let mut recent = Vec::new();
+    loop {
+        let page = db
+            .list_markets_by_device_id("device-l", &query)
+            .await
+            .unwrap();
+        assert!(page.items.len() <= 5);
+        recent.extend(page.items.iter().map(|(market, _)| market.id));
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => break,
+        }
+    }
+    assert_eq!(recent, joined.iter().rev().copied().collect::<Vec<_>>());
+
+    let newest = db
+        .list_markets_by_device_id(
+            "device-l",
+            &DeviceMarketQuery {
+                sort: DeviceMarketSort::Newest,
+                created_before: Some(start - Duration::minutes(8)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let ids: Vec<Uuid> = newest.items.iter().map(|(market, _)| market.id).collect();
+    assert_eq!(ids, vec![joined[9], joined[10], joined[11]]);
+    assert!(newest.next_cursor.is_none());
+
+    let open = db
+        .list_markets_by_device_id(
+            "device-l",
+            &DeviceMarketQuery {
+                status: Some(MarketStatus::Open),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let ids: Vec<Uuid> = open.items.iter().map(|(market, _)| market.id).collect();
+    assert_eq!(ids, vec![joined[9], joined[6], joined[3], joined[0]]);
+    assert!(open
+        .items
+        .iter()
+        .all(|(market, user)| user.market_id == market.id && user.device_id == "device-l"));
+
+    assert!(db
+        .list_markets_by_device_id("device-unknown", &DeviceMarketQuery::default())
+        .await
+        .unwrap()
+        .items
+        .is_empty());
+}
+
+#[tokio::test]
+async fn conformance_list_users() {
+    let db = setup_database().await;
+    let (market, first) = seeded(&db, "LISTU1").await;
+    let start = first.joined_at;
+    let mut users = vec![first];
+    for (i, balance) in [(1, 1500), (2, 700), (3, 1500)] {
+        users.push(
+            db.create_user(User {
+                balance,
+                ..user(
+                    market.id,
+                    &format!("device-{}", i),
+                    start + Duration::minutes(i),
+                )
+            })
+            .await
+            .unwrap(),
+        );
+    }
+    db.update_user_status(users[2].id, MembershipStatus::Left)
+        .await
+        .unwrap();
+    seeded(&db, "LISTU2").await;
+
+    let ids = |page: Page<User>| page.items.iter().map(|u| u.id).collect::<Vec<_>>();
+
+    let joined = db
+        .list_users_in_market(market.id, &UserQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(ids(joined), users.iter().map(|u| u.id).collect::<Vec<_>>());
+
+    // Equal balances fall back to id order, and pages don't repeat them
+    let first_page = db
+        .list_users_in_market(
+            market.id,
+            &UserQuery {
+                sort: UserSort::Balance,
+                limit: Some(1),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let cursor = first_page.next_cursor.clone();
+    let rest = db
+        .list_users_in_market(
+            market.id,
+            &UserQuery {
+                sort: UserSort::Balance,
+                cursor,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let richest = [users[1].id.max(users[3].id), users[1].id.min(users[3].id)];
+    assert_eq!(ids(first_page), vec![richest[0]]);
+    assert_eq!(ids(rest), vec![richest[1], users[0].id, users[2].id]);
+
+    let active_late = db
+        .list_users_in_market(
+            market.id,
+            &UserQuery {
+                status: Some(MembershipStatus::Active),
+                joined_after: Some(start + Duration::minutes(1)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(ids(active_late), vec![users[1].id, users[3].id]);
+}
+
+#[tokio::test]
+async fn conformance_players() {
+    let db = setup_database().await;
+    let player = db
+        .create_player(Player {
+            id: Uuid::new_v4(),
+            display_name: "Grandma".to_string(),
+            avatar: "👵".to_string(),
+            created_at: now(),
+        })
+        .await
+        .unwrap();
+
+    let fetched = db.get_player(player.id).await.unwrap();
+    assert_eq!(fetched.display_name, "Grandma");
+    assert_eq!(fetched.avatar, "👵");
+    assert_eq!(fetched.created_at, player.created_at);
+    assert!(is_not_found(db.get_player(Uuid::new_v4()).await));
+
+    // Seats come back in the order they joined
+    let start = now() - Duration::hours(1);
+    let mut seats = Vec::new();
+    for i in (0..3).rev() {
+        let market = db
+            .create_market(market(&format!("PLAYER{}", i)))
+            .await
+            .unwrap();
+        let seat = db
+            .create_user(user(market.id, "device-p", start + Duration::minutes(i)))
+            .await
+            .unwrap();
+        db.update_user_player(seat.id, player.id).await.unwrap();
+        seats.push(seat.id);
+    }
+    seats.reverse();
+
+    let linked: Vec<Uuid> = db
+        .get_users_by_player(player.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|u| u.id)
+        .collect();
+    assert_eq!(linked, seats);
+    assert_eq!(
+        db.get_user(seats[0]).await.unwrap().player_id,
+        Some(player.id)
+    );
+
+    // Seats can only link to players that exist
+    assert!(db
+        .update_user_player(seats[0], Uuid::new_v4())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn conformance_templates() {
+    let db = setup_database().await;
+    let template = db
+        .create_template(MarketTemplate {
+            id: Uuid::new_v4(),
+            name: "Family dinner".to_string(),
+            starting_balance: 500,
+            duration_hours: 6,
+            bets: vec![TemplateBet {
+                description: "Dad falls asleep".to_string(),
+                initial_odds: "2:1".to_string(),
+                hide_from_subject: true,
+                about_everyone: false,
+                subject_names: vec!["Dad".to_string()],
+                creator_name: None,
+            }],
+            created_at: now(),
+        })
+        .await
+        .unwrap();
+
+    let fetched = db.get_template(template.id).await.unwrap();
+    assert_eq!(fetched.name, "Family dinner");
+    assert_eq!(fetched.starting_balance, 500);
+    assert_eq!(fetched.duration_hours, 6);
+    assert_eq!(fetched.bets.len(), 1);
+    assert_eq!(fetched.bets[0].subject_names, vec!["Dad".to_string()]);
+    assert!(fetched.bets[0].hide_from_subject);
+    assert!(is_not_found(db.get_template(Uuid::new_v4()).await));
+}
+
+#[tokio::test]
+async fn conformance_seasons() {
+    let db = setup_database().await;
+    let season = db
+        .create_season(Season {
+            id: Uuid::new_v4(),
+            name: "Summer".to_string(),
+            carry_over_balances: true,
+            created_at: now(),
+        })
+        .await
+        .unwrap();
+    let fetched = db.get_season(season.id).await.unwrap();
+    assert_eq!(fetched.name, "Summer");
+    assert!(fetched.carry_over_balances);
+    assert!(is_not_found(db.get_season(Uuid::new_v4()).await));
+
+    // Markets come back by position, not insertion
+    let (second, carried) = seeded(&db, "SEASON2").await;
+    let (first, _) = seeded(&db, "SEASON1").await;
+    let (outside, _) = seeded(&db, "SEASON3").await;
+    db.add_season_market(season.id, second.id, 1).await.unwrap();
+    db.add_season_market(season.id, first.id, 0).await.unwrap();
+
+    let ids: Vec<Uuid> = db
+        .get_season_markets(season.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|m| m.id)
+        .collect();
+    assert_eq!(ids, vec![first.id, second.id]);
+    assert_eq!(
+        db.get_season_for_market(second.id).await.unwrap().id,
+        season.id
+    );
+    assert!(is_not_found(db.get_season_for_market(outside.id).await));
+
+    // A market belongs to at most one season
+    assert!(db.add_season_market(season.id, first.id, 2).await.is_err());
+
+    db.create_carry_over(second.id, carried.id, -200)
+        .await
+        .unwrap();
+    assert_eq!(
+        db.get_carry_overs(season.id).await.unwrap(),
+        vec![(carried.id, -200)]
+    );
+    assert!(db.get_carry_overs(Uuid::new_v4()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn conformance_link_codes() {
+    let db = setup_database().await;
+    let created_at = now();
+    db.create_link_code(LinkCode {
+        code: "LINK42".to_string(),
+        device_id: "device-old".to_string(),
+        created_at,
+        expires_at: created_at + Duration::minutes(10),
+        redeemed_at: None,
+        redeemed_by: None,
+    })
+    .await
+    .unwrap();
+
+    let fetched = db.get_link_code("LINK42").await.unwrap();
+    assert_eq!(fetched.device_id, "device-old");
+    assert_eq!(fetched.expires_at, created_at + Duration::minutes(10));
+    assert!(fetched.redeemed_at.is_none());
+    assert!(is_not_found(db.get_link_code("NOPE").await));
+
+    // Codes redeem exactly once
+    let redeemed_at = now();
+    assert!(db
+        .redeem_link_code("LINK42", "device-new", redeemed_at)
+        .await
+        .unwrap());
+    assert!(!db
+        .redeem_link_code("LINK42", "device-other", now())
+        .await
+        .unwrap());
+    assert!(!db
+        .redeem_link_code("NOPE", "device-new", now())
+        .await
+        .unwrap());
+
+    let fetched = db.get_link_code("LINK42").await.unwrap();
+    assert_eq!(fetched.redeemed_at, Some(redeemed_at));
+    assert_eq!(fetched.redeemed_by.as_deref(), Some("device-new"));
+}
+
+#[tokio::test]
+async fn conformance_audit_entries() {
+    let db = setup_database().await;
+    let start = now();
+    for (minutes_ago, action, device) in [
+        (30, "link", "device-a"),
+        (5, "link", "device-a"),
+        (1, "link", "device-a"),
+        (1, "redeem", "device-a"),
+        (1, "link", "device-b"),
+    ] {
+        db.create_audit_entry(AuditEntry {
+            id: Uuid::new_v4(),
+            action: action.to_string(),
+            device_id: device.to_string(),
+            detail: String::new(),
+            created_at: start - Duration::minutes(minutes_ago),
+        })
+        .await
+        .unwrap();
+    }
+
+    let since = start - Duration::minutes(10);
+    assert_eq!(
+        db.count_audit_entries(Some("device-a"), "link", since)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        db.count_audit_entries(Some("device-a"), "redeem", since)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        db.count_audit_entries(Some("device-c"), "link", since)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        db.count_audit_entries(None, "link", since).await.unwrap(),
+        3
+    );
+}
+
+#[tokio::test]
+async fn conformance_spectators() {
+    let db = setup_database().await;
+    let (market, _) = seeded(&db, "WATCH1").await;
+    let spectator = db
+        .create_spectator(Spectator {
+            id: Uuid::new_v4(),
+            market_id: market.id,
+            display_name: "Neighbour".to_string(),
+            token: "secret-token".to_string(),
+            joined_at: now(),
+        })
+        .await
+        .unwrap();
+
+    let fetched = db.get_spectator_by_token("secret-token").await.unwrap();
+    assert_eq!(fetched.id, spectator.id);
+    assert_eq!(fetched.display_name, "Neighbour");
+    assert!(is_not_found(db.get_spectator_by_token("wrong").await));
+
+    let watching = db.get_spectators_in_market(market.id).await.unwrap();
+    assert_eq!(watching.len(), 1);
+
+    // Tokens are unique
+    assert!(db
+        .create_spectator(Spectator {
+            id: Uuid::new_v4(),
+            market_id: market.id,
+            display_name: "Copycat".to_string(),
+            token: "secret-token".to_string(),
+            joined_at: now(),
+        })
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn conformance_webhooks() {
+    let db = setup_database().await;
+    let (market, _) = seeded(&db, "HOOKS1").await;
+    let earlier = now() - Duration::minutes(5);
+
+    let later = db
+        .create_webhook(webhook(market.id, "https://b.example/hook", now()))
+        .await
+        .unwrap();
+    let first = db
+        .create_webhook(webhook(market.id, "https://a.example/hook", earlier))
+        .await
+        .unwrap();
+
+    let fetched = db.get_webhook(first.id).await.unwrap();
+    assert_eq!(fetched.url, "https://a.example/hook");
+    assert_eq!(fetched.secret, "0123456789abcdef");
+    assert_eq!(fetched.events, vec!["bet_created", "wager_placed"]);
+    assert_eq!(fetched.created_at, earlier);
+    assert!(is_not_found(db.get_webhook(Uuid::new_v4()).await));
+
+    // Oldest first
+    let ids: Vec<Uuid> = db
+        .get_webhooks_in_market(market.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|w| w.id)
+        .collect();
+    assert_eq!(ids, vec![first.id, later.id]);
+
+    // Webhooks need a market, deliveries need a webhook
+    assert!(db
+        .create_webhook(webhook(Uuid::new_v4(), "https://c.example/hook", now()))
+        .await
+        .is_err());
+    assert!(db
+        .create_webhook_delivery(delivery(Uuid::new_v4(), 1, now()))
+        .await
+        .is_err());
+
+    let retried = db
+        .create_webhook_delivery(WebhookDelivery {
+            status_code: Some(204),
+            succeeded: true,
+            ..delivery(first.id, 2, now())
+        })
+        .await
+        .unwrap();
+    let failed = db
+        .create_webhook_delivery(WebhookDelivery {
+            status_code: None,
+            error: Some("Connection refused".to_string()),
+            ..delivery(first.id, 1, earlier)
+        })
+        .await
+        .unwrap();
+
+    let log = db.get_webhook_deliveries(first.id).await.unwrap();
+    assert_eq!(
+        log.iter().map(|d| d.id).collect::<Vec<_>>(),
+        vec![failed.id, retried.id]
+    );
+    assert_eq!(log[0].status_code, None);
+    assert_eq!(log[0].error.as_deref(), Some("Connection refused"));
+    assert!(!log[0].succeeded);
+    assert_eq!(log[1].attempt, 2);
+    assert_eq!(log[1].status_code, Some(204));
+    assert!(log[1].succeeded);
+    assert!(db
+        .get_webhook_deliveries(later.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Deleting takes the delivery log with it
+    db.delete_webhook(first.id).await.unwrap();
+    assert!(is_not_found(db.get_webhook(first.id).await));
+    assert!(db
+        .get_webhook_deliveries(first.id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(db.get_webhooks_in_market(market.id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn conformance_bet_round_trip() {
+    let db = setup_database().await;
+    let (market, creator) = seeded(&db, "BETS01").await;
+    let subject = db
+        .create_user(user(market.id, "device-s", now()))
+        .await
+        .unwrap();
+    let created = db
+        .create_bet(bet(market.id, creator.id, vec![subject.id]))
+        .await
+        .unwrap();
+
+    let fetched = db.get_bet(created.id).await.unwrap();
+    assert_eq!(fetched.market_id, market.id);
+    assert_eq!(fetched.subject_user_ids, vec![subject.id]);
+    assert!(!fetched.about_everyone);
+    assert_eq!(fetched.created_by, creator.id);
+    assert_eq!(fetched.description, created.description);
+    assert_eq!(fetched.initial_odds, "1:1");
+    assert_eq!(fetched.status, BetStatus::Active);
+    assert_eq!((fetched.yes_pool, fetched.no_pool), (100, 100));
+    assert!(fetched.hide_from_subject);
+    assert_eq!(fetched.created_at, created.created_at);
+    assert_eq!(fetched.resolved_at, None);
+    assert!(is_not_found(db.get_bet(Uuid::new_v4()).await));
+
+    db.update_bet_pools(created.id, 150, 120).await.unwrap();
+    let fetched = db.get_bet(created.id).await.unwrap();
+    assert_eq!((fetched.yes_pool, fetched.no_pool), (150, 120));
+
+    // Subjects must be users, and a bet whose subjects fail isn't kept,
+    // since a bet about nobody would be visible to its intended subject
+    let orphan = bet(market.id, creator.id, vec![subject.id, Uuid::new_v4()]);
+    assert!(db.create_bet(orphan.clone()).await.is_err());
+    assert!(is_not_found(db.get_bet(orphan.id).await));
+    assert_eq!(db.get_bets_in_market(market.id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn conformance_bet_status_and_resolution_time() {
+    let db = setup_database().await;
+    let (market, creator) = seeded(&db, "BETS02").await;
+    let created = db
+        .create_bet(bet(market.id, creator.id, vec![]))
+        .await
+        .unwrap();
+
+    db.update_bet_status(created.id, BetStatus::ResolvedYes)
+        .await
+        .unwrap();
+    let resolved = db.get_bet(created.id).await.unwrap();
+    assert_eq!(resolved.status, BetStatus::ResolvedYes);
+    assert!(resolved.resolved_at.is_some());
+
+    // Leaving a resolved status clears the resolution time
+    db.update_bet_status(created.id, BetStatus::Challenged)
+        .await
+        .unwrap();
+    let challenged = db.get_bet(created.id).await.unwrap();
+    assert_eq!(challenged.status, BetStatus::Challenged);
+    assert!(challenged.resolved_at.is_none());
+}
+
+#[tokio::test]
+async fn conformance_bets_in_market_and_pending() {
+    let db = setup_database().await;
+    let (market, creator) = seeded(&db, "BETS03").await;
+    let (other, other_creator) = seeded(&db, "BETS04").await;
+
+    let active = db
+        .create_bet(bet(market.id, creator.id, vec![]))
+        .await
+        .unwrap();
+    let pending = db
+        .create_bet(Bet {
+            status: BetStatus::Pending,
+            ..bet(market.id, creator.id, vec![])
+        })
+        .await
+        .unwrap();
+    db.create_bet(bet(other.id, other_creator.id, vec![]))
+        .await
+        .unwrap();
+
+    let ids: Vec<Uuid> = db
+        .get_bets_in_market(market.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|b| b.id)
+        .collect();
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains(&active.id) && ids.contains(&pending.id));
+
+    let pending_ids: Vec<Uuid> = db
+        .get_pending_bets(market.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|b| b.id)
+        .collect();
+    assert_eq!(pending_ids, vec![pending.id]);
+}
+
+#[tokio::test]
+async fn conformance_list_bets() {
+    let db = setup_database().await;
+    let (market, creator) = seeded(&db, "LISTB1").await;
+    let subject = db
+        .create_user(user(market.id, "device-b", now()))
+        .await
+        .unwrap();
+    let other = db
+        .create_user(user(market.id, "device-c", now()))
+        .await
+        .unwrap();
+    let (elsewhere, elsewhere_creator) = seeded(&db, "LISTB2").await;
+    db.create_bet(bet(elsewhere.id, elsewhere_creator.id, vec![]))
+        .await
+        .unwrap();
+
+    let start = now() - Duration::hours(1);
+    let mut bets = Vec::new();
+    for (i, bet) in [
+        bet(market.id, creator.id, vec![subject.id]),
+        Bet {
+            about_everyone: true,
+            status: BetStatus::Pending,
+            yes_pool: 400,
+            ..bet(market.id, subject.id, vec![])
+        },
+        Bet {
+            yes_pool: 200,
+            ..bet(market.id, creator.id, vec![other.id])
+        },
+        Bet {
+            yes_pool: 200,
+            status: BetStatus::Void,
+            ..bet(market.id, creator.id, vec![subject.id, other.id])
+        },
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let bet = Bet {
+            created_at: start + Duration::minutes(i as i64),
+            ..bet
+        };
+        bets.push(db.create_bet(bet).await.unwrap().id);
+    }
+
+    let list = |query: BetQuery| {
+        let db = &db;
+        async move {
+            db.list_bets_in_market(market.id, &query)
+                .await
+                .unwrap()
+                .items
+                .iter()
+                .map(|b| b.id)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        list(BetQuery::default()).await,
+        vec![bets[3], bets[2], bets[1], bets[0]]
+    );
+    assert_eq!(
+        list(BetQuery {
+            sort: BetSort::Oldest,
+            ..Default::default()
+        })
+        .await,
+        bets
+    );
+    let tied = [bets[2].max(bets[3]), bets[2].min(bets[3])];
+    assert_eq!(
+        list(BetQuery {
+            sort: BetSort::Pool,
+            ..Default::default()
+        })
+        .await,
+        vec![bets[1], tied[0], tied[1], bets[0]]
+    );
+
+    // Filters
+    assert_eq!(
+        list(BetQuery {
+            status: Some(BetStatus::Active),
+            ..Default::default()
+        })
+        .await,
+        vec![bets[2], bets[0]]
+    );
+    assert_eq!(
+        list(BetQuery {
+            subject: Some(subject.id),
+            ..Default::default()
+        })
+        .await,
+        vec![bets[3], bets[1], bets[0]]
+    );
+    assert_eq!(
+        list(BetQuery {
+            creator: Some(creator.id),
+            subject: Some(other.id),
+            ..Default::default()
+        })
+        .await,
+        vec![bets[3], bets[2]]
+    );
+    assert_eq!(
+        list(BetQuery {
+            created_after: Some(start + Duration::minutes(1)),
+            created_before: Some(start + Duration::minutes(3)),
+            ..Default::default()
+        })
+        .await,
+        vec![bets[2], bets[1]]
+    );
+
+    // Paging through the pool order returns every bet once
+    let mut query = BetQuery {
+        sort: BetSort::Pool,
+        limit: Some(2),
+        ..Default::default()
+    };
+    let mut paged = Vec::new();
+    loop {
+        let page = db.list_bets_in_market(market.id, &query).await.unwrap();
+        paged.extend(page.items.iter().map(|b| b.id));
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => break,
+        }
+    }
+    assert_eq!(paged, vec![bets[1], tied[0], tied[1], bets[0]]);
+
+    // Paged rows come back whole, subjects included
+    let page = db
+        .list_bets_in_market(
+            market.id,
+            &BetQuery {
+                limit: Some(1),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.items[0].subject_user_ids.len(), 2);
+    assert_eq!(page.items[0].status, BetStatus::Void);
+
+    // Cursors belong to their sort order
+    assert!(matches!(
+        db.list_bets_in_market(
+            market.id,
+            &BetQuery {
+                cursor: page.next_cursor,
+                sort: BetSort::Pool,
+                ..Default::default()
+            },
+        )
+        .await,
+        Err(DbError::Constraint(_))
+    ));
+}
+
+#[tokio::test]
+async fn conformance_market_contents() {
+    let db = setup_database().await;
+    let (market, creator) = seeded(&db, "WHOLE1").await;
+    let subject = db
+        .create_user(user(market.id, "device-s", now()))
+        .await
+        .unwrap();
+    db.update_user_status(subject.id, MembershipStatus::Left)
+        .await
+        .unwrap();
+    let active = db
+        .create_bet(bet(market.id, creator.id, vec![subject.id]))
+        .await
+        .unwrap();
+    let pending = db
+        .create_bet(bet(market.id, creator.id, vec![]))
+        .await
+        .unwrap();
+    db.update_bet_status(pending.id, BetStatus::Pending)
+        .await
+        .unwrap();
+
+    // Neighbouring markets stay out
+    let (other, _) = seeded(&db, "WHOLE2").await;
+    db.create_bet(bet(other.id, creator.id, vec![]))
+        .await
+        .unwrap();
+
+    let contents = db.get_market_contents(market.id).await.unwrap();
+    assert_eq!(contents.market.invite_code, "WHOLE1");
+
+    // Everyone, whatever their status, and every bet, with subjects
+    let mut user_ids: Vec<Uuid> = contents.users.iter().map(|u| u.id).collect();
+    user_ids.sort();
+    let mut expected = vec![creator.id, subject.id];
+    expected.sort();
+    assert_eq!(user_ids, expected);
+
+    assert_eq!(contents.bets.len(), 2);
+    let fetched = contents.bets.iter().find(|b| b.id == active.id).unwrap();
+    assert_eq!(fetched.subject_user_ids, vec![subject.id]);
+    let fetched = contents.bets.iter().find(|b| b.id == pending.id).unwrap();
+    assert_eq!(fetched.status, BetStatus::Pending);
+
+    assert!(is_not_found(db.get_market_contents(Uuid::new_v4()).await));
+}
+
+#[tokio::test]
+async fn conformance_bet_visibility() {
+    let db = setup_database().await;
+    let (market, creator) = seeded(&db, "HIDDEN").await;
+    let subject = db
+        .create_user(user(market.id, "device-s", now()))
+        .await
+        .unwrap();
+    let hidden = db
+        .create_bet(bet(market.id, creator.id, vec![subject.id]))
+        .await
+        .unwrap();
+    let open = db
+        .create_bet(Bet {
+            hide_from_subject: false,
+            ..bet(market.id, creator.id, vec![subject.id])
+        })
+        .await
+        .unwrap();
+
+    // The subject sees that a hidden bet exists, but not what it says
+    let views = db.get_bets_for_user(market.id, subject.id).await.unwrap();
+    assert_eq!(views.len(), 2);
+    let hidden_view = views.iter().find(|v| v.id == hidden.id).unwrap();
+    assert!(hidden_view.is_hidden);
+    assert!(hidden_view.description.is_none());
+    assert!(hidden_view.subject_user_ids.is_none());
+    let open_view = views.iter().find(|v| v.id == open.id).unwrap();
+    assert!(!open_view.is_hidden);
+    assert_eq!(
+        open_view.description.as_deref(),
+        Some(open.description.as_str())
+    );
+
+    // Everyone else sees it all
+    let views = db.get_bets_for_user(market.id, creator.id).await.unwrap();
+    assert!(views.iter().all(|v| !v.is_hidden));
+    assert_eq!(
+        views
+            .iter()
+            .find(|v| v.id == hidden.id)
+            .unwrap()
+            .subject_user_ids,
+        Some(vec![subject.id])
+    );
+}
+
+#[tokio::test]
+async fn conformance_bets_about_user() {
+    let db = setup_database().await;
+    let (market, creator) = seeded(&db, "ABOUT1").await;
+    let subject = db
+        .create_user(user(market.id, "device-s", now()))
+        .await
+        .unwrap();
+    let (other, other_creator) = seeded(&db, "ABOUT2").await;
+
+    let about = db
+        .create_bet(bet(market.id, creator.id, vec![subject.id]))
+        .await
+        .unwrap();
+    let everyone = db
+        .create_bet(Bet {
+            about_everyone: true,
+            ..bet(market.id, creator.id, vec![])
+        })
+        .await
+        .unwrap();
+    db.create_bet(bet(market.id, creator.id, vec![creator.id]))
+        .await
+        .unwrap();
+    db.create_bet(Bet {
+        about_everyone: true,
+        ..bet(other.id, other_creator.id, vec![])
+    })
+    .await
+    .unwrap();
+
+    let ids: Vec<Uuid> = db
+        .get_bets_about_user(subject.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|b| b.id)
+        .collect();
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains(&about.id) && ids.contains(&everyone.id));
+}
+
+#[tokio::test]
+async fn conformance_wagers() {
+    let db = setup_database().await;
+    let (market, bettor) = seeded(&db, "WAGER1").await;
+    let created = db
+        .create_bet(bet(market.id, bettor.id, vec![]))
+        .await
+        .unwrap();
+    let start = now() - Duration::minutes(10);
+
+    // Inserted out of order; they come back by placement time
+    let later = db
+        .create_wager(wager(
+            created.id,
+            bettor.id,
+            20,
+            start + Duration::minutes(2),
+        ))
+        .await
+        .unwrap();
+    let earlier = db
+        .create_wager(Wager {
+            side: Side::No,
+            ..wager(created.id, bettor.id, 10, start)
+        })
+        .await
+        .unwrap();
+
+    let for_bet = db.get_wagers_for_bet(created.id).await.unwrap();
+    assert_eq!(
+        for_bet.iter().map(|w| w.id).collect::<Vec<_>>(),
+        vec![earlier.id, later.id]
+    );
+    assert_eq!(for_bet[0].side, Side::No);
+    assert_eq!(for_bet[0].amount, 10);
+    assert_eq!(for_bet[0].placed_at, start);
+    assert_eq!(for_bet[1].yes_pool_after, 120);
+    assert!((for_bet[1].probability_after - later.probability_after).abs() < 1e-9);
+
+    let for_user = db.get_wagers_for_user(bettor.id).await.unwrap();
+    assert_eq!(
+        for_user.iter().map(|w| w.id).collect::<Vec<_>>(),
+        vec![earlier.id, later.id]
+    );
+
+    assert!(for_bet.iter().all(|w| !w.refunded));
+
+    // Wagers need a bet and a user
+    assert!(db
+        .create_wager(wager(Uuid::new_v4(), bettor.id, 5, now()))
+        .await
+        .is_err());
+    assert!(db
+        .create_wager(wager(created.id, Uuid::new_v4(), 5, now()))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn conformance_list_wagers() {
+    let db = setup_database().await;
+    let (market, first) = seeded(&db, "LISTW1").await;
+    let second = db
+        .create_user(user(market.id, "device-b", now()))
+        .await
+        .unwrap();
+    let created = db
+        .create_bet(bet(market.id, first.id, vec![]))
+        .await
+        .unwrap();
+    let start = now() - Duration::minutes(10);
+
+    let mut wagers = Vec::new();
+    for (i, (user_id, side, amount)) in [
+        (first.id, Side::Yes, 30),
+        (second.id, Side::No, 50),
+        (first.id, Side::No, 10),
+        (second.id, Side::Yes, 40),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let wager = Wager {
+            side,
+            ..wager(
+                created.id,
+                user_id,
+                amount,
+                start + Duration::minutes(i as i64),
+            )
+        };
+        wagers.push(db.create_wager(wager).await.unwrap().id);
+    }
+
+    let list = |query: WagerQuery| {
+        let db = &db;
+        async move {
+            db.list_wagers_for_bet(created.id, &query)
+                .await
+                .unwrap()
+                .items
+                .iter()
+                .map(|w| w.id)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        list(WagerQuery::default()).await,
+        vec![wagers[3], wagers[2], wagers[1], wagers[0]]
+    );
+    assert_eq!(
+        list(WagerQuery {
+            sort: WagerSort::Largest,
+            ..Default::default()
+        })
+        .await,
+        vec![wagers[1], wagers[3], wagers[0], wagers[2]]
+    );
+    assert_eq!(
+        list(WagerQuery {
+            user: Some(first.id),
+            sort: WagerSort::Oldest,
+            ..Default::default()
+        })
+        .await,
+        vec![wagers[0], wagers[2]]
+    );
+    assert_eq!(
+        list(WagerQuery {
+            side: Some(Side::No),
+            placed_before: Some(start + Duration::minutes(2)),
+            ..Default::default()
+        })
+        .await,
+        vec![wagers[1]]
+    );
+
+    let first_page = db
+        .list_wagers_for_bet(
+            created.id,
+            &WagerQuery {
+                sort: WagerSort::Largest,
+                limit: Some(3),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(first_page.items.len(), 3);
+    let rest = list(WagerQuery {
+        sort: WagerSort::Largest,
+        limit: Some(3),
+        cursor: first_page.next_cursor,
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(rest, vec![wagers[2]]);
+
+    assert!(db
+        .list_wagers_for_bet(Uuid::new_v4(), &WagerQuery::default())
+        .await
+        .unwrap()
+        .items
+        .is_empty());
+}
+
+#[tokio::test]
+async fn conformance_apply_wager() {
+    let db = setup_database().await;
+    let (market, bettor) = seeded(&db, "APPLY1").await;
+    let created = db
+        .create_bet(bet(market.id, bettor.id, vec![]))
+        .await
+        .unwrap();
+
+    let applied = db
+        .apply_wager(wager(created.id, bettor.id, 50, now()))
+        .await
+        .unwrap();
+    assert_eq!((applied.yes_pool_after, applied.no_pool_after), (150, 100));
+
+    let after = db.get_bet(created.id).await.unwrap();
+    assert_eq!((after.yes_pool, after.no_pool), (150, 100));
+    assert_eq!(db.get_user(bettor.id).await.unwrap().balance, 950);
+    assert_eq!(db.get_wagers_for_bet(created.id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn conformance_apply_removal() {
+    let db = setup_database().await;
+    let (market, creator) = seeded(&db, "REMOVE").await;
+    let leaver = db
+        .create_user(user(market.id, "device-b", now()))
+        .await
+        .unwrap();
+    let carries_on = db
+        .create_bet(bet(market.id, creator.id, vec![]))
+        .await
+        .unwrap();
+    let about_leaver = db
+        .create_bet(bet(market.id, creator.id, vec![leaver.id]))
+        .await
+        .unwrap();
+    let own = db
+        .create_wager(wager(carries_on.id, leaver.id, 30, now()))
+        .await
+        .unwrap();
+    let other = db
+        .create_wager(wager(about_leaver.id, creator.id, 20, now()))
+        .await
+        .unwrap();
+
+    db.apply_removal(&Removal {
+        user_id: leaver.id,
+        status: MembershipStatus::Kicked,
+        refunds: vec![own.clone(), other.clone()],
+        voided_bet_ids: vec![about_leaver.id],
+    })
+    .await
+    .unwrap();
+
+    // Both wagers stay, marked refunded, and their stakes are credited
+    for (bet_id, wager_id) in [(carries_on.id, own.id), (about_leaver.id, other.id)] {
+        let wagers = db.get_wagers_for_bet(bet_id).await.unwrap();
+        assert_eq!(wagers.len(), 1);
+        assert_eq!(wagers[0].id, wager_id);
+        assert!(wagers[0].refunded);
+    }
+    let leaver = db.get_user(leaver.id).await.unwrap();
+    assert_eq!(leaver.balance, 1030);
+    assert_eq!(leaver.status, MembershipStatus::Kicked);
+    assert_eq!(db.get_user(creator.id).await.unwrap().balance, 1020);
+
+    // The open bet loses the stake; the voided one keeps its pools
+    let carries_on = db.get_bet(carries_on.id).await.unwrap();
+    assert_eq!((carries_on.yes_pool, carries_on.no_pool), (70, 100));
+    assert_eq!(carries_on.status, BetStatus::Active);
+    let about_leaver = db.get_bet(about_leaver.id).await.unwrap();
+    assert_eq!((about_leaver.yes_pool, about_leaver.no_pool), (100, 100));
+    assert_eq!(about_leaver.status, BetStatus::Void);
+}
+
+#[tokio::test]
+async fn conformance_import_archive() {
+    let db = setup_database().await;
+    let known = db
+        .create_player(Player {
+            id: Uuid::new_v4(),
+            display_name: "Grandma".to_string(),
+            avatar: "👵".to_string(),
+            created_at: now(),
+        })
+        .await
+        .unwrap();
+    let new_player = Player {
+        id: Uuid::new_v4(),
+        display_name: "Grandpa".to_string(),
+        avatar: "👴".to_string(),
+        created_at: now(),
+    };
+    let season = Season {
+        id: Uuid::new_v4(),
+        name: "Holidays".to_string(),
+        carry_over_balances: true,
+        created_at: now(),
+    };
+
+    let market = market("IMPORT");
+    let mut admin = user(market.id, "device-a", now());
+    admin.is_admin = true;
+    admin.player_id = Some(known.id);
+    let mut subject = user(market.id, "device-b", now());
+    subject.player_id = Some(new_player.id);
+    let bet = bet(market.id, admin.id, vec![subject.id]);
+    let mut refunded = wager(bet.id, admin.id, 40, now());
+    refunded.refunded = true;
+    let spectator = Spectator {
+        id: Uuid::new_v4(),
+        market_id: market.id,
+        display_name: "Neighbour".to_string(),
+        token: "import-token".to_string(),
+        joined_at: now(),
+    };
+    let archive = MarketArchive {
+        version: ARCHIVE_VERSION,
+        exported_at: now(),
+        market: market.clone(),
+        users: vec![admin.clone(), subject.clone()],
+        bets: vec![bet.clone()],
+        wagers: vec![refunded.clone()],
+        spectators: vec![spectator.clone()],
+        reveal_ceremony: Some(RevealCeremony {
+            market_id: market.id,
+            bet_ids: vec![bet.id],
+            revealed_count: 1,
+            started_at: now(),
+            completed_at: None,
+        }),
+        players: vec![known.clone(), new_player.clone()],
+        season: Some(SeasonMembership {
+            season: season.clone(),
+            position: 3,
+        }),
+        carry_overs: vec![CarryOver {
+            user_id: subject.id,
+            amount: -150,
+        }],
+    };
+
+    db.import_archive(&archive).await.unwrap();
+
+    // Every row comes back under its archived id
+    let fetched = db.get_market(market.id).await.unwrap();
+    assert_eq!(fetched.invite_code, "IMPORT");
+    assert_eq!(fetched.created_by, market.created_by);
+    let users = db.get_users_in_market(market.id).await.unwrap();
+    assert_eq!(users.len(), 2);
+    assert!(db.get_user(admin.id).await.unwrap().is_admin);
+    assert_eq!(
+        db.get_user(subject.id).await.unwrap().player_id,
+        Some(new_player.id)
+    );
+    assert_eq!(
+        db.get_bet(bet.id).await.unwrap().subject_user_ids,
+        vec![subject.id]
+    );
+    let wagers = db.get_wagers_for_bet(bet.id).await.unwrap();
+    assert_eq!(wagers.len(), 1);
+    assert_eq!(wagers[0].id, refunded.id);
+    assert!(wagers[0].refunded);
+    assert_eq!(
+        db.get_spectator_by_token("import-token").await.unwrap().id,
+        spectator.id
+    );
+    assert_eq!(
+        db.get_reveal_ceremony(market.id)
+            .await
+            .unwrap()
+            .revealed_count,
+        1
+    );
+
+    // Missing players and the season are created; known ones are kept as is
+    assert_eq!(
+        db.get_player(new_player.id).await.unwrap().display_name,
+        "Grandpa"
+    );
+    assert_eq!(
+        db.get_player(known.id).await.unwrap().display_name,
+        "Grandma"
+    );
+    assert_eq!(db.get_season(season.id).await.unwrap().name, "Holidays");
+    assert_eq!(
+        db.get_season_for_market(market.id).await.unwrap().id,
+        season.id
+    );
+    assert_eq!(
+        db.get_carry_overs(season.id).await.unwrap(),
+        vec![(subject.id, -150)]
+    );
+
+    // The same ids can't be imported twice
+    assert!(db.import_archive(&archive).await.is_err());
+    assert_eq!(db.get_users_in_market(market.id).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn conformance_reveal_ceremony() {
+    let db = setup_database().await;
+    let (market, _) = seeded(&db, "REVEAL").await;
+    let bet_ids = vec![Uuid::new_v4(), Uuid::new_v4()];
+    let started_at = now();
+    db.create_reveal_ceremony(RevealCeremony {
+        market_id: market.id,
+        bet_ids: bet_ids.clone(),
+        revealed_count: 0,
+        started_at,
+        completed_at: None,
+    })
+    .await
+    .unwrap();
+
+    let fetched = db.get_reveal_ceremony(market.id).await.unwrap();
+    assert_eq!(fetched.bet_ids, bet_ids);
+    assert_eq!(fetched.revealed_count, 0);
+    assert_eq!(fetched.started_at, started_at);
+    assert!(is_not_found(db.get_reveal_ceremony(Uuid::new_v4()).await));
+
+    let completed_at = now();
+    db.update_reveal_progress(market.id, 2, Some(completed_at))
+        .await
+        .unwrap();
+    let fetched = db.get_reveal_ceremony(market.id).await.unwrap();
+    assert_eq!(fetched.revealed_count, 2);
+    assert_eq!(fetched.completed_at, Some(completed_at));
+
+    // One ceremony per market
+    assert!(db
+        .create_reveal_ceremony(RevealCeremony {
+            market_id: market.id,
+            bet_ids: vec![],
+            revealed_count: 0,
+            started_at: now(),
+            completed_at: None,
+        })
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn conformance_delete_market_cascades() {
+    let db = setup_database().await;
+    let (market, creator) = seeded(&db, "CASCADE").await;
+    let subject = db
+        .create_user(user(market.id, "device-s", now()))
+        .await
+        .unwrap();
+    let doomed = db
+        .create_bet(bet(market.id, creator.id, vec![subject.id]))
+        .await
+        .unwrap();
+    db.create_wager(wager(doomed.id, creator.id, 10, now()))
+        .await
+        .unwrap();
+    db.create_spectator(Spectator {
+        id: Uuid::new_v4(),
+        market_id: market.id,
+        display_name: "Neighbour".to_string(),
+        token: "cascade-token".to_string(),
+        joined_at: now(),
+    })
+    .await
+    .unwrap();
+    db.create_reveal_ceremony(RevealCeremony {
+        market_id: market.id,
+        bet_ids: vec![doomed.id],
+        revealed_count: 0,
+        started_at: now(),
+        completed_at: None,
+    })
+    .await
+    .unwrap();
+    let hook = db
+        .create_webhook(webhook(market.id, "https://doomed.example/hook", now()))
+        .await
+        .unwrap();
+    db.create_webhook_delivery(delivery(hook.id, 1, now()))
+        .await
+        .unwrap();
+    let season = db
+        .create_season(Season {
+            id: Uuid::new_v4(),
+            name: "Doomed".to_string(),
+            carry_over_balances: true,
+            created_at: now(),
+        })
+        .await
+        .unwrap();
+    db.add_season_market(season.id, market.id, 0).await.unwrap();
+    db.create_carry_over(market.id, creator.id, 100)
+        .await
+        .unwrap();
+
+    // A neighbouring market is left alone
+    let (kept, kept_user) = seeded(&db, "KEPT01").await;
+
+    db.delete_market(market.id).await.unwrap();
+
+    assert!(is_not_found(db.get_market(market.id).await));
+    assert!(is_not_found(db.get_user(creator.id).await));
+    assert!(is_not_found(db.get_bet(doomed.id).await));
+    assert!(db.get_wagers_for_bet(doomed.id).await.unwrap().is_empty());
+    assert!(is_not_found(
+        db.get_spectator_by_token("cascade-token").await
+    ));
+    assert!(is_not_found(db.get_reveal_ceremony(market.id).await));
+    assert!(is_not_found(db.get_webhook(hook.id).await));
+    assert!(db.get_webhook_deliveries(hook.id).await.unwrap().is_empty());
+    assert!(db.get_season_markets(season.id).await.unwrap().is_empty());
+    assert!(db.get_carry_overs(season.id).await.unwrap().is_empty());
+    assert!(db.get_market_by_invite_code("CASCADE").await.is_err());
+
+    assert_eq!(db.get_market(kept.id).await.unwrap().invite_code, "KEPT01");
+    assert!(db.get_user(kept_user.id).await.is_ok());
+}
+
+#[tokio::test]
+async fn conformance_updates_to_missing_rows_are_no_ops() {
+    let db = setup_database().await;
+    let missing = Uuid::new_v4();
+
+    db.update_market_status(missing, MarketStatus::Open)
+        .await
+        .unwrap();
+    db.update_user_balance(missing, 10).await.unwrap();
+    db.update_user_status(missing, MembershipStatus::Left)
+        .await
+        .unwrap();
+    db.update_user_profile(missing, "Nobody", "👻")
+        .await
+        .unwrap();
+    db.update_bet_status(missing, BetStatus::Void)
+        .await
+        .unwrap();
+    db.update_bet_pools(missing, 1, 1).await.unwrap();
+    db.update_reveal_progress(missing, 1, None).await.unwrap();
+    db.apply_removal(&Removal {
+        user_id: missing,
+        status: MembershipStatus::Left,
+        refunds: Vec::new(),
+        voided_bet_ids: vec![missing],
+    })
+    .await
+    .unwrap();
+    db.delete_webhook(missing).await.unwrap();
+    db.delete_market(missing).await.unwrap();
+}
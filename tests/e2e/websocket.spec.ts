@@ -152,7 +152,7 @@ test('websocket broadcasts user_joined event', async ({ browser, baseURL }) => {
   await Promise.all([adminContext.close(), userContext.close()]);
 });
 
-test('websocket broadcasts market_opened event', async ({ browser, baseURL }) => {
+test('websocket broadcasts market_status_changed when the market opens', async ({ browser, baseURL }) => {
   const url = baseURL ?? LOCAL_URL;
 
   // Create market
@@ -194,10 +194,10 @@ test('websocket broadcasts market_opened event', async ({ browser, baseURL }) =>
   await adminPage.getByRole('button', { name: 'Open Market' }).click();
   await adminPage.waitForSelector('#market-screen.active');
 
-  // User should receive market_opened WebSocket message and auto-transition to market screen
-  const marketOpenedMsg = await waitForWebSocketMessage(userPage, 'market_opened', 10000);
+  // User should receive market_status_changed and auto-transition to market screen
+  const marketOpenedMsg = await waitForWebSocketMessage(userPage, 'market_status_changed', 10000);
   expect(marketOpenedMsg).toBeTruthy();
-  expect(marketOpenedMsg.type).toBe('market_opened');
+  expect(marketOpenedMsg.status).toBe('open');
 
   // User should automatically transition to market screen
   await userPage.waitForSelector('#market-screen.active', { timeout: 5000 });
@@ -434,15 +434,15 @@ test('multiple clients receive same websocket broadcast', async ({ browser, base
     });
   }
 
-  // Admin opens market - all clients should receive market_opened
+  // Admin opens market - all clients should receive market_status_changed
   await adminPage.getByRole('button', { name: 'Open Market' }).click();
   await adminPage.waitForSelector('#market-screen.active');
 
   // Verify all clients received the broadcast
   for (let i = 0; i < clients.length; i++) {
-    const msg = await waitForWebSocketMessage(clients[i].page, 'market_opened', 10000);
+    const msg = await waitForWebSocketMessage(clients[i].page, 'market_status_changed', 10000);
     expect(msg).toBeTruthy();
-    expect(msg.type).toBe('market_opened');
+    expect(msg.status).toBe('open');
 
     // All should transition to market screen
     await clients[i].page.waitForSelector('#market-screen.active');
@@ -2,460 +2,449 @@
 
 /// Integration tests for Cazino betting engine
 /// These test full user workflows end-to-end
-use cazino::db::SqliteDatabase;
-use cazino::domain::models::{BetStatus, MarketStatus, Side};
-use cazino::service::{CazinoService, CreateMarketParams};
+#[cfg(not(feature = "postgres"))]
+use cazino::db::{migrations, Database, SqliteDatabase};
+#[cfg(feature = "postgres")]
+use cazino::domain::models::Side;
+use cazino::service::CazinoService;
+#[cfg(feature = "postgres")]
+use cazino::service::CreateMarketParams;
 use std::sync::Arc;
+#[cfg(not(feature = "postgres"))]
+use uuid::Uuid;
+
+mod conformance;
+mod suite;
+
+/// Backend the suite runs against: SQLite, or Postgres with `--features postgres`
+#[cfg(not(feature = "postgres"))]
+type TestDatabase = SqliteDatabase;
+#[cfg(feature = "postgres")]
+type TestDatabase = cazino::db::PostgresDatabase;
+
+/// Helper to create a service over a fresh test database
+async fn setup_test_db() -> CazinoService<TestDatabase> {
+    CazinoService::new(Arc::new(setup_database().await))
+}
 
 /// Helper to create an in-memory test database
-async fn setup_test_db() -> CazinoService<SqliteDatabase> {
+#[cfg(not(feature = "postgres"))]
+async fn setup_database() -> TestDatabase {
     let db = SqliteDatabase::new("sqlite::memory:").await.unwrap();
     db.run_migrations().await.unwrap();
-    CazinoService::new(Arc::new(db))
+    db
 }
 
-#[tokio::test]
-async fn test_full_market_lifecycle() {
-    let service = setup_test_db().await;
+/// Helper to create a fresh schema in the Postgres test database
+///
+/// Uses `CAZINO_TEST_POSTGRES_URL` (default: local `cazino_test`); each test
+/// gets its own schema so tests can run in parallel.
+#[cfg(feature = "postgres")]
+async fn setup_database() -> TestDatabase {
+    let url = std::env::var("CAZINO_TEST_POSTGRES_URL")
+        .unwrap_or_else(|_| "postgres://postgres@localhost/cazino_test".to_string());
+    let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+
+    let admin = sqlx::PgPool::connect(&url).await.unwrap();
+    sqlx::query(&format!("CREATE SCHEMA {}", schema))
+        .execute(&admin)
+        .await
+        .unwrap();
+    admin.close().await;
+
+    let separator = if url.contains('?') { '&' } else { '?' };
+    let db = TestDatabase::new(&format!(
+        "{}{}options=-c%20search_path%3D{}",
+        url, separator, schema
+    ))
+    .await
+    .unwrap();
+    db.run_migrations().await.unwrap();
+    db
+}
 
-    // 1. Create market
-    let (market, admin) = service
-        .create_market(CreateMarketParams {
-            name: "Test Market".to_string(),
-            admin_device_id: "admin-device".to_string(),
-            admin_name: "Admin".to_string(),
-            admin_avatar: "👑".to_string(),
-            starting_balance: 1000,
-            duration_hours: 24,
-            custom_invite_code: None,
+/// Every table, index and its SQL (whitespace collapsed), for comparing schemas
+#[cfg(not(feature = "postgres"))]
+async fn schema_of(pool: &sqlx::SqlitePool) -> Vec<(String, String, Option<String>)> {
+    let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(
+        "SELECT type, name, sql FROM sqlite_master \
+         WHERE name NOT LIKE 'sqlite_%' \
+         AND name NOT IN ('_sqlx_migrations', 'schema_migrations') \
+         ORDER BY type, name",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap();
+    rows.into_iter()
+        .map(|(kind, name, sql)| {
+            let sql = sql.map(|sql| sql.split_whitespace().collect::<Vec<_>>().join(" "));
+            (kind, name, sql)
         })
-        .await
-        .unwrap();
-
-    assert_eq!(market.name, "Test Market");
-    assert_eq!(market.status, MarketStatus::Draft);
-    assert_eq!(admin.balance, 1000);
-    assert!(admin.is_admin);
-
-    // 2. Join as users
-    let (_, alice) = service
-        .join_market(
-            market.invite_code.clone(),
-            "alice-device".to_string(),
-            "Alice".to_string(),
-            "👩".to_string(),
-        )
-        .await
-        .unwrap();
-
-    let (_, bob) = service
-        .join_market(
-            market.invite_code.clone(),
-            "bob-device".to_string(),
-            "Bob".to_string(),
-            "👨".to_string(),
-        )
-        .await
-        .unwrap();
-
-    assert_eq!(alice.balance, 1000);
-    assert!(!alice.is_admin);
-    assert_eq!(bob.balance, 1000);
-
-    // 3. Open market
-    service.open_market(market.id, admin.id).await.unwrap();
-    let market = service.get_market(market.id).await.unwrap();
-    assert_eq!(market.status, MarketStatus::Open);
-
-    // 4. Create bet about Bob
-    let bet = service
-        .create_bet(
-            market.id,
-            alice.id,
-            bob.id,
-            "Bob will fall asleep".to_string(),
-            "1:1".to_string(),
-            100,
-            false,
-        )
-        .await
-        .unwrap();
-
-    assert_eq!(bet.status, BetStatus::Active);
-    assert_eq!(bet.yes_pool, 100);
-    assert_eq!(bet.no_pool, 0);
-
-    // Alice's balance should be reduced
-    let alice = service.get_user(alice.id).await.unwrap();
-    assert_eq!(alice.balance, 900);
-
-    // 6. Admin places wager
-    let wager = service
-        .place_wager(bet.id, admin.id, Side::No, 200)
-        .await
-        .unwrap();
-
-    assert_eq!(wager.amount, 200);
-    assert_eq!(wager.yes_pool_after, 100);
-    assert_eq!(wager.no_pool_after, 200);
-    // Probability should be 100/300 = 0.333...
-    assert!((wager.probability_after - 0.333).abs() < 0.01);
-
-    // 7. Resolve bet (YES wins)
-    let payouts = service
-        .resolve_bet(bet.id, admin.id, Side::Yes)
-        .await
-        .unwrap();
-
-    // Alice wagered 100 on YES (as creator), admin wagered 200 on NO
-    // Total pool: 300
-    // Alice gets: (100/100) × 300 = 300 coins
-    // Note: Alice is the only YES bettor (creators always bet YES)
-    assert_eq!(payouts.len(), 1);
-    assert_eq!(payouts[0].0, alice.id);
-    assert_eq!(payouts[0].1, 300);
+        .collect()
+}
 
-    // Check Alice's final balance
-    let alice = service.get_user(alice.id).await.unwrap();
-    assert_eq!(alice.balance, 1200); // 900 + 300
+/// A plain SQLite pool driven the way D1 is, one statement at a time with
+/// each batch in a transaction, so the worker's runner is checked natively
+///
+/// D1 honours `defer_foreign_keys` (see 004) by checking foreign keys when a
+/// batch commits. Plain SQLite's deferred counter can't see a rebuilt table
+/// take the old one's place, so here checks are off during the batch and run
+/// before it commits.
+#[cfg(not(feature = "postgres"))]
+struct D1Like(sqlx::SqlitePool);
+
+#[cfg(not(feature = "postgres"))]
+#[async_trait::async_trait]
+impl migrations::MigrationTarget for D1Like {
+    type Error = sqlx::Error;
+
+    async fn texts(&self, sql: &str) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(sql).fetch_all(&self.0).await
+    }
+
+    async fn integers(&self, sql: &str) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar(sql).fetch_all(&self.0).await
+    }
+
+    async fn batch(&self, statements: Vec<migrations::Statement>) -> Result<(), sqlx::Error> {
+        let mut conn = self.0.acquire().await?;
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await?;
+        let result = batch(&mut conn, statements).await;
+        sqlx::query("PRAGMA foreign_keys = ON")
+            .execute(&mut *conn)
+            .await?;
+        result
+    }
+}
 
-    // 8. Close market
-    service.close_market(market.id, admin.id).await.unwrap();
-    let market = service.get_market(market.id).await.unwrap();
-    assert_eq!(market.status, MarketStatus::Closed);
+/// Run a `D1Like` batch in one transaction, failing on broken foreign keys
+#[cfg(not(feature = "postgres"))]
+async fn batch(
+    conn: &mut sqlx::SqliteConnection,
+    statements: Vec<migrations::Statement>,
+) -> Result<(), sqlx::Error> {
+    use sqlx::Connection;
+
+    let mut tx = conn.begin().await?;
+    for statement in statements {
+        match statement {
+            migrations::Statement::Sql(sql) => {
+                sqlx::query(&sql).execute(&mut *tx).await?;
+            }
+            migrations::Statement::Record {
+                version,
+                description,
+                applied_at,
+            } => {
+                sqlx::query(migrations::INSERT_SCHEMA_MIGRATION)
+                    .bind(version)
+                    .bind(description)
+                    .bind(applied_at)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+    }
+
+    let violations = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(&mut *tx)
+        .await?;
+    if !violations.is_empty() {
+        return Err(sqlx::Error::Protocol(format!(
+            "{} rows break foreign keys",
+            violations.len()
+        )));
+    }
+    tx.commit().await
 }
 
+#[cfg(not(feature = "postgres"))]
 #[tokio::test]
-async fn test_hidden_bet_mechanics() {
-    let service = setup_test_db().await;
-
-    // Create market and users
-    let (market, admin) = service
-        .create_market(CreateMarketParams {
-            name: "Hidden Bet Test".to_string(),
-            admin_device_id: "admin-device".to_string(),
-            admin_name: "Admin".to_string(),
-            admin_avatar: "👑".to_string(),
-            starting_balance: 1000,
-            duration_hours: 24,
-            custom_invite_code: None,
-        })
-        .await
-        .unwrap();
-
-    let (_, alice) = service
-        .join_market(
-            market.invite_code.clone(),
-            "alice-device".to_string(),
-            "Alice".to_string(),
-            "👩".to_string(),
-        )
-        .await
-        .unwrap();
-
-    let (_, bob) = service
-        .join_market(
-            market.invite_code.clone(),
-            "bob-device".to_string(),
-            "Bob".to_string(),
-            "👨".to_string(),
-        )
-        .await
-        .unwrap();
-
-    service.open_market(market.id, admin.id).await.unwrap();
-
-    // Create bet about Bob
-    let bet = service
-        .create_bet(
-            market.id,
-            alice.id,
-            bob.id,
-            "Bob secret bet".to_string(),
-            "1:1".to_string(),
-            100,
-            true, // hidden from subject
-        )
-        .await
-        .unwrap();
-
-    service.approve_bet(bet.id, admin.id).await.unwrap();
-
-    // Test 1: Bob can't see the bet description
-    let bets = service.get_bets(market.id, bob.id).await.unwrap();
-    assert_eq!(bets.len(), 1);
-    assert!(bets[0].is_hidden);
-    assert!(bets[0].description.is_none());
-
-    // Test 2: Alice CAN see the bet
-    let bets = service.get_bets(market.id, alice.id).await.unwrap();
-    assert_eq!(bets.len(), 1);
-    assert!(!bets[0].is_hidden);
-    assert_eq!(bets[0].description, Some("Bob secret bet".to_string()));
-
-    // Test 3: Bob can't wager on bet about himself
-    let result = service.place_wager(bet.id, bob.id, Side::Yes, 100).await;
-    assert!(result.is_err());
-    assert!(result
-        .unwrap_err()
-        .to_string()
-        .contains("Cannot bet on bets about yourself"));
-
-    // Test 4: After resolution, Bob CAN see the bet
-    service
-        .resolve_bet(bet.id, admin.id, Side::Yes)
-        .await
-        .unwrap();
-
-    let bets = service.get_bets(market.id, bob.id).await.unwrap();
-    assert!(!bets[0].is_hidden); // No longer hidden
-    assert!(bets[0].description.is_some());
+async fn test_migration_paths_produce_identical_schemas() {
+    let path = std::env::temp_dir().join(format!("cazino-{}.db", Uuid::new_v4()));
+    let url = format!("sqlite://{}?mode=rwc", path.display());
+
+    // Local path: sqlx's migrator runs each file whole
+    let db = SqliteDatabase::new(&url).await.unwrap();
+    let applied = db.run_migrations().await.unwrap();
+    assert_eq!(applied.len(), migrations::MIGRATIONS.len());
+    assert!(db.run_migrations().await.unwrap().is_empty());
+    assert!(db
+        .migration_status()
+        .await
+        .unwrap()
+        .iter()
+        .all(|m| m.applied_at.is_some()));
+
+    // D1 path: the worker's runner, each file split into statements
+    let d1 = D1Like(sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap());
+    let applied = migrations::run_migrations(&d1).await.unwrap();
+    assert_eq!(applied.len(), migrations::MIGRATIONS.len());
+    assert!(migrations::run_migrations(&d1).await.unwrap().is_empty());
+
+    let local = sqlx::SqlitePool::connect(&url).await.unwrap();
+    assert_eq!(schema_of(&local).await, schema_of(&d1.0).await);
+
+    local.close().await;
+    drop(db);
+    let _ = std::fs::remove_file(path);
 }
 
+#[cfg(not(feature = "postgres"))]
 #[tokio::test]
-async fn test_parimutuel_payout_calculation() {
-    let service = setup_test_db().await;
+async fn test_d1_runner_carries_over_wrangler_migrations() {
+    // Early D1 deployments ran 001 with `wrangler d1 migrations apply`
+    let d1 = D1Like(sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap());
+    sqlx::query(
+        "CREATE TABLE d1_migrations (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT UNIQUE, \
+         applied_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP)",
+    )
+    .execute(&d1.0)
+    .await
+    .unwrap();
+    sqlx::query("INSERT INTO d1_migrations (name) VALUES ('001_initial_schema.sql')")
+        .execute(&d1.0)
+        .await
+        .unwrap();
+    sqlx::raw_sql(migrations::MIGRATIONS[0].sql)
+        .execute(&d1.0)
+        .await
+        .unwrap();
+
+    let applied: Vec<i64> = migrations::run_migrations(&d1)
+        .await
+        .unwrap()
+        .iter()
+        .map(|m| m.version)
+        .collect();
+    assert_eq!(
+        applied,
+        (2..=migrations::MIGRATIONS.len() as i64).collect::<Vec<_>>()
+    );
+}
 
-    let (market, admin) = service
-        .create_market(CreateMarketParams {
-            name: "Payout Test".to_string(),
-            admin_device_id: "admin-device".to_string(),
-            admin_name: "Admin".to_string(),
-            admin_avatar: "👑".to_string(),
-            starting_balance: 2000,
-            duration_hours: 24,
-            custom_invite_code: None,
-        })
-        .await
-        .unwrap();
+/// The schema `SqliteDatabase::run_migrations` created inline before
+/// versioned migrations, verbatim
+#[cfg(not(feature = "postgres"))]
+const INLINE_SCHEMA: &str = r#"
+    CREATE TABLE IF NOT EXISTS markets (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        status TEXT NOT NULL,
+        created_by TEXT NOT NULL,
+        opens_at TEXT NOT NULL,
+        closes_at TEXT NOT NULL,
+        starting_balance INTEGER NOT NULL,
+        invite_code TEXT NOT NULL UNIQUE,
+        created_at TEXT NOT NULL
+    );
 
-    let (_, alice) = service
-        .join_market(
-            market.invite_code.clone(),
-            "alice-device".to_string(),
-            "Alice".to_string(),
-            "👩".to_string(),
-        )
-        .await
-        .unwrap();
+    CREATE TABLE IF NOT EXISTS users (
+        id TEXT PRIMARY KEY,
+        market_id TEXT NOT NULL,
+        device_id TEXT NOT NULL,
+        display_name TEXT NOT NULL,
+        avatar TEXT NOT NULL,
+        balance INTEGER NOT NULL,
+        is_admin INTEGER NOT NULL,
+        joined_at TEXT NOT NULL,
+        FOREIGN KEY (market_id) REFERENCES markets(id),
+        UNIQUE(market_id, device_id)
+    );
 
-    let (_, bob) = service
-        .join_market(
-            market.invite_code.clone(),
-            "bob-device".to_string(),
-            "Bob".to_string(),
-            "👨".to_string(),
-        )
-        .await
-        .unwrap();
+    CREATE TABLE IF NOT EXISTS bets (
+        id TEXT PRIMARY KEY,
+        market_id TEXT NOT NULL,
+        subject_user_id TEXT NOT NULL,
+        created_by TEXT NOT NULL,
+        description TEXT NOT NULL,
+        initial_odds TEXT NOT NULL,
+        status TEXT NOT NULL,
+        yes_pool INTEGER NOT NULL,
+        no_pool INTEGER NOT NULL,
+        hide_from_subject INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL,
+        resolved_at TEXT,
+        FOREIGN KEY (market_id) REFERENCES markets(id),
+        FOREIGN KEY (subject_user_id) REFERENCES users(id),
+        FOREIGN KEY (created_by) REFERENCES users(id)
+    );
 
-    let (_, carol) = service
-        .join_market(
-            market.invite_code.clone(),
-            "carol-device".to_string(),
-            "Carol".to_string(),
-            "👵".to_string(),
-        )
-        .await
-        .unwrap();
+    CREATE TABLE IF NOT EXISTS wagers (
+        id TEXT PRIMARY KEY,
+        bet_id TEXT NOT NULL,
+        user_id TEXT NOT NULL,
+        side TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        placed_at TEXT NOT NULL,
+        yes_pool_after INTEGER NOT NULL,
+        no_pool_after INTEGER NOT NULL,
+        probability_after REAL NOT NULL,
+        FOREIGN KEY (bet_id) REFERENCES bets(id),
+        FOREIGN KEY (user_id) REFERENCES users(id)
+    );
 
-    service.open_market(market.id, admin.id).await.unwrap();
+    CREATE INDEX IF NOT EXISTS idx_users_market ON users(market_id);
+    CREATE INDEX IF NOT EXISTS idx_users_device ON users(market_id, device_id);
+    CREATE INDEX IF NOT EXISTS idx_bets_market ON bets(market_id);
+    CREATE INDEX IF NOT EXISTS idx_bets_status ON bets(status);
+    CREATE INDEX IF NOT EXISTS idx_bets_subject ON bets(subject_user_id);
+    CREATE INDEX IF NOT EXISTS idx_wagers_bet ON wagers(bet_id);
+    CREATE INDEX IF NOT EXISTS idx_wagers_user ON wagers(user_id);
+"#;
+
+/// Create a database at `url` from pre-migration DDL with one bet in it,
+/// returning the bet's id and its subject
+#[cfg(not(feature = "postgres"))]
+async fn legacy_database(url: &str, ddl: &str, hide_from_subject: bool) -> (Uuid, Uuid) {
+    let (market, alice, bob, bet) = (
+        Uuid::new_v4(),
+        Uuid::new_v4(),
+        Uuid::new_v4(),
+        Uuid::new_v4(),
+    );
 
-    // Create and approve bet
-    let bet = service
-        .create_bet(
-            market.id,
-            admin.id,
-            alice.id,
-            "Test bet".to_string(),
-            "1:1".to_string(),
-            100,
-            false,
+    let legacy = sqlx::SqlitePool::connect(url).await.unwrap();
+    sqlx::query(ddl).execute(&legacy).await.unwrap();
+    sqlx::query(
+        "INSERT INTO markets VALUES (?, 'Old', 'open', ?, '2024-01-01T00:00:00+00:00', \
+         '2024-01-02T00:00:00+00:00', 1000, 'OLD123', '2024-01-01T00:00:00+00:00')",
+    )
+    .bind(market.to_string())
+    .bind(alice.to_string())
+    .execute(&legacy)
+    .await
+    .unwrap();
+    for (user, device, is_admin) in [(alice, "alice-device", 1), (bob, "bob-device", 0)] {
+        sqlx::query(
+            "INSERT INTO users VALUES (?, ?, ?, 'Player', '🙂', 1000, ?, '2024-01-01T00:00:00+00:00')",
         )
-        .await
-        .unwrap();
-
-    service.approve_bet(bet.id, admin.id).await.unwrap();
-
-    // Place wagers:
-    // YES side: Admin (100 from creation), Bob (200), Carol (100) = 400 total
-    // NO side: (none initially)
-    service
-        .place_wager(bet.id, bob.id, Side::Yes, 200)
-        .await
-        .unwrap();
-
-    service
-        .place_wager(bet.id, carol.id, Side::Yes, 100)
-        .await
-        .unwrap();
-
-    // Add a NO bet so there's something to win from
-    service
-        .place_wager(bet.id, bob.id, Side::No, 100)
-        .await
-        .unwrap();
-
-    // Total pool: 500 (400 YES + 100 NO)
-    // If YES wins:
-    //   Admin gets: (100/400) × 500 = 125 coins
-    //   Bob gets: (200/400) × 500 = 250 coins
-    //   Carol gets: (100/400) × 500 = 125 coins
-
-    let payouts = service
-        .resolve_bet(bet.id, admin.id, Side::Yes)
-        .await
-        .unwrap();
-
-    assert_eq!(payouts.len(), 3);
-
-    // Find each user's payout
-    let admin_payout = payouts.iter().find(|(id, _)| *id == admin.id).unwrap().1;
-    let bob_payout = payouts.iter().find(|(id, _)| *id == bob.id).unwrap().1;
-    let carol_payout = payouts.iter().find(|(id, _)| *id == carol.id).unwrap().1;
-
-    assert_eq!(admin_payout, 125);
-    assert_eq!(bob_payout, 250);
-    assert_eq!(carol_payout, 125);
-
-    // Total should equal pool
-    assert_eq!(admin_payout + bob_payout + carol_payout, 500);
-
-    // Check final balances
-    let admin_final = service.get_user(admin.id).await.unwrap();
-    let bob_final = service.get_user(bob.id).await.unwrap();
-    let carol_final = service.get_user(carol.id).await.unwrap();
+        .bind(user.to_string())
+        .bind(market.to_string())
+        .bind(device)
+        .bind(is_admin)
+        .execute(&legacy)
+        .await
+        .unwrap();
+    }
+    let columns = if hide_from_subject {
+        "id, market_id, subject_user_id, created_by, description, initial_odds, status, \
+         yes_pool, no_pool, created_at, hide_from_subject"
+    } else {
+        "id, market_id, subject_user_id, created_by, description, initial_odds, status, \
+         yes_pool, no_pool, created_at"
+    };
+    let values = if hide_from_subject { ", 1" } else { "" };
+    sqlx::query(&format!(
+        "INSERT INTO bets ({}) VALUES (?, ?, ?, ?, 'Bob naps', '1:1', 'active', 100, 0, \
+         '2024-01-01T00:00:00+00:00'{})",
+        columns, values
+    ))
+    .bind(bet.to_string())
+    .bind(market.to_string())
+    .bind(bob.to_string())
+    .bind(alice.to_string())
+    .execute(&legacy)
+    .await
+    .unwrap();
+    legacy.close().await;
+
+    (bet, bob)
+}
 
-    assert_eq!(admin_final.balance, 2000 - 100 + 125); // Started 2000, bet 100, won 125
-    assert_eq!(bob_final.balance, 2000 - 200 - 100 + 250); // Started 2000, bet 200 YES + 100 NO, won 250
-    assert_eq!(carol_final.balance, 2000 - 100 + 125); // Started 2000, bet 100, won 125
+/// Start two databases from the same pre-migration DDL, migrate one with
+/// the server's runner and one with the worker's, and check both end up
+/// like a fresh database with their bet intact
+#[cfg(not(feature = "postgres"))]
+async fn migrate_from(ddl: &str, hide_from_subject: bool) -> Vec<i64> {
+    let temp = || std::env::temp_dir().join(format!("cazino-{}.db", Uuid::new_v4()));
+    let (path, d1_path) = (temp(), temp());
+    let url = format!("sqlite://{}?mode=rwc", path.display());
+    let d1_url = format!("sqlite://{}?mode=rwc", d1_path.display());
+
+    let (bet, bob) = legacy_database(&url, ddl, hide_from_subject).await;
+    let db = SqliteDatabase::new(&url).await.unwrap();
+    let applied: Vec<i64> = db
+        .run_migrations()
+        .await
+        .unwrap()
+        .iter()
+        .map(|m| m.version)
+        .collect();
+    assert!(db.run_migrations().await.unwrap().is_empty());
+    let status = db.migration_status().await.unwrap();
+    assert_eq!(status.len(), migrations::MIGRATIONS.len());
+    assert!(status.iter().all(|m| m.applied_at.is_some()));
+
+    let (d1_bet, d1_bob) = legacy_database(&d1_url, ddl, hide_from_subject).await;
+    let d1 = D1Like(sqlx::SqlitePool::connect(&d1_url).await.unwrap());
+    let d1_applied: Vec<i64> = migrations::run_migrations(&d1)
+        .await
+        .unwrap()
+        .iter()
+        .map(|m| m.version)
+        .collect();
+    assert_eq!(d1_applied, applied);
+    assert!(migrations::run_migrations(&d1).await.unwrap().is_empty());
+
+    // The bet kept its subject and visibility through the bet_subjects rebuild
+    let d1_db = SqliteDatabase::new(&d1_url).await.unwrap();
+    for (db, bet, bob) in [(&db, bet, bob), (&d1_db, d1_bet, d1_bob)] {
+        let migrated = db.get_bet(bet).await.unwrap();
+        assert_eq!(migrated.subject_user_ids, vec![bob]);
+        assert_eq!(migrated.hide_from_subject, hide_from_subject);
+    }
+
+    let fresh_pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+    migrations::MIGRATOR.run(&fresh_pool).await.unwrap();
+    let migrated_pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    assert_eq!(
+        schema_of(&migrated_pool).await,
+        schema_of(&fresh_pool).await
+    );
+    assert_eq!(schema_of(&d1.0).await, schema_of(&fresh_pool).await);
+
+    migrated_pool.close().await;
+    d1.0.close().await;
+    drop((db, d1_db));
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(d1_path);
+    applied
 }
 
+#[cfg(not(feature = "postgres"))]
 #[tokio::test]
-async fn test_insufficient_balance() {
-    let service = setup_test_db().await;
-
-    let (market, admin) = service
-        .create_market(CreateMarketParams {
-            name: "Balance Test".to_string(),
-            admin_device_id: "admin-device".to_string(),
-            admin_name: "Admin".to_string(),
-            admin_avatar: "👑".to_string(),
-            starting_balance: 100, // Small starting balance
-            duration_hours: 24,
-            custom_invite_code: None,
-        })
-        .await
-        .unwrap();
-
-    let (_, alice) = service
-        .join_market(
-            market.invite_code.clone(),
-            "alice-device".to_string(),
-            "Alice".to_string(),
-            "👩".to_string(),
-        )
-        .await
-        .unwrap();
-
-    service.open_market(market.id, admin.id).await.unwrap();
-
-    // Try to create bet with more than balance
-    let result = service
-        .create_bet(
-            market.id,
-            alice.id,
-            admin.id,
-            "Expensive bet".to_string(),
-            "1:1".to_string(),
-            200, // More than her 100 balance
-            false,
-        )
-        .await;
-
-    assert!(result.is_err());
-    assert!(result.unwrap_err().to_string().contains("Insufficient"));
+async fn test_migrations_upgrade_the_old_inline_schema() {
+    // That schema already had hide_from_subject, so it starts after version 2
+    let applied = migrate_from(INLINE_SCHEMA, true).await;
+    assert_eq!(
+        applied,
+        (3..=migrations::MIGRATIONS.len() as i64).collect::<Vec<_>>()
+    );
 }
 
+#[cfg(not(feature = "postgres"))]
 #[tokio::test]
-async fn test_probability_chart_tracking() {
-    let service = setup_test_db().await;
-
-    let (market, admin) = service
-        .create_market(CreateMarketParams {
-            name: "Chart Test".to_string(),
-            admin_device_id: "admin-device".to_string(),
-            admin_name: "Admin".to_string(),
-            admin_avatar: "👑".to_string(),
-            starting_balance: 1000,
-            duration_hours: 24,
-            custom_invite_code: None,
-        })
-        .await
-        .unwrap();
-
-    let (_, alice) = service
-        .join_market(
-            market.invite_code.clone(),
-            "alice-device".to_string(),
-            "Alice".to_string(),
-            "👩".to_string(),
-        )
-        .await
-        .unwrap();
-
-    service.open_market(market.id, admin.id).await.unwrap();
-
-    let bet = service
-        .create_bet(
-            market.id,
-            admin.id,
-            alice.id,
-            "Chart bet".to_string(),
-            "1:1".to_string(),
-            100,
-            false,
-        )
-        .await
-        .unwrap();
-
-    service.approve_bet(bet.id, admin.id).await.unwrap();
-
-    // Place multiple wagers to create chart data
-    // Note: Can't bet on bets about yourself, so admin places wagers instead
-    service
-        .place_wager(bet.id, admin.id, Side::No, 100)
-        .await
-        .unwrap();
-
-    service
-        .place_wager(bet.id, admin.id, Side::Yes, 50)
-        .await
-        .unwrap();
-
-    // Get probability chart
-    let chart = service.get_probability_chart(bet.id).await.unwrap();
-
-    // Should have 3 data points (opening wager + 2 additional wagers)
-    assert_eq!(chart.len(), 3);
-
-    // First point: 100 YES (opening wager), 0 NO = 100% YES
-    assert!((chart[0].yes_probability - 1.0).abs() < 0.01);
-
-    // Second point: 100 YES, 100 NO (first additional wager) = 50%
-    assert!((chart[1].yes_probability - 0.5).abs() < 0.01);
-
-    // Third point: 150 YES, 100 NO = 60%
-    assert!((chart[2].yes_probability - 0.6).abs() < 0.01);
+async fn test_migrations_upgrade_the_initial_schema_file() {
+    // Databases made from 001 alone (e.g. early D1 deployments) get every later file
+    let applied = migrate_from(migrations::MIGRATIONS[0].sql, false).await;
+    assert_eq!(
+        applied,
+        (2..=migrations::MIGRATIONS.len() as i64).collect::<Vec<_>>()
+    );
 }
 
+/// Concurrent wagers queue up on the locked rows instead of overwriting each other
+#[cfg(feature = "postgres")]
 #[tokio::test]
-async fn test_admin_only_actions() {
-    let service = setup_test_db().await;
+async fn test_concurrent_wagers_lock_rows() {
+    let service = Arc::new(setup_test_db().await);
 
     let (market, admin) = service
         .create_market(CreateMarketParams {
-            name: "Admin Test".to_string(),
+            name: "Race Test".to_string(),
             admin_device_id: "admin-device".to_string(),
             admin_name: "Admin".to_string(),
             admin_avatar: "👑".to_string(),
@@ -465,7 +454,6 @@ async fn test_admin_only_actions() {
         })
         .await
         .unwrap();
-
     let (_, alice) = service
         .join_market(
             market.invite_code.clone(),
@@ -475,169 +463,55 @@ async fn test_admin_only_actions() {
         )
         .await
         .unwrap();
-
     service.open_market(market.id, admin.id).await.unwrap();
-
     let bet = service
         .create_bet(
             market.id,
-            alice.id,
             admin.id,
-            "Admin bet".to_string(),
+            vec![],
+            true,
+            "Someone burns the toast".to_string(),
             "1:1".to_string(),
             100,
-            false,
+            true,
         )
         .await
         .unwrap();
 
-    // Non-admin tries to approve bet
-    let result = service.approve_bet(bet.id, alice.id).await;
-    assert!(result.is_err());
-    let err_msg = result.unwrap_err().to_string();
-    assert!(
-        err_msg.contains("Admin only") || err_msg.contains("Constraint"),
-        "Expected admin error but got: {}",
-        err_msg
-    );
-
-    // Approve as admin
-    service.approve_bet(bet.id, admin.id).await.unwrap();
-
-    // Non-admin tries to resolve bet
-    let result = service.resolve_bet(bet.id, alice.id, Side::Yes).await;
-    assert!(result.is_err());
-    let err_msg = result.unwrap_err().to_string();
-    assert!(
-        err_msg.contains("Admin only") || err_msg.contains("Constraint"),
-        "Expected admin error but got: {}",
-        err_msg
-    );
-}
-
-#[tokio::test]
-async fn test_returning_user() {
-    let service = setup_test_db().await;
-
-    let (market, _admin) = service
-        .create_market(CreateMarketParams {
-            name: "Returning User Test".to_string(),
-            admin_device_id: "admin-device".to_string(),
-            admin_name: "Admin".to_string(),
-            admin_avatar: "👑".to_string(),
-            starting_balance: 1000,
-            duration_hours: 24,
-            custom_invite_code: None,
+    // Twelve wagers of 100 from a balance of 1000: exactly ten can succeed
+    let handles: Vec<_> = (0..12)
+        .map(|_| {
+            let service = service.clone();
+            tokio::spawn(async move { service.place_wager(bet.id, alice.id, Side::Yes, 100).await })
         })
-        .await
-        .unwrap();
-
-    // Alice joins first time
-    let (_, alice1) = service
-        .join_market(
-            market.invite_code.clone(),
-            "alice-device".to_string(),
-            "Alice".to_string(),
-            "👩".to_string(),
-        )
-        .await
-        .unwrap();
+        .collect();
+    let mut placed = 0;
+    for handle in handles {
+        if handle.await.unwrap().is_ok() {
+            placed += 1;
+        }
+    }
+    assert_eq!(placed, 10);
 
-    // Alice "returns" with same device ID
-    let (_, alice2) = service
-        .join_market(
-            market.invite_code.clone(),
-            "alice-device".to_string(),  // Same device ID
-            "Alice Updated".to_string(), // Different name (should be ignored)
-            "👸".to_string(),            // Different avatar (should be ignored)
-        )
-        .await
-        .unwrap();
-
-    // Should get same user back
-    assert_eq!(alice1.id, alice2.id);
-    assert_eq!(alice2.display_name, "Alice"); // Original name preserved
-    assert_eq!(alice2.avatar, "👩"); // Original avatar preserved
-}
-
-#[tokio::test]
-async fn test_multiple_bets_same_subject() {
-    let service = setup_test_db().await;
-
-    let (market, admin) = service
-        .create_market(CreateMarketParams {
-            name: "Multiple Bets Test".to_string(),
-            admin_device_id: "admin-device".to_string(),
-            admin_name: "Admin".to_string(),
-            admin_avatar: "👑".to_string(),
-            starting_balance: 1000,
-            duration_hours: 24,
-            custom_invite_code: None,
-        })
-        .await
-        .unwrap();
-
-    let (_, alice) = service
-        .join_market(
-            market.invite_code.clone(),
-            "alice-device".to_string(),
-            "Alice".to_string(),
-            "👩".to_string(),
-        )
-        .await
-        .unwrap();
-
-    service.open_market(market.id, admin.id).await.unwrap();
-
-    // Create multiple bets about Alice
-    let bet1 = service
-        .create_bet(
-            market.id,
-            admin.id,
-            alice.id,
-            "Alice will be late".to_string(),
-            "1:1".to_string(),
-            50,
-            true, // hidden from subject
-        )
-        .await
-        .unwrap();
-
-    let bet2 = service
-        .create_bet(
-            market.id,
-            admin.id,
-            alice.id,
-            "Alice will spill drink".to_string(),
-            "1:1".to_string(),
-            50,
-            true, // hidden from subject
-        )
-        .await
-        .unwrap();
-
-    service.approve_bet(bet1.id, admin.id).await.unwrap();
-    service.approve_bet(bet2.id, admin.id).await.unwrap();
-
-    // Alice should see 2 hidden bets
-    let bets = service.get_bets(market.id, alice.id).await.unwrap();
-    assert_eq!(bets.len(), 2);
-    assert!(bets.iter().all(|b| b.is_hidden));
-
-    // After resolution, Alice can see them
-    service
-        .resolve_bet(bet1.id, admin.id, Side::Yes)
-        .await
-        .unwrap();
-    service
-        .resolve_bet(bet2.id, admin.id, Side::No)
-        .await
-        .unwrap();
+    let alice = service.get_user(alice.id).await.unwrap();
+    assert_eq!(alice.balance, 0);
 
-    let bets = service.get_bets(market.id, alice.id).await.unwrap();
-    assert!(bets.iter().all(|b| !b.is_hidden));
+    let after = service.get_bet(bet.id).await.unwrap();
+    assert_eq!(
+        after.yes_pool + after.no_pool,
+        bet.yes_pool + bet.no_pool + 1000
+    );
 
-    // Get bets about Alice for reveal screen
-    let reveal_bets = service.get_bets_about_user(alice.id).await.unwrap();
-    assert_eq!(reveal_bets.len(), 2);
+    // Every snapshot builds on the one before it, so none repeat
+    let archive = service.export_market(market.id).await.unwrap();
+    let mut pools: Vec<i64> = archive
+        .wagers
+        .iter()
+        .filter(|w| w.user_id == alice.id)
+        .map(|w| w.yes_pool_after)
+        .collect();
+    pools.sort();
+    pools.dedup();
+    assert_eq!(pools.len(), 10);
+    assert_eq!(pools.last(), Some(&after.yes_pool));
 }
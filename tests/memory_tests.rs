@@ -0,0 +1,20 @@
+#![cfg(feature = "memory")]
+
+/// The shared scenarios and conformance checks against the in-memory backend
+use cazino::db::InMemoryDatabase;
+use cazino::service::CazinoService;
+use std::sync::Arc;
+
+mod conformance;
+mod suite;
+
+type TestDatabase = InMemoryDatabase;
+
+/// Helper to create a service over an empty in-memory database
+async fn setup_test_db() -> CazinoService<TestDatabase> {
+    CazinoService::new(Arc::new(setup_database().await))
+}
+
+async fn setup_database() -> TestDatabase {
+    InMemoryDatabase::new()
+}
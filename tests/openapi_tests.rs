@@ -0,0 +1,199 @@
+#![cfg(any(feature = "server", feature = "wasm"))]
+
+/// Tests for the generated OpenAPI document and protocol schema
+///
+/// Both documents are pinned by golden files in `tests/golden/`, so a change
+/// to an API model shows up as a diff for client authors to review. After an
+/// intended change, run with `UPDATE_GOLDEN=1` to rewrite the files.
+use cazino::api::dispatch::Method;
+use cazino::api::openapi::{self, Operation};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+fn check_golden(name: &str, document: &Value) {
+    let encoded = serde_json::to_string_pretty(document).unwrap() + "\n";
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &encoded).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing golden file {}: {}", path.display(), e));
+    assert_eq!(
+        encoded, golden,
+        "{} has drifted from the API models; rerun with UPDATE_GOLDEN=1 if that's intended",
+        name
+    );
+}
+
+#[test]
+fn test_openapi_matches_golden_file() {
+    check_golden("openapi.json", &openapi::spec());
+}
+
+#[test]
+fn test_protocol_schema_matches_golden_file() {
+    check_golden("protocol.schema.json", &openapi::protocol_schema());
+}
+
+/// A route as `(method, path)` with every parameter written `{}`
+fn route(method: Method, segments: impl IntoIterator<Item = String>) -> (String, String) {
+    let path: Vec<String> = segments.into_iter().collect();
+    (format!("{:?}", method), format!("/{}", path.join("/")))
+}
+
+/// Routes matched in `dispatch`, read from its source
+fn dispatched_routes() -> BTreeSet<(String, String)> {
+    let source = include_str!("../src/api/dispatch.rs");
+    let mut routes = BTreeSet::new();
+
+    for (method, marker) in [(Method::Get, "(Get, ["), (Method::Post, "(Post, [")] {
+        for (start, _) in source.match_indices(marker) {
+            let rest = &source[start + marker.len()..];
+            let pattern = &rest[..rest.find(']').unwrap()];
+            let segments = pattern
+                .split(", ")
+                .map(|segment| match segment.strip_prefix('"') {
+                    Some(literal) => literal.trim_end_matches('"').to_string(),
+                    None => "{}".to_string(),
+                });
+            routes.insert(route(method, segments));
+        }
+    }
+
+    routes
+}
+
+fn documented_route(op: &Operation) -> (String, String) {
+    let segments = op.path.trim_start_matches('/').split('/').map(|segment| {
+        if segment.starts_with('{') {
+            "{}".to_string()
+        } else {
+            segment.to_string()
+        }
+    });
+    route(op.method, segments)
+}
+
+#[test]
+fn test_every_route_is_documented() {
+    let documented: BTreeSet<_> = openapi::operations()
+        .iter()
+        .filter(|op| !op.server_only)
+        .map(documented_route)
+        .collect();
+    let dispatched = dispatched_routes();
+
+    let undocumented: Vec<_> = dispatched.difference(&documented).collect();
+    assert!(
+        undocumented.is_empty(),
+        "routed but not in openapi::operations: {:?}",
+        undocumented
+    );
+    let unrouted: Vec<_> = documented.difference(&dispatched).collect();
+    assert!(
+        unrouted.is_empty(),
+        "documented but not routed: {:?}",
+        unrouted
+    );
+}
+
+#[test]
+fn test_schema_references_resolve() {
+    let spec = openapi::spec();
+    let schemas = spec["components"]["schemas"].as_object().unwrap();
+
+    fn refs<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(target)) = map.get("$ref") {
+                    found.push(target);
+                }
+                map.values().for_each(|v| refs(v, found));
+            }
+            Value::Array(items) => items.iter().for_each(|v| refs(v, found)),
+            _ => {}
+        }
+    }
+
+    let mut found = Vec::new();
+    refs(&spec, &mut found);
+    assert!(!found.is_empty());
+    for target in found {
+        let name = target
+            .strip_prefix("#/components/schemas/")
+            .unwrap_or_else(|| panic!("unexpected reference {}", target));
+        assert!(schemas.contains_key(name), "{} is not defined", target);
+    }
+
+    // The models clients build requests from are all there
+    for name in [
+        "CreateMarketRequest",
+        "PlaceWagerRequest",
+        "WagerResponse",
+        "WsMessage",
+    ] {
+        assert!(schemas.contains_key(name), "{} is missing", name);
+    }
+}
+
+#[test]
+fn test_path_parameters_match_templates() {
+    let spec = openapi::spec();
+
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        let expected: Vec<&str> = path
+            .split('/')
+            .filter_map(|s| s.strip_prefix('{')?.strip_suffix('}'))
+            .collect();
+        for operation in item.as_object().unwrap().values() {
+            let declared: Vec<&str> = operation["parameters"]
+                .as_array()
+                .map(|params| {
+                    params
+                        .iter()
+                        .filter(|p| p["in"] == "path")
+                        .map(|p| p["name"].as_str().unwrap())
+                        .collect()
+                })
+                .unwrap_or_default();
+            assert_eq!(declared, expected, "{}", path);
+        }
+    }
+}
+
+#[test]
+fn test_route_of_prefers_literal_segments() {
+    let id = "00000000-0000-0000-0000-000000000000";
+    let route = |method, path: String| openapi::route_of(method, &path);
+
+    assert_eq!(
+        route(Method::Get, format!("/markets/{}", id)),
+        Some("/markets/{market_id}")
+    );
+    assert_eq!(
+        route(Method::Get, format!("/markets/{}/bets/pending", id)),
+        Some("/markets/{market_id}/bets/pending")
+    );
+    assert_eq!(
+        route(Method::Get, format!("/markets/{}/bets/{}", id, id)),
+        Some("/markets/{market_id}/bets/{user_id}")
+    );
+    assert_eq!(
+        route(Method::Get, format!("/markets/{}/bets/{}/page", id, id)),
+        Some("/markets/{market_id}/bets/{user_id}/page")
+    );
+    assert_eq!(
+        route(Method::Post, "/markets/import".to_string()),
+        Some("/markets/import")
+    );
+    assert_eq!(
+        route(Method::Get, "/markets/import/nowhere".to_string()),
+        None
+    );
+}
@@ -0,0 +1,300 @@
+#![cfg(any(feature = "server", feature = "wasm"))]
+
+/// Golden-file tests for the WebSocket protocol
+///
+/// Each message type has a fixed sample whose JSON lives in
+/// `tests/golden/protocol/<type>.json`. Clients depend on this encoding, so a
+/// diff here means bumping `PROTOCOL_VERSION`. After an intended change, run
+/// with `UPDATE_GOLDEN=1` to rewrite the files.
+use cazino::api::protocol::{WsMessage, EVENT_TYPES, PROTOCOL_VERSION};
+use cazino::domain::models::{
+    BetReveal, BetStatus, BetView, Market, MarketStatus, MembershipStatus, RevealWinner, Side,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+fn id(n: u128) -> Uuid {
+    Uuid::from_u128(n)
+}
+
+fn at(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 1, 1, hour, 0, 0).unwrap()
+}
+
+/// One sample of every message, named by its `type`
+fn samples() -> Vec<(&'static str, WsMessage)> {
+    let market = Market {
+        id: id(1),
+        name: "Thanksgiving".to_string(),
+        status: MarketStatus::Open,
+        created_by: id(2),
+        opens_at: at(12),
+        closes_at: at(18),
+        starting_balance: 1000,
+        invite_code: "TURKEY".to_string(),
+        created_at: at(11),
+    };
+    let reveal = BetReveal {
+        bet: BetView {
+            id: id(3),
+            market_id: id(1),
+            is_hidden: false,
+            subject_user_ids: Some(vec![id(4)]),
+            description: Some("Alice burns the gravy".to_string()),
+            about_everyone: false,
+            created_by: id(2),
+            initial_odds: "1:1".to_string(),
+            status: BetStatus::ResolvedYes,
+            yes_pool: 150,
+            no_pool: 50,
+            created_at: at(13),
+            resolved_at: Some(at(17)),
+        },
+        outcome: Some(Side::Yes),
+        top_winners: vec![RevealWinner {
+            user_id: id(5),
+            display_name: "bob".to_string(),
+            avatar: "🦃".to_string(),
+            payout: 200,
+        }],
+    };
+
+    vec![
+        (
+            "subscribe",
+            WsMessage::Subscribe {
+                market_id: id(1),
+                protocol_version: PROTOCOL_VERSION,
+            },
+        ),
+        ("ping", WsMessage::Ping),
+        (
+            "subscribed",
+            WsMessage::Subscribed {
+                market_id: id(1),
+                protocol_version: PROTOCOL_VERSION,
+            },
+        ),
+        ("market_update", WsMessage::MarketUpdate { market }),
+        (
+            "bet_created",
+            WsMessage::BetCreated {
+                bet_id: id(3),
+                description: "Alice burns the gravy".to_string(),
+                hide_from_subject: true,
+            },
+        ),
+        ("bet_approved", WsMessage::BetApproved { bet_id: id(3) }),
+        (
+            "user_joined",
+            WsMessage::UserJoined {
+                user_id: id(4),
+                display_name: "alice".to_string(),
+                market_id: id(1),
+            },
+        ),
+        (
+            "wager_placed",
+            WsMessage::WagerPlaced {
+                bet_id: id(3),
+                user_id: id(5),
+                side: Side::Yes,
+                amount: 50,
+                new_yes_pool: 150,
+                new_no_pool: 50,
+                new_probability: 0.75,
+            },
+        ),
+        (
+            "bet_resolved",
+            WsMessage::BetResolved {
+                bet_id: id(3),
+                outcome: Side::Yes,
+                status: BetStatus::ResolvedYes,
+            },
+        ),
+        (
+            "market_status_changed",
+            WsMessage::MarketStatusChanged {
+                market_id: id(1),
+                status: MarketStatus::Closed,
+            },
+        ),
+        (
+            "market_deleted",
+            WsMessage::MarketDeleted { market_id: id(1) },
+        ),
+        (
+            "user_updated",
+            WsMessage::UserUpdated {
+                user_id: id(4),
+                display_name: "alice".to_string(),
+                avatar: "🥧".to_string(),
+            },
+        ),
+        (
+            "membership_changed",
+            WsMessage::MembershipChanged {
+                market_id: id(1),
+                user_id: id(4),
+                status: MembershipStatus::Kicked,
+            },
+        ),
+        ("bet_voided", WsMessage::BetVoided { bet_id: id(3) }),
+        (
+            "reveal_started",
+            WsMessage::RevealStarted {
+                market_id: id(1),
+                total: 3,
+            },
+        ),
+        (
+            "bet_revealed",
+            WsMessage::BetRevealed {
+                market_id: id(1),
+                reveal,
+                revealed_count: 1,
+                total: 3,
+            },
+        ),
+        ("pong", WsMessage::Pong),
+        (
+            "error",
+            WsMessage::Error {
+                message: "Unsupported protocol version 2 (this server speaks 1)".to_string(),
+            },
+        ),
+    ]
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden/protocol")
+        .join(format!("{}.json", name))
+}
+
+#[test]
+fn test_messages_match_golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+    for (name, message) in samples() {
+        let encoded = serde_json::to_string_pretty(&message).unwrap() + "\n";
+        let path = golden_path(name);
+
+        if update {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &encoded).unwrap();
+            continue;
+        }
+
+        let golden = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("missing golden file {}: {}", path.display(), e));
+        assert_eq!(
+            encoded, golden,
+            "{} no longer matches its golden file",
+            name
+        );
+
+        // The type tag is the file name, and the file decodes to the same message
+        let value: serde_json::Value = serde_json::from_str(&golden).unwrap();
+        assert_eq!(value["type"], name);
+        let decoded: WsMessage = serde_json::from_str(&golden).unwrap();
+        assert_eq!(
+            serde_json::to_string_pretty(&decoded).unwrap() + "\n",
+            golden
+        );
+    }
+}
+
+#[test]
+fn test_every_golden_file_has_a_sample() {
+    // Files are being rewritten by the test above
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        return;
+    }
+
+    let names: Vec<&str> = samples().into_iter().map(|(name, _)| name).collect();
+    let dir = golden_path("x").parent().unwrap().to_path_buf();
+
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let file_name = entry.unwrap().file_name();
+        let file_name = file_name.to_string_lossy();
+        let name = file_name.trim_end_matches(".json");
+        assert!(names.contains(&name), "stale golden file {}", file_name);
+    }
+}
+
+#[test]
+fn test_handshake() {
+    let subscribe = |version: u32| WsMessage::Subscribe {
+        market_id: id(1),
+        protocol_version: version,
+    };
+
+    match subscribe(PROTOCOL_VERSION).reply() {
+        Some(WsMessage::Subscribed {
+            market_id,
+            protocol_version,
+        }) => {
+            assert_eq!(market_id, id(1));
+            assert_eq!(protocol_version, PROTOCOL_VERSION);
+        }
+        other => panic!("expected subscribed, got {:?}", other),
+    }
+
+    assert!(matches!(
+        subscribe(PROTOCOL_VERSION + 1).reply(),
+        Some(WsMessage::Error { .. })
+    ));
+    assert!(matches!(WsMessage::Ping.reply(), Some(WsMessage::Pong)));
+
+    // Events sent by clients get no reply
+    assert!(WsMessage::BetVoided { bet_id: id(3) }.reply().is_none());
+}
+
+#[test]
+fn test_unversioned_subscribe_speaks_version_one() {
+    let message: WsMessage = serde_json::from_str(&format!(
+        r#"{{"type":"subscribe","market_id":"{}"}}"#,
+        id(1)
+    ))
+    .unwrap();
+
+    assert!(matches!(
+        message,
+        WsMessage::Subscribe {
+            protocol_version: 1,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_spectator_visibility() {
+    let created = |hide_from_subject| WsMessage::BetCreated {
+        bet_id: id(3),
+        description: "Alice burns the gravy".to_string(),
+        hide_from_subject,
+    };
+
+    assert!(created(false).is_public());
+    assert!(!created(true).is_public());
+    assert!(!WsMessage::Pong.is_public());
+    assert!(WsMessage::BetVoided { bet_id: id(3) }.is_public());
+}
+
+#[test]
+fn test_message_types() {
+    for (name, message) in samples() {
+        assert_eq!(message.message_type(), name);
+    }
+
+    // Every server event can be subscribed to; handshakes and replies can't
+    let events: Vec<&str> = samples()
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| !["subscribe", "ping", "subscribed", "pong", "error"].contains(name))
+        .collect();
+    assert_eq!(events, EVENT_TYPES);
+}
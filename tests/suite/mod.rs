@@ -0,0 +1,2630 @@
+/// End-to-end scenarios shared by every backend
+///
+/// Each test binary provides `TestDatabase` and `setup_test_db`; the
+/// scenarios below only go through `CazinoService`.
+use super::{setup_test_db, TestDatabase};
+use async_trait::async_trait;
+use cazino::api::protocol::WsMessage;
+use cazino::api::webhooks::{self, WebhookClient};
+use cazino::domain::csv::CsvReport;
+use cazino::domain::models::{BetStatus, MarketStatus, MembershipStatus, Side, User};
+use cazino::domain::{archive, rules};
+use cazino::service::{CazinoService, CreateMarketParams};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Headers and body of a webhook post
+type Posted = (Vec<(String, String)>, String);
+
+/// Answers webhook posts from a script of statuses, recording each request
+struct ScriptedClient {
+    statuses: Mutex<Vec<u16>>,
+    requests: Mutex<Vec<Posted>>,
+}
+
+impl ScriptedClient {
+    fn new(mut statuses: Vec<u16>) -> Self {
+        statuses.reverse();
+        Self {
+            statuses: Mutex::new(statuses),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl WebhookClient for ScriptedClient {
+    async fn post(
+        &self,
+        _url: &str,
+        headers: &[(&str, String)],
+        body: &str,
+    ) -> Result<u16, String> {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        self.requests
+            .lock()
+            .unwrap()
+            .push((headers, body.to_string()));
+        self.statuses
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or_else(|| "Connection refused".to_string())
+    }
+
+    async fn sleep(&self, _delay: Duration) {}
+}
+
+#[tokio::test]
+async fn test_full_market_lifecycle() {
+    let service = setup_test_db().await;
+
+    // 1. Create market
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Test Market".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(market.name, "Test Market");
+    assert_eq!(market.status, MarketStatus::Draft);
+    assert_eq!(admin.balance, 1000);
+    assert!(admin.is_admin);
+
+    // 2. Join as users
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    let (_, bob) = service
+        .join_market(
+            market.invite_code.clone(),
+            "bob-device".to_string(),
+            "Bob".to_string(),
+            "👨".to_string(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(alice.balance, 1000);
+    assert!(!alice.is_admin);
+    assert_eq!(bob.balance, 1000);
+
+    // 3. Open market
+    service.open_market(market.id, admin.id).await.unwrap();
+    let market = service.get_market(market.id).await.unwrap();
+    assert_eq!(market.status, MarketStatus::Open);
+
+    // 4. Create bet about Bob
+    let bet = service
+        .create_bet(
+            market.id,
+            alice.id,
+            vec![bob.id],
+            false,
+            "Bob will fall asleep".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(bet.status, BetStatus::Active);
+    assert_eq!(bet.yes_pool, 100);
+    assert_eq!(bet.no_pool, 0);
+
+    // Alice's balance should be reduced
+    let alice = service.get_user(alice.id).await.unwrap();
+    assert_eq!(alice.balance, 900);
+
+    // 6. Admin places wager
+    let wager = service
+        .place_wager(bet.id, admin.id, Side::No, 200)
+        .await
+        .unwrap();
+
+    assert_eq!(wager.amount, 200);
+    assert_eq!(wager.yes_pool_after, 100);
+    assert_eq!(wager.no_pool_after, 200);
+    // Probability should be 100/300 = 0.333...
+    assert!((wager.probability_after - 0.333).abs() < 0.01);
+
+    // 7. Resolve bet (YES wins)
+    let payouts = service
+        .resolve_bet(bet.id, admin.id, Side::Yes)
+        .await
+        .unwrap();
+
+    // Alice wagered 100 on YES (as creator), admin wagered 200 on NO
+    // Total pool: 300
+    // Alice gets: (100/100) × 300 = 300 coins
+    // Note: Alice is the only YES bettor (creators always bet YES)
+    assert_eq!(payouts.len(), 1);
+    assert_eq!(payouts[0].0, alice.id);
+    assert_eq!(payouts[0].1, 300);
+
+    // Check Alice's final balance
+    let alice = service.get_user(alice.id).await.unwrap();
+    assert_eq!(alice.balance, 1200); // 900 + 300
+
+    // 8. Close market
+    service.close_market(market.id, admin.id).await.unwrap();
+    let market = service.get_market(market.id).await.unwrap();
+    assert_eq!(market.status, MarketStatus::Closed);
+}
+
+#[tokio::test]
+async fn test_hidden_bet_mechanics() {
+    let service = setup_test_db().await;
+
+    // Create market and users
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Hidden Bet Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    let (_, bob) = service
+        .join_market(
+            market.invite_code.clone(),
+            "bob-device".to_string(),
+            "Bob".to_string(),
+            "👨".to_string(),
+        )
+        .await
+        .unwrap();
+
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    // Create bet about Bob
+    let bet = service
+        .create_bet(
+            market.id,
+            alice.id,
+            vec![bob.id],
+            false,
+            "Bob secret bet".to_string(),
+            "1:1".to_string(),
+            100,
+            true, // hidden from subject
+        )
+        .await
+        .unwrap();
+
+    service.approve_bet(bet.id, admin.id).await.unwrap();
+
+    // Test 1: Bob can't see the bet description
+    let bets = service.get_bets(market.id, bob.id).await.unwrap();
+    assert_eq!(bets.len(), 1);
+    assert!(bets[0].is_hidden);
+    assert!(bets[0].description.is_none());
+
+    // Test 2: Alice CAN see the bet
+    let bets = service.get_bets(market.id, alice.id).await.unwrap();
+    assert_eq!(bets.len(), 1);
+    assert!(!bets[0].is_hidden);
+    assert_eq!(bets[0].description, Some("Bob secret bet".to_string()));
+
+    // Test 3: Bob can't wager on bet about himself
+    let result = service.place_wager(bet.id, bob.id, Side::Yes, 100).await;
+    assert!(result.is_err());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Cannot bet on bets about yourself"));
+
+    // Test 4: After resolution, Bob CAN see the bet
+    service
+        .resolve_bet(bet.id, admin.id, Side::Yes)
+        .await
+        .unwrap();
+
+    let bets = service.get_bets(market.id, bob.id).await.unwrap();
+    assert!(!bets[0].is_hidden); // No longer hidden
+    assert!(bets[0].description.is_some());
+}
+
+#[tokio::test]
+async fn test_parimutuel_payout_calculation() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Payout Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 2000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    let (_, bob) = service
+        .join_market(
+            market.invite_code.clone(),
+            "bob-device".to_string(),
+            "Bob".to_string(),
+            "👨".to_string(),
+        )
+        .await
+        .unwrap();
+
+    let (_, carol) = service
+        .join_market(
+            market.invite_code.clone(),
+            "carol-device".to_string(),
+            "Carol".to_string(),
+            "👵".to_string(),
+        )
+        .await
+        .unwrap();
+
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    // Create and approve bet
+    let bet = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![alice.id],
+            false,
+            "Test bet".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+
+    service.approve_bet(bet.id, admin.id).await.unwrap();
+
+    // Place wagers:
+    // YES side: Admin (100 from creation), Bob (200), Carol (100) = 400 total
+    // NO side: (none initially)
+    service
+        .place_wager(bet.id, bob.id, Side::Yes, 200)
+        .await
+        .unwrap();
+
+    service
+        .place_wager(bet.id, carol.id, Side::Yes, 100)
+        .await
+        .unwrap();
+
+    // Add a NO bet so there's something to win from
+    service
+        .place_wager(bet.id, bob.id, Side::No, 100)
+        .await
+        .unwrap();
+
+    // Total pool: 500 (400 YES + 100 NO)
+    // If YES wins:
+    //   Admin gets: (100/400) × 500 = 125 coins
+    //   Bob gets: (200/400) × 500 = 250 coins
+    //   Carol gets: (100/400) × 500 = 125 coins
+
+    let payouts = service
+        .resolve_bet(bet.id, admin.id, Side::Yes)
+        .await
+        .unwrap();
+
+    assert_eq!(payouts.len(), 3);
+
+    // Find each user's payout
+    let admin_payout = payouts.iter().find(|(id, _)| *id == admin.id).unwrap().1;
+    let bob_payout = payouts.iter().find(|(id, _)| *id == bob.id).unwrap().1;
+    let carol_payout = payouts.iter().find(|(id, _)| *id == carol.id).unwrap().1;
+
+    assert_eq!(admin_payout, 125);
+    assert_eq!(bob_payout, 250);
+    assert_eq!(carol_payout, 125);
+
+    // Total should equal pool
+    assert_eq!(admin_payout + bob_payout + carol_payout, 500);
+
+    // Check final balances
+    let admin_final = service.get_user(admin.id).await.unwrap();
+    let bob_final = service.get_user(bob.id).await.unwrap();
+    let carol_final = service.get_user(carol.id).await.unwrap();
+
+    assert_eq!(admin_final.balance, 2000 - 100 + 125); // Started 2000, bet 100, won 125
+    assert_eq!(bob_final.balance, 2000 - 200 - 100 + 250); // Started 2000, bet 200 YES + 100 NO, won 250
+    assert_eq!(carol_final.balance, 2000 - 100 + 125); // Started 2000, bet 100, won 125
+}
+
+#[tokio::test]
+async fn test_insufficient_balance() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Balance Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 100, // Small starting balance
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    // Try to create bet with more than balance
+    let result = service
+        .create_bet(
+            market.id,
+            alice.id,
+            vec![admin.id],
+            false,
+            "Expensive bet".to_string(),
+            "1:1".to_string(),
+            200, // More than her 100 balance
+            false,
+        )
+        .await;
+
+    assert!(result.is_err());
+    assert!(result.unwrap_err().to_string().contains("Insufficient"));
+}
+
+#[tokio::test]
+async fn test_probability_chart_tracking() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Chart Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    let bet = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![alice.id],
+            false,
+            "Chart bet".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+
+    service.approve_bet(bet.id, admin.id).await.unwrap();
+
+    // Place multiple wagers to create chart data
+    // Note: Can't bet on bets about yourself, so admin places wagers instead
+    service
+        .place_wager(bet.id, admin.id, Side::No, 100)
+        .await
+        .unwrap();
+
+    service
+        .place_wager(bet.id, admin.id, Side::Yes, 50)
+        .await
+        .unwrap();
+
+    // Get probability chart
+    let chart = service.get_probability_chart(bet.id).await.unwrap();
+
+    // Should have 3 data points (opening wager + 2 additional wagers)
+    assert_eq!(chart.len(), 3);
+
+    // First point: 100 YES (opening wager), 0 NO = 100% YES
+    assert!((chart[0].yes_probability - 1.0).abs() < 0.01);
+
+    // Second point: 100 YES, 100 NO (first additional wager) = 50%
+    assert!((chart[1].yes_probability - 0.5).abs() < 0.01);
+
+    // Third point: 150 YES, 100 NO = 60%
+    assert!((chart[2].yes_probability - 0.6).abs() < 0.01);
+}
+
+#[tokio::test]
+async fn test_admin_only_actions() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Admin Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    let bet = service
+        .create_bet(
+            market.id,
+            alice.id,
+            vec![admin.id],
+            false,
+            "Admin bet".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+
+    // Non-admin tries to approve bet
+    let result = service.approve_bet(bet.id, alice.id).await;
+    assert!(result.is_err());
+    let err_msg = result.unwrap_err().to_string();
+    assert!(
+        err_msg.contains("Admin only") || err_msg.contains("Constraint"),
+        "Expected admin error but got: {}",
+        err_msg
+    );
+
+    // Approve as admin
+    service.approve_bet(bet.id, admin.id).await.unwrap();
+
+    // Non-admin tries to resolve bet
+    let result = service.resolve_bet(bet.id, alice.id, Side::Yes).await;
+    assert!(result.is_err());
+    let err_msg = result.unwrap_err().to_string();
+    assert!(
+        err_msg.contains("Admin only") || err_msg.contains("Constraint"),
+        "Expected admin error but got: {}",
+        err_msg
+    );
+}
+
+#[tokio::test]
+async fn test_returning_user() {
+    let service = setup_test_db().await;
+
+    let (market, _admin) = service
+        .create_market(CreateMarketParams {
+            name: "Returning User Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    // Alice joins first time
+    let (_, alice1) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    // Alice "returns" with same device ID
+    let (_, alice2) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),  // Same device ID
+            "Alice Updated".to_string(), // Different name (should be ignored)
+            "👸".to_string(),            // Different avatar (should be ignored)
+        )
+        .await
+        .unwrap();
+
+    // Should get same user back
+    assert_eq!(alice1.id, alice2.id);
+    assert_eq!(alice2.display_name, "Alice"); // Original name preserved
+    assert_eq!(alice2.avatar, "👩"); // Original avatar preserved
+}
+
+#[tokio::test]
+async fn test_multiple_bets_same_subject() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Multiple Bets Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    // Create multiple bets about Alice
+    let bet1 = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![alice.id],
+            false,
+            "Alice will be late".to_string(),
+            "1:1".to_string(),
+            50,
+            true, // hidden from subject
+        )
+        .await
+        .unwrap();
+
+    let bet2 = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![alice.id],
+            false,
+            "Alice will spill drink".to_string(),
+            "1:1".to_string(),
+            50,
+            true, // hidden from subject
+        )
+        .await
+        .unwrap();
+
+    service.approve_bet(bet1.id, admin.id).await.unwrap();
+    service.approve_bet(bet2.id, admin.id).await.unwrap();
+
+    // Alice should see 2 hidden bets
+    let bets = service.get_bets(market.id, alice.id).await.unwrap();
+    assert_eq!(bets.len(), 2);
+    assert!(bets.iter().all(|b| b.is_hidden));
+
+    // After resolution, Alice can see them
+    service
+        .resolve_bet(bet1.id, admin.id, Side::Yes)
+        .await
+        .unwrap();
+    service
+        .resolve_bet(bet2.id, admin.id, Side::No)
+        .await
+        .unwrap();
+
+    let bets = service.get_bets(market.id, alice.id).await.unwrap();
+    assert!(bets.iter().all(|b| !b.is_hidden));
+
+    // Get bets about Alice for reveal screen
+    let reveal_bets = service.get_bets_about_user(alice.id).await.unwrap();
+    assert_eq!(reveal_bets.len(), 2);
+}
+
+#[tokio::test]
+async fn test_bets_about_multiple_subjects() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Group Bets Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let mut players = Vec::new();
+    for name in ["Alice", "Bob", "Carol"] {
+        let (_, user) = service
+            .join_market(
+                market.invite_code.clone(),
+                format!("{}-device", name.to_lowercase()),
+                name.to_string(),
+                "🙂".to_string(),
+            )
+            .await
+            .unwrap();
+        players.push(user);
+    }
+    let (alice, bob, carol) = (&players[0], &players[1], &players[2]);
+
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    // Alice and Bob argue about politics
+    let argument = service
+        .create_bet(
+            market.id,
+            carol.id,
+            vec![alice.id, bob.id],
+            false,
+            "Alice and Bob argue about politics".to_string(),
+            "1:1".to_string(),
+            100,
+            true, // hidden from subjects
+        )
+        .await
+        .unwrap();
+    assert_eq!(argument.subject_user_ids.len(), 2);
+
+    // Hidden from both subjects, visible to everyone else
+    for subject in [alice, bob] {
+        let bets = service.get_bets(market.id, subject.id).await.unwrap();
+        assert!(bets[0].is_hidden);
+        assert!(bets[0].subject_user_ids.is_none());
+    }
+    let bets = service.get_bets(market.id, admin.id).await.unwrap();
+    assert!(!bets[0].is_hidden);
+
+    // Neither subject can wager on it
+    for subject in [alice, bob] {
+        let result = service
+            .place_wager(argument.id, subject.id, Side::No, 50)
+            .await;
+        assert!(result.is_err());
+    }
+    service
+        .place_wager(argument.id, admin.id, Side::No, 50)
+        .await
+        .unwrap();
+
+    // Everyone is a subject of an "everyone" bet, so only its author can
+    // wager on it, and hiding it hides it from everyone but them
+    let toast = service
+        .create_bet(
+            market.id,
+            carol.id,
+            Vec::new(),
+            true,
+            "Someone cries during the toast".to_string(),
+            "1:1".to_string(),
+            100,
+            true,
+        )
+        .await
+        .unwrap();
+
+    for player in [&admin, alice, bob] {
+        let bets = service.get_bets(market.id, player.id).await.unwrap();
+        let view = bets.iter().find(|b| b.id == toast.id).unwrap();
+        assert!(view.is_hidden);
+        assert!(view.description.is_none());
+
+        let result = service.place_wager(toast.id, player.id, Side::No, 50).await;
+        assert!(result.is_err());
+    }
+    let bets = service.get_bets(market.id, carol.id).await.unwrap();
+    assert!(!bets.iter().find(|b| b.id == toast.id).unwrap().is_hidden);
+    service
+        .place_wager(toast.id, carol.id, Side::Yes, 50)
+        .await
+        .unwrap();
+
+    // Reveal screens pick up both kinds of bet
+    let about_alice = service.get_bets_about_user(alice.id).await.unwrap();
+    assert_eq!(about_alice.len(), 2);
+    let about_admin = service.get_bets_about_user(admin.id).await.unwrap();
+    assert_eq!(about_admin.len(), 1);
+
+    // A bet has to be about someone
+    let result = service
+        .create_bet(
+            market.id,
+            carol.id,
+            Vec::new(),
+            false,
+            "Nobody in particular".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_reveal_ceremony() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Reveal Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    let (_, bob) = service
+        .join_market(
+            market.invite_code.clone(),
+            "bob-device".to_string(),
+            "Bob".to_string(),
+            "👨".to_string(),
+        )
+        .await
+        .unwrap();
+
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    let first = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![alice.id],
+            false,
+            "Alice cries at the toast".to_string(),
+            "1:1".to_string(),
+            100,
+            true, // hidden from subject
+        )
+        .await
+        .unwrap();
+
+    let second = service
+        .create_bet(
+            market.id,
+            alice.id,
+            vec![bob.id],
+            false,
+            "Bob burns the turkey".to_string(),
+            "1:1".to_string(),
+            100,
+            true, // hidden from subject
+        )
+        .await
+        .unwrap();
+
+    // Visible bets aren't part of the ceremony
+    service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![bob.id],
+            false,
+            "Bob wins charades".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+
+    service
+        .place_wager(first.id, bob.id, Side::No, 300)
+        .await
+        .unwrap();
+
+    // No spoilers while betting is open
+    assert!(service.get_reveal(alice.id).await.is_err());
+    assert!(service
+        .start_reveal_ceremony(market.id, admin.id)
+        .await
+        .is_err());
+
+    service
+        .resolve_bet(first.id, admin.id, Side::No)
+        .await
+        .unwrap();
+    service.close_market(market.id, admin.id).await.unwrap();
+
+    assert_eq!(service.get_reveal(alice.id).await.unwrap().len(), 1);
+
+    // Only the admin runs the ceremony
+    assert!(service
+        .start_reveal_ceremony(market.id, alice.id)
+        .await
+        .is_err());
+
+    let ceremony = service
+        .start_reveal_ceremony(market.id, admin.id)
+        .await
+        .unwrap();
+    assert_eq!(ceremony.bet_ids, vec![first.id, second.id]);
+    assert_eq!(ceremony.revealed_count, 0);
+
+    let (ceremony, reveal) = service.reveal_next_bet(market.id, admin.id).await.unwrap();
+    assert_eq!(ceremony.revealed_count, 1);
+    assert!(!ceremony.is_complete());
+    assert_eq!(reveal.bet.id, first.id);
+    assert_eq!(reveal.outcome, Some(Side::No));
+    assert_eq!(reveal.top_winners.len(), 1);
+    assert_eq!(reveal.top_winners[0].user_id, bob.id);
+    assert_eq!(reveal.top_winners[0].payout, 400);
+
+    // A reconnecting client (or a restarted ceremony) resumes where it left off
+    let resumed = service
+        .start_reveal_ceremony(market.id, admin.id)
+        .await
+        .unwrap();
+    assert_eq!(resumed.revealed_count, 1);
+    let (state, revealed) = service.get_reveal_ceremony(market.id).await.unwrap();
+    assert_eq!(state.revealed_count, 1);
+    assert_eq!(revealed.len(), 1);
+    assert_eq!(revealed[0].bet.id, first.id);
+
+    // Unresolved bets are still revealed, just without an outcome
+    let (ceremony, reveal) = service.reveal_next_bet(market.id, admin.id).await.unwrap();
+    assert!(ceremony.is_complete());
+    assert_eq!(reveal.bet.id, second.id);
+    assert_eq!(reveal.outcome, None);
+    assert!(reveal.top_winners.is_empty());
+
+    assert!(service.reveal_next_bet(market.id, admin.id).await.is_err());
+}
+
+#[tokio::test]
+async fn test_kick_ban_and_leave() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Membership Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let mut players = Vec::new();
+    for name in ["Alice", "Bob", "Troll"] {
+        let (_, user) = service
+            .join_market(
+                market.invite_code.clone(),
+                format!("{}-device", name.to_lowercase()),
+                name.to_string(),
+                "🙂".to_string(),
+            )
+            .await
+            .unwrap();
+        players.push(user);
+    }
+    let (alice, bob, troll) = (&players[0], &players[1], &players[2]);
+
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    let about_bob = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![bob.id],
+            false,
+            "Bob naps".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+    let about_alice = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![alice.id],
+            false,
+            "Alice sings".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+
+    service
+        .place_wager(about_bob.id, alice.id, Side::No, 200)
+        .await
+        .unwrap();
+    service
+        .place_wager(about_alice.id, bob.id, Side::No, 150)
+        .await
+        .unwrap();
+    service
+        .place_wager(about_alice.id, troll.id, Side::No, 300)
+        .await
+        .unwrap();
+
+    // The admin can't walk out on their own market
+    assert!(service.leave_market(admin.id).await.is_err());
+
+    // Bob leaves: his open wager is refunded and the bet about him is voided
+    let change = service.leave_market(bob.id).await.unwrap();
+    assert_eq!(change.user.status, MembershipStatus::Left);
+    assert_eq!(change.user.balance, 1000);
+    assert_eq!(change.voided_bet_ids, vec![about_bob.id]);
+
+    let bet = service.get_bet(about_bob.id).await.unwrap();
+    assert_eq!(bet.status, BetStatus::Void);
+    let alice_now = service.get_user(alice.id).await.unwrap();
+    assert_eq!(alice_now.balance, 1000); // Wager on the voided bet refunded
+    let admin_now = service.get_user(admin.id).await.unwrap();
+    assert_eq!(admin_now.balance, 900); // Only the bet about Alice is still open
+
+    let bet = service.get_bet(about_alice.id).await.unwrap();
+    assert_eq!(bet.no_pool, 300); // Bob's stake is gone, Troll's remains
+    let wagers = service
+        .list_wagers(about_alice.id, admin.id, &Default::default())
+        .await
+        .unwrap();
+    let bobs = wagers.items.iter().find(|w| w.user_id == bob.id).unwrap();
+    assert!(bobs.refunded); // Kept for the history
+
+    // Only players can act on a market they're still in
+    assert!(service
+        .place_wager(about_alice.id, bob.id, Side::No, 10)
+        .await
+        .is_err());
+
+    // Players can't be kicked by other players
+    assert!(service
+        .kick_user(market.id, alice.id, troll.id)
+        .await
+        .is_err());
+
+    // Coins held by players plus coins staked on open bets
+    async fn coins(
+        service: &CazinoService<TestDatabase>,
+        users: &[&User],
+        bet_id: uuid::Uuid,
+    ) -> i64 {
+        let mut total = 0;
+        for user in users {
+            total += service.get_user(user.id).await.unwrap().balance;
+        }
+        let bet = service.get_bet(bet_id).await.unwrap();
+        if bet.status == BetStatus::Active {
+            total += bet.yes_pool + bet.no_pool;
+        }
+        total
+    }
+    let everyone = [&admin, alice, bob, troll];
+    let before = coins(&service, &everyone, about_alice.id).await;
+
+    // Banning forfeits open wagers and blocks the device from rejoining
+    let change = service
+        .ban_user(market.id, admin.id, troll.id)
+        .await
+        .unwrap();
+    assert_eq!(change.user.status, MembershipStatus::Banned);
+    assert_eq!(change.user.balance, 700);
+
+    // The stake stays in the pool, with its history, for the others to win
+    let bet = service.get_bet(about_alice.id).await.unwrap();
+    assert_eq!(bet.no_pool, 300);
+    assert_eq!(bet.yes_pool, 100);
+    let wagers = service
+        .list_wagers(about_alice.id, admin.id, &Default::default())
+        .await
+        .unwrap();
+    assert!(wagers.items.iter().any(|w| w.user_id == troll.id));
+    assert_eq!(coins(&service, &everyone, about_alice.id).await, before);
+
+    let rejoin = service
+        .join_market(
+            market.invite_code.clone(),
+            "troll-device".to_string(),
+            "Troll2".to_string(),
+            "👹".to_string(),
+        )
+        .await;
+    assert!(rejoin.is_err());
+
+    // Kicked and departed players may come back
+    let (_, bob_again) = service
+        .join_market(
+            market.invite_code.clone(),
+            "bob-device".to_string(),
+            "Bob".to_string(),
+            "🙂".to_string(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(bob_again.id, bob.id);
+    assert_eq!(bob_again.status, MembershipStatus::Active);
+
+    let users = service.get_users(market.id).await.unwrap();
+    assert_eq!(users.len(), 3);
+    assert!(users.iter().all(|u| u.id != troll.id));
+
+    // The admin's opening wager wins the pool, Troll's forfeited stake included
+    service.close_market(market.id, admin.id).await.unwrap();
+    service
+        .resolve_bet(about_alice.id, admin.id, Side::Yes)
+        .await
+        .unwrap();
+    assert_eq!(service.get_user(admin.id).await.unwrap().balance, 1300);
+    assert_eq!(service.get_user(troll.id).await.unwrap().balance, 700);
+    assert_eq!(coins(&service, &everyone, about_alice.id).await, before);
+}
+
+#[tokio::test]
+async fn test_voided_bets_keep_forfeited_stakes() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Forfeit Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+    let mut players = Vec::new();
+    for name in ["Alice", "Troll"] {
+        let (_, user) = service
+            .join_market(
+                market.invite_code.clone(),
+                format!("{}-device", name.to_lowercase()),
+                name.to_string(),
+                "🙂".to_string(),
+            )
+            .await
+            .unwrap();
+        players.push(user);
+    }
+    let (alice, troll) = (&players[0], &players[1]);
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    let about_alice = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![alice.id],
+            false,
+            "Alice leaves early".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+    service
+        .place_wager(about_alice.id, troll.id, Side::No, 200)
+        .await
+        .unwrap();
+    service
+        .ban_user(market.id, admin.id, troll.id)
+        .await
+        .unwrap();
+
+    // Alice leaves, voiding the bet: the admin is refunded, but the banned
+    // troll's stake stays forfeited
+    let change = service.leave_market(alice.id).await.unwrap();
+    assert_eq!(change.voided_bet_ids, vec![about_alice.id]);
+    assert_eq!(service.get_user(admin.id).await.unwrap().balance, 1000);
+    assert_eq!(service.get_user(troll.id).await.unwrap().balance, 800);
+
+    let wagers = service
+        .list_wagers(about_alice.id, admin.id, &Default::default())
+        .await
+        .unwrap();
+    assert_eq!(wagers.items.len(), 2);
+    for wager in &wagers.items {
+        assert_eq!(wager.refunded, wager.user_id == admin.id);
+    }
+}
+
+#[tokio::test]
+async fn test_update_profile() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Profile Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    // Rename and change avatar
+    let updated = service
+        .update_profile(alice.id, Some(" Ally ".to_string()), Some("🦄".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(updated.display_name, "Ally");
+    assert_eq!(updated.avatar, "🦄");
+
+    // Partial updates keep the other field
+    let updated = service
+        .update_profile(alice.id, None, Some("🐙".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(updated.display_name, "Ally");
+
+    let stored = service.get_user(alice.id).await.unwrap();
+    assert_eq!(stored.display_name, "Ally");
+    assert_eq!(stored.avatar, "🐙");
+
+    // Names are unique within the market, ignoring case
+    let result = service
+        .update_profile(alice.id, Some("ADMIN".to_string()), None)
+        .await;
+    assert!(result.is_err());
+
+    // Avatars must be a single emoji
+    let result = service
+        .update_profile(alice.id, None, Some("🐙🐙".to_string()))
+        .await;
+    assert!(result.is_err());
+
+    // Keeping your own name is fine
+    service
+        .update_profile(admin.id, Some("admin".to_string()), None)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_link_code_moves_players_to_new_device() {
+    let service = setup_test_db().await;
+
+    let (market_a, _) = service
+        .create_market(CreateMarketParams {
+            name: "Market A".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+    let (market_b, _) = service
+        .create_market(CreateMarketParams {
+            name: "Market B".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    // Alice plays in both markets from her phone
+    let (_, alice_a) = service
+        .join_market(
+            market_a.invite_code.clone(),
+            "phone".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+    service
+        .join_market(
+            market_b.invite_code.clone(),
+            "phone".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    // Her laptop already joined market B separately
+    let (_, laptop_b) = service
+        .join_market(
+            market_b.invite_code.clone(),
+            "laptop".to_string(),
+            "Alice (laptop)".to_string(),
+            "💻".to_string(),
+        )
+        .await
+        .unwrap();
+
+    // A device with no markets has nothing to link
+    assert!(service.create_link_code("tablet").await.is_err());
+
+    let link = service.create_link_code("phone").await.unwrap();
+    assert_eq!(link.code.len(), 12);
+
+    // The issuing device can't redeem its own code
+    assert!(service.redeem_link_code(&link.code, "phone").await.is_err());
+
+    // Codes are case-insensitive
+    let markets = service
+        .redeem_link_code(&link.code.to_lowercase(), "laptop")
+        .await
+        .unwrap();
+    assert_eq!(markets.len(), 2);
+
+    // Market A moved over; market B kept the laptop's own player
+    let moved = service.get_user(alice_a.id).await.unwrap();
+    assert_eq!(moved.device_id, "laptop");
+    let (_, b_user) = markets.iter().find(|(m, _)| m.id == market_b.id).unwrap();
+    assert_eq!(b_user.id, laptop_b.id);
+
+    // Codes are single use
+    assert!(service
+        .redeem_link_code(&link.code, "desktop")
+        .await
+        .is_err());
+
+    // Generating codes is rate limited
+    for _ in 0..rules::MAX_LINK_CODES_PER_HOUR {
+        service.create_link_code("laptop").await.unwrap();
+    }
+    assert!(service.create_link_code("laptop").await.is_err());
+
+    // So is guessing them
+    for _ in 0..rules::MAX_LINK_REDEEMS_PER_HOUR {
+        assert!(service
+            .redeem_link_code("WRONG2345678", "desktop")
+            .await
+            .is_err());
+    }
+    let fresh = service.create_link_code("phone").await;
+    assert!(fresh.is_ok());
+    let result = service
+        .redeem_link_code(&fresh.unwrap().code, "desktop")
+        .await;
+    assert!(result.is_err());
+
+    // Other devices' wrong guesses don't lock anyone else out
+    for i in 0..rules::MAX_LINK_REDEEMS_PER_HOUR {
+        let _ = service
+            .redeem_link_code("WRONG2345678", &format!("guesser-{}", i))
+            .await;
+    }
+    let fresh = service.create_link_code("phone").await.unwrap();
+    service
+        .redeem_link_code(&fresh.code, "tablet")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_banned_seats_cannot_be_linked() {
+    let service = setup_test_db().await;
+
+    let mut markets = Vec::new();
+    for name in ["Market A", "Market B"] {
+        let (market, admin) = service
+            .create_market(CreateMarketParams {
+                name: name.to_string(),
+                admin_device_id: "admin-device".to_string(),
+                admin_name: "Admin".to_string(),
+                admin_avatar: "👑".to_string(),
+                starting_balance: 1000,
+                duration_hours: 24,
+                custom_invite_code: None,
+            })
+            .await
+            .unwrap();
+        let (_, troll) = service
+            .join_market(
+                market.invite_code.clone(),
+                "troll-phone".to_string(),
+                "Troll".to_string(),
+                "👹".to_string(),
+            )
+            .await
+            .unwrap();
+        markets.push((market, admin, troll));
+    }
+    let (market_a, admin_a, troll_a) = &markets[0];
+    let (_, admin_b, troll_b) = &markets[1];
+
+    service
+        .ban_user(market_a.id, admin_a.id, troll_a.id)
+        .await
+        .unwrap();
+
+    // The banned seat stays on the banned device; the other one moves
+    let link = service.create_link_code("troll-phone").await.unwrap();
+    let linked = service
+        .redeem_link_code(&link.code, "troll-laptop")
+        .await
+        .unwrap();
+    assert_eq!(linked.len(), 1);
+    assert_eq!(linked[0].1.id, troll_b.id);
+    let banned = service.get_user(troll_a.id).await.unwrap();
+    assert_eq!(banned.device_id, "troll-phone");
+
+    // A device whose every seat is banned gets no code at all
+    service
+        .ban_user(linked[0].0.id, admin_b.id, troll_b.id)
+        .await
+        .unwrap();
+    assert!(service.create_link_code("troll-laptop").await.is_err());
+    assert!(service.create_link_code("troll-phone").await.is_err());
+}
+
+#[tokio::test]
+async fn test_player_profile_across_markets() {
+    let service = setup_test_db().await;
+
+    let mut alice_seats = Vec::new();
+    let mut bob_seats = Vec::new();
+    for (name, outcome) in [("Thanksgiving", Side::Yes), ("Christmas", Side::No)] {
+        let (market, admin) = service
+            .create_market(CreateMarketParams {
+                name: name.to_string(),
+                admin_device_id: "admin-device".to_string(),
+                admin_name: "Admin".to_string(),
+                admin_avatar: "👑".to_string(),
+                starting_balance: 1000,
+                duration_hours: 24,
+                custom_invite_code: None,
+            })
+            .await
+            .unwrap();
+        let (_, alice) = service
+            .join_market(
+                market.invite_code.clone(),
+                "alice-device".to_string(),
+                "Alice".to_string(),
+                "👩".to_string(),
+            )
+            .await
+            .unwrap();
+        let (_, bob) = service
+            .join_market(
+                market.invite_code.clone(),
+                "bob-device".to_string(),
+                "Bob".to_string(),
+                "👨".to_string(),
+            )
+            .await
+            .unwrap();
+        service.open_market(market.id, admin.id).await.unwrap();
+
+        // Alice opens with 100 on YES, the admin takes 200 on NO
+        let bet = service
+            .create_bet(
+                market.id,
+                alice.id,
+                vec![bob.id],
+                false,
+                "Bob will carve the turkey".to_string(),
+                "1:1".to_string(),
+                100,
+                false,
+            )
+            .await
+            .unwrap();
+        service
+            .place_wager(bet.id, admin.id, Side::No, 200)
+            .await
+            .unwrap();
+        service
+            .resolve_bet(bet.id, admin.id, outcome)
+            .await
+            .unwrap();
+
+        alice_seats.push(alice);
+        bob_seats.push(bob);
+    }
+
+    // First link creates the player from the seat's name and avatar
+    let player = service
+        .link_player(alice_seats[0].id, "alice-device", None)
+        .await
+        .unwrap();
+    assert_eq!(player.display_name, "Alice");
+    let linked = service
+        .link_player(alice_seats[1].id, "alice-device", Some(player.id))
+        .await
+        .unwrap();
+    assert_eq!(linked.id, player.id);
+
+    // Relinking the same seat is a no-op
+    service
+        .link_player(alice_seats[0].id, "alice-device", Some(player.id))
+        .await
+        .unwrap();
+
+    // A seat can't move to another player
+    assert!(service
+        .link_player(alice_seats[0].id, "alice-device", None)
+        .await
+        .is_err());
+
+    // Only the seat's own device can link it, and only to its own player
+    assert!(service
+        .link_player(bob_seats[0].id, "alice-device", None)
+        .await
+        .is_err());
+    assert!(service
+        .link_player(bob_seats[0].id, "bob-device", Some(player.id))
+        .await
+        .is_err());
+    assert_eq!(
+        service.get_user(bob_seats[0].id).await.unwrap().player_id,
+        None
+    );
+
+    let stored = service.get_user(alice_seats[1].id).await.unwrap();
+    assert_eq!(stored.player_id, Some(player.id));
+
+    let (profile, stats) = service.get_player_profile(player.id).await.unwrap();
+    assert_eq!(profile.id, player.id);
+    assert_eq!(stats.markets_played, 2);
+    // Won 300 on Thanksgiving (+200), lost the 100 stake at Christmas (-100)
+    assert_eq!(stats.net_profit, 100);
+    assert_eq!(stats.bets_settled, 2);
+    assert_eq!(stats.bets_won, 1);
+    assert!((stats.win_rate - 0.5).abs() < f64::EPSILON);
+    assert_eq!(stats.biggest_payout, 300);
+}
+
+#[tokio::test]
+async fn test_season_leaderboard_with_carry_over() {
+    let service = setup_test_db().await;
+
+    let season = service
+        .create_season("Holidays 2024".to_string(), true)
+        .await
+        .unwrap();
+
+    let new_market = |name: &str| CreateMarketParams {
+        name: name.to_string(),
+        admin_device_id: "admin-device".to_string(),
+        admin_name: "Admin".to_string(),
+        admin_avatar: "👑".to_string(),
+        starting_balance: 1000,
+        duration_hours: 24,
+        custom_invite_code: None,
+    };
+
+    // Thanksgiving: Alice wins 200 off the admin
+    let (thanksgiving, admin) = service
+        .create_market(new_market("Thanksgiving"))
+        .await
+        .unwrap();
+    service
+        .add_market_to_season(season.id, thanksgiving.id, admin.id)
+        .await
+        .unwrap();
+
+    let (_, alice) = service
+        .join_market(
+            thanksgiving.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+    let (_, bob) = service
+        .join_market(
+            thanksgiving.invite_code.clone(),
+            "bob-device".to_string(),
+            "Bob".to_string(),
+            "👨".to_string(),
+        )
+        .await
+        .unwrap();
+
+    // Only the market's admin can add it, and only to one season
+    assert!(service
+        .add_market_to_season(season.id, thanksgiving.id, alice.id)
+        .await
+        .is_err());
+    assert!(service
+        .add_market_to_season(season.id, thanksgiving.id, admin.id)
+        .await
+        .is_err());
+
+    service
+        .open_market(thanksgiving.id, admin.id)
+        .await
+        .unwrap();
+    let bet = service
+        .create_bet(
+            thanksgiving.id,
+            alice.id,
+            vec![bob.id],
+            false,
+            "Bob burns the pie".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+    service
+        .place_wager(bet.id, admin.id, Side::No, 200)
+        .await
+        .unwrap();
+    service
+        .resolve_bet(bet.id, admin.id, Side::Yes)
+        .await
+        .unwrap();
+
+    // Christmas: the admin's existing seat and Alice's new one carry balances over
+    let (christmas, christmas_admin) = service
+        .create_market(new_market("Christmas"))
+        .await
+        .unwrap();
+    let (season_again, markets) = service
+        .add_market_to_season(season.id, christmas.id, christmas_admin.id)
+        .await
+        .unwrap();
+    assert_eq!(season_again.id, season.id);
+    assert_eq!(markets.len(), 2);
+    assert_eq!(markets[0].id, thanksgiving.id);
+
+    let christmas_admin = service.get_user(christmas_admin.id).await.unwrap();
+    assert_eq!(christmas_admin.balance, 800);
+
+    let (_, alice_christmas) = service
+        .join_market(
+            christmas.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(alice_christmas.balance, 1200);
+
+    // Carried balances aren't counted twice
+    let (_, standings) = service.get_season_leaderboard(season.id).await.unwrap();
+    assert_eq!(standings.len(), 3);
+
+    assert_eq!(standings[0].display_name, "Alice");
+    assert_eq!(standings[0].profit, 200);
+    assert_eq!(standings[0].markets_played, 2);
+    assert_eq!(standings[0].rank, 1);
+
+    assert_eq!(standings[1].display_name, "Bob");
+    assert_eq!(standings[1].profit, 0);
+    assert_eq!(standings[1].markets_played, 1);
+
+    assert_eq!(standings[2].display_name, "Admin");
+    assert_eq!(standings[2].profit, -200);
+    assert_eq!(standings[2].rank, 3);
+
+    // Leaving a market partway through the season doesn't wipe its results
+    service.leave_market(alice.id).await.unwrap();
+    let (_, standings) = service.get_season_leaderboard(season.id).await.unwrap();
+    assert_eq!(standings[0].display_name, "Alice");
+    assert_eq!(standings[0].profit, 200);
+    assert_eq!(standings[0].markets_played, 2);
+}
+
+#[tokio::test]
+async fn test_templates_and_cloning() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Thanksgiving 2025".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 500,
+            duration_hours: 48,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let mut players = Vec::new();
+    for name in ["Alice", "Bob", "Carol"] {
+        let (_, user) = service
+            .join_market(
+                market.invite_code.clone(),
+                format!("{}-device", name.to_lowercase()),
+                name.to_string(),
+                "🙂".to_string(),
+            )
+            .await
+            .unwrap();
+        players.push(user);
+    }
+    let (alice, bob, carol) = (&players[0], &players[1], &players[2]);
+
+    service.open_market(market.id, admin.id).await.unwrap();
+    service
+        .create_bet(
+            market.id,
+            alice.id,
+            vec![bob.id],
+            false,
+            "Bob naps before dessert".to_string(),
+            "3:1".to_string(),
+            50,
+            true,
+        )
+        .await
+        .unwrap();
+    service
+        .create_bet(
+            market.id,
+            bob.id,
+            vec![],
+            true,
+            "Someone spills gravy".to_string(),
+            "1:1".to_string(),
+            20,
+            false,
+        )
+        .await
+        .unwrap();
+    service
+        .create_bet(
+            market.id,
+            alice.id,
+            vec![carol.id],
+            false,
+            "Carol brings a date".to_string(),
+            "1:1".to_string(),
+            20,
+            false,
+        )
+        .await
+        .unwrap();
+
+    // Carol's kicked, so the bet about her is voided and left out
+    service
+        .kick_user(market.id, admin.id, carol.id)
+        .await
+        .unwrap();
+
+    // Only the admin can save or clone
+    assert!(service
+        .save_template(market.id, alice.id, None)
+        .await
+        .is_err());
+    assert!(service
+        .clone_market(market.id, alice.id, "Nope".to_string())
+        .await
+        .is_err());
+
+    let template = service
+        .save_template(market.id, admin.id, Some("Thanksgiving".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(template.name, "Thanksgiving");
+    assert_eq!(template.starting_balance, 500);
+    assert_eq!(template.duration_hours, 48);
+    assert_eq!(template.bets.len(), 2);
+    assert_eq!(
+        service.get_template(template.id).await.unwrap().bets.len(),
+        2
+    );
+
+    // Cloning seats the same players and remaps subjects by name
+    let (cloned, cloned_admin, bets) = service
+        .clone_market(market.id, admin.id, "Thanksgiving 2026".to_string())
+        .await
+        .unwrap();
+    assert_eq!(cloned.status, MarketStatus::Draft);
+    assert_eq!(cloned.starting_balance, 500);
+    assert_eq!(cloned_admin.device_id, "admin-device");
+
+    let seats = service.get_users(cloned.id).await.unwrap();
+    assert_eq!(seats.len(), 3);
+    let new_alice = seats.iter().find(|u| u.display_name == "Alice").unwrap();
+    let new_bob = seats.iter().find(|u| u.display_name == "Bob").unwrap();
+    assert_eq!(new_bob.balance, 500);
+
+    assert_eq!(bets.len(), 2);
+    assert!(bets.iter().all(|b| b.status == BetStatus::Pending));
+    let nap = bets
+        .iter()
+        .find(|b| b.description.contains("naps"))
+        .unwrap();
+    assert_eq!(nap.subject_user_ids, vec![new_bob.id]);
+    assert_eq!(nap.created_by, new_alice.id);
+    assert!(nap.hide_from_subject);
+    assert_eq!(service.get_pending_bets(cloned.id).await.unwrap().len(), 2);
+
+    // From a template only the admin is seated, so only the everyone-bet fits
+    let (fresh, fresh_admin, bets) = service
+        .create_market_from_template(
+            template.id,
+            None,
+            "new-admin".to_string(),
+            "Host".to_string(),
+            "🦃".to_string(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(fresh.name, "Thanksgiving");
+    assert_eq!(bets.len(), 1);
+    assert!(bets[0].about_everyone);
+    assert_eq!(bets[0].created_by, fresh_admin.id);
+}
+
+#[tokio::test]
+async fn test_export_and_import_archive() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Archive Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+    let (_, bob) = service
+        .join_market(
+            market.invite_code.clone(),
+            "bob-device".to_string(),
+            "Bob".to_string(),
+            "👨".to_string(),
+        )
+        .await
+        .unwrap();
+    service
+        .link_player(alice.id, "alice-device", None)
+        .await
+        .unwrap();
+    let (_, grandma) = service
+        .join_as_spectator(market.invite_code.clone(), "Grandma".to_string())
+        .await
+        .unwrap();
+
+    service.open_market(market.id, admin.id).await.unwrap();
+    let bet = service
+        .create_bet(
+            market.id,
+            alice.id,
+            vec![admin.id],
+            false,
+            "Admin forgets the rolls".to_string(),
+            "1:1".to_string(),
+            100,
+            true,
+        )
+        .await
+        .unwrap();
+    service
+        .place_wager(bet.id, bob.id, Side::No, 50)
+        .await
+        .unwrap();
+
+    // Exporting through the API path is admin only
+    assert!(service
+        .export_market_as_admin(market.id, alice.id)
+        .await
+        .is_err());
+    let archive = service
+        .export_market_as_admin(market.id, admin.id)
+        .await
+        .unwrap();
+    assert_eq!(archive.version, archive::ARCHIVE_VERSION);
+    assert_eq!(archive.users.len(), 3);
+    assert_eq!(archive.wagers.len(), 2);
+    assert_eq!(archive.spectators.len(), 1);
+    assert!(archive.spectators[0].token.is_empty());
+    assert_eq!(archive.players.len(), 1);
+
+    // Round-trip through JSON into a fresh database
+    let json = serde_json::to_string(&archive).unwrap();
+    let archive: archive::MarketArchive = serde_json::from_str(&json).unwrap();
+
+    // The market already exists here
+    assert!(service.import_market(archive.clone()).await.is_err());
+
+    let other = setup_test_db().await;
+
+    let mut future = archive.clone();
+    future.version = archive::ARCHIVE_VERSION + 1;
+    assert!(other.import_market(future).await.is_err());
+
+    let mut broken = archive.clone();
+    broken.wagers[0].user_id = uuid::Uuid::new_v4();
+    assert!(other.import_market(broken).await.is_err());
+
+    let imported = other.import_market(archive).await.unwrap();
+    assert_eq!(imported.id, market.id);
+    assert_eq!(imported.invite_code, market.invite_code);
+
+    let bet = other.get_bet(bet.id).await.unwrap();
+    assert_eq!(bet.subject_user_ids, vec![admin.id]);
+    assert_eq!(bet.yes_pool, 100);
+    assert_eq!(bet.no_pool, 50);
+
+    let alice = other.get_user(alice.id).await.unwrap();
+    assert_eq!(alice.balance, 900);
+    assert!(alice.player_id.is_some());
+
+    // Spectators need to join again; their old token means nothing here
+    assert!(other.get_spectator(&grandma.token).await.is_err());
+}
+
+/// Everything an archive holds except when it was taken
+fn archived_rows(archive: &archive::MarketArchive) -> serde_json::Value {
+    let mut rows = serde_json::to_value(archive).unwrap();
+    rows.as_object_mut().unwrap().remove("exported_at");
+    rows
+}
+
+#[tokio::test]
+async fn test_archive_round_trip_keeps_every_row() {
+    let service = setup_test_db().await;
+
+    let season = service
+        .create_season("Holidays 2024".to_string(), true)
+        .await
+        .unwrap();
+    let new_market = |name: &str| CreateMarketParams {
+        name: name.to_string(),
+        admin_device_id: "admin-device".to_string(),
+        admin_name: "Admin".to_string(),
+        admin_avatar: "👑".to_string(),
+        starting_balance: 1000,
+        duration_hours: 24,
+        custom_invite_code: None,
+    };
+
+    // Thanksgiving's balances carry into Christmas
+    let (thanksgiving, admin) = service
+        .create_market(new_market("Thanksgiving"))
+        .await
+        .unwrap();
+    service
+        .add_market_to_season(season.id, thanksgiving.id, admin.id)
+        .await
+        .unwrap();
+    let (_, alice) = service
+        .join_market(
+            thanksgiving.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+    service
+        .open_market(thanksgiving.id, admin.id)
+        .await
+        .unwrap();
+    let bet = service
+        .create_bet(
+            thanksgiving.id,
+            alice.id,
+            vec![admin.id],
+            false,
+            "Admin naps".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+    service
+        .resolve_bet(bet.id, admin.id, Side::Yes)
+        .await
+        .unwrap();
+
+    let (christmas, christmas_admin) = service
+        .create_market(new_market("Christmas"))
+        .await
+        .unwrap();
+    service
+        .add_market_to_season(season.id, christmas.id, christmas_admin.id)
+        .await
+        .unwrap();
+    let (_, alice) = service
+        .join_market(
+            christmas.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+    service
+        .link_player(alice.id, "alice-device", None)
+        .await
+        .unwrap();
+    service
+        .join_as_spectator(christmas.invite_code.clone(), "Grandma".to_string())
+        .await
+        .unwrap();
+    service
+        .open_market(christmas.id, christmas_admin.id)
+        .await
+        .unwrap();
+    let bet = service
+        .create_bet(
+            christmas.id,
+            christmas_admin.id,
+            vec![alice.id],
+            false,
+            "Alice burns the roast".to_string(),
+            "1:1".to_string(),
+            50,
+            true,
+        )
+        .await
+        .unwrap();
+    service
+        .place_wager(bet.id, christmas_admin.id, Side::No, 20)
+        .await
+        .unwrap();
+    service
+        .close_market(christmas.id, christmas_admin.id)
+        .await
+        .unwrap();
+    service
+        .start_reveal_ceremony(christmas.id, christmas_admin.id)
+        .await
+        .unwrap();
+
+    let archive = service.export_market(christmas.id).await.unwrap();
+    assert_eq!(archive.season.as_ref().unwrap().season.id, season.id);
+    assert_eq!(archive.season.as_ref().unwrap().position, 1);
+    assert_eq!(archive.carry_overs.len(), 2);
+    assert!(archive.reveal_ceremony.is_some());
+    let (_, standings) = service.get_season_leaderboard(season.id).await.unwrap();
+
+    // Re-importing a deleted market restores every row, season links included
+    service
+        .delete_market(christmas.id, christmas_admin.id)
+        .await
+        .unwrap();
+    service.import_market(archive.clone()).await.unwrap();
+    assert_eq!(
+        archived_rows(&service.export_market(christmas.id).await.unwrap()),
+        archived_rows(&archive)
+    );
+    let (_, markets) = service.get_season(season.id).await.unwrap();
+    let ids: Vec<_> = markets.iter().map(|m| m.id).collect();
+    assert_eq!(ids, vec![thanksgiving.id, christmas.id]);
+    let (_, again) = service.get_season_leaderboard(season.id).await.unwrap();
+    assert_eq!(
+        serde_json::to_value(&again).unwrap(),
+        serde_json::to_value(&standings).unwrap()
+    );
+
+    // A fresh database gets the season and players created for it, with
+    // Christmas as the season's only market there
+    let other = setup_test_db().await;
+    other.import_market(archive.clone()).await.unwrap();
+    let mut expected = archive.clone();
+    expected.season.as_mut().unwrap().position = 0;
+    assert_eq!(
+        archived_rows(&other.export_market(christmas.id).await.unwrap()),
+        archived_rows(&expected)
+    );
+
+    // An import that fails partway leaves nothing behind
+    let fresh = setup_test_db().await;
+    let (elsewhere, _) = fresh.create_market(new_market("Elsewhere")).await.unwrap();
+    let (_, grandpa) = fresh
+        .join_as_spectator(elsewhere.invite_code.clone(), "Grandpa".to_string())
+        .await
+        .unwrap();
+    let mut clashing = archive.clone();
+    clashing.spectators[0].id = grandpa.id;
+    assert!(fresh.import_market(clashing).await.is_err());
+    assert!(fresh.get_market(christmas.id).await.is_err());
+    assert!(fresh.get_user(alice.id).await.is_err());
+    assert!(fresh.get_bet(bet.id).await.is_err());
+
+    // Carry-overs only make sense as part of a season
+    let mut seasonless = archive;
+    seasonless.season = None;
+    assert!(fresh.import_market(seasonless).await.is_err());
+}
+
+#[tokio::test]
+async fn test_csv_exports() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "CSV Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice, Jr.".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+    let (_, bob) = service
+        .join_market(
+            market.invite_code.clone(),
+            "bob-device".to_string(),
+            "Bob".to_string(),
+            "👨".to_string(),
+        )
+        .await
+        .unwrap();
+
+    service.open_market(market.id, admin.id).await.unwrap();
+    let bet = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![bob.id],
+            false,
+            "Bob says \"turkey\" first".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+    service
+        .place_wager(bet.id, alice.id, Side::No, 50)
+        .await
+        .unwrap();
+    service
+        .resolve_bet(bet.id, admin.id, Side::No)
+        .await
+        .unwrap();
+
+    async fn collect(
+        service: &CazinoService<TestDatabase>,
+        market_id: uuid::Uuid,
+        admin_id: uuid::Uuid,
+        report: CsvReport,
+    ) -> Vec<String> {
+        let mut export = service
+            .export_csv_as_admin(market_id, admin_id, report)
+            .await
+            .unwrap();
+        let mut csv = String::new();
+        while let Some(chunk) = export.next_chunk().await.unwrap() {
+            csv.push_str(&chunk);
+        }
+        csv.lines().map(str::to_string).collect()
+    }
+
+    // Admin only
+    assert!(service
+        .export_csv_as_admin(market.id, alice.id, CsvReport::Wagers)
+        .await
+        .is_err());
+
+    // Header plus the opening wager and Alice's wager
+    let wagers = collect(&service, market.id, admin.id, CsvReport::Wagers).await;
+    assert_eq!(wagers.len(), 3);
+    assert!(wagers[0].starts_with("bet_id,bet,bet_status,wager_id"));
+    assert!(wagers[2].contains("\"Alice, Jr.\",NO,50,"));
+    assert!(wagers[2].contains("\"Bob says \"\"turkey\"\" first\""));
+
+    let results = collect(&service, market.id, admin.id, CsvReport::Results).await;
+    assert_eq!(results.len(), 4);
+    let alice_row = results
+        .iter()
+        .find(|row| row.starts_with(&alice.id.to_string()))
+        .unwrap();
+    let alice_user = service.get_user(alice.id).await.unwrap();
+    let payout = alice_user.balance - (1000 - 50);
+    assert!(alice_row.ends_with(&format!(
+        ",active,1,50,1,1,{},{},{}",
+        payout,
+        alice_user.balance,
+        alice_user.balance - 1000
+    )));
+
+    // Alice won, so she leads
+    let leaderboard = collect(&service, market.id, admin.id, CsvReport::Leaderboard).await;
+    assert_eq!(leaderboard.len(), 4);
+    assert!(leaderboard[1].starts_with(&format!("1,{},", alice.id)));
+}
+
+#[tokio::test]
+async fn test_spectator_mode() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Spectator Test".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+
+    let (_, bob) = service
+        .join_market(
+            market.invite_code.clone(),
+            "bob-device".to_string(),
+            "Bob".to_string(),
+            "👨".to_string(),
+        )
+        .await
+        .unwrap();
+
+    // Grandma watches on the TV
+    let (spectated, spectator) = service
+        .join_as_spectator(market.invite_code.clone(), "Living Room TV".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(spectated.id, market.id);
+    assert_eq!(spectator.market_id, market.id);
+    assert!(!spectator.token.is_empty());
+
+    let found = service.get_spectator(&spectator.token).await.unwrap();
+    assert_eq!(found.id, spectator.id);
+
+    service.open_market(market.id, admin.id).await.unwrap();
+
+    let secret = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![bob.id],
+            false,
+            "Bob secret bet".to_string(),
+            "1:1".to_string(),
+            100,
+            true, // hidden from subject
+        )
+        .await
+        .unwrap();
+
+    service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![bob.id],
+            false,
+            "Bob public bet".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await
+        .unwrap();
+
+    // Spectators see public bets but hidden bets stay redacted
+    let bets = service.get_spectator_bets(&spectator.token).await.unwrap();
+    assert_eq!(bets.len(), 2);
+    let hidden = bets.iter().find(|b| b.id == secret.id).unwrap();
+    assert!(hidden.is_hidden);
+    assert!(hidden.description.is_none());
+    assert_eq!(bets.iter().filter(|b| !b.is_hidden).count(), 1);
+
+    // Spectators are not players, so they can't be the subject of a bet
+    let result = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![spectator.id],
+            false,
+            "The TV turns off".to_string(),
+            "1:1".to_string(),
+            100,
+            false,
+        )
+        .await;
+    assert!(result.is_err());
+
+    // Once resolved, the hidden bet is revealed on the shared screen too
+    service
+        .resolve_bet(secret.id, admin.id, Side::No)
+        .await
+        .unwrap();
+
+    let bets = service.get_spectator_bets(&spectator.token).await.unwrap();
+    assert!(bets.iter().all(|b| !b.is_hidden));
+
+    // Unknown tokens are rejected
+    assert!(service.get_spectator("not-a-token").await.is_err());
+}
+
+#[tokio::test]
+async fn test_delete_market_cascades() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Doomed Market".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+    service.open_market(market.id, admin.id).await.unwrap();
+    let bet = service
+        .create_bet(
+            market.id,
+            admin.id,
+            vec![alice.id],
+            false,
+            "Alice oversleeps".to_string(),
+            "1:1".to_string(),
+            100,
+            true,
+        )
+        .await
+        .unwrap();
+    service
+        .place_wager(bet.id, admin.id, Side::Yes, 50)
+        .await
+        .unwrap();
+
+    // Only the admin may delete
+    assert!(service.delete_market(market.id, alice.id).await.is_err());
+    service.delete_market(market.id, admin.id).await.unwrap();
+
+    assert!(service.get_market(market.id).await.is_err());
+    assert!(service.get_bet(bet.id).await.is_err());
+    assert!(service.get_user(alice.id).await.is_err());
+
+    // The invite code is free again
+    let (again, _) = service
+        .create_market(CreateMarketParams {
+            name: "Second Try".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: Some(market.invite_code.clone()),
+        })
+        .await
+        .unwrap();
+    assert_eq!(again.invite_code, market.invite_code);
+}
+
+#[tokio::test]
+async fn test_webhooks() {
+    let service = setup_test_db().await;
+
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Hooked Market".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+    let (_, alice) = service
+        .join_market(
+            market.invite_code.clone(),
+            "alice-device".to_string(),
+            "Alice".to_string(),
+            "👩".to_string(),
+        )
+        .await
+        .unwrap();
+
+    let secret = "family-chat-secret".to_string();
+    let url = "https://chat.example/hook".to_string();
+
+    // Only the admin may register, with a real URL and a long enough secret
+    assert!(service
+        .create_webhook(market.id, alice.id, url.clone(), secret.clone(), vec![])
+        .await
+        .is_err());
+    assert!(service
+        .create_webhook(
+            market.id,
+            admin.id,
+            "chat.example".to_string(),
+            secret.clone(),
+            vec![]
+        )
+        .await
+        .is_err());
+    assert!(service
+        .create_webhook(
+            market.id,
+            admin.id,
+            url.clone(),
+            "short".to_string(),
+            vec![]
+        )
+        .await
+        .is_err());
+
+    let hook = service
+        .create_webhook(
+            market.id,
+            admin.id,
+            url.clone(),
+            secret.clone(),
+            vec!["user_joined".to_string()],
+        )
+        .await
+        .unwrap();
+    let everything = service
+        .create_webhook(market.id, admin.id, url.clone(), secret.clone(), vec![])
+        .await
+        .unwrap();
+    assert_eq!(
+        service
+            .get_webhooks(market.id, admin.id)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(service.get_webhooks(market.id, alice.id).await.is_err());
+
+    // The event filter picks who hears what
+    let joined = service
+        .webhooks_for_event(market.id, "user_joined", true)
+        .await
+        .unwrap();
+    assert_eq!(joined.len(), 2);
+    let voided = service
+        .webhooks_for_event(market.id, "bet_voided", true)
+        .await
+        .unwrap();
+    assert_eq!(voided.len(), 1);
+    assert_eq!(voided[0].id, everything.id);
+
+    // Bets hidden from their subject never leave the server
+    let created = |hide_from_subject| WsMessage::BetCreated {
+        bet_id: uuid::Uuid::new_v4(),
+        description: "Alice gets a dog".to_string(),
+        hide_from_subject,
+    };
+    let hidden = created(true);
+    assert!(webhooks::webhooks_for(&service, market.id, &hidden)
+        .await
+        .is_empty());
+    let client = ScriptedClient::new(vec![200]);
+    assert!(!webhooks::deliver(&service, &client, &everything, market.id, &hidden).await);
+    assert!(client.requests.lock().unwrap().is_empty());
+    assert_eq!(
+        webhooks::webhooks_for(&service, market.id, &created(false))
+            .await
+            .len(),
+        1
+    );
+
+    // Failures are retried until accepted, each attempt logged
+    let message = WsMessage::UserJoined {
+        user_id: alice.id,
+        display_name: alice.display_name.clone(),
+        market_id: market.id,
+    };
+    let client = ScriptedClient::new(vec![500, 502, 204]);
+    assert!(webhooks::deliver(&service, &client, &hook, market.id, &message).await);
+
+    let requests = client.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 3);
+    let (headers, body) = &requests[0];
+    assert_eq!(body, &serde_json::to_string(&message).unwrap());
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+            .unwrap()
+    };
+    assert_eq!(header("X-Cazino-Event"), "user_joined");
+    assert_eq!(header("X-Cazino-Market"), market.id.to_string());
+    assert_eq!(
+        header("X-Cazino-Signature"),
+        format!("sha256={}", webhooks::sign(&secret, body))
+    );
+    assert_eq!(
+        requests[2]
+            .0
+            .iter()
+            .find(|(n, _)| n == "X-Cazino-Attempt")
+            .unwrap()
+            .1,
+        "3"
+    );
+
+    let log = service
+        .get_webhook_deliveries(hook.id, admin.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        log.iter()
+            .map(|d| (d.attempt, d.status_code, d.succeeded))
+            .collect::<Vec<_>>(),
+        vec![
+            (1, Some(500), false),
+            (2, Some(502), false),
+            (3, Some(204), true)
+        ]
+    );
+    assert!(service
+        .get_webhook_deliveries(hook.id, alice.id)
+        .await
+        .is_err());
+
+    // Unreachable endpoints are given up on after the last attempt
+    let client = ScriptedClient::new(vec![]);
+    assert!(!webhooks::deliver(&service, &client, &everything, market.id, &message).await);
+    let log = service
+        .get_webhook_deliveries(everything.id, admin.id)
+        .await
+        .unwrap();
+    assert_eq!(log.len(), webhooks::MAX_ATTEMPTS as usize);
+    assert!(log
+        .iter()
+        .all(|d| d.status_code.is_none() && d.error.as_deref() == Some("Connection refused")));
+
+    // Removing a webhook takes its log with it
+    assert!(service.delete_webhook(hook.id, alice.id).await.is_err());
+    service.delete_webhook(hook.id, admin.id).await.unwrap();
+    assert!(service.get_webhook(hook.id).await.is_err());
+    assert_eq!(
+        service
+            .get_webhooks(market.id, admin.id)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
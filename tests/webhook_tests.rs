@@ -0,0 +1,306 @@
+#![cfg(feature = "server")]
+
+/// Webhook deliveries from the axum server, against a local HTTP stand-in
+///
+/// The stand-in answers each POST with the next status from its script and
+/// hands the raw request back to the test.
+use cazino::api::delivery::{self, HttpClient};
+use cazino::api::protocol::WsMessage;
+use cazino::api::webhooks;
+use cazino::api::websocket;
+use cazino::db::InMemoryDatabase;
+use cazino::service::{CazinoService, CreateMarketParams};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// A request the stand-in received
+struct Received {
+    request_line: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Received {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Start the stand-in, returning its URL and the requests it receives
+async fn stand_in(statuses: Vec<u16>) -> (String, mpsc::UnboundedReceiver<Received>) {
+    let responses = statuses
+        .into_iter()
+        .map(|status| format!("HTTP/1.1 {} Whatever\r\nContent-Length: 0\r\n\r\n", status))
+        .collect();
+    scripted(responses).await
+}
+
+/// Start a stand-in that answers each request with the next raw response
+async fn scripted(responses: Vec<String>) -> (String, mpsc::UnboundedReceiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        for response in responses {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).await.unwrap();
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).await.unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_once(": ").unwrap();
+                headers.push((name.to_string(), value.to_string()));
+            }
+            let length: usize = headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case("content-length"))
+                .map(|(_, v)| v.parse().unwrap())
+                .unwrap_or(0);
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await.unwrap();
+
+            reader
+                .get_mut()
+                .write_all(response.as_bytes())
+                .await
+                .unwrap();
+
+            let _ = tx.send(Received {
+                request_line: request_line.trim_end().to_string(),
+                headers,
+                body: String::from_utf8(body).unwrap(),
+            });
+        }
+    });
+
+    (url, rx)
+}
+
+async fn next(rx: &mut mpsc::UnboundedReceiver<Received>) -> Received {
+    tokio::time::timeout(Duration::from_secs(10), rx.recv())
+        .await
+        .expect("no delivery arrived")
+        .unwrap()
+}
+
+async fn setup() -> (Arc<CazinoService<InMemoryDatabase>>, uuid::Uuid, uuid::Uuid) {
+    let service = CazinoService::new(Arc::new(InMemoryDatabase::new())).allow_private_webhooks();
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Hooked Market".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+    (Arc::new(service), market.id, admin.id)
+}
+
+#[tokio::test]
+async fn test_http_client_posts_signed_json() {
+    let (service, market_id, admin_id) = setup().await;
+    let (url, mut received) = stand_in(vec![200]).await;
+    let secret = "family-chat-secret";
+    let hook = service
+        .create_webhook(market_id, admin_id, url, secret.to_string(), vec![])
+        .await
+        .unwrap();
+
+    let message = WsMessage::MarketDeleted { market_id };
+    assert!(
+        webhooks::deliver(
+            &service,
+            &HttpClient::new().allow_private(),
+            &hook,
+            market_id,
+            &message
+        )
+        .await
+    );
+
+    let request = next(&mut received).await;
+    assert_eq!(request.request_line, "POST /hook HTTP/1.1");
+    assert_eq!(request.header("Content-Type"), Some("application/json"));
+    assert_eq!(request.header("X-Cazino-Event"), Some("market_deleted"));
+    assert_eq!(request.body, serde_json::to_string(&message).unwrap());
+    assert_eq!(
+        request.header("X-Cazino-Signature").unwrap(),
+        format!("sha256={}", webhooks::sign(secret, &request.body))
+    );
+
+    let log = service
+        .get_webhook_deliveries(hook.id, admin_id)
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].status_code, Some(200));
+    assert!(log[0].succeeded);
+}
+
+#[tokio::test]
+async fn test_broadcast_events_reach_subscribed_webhooks() {
+    let (service, market_id, admin_id) = setup().await;
+    let (url, mut received) = stand_in(vec![200]).await;
+    service
+        .create_webhook(
+            market_id,
+            admin_id,
+            url,
+            "family-chat-secret".to_string(),
+            vec!["market_deleted".to_string()],
+        )
+        .await
+        .unwrap();
+
+    let (tx, _) = websocket::create_broadcast_channel(Default::default());
+    delivery::spawn_webhook_delivery(
+        service.clone(),
+        Arc::new(HttpClient::new().allow_private()),
+        tx.subscribe(),
+    );
+
+    // Filtered out, then delivered
+    let bet_id = uuid::Uuid::new_v4();
+    websocket::broadcast(&tx, market_id, WsMessage::BetApproved { bet_id });
+    websocket::broadcast(&tx, market_id, WsMessage::MarketDeleted { market_id });
+
+    let request = next(&mut received).await;
+    assert_eq!(request.header("X-Cazino-Event"), Some("market_deleted"));
+    assert_eq!(request.header("X-Cazino-Attempt"), Some("1"));
+}
+
+#[tokio::test]
+async fn test_http_client_reports_unreachable_endpoints() {
+    // Nothing listens on a port we just released
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    drop(listener);
+
+    let result =
+        webhooks::WebhookClient::post(&HttpClient::new().allow_private(), &url, &[], "{}").await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_private_hosts_are_refused_by_default() {
+    let (url, _received) = stand_in(vec![]).await;
+
+    let service = CazinoService::new(Arc::new(InMemoryDatabase::new()));
+    let (market, admin) = service
+        .create_market(CreateMarketParams {
+            name: "Hooked Market".to_string(),
+            admin_device_id: "admin-device".to_string(),
+            admin_name: "Admin".to_string(),
+            admin_avatar: "👑".to_string(),
+            starting_balance: 1000,
+            duration_hours: 24,
+            custom_invite_code: None,
+        })
+        .await
+        .unwrap();
+    let result = service
+        .create_webhook(
+            market.id,
+            admin.id,
+            url.clone(),
+            "family-chat-secret".to_string(),
+            vec![],
+        )
+        .await;
+    assert!(result.is_err());
+
+    // So is a name that resolves to loopback, when it's time to deliver
+    let url = url.replace("127.0.0.1", "localhost");
+    let result = webhooks::WebhookClient::post(&HttpClient::new(), &url, &[], "{}").await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_http_client_skips_informational_responses() {
+    let (url, mut received) = scripted(vec![
+        "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n"
+            .to_string(),
+    ])
+    .await;
+
+    let result =
+        webhooks::WebhookClient::post(&HttpClient::new().allow_private(), &url, &[], "{}").await;
+    assert_eq!(result, Ok(202));
+    assert_eq!(next(&mut received).await.body, "{}");
+}
+
+#[tokio::test]
+async fn test_http_client_against_a_real_server() {
+    use axum::body::Body;
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+    use axum::routing::post;
+    use futures::StreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Chunked bodies over a kept-alive connection, and a redirect
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counted = hits.clone();
+    let app = axum::Router::new()
+        .route(
+            "/hook",
+            post(move |body: String| async move {
+                counted.fetch_add(1, Ordering::SeqCst);
+                let chunks =
+                    ["got ", "it: "].map(|chunk| Ok::<_, std::io::Error>(chunk.to_string()));
+                let stream = futures::stream::iter(chunks)
+                    .chain(futures::stream::once(async move { Ok(body) }));
+                (StatusCode::CREATED, Body::from_stream(stream))
+            }),
+        )
+        .route(
+            "/moved",
+            post(|| async {
+                (
+                    StatusCode::TEMPORARY_REDIRECT,
+                    [(header::LOCATION, "/hook")],
+                )
+                    .into_response()
+            }),
+        );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = HttpClient::new().allow_private();
+    let hook = format!("{}/hook", base);
+    let headers = [("Content-Type", "application/json".to_string())];
+    for _ in 0..2 {
+        let result = webhooks::WebhookClient::post(&client, &hook, &headers, "{}").await;
+        assert_eq!(result, Ok(201));
+    }
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    // Redirects are reported, not followed
+    let moved = format!("{}/moved", base);
+    let result = webhooks::WebhookClient::post(&client, &moved, &headers, "{}").await;
+    assert_eq!(result, Ok(307));
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    // Private addresses written into the URL are refused without a lookup
+    let result = webhooks::WebhookClient::post(&HttpClient::new(), &hook, &headers, "{}").await;
+    assert!(result.unwrap_err().contains("not a public address"));
+}
@@ -33,6 +33,9 @@ function resolveWsUrl(value, fallbackPath) {
 const API_BASE = resolveBaseUrl(runtimeConfig.apiBase, "/api");
 const WS_URL = resolveWsUrl(runtimeConfig.wsUrl, "/ws");
 
+// WebSocket protocol this client speaks (see src/api/protocol.rs)
+const PROTOCOL_VERSION = 1;
+
 // ===== State Management =====
 const state = {
   market: null,
@@ -110,6 +113,37 @@ async function renderRecentMarkets() {
   });
 }
 
+// ===== Device Linking Functions =====
+async function createLinkCode() {
+  try {
+    const deviceId = getDeviceFingerprint();
+    const result = await apiCall(`/devices/${deviceId}/link-code`, {
+      method: "POST",
+    });
+    alert(
+      `Your link code is ${result.code}\n\nEnter it on your other device within 10 minutes.`,
+    );
+  } catch (error) {
+    showError("Failed to create link code: " + error.message);
+  }
+}
+
+async function redeemLinkCode() {
+  const code = prompt("Enter the link code from your other device");
+  if (!code) return;
+
+  try {
+    const deviceId = getDeviceFingerprint();
+    await apiCall(`/devices/${deviceId}/link`, {
+      method: "POST",
+      body: JSON.stringify({ code: code.trim() }),
+    });
+    renderRecentMarkets();
+  } catch (error) {
+    showError("Failed to link device: " + error.message);
+  }
+}
+
 async function rejoinMarket(inviteCode) {
   try {
     const result = await apiCall(`/markets/${inviteCode}/join`, {
@@ -213,6 +247,7 @@ function connectWebSocket() {
         JSON.stringify({
           type: "subscribe",
           market_id: state.market.id,
+          protocol_version: PROTOCOL_VERSION,
         }),
       );
     }
@@ -238,6 +273,20 @@ function handleWebSocketMessage(message) {
   console.log("WS Message:", message);
 
   switch (message.type) {
+    case "subscribed":
+      console.log("Subscribed with protocol version", message.protocol_version);
+      // Catch up on anything missed while disconnected
+      if (
+        document.getElementById("market-screen").classList.contains("active")
+      ) {
+        loadSnapshot();
+      }
+      break;
+
+    case "error":
+      console.error("WebSocket error from server:", message.message);
+      break;
+
     case "market_update":
       state.market = message.market;
       updateMarketDisplay();
@@ -247,12 +296,6 @@ function handleWebSocketMessage(message) {
       loadUsers();
       break;
 
-    case "market_opened":
-      // Market has been opened, transition from lobby to market view
-      loadMarket();
-      showMarket();
-      break;
-
     case "bet_created":
       loadBets();
       break;
@@ -271,6 +314,35 @@ function handleWebSocketMessage(message) {
       updateUserBalance();
       break;
 
+    case "user_updated":
+      loadUsers();
+      loadLeaderboard();
+      loadBets();
+      break;
+
+    case "membership_changed":
+      if (
+        message.user_id === state.user.id &&
+        (message.status === "kicked" || message.status === "banned")
+      ) {
+        showError(`You have been ${message.status} from this market`);
+      }
+      loadUsers();
+      loadBets();
+      updateUserBalance();
+      break;
+
+    case "bet_voided":
+      loadBets();
+      updateUserBalance();
+      break;
+
+    case "reveal_started":
+    case "bet_revealed":
+      loadBets();
+      loadReveal();
+      break;
+
     case "market_status_changed":
       // Reload market data
       loadMarket().then(() => {
@@ -469,29 +541,38 @@ async function createBet() {
   const wager = parseInt(document.getElementById("opening-wager").value);
   const hideFromSubject = document.getElementById("hide-from-subject").checked;
 
-  // Parse @username from description
-  const mentionMatch = description.match(/@([a-zA-Z0-9_]+)/);
-  if (!mentionMatch) {
+  // Parse @usernames (or @everyone) from description
+  const mentions = [...description.matchAll(/@([a-zA-Z0-9_]+)/g)].map((m) =>
+    m[1].toLowerCase(),
+  );
+  if (mentions.length === 0) {
     showError("Please include @username in the bet description");
     return;
   }
 
-  const username = mentionMatch[1].toLowerCase();
+  const aboutEveryone = mentions.includes("everyone");
 
-  // Find user by username
-  const subjectUser = state.users.find(
-    (u) => u.display_name.toLowerCase() === username,
-  );
-  if (!subjectUser) {
-    showError(`User @${username} not found in this market`);
-    return;
+  // Find users by username
+  const subjectIds = [];
+  if (!aboutEveryone) {
+    for (const username of mentions) {
+      const subjectUser = state.users.find(
+        (u) => u.display_name.toLowerCase() === username,
+      );
+      if (!subjectUser) {
+        showError(`User @${username} not found in this market`);
+        return;
+      }
+      subjectIds.push(subjectUser.id);
+    }
   }
 
   try {
     await apiCall(`/markets/${state.market.id}/bets/${state.user.id}`, {
       method: "POST",
       body: JSON.stringify({
-        subject_user_id: subjectUser.id,
+        subject_user_ids: subjectIds,
+        about_everyone: aboutEveryone,
         description,
         initial_odds: odds,
         opening_wager: wager,
@@ -546,6 +627,32 @@ async function resolveBet(betId, outcome) {
   }
 }
 
+// ===== Snapshot Functions =====
+// Load the market, bets, leaderboard and players in one request
+async function loadSnapshot() {
+  try {
+    const snapshot = await apiCall(
+      `/markets/${state.market.id}/snapshot?user_id=${state.user.id}`,
+    );
+    state.market = snapshot.market;
+    state.user.balance = snapshot.user.balance;
+    state.bets = snapshot.bets;
+    state.leaderboard = snapshot.leaderboard;
+    state.users = snapshot.leaderboard.map((u) => u.user);
+
+    document.getElementById("market-status").textContent = state.market.status;
+    document.getElementById("user-balance").textContent = formatBalance(
+      state.user.balance,
+    );
+    renderBets();
+    renderFeed();
+    renderLeaderboard();
+    renderPlayerList();
+  } catch (error) {
+    console.error("Failed to load snapshot:", error);
+  }
+}
+
 // ===== Leaderboard Functions =====
 async function loadLeaderboard() {
   try {
@@ -563,7 +670,10 @@ async function loadReveal() {
     const result = await apiCall(`/users/${state.user.id}/reveal`);
     renderReveal(result.bets);
   } catch (error) {
+    // Reveal unlocks once the market closes
     console.error("Failed to load reveal:", error);
+    document.getElementById("reveal-list").innerHTML =
+      '<div class="empty-state">Bets are revealed once the market is closed</div>';
   }
 }
 
@@ -616,9 +726,7 @@ function showMarket() {
     document.getElementById("admin-tab-btn").style.display = "none";
   }
 
-  loadBets();
-  loadLeaderboard();
-  loadUsers();
+  loadSnapshot();
   showScreen("market-screen");
 }
 
@@ -668,7 +776,7 @@ function renderBets() {
             <div class="bet-header">
                 <div>
                     <div class="bet-description">${bet.description || "[Hidden - about you]"}</div>
-                    ${bet.subject_user_id ? `<div class="bet-subject">About ${getUserName(bet.subject_user_id)}</div>` : ""}
+                    ${bet.about_everyone ? `<div class="bet-subject">About everyone</div>` : bet.subject_user_ids ? `<div class="bet-subject">About ${bet.subject_user_ids.map(getUserName).join(", ")}</div>` : ""}
                 </div>
                 <span class="bet-status-badge ${bet.status}">${bet.status}</span>
             </div>
@@ -785,7 +893,10 @@ function renderFeed() {
 
   state.bets.forEach((bet) => {
     // Add bet creation event
-    const isBetAboutMe = bet.subject_user_id === state.user.id;
+    const isBetAboutMe =
+      bet.is_hidden ||
+      bet.about_everyone ||
+      (bet.subject_user_ids || []).includes(state.user.id);
     const betDescription =
       isBetAboutMe && !bet.is_hidden
         ? bet.description
@@ -1105,6 +1216,14 @@ document.getElementById("join-market-form").addEventListener("submit", (e) => {
   joinMarket();
 });
 
+document
+  .getElementById("create-link-code-btn")
+  .addEventListener("click", createLinkCode);
+
+document
+  .getElementById("redeem-link-code-btn")
+  .addEventListener("click", redeemLinkCode);
+
 document.getElementById("copy-invite-btn").addEventListener("click", () => {
   navigator.clipboard.writeText(state.inviteCode);
   alert("Invite code copied to clipboard!");
@@ -1,16 +1,25 @@
 /// Cloudflare Worker for Cazino API
 use worker::*;
 
-mod d1_database;
+mod limiter;
 mod room;
 
-use cazino::api::models::*;
-use cazino::domain::models::BetView;
-use cazino::service::{CazinoService, CreateMarketParams};
-use d1_database::D1Database;
+use async_trait::async_trait;
+use cazino::api::dispatch::{self, dispatch, ApiError, Reply};
+use cazino::api::handlers::EventSink;
+use cazino::api::models::ErrorResponse;
+use cazino::api::protocol::WsMessage;
+use cazino::db::{D1Database, Database};
+use cazino::service::CazinoService;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use uuid::Uuid;
 
+/// Set once this isolate has brought the D1 schema up to date
+static MIGRATED: AtomicBool = AtomicBool::new(false);
+
+type Service = Arc<CazinoService<D1Database>>;
+
 #[event(fetch)]
 async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
     console_error_panic_hook::set_once();
@@ -38,626 +47,222 @@ async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
     // Get D1 database from environment
     let d1 = env.d1("CAZINO_DB")?;
     let db = Arc::new(D1Database::new(d1));
+    if !MIGRATED.load(Ordering::Relaxed) {
+        for migration in db.run_migrations().await? {
+            console_log!(
+                "Applied migration {:03} {}",
+                migration.version,
+                migration.description
+            );
+        }
+        MIGRATED.store(true, Ordering::Relaxed);
+    }
     let service = Arc::new(CazinoService::new(db));
 
-    // Create router - clone service for each route
-    let router = Router::new();
-
-    let svc1 = service.clone();
-    let svc2 = service.clone();
-    let svc3 = service.clone();
-    let svc4 = service.clone();
-    let svc5 = service.clone();
-    let svc6 = service.clone();
-    let svc7 = service.clone();
-    let svc8 = service.clone();
-    let svc9 = service.clone();
-    let svc10 = service.clone();
-    let svc11 = service.clone();
-    let svc12 = service.clone();
-    let svc13 = service.clone();
-    let svc14 = service.clone();
-    let svc15 = service.clone();
-    let svc16 = service.clone();
-
-    router
-        // Market routes
-        .post_async("/api/markets", move |req, _ctx| {
-            let service = svc1.clone();
-            async move { handle_create_market(req, service).await }
-        })
-        .post_async("/api/markets/:invite_code/join", move |req, ctx| {
-            let service = svc2.clone();
-            async move { handle_join_market(req, ctx, service).await }
-        })
-        .get_async("/api/markets/:market_id", move |_req, ctx| {
-            let service = svc3.clone();
-            async move { handle_get_market(ctx, service).await }
-        })
-        .get_async("/api/markets/:market_id/leaderboard", move |_req, ctx| {
-            let service = svc4.clone();
-            async move { handle_get_leaderboard(ctx, service).await }
-        })
-        .post_async(
-            "/api/markets/:market_id/open/:admin_id",
-            move |_req, ctx| {
-                let service = svc5.clone();
-                async move { handle_open_market(ctx, service).await }
-            },
-        )
-        .post_async(
-            "/api/markets/:market_id/close/:admin_id",
-            move |_req, ctx| {
-                let service = svc6.clone();
-                async move { handle_close_market(ctx, service).await }
-            },
-        )
-        .post_async(
-            "/api/markets/:market_id/delete/:admin_id",
-            move |_req, ctx| {
-                let service = svc16.clone();
-                async move { handle_delete_market(ctx, service).await }
-            },
-        )
-        // Bet routes
-        .get_async("/api/markets/:market_id/bets/:user_id", move |_req, ctx| {
-            let service = svc7.clone();
-            async move { handle_get_bets(ctx, service).await }
-        })
-        .post_async(
-            "/api/markets/:market_id/bets/:creator_id",
-            move |req, ctx| {
-                let service = svc8.clone();
-                async move { handle_create_bet(req, ctx, service).await }
-            },
-        )
-        .get_async("/api/markets/:market_id/bets-pending", move |_req, ctx| {
-            let service = svc9.clone();
-            async move { handle_get_pending_bets(ctx, service).await }
-        })
-        .post_async("/api/bets/:bet_id/approve/:admin_id", move |_req, ctx| {
-            let service = svc10.clone();
-            async move { handle_approve_bet(ctx, service).await }
-        })
-        .post_async("/api/bets/:bet_id/wager/:user_id", move |req, ctx| {
-            let service = svc11.clone();
-            async move { handle_place_wager(req, ctx, service).await }
-        })
-        .get_async("/api/bets/:bet_id/chart", move |_req, ctx| {
-            let service = svc12.clone();
-            async move { handle_get_probability_chart(ctx, service).await }
-        })
-        .post_async("/api/bets/:bet_id/resolve/:admin_id", move |req, ctx| {
-            let service = svc13.clone();
-            async move { handle_resolve_bet(req, ctx, service).await }
-        })
-        // User routes
-        .get_async("/api/users/:user_id/reveal", move |_req, ctx| {
-            let service = svc14.clone();
-            async move { handle_get_reveal(ctx, service).await }
-        })
-        // Device routes (fingerprint-based)
-        .get_async("/api/devices/:device_id/markets", move |_req, ctx| {
-            let service = svc15.clone();
-            async move { handle_get_device_markets(ctx, service).await }
-        })
+    Router::with_data(service)
+        // API routes, shared with the axum server (see cazino::api::dispatch)
+        .on_async("/api/*path", handle_api)
         // WebSocket route - forward to Durable Object
-        .get_async("/ws/:market_id", |req, ctx| async move {
-            handle_websocket(req, ctx).await
-        })
+        .get_async("/ws/:market_id", handle_websocket)
+        .get_async("/ws/:market_id/spectate/:token", handle_spectator_websocket)
         .run(req, env)
         .await
 }
 
-// ===== Helper Functions =====
-
-/// Broadcast a message to all WebSocket clients connected to a market
-async fn broadcast_to_market(
-    ctx: &RouteContext<()>,
-    market_id: &str,
-    message: serde_json::Value,
-) -> Result<()> {
-    console_log!("Broadcasting to market {}: {:?}", market_id, message);
-
-    // Get the Durable Object namespace
-    let namespace = ctx.durable_object("ROOM")?;
-
-    // Create a Durable Object ID for this market
-    let id = namespace.id_from_name(market_id)?;
-
-    // Get the Durable Object stub
-    let stub = id.get_stub()?;
-
-    // Serialize the message
-    let body = serde_json::to_string(&message)
-        .map_err(|e| Error::RustError(format!("Failed to serialize message: {}", e)))?;
-
-    // Create a POST request to the /broadcast endpoint
-    let mut request = Request::new_with_init(
-        "https://fake-host/broadcast",
-        RequestInit::new()
-            .with_method(Method::Post)
-            .with_body(Some(body.into())),
-    )?;
-
-    request
-        .headers_mut()?
-        .set("Content-Type", "application/json")?;
-
-    // Send the request to the Durable Object
-    let response = stub.fetch_with_request(request).await?;
-
-    if response.status_code() != 200 {
-        console_log!("Broadcast failed with status: {}", response.status_code());
-    }
-
-    Ok(())
-}
-
-// ===== Handler Functions =====
-
-async fn handle_create_market(
-    mut req: Request,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let body: CreateMarketRequest = req.json().await?;
-
-    let device_id = body
-        .device_id
-        .clone()
-        .unwrap_or_else(|| Uuid::new_v4().to_string());
-
-    let (market, user) = service
-        .create_market(CreateMarketParams {
-            name: body.name,
-            admin_device_id: device_id,
-            admin_name: body.admin_name,
-            admin_avatar: "👑".to_string(),
-            starting_balance: body.starting_balance,
-            duration_hours: body.duration_hours,
-            custom_invite_code: body.invite_code,
-        })
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    let invite_code = market.invite_code.clone();
-
-    let response = CreateMarketResponse {
-        market,
-        user,
-        invite_code,
+// ===== API Handler =====
+
+/// Serve an `/api` request through the shared dispatcher
+///
+/// Writes to a single market are handed to that market's Durable Object,
+/// which applies them one at a time; everything else is served here.
+async fn handle_api(mut req: Request, ctx: RouteContext<Service>) -> Result<Response> {
+    let method = match req.method() {
+        Method::Get => dispatch::Method::Get,
+        Method::Post => dispatch::Method::Post,
+        method => return error_response(ApiError::new(405, format!("{} is not allowed", method))),
     };
-
-    Response::from_json(&response).and_then(|r| add_cors_headers(r))
-}
-
-async fn handle_join_market(
-    mut req: Request,
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let invite_code = ctx.param("invite_code").unwrap().to_string();
-    let body: JoinMarketRequest = req.json().await?;
-
-    let device_id = body
-        .device_id
-        .clone()
-        .unwrap_or_else(|| Uuid::new_v4().to_string());
-
-    let (market, user) = service
-        .join_market(invite_code, device_id, body.display_name, body.avatar)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    // Broadcast user joined event to all connected clients
-    let broadcast_msg = serde_json::json!({
-        "type": "user_joined",
-        "data": {
-            "user_id": user.id,
-            "display_name": user.display_name,
-            "avatar": user.avatar,
-            "balance": user.balance
-        }
-    });
-
-    let _ = broadcast_to_market(&ctx, &market.id.to_string(), broadcast_msg).await;
-
-    let response = JoinMarketResponse { market, user };
-
-    Response::from_json(&response).and_then(|r| add_cors_headers(r))
-}
-
-async fn handle_get_market(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let market_id = parse_uuid(ctx.param("market_id").unwrap())?;
-
-    let market = service
-        .get_market(market_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    Response::from_json(&market).and_then(|r| add_cors_headers(r))
-}
-
-async fn handle_get_leaderboard(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let market_id = parse_uuid(ctx.param("market_id").unwrap())?;
-
-    let market = service
-        .get_market(market_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    let mut users = service
-        .get_users(market_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    // Sort by balance descending
-    users.sort_by(|a, b| b.balance.cmp(&a.balance));
-
-    let users_with_stats: Vec<UserWithStats> = users
-        .into_iter()
-        .enumerate()
-        .map(|(idx, user)| UserWithStats {
-            profit: user.balance - market.starting_balance,
-            rank: idx + 1,
-            user,
-        })
-        .collect();
-
-    let response = LeaderboardResponse {
-        users: users_with_stats,
+    let path = req.path();
+    let url = req.url()?;
+    let body = if method == dispatch::Method::Post {
+        req.bytes().await?
+    } else {
+        Vec::new()
     };
 
-    Response::from_json(&response).and_then(|r| add_cors_headers(r))
-}
-
-async fn handle_open_market(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let market_id = parse_uuid(ctx.param("market_id").unwrap())?;
-    let admin_id = parse_uuid(ctx.param("admin_id").unwrap())?;
-
-    service
-        .open_market(market_id, admin_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    // Get updated market to broadcast
-    let market = service
-        .get_market(market_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    // Broadcast market opened event to all connected clients
-    let broadcast_msg = serde_json::json!({
-        "type": "market_opened",
-        "data": {
-            "market_id": market_id,
-            "status": format!("{:?}", market.status)
-        }
-    });
-
-    broadcast_to_market(&ctx, &market_id.to_string(), broadcast_msg).await?;
-
-    Response::empty().and_then(|r| add_cors_headers(r))
-}
-
-async fn handle_close_market(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let market_id = parse_uuid(ctx.param("market_id").unwrap())?;
-    let admin_id = parse_uuid(ctx.param("admin_id").unwrap())?;
-
-    service
-        .close_market(market_id, admin_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    Response::empty().and_then(|r| add_cors_headers(r))
-}
-
-async fn handle_delete_market(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let market_id = parse_uuid(ctx.param("market_id").unwrap())?;
-    let admin_id = parse_uuid(ctx.param("admin_id").unwrap())?;
-
-    service
-        .delete_market(market_id, admin_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    // Broadcast market deleted event
-    let broadcast_msg = serde_json::json!({
-        "type": "market_deleted",
-        "data": {
-            "market_id": market_id
-        }
-    });
-
-    let _ = broadcast_to_market(&ctx, &market_id.to_string(), broadcast_msg).await;
-
-    Response::empty().and_then(|r| add_cors_headers(r))
-}
-
-async fn handle_get_bets(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let market_id = parse_uuid(ctx.param("market_id").unwrap())?;
-    let user_id = parse_uuid(ctx.param("user_id").unwrap())?;
-
-    let bets = service
-        .get_bets(market_id, user_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    Response::from_json(&bets).and_then(|r| add_cors_headers(r))
-}
+    let request = dispatch::Request {
+        method,
+        path: path.strip_prefix("/api").unwrap_or(&path),
+        query: url.query().unwrap_or(""),
+        body: &body,
+    };
 
-async fn handle_create_bet(
-    mut req: Request,
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let market_id = parse_uuid(ctx.param("market_id").unwrap())?;
-    let creator_id = parse_uuid(ctx.param("creator_id").unwrap())?;
-    let body: CreateBetRequest = req.json().await?;
-
-    let bet = service
-        .create_bet(
-            market_id,
-            creator_id,
-            body.subject_user_id,
-            body.description,
-            body.initial_odds,
-            body.opening_wager,
-            body.hide_from_subject,
-        )
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
+    // Counted across every isolate (see `limiter::RateLimits`)
+    let peer = req.headers().get("CF-Connecting-IP")?;
+    let ip = peer.as_deref().unwrap_or("unknown");
+    if let Err(e) = limiter::check(&ctx.env, &request, ip).await {
+        return error_response(e.into());
+    }
 
-    // Broadcast bet created event to all connected clients
-    let yes_prob = cazino::domain::parimutuel::calculate_probability(bet.yes_pool, bet.no_pool);
-    let broadcast_msg = serde_json::json!({
-        "type": "bet_created",
-        "data": {
-            "bet_id": bet.id,
-            "description": bet.description,
-            "created_by": bet.created_by,
-            "subject_user_id": bet.subject_user_id,
-            "status": format!("{:?}", bet.status),
-            "yes_probability": yes_prob
+    let response = match dispatch::market_of(&ctx.data, request).await {
+        Ok(Some(market_id)) => forward_to_room(&ctx.env, market_id, &req, &body).await?,
+        Ok(None) => {
+            let events = RoomEvents {
+                env: ctx.env.clone(),
+            };
+            serve_api(&ctx.data, &events, request).await?
         }
-    });
-
-    let _ = broadcast_to_market(&ctx, &market_id.to_string(), broadcast_msg).await;
-
-    let response = BetResponse {
-        bet: bet.to_view(creator_id),
+        Err(e) => error_response(e)?,
     };
-
-    Response::from_json(&response).and_then(|r| add_cors_headers(r))
-}
-
-async fn handle_get_pending_bets(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let market_id = parse_uuid(ctx.param("market_id").unwrap())?;
-
-    let bets = service
-        .get_pending_bets(market_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    // Convert to BetView (admin can see all)
-    let bet_views: Vec<BetView> = bets.iter().map(|b| b.to_view(Uuid::nil())).collect();
-
-    Response::from_json(&bet_views).and_then(|r| add_cors_headers(r))
+    limiter::record(&ctx.env, &request, ip, response.status_code()).await;
+    Ok(response)
 }
 
-async fn handle_approve_bet(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
+/// Hand a write to the market's Durable Object (see `room::CazinoRoom`)
+///
+/// The original headers go along, plus `room::MARKET_HEADER` naming the
+/// market so the room knows which rows are its own.
+async fn forward_to_room(
+    env: &Env,
+    market_id: Uuid,
+    req: &Request,
+    body: &[u8],
 ) -> Result<Response> {
-    let bet_id = parse_uuid(ctx.param("bet_id").unwrap())?;
-    let admin_id = parse_uuid(ctx.param("admin_id").unwrap())?;
-
-    // Get bet before approving to get market_id
-    let bet = service
-        .get_bet(bet_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    let market_id = bet.market_id;
-
-    service
-        .approve_bet(bet_id, admin_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
+    let namespace = env.durable_object("ROOM")?;
+    let id = namespace.id_from_name(&market_id.to_string())?;
+    let stub = id.get_stub()?;
 
-    // Broadcast bet approved event to all connected clients
-    let broadcast_msg = serde_json::json!({
-        "type": "bet_approved",
-        "data": {
-            "bet_id": bet_id,
-            "description": bet.description
-        }
-    });
+    let headers = req.headers().clone();
+    headers.set(room::MARKET_HEADER, &market_id.to_string())?;
 
-    let _ = broadcast_to_market(&ctx, &market_id.to_string(), broadcast_msg).await;
+    let request = Request::new_with_init(
+        &format!("https://fake-host{}", req.path()),
+        RequestInit::new()
+            .with_method(Method::Post)
+            .with_headers(headers)
+            .with_body(Some(js_sys::Uint8Array::from(body).into())),
+    )?;
 
-    Response::empty().and_then(|r| add_cors_headers(r))
+    stub.fetch_with_request(request).await
 }
 
-async fn handle_place_wager(
-    mut req: Request,
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
+/// Dispatch a request and turn its reply into a response
+pub(crate) async fn serve_api<D: Database + 'static, E: EventSink>(
+    service: &CazinoService<D>,
+    events: &E,
+    request: dispatch::Request<'_>,
 ) -> Result<Response> {
-    let bet_id = parse_uuid(ctx.param("bet_id").unwrap())?;
-    let user_id = parse_uuid(ctx.param("user_id").unwrap())?;
-    let body: PlaceWagerRequest = req.json().await?;
-
-    let wager = service
-        .place_wager(bet_id, user_id, body.side, body.amount)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    // Get bet to find market_id for broadcasting
-    let bet = service
-        .get_bet(bet_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
+    let reply = match dispatch(service, events, request).await {
+        Ok(reply) => reply,
+        Err(e) => return error_response(e),
+    };
 
-    // Broadcast wager placed event to all connected clients
-    let broadcast_msg = serde_json::json!({
-        "type": "wager_placed",
-        "data": {
-            "bet_id": bet_id,
-            "user_id": user_id,
-            "side": format!("{:?}", body.side),
-            "amount": body.amount,
-            "new_probability": wager.probability_after
+    let response = match reply {
+        Reply::Json(value) => Response::from_json(&value)?,
+        Reply::Empty => Response::empty()?,
+        Reply::Csv { report, export } => {
+            // Stream one bet or user per chunk
+            let stream = futures_util::stream::unfold(Some(export), |export| async move {
+                let mut export = export?;
+                match export.next_chunk().await {
+                    Ok(Some(chunk)) => Some((Ok(chunk), Some(export))),
+                    Ok(None) => None,
+                    Err(e) => Some((Err(Error::RustError(e.to_string())), None)),
+                }
+            });
+
+            let mut response = Response::from_stream(stream)?;
+            let headers = response.headers_mut();
+            headers.set("Content-Type", "text/csv; charset=utf-8")?;
+            headers.set(
+                "Content-Disposition",
+                &format!("attachment; filename=\"{}\"", report.file_name()),
+            )?;
+            response
         }
-    });
-
-    let _ = broadcast_to_market(&ctx, &bet.market_id.to_string(), broadcast_msg).await;
-
-    let response = WagerResponse {
-        bet_id,
-        user_id,
-        side: body.side,
-        amount: body.amount,
-        new_probability: wager.probability_after,
     };
 
-    Response::from_json(&response).and_then(|r| add_cors_headers(r))
+    add_cors_headers(response)
 }
 
-async fn handle_get_probability_chart(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let bet_id = parse_uuid(ctx.param("bet_id").unwrap())?;
-
-    let chart = service
-        .get_probability_chart(bet_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    let points: Vec<ProbabilityPoint> = chart
-        .into_iter()
-        .map(|p| ProbabilityPoint {
-            timestamp: p.timestamp.to_rfc3339(),
-            yes_probability: p.yes_probability,
-        })
-        .collect();
+/// Report an API error as JSON, like the axum server does
+fn error_response(error: ApiError) -> Result<Response> {
+    console_log!("API error: {}", error);
 
-    let response = ProbabilityChartResponse { points };
+    let mut response = Response::from_json(&ErrorResponse {
+        error: error.message,
+        retry_after: error.retry_after,
+    })?
+    .with_status(error.status);
+    if let Some(secs) = error.retry_after {
+        response
+            .headers_mut()
+            .set("Retry-After", &secs.to_string())?;
+    }
 
-    Response::from_json(&response).and_then(|r| add_cors_headers(r))
+    add_cors_headers(response)
 }
 
-async fn handle_resolve_bet(
-    mut req: Request,
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let bet_id = parse_uuid(ctx.param("bet_id").unwrap())?;
-    let admin_id = parse_uuid(ctx.param("admin_id").unwrap())?;
-    let body: ResolveBetRequest = req.json().await?;
-
-    // Get bet before resolving to get market_id
-    let bet = service
-        .get_bet(bet_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    let market_id = bet.market_id;
+// ===== Events =====
 
-    service
-        .resolve_bet(bet_id, admin_id, body.outcome)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
+/// Sends events to the market's Durable Object, which fans them out to its
+/// WebSocket clients and webhooks
+struct RoomEvents {
+    env: Env,
+}
 
-    // Broadcast bet resolved event to all connected clients
-    let broadcast_msg = serde_json::json!({
-        "type": "bet_resolved",
-        "data": {
-            "bet_id": bet_id,
-            "outcome": body.outcome
+#[async_trait(?Send)]
+impl EventSink for RoomEvents {
+    async fn publish(&self, market_id: Uuid, message: WsMessage) {
+        if let Err(e) = broadcast_to_market(&self.env, market_id, &message).await {
+            console_log!("Broadcast to market {} failed: {}", market_id, e);
         }
-    });
-
-    let _ = broadcast_to_market(&ctx, &market_id.to_string(), broadcast_msg).await;
-
-    Response::empty().and_then(|r| add_cors_headers(r))
+    }
 }
 
-async fn handle_get_reveal(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let user_id = parse_uuid(ctx.param("user_id").unwrap())?;
-
-    let bets = service
-        .get_bets_about_user(user_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
-
-    // For reveal, show full details (use nil UUID so nothing is hidden)
-    let bet_views: Vec<BetView> = bets.iter().map(|b| b.to_view(Uuid::nil())).collect();
-
-    let response = RevealResponse { bets: bet_views };
+/// Broadcast a message to all WebSocket clients connected to a market
+///
+/// The room also drops its cached rows, since the write behind the message
+/// was made outside it.
+pub(crate) async fn broadcast_to_market(
+    env: &Env,
+    market_id: Uuid,
+    message: &WsMessage,
+) -> Result<()> {
+    console_log!("Broadcasting to market {}: {:?}", market_id, message);
 
-    Response::from_json(&response).and_then(|r| add_cors_headers(r))
-}
+    // Get the Durable Object namespace
+    let namespace = env.durable_object("ROOM")?;
 
-async fn handle_get_device_markets(
-    ctx: RouteContext<()>,
-    service: Arc<CazinoService<D1Database>>,
-) -> Result<Response> {
-    let device_id = ctx.param("device_id").unwrap().to_string();
+    // Create a Durable Object ID for this market
+    let id = namespace.id_from_name(&market_id.to_string())?;
 
-    console_log!("Getting markets for device: {}", device_id);
+    // Get the Durable Object stub
+    let stub = id.get_stub()?;
 
-    let markets = service
-        .get_markets_by_device_id(&device_id)
-        .await
-        .map_err(|e| Error::RustError(e.to_string()))?;
+    // Serialize the message
+    let body = serde_json::to_string(message)
+        .map_err(|e| Error::RustError(format!("Failed to serialize message: {}", e)))?;
 
-    let market_infos: Vec<DeviceMarketInfo> = markets
-        .into_iter()
-        .map(|(market, user)| DeviceMarketInfo { market, user })
-        .collect();
+    // Create a POST request to the /broadcast endpoint
+    let mut request = Request::new_with_init(
+        &format!("https://fake-host/broadcast/{}", market_id),
+        RequestInit::new()
+            .with_method(Method::Post)
+            .with_body(Some(body.into())),
+    )?;
 
-    let response = DeviceMarketsResponse {
-        markets: market_infos,
-    };
+    request
+        .headers_mut()?
+        .set("Content-Type", "application/json")?;
 
-    Response::from_json(&response).and_then(|r| add_cors_headers(r))
-}
+    // Send the request to the Durable Object
+    let response = stub.fetch_with_request(request).await?;
 
-#[derive(Debug, serde::Serialize)]
-struct DeviceMarketsResponse {
-    markets: Vec<DeviceMarketInfo>,
-}
+    if response.status_code() != 200 {
+        console_log!("Broadcast failed with status: {}", response.status_code());
+    }
 
-#[derive(Debug, serde::Serialize)]
-struct DeviceMarketInfo {
-    market: cazino::domain::models::Market,
-    user: cazino::domain::models::User,
+    Ok(())
 }
 
 // ===== Helper Functions =====
@@ -682,7 +287,7 @@ fn add_cors_headers(mut response: Response) -> Result<Response> {
 
 // ===== WebSocket Handler =====
 
-async fn handle_websocket(req: Request, ctx: RouteContext<()>) -> Result<Response> {
+async fn handle_websocket(req: Request, ctx: RouteContext<Service>) -> Result<Response> {
     let market_id = ctx.param("market_id").map_or("default", |v| v.as_str());
 
     console_log!("WebSocket request for market: {}", market_id);
@@ -699,3 +304,32 @@ async fn handle_websocket(req: Request, ctx: RouteContext<()>) -> Result<Respons
     // Forward the request to the Durable Object
     stub.fetch_with_request(req).await
 }
+
+/// Validate a spectator token, then hand the socket to the market's Durable
+/// Object, which tags it as read-only
+async fn handle_spectator_websocket(req: Request, ctx: RouteContext<Service>) -> Result<Response> {
+    let market_id = parse_uuid(ctx.param("market_id").unwrap())?;
+    let token = ctx.param("token").unwrap().to_string();
+
+    let spectator = ctx
+        .data
+        .get_spectator(&token)
+        .await
+        .map_err(|e| Error::RustError(e.to_string()))?;
+
+    if spectator.market_id != market_id {
+        return Response::error("Spectator token is not valid for this market", 403);
+    }
+
+    console_log!(
+        "Spectator WebSocket request for market {} by {}",
+        market_id,
+        spectator.display_name
+    );
+
+    let namespace = ctx.durable_object("ROOM")?;
+    let id = namespace.id_from_name(&market_id.to_string())?;
+    let stub = id.get_stub()?;
+
+    stub.fetch_with_request(req).await
+}
@@ -0,0 +1,231 @@
+/// Durable Object keeping rate-limit counts (see `cazino::api::limits`)
+///
+/// The worker runs in many isolates at once, so counts kept in one would
+/// only see part of a client's traffic. Each IP and each device gets its own
+/// object instead, named `ip:{ip}` or `device:{id}`, holding a limiter for
+/// that partition. Strikes are saved to storage as soon as they're recorded,
+/// so lockouts survive the object being evicted; taken tokens are saved on an
+/// alarm a few seconds later, so an eviction can at most hand a client back
+/// the tokens it spent since.
+///
+/// The `LIMITS` binding and its migration are in `worker/wrangler.toml.example`.
+use cazino::api::dispatch;
+use cazino::api::limits::{self, Partition, RateLimitConfig, RateLimiter, Snapshot};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, OnceCell};
+use worker::*;
+
+/// Storage key for the limiter's counts
+const SNAPSHOT_KEY: &str = "snapshot";
+
+/// How long taken tokens wait before being saved
+const SAVE_AFTER: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// A request as the limiter sees it, sent to `/peek`, `/check` or `/record`
+#[derive(Serialize, Deserialize)]
+struct Usage {
+    partition: Partition,
+    post: bool,
+    path: String,
+    body: String,
+    ip: String,
+    status: Option<u16>, // Only for `/record`
+}
+
+impl Usage {
+    fn request(&self) -> dispatch::Request<'_> {
+        dispatch::Request {
+            method: match self.post {
+                true => dispatch::Method::Post,
+                false => dispatch::Method::Get,
+            },
+            path: &self.path,
+            query: "",
+            body: self.body.as_bytes(),
+        }
+    }
+}
+
+#[durable_object]
+pub struct RateLimits {
+    state: State,
+    env: Env,
+    limiter: OnceCell<RateLimiter>, // Loaded from storage on first use
+    save_due: Cell<bool>,           // An alarm will save the counts
+}
+
+impl DurableObject for RateLimits {
+    fn new(state: State, env: Env) -> Self {
+        Self {
+            state,
+            env,
+            limiter: OnceCell::new(),
+            save_due: Cell::new(false),
+        }
+    }
+
+    async fn fetch(&self, mut req: Request) -> Result<Response> {
+        let usage: Usage = req.json().await?;
+        let limiter = self.limiter(usage.partition).await?;
+        let request = usage.request();
+        let now = chrono::Utc::now();
+
+        match (req.path().as_str(), usage.status) {
+            ("/peek", None) => match limiter.peek(&request, &usage.ip, now) {
+                Ok(()) => Response::empty(),
+                Err(refused) => Ok(Response::from_json(&refused)?.with_status(429)),
+            },
+            ("/check", None) => match limiter.check(&request, &usage.ip, now) {
+                Ok(()) => {
+                    self.save_soon().await?;
+                    Response::empty()
+                }
+                Err(refused) => Ok(Response::from_json(&refused)?.with_status(429)),
+            },
+            ("/record", Some(status)) => {
+                limiter.record(&request, &usage.ip, status, now);
+                self.save(limiter).await?;
+                Response::empty()
+            }
+            _ => Response::error("Unknown rate limit request", 400),
+        }
+    }
+
+    async fn alarm(&self) -> Result<Response> {
+        self.save_due.set(false);
+        // Nothing to save if the object was evicted since the alarm was set
+        if let Some(limiter) = self.limiter.get() {
+            self.save(limiter).await?;
+        }
+        Response::empty()
+    }
+}
+
+impl RateLimits {
+    /// The object's limiter, configured from the worker's vars and holding
+    /// whatever counts were saved
+    async fn limiter(&self, partition: Partition) -> Result<&RateLimiter> {
+        if let Some(limiter) = self.limiter.get() {
+            return Ok(limiter);
+        }
+
+        let config =
+            RateLimitConfig::from_vars(|name| self.env.var(name).ok().map(|v| v.to_string()))
+                .map_err(|e| {
+                    Error::RustError(format!("Invalid rate limit configuration: {}", e))
+                })?;
+        let limiter = RateLimiter::partitioned(config, partition);
+        if let Ok(saved) = self.state.storage().get::<String>(SNAPSHOT_KEY).await {
+            match serde_json::from_str::<Snapshot>(&saved) {
+                Ok(snapshot) => limiter.restore(snapshot),
+                Err(e) => console_log!("Dropping unreadable rate limits: {}", e),
+            }
+        }
+
+        // Storage reads yield, so another request may have got here first
+        Ok(self.limiter.get_or_init(|| limiter))
+    }
+
+    async fn save(&self, limiter: &RateLimiter) -> Result<()> {
+        let snapshot = serde_json::to_string(&limiter.snapshot())
+            .map_err(|e| Error::RustError(format!("Failed to save rate limits: {}", e)))?;
+        self.state.storage().put(SNAPSHOT_KEY, snapshot).await
+    }
+
+    /// Save the counts on an alarm, unless one is already set
+    async fn save_soon(&self) -> Result<()> {
+        if !self.save_due.replace(true) {
+            if let Err(e) = self.state.storage().set_alarm(SAVE_AFTER).await {
+                self.save_due.set(false);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The objects holding a request's counts
+fn objects(request: &dispatch::Request, ip: &str) -> Vec<(String, Partition)> {
+    let mut objects = vec![(format!("ip:{}", ip), Partition::Ips)];
+    objects.extend(
+        limits::device_id(request).map(|id| (format!("device:{}", id), Partition::Devices)),
+    );
+    objects
+}
+
+/// Send a request's usage to each of its objects as `action`, stopping at
+/// the first refusal
+async fn send(
+    env: &Env,
+    request: &dispatch::Request<'_>,
+    ip: &str,
+    action: &str,
+    status: Option<u16>,
+) -> std::result::Result<(), limits::RateLimited> {
+    for (name, partition) in objects(request, ip) {
+        let usage = Usage {
+            partition,
+            post: request.method == dispatch::Method::Post,
+            path: request.path.to_string(),
+            body: String::from_utf8_lossy(request.body).into_owned(),
+            ip: ip.to_string(),
+            status,
+        };
+        match post(env, &name, action, &usage).await {
+            Ok(mut response) if response.status_code() == 429 => {
+                match response.json::<limits::RateLimited>().await {
+                    Ok(refused) => return Err(refused),
+                    Err(e) => console_log!("Unreadable refusal from {}: {}", name, e),
+                }
+            }
+            Ok(response) if response.status_code() != 200 => {
+                console_log!(
+                    "Rate limits for {} failed: {}",
+                    name,
+                    response.status_code()
+                );
+            }
+            Ok(_) => {}
+            // Limits are best effort; a broken object shouldn't take the API down
+            Err(e) => console_log!("Rate limits for {} failed: {}", name, e),
+        }
+    }
+    Ok(())
+}
+
+async fn post(env: &Env, name: &str, action: &str, usage: &Usage) -> Result<Response> {
+    let stub = env
+        .durable_object("LIMITS")?
+        .id_from_name(name)?
+        .get_stub()?;
+    let body = serde_json::to_string(usage)
+        .map_err(|e| Error::RustError(format!("Failed to serialize usage: {}", e)))?;
+
+    let request = Request::new_with_init(
+        &format!("https://fake-host/{}", action),
+        RequestInit::new()
+            .with_method(Method::Post)
+            .with_body(Some(body.into())),
+    )?;
+    stub.fetch_with_request(request).await
+}
+
+/// Let a request from `ip` through, taking its tokens, or refuse it
+pub async fn check(
+    env: &Env,
+    request: &dispatch::Request<'_>,
+    ip: &str,
+) -> std::result::Result<(), limits::RateLimited> {
+    // Ask every object before any takes a token, so the device's object
+    // refusing doesn't cost the IP anything
+    send(env, request, ip, "peek", None).await?;
+    send(env, request, ip, "check", None).await
+}
+
+/// Note how a request went; only wrong codes are worth the round trip
+pub async fn record(env: &Env, request: &dispatch::Request<'_>, ip: &str, status: u16) {
+    if limits::is_wrong_code(request, status) {
+        // Nothing is refused here
+        let _ = send(env, request, ip, "record", Some(status)).await;
+    }
+}
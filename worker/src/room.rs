@@ -1,57 +1,98 @@
-use serde::{Deserialize, Serialize};
 /// Durable Object for WebSocket room management
 /// Each market gets its own Durable Object instance
+///
+/// The worker also forwards every API write for the market here. Writes are
+/// applied one at a time against a write-through cache of the market's users
+/// and bets, so wagers can't race each other, and their events go straight to
+/// the room's sockets. Every event the room broadcasts is also delivered to
+/// the market's webhooks once the response has gone out.
+///
+/// Only this market's rows are cached. Writes to them made anywhere else
+/// (the worker itself, or another market's room) publish an event here,
+/// which clears the cache.
+use async_trait::async_trait;
+use cazino::api::dispatch;
+use cazino::api::handlers::EventSink;
+use cazino::api::protocol::WsMessage;
+use cazino::api::webhooks::{self, WebhookClient};
+use cazino::db::{CachedDatabase, D1Database};
+use cazino::service::CazinoService;
+use futures_util::lock::Mutex;
+use std::cell::OnceCell;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
 use worker::*;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "type")]
-pub enum WsMessage {
-    // Client -> Server
-    #[serde(rename = "subscribe")]
-    Subscribe { market_id: String },
+/// Hibernation tag for read-only spectator sockets
+const SPECTATOR_TAG: &str = "spectator";
 
-    #[serde(rename = "ping")]
-    Ping,
+/// Header naming the market on writes forwarded by the worker
+pub(crate) const MARKET_HEADER: &str = "X-Cazino-Room-Market";
 
-    // Server -> Client
-    #[serde(rename = "pong")]
-    Pong,
-
-    #[serde(rename = "subscribed")]
-    Subscribed { market_id: String },
-
-    #[serde(rename = "market_update")]
-    MarketUpdate { data: serde_json::Value },
-
-    #[serde(rename = "bet_created")]
-    BetCreated { bet_id: String, description: String },
-
-    #[serde(rename = "bet_updated")]
-    BetUpdated { bet_id: String },
-
-    #[serde(rename = "wager_placed")]
-    WagerPlaced { bet_id: String, amount: i64 },
-
-    #[serde(rename = "bet_resolved")]
-    BetResolved { bet_id: String, outcome: bool },
-}
+type MarketDatabase = CachedDatabase<D1Database>;
 
 #[durable_object]
 pub struct CazinoRoom {
     state: State,
-    _env: Env,
+    env: Env,
+    db: OnceCell<Arc<MarketDatabase>>, // Opened on the first write
+    writes: Mutex<()>,                 // Held while a write is applied
 }
 
 impl DurableObject for CazinoRoom {
     fn new(state: State, env: Env) -> Self {
-        Self { state, _env: env }
+        Self {
+            state,
+            env,
+            db: OnceCell::new(),
+            writes: Mutex::new(()),
+        }
     }
 
     async fn fetch(&self, mut req: Request) -> Result<Response> {
-        // Check if this is a broadcast request (POST /broadcast)
-        if req.method() == Method::Post && req.path().ends_with("/broadcast") {
+        // API writes forwarded by the worker (see `handle_api`)
+        if req.method() == Method::Post && req.path().starts_with("/api/") {
+            let path = req.path();
+            let market_id = req
+                .headers()
+                .get(MARKET_HEADER)?
+                .and_then(|id| Uuid::parse_str(&id).ok())
+                .ok_or_else(|| Error::RustError("Missing market ID".to_string()))?;
+            let body = req.bytes().await?;
+            let request = dispatch::Request {
+                method: dispatch::Method::Post,
+                path: path.strip_prefix("/api").unwrap_or(&path),
+                query: "", // Only writes come here
+                body: &body,
+            };
+
+            // Requests interleave at every await, so take turns explicitly
+            let _turn = self.writes.lock().await;
+            let service = CazinoService::new(self.db(market_id)?);
+            let events = RoomBroadcast {
+                room: self,
+                market_id,
+            };
+            return crate::serve_api(&service, &events, request).await;
+        }
+
+        // Check if this is a broadcast request (POST /broadcast/{market_id})
+        if req.method() == Method::Post && req.path().starts_with("/broadcast/") {
+            // Only writes made outside this room arrive here; they may have
+            // changed rows we hold
+            if let Some(db) = self.db.get() {
+                db.clear();
+            }
+
+            let market_id = req
+                .path()
+                .strip_prefix("/broadcast/")
+                .and_then(|id| Uuid::parse_str(id).ok())
+                .ok_or_else(|| Error::RustError("Invalid market ID".to_string()))?;
+
             // Parse the JSON message
-            let message: serde_json::Value = req.json().await?;
+            let message: WsMessage = req.json().await?;
             let message_str = serde_json::to_string(&message)
                 .map_err(|e| Error::RustError(format!("Failed to serialize message: {}", e)))?;
 
@@ -59,6 +100,7 @@ impl DurableObject for CazinoRoom {
 
             // Broadcast to all connected clients
             self.broadcast(&message_str)?;
+            self.deliver_webhooks(market_id, message)?;
 
             return Response::ok("Broadcast sent");
         }
@@ -77,7 +119,13 @@ impl DurableObject for CazinoRoom {
 
         // Accept the WebSocket using hibernation API
         // This allows the Durable Object to be evicted from memory during inactivity
-        self.state.accept_web_socket(&server);
+        // Spectator sockets are tagged so they only receive public events
+        if req.path().contains("/spectate/") {
+            self.state
+                .accept_websocket_with_tags(&server, &[SPECTATOR_TAG]);
+        } else {
+            self.state.accept_web_socket(&server);
+        }
 
         console_log!(
             "New WebSocket connection accepted. Total active connections: {}",
@@ -105,32 +153,23 @@ impl DurableObject for CazinoRoom {
 
         console_log!("Received message: {}", text);
 
-        // Parse and handle the message
+        // Clients only get replies; events come from the API
         match serde_json::from_str::<WsMessage>(&text) {
-            Ok(ws_msg) => {
-                match ws_msg {
-                    WsMessage::Subscribe { market_id } => {
+            Ok(ws_msg) => match ws_msg.reply() {
+                Some(reply) => {
+                    if let WsMessage::Subscribe { market_id, .. } = &ws_msg {
                         console_log!("Client subscribed to market: {}", market_id);
-
-                        // Send confirmation back to this client
-                        let response = serde_json::to_string(&WsMessage::Subscribed {
-                            market_id: market_id.clone(),
-                        })
-                        .unwrap();
-
-                        let _ = ws.send_with_str(&response);
-                    }
-                    WsMessage::Ping => {
-                        // Respond with pong
-                        let response = serde_json::to_string(&WsMessage::Pong).unwrap();
-                        let _ = ws.send_with_str(&response);
-                    }
-                    _ => {
-                        // Broadcast other messages to all connected clients
-                        self.broadcast(&text)?;
                     }
+
+                    let response = serde_json::to_string(&reply).map_err(|e| {
+                        Error::RustError(format!("Failed to serialize reply: {}", e))
+                    })?;
+                    let _ = ws.send_with_str(&response);
                 }
-            }
+                None => {
+                    console_log!("Ignoring message from client");
+                }
+            },
             Err(e) => {
                 console_log!("Failed to parse message: {}", e);
             }
@@ -174,6 +213,35 @@ impl DurableObject for CazinoRoom {
 }
 
 impl CazinoRoom {
+    /// The market's database, opened on first use
+    ///
+    /// A room only ever serves one market, so the ID passed first sticks.
+    fn db(&self, market_id: Uuid) -> Result<Arc<MarketDatabase>> {
+        if let Some(db) = self.db.get() {
+            return Ok(db.clone());
+        }
+
+        let d1 = self.env.d1("CAZINO_DB")?;
+        Ok(self
+            .db
+            .get_or_init(|| Arc::new(CachedDatabase::for_market(D1Database::new(d1), market_id)))
+            .clone())
+    }
+
+    /// Send an event to the market's webhooks in the background
+    ///
+    /// Retries back off for over a minute, so deliveries outlive the request
+    /// that caused them.
+    fn deliver_webhooks(&self, market_id: Uuid, message: WsMessage) -> Result<()> {
+        let service = CazinoService::new(self.db(market_id)?);
+        self.state.wait_until(async move {
+            for webhook in webhooks::webhooks_for(&service, market_id, &message).await {
+                webhooks::deliver(&service, &FetchClient, &webhook, market_id, &message).await;
+            }
+        });
+        Ok(())
+    }
+
     /// Broadcast a message to all connected sessions
     pub fn broadcast(&self, message: &str) -> Result<()> {
         // Get all connected websockets from the hibernation state
@@ -181,9 +249,21 @@ impl CazinoRoom {
 
         console_log!("Broadcasting to {} sessions", websockets.len());
 
+        let public = is_public_message(message);
+
         // Send to all connected clients
         // The runtime handles disconnected sockets automatically
         for ws in websockets {
+            if !public
+                && self
+                    .state
+                    .get_tags(&ws)
+                    .iter()
+                    .any(|tag| tag == SPECTATOR_TAG)
+            {
+                continue;
+            }
+
             if let Err(e) = ws.send_with_str(message) {
                 console_log!("Failed to send to websocket: {:?}", e);
                 // Continue sending to other clients even if one fails
@@ -193,3 +273,83 @@ impl CazinoRoom {
         Ok(())
     }
 }
+
+/// Publishes events from writes applied in the room to its own sockets
+///
+/// Events about other markets go to their rooms, like the worker's own.
+struct RoomBroadcast<'a> {
+    room: &'a CazinoRoom,
+    market_id: Uuid,
+}
+
+#[async_trait(?Send)]
+impl EventSink for RoomBroadcast<'_> {
+    async fn publish(&self, market_id: Uuid, message: WsMessage) {
+        if market_id != self.market_id {
+            if let Err(e) = crate::broadcast_to_market(&self.room.env, market_id, &message).await {
+                console_log!("Broadcast to market {} failed: {}", market_id, e);
+            }
+            return;
+        }
+
+        let message_str = match serde_json::to_string(&message) {
+            Ok(message_str) => message_str,
+            Err(e) => {
+                console_log!("Failed to serialize event: {}", e);
+                return;
+            }
+        };
+
+        console_log!("Broadcasting to market {}: {}", market_id, message_str);
+        if let Err(e) = self.room.broadcast(&message_str) {
+            console_log!("Broadcast to market {} failed: {}", market_id, e);
+        }
+        if let Err(e) = self.room.deliver_webhooks(market_id, message) {
+            console_log!("Webhooks for market {} failed: {}", market_id, e);
+        }
+    }
+}
+
+/// Webhook deliveries over the Workers `fetch` API
+struct FetchClient;
+
+#[async_trait(?Send)]
+impl WebhookClient for FetchClient {
+    async fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: &str,
+    ) -> std::result::Result<u16, String> {
+        let request_headers = Headers::new();
+        for (name, value) in headers {
+            request_headers
+                .set(name, value)
+                .map_err(|e| e.to_string())?;
+        }
+
+        let request = Request::new_with_init(
+            url,
+            RequestInit::new()
+                .with_method(Method::Post)
+                .with_headers(request_headers)
+                .with_body(Some(body.into())),
+        )
+        .map_err(|e| e.to_string())?;
+
+        let response = Fetch::Request(request)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(response.status_code())
+    }
+
+    async fn sleep(&self, delay: Duration) {
+        Delay::from(delay).await;
+    }
+}
+
+/// Whether a broadcast may be shown on a spectator screen
+fn is_public_message(message: &str) -> bool {
+    serde_json::from_str::<WsMessage>(message).is_ok_and(|message| message.is_public())
+}